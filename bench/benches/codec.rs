@@ -0,0 +1,78 @@
+//! Decoding and encoding of frames, layer by layer
+use bacnet::application::{IAm, APDU};
+use bacnet::encoding::{decode_buf, parse_bacnet_tag, Reader};
+use bacnet::network::NPDU;
+use bacnet::transport::bacnetip::BVLC;
+use bacnet::{Decode, Encode};
+use bytes::BytesMut;
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+
+/// ReadProperty ComplexACK of the present value of analog input 5
+const COMPLEX_ACK: &str = "30010c 0c00000005 1955 3e444290999a3f";
+/// Broadcast Who-Is in a BACnet/IP frame
+const WHO_IS: &str = "810b000c0120ffff00ff1008";
+/// Remote broadcast I-Am of device 599, routed from network 5
+const I_AM_NPDU: &str = "0128ffff00000501 0aff 1000 c402000257 220400 9100 210f";
+/// I-Am service data of device 599
+const I_AM: &str = "c402000257 220400 9100 210f";
+
+fn frame(hex: &str) -> Vec<u8> {
+    let digits: String = hex.split_whitespace().collect();
+    hex::decode(digits).unwrap()
+}
+
+fn tags(c: &mut Criterion) {
+    let apdu = APDU::decode_slice(&frame(COMPLEX_ACK)).unwrap();
+    let data = apdu.user_data().to_vec();
+    c.bench_function("parse_bacnet_tag", |b| {
+        b.iter(|| parse_bacnet_tag(black_box(&data)))
+    });
+    c.bench_function("decode_buf", |b| b.iter(|| decode_buf(black_box(&data))));
+}
+
+fn values(c: &mut Criterion) {
+    let apdu = APDU::decode_slice(&frame(COMPLEX_ACK)).unwrap();
+    let data = apdu.user_data().to_vec();
+    c.bench_function("values_to_end", |b| {
+        b.iter(|| Reader::new(black_box(&data)).values_to_end())
+    });
+    let i_am = frame(I_AM);
+    c.bench_function("i_am_decode", |b| {
+        b.iter(|| IAm::decode_slice(black_box(&i_am)))
+    });
+}
+
+fn round_trips(c: &mut Criterion) {
+    let npdu = frame(I_AM_NPDU);
+    c.bench_function("npdu_round_trip", |b| {
+        b.iter(|| {
+            let decoded = NPDU::decode_slice(black_box(&npdu)).unwrap();
+            decoded.encode_vec().unwrap()
+        })
+    });
+    let decoded = NPDU::decode_slice(&npdu).unwrap();
+    let mut buf = BytesMut::with_capacity(1497);
+    c.bench_function("npdu_encode_buf", |b| {
+        b.iter(|| {
+            buf.clear();
+            decoded.encode_buf(&mut buf).unwrap()
+        })
+    });
+    let bvlc = frame(WHO_IS);
+    c.bench_function("bvlc_round_trip", |b| {
+        b.iter(|| {
+            let decoded = BVLC::decode_slice(black_box(&bvlc)).unwrap();
+            decoded.encode_vec().unwrap()
+        })
+    });
+    let apdu = frame(COMPLEX_ACK);
+    c.bench_function("apdu_round_trip", |b| {
+        b.iter(|| {
+            let decoded = APDU::decode_slice(black_box(&apdu)).unwrap();
+            decoded.encode_vec().unwrap()
+        })
+    });
+}
+
+criterion_group!(benches, tags, values, round_trips);
+criterion_main!(benches);
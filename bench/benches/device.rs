@@ -0,0 +1,59 @@
+//! Who-Is answered by a device, from the received NPDU to the decoded I-Am
+use async_std::channel::{self, Receiver, Sender};
+use async_std::task;
+use bacnet::application::{IAm, WhoIsBuilder};
+use bacnet::network::{NPDUContent, NPDU};
+use bacnet::server::{BacnetDevice, DeviceInfo};
+use bacnet::transport::{BoxFuture, DataLink};
+use bacnet::Decode;
+use criterion::{criterion_group, criterion_main, Criterion};
+
+struct Link {
+    incoming: Receiver<(Vec<u8>, NPDU)>,
+    outgoing: Sender<(Vec<u8>, NPDU)>,
+}
+
+impl DataLink for Link {
+    fn send<'a>(&'a self, mac: &'a [u8], npdu: &'a NPDU) -> BoxFuture<'a, std::io::Result<()>> {
+        Box::pin(async move {
+            let _ = self.outgoing.send((mac.to_vec(), npdu.clone())).await;
+            Ok(())
+        })
+    }
+
+    fn recv(&self) -> BoxFuture<'_, std::io::Result<(Vec<u8>, NPDU)>> {
+        Box::pin(async move {
+            self.incoming
+                .recv()
+                .await
+                .map_err(|_| std::io::ErrorKind::BrokenPipe.into())
+        })
+    }
+}
+
+fn who_is(c: &mut Criterion) {
+    let (to_device, incoming) = channel::unbounded();
+    let (outgoing, from_device) = channel::unbounded();
+    let device = BacnetDevice::new(
+        Link { incoming, outgoing },
+        DeviceInfo::new(12, "Bench", 15),
+    );
+    let who_is = NPDU::builder().build(WhoIsBuilder::new().build());
+
+    c.bench_function("who_is_i_am", |b| {
+        b.iter(|| {
+            task::block_on(async {
+                to_device.send((vec![2], who_is.clone())).await.unwrap();
+                let (_, npdu) = from_device.recv().await.unwrap();
+                match npdu.content {
+                    NPDUContent::APDU(apdu) => IAm::decode_slice(apdu.user_data()).unwrap(),
+                    _ => panic!("Expected an I-Am"),
+                }
+            })
+        })
+    });
+    drop(device);
+}
+
+criterion_group!(benches, who_is);
+criterion_main!(benches);
@@ -0,0 +1,12 @@
+#![no_main]
+use bacnet::application::APDU;
+use bacnet::encoding::Reader;
+use bacnet::{Decode, Encode};
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(apdu) = APDU::decode_slice(data) {
+        let _ = apdu.encode_vec();
+        let _ = Reader::new(apdu.user_data()).values_to_end();
+    }
+});
@@ -0,0 +1,10 @@
+#![no_main]
+use bacnet::transport::bacnetip::BVLC;
+use bacnet::{Decode, Encode};
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(bvlc) = BVLC::decode_slice(data) {
+        let _ = bvlc.encode_vec();
+    }
+});
@@ -0,0 +1,11 @@
+#![no_main]
+use bacnet::capture::CaptureReader;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(reader) = CaptureReader::new(data) {
+        for record in reader.flatten() {
+            let _ = record.to_json();
+        }
+    }
+});
@@ -0,0 +1,8 @@
+#![no_main]
+use bacnet::application::CovNotification;
+use bacnet::Decode;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    let _ = CovNotification::decode_slice(data);
+});
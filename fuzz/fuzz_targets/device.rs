@@ -0,0 +1,66 @@
+#![no_main]
+//! Requests served by a device, the input is an APDU
+use async_std::channel::{self, Receiver, Sender};
+use async_std::task;
+use bacnet::application::{ConfirmedServiceChoice, APDU};
+use bacnet::network::{NPDUPriority, NPDU};
+use bacnet::objects::LightingOutput;
+use bacnet::server::{BacnetDevice, DeviceInfo};
+use bacnet::transport::{BoxFuture, DataLink};
+use bacnet::Decode;
+use libfuzzer_sys::fuzz_target;
+
+/// MAC address of the station sending the marker request
+const MARKER: &[u8] = &[9];
+
+struct Link {
+    incoming: Receiver<(Vec<u8>, NPDU)>,
+    outgoing: Sender<(Vec<u8>, NPDU)>,
+}
+
+impl DataLink for Link {
+    fn send<'a>(&'a self, mac: &'a [u8], npdu: &'a NPDU) -> BoxFuture<'a, std::io::Result<()>> {
+        Box::pin(async move {
+            let _ = self.outgoing.send((mac.to_vec(), npdu.clone())).await;
+            Ok(())
+        })
+    }
+
+    fn recv(&self) -> BoxFuture<'_, std::io::Result<(Vec<u8>, NPDU)>> {
+        Box::pin(async move {
+            self.incoming
+                .recv()
+                .await
+                .map_err(|_| std::io::ErrorKind::BrokenPipe.into())
+        })
+    }
+}
+
+fuzz_target!(|data: &[u8]| {
+    let apdu = match APDU::decode_slice(data) {
+        Ok(apdu) => apdu,
+        Err(_) => return,
+    };
+    task::block_on(async {
+        let (to_device, incoming) = channel::unbounded();
+        let (outgoing, from_device) = channel::unbounded();
+        let device =
+            BacnetDevice::new(Link { incoming, outgoing }, DeviceInfo::new(12, "Fuzz", 15));
+        device.objects().insert(LightingOutput::new(1, "Lamp"));
+
+        let npdu = NPDU::new(apdu, None, None, NPDUPriority::Normal);
+        to_device.send((vec![2], npdu)).await.unwrap();
+        // DeviceCommunicationControl enabling communication, which is
+        // answered in any state, marks the end of the responses to the input
+        let service = ConfirmedServiceChoice::DeviceCommunicationControl as u8;
+        let marker = APDU::confirmed_request(0, service, vec![0x19, 0x00]);
+        let npdu = NPDU::new(marker, None, None, NPDUPriority::Normal);
+        to_device.send((MARKER.to_vec(), npdu)).await.unwrap();
+        while let Ok((mac, _)) = from_device.recv().await {
+            if mac == MARKER {
+                break;
+            }
+        }
+        drop(device);
+    });
+});
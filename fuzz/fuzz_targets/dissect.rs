@@ -0,0 +1,8 @@
+#![no_main]
+use bacnet::dissect::{dissect_bvlc, dissect_npdu};
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    let _ = dissect_bvlc(data).to_string();
+    let _ = dissect_npdu(data).to_string();
+});
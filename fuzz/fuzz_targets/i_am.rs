@@ -0,0 +1,8 @@
+#![no_main]
+use bacnet::application::IAm;
+use bacnet::Decode;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    let _ = IAm::decode_slice(data);
+});
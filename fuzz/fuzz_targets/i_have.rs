@@ -0,0 +1,8 @@
+#![no_main]
+use bacnet::application::IHave;
+use bacnet::Decode;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    let _ = IHave::decode_slice(data);
+});
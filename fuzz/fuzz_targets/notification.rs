@@ -0,0 +1,12 @@
+#![no_main]
+//! Unsolicited requests decoded by the client, the first octet is the
+//! service choice
+use bacnet::client::Notification;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    if let Some((service_choice, data)) = data.split_first() {
+        let _ = Notification::confirmed(*service_choice, data);
+        let _ = Notification::unconfirmed(*service_choice, data);
+    }
+});
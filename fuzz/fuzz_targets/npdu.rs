@@ -0,0 +1,13 @@
+#![no_main]
+use bacnet::network::{NPDUContent, NPDU};
+use bacnet::{Decode, Encode};
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(npdu) = NPDU::decode_slice(data) {
+        let _ = npdu.encode_vec();
+        if let NPDUContent::Message(message) = &npdu.content {
+            let _ = message.networks();
+        }
+    }
+});
@@ -0,0 +1,8 @@
+#![no_main]
+use bacnet::application::SubscribeCov;
+use bacnet::Decode;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    let _ = SubscribeCov::decode_slice(data);
+});
@@ -0,0 +1,9 @@
+#![no_main]
+use bacnet::encoding::{decode_buf, parse_bacnet_tag, Reader};
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    let _ = parse_bacnet_tag(data);
+    let _ = decode_buf(data);
+    let _ = Reader::new(data).values_to_end();
+});
@@ -0,0 +1,8 @@
+#![no_main]
+use bacnet::application::UnconfirmedService;
+use bacnet::Decode;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    let _ = UnconfirmedService::decode_slice(data);
+});
@@ -1,9 +1,37 @@
+#[cfg(feature = "serde")]
+use crate::encoding::Reader;
+use crate::error::{EncodingError, ServiceError};
 use crate::{Decode, Encode};
 
-use byteorder::{BigEndian, ReadBytesExt, WriteBytesExt};
+use byteorder::{ReadBytesExt, WriteBytesExt};
+use bytes::Bytes;
+#[cfg(any(feature = "runtime", feature = "serde"))]
+use num_traits::FromPrimitive;
+#[cfg(feature = "serde")]
+use serde::ser::{Serialize, SerializeStruct, Serializer};
 
+pub mod bit_string;
+pub mod character_string;
+pub mod error;
+pub mod identifier;
+pub mod property;
+pub mod reference;
+pub mod segmentation;
 pub mod service;
+pub mod time;
+pub mod value;
+pub mod vendor;
+pub use bit_string::*;
+pub use character_string::*;
+pub use error::*;
+pub use identifier::*;
+pub use property::*;
+pub use reference::*;
+pub use segmentation::*;
 pub use service::*;
+pub use time::*;
+pub use value::*;
+pub use vendor::*;
 
 use tracing::trace;
 
@@ -23,6 +51,7 @@ use tracing::trace;
 /// ```
 ///
 #[derive(Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum BACnetPDU {
     ConfirmedRequest,   // = 0x00;
     UnconfirmedRequest, // = 0x01;
@@ -35,7 +64,7 @@ pub enum BACnetPDU {
 }
 
 impl BACnetPDU {
-    fn as_u8(&self) -> u8 {
+    pub fn as_u8(&self) -> u8 {
         match self {
             Self::ConfirmedRequest => 0,
             Self::UnconfirmedRequest => 1,
@@ -53,48 +82,851 @@ impl BACnetPDU {
 #[derive(Clone, Debug, Eq, PartialEq)]
 pub struct BACnetUnconfirmedRequestPDU {}
 
+/// Flags of the first octet of the header (20.1.2.1 to 20.1.2.3, 20.1.6.1,
+/// 20.1.6.2, 20.1.9.1)
+pub(crate) const SEGMENTED_MESSAGE: u8 = 0b1000;
+pub(crate) const MORE_FOLLOWS: u8 = 0b0100;
+pub(crate) const SEGMENTED_RESPONSE_ACCEPTED: u8 = 0b0010;
+pub(crate) const NEGATIVE_ACK: u8 = 0b0010;
+pub(crate) const SERVER: u8 = 0b0001;
+
+/// Max APDU length accepted sent with confirmed requests, up to 1476 octets
+/// (20.1.2.5)
+const MAX_APDU: u8 = 0x05;
+
+/// Segmentation fields of a segmented confirmed request or complex ACK
+/// (20.1.2.2, 20.1.2.3, 20.1.2.10, 20.1.2.11)
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Segment {
+    /// More segments follow this one
+    pub more_follows: bool,
+    /// The sequence number of the segment, modulo 256
+    pub sequence_number: u8,
+    /// The number of segments the sender sends before it waits for a
+    /// SegmentACK, 1 to 127
+    pub proposed_window_size: u8,
+}
+
+/// Application Layer PDU (20.1)
+///
+/// Every PDU type carries the fields of its header. The service parameters
+/// share the buffer of the frame they were decoded from, see
+/// [`Decode::decode_bytes`].
+///
+/// Segmented confirmed requests and complex ACKs carry their [`Segment`]
+/// fields, the service parameters are those of the segment.
 #[derive(Clone, Debug, Eq, PartialEq)]
-pub struct APDU {
-    apdu_type: u8,
-    pub service_choice: u8,
-    user_data: Vec<u8>,
+pub enum APDU {
+    /// BACnet-Confirmed-Request-PDU (20.1.2)
+    ConfirmedRequest {
+        segmented_response_accepted: bool,
+        /// Max segments accepted as encoded, 0 if unspecified (20.1.2.4)
+        max_segments: u8,
+        /// Max APDU length accepted as encoded, see
+        /// [`max_apdu_length_accepted`](Self::max_apdu_length_accepted)
+        max_apdu: u8,
+        invoke_id: u8,
+        /// The segment of a segmented request
+        segment: Option<Segment>,
+        service_choice: u8,
+        user_data: Bytes,
+    },
+    /// BACnet-Unconfirmed-Request-PDU (20.1.3)
+    UnconfirmedRequest {
+        service_choice: u8,
+        user_data: Bytes,
+    },
+    /// BACnet-SimpleACK-PDU (20.1.4)
+    SimpleAck { invoke_id: u8, service_choice: u8 },
+    /// BACnet-ComplexACK-PDU (20.1.5)
+    ComplexAck {
+        invoke_id: u8,
+        /// The segment of a segmented ACK
+        segment: Option<Segment>,
+        service_choice: u8,
+        user_data: Bytes,
+    },
+    /// BACnet-SegmentACK-PDU (20.1.6)
+    SegmentAck {
+        /// The segment is not in sequence, it has to be sent again
+        negative: bool,
+        /// Sent by the server of the transaction
+        server: bool,
+        invoke_id: u8,
+        sequence_number: u8,
+        actual_window_size: u8,
+    },
+    /// BACnet-Error-PDU (20.1.7), the user data is a [`BACnetError`], see
+    /// [`ErrorPDU`]
+    Error {
+        invoke_id: u8,
+        service_choice: u8,
+        user_data: Bytes,
+    },
+    /// BACnet-Reject-PDU (20.1.8)
+    Reject { invoke_id: u8, reason: u8 },
+    /// BACnet-Abort-PDU (20.1.9)
+    Abort {
+        /// Sent by the server of the transaction
+        server: bool,
+        invoke_id: u8,
+        reason: u8,
+    },
 }
 
 impl APDU {
-    pub fn new(apdu_type: u8, service_choice: u8, user_data: Vec<u8>) -> Self {
+    pub fn confirmed_request(
+        invoke_id: u8,
+        service_choice: u8,
+        user_data: impl Into<Bytes>,
+    ) -> Self {
+        Self::ConfirmedRequest {
+            segmented_response_accepted: false,
+            max_segments: 0,
+            max_apdu: MAX_APDU,
+            invoke_id,
+            segment: None,
+            service_choice,
+            user_data: user_data.into(),
+        }
+    }
+
+    pub fn unconfirmed_request(service_choice: u8, user_data: impl Into<Bytes>) -> Self {
+        Self::UnconfirmedRequest {
+            service_choice,
+            user_data: user_data.into(),
+        }
+    }
+
+    pub fn simple_ack(invoke_id: u8, service_choice: u8) -> Self {
+        Self::SimpleAck {
+            invoke_id,
+            service_choice,
+        }
+    }
+
+    pub fn complex_ack(invoke_id: u8, service_choice: u8, user_data: impl Into<Bytes>) -> Self {
+        Self::ComplexAck {
+            invoke_id,
+            segment: None,
+            service_choice,
+            user_data: user_data.into(),
+        }
+    }
+
+    pub fn segment_ack(
+        negative: bool,
+        server: bool,
+        invoke_id: u8,
+        sequence_number: u8,
+        actual_window_size: u8,
+    ) -> Self {
+        Self::SegmentAck {
+            negative,
+            server,
+            invoke_id,
+            sequence_number,
+            actual_window_size,
+        }
+    }
+
+    pub fn error(invoke_id: u8, service_choice: u8, user_data: impl Into<Bytes>) -> Self {
+        Self::Error {
+            invoke_id,
+            service_choice,
+            user_data: user_data.into(),
+        }
+    }
+
+    pub fn reject(invoke_id: u8, reason: u8) -> Self {
+        Self::Reject { invoke_id, reason }
+    }
+
+    pub fn abort(server: bool, invoke_id: u8, reason: u8) -> Self {
+        Self::Abort {
+            server,
+            invoke_id,
+            reason,
+        }
+    }
+
+    pub fn pdu_type(&self) -> BACnetPDU {
+        match self {
+            Self::ConfirmedRequest { .. } => BACnetPDU::ConfirmedRequest,
+            Self::UnconfirmedRequest { .. } => BACnetPDU::UnconfirmedRequest,
+            Self::SimpleAck { .. } => BACnetPDU::SimpleACK,
+            Self::ComplexAck { .. } => BACnetPDU::ComplexACK,
+            Self::SegmentAck { .. } => BACnetPDU::SegmentACK,
+            Self::Error { .. } => BACnetPDU::Error,
+            Self::Reject { .. } => BACnetPDU::Reject,
+            Self::Abort { .. } => BACnetPDU::Abort,
+        }
+    }
+
+    /// The invoke ID, `None` for unconfirmed requests
+    pub fn invoke_id(&self) -> Option<u8> {
+        match self {
+            Self::UnconfirmedRequest { .. } => None,
+            Self::ConfirmedRequest { invoke_id, .. }
+            | Self::SimpleAck { invoke_id, .. }
+            | Self::ComplexAck { invoke_id, .. }
+            | Self::SegmentAck { invoke_id, .. }
+            | Self::Error { invoke_id, .. }
+            | Self::Reject { invoke_id, .. }
+            | Self::Abort { invoke_id, .. } => Some(*invoke_id),
+        }
+    }
+
+    /// The service choice, `None` for segment ACKs, rejects and aborts
+    pub fn service_choice(&self) -> Option<u8> {
+        match self {
+            Self::ConfirmedRequest { service_choice, .. }
+            | Self::UnconfirmedRequest { service_choice, .. }
+            | Self::SimpleAck { service_choice, .. }
+            | Self::ComplexAck { service_choice, .. }
+            | Self::Error { service_choice, .. } => Some(*service_choice),
+            Self::SegmentAck { .. } | Self::Reject { .. } | Self::Abort { .. } => None,
+        }
+    }
+
+    /// The segmentation fields of a segmented confirmed request or complex
+    /// ACK
+    pub fn segment(&self) -> Option<Segment> {
+        match self {
+            Self::ConfirmedRequest { segment, .. } | Self::ComplexAck { segment, .. } => *segment,
+            _ => None,
+        }
+    }
+
+    /// The reason of a reject or an abort
+    pub fn reason(&self) -> Option<u8> {
+        match self {
+            Self::Reject { reason, .. } | Self::Abort { reason, .. } => Some(*reason),
+            _ => None,
+        }
+    }
+
+    /// Max APDU length accepted by the sender of a confirmed request
+    /// (20.1.2.5), `None` for other PDUs and reserved values
+    pub fn max_apdu_length_accepted(&self) -> Option<u32> {
+        match self {
+            Self::ConfirmedRequest { max_apdu, .. } => match max_apdu {
+                0 => Some(50),
+                1 => Some(128),
+                2 => Some(206),
+                3 => Some(480),
+                4 => Some(1024),
+                5 => Some(1476),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    /// Max segments accepted by the sender of a confirmed request
+    /// (20.1.2.4), `None` if unspecified or more than 64
+    pub fn max_segments_accepted(&self) -> Option<usize> {
+        match self {
+            Self::ConfirmedRequest { max_segments, .. } => match max_segments {
+                1..=6 => Some(1 << max_segments),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    /// The service parameters following the header, empty for PDUs
+    /// without them
+    pub fn user_data(&self) -> &[u8] {
+        match self {
+            Self::ConfirmedRequest { user_data, .. }
+            | Self::UnconfirmedRequest { user_data, .. }
+            | Self::ComplexAck { user_data, .. }
+            | Self::Error { user_data, .. } => user_data,
+            _ => &[],
+        }
+    }
+
+    pub fn into_user_data(self) -> Bytes {
+        match self {
+            Self::ConfirmedRequest { user_data, .. }
+            | Self::UnconfirmedRequest { user_data, .. }
+            | Self::ComplexAck { user_data, .. }
+            | Self::Error { user_data, .. } => user_data,
+            _ => Bytes::new(),
+        }
+    }
+
+    /// The name of the service, `None` if it is unknown or the PDU has no
+    /// service choice
+    #[cfg(any(feature = "runtime", feature = "serde"))]
+    pub(crate) fn service_name(&self) -> Option<String> {
+        match self {
+            Self::UnconfirmedRequest { service_choice, .. } => {
+                UnconfirmedServiceChoice::from_u8(*service_choice).map(|s| format!("{:?}", s))
+            }
+            _ => self
+                .service_choice()
+                .and_then(ConfirmedServiceChoice::from_u8)
+                .map(|s| format!("{:?}", s)),
+        }
+    }
+
+    /// Length of the header, up to the service parameters
+    fn header_len(&self) -> usize {
+        match self {
+            Self::ConfirmedRequest { segment, .. } => 4 + segment.map_or(0, |_| 2),
+            Self::ComplexAck { segment, .. } => 3 + segment.map_or(0, |_| 2),
+            Self::UnconfirmedRequest { .. } => 2,
+            Self::SegmentAck { .. } => 4,
+            _ => 3,
+        }
+    }
+}
+
+/// Builder of a BACnet-Confirmed-Request-PDU (20.1.2), which by default
+/// accepts responses of up to 1476 octets, unsegmented
+#[derive(Clone, Debug)]
+pub struct ConfirmedRequestBuilder {
+    invoke_id: u8,
+    service_choice: ConfirmedServiceChoice,
+    segmented_response_accepted: bool,
+    max_apdu: u8,
+    user_data: Bytes,
+}
+
+impl ConfirmedRequestBuilder {
+    pub fn new(service_choice: ConfirmedServiceChoice) -> Self {
         Self {
-            apdu_type,
+            invoke_id: 0,
             service_choice,
-            user_data,
+            segmented_response_accepted: false,
+            max_apdu: MAX_APDU,
+            user_data: Bytes::new(),
+        }
+    }
+
+    pub fn invoke_id(mut self, invoke_id: u8) -> Self {
+        self.invoke_id = invoke_id;
+        self
+    }
+
+    /// The largest response accepted, rounded down to a length that can be
+    /// encoded (20.1.2.5) but at least 50 octets
+    pub fn max_apdu_length_accepted(mut self, length: u32) -> Self {
+        self.max_apdu = match length {
+            0..=127 => 0,
+            128..=205 => 1,
+            206..=479 => 2,
+            480..=1023 => 3,
+            1024..=1475 => 4,
+            _ => 5,
+        };
+        self
+    }
+
+    /// Accept a segmented response, of any number of segments
+    pub fn segmented_response_accepted(mut self) -> Self {
+        self.segmented_response_accepted = true;
+        self
+    }
+
+    /// The service parameters
+    pub fn user_data(mut self, user_data: impl Into<Bytes>) -> Self {
+        self.user_data = user_data.into();
+        self
+    }
+
+    pub fn build(self) -> APDU {
+        APDU::ConfirmedRequest {
+            segmented_response_accepted: self.segmented_response_accepted,
+            max_segments: 0,
+            max_apdu: self.max_apdu,
+            invoke_id: self.invoke_id,
+            segment: None,
+            service_choice: self.service_choice as u8,
+            user_data: self.user_data,
         }
     }
 }
 
 impl Encode for APDU {
-    fn encode<T: std::io::Write + Sized>(&self, writer: &mut T) -> std::io::Result<()> {
-        writer.write_u8(self.apdu_type << 4)?;
-        writer.write_u8(self.service_choice)?;
-        writer.write(&self.user_data)?;
+    fn encode<T: std::io::Write + Sized>(&self, writer: &mut T) -> crate::error::Result<()> {
+        let pdu_type = self.pdu_type().as_u8() << 4;
+        match self {
+            Self::ConfirmedRequest {
+                segmented_response_accepted,
+                max_segments,
+                max_apdu,
+                invoke_id,
+                segment,
+                service_choice,
+                ..
+            } => {
+                let flags = match segmented_response_accepted {
+                    true => SEGMENTED_RESPONSE_ACCEPTED,
+                    false => 0,
+                };
+                writer.write_u8(pdu_type | flags | segment_flags(segment))?;
+                writer.write_u8((max_segments & 0x07) << 4 | max_apdu & 0x0F)?;
+                writer.write_u8(*invoke_id)?;
+                write_segment(writer, segment)?;
+                writer.write_u8(*service_choice)?;
+            }
+            Self::ComplexAck {
+                invoke_id,
+                segment,
+                service_choice,
+                ..
+            } => {
+                writer.write_u8(pdu_type | segment_flags(segment))?;
+                writer.write_u8(*invoke_id)?;
+                write_segment(writer, segment)?;
+                writer.write_u8(*service_choice)?;
+            }
+            Self::UnconfirmedRequest { service_choice, .. } => {
+                writer.write_u8(pdu_type)?;
+                writer.write_u8(*service_choice)?;
+            }
+            Self::SimpleAck {
+                invoke_id,
+                service_choice,
+            }
+            | Self::Error {
+                invoke_id,
+                service_choice,
+                ..
+            } => {
+                writer.write_u8(pdu_type)?;
+                writer.write_u8(*invoke_id)?;
+                writer.write_u8(*service_choice)?;
+            }
+            Self::SegmentAck {
+                negative,
+                server,
+                invoke_id,
+                sequence_number,
+                actual_window_size,
+            } => {
+                let negative = if *negative { NEGATIVE_ACK } else { 0 };
+                let server = if *server { SERVER } else { 0 };
+                writer.write_u8(pdu_type | negative | server)?;
+                writer.write_u8(*invoke_id)?;
+                writer.write_u8(*sequence_number)?;
+                writer.write_u8(*actual_window_size)?;
+            }
+            Self::Reject { invoke_id, reason } => {
+                writer.write_u8(pdu_type)?;
+                writer.write_u8(*invoke_id)?;
+                writer.write_u8(*reason)?;
+            }
+            Self::Abort {
+                server,
+                invoke_id,
+                reason,
+            } => {
+                writer.write_u8(pdu_type | if *server { SERVER } else { 0 })?;
+                writer.write_u8(*invoke_id)?;
+                writer.write_u8(*reason)?;
+            }
+        }
+        writer.write_all(self.user_data())?;
         Ok(())
     }
 
     fn len(&self) -> usize {
-        let mut l = 0;
-        l += 1; // Type
-        l += 1; // Service Choice
-        l += self.user_data.len(); // Content
-        l
+        self.header_len() + self.user_data().len()
+    }
+}
+
+/// The header fields, the name of the service and the service parameters
+/// decoded with [`Reader::values_to_end`], `null` if they are malformed
+#[cfg(feature = "serde")]
+impl Serialize for APDU {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let service = self.service_name();
+        let values = Reader::new(self.user_data()).values_to_end().ok();
+
+        let mut apdu = serializer.serialize_struct("APDU", 10)?;
+        apdu.serialize_field("pdu_type", &self.pdu_type())?;
+        apdu.serialize_field("invoke_id", &self.invoke_id())?;
+        apdu.serialize_field("service_choice", &self.service_choice())?;
+        if let Self::ConfirmedRequest {
+            segmented_response_accepted,
+            max_segments,
+            max_apdu,
+            ..
+        } = self
+        {
+            apdu.serialize_field("segmented_response_accepted", segmented_response_accepted)?;
+            apdu.serialize_field("max_segments", max_segments)?;
+            apdu.serialize_field("max_apdu", max_apdu)?;
+        }
+        match self {
+            Self::ConfirmedRequest {
+                segment: Some(segment),
+                ..
+            }
+            | Self::ComplexAck {
+                segment: Some(segment),
+                ..
+            } => apdu.serialize_field("segment", segment)?,
+            Self::SegmentAck {
+                negative,
+                server,
+                sequence_number,
+                actual_window_size,
+                ..
+            } => {
+                apdu.serialize_field("negative", negative)?;
+                apdu.serialize_field("server", server)?;
+                apdu.serialize_field("sequence_number", sequence_number)?;
+                apdu.serialize_field("actual_window_size", actual_window_size)?;
+            }
+            Self::Reject { reason, .. } => apdu.serialize_field("reason", reason)?,
+            Self::Abort { server, reason, .. } => {
+                apdu.serialize_field("server", server)?;
+                apdu.serialize_field("reason", reason)?;
+            }
+            _ => {}
+        }
+        apdu.serialize_field("service", &service)?;
+        apdu.serialize_field("values", &values)?;
+        apdu.serialize_field("data", &hex::encode(self.user_data()))?;
+        apdu.end()
+    }
+}
+
+/// The fields written by the [`Serialize`] implementation, `service` and
+/// `values` are derived from the others and ignored
+#[cfg(feature = "serde")]
+#[derive(serde::Deserialize)]
+struct SerializedAPDU {
+    pdu_type: BACnetPDU,
+    invoke_id: Option<u8>,
+    service_choice: Option<u8>,
+    #[serde(default)]
+    segmented_response_accepted: bool,
+    #[serde(default)]
+    max_segments: u8,
+    #[serde(default = "max_apdu")]
+    max_apdu: u8,
+    segment: Option<Segment>,
+    #[serde(default)]
+    negative: bool,
+    #[serde(default)]
+    server: bool,
+    #[serde(default)]
+    sequence_number: u8,
+    #[serde(default)]
+    actual_window_size: u8,
+    #[serde(default)]
+    reason: u8,
+    #[serde(default)]
+    data: String,
+}
+
+#[cfg(feature = "serde")]
+fn max_apdu() -> u8 {
+    MAX_APDU
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for APDU {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        use serde::de::Error;
+
+        let apdu = SerializedAPDU::deserialize(deserializer)?;
+        let invoke_id = apdu
+            .invoke_id
+            .ok_or_else(|| D::Error::missing_field("invoke_id"));
+        let service_choice = apdu
+            .service_choice
+            .ok_or_else(|| D::Error::missing_field("service_choice"));
+        let user_data = || {
+            hex::decode(&apdu.data)
+                .map(Bytes::from)
+                .map_err(|_| D::Error::custom("data is not hex"))
+        };
+        Ok(match apdu.pdu_type {
+            BACnetPDU::ConfirmedRequest => Self::ConfirmedRequest {
+                segmented_response_accepted: apdu.segmented_response_accepted,
+                max_segments: apdu.max_segments,
+                max_apdu: apdu.max_apdu,
+                invoke_id: invoke_id?,
+                segment: apdu.segment,
+                service_choice: service_choice?,
+                user_data: user_data()?,
+            },
+            BACnetPDU::UnconfirmedRequest => Self::UnconfirmedRequest {
+                service_choice: service_choice?,
+                user_data: user_data()?,
+            },
+            BACnetPDU::SimpleACK => Self::SimpleAck {
+                invoke_id: invoke_id?,
+                service_choice: service_choice?,
+            },
+            BACnetPDU::ComplexACK => Self::ComplexAck {
+                invoke_id: invoke_id?,
+                segment: apdu.segment,
+                service_choice: service_choice?,
+                user_data: user_data()?,
+            },
+            BACnetPDU::SegmentACK => Self::SegmentAck {
+                negative: apdu.negative,
+                server: apdu.server,
+                invoke_id: invoke_id?,
+                sequence_number: apdu.sequence_number,
+                actual_window_size: apdu.actual_window_size,
+            },
+            BACnetPDU::Error => Self::Error {
+                invoke_id: invoke_id?,
+                service_choice: service_choice?,
+                user_data: user_data()?,
+            },
+            BACnetPDU::Reject => Self::Reject {
+                invoke_id: invoke_id?,
+                reason: apdu.reason,
+            },
+            BACnetPDU::Abort => Self::Abort {
+                server: apdu.server,
+                invoke_id: invoke_id?,
+                reason: apdu.reason,
+            },
+        })
+    }
+}
+
+fn segment_flags(segment: &Option<Segment>) -> u8 {
+    match segment {
+        Some(segment) if segment.more_follows => SEGMENTED_MESSAGE | MORE_FOLLOWS,
+        Some(_) => SEGMENTED_MESSAGE,
+        None => 0,
+    }
+}
+
+fn write_segment<T: std::io::Write>(
+    writer: &mut T,
+    segment: &Option<Segment>,
+) -> std::io::Result<()> {
+    if let Some(segment) = segment {
+        writer.write_u8(segment.sequence_number)?;
+        writer.write_u8(segment.proposed_window_size)?;
+    }
+    Ok(())
+}
+
+/// The sequence number and proposed window size following the invoke ID of
+/// a segmented message
+fn read_segment<T: std::io::Read>(reader: &mut T, flags: u8) -> std::io::Result<Option<Segment>> {
+    match flags & SEGMENTED_MESSAGE != 0 {
+        true => Ok(Some(Segment {
+            more_follows: flags & MORE_FOLLOWS != 0,
+            sequence_number: reader.read_u8()?,
+            proposed_window_size: reader.read_u8()?,
+        })),
+        false => Ok(None),
+    }
+}
+
+impl APDU {
+    /// Decode the header, the service parameters that follow it are read by
+    /// `user_data` from the rest of the reader
+    fn decode_with<T: std::io::Read + Sized>(
+        reader: &mut T,
+        user_data: impl FnOnce(&mut T) -> crate::error::Result<Bytes>,
+    ) -> crate::error::Result<Self> {
+        let first = reader.read_u8()?;
+        let flags = first & 0x0F;
+        trace!("APDU Type: {}", first >> 4);
+
+        let apdu = match first >> 4 {
+            0 => {
+                let max_response = reader.read_u8()?;
+                Self::ConfirmedRequest {
+                    segmented_response_accepted: flags & SEGMENTED_RESPONSE_ACCEPTED != 0,
+                    max_segments: max_response >> 4 & 0x07,
+                    max_apdu: max_response & 0x0F,
+                    invoke_id: reader.read_u8()?,
+                    segment: read_segment(reader, flags)?,
+                    service_choice: reader.read_u8()?,
+                    user_data: user_data(reader)?,
+                }
+            }
+            1 => Self::UnconfirmedRequest {
+                service_choice: reader.read_u8()?,
+                user_data: user_data(reader)?,
+            },
+            2 => Self::SimpleAck {
+                invoke_id: reader.read_u8()?,
+                service_choice: reader.read_u8()?,
+            },
+            3 => Self::ComplexAck {
+                invoke_id: reader.read_u8()?,
+                segment: read_segment(reader, flags)?,
+                service_choice: reader.read_u8()?,
+                user_data: user_data(reader)?,
+            },
+            4 => Self::SegmentAck {
+                negative: flags & NEGATIVE_ACK != 0,
+                server: flags & SERVER != 0,
+                invoke_id: reader.read_u8()?,
+                sequence_number: reader.read_u8()?,
+                actual_window_size: reader.read_u8()?,
+            },
+            5 => Self::Error {
+                invoke_id: reader.read_u8()?,
+                service_choice: reader.read_u8()?,
+                user_data: user_data(reader)?,
+            },
+            6 => Self::Reject {
+                invoke_id: reader.read_u8()?,
+                reason: reader.read_u8()?,
+            },
+            7 => Self::Abort {
+                server: flags & SERVER != 0,
+                invoke_id: reader.read_u8()?,
+                reason: reader.read_u8()?,
+            },
+            t => return Err(ServiceError::UnsupportedPduType(t).into()),
+        };
+        Ok(apdu)
     }
 }
 
 impl Decode for APDU {
-    fn decode<T: std::io::Read + Sized>(reader: &mut T) -> std::io::Result<Self> {
-        let apdu_type = reader.read_u8()? >> 4;
-        let service_choice = reader.read_u8()?;
-        let mut content = Vec::new(); // TODO: What capacity?
-        reader.read_to_end(&mut content)?;
-        trace!("APDU Type: {}", apdu_type);
-        Ok(APDU::new(apdu_type, service_choice, content))
+    fn decode<T: std::io::Read + Sized>(reader: &mut T) -> crate::error::Result<Self> {
+        Self::decode_with(reader, |reader| {
+            let mut user_data = Vec::new();
+            reader.read_to_end(&mut user_data)?;
+            Ok(user_data.into())
+        })
+    }
+
+    fn decode_bytes(data: Bytes) -> crate::error::Result<Self> {
+        let mut reader = std::io::Cursor::new(&data[..]);
+        Self::decode_with(&mut reader, |reader| {
+            Ok(data.slice(reader.position() as usize..))
+        })
+    }
+}
+
+/// An APDU borrowing the buffer it was decoded from
+///
+/// Decoding only checks the header, the fields are read from the buffer
+/// when they are accessed. Packet inspectors look at the header and the
+/// service parameters of a frame without copying them, see [`APDU`] for its
+/// owned counterpart.
+///
+/// ```
+/// use bacnet::application::{APDUSlice, BACnetPDU};
+///
+/// let data = [0x00, 0x05, 0x01, 0x0c, 0x0c, 0x00, 0x00, 0x00, 0x01, 0x19, 0x55];
+/// let apdu = APDUSlice::from_slice(&data).unwrap();
+/// assert_eq!(apdu.pdu_type(), BACnetPDU::ConfirmedRequest);
+/// assert_eq!(apdu.invoke_id(), Some(1));
+/// assert_eq!(apdu.service_choice(), Some(0x0c));
+/// assert_eq!(apdu.user_data(), &data[4..]);
+/// ```
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct APDUSlice<'a> {
+    data: &'a [u8],
+    header_len: usize,
+}
+
+impl<'a> APDUSlice<'a> {
+    /// Fails if the PDU type is unknown or `data` ends within the header
+    pub fn from_slice(data: &'a [u8]) -> crate::error::Result<Self> {
+        let first = *data.first().ok_or(EncodingError::Truncated)?;
+        let segmented = first & SEGMENTED_MESSAGE != 0;
+        let header_len = match first >> 4 {
+            0 => 4 + if segmented { 2 } else { 0 },
+            3 => 3 + if segmented { 2 } else { 0 },
+            1 => 2,
+            4 => 4,
+            2 | 5 | 6 | 7 => 3,
+            t => return Err(ServiceError::UnsupportedPduType(t).into()),
+        };
+        if data.len() < header_len {
+            return Err(EncodingError::Truncated.into());
+        }
+        Ok(Self { data, header_len })
+    }
+
+    pub fn pdu_type(&self) -> BACnetPDU {
+        match self.data[0] >> 4 {
+            0 => BACnetPDU::ConfirmedRequest,
+            1 => BACnetPDU::UnconfirmedRequest,
+            2 => BACnetPDU::SimpleACK,
+            3 => BACnetPDU::ComplexACK,
+            4 => BACnetPDU::SegmentACK,
+            5 => BACnetPDU::Error,
+            6 => BACnetPDU::Reject,
+            _ => BACnetPDU::Abort,
+        }
+    }
+
+    /// The invoke ID, `None` for unconfirmed requests
+    pub fn invoke_id(&self) -> Option<u8> {
+        match self.pdu_type() {
+            BACnetPDU::UnconfirmedRequest => None,
+            BACnetPDU::ConfirmedRequest => Some(self.data[2]),
+            _ => Some(self.data[1]),
+        }
+    }
+
+    /// The service choice, `None` for segment ACKs, rejects and aborts
+    pub fn service_choice(&self) -> Option<u8> {
+        match self.pdu_type() {
+            BACnetPDU::ConfirmedRequest | BACnetPDU::ComplexACK => {
+                Some(self.data[self.header_len - 1])
+            }
+            BACnetPDU::UnconfirmedRequest => Some(self.data[1]),
+            BACnetPDU::SimpleACK | BACnetPDU::Error => Some(self.data[2]),
+            _ => None,
+        }
+    }
+
+    /// The segmentation fields of a segmented confirmed request or complex
+    /// ACK
+    pub fn segment(&self) -> Option<Segment> {
+        let sequence_number = match self.pdu_type() {
+            BACnetPDU::ConfirmedRequest => 3,
+            BACnetPDU::ComplexACK => 2,
+            _ => return None,
+        };
+        let flags = self.data[0];
+        (flags & SEGMENTED_MESSAGE != 0).then(|| Segment {
+            more_follows: flags & MORE_FOLLOWS != 0,
+            sequence_number: self.data[sequence_number],
+            proposed_window_size: self.data[sequence_number + 1],
+        })
+    }
+
+    /// The reason of a reject or an abort
+    pub fn reason(&self) -> Option<u8> {
+        match self.pdu_type() {
+            BACnetPDU::Reject | BACnetPDU::Abort => Some(self.data[2]),
+            _ => None,
+        }
+    }
+
+    /// The service parameters following the header, empty for PDUs
+    /// without them
+    pub fn user_data(&self) -> &'a [u8] {
+        match self.pdu_type() {
+            BACnetPDU::ConfirmedRequest
+            | BACnetPDU::UnconfirmedRequest
+            | BACnetPDU::ComplexACK
+            | BACnetPDU::Error => &self.data[self.header_len..],
+            _ => &[],
+        }
+    }
+
+    /// The APDU with its service parameters copied
+    pub fn to_apdu(&self) -> crate::error::Result<APDU> {
+        APDU::decode_slice(self.data)
     }
 }
 
@@ -105,26 +937,237 @@ mod tests {
     use bytes::{BufMut, BytesMut};
     use hex;
 
-    use crate::tests::*;
-
     #[test]
     fn test_encode_apdu() {
         let content = vec![0, 0, 0];
-        let apdu = APDU::new(1, 8, content);
+        let apdu = APDU::unconfirmed_request(8, content);
 
         let mut w = BytesMut::new().writer();
         apdu.encode(&mut w).expect("Write APDU to buffer");
         assert_eq!(w.into_inner().to_vec(), vec![16, 8, 0, 0, 0]);
     }
 
+    #[test]
+    fn test_confirmed_request_builder() {
+        let apdu = ConfirmedRequestBuilder::new(ConfirmedServiceChoice::ReadProperty)
+            .invoke_id(3)
+            .max_apdu_length_accepted(480)
+            .user_data(vec![0x0c, 0x00, 0x00, 0x00, 0x01, 0x19, 0x55])
+            .build();
+        assert_eq!(
+            apdu.encode_vec().unwrap(),
+            hex::decode("000303 0c 0c00000001 1955".replace(' ', "")).unwrap()
+        );
+        assert_eq!(apdu.max_apdu_length_accepted(), Some(480));
+
+        let apdu = ConfirmedRequestBuilder::new(ConfirmedServiceChoice::ReadProperty)
+            .max_apdu_length_accepted(1000)
+            .build();
+        assert_eq!(apdu.max_apdu_length_accepted(), Some(480));
+        let apdu = ConfirmedRequestBuilder::new(ConfirmedServiceChoice::ReadProperty).build();
+        assert_eq!(
+            apdu,
+            APDU::confirmed_request(0, ConfirmedServiceChoice::ReadProperty as u8, vec![])
+        );
+    }
+
+    #[test]
+    fn test_encode_confirmed_request() {
+        // ReadProperty of analog-input,1 present-value
+        let apdu = APDU::confirmed_request(1, 12, hex::decode("0c000000011955").unwrap());
+        let data = apdu.encode_vec().unwrap();
+        assert_eq!(data, hex::decode("0005010c0c000000011955").unwrap());
+        assert_eq!(apdu.len(), data.len());
+        assert_eq!(APDU::decode_slice(&data).unwrap(), apdu);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serialize_apdu() {
+        let data = hex::decode("30010c0c0000000119553e4441a000003f").unwrap();
+        let apdu = APDU::decode_slice(&data).unwrap();
+        assert_eq!(
+            crate::json::to_string(&apdu).unwrap(),
+            concat!(
+                r#"{"pdu_type":"ComplexACK","invoke_id":1,"service_choice":12,"#,
+                r#""service":"ReadProperty","values":[{"Constructed":[[0,{"OctetString":[0,0,0,1]}],"#,
+                r#"[1,{"OctetString":[85]}],[3,{"Real":20}]]}],"data":"0c0000000119553e4441a000003f"}"#
+            )
+        );
+
+        // Reason of a reject
+        let json = crate::json::to_string(&APDU::reject(3, 9)).unwrap();
+        assert!(json.contains(r#""service_choice":null,"reason":9,"service":null,"values":[]"#));
+    }
+
+    #[test]
+    fn test_decode_complex_ack() {
+        let data = hex::decode("30010c0c0000000119553e4441a000003f").unwrap();
+        let apdu = APDU::decode_slice(&data).expect("Decode APDU");
+
+        assert_eq!(apdu.pdu_type(), BACnetPDU::ComplexACK);
+        assert_eq!(apdu.invoke_id(), Some(1));
+        assert_eq!(apdu.service_choice(), Some(12));
+        assert_eq!(apdu.user_data(), &data[3..]);
+        assert_eq!(apdu.encode_vec().unwrap(), data);
+
+        // The service parameters share the buffer of the frame
+        let data = Bytes::from(data);
+        let apdu = APDU::decode_bytes(data.clone()).expect("Decode APDU");
+        assert_eq!(apdu.user_data(), &data[3..]);
+        assert_eq!(apdu.user_data().as_ptr(), data[3..].as_ptr());
+    }
+
+    #[test]
+    fn test_decode_abort() {
+        let apdu = APDU::decode_slice(&[0x71, 0x05, 0x04]).expect("Decode APDU");
+
+        assert_eq!(apdu, APDU::abort(true, 5, 4));
+        assert_eq!(apdu.reason(), Some(4));
+        assert_eq!(apdu.service_choice(), None);
+        assert_eq!(apdu.len(), 3);
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let apdus = [
+            (
+                "0275030c0c00000001",
+                APDU::ConfirmedRequest {
+                    segmented_response_accepted: true,
+                    max_segments: 7,
+                    max_apdu: 5,
+                    invoke_id: 3,
+                    segment: None,
+                    service_choice: 12,
+                    user_data: Bytes::from_static(&[0x0c, 0x00, 0x00, 0x00, 0x01]),
+                },
+            ),
+            (
+                "0e0509000f0e3e",
+                APDU::ConfirmedRequest {
+                    segmented_response_accepted: true,
+                    max_segments: 0,
+                    max_apdu: 5,
+                    invoke_id: 9,
+                    segment: Some(Segment {
+                        more_follows: true,
+                        sequence_number: 0,
+                        proposed_window_size: 15,
+                    }),
+                    service_choice: 14,
+                    user_data: Bytes::from_static(&[0x3e]),
+                },
+            ),
+            (
+                "3807020a0e3f",
+                APDU::ComplexAck {
+                    invoke_id: 7,
+                    segment: Some(Segment {
+                        more_follows: false,
+                        sequence_number: 2,
+                        proposed_window_size: 10,
+                    }),
+                    service_choice: 14,
+                    user_data: Bytes::from_static(&[0x3f]),
+                },
+            ),
+            ("1008", APDU::unconfirmed_request(8, vec![])),
+            ("20070f", APDU::simple_ack(7, 15)),
+            ("30070c3e3f", APDU::complex_ack(7, 12, vec![0x3e, 0x3f])),
+            ("430a0504", APDU::segment_ack(true, true, 10, 5, 4)),
+            (
+                "50070c91029120",
+                APDU::error(7, 12, vec![0x91, 0x02, 0x91, 0x20]),
+            ),
+            ("600709", APDU::reject(7, 9)),
+            ("700701", APDU::abort(false, 7, 1)),
+        ];
+        for (data, apdu) in apdus.iter() {
+            let data = hex::decode(data).unwrap();
+            assert_eq!(&APDU::decode_slice(&data).unwrap(), apdu, "{:02x?}", data);
+            assert_eq!(apdu.encode_vec().unwrap(), data);
+            assert_eq!(apdu.len(), data.len());
+
+            // The borrowed header fields are those of the owned APDU
+            let slice = APDUSlice::from_slice(&data).unwrap();
+            assert_eq!(slice.pdu_type(), apdu.pdu_type());
+            assert_eq!(slice.invoke_id(), apdu.invoke_id());
+            assert_eq!(slice.service_choice(), apdu.service_choice());
+            assert_eq!(slice.segment(), apdu.segment());
+            assert_eq!(slice.reason(), apdu.reason());
+            assert_eq!(slice.user_data(), apdu.user_data());
+            assert_eq!(&slice.to_apdu().unwrap(), apdu);
+        }
+    }
+
+    #[test]
+    fn test_header_fields() {
+        let apdu = APDU::decode_slice(&hex::decode("0205030c").unwrap()).unwrap();
+        assert_eq!(apdu.pdu_type(), BACnetPDU::ConfirmedRequest);
+        assert_eq!(apdu.invoke_id(), Some(3));
+        assert_eq!(apdu.service_choice(), Some(12));
+        assert_eq!(apdu.max_apdu_length_accepted(), Some(1476));
+        assert!(matches!(
+            apdu,
+            APDU::ConfirmedRequest {
+                segmented_response_accepted: true,
+                max_segments: 0,
+                ..
+            }
+        ));
+
+        let apdu = APDU::decode_slice(&[0x40, 0x0a, 0x05, 0x04]).unwrap();
+        assert_eq!(apdu.invoke_id(), Some(10));
+        assert_eq!(apdu.service_choice(), None);
+        assert_eq!(apdu.max_apdu_length_accepted(), None);
+        assert!(apdu.user_data().is_empty());
+
+        let apdu = APDU::decode_slice(&hex::decode("3c0703040c3e").unwrap()).unwrap();
+        assert_eq!(
+            apdu.segment(),
+            Some(Segment {
+                more_follows: true,
+                sequence_number: 3,
+                proposed_window_size: 4,
+            })
+        );
+        assert_eq!(apdu.service_choice(), Some(12));
+        assert_eq!(apdu.user_data(), [0x3e]);
+        assert_eq!(APDU::complex_ack(7, 12, vec![]).segment(), None);
+    }
+
+    #[test]
+    fn test_decode_invalid() {
+        let err = APDU::decode_slice(&[0x80, 0x01]).unwrap_err();
+        assert!(matches!(
+            err,
+            crate::Error::Service(crate::error::ServiceError::UnsupportedPduType(8))
+        ));
+        // Headers that end early
+        for data in [
+            "", "00", "000501", "0805010a", "10", "2001", "380701", "400a05", "6001",
+        ] {
+            let data = hex::decode(data).unwrap();
+            assert!(matches!(
+                APDU::decode_slice(&data),
+                Err(crate::Error::Encoding(
+                    crate::error::EncodingError::Truncated
+                ))
+            ));
+            assert!(APDUSlice::from_slice(&data).is_err());
+        }
+        assert!(APDUSlice::from_slice(&[0x80, 0x01]).is_err());
+    }
+
     #[test]
     fn test_who_is() {
         let mut data = hex::decode("1008").unwrap();
 
         let apdu = APDU::decode(&mut std::io::Cursor::new(&mut data)).expect("Decode APDU");
 
-        assert_eq!(apdu.apdu_type, 0x01);
-        assert_eq!(apdu.service_choice, 0x08);
+        assert_eq!(apdu.pdu_type(), BACnetPDU::UnconfirmedRequest);
+        assert_eq!(apdu.service_choice(), Some(0x08));
 
         let mut w = BytesMut::new().writer();
         apdu.encode(&mut w).expect("Write APDU to buffer");
@@ -137,11 +1180,11 @@ mod tests {
 
         let apdu = APDU::decode(&mut std::io::Cursor::new(&mut data)).expect("Decode APDU");
 
-        assert_eq!(apdu.apdu_type, 0x01);
-        assert_eq!(apdu.service_choice, 0x00);
+        assert_eq!(apdu.pdu_type(), BACnetPDU::UnconfirmedRequest);
+        assert_eq!(apdu.service_choice(), Some(0x00));
         assert_eq!(
-            apdu.user_data,
-            vec![196, 2, 0, 2, 87, 34, 4, 0, 145, 0, 33, 15]
+            apdu.user_data(),
+            &[196, 2, 0, 2, 87, 34, 4, 0, 145, 0, 33, 15]
         );
 
         let mut w = BytesMut::new().writer();
@@ -0,0 +1,467 @@
+use crate::application::{
+    BACnetError, BACnetValue, ConfirmedServiceChoice, ErrorCode, ObjectType,
+    UnconfirmedServiceChoice,
+};
+use crate::error::EncodingError;
+use crate::{Decode, Encode};
+
+use std::convert::TryFrom;
+use std::iter::FromIterator;
+use std::ops::Index;
+
+/// BIT STRING (20.2.10) of any length
+///
+/// Bits beyond the end read as not set, so a shorter bit string sent by an
+/// older device is read like one with the missing bits cleared.
+///
+/// ```
+/// use bacnet::application::BitString;
+/// use bacnet::{Decode, Encode};
+///
+/// let bits = BitString::decode_slice(&[0x03, 0xA8]).unwrap();
+/// assert_eq!(bits.bit_len(), 5);
+/// assert!(bits[0] && !bits[1] && bits[2]);
+/// assert!(!bits[12]);
+/// assert_eq!(bits.ones().collect::<Vec<_>>(), [0, 2, 4]);
+/// assert_eq!(bits.encode_vec().unwrap(), [0x03, 0xA8]);
+/// ```
+#[derive(Clone, Debug, Default, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct BitString(Vec<bool>);
+
+impl BitString {
+    /// A bit string of the given length with no bit set
+    pub fn new(len: usize) -> Self {
+        Self(vec![false; len])
+    }
+
+    /// Number of bits, apart from the encoded length of [`Encode::len`]
+    pub fn bit_len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Whether a bit is set, `false` beyond the end
+    pub fn get(&self, bit: usize) -> bool {
+        self.0.get(bit).copied().unwrap_or(false)
+    }
+
+    /// Set or clear a bit, setting a bit beyond the end extends the string
+    pub fn set(&mut self, bit: usize, value: bool) {
+        if bit >= self.0.len() {
+            if !value {
+                return;
+            }
+            self.0.resize(bit + 1, false);
+        }
+        self.0[bit] = value;
+    }
+
+    /// The numbers of the bits that are set
+    pub fn ones(&self) -> impl Iterator<Item = usize> + '_ {
+        self.0
+            .iter()
+            .enumerate()
+            .filter(|(_, set)| **set)
+            .map(|(bit, _)| bit)
+    }
+
+    pub fn bits(&self) -> &[bool] {
+        &self.0
+    }
+
+    /// Decode the octets of a bit string, starting with the number of
+    /// unused bits in the last octet
+    pub fn from_octets(data: &[u8]) -> crate::error::Result<Self> {
+        match data.split_first() {
+            Some((unused, bits)) if *unused < 8 && (!bits.is_empty() || *unused == 0) => {
+                let count = bits.len() * 8 - *unused as usize;
+                Ok((0..count)
+                    .map(|i| bits[i / 8] & (0x80 >> (i % 8)) != 0)
+                    .collect())
+            }
+            _ => Err(EncodingError::Invalid("Invalid bit string").into()),
+        }
+    }
+
+    /// The octets of the bit string, starting with the number of unused
+    /// bits in the last octet
+    pub fn to_octets(&self) -> Vec<u8> {
+        let unused = (8 - self.0.len() % 8) % 8;
+        let mut data = Vec::with_capacity(self.len_octets());
+        data.push(unused as u8);
+        data.extend(self.0.chunks(8).map(|chunk| {
+            chunk
+                .iter()
+                .enumerate()
+                .fold(0u8, |byte, (i, bit)| byte | (*bit as u8) << (7 - i))
+        }));
+        data
+    }
+
+    fn len_octets(&self) -> usize {
+        1 + self.0.len().div_ceil(8)
+    }
+}
+
+impl Index<usize> for BitString {
+    type Output = bool;
+
+    fn index(&self, bit: usize) -> &bool {
+        self.0.get(bit).unwrap_or(&false)
+    }
+}
+
+impl FromIterator<bool> for BitString {
+    fn from_iter<I: IntoIterator<Item = bool>>(iter: I) -> Self {
+        Self(iter.into_iter().collect())
+    }
+}
+
+impl From<Vec<bool>> for BitString {
+    fn from(bits: Vec<bool>) -> Self {
+        Self(bits)
+    }
+}
+
+impl From<BitString> for Vec<bool> {
+    fn from(bits: BitString) -> Self {
+        bits.0
+    }
+}
+
+impl From<BitString> for BACnetValue {
+    fn from(bits: BitString) -> Self {
+        BACnetValue::BitString(bits)
+    }
+}
+
+impl TryFrom<BACnetValue> for BitString {
+    type Error = BACnetError;
+
+    fn try_from(value: BACnetValue) -> Result<Self, Self::Error> {
+        match value {
+            BACnetValue::BitString(bits) => Ok(bits),
+            _ => Err(BACnetError::property(ErrorCode::InvalidDataType)),
+        }
+    }
+}
+
+impl Decode for BitString {
+    fn decode<T: std::io::Read + Sized>(reader: &mut T) -> crate::error::Result<Self> {
+        let mut data = Vec::new();
+        reader.read_to_end(&mut data)?;
+        Self::from_octets(&data)
+    }
+}
+
+impl Encode for BitString {
+    fn encode<T: std::io::Write + Sized>(&self, writer: &mut T) -> crate::error::Result<()> {
+        writer.write_all(&self.to_octets())?;
+        Ok(())
+    }
+
+    fn len(&self) -> usize {
+        self.len_octets()
+    }
+}
+
+/// BACnetStatusFlags (Clause 21)
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct StatusFlags {
+    pub in_alarm: bool,
+    pub fault: bool,
+    pub overridden: bool,
+    pub out_of_service: bool,
+}
+
+impl From<[bool; 4]> for StatusFlags {
+    fn from([in_alarm, fault, overridden, out_of_service]: [bool; 4]) -> Self {
+        Self {
+            in_alarm,
+            fault,
+            overridden,
+            out_of_service,
+        }
+    }
+}
+
+impl From<StatusFlags> for [bool; 4] {
+    fn from(flags: StatusFlags) -> Self {
+        [
+            flags.in_alarm,
+            flags.fault,
+            flags.overridden,
+            flags.out_of_service,
+        ]
+    }
+}
+
+/// Flags missing in the bit string are not set
+impl From<&BitString> for StatusFlags {
+    fn from(bits: &BitString) -> Self {
+        [bits[0], bits[1], bits[2], bits[3]].into()
+    }
+}
+
+impl From<StatusFlags> for BitString {
+    fn from(flags: StatusFlags) -> Self {
+        <[bool; 4]>::from(flags).to_vec().into()
+    }
+}
+
+impl From<StatusFlags> for BACnetValue {
+    fn from(flags: StatusFlags) -> Self {
+        BitString::from(flags).into()
+    }
+}
+
+impl TryFrom<BACnetValue> for StatusFlags {
+    type Error = BACnetError;
+
+    fn try_from(value: BACnetValue) -> Result<Self, Self::Error> {
+        Ok(Self::from(&BitString::try_from(value)?))
+    }
+}
+
+/// Bit of a confirmed service in BACnetServicesSupported (Clause 21)
+fn confirmed_service_bit(service: ConfirmedServiceChoice) -> usize {
+    use ConfirmedServiceChoice::*;
+    match service {
+        ReadRange => 35,
+        LifeSafetyOperation => 37,
+        SubscribeCovProperty => 38,
+        GetEventInformation => 39,
+        SubscribeCovPropertyMultiple => 41,
+        ConfirmedCovNotificationMultiple => 42,
+        ConfirmedAuditNotification => 44,
+        AuditLogQuery => 45,
+        // Up to VT-Data (23) the bits are the service choices
+        service => service as usize,
+    }
+}
+
+/// Bit of an unconfirmed service in BACnetServicesSupported (Clause 21)
+fn unconfirmed_service_bit(service: UnconfirmedServiceChoice) -> usize {
+    use UnconfirmedServiceChoice::*;
+    match service {
+        UtcTimeSynchronization => 36,
+        WriteGroup => 40,
+        UnconfirmedCovNotificationMultiple => 43,
+        UnconfirmedAuditNotification => 46,
+        WhoAmI => 47,
+        YouAre => 48,
+        // I-Am (0) to Who-Is (8) follow the confirmed services
+        service => service as usize + 26,
+    }
+}
+
+/// BACnetServicesSupported (Clause 21), the Protocol_Services_Supported of
+/// a device
+///
+/// ```
+/// use bacnet::application::{ConfirmedServiceChoice, ServicesSupported};
+///
+/// let mut services = ServicesSupported::new();
+/// services.insert_confirmed(ConfirmedServiceChoice::ReadRange);
+/// assert!(services.confirmed(ConfirmedServiceChoice::ReadRange));
+/// assert!(services.bits()[35]);
+/// ```
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ServicesSupported(BitString);
+
+impl ServicesSupported {
+    /// Number of bits, up to You-Are (48)
+    pub const LEN: usize = 49;
+
+    /// No service supported
+    pub fn new() -> Self {
+        Self(BitString::new(Self::LEN))
+    }
+
+    pub fn confirmed(&self, service: ConfirmedServiceChoice) -> bool {
+        self.0.get(confirmed_service_bit(service))
+    }
+
+    pub fn unconfirmed(&self, service: UnconfirmedServiceChoice) -> bool {
+        self.0.get(unconfirmed_service_bit(service))
+    }
+
+    pub fn insert_confirmed(&mut self, service: ConfirmedServiceChoice) {
+        self.0.set(confirmed_service_bit(service), true);
+    }
+
+    pub fn insert_unconfirmed(&mut self, service: UnconfirmedServiceChoice) {
+        self.0.set(unconfirmed_service_bit(service), true);
+    }
+
+    pub fn bits(&self) -> &BitString {
+        &self.0
+    }
+}
+
+impl Default for ServicesSupported {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl From<BitString> for ServicesSupported {
+    fn from(bits: BitString) -> Self {
+        Self(bits)
+    }
+}
+
+impl From<ServicesSupported> for BACnetValue {
+    fn from(services: ServicesSupported) -> Self {
+        services.0.into()
+    }
+}
+
+impl TryFrom<BACnetValue> for ServicesSupported {
+    type Error = BACnetError;
+
+    fn try_from(value: BACnetValue) -> Result<Self, Self::Error> {
+        BitString::try_from(value).map(Self)
+    }
+}
+
+/// BACnetObjectTypesSupported (Clause 21), the
+/// Protocol_Object_Types_Supported of a device
+///
+/// Proprietary object types have no bit.
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ObjectTypesSupported(BitString);
+
+impl ObjectTypesSupported {
+    /// Number of bits, up to Color Temperature (64)
+    pub const LEN: usize = ObjectType::ColorTemperature.number() as usize + 1;
+
+    /// No object type supported
+    pub fn new() -> Self {
+        Self(BitString::new(Self::LEN))
+    }
+
+    pub fn contains(&self, object_type: ObjectType) -> bool {
+        match object_type {
+            ObjectType::Proprietary(_) => false,
+            object_type => self.0.get(object_type.number() as usize),
+        }
+    }
+
+    pub fn insert(&mut self, object_type: ObjectType) {
+        if !matches!(object_type, ObjectType::Proprietary(_)) {
+            self.0.set(object_type.number() as usize, true);
+        }
+    }
+
+    /// The standard object types that are supported
+    pub fn object_types(&self) -> impl Iterator<Item = ObjectType> + '_ {
+        self.0
+            .ones()
+            .filter_map(num_traits::FromPrimitive::from_usize)
+            .filter(|object_type| !matches!(object_type, ObjectType::Proprietary(_)))
+    }
+
+    pub fn bits(&self) -> &BitString {
+        &self.0
+    }
+}
+
+impl Default for ObjectTypesSupported {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl From<BitString> for ObjectTypesSupported {
+    fn from(bits: BitString) -> Self {
+        Self(bits)
+    }
+}
+
+impl From<ObjectTypesSupported> for BACnetValue {
+    fn from(object_types: ObjectTypesSupported) -> Self {
+        object_types.0.into()
+    }
+}
+
+impl TryFrom<BACnetValue> for ObjectTypesSupported {
+    type Error = BACnetError;
+
+    fn try_from(value: BACnetValue) -> Result<Self, Self::Error> {
+        BitString::try_from(value).map(Self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bit_string() {
+        let mut bits = BitString::new(3);
+        bits.set(1, true);
+        bits.set(20, false);
+        assert_eq!(bits.bit_len(), 3);
+        bits.set(9, true);
+        assert_eq!(bits.bit_len(), 10);
+        assert_eq!(bits.ones().collect::<Vec<_>>(), [1, 9]);
+        assert_eq!(bits.encode_vec().unwrap(), [0x06, 0x40, 0x40]);
+        assert_eq!(BitString::decode_slice(&[0x06, 0x40, 0x40]).unwrap(), bits);
+
+        assert!(BitString::decode_slice(&[0x00]).unwrap().is_empty());
+        assert!(BitString::decode_slice(&[]).is_err());
+        assert!(BitString::decode_slice(&[0x08, 0xFF]).is_err());
+        assert!(BitString::decode_slice(&[0x01]).is_err());
+    }
+
+    #[test]
+    fn test_status_flags() {
+        let flags = StatusFlags {
+            fault: true,
+            ..Default::default()
+        };
+        let value = BACnetValue::from(flags);
+        assert_eq!(
+            value,
+            BACnetValue::BitString(vec![false, true, false, false].into())
+        );
+        assert_eq!(StatusFlags::try_from(value), Ok(flags));
+        // Short bit strings leave the missing flags unset
+        let short = BACnetValue::BitString(vec![true].into());
+        assert_eq!(
+            StatusFlags::try_from(short).map(<[bool; 4]>::from),
+            Ok([true, false, false, false])
+        );
+        assert!(StatusFlags::try_from(BACnetValue::Null).is_err());
+    }
+
+    #[test]
+    fn test_supported() {
+        let mut services = ServicesSupported::new();
+        services.insert_unconfirmed(UnconfirmedServiceChoice::WhoIs);
+        services.insert_unconfirmed(UnconfirmedServiceChoice::YouAre);
+        assert!(services.unconfirmed(UnconfirmedServiceChoice::WhoIs));
+        assert!(!services.confirmed(ConfirmedServiceChoice::ReadProperty));
+        assert_eq!(services.bits().ones().collect::<Vec<_>>(), [34, 48]);
+        assert_eq!(services.bits().bit_len(), ServicesSupported::LEN);
+
+        let mut object_types = ObjectTypesSupported::new();
+        object_types.insert(ObjectType::Device);
+        object_types.insert(ObjectType::Proprietary(130));
+        assert!(object_types.contains(ObjectType::Device));
+        assert!(!object_types.contains(ObjectType::Proprietary(130)));
+        assert_eq!(
+            object_types.object_types().collect::<Vec<_>>(),
+            [ObjectType::Device]
+        );
+        assert_eq!(object_types.bits().bit_len(), 65);
+    }
+}
@@ -0,0 +1,324 @@
+use crate::error::{EncodingError, Error};
+use crate::{Decode, Encode};
+
+use std::convert::TryFrom;
+use std::fmt;
+
+/// Character set of a CharacterString (20.2.9), the initial octet of its
+/// encoding
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum CharacterSet {
+    #[default]
+    Utf8,
+    /// IBM/Microsoft DBCS with its code page
+    Dbcs(u16),
+    JisX0208,
+    Ucs4,
+    Ucs2,
+    /// ISO 8859-1
+    Latin1,
+}
+
+impl CharacterSet {
+    pub fn number(self) -> u8 {
+        match self {
+            Self::Utf8 => 0,
+            Self::Dbcs(_) => 1,
+            Self::JisX0208 => 2,
+            Self::Ucs4 => 3,
+            Self::Ucs2 => 4,
+            Self::Latin1 => 5,
+        }
+    }
+
+    /// Whether every character of a string can be represented
+    pub fn can_encode(self, s: &str) -> bool {
+        match self {
+            Self::Utf8 | Self::Ucs4 => true,
+            Self::Ucs2 => s.chars().all(|c| (c as u32) <= 0xFFFF),
+            Self::Latin1 => s.chars().all(|c| (c as u32) <= 0xFF),
+            Self::Dbcs(_) | Self::JisX0208 => false,
+        }
+    }
+}
+
+fn unsupported() -> Error {
+    Error::from(EncodingError::Invalid("Unsupported character set"))
+}
+
+fn not_representable() -> Error {
+    Error::from(EncodingError::Invalid("Character not in the character set"))
+}
+
+/// A CharacterString (20.2.9) with the character set it is encoded in
+///
+/// The string is decoded from UTF-8, UCS-2, UCS-4 and ISO 8859-1, which is
+/// what devices use in practice. Strings in DBCS or JIS X 0208 are kept as
+/// their [`undecoded`](Self::undecoded) octets instead. Encoding keeps the
+/// character set, so a string is written back like it was read.
+///
+/// ```
+/// use bacnet::application::{CharacterSet, CharacterString};
+/// use bacnet::{Decode, Encode};
+///
+/// let s = CharacterString::decode_slice(&[0x04, 0x00, 0x42, 0x00, 0xE4]).unwrap();
+/// assert_eq!(s.character_set, CharacterSet::Ucs2);
+/// assert_eq!(s.value, "Bä");
+/// assert_eq!(s.encode_vec().unwrap(), [0x04, 0x00, 0x42, 0x00, 0xE4]);
+///
+/// let latin = CharacterString::with_character_set("Bä", CharacterSet::Latin1);
+/// assert_eq!(latin.encode_vec().unwrap(), [0x05, 0x42, 0xE4]);
+///
+/// let jis = CharacterString::decode_slice(&[0x02, 0x30, 0x21]).unwrap();
+/// assert_eq!(jis.character_set, CharacterSet::JisX0208);
+/// assert_eq!(jis.undecoded.as_deref(), Some(&[0x30, 0x21][..]));
+/// assert_eq!(jis.encode_vec().unwrap(), [0x02, 0x30, 0x21]);
+/// ```
+#[derive(Clone, Debug, Default, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CharacterString {
+    pub character_set: CharacterSet,
+    pub value: String,
+    /// The encoded characters of a string in DBCS or JIS X 0208, without
+    /// the character set and code page, `value` is empty then
+    pub undecoded: Option<Vec<u8>>,
+}
+
+impl CharacterString {
+    /// A string encoded in UTF-8
+    pub fn new(value: impl Into<String>) -> Self {
+        Self::with_character_set(value, CharacterSet::Utf8)
+    }
+
+    pub fn with_character_set(value: impl Into<String>, character_set: CharacterSet) -> Self {
+        Self {
+            character_set,
+            value: value.into(),
+            undecoded: None,
+        }
+    }
+
+    /// Whether the characters are decoded into `value`
+    pub fn is_decoded(&self) -> bool {
+        self.undecoded.is_none()
+    }
+
+    /// The decoded string, an error for strings in a character set that is
+    /// not decoded
+    pub fn into_string(self) -> crate::error::Result<String> {
+        match self.undecoded {
+            None => Ok(self.value),
+            Some(_) => Err(unsupported()),
+        }
+    }
+
+    /// Decode the octets of a character string, starting with the
+    /// character set
+    pub fn from_octets(data: &[u8]) -> crate::error::Result<Self> {
+        let (charset, data) = data
+            .split_first()
+            .ok_or(EncodingError::Invalid("Missing character set"))?;
+        let chars = |width: usize| -> crate::error::Result<String> {
+            if !data.len().is_multiple_of(width) {
+                return Err(EncodingError::InvalidLength("character string").into());
+            }
+            data.chunks(width)
+                .map(|unit| {
+                    let code = unit.iter().fold(0, |code, b| code << 8 | *b as u32);
+                    char::from_u32(code).ok_or_else(not_representable)
+                })
+                .collect()
+        };
+        let (character_set, value) = match charset {
+            0 => (
+                CharacterSet::Utf8,
+                String::from_utf8(data.to_vec())
+                    .map_err(|_| EncodingError::Invalid("Invalid UTF-8 in character string"))?,
+            ),
+            3 => (CharacterSet::Ucs4, chars(4)?),
+            4 => (CharacterSet::Ucs2, chars(2)?),
+            5 => (
+                CharacterSet::Latin1,
+                data.iter().map(|b| *b as char).collect(),
+            ),
+            // Kept as encoded, the code page of DBCS precedes the characters
+            1 => {
+                let (code_page, data) = match data {
+                    [high, low, data @ ..] => (u16::from_be_bytes([*high, *low]), data),
+                    _ => return Err(EncodingError::InvalidLength("character string").into()),
+                };
+                return Ok(Self::undecoded(CharacterSet::Dbcs(code_page), data));
+            }
+            2 => return Ok(Self::undecoded(CharacterSet::JisX0208, data)),
+            _ => return Err(unsupported()),
+        };
+        Ok(Self::with_character_set(value, character_set))
+    }
+
+    fn undecoded(character_set: CharacterSet, data: &[u8]) -> Self {
+        Self {
+            character_set,
+            value: String::new(),
+            undecoded: Some(data.to_vec()),
+        }
+    }
+
+    /// The octets of the string in its character set, starting with the
+    /// character set
+    pub fn to_octets(&self) -> crate::error::Result<Vec<u8>> {
+        let mut data = Vec::with_capacity(self.len());
+        data.push(self.character_set.number());
+        if let CharacterSet::Dbcs(code_page) = self.character_set {
+            data.extend_from_slice(&code_page.to_be_bytes());
+        }
+        if let Some(undecoded) = &self.undecoded {
+            data.extend_from_slice(undecoded);
+            return Ok(data);
+        }
+        match self.character_set {
+            CharacterSet::Utf8 => data.extend_from_slice(self.value.as_bytes()),
+            CharacterSet::Ucs4 => self
+                .value
+                .chars()
+                .for_each(|c| data.extend_from_slice(&(c as u32).to_be_bytes())),
+            CharacterSet::Ucs2 => {
+                for c in self.value.chars() {
+                    let unit = u16::try_from(c as u32).map_err(|_| not_representable())?;
+                    data.extend_from_slice(&unit.to_be_bytes());
+                }
+            }
+            CharacterSet::Latin1 => {
+                for c in self.value.chars() {
+                    data.push(u8::try_from(c as u32).map_err(|_| not_representable())?);
+                }
+            }
+            CharacterSet::Dbcs(_) | CharacterSet::JisX0208 => return Err(unsupported()),
+        }
+        Ok(data)
+    }
+
+    /// Encode in the character set if it can represent the string, in
+    /// UTF-8 otherwise. Strings that are not decoded keep their character
+    /// set.
+    pub fn or_utf8(mut self) -> Self {
+        if self.is_decoded() && !self.character_set.can_encode(&self.value) {
+            self.character_set = CharacterSet::Utf8;
+        }
+        self
+    }
+}
+
+impl Decode for CharacterString {
+    fn decode<T: std::io::Read + Sized>(reader: &mut T) -> crate::error::Result<Self> {
+        let mut data = Vec::new();
+        reader.read_to_end(&mut data)?;
+        Self::from_octets(&data)
+    }
+}
+
+impl Encode for CharacterString {
+    fn encode<T: std::io::Write + Sized>(&self, writer: &mut T) -> crate::error::Result<()> {
+        writer.write_all(&self.to_octets()?)?;
+        Ok(())
+    }
+
+    fn len(&self) -> usize {
+        let chars = || self.value.chars().count();
+        match (self.character_set, &self.undecoded) {
+            (CharacterSet::Dbcs(_), Some(undecoded)) => 3 + undecoded.len(),
+            (_, Some(undecoded)) => 1 + undecoded.len(),
+            (CharacterSet::Utf8, None) => 1 + self.value.len(),
+            (CharacterSet::Ucs4, None) => 1 + 4 * chars(),
+            (CharacterSet::Ucs2, None) => 1 + 2 * chars(),
+            (CharacterSet::Latin1, None) => 1 + chars(),
+            // Strings can't be encoded in them
+            (CharacterSet::Dbcs(_) | CharacterSet::JisX0208, None) => 0,
+        }
+    }
+}
+
+impl From<String> for CharacterString {
+    fn from(value: String) -> Self {
+        Self::new(value)
+    }
+}
+
+impl From<&str> for CharacterString {
+    fn from(value: &str) -> Self {
+        Self::new(value)
+    }
+}
+
+impl From<CharacterString> for String {
+    fn from(s: CharacterString) -> Self {
+        s.value
+    }
+}
+
+impl fmt::Display for CharacterString {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_character_sets() {
+        let value = "Grüße 🌡";
+        for (character_set, len) in [(CharacterSet::Utf8, 13), (CharacterSet::Ucs4, 29)] {
+            let s = CharacterString::with_character_set(value, character_set);
+            let data = s.encode_vec().unwrap();
+            assert_eq!(data.len(), len);
+            assert_eq!(data[0], character_set.number());
+            assert_eq!(CharacterString::decode_slice(&data).unwrap(), s);
+        }
+
+        // Not representable in UCS-2 and ISO 8859-1
+        for character_set in [CharacterSet::Ucs2, CharacterSet::Latin1] {
+            let s = CharacterString::with_character_set(value, character_set);
+            assert!(s.encode_vec().is_err());
+            assert_eq!(s.clone().or_utf8().character_set, CharacterSet::Utf8);
+            let s = CharacterString::with_character_set("Grüße", character_set);
+            assert_eq!(s.clone().or_utf8(), s);
+            assert_eq!(
+                CharacterString::decode_slice(&s.encode_vec().unwrap()).unwrap(),
+                s
+            );
+        }
+    }
+
+    #[test]
+    fn test_undecoded_character_sets() {
+        // DBCS with code page 932, and JIS X 0208
+        for data in [&[0x01, 0x03, 0xA4, 0x93, 0xFA][..], &[0x02, 0x30, 0x21]] {
+            let s = CharacterString::decode_slice(data).unwrap();
+            assert!(!s.is_decoded());
+            assert_eq!(s.clone().or_utf8(), s);
+            assert_eq!(s.len(), data.len());
+            assert_eq!(s.encode_vec().unwrap(), data);
+            assert!(s.into_string().is_err());
+        }
+        let s = CharacterString::decode_slice(&[0x01, 0x03, 0xA4]).unwrap();
+        assert_eq!(s.character_set, CharacterSet::Dbcs(932));
+        assert_eq!(s.undecoded, Some(vec![]));
+
+        let s = CharacterString::with_character_set("A", CharacterSet::JisX0208);
+        assert!(s.encode_vec().is_err());
+        assert_eq!(s.len(), 0);
+    }
+
+    #[test]
+    fn test_invalid_character_string() {
+        assert!(CharacterString::decode_slice(&[]).is_err());
+        assert!(CharacterString::decode_slice(&[0x00, 0xC3]).is_err());
+        assert!(CharacterString::decode_slice(&[0x04, 0x00, 0x42, 0x00]).is_err());
+        // Surrogates are no characters
+        assert!(CharacterString::decode_slice(&[0x04, 0xD8, 0x00]).is_err());
+        assert!(CharacterString::decode_slice(&[0x01, 0x03]).is_err());
+        assert!(CharacterString::decode_slice(&[0x06, 0x41]).is_err());
+    }
+}
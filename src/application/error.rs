@@ -0,0 +1,602 @@
+use crate::application::{BACnetValue, ConfirmedServiceChoice, APDU};
+use crate::encoding::{encode_application, Reader};
+use crate::error::ServiceError;
+use crate::{Decode, Encode};
+
+use num_derive::{FromPrimitive, ToPrimitive};
+use num_traits::FromPrimitive;
+use std::convert::TryFrom;
+
+/// Error Class (Clause 18)
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash, FromPrimitive, ToPrimitive)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ErrorClass {
+    Device = 0,
+    Object = 1,
+    Property = 2,
+    Resources = 3,
+    Security = 4,
+    Services = 5,
+    VT = 6,
+    Communication = 7,
+}
+
+/// Error Code (Clause 18)
+///
+/// Codes from 256 are proprietary and decoded as `other`.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash, FromPrimitive, ToPrimitive)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ErrorCode {
+    Other = 0,
+    AuthenticationFailed = 1,
+    ConfigurationInProgress = 2,
+    DeviceBusy = 3,
+    DynamicCreationNotSupported = 4,
+    FileAccessDenied = 5,
+    IncompatibleSecurityLevels = 6,
+    InconsistentParameters = 7,
+    InconsistentSelectionCriterion = 8,
+    InvalidDataType = 9,
+    InvalidFileAccessMethod = 10,
+    InvalidFileStartPosition = 11,
+    InvalidOperatorName = 12,
+    InvalidParameterDataType = 13,
+    InvalidTimeStamp = 14,
+    KeyGenerationError = 15,
+    MissingRequiredParameter = 16,
+    NoObjectsOfSpecifiedType = 17,
+    NoSpaceForObject = 18,
+    NoSpaceToAddListElement = 19,
+    NoSpaceToWriteProperty = 20,
+    NoVTSessionsAvailable = 21,
+    PropertyIsNotAList = 22,
+    ObjectDeletionNotPermitted = 23,
+    ObjectIdentifierAlreadyExists = 24,
+    OperationalProblem = 25,
+    PasswordFailure = 26,
+    ReadAccessDenied = 27,
+    SecurityNotSupported = 28,
+    ServiceRequestDenied = 29,
+    Timeout = 30,
+    UnknownObject = 31,
+    UnknownProperty = 32,
+    UnknownVTClass = 34,
+    UnknownVTSession = 35,
+    UnsupportedObjectType = 36,
+    ValueOutOfRange = 37,
+    VTSessionAlreadyClosed = 38,
+    VTSessionTerminationFailure = 39,
+    WriteAccessDenied = 40,
+    CharacterSetNotSupported = 41,
+    InvalidArrayIndex = 42,
+    COVSubscriptionFailed = 43,
+    NotCOVProperty = 44,
+    OptionalFunctionalityNotSupported = 45,
+    InvalidConfigurationData = 46,
+    DatatypeNotSupported = 47,
+    DuplicateName = 48,
+    DuplicateObjectId = 49,
+    PropertyIsNotAnArray = 50,
+    AbortBufferOverflow = 51,
+    AbortInvalidAPDUInThisState = 52,
+    AbortPreemptedByHigherPriorityTask = 53,
+    AbortSegmentationNotSupported = 54,
+    AbortProprietary = 55,
+    AbortOther = 56,
+    InvalidTag = 57,
+    NetworkDown = 58,
+    RejectBufferOverflow = 59,
+    RejectInconsistentParameters = 60,
+    RejectInvalidParameterDataType = 61,
+    RejectInvalidTag = 62,
+    RejectMissingRequiredParameter = 63,
+    RejectParameterOutOfRange = 64,
+    RejectTooManyArguments = 65,
+    RejectUndefinedEnumeration = 66,
+    RejectUnrecognizedService = 67,
+    RejectProprietary = 68,
+    RejectOther = 69,
+    UnknownDevice = 70,
+    UnknownRoute = 71,
+    ValueNotInitialized = 72,
+    InvalidEventState = 73,
+    NoAlarmConfigured = 74,
+    LogBufferFull = 75,
+    LoggedValuePurged = 76,
+    NoPropertySpecified = 77,
+    NotConfiguredForTriggeredLogging = 78,
+    UnknownSubscription = 79,
+    ParameterOutOfRange = 80,
+    ListElementNotFound = 81,
+    Busy = 82,
+    CommunicationDisabled = 83,
+    Success = 84,
+    AccessDenied = 85,
+    BadDestinationAddress = 86,
+    BadDestinationDeviceId = 87,
+    BadSignature = 88,
+    BadSourceAddress = 89,
+    BadTimestamp = 90,
+    CannotUseKey = 91,
+    CannotVerifyMessageId = 92,
+    CorrectKeyRevision = 93,
+    DestinationDeviceIdRequired = 94,
+    DuplicateMessage = 95,
+    EncryptionNotConfigured = 96,
+    EncryptionRequired = 97,
+    IncorrectKey = 98,
+    InvalidKeyData = 99,
+    KeyUpdateInProgress = 100,
+    MalformedMessage = 101,
+    NotKeyServer = 102,
+    SecurityNotConfigured = 103,
+    SourceSecurityRequired = 104,
+    TooManyKeys = 105,
+    UnknownAuthenticationType = 106,
+    UnknownKey = 107,
+    UnknownKeyRevision = 108,
+    UnknownSourceMessage = 109,
+    NotRouterToDNET = 110,
+    RouterBusy = 111,
+    UnknownNetworkMessage = 112,
+    MessageTooLong = 113,
+    SecurityError = 114,
+    AddressingError = 115,
+    WriteBDTFailed = 116,
+    ReadBDTFailed = 117,
+    RegisterForeignDeviceFailed = 118,
+    ReadFDTFailed = 119,
+    DeleteFDTEntryFailed = 120,
+    DistributeBroadcastFailed = 121,
+    UnknownFileSize = 122,
+    AbortAPDUTooLong = 123,
+    AbortApplicationExceededReplyTime = 124,
+    AbortOutOfResources = 125,
+    AbortTSMTimeout = 126,
+    AbortWindowSizeOutOfRange = 127,
+    FileFull = 128,
+    InconsistentConfiguration = 129,
+    InconsistentObjectType = 130,
+    InternalError = 131,
+    NotConfigured = 132,
+    OutOfMemory = 133,
+    ValueTooLong = 134,
+    AbortInsufficientSecurity = 135,
+    AbortSecurityError = 136,
+    DuplicateEntry = 137,
+    InvalidValueInThisState = 138,
+    InvalidOperationInThisState = 139,
+    ListItemNotNumbered = 140,
+    ListItemNotTimestamped = 141,
+    InvalidDataEncoding = 142,
+    BVLCFunctionUnknown = 143,
+    BVLCProprietaryFunctionUnknown = 144,
+    HeaderEncodingError = 145,
+    HeaderNotUnderstood = 146,
+    MessageIncomplete = 147,
+    NotABACnetSCHub = 148,
+    PayloadExpected = 149,
+    UnexpectedData = 150,
+    NodeDuplicateVMAC = 151,
+    HTTPUnexpectedResponseCode = 152,
+    HTTPNoUpgrade = 153,
+    HTTPResourceNotLocal = 154,
+    HTTPProxyAuthenticationFailed = 155,
+    HTTPResponseTimeout = 156,
+    HTTPResponseSyntaxError = 157,
+    HTTPResponseValueError = 158,
+    HTTPResponseMissingHeader = 159,
+    HTTPWebSocketHeaderError = 160,
+    HTTPUpgradeRequired = 161,
+    HTTPUpgradeError = 162,
+    HTTPTemporaryUnavailable = 163,
+    HTTPNotAServer = 164,
+    HTTPError = 165,
+    WebSocketSchemeNotSupported = 166,
+    WebSocketUnknownControlMessage = 167,
+    WebSocketCloseError = 168,
+    WebSocketClosedByPeer = 169,
+    WebSocketEndpointLeaves = 170,
+    WebSocketProtocolError = 171,
+    WebSocketDataNotAccepted = 172,
+    WebSocketClosedAbnormally = 173,
+    WebSocketDataInconsistent = 174,
+    WebSocketDataAgainstPolicy = 175,
+    WebSocketFrameTooLong = 176,
+    WebSocketExtensionMissing = 177,
+    WebSocketRequestUnavailable = 178,
+    WebSocketError = 179,
+    TLSClientCertificateError = 180,
+    TLSServerCertificateError = 181,
+    TLSClientAuthenticationFailed = 182,
+    TLSServerAuthenticationFailed = 183,
+    TLSClientCertificateExpired = 184,
+    TLSServerCertificateExpired = 185,
+    TLSClientCertificateRevoked = 186,
+    TLSServerCertificateRevoked = 187,
+    TLSError = 188,
+    DNSUnavailable = 189,
+    DNSNameResolutionFailed = 190,
+    DNSResolverFailure = 191,
+    DNSError = 192,
+    TCPConnectTimeout = 193,
+    TCPConnectionRefused = 194,
+    TCPClosedByLocal = 195,
+    TCPClosedOther = 196,
+    TCPError = 197,
+    IPAddressNotReachable = 198,
+    IPError = 199,
+    CertificateExpired = 200,
+    CertificateInvalid = 201,
+    CertificateMalformed = 202,
+    CertificateRevoked = 203,
+    UnknownSecurityKey = 204,
+    ReferencedPortInError = 205,
+}
+
+/// Error ::= SEQUENCE { error-class, error-code } (Clause 21)
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct BACnetError {
+    pub error_class: ErrorClass,
+    pub error_code: ErrorCode,
+}
+
+impl BACnetError {
+    pub fn new(error_class: ErrorClass, error_code: ErrorCode) -> Self {
+        Self {
+            error_class,
+            error_code,
+        }
+    }
+
+    /// Shorthand for errors of class `object`
+    pub fn object(error_code: ErrorCode) -> Self {
+        Self::new(ErrorClass::Object, error_code)
+    }
+
+    /// Shorthand for errors of class `property`
+    pub fn property(error_code: ErrorCode) -> Self {
+        Self::new(ErrorClass::Property, error_code)
+    }
+
+    fn values(&self) -> [BACnetValue; 2] {
+        [
+            BACnetValue::Enumerated(self.error_class as u32),
+            BACnetValue::Enumerated(self.error_code as u32),
+        ]
+    }
+}
+
+impl std::fmt::Display for BACnetError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?}: {:?}", self.error_class, self.error_code)
+    }
+}
+
+impl std::error::Error for BACnetError {}
+
+impl Decode for BACnetError {
+    /// Decode the parameters of an Error PDU, error codes this crate does not
+    /// know are returned as `other`
+    fn decode<T: std::io::Read + Sized>(reader: &mut T) -> crate::error::Result<Self> {
+        let mut data = Vec::new();
+        reader.read_to_end(&mut data)?;
+        let mut reader = Reader::new(&data);
+        let invalid = || ServiceError::Invalid("Invalid error parameters");
+        match (reader.application_value()?, reader.application_value()?) {
+            (BACnetValue::Enumerated(class), BACnetValue::Enumerated(code)) => Ok(Self::new(
+                ErrorClass::from_u32(class).ok_or_else(invalid)?,
+                ErrorCode::from_u32(code).unwrap_or(ErrorCode::Other),
+            )),
+            _ => Err(invalid().into()),
+        }
+    }
+}
+
+impl Encode for BACnetError {
+    /// Encode the parameters of an Error PDU
+    fn encode<T: std::io::Write + Sized>(&self, writer: &mut T) -> crate::error::Result<()> {
+        let mut data = Vec::new();
+        for v in self.values() {
+            encode_application(&mut data, &v)?;
+        }
+        writer.write_all(&data)?;
+        Ok(())
+    }
+
+    fn len(&self) -> usize {
+        let mut data = Vec::new();
+        self.values()
+            .iter()
+            .try_for_each(|v| encode_application(&mut data, v))
+            .map_or(0, |_| data.len())
+    }
+}
+
+/// BACnet-Error-PDU (20.1.7) with its decoded error
+///
+/// The errors of services with their own error parameters, such as a
+/// ConfirmedPrivateTransfer-Error, are the error in their context tag 0.
+///
+/// ```
+/// use bacnet::application::{ErrorCode, ErrorPDU, APDU};
+/// use std::convert::TryFrom;
+///
+/// let apdu = APDU::error(3, 15, vec![0x91, 0x02, 0x91, 0x28]);
+/// let error = ErrorPDU::try_from(&apdu).unwrap();
+/// assert_eq!(error.error.error_code, ErrorCode::WriteAccessDenied);
+/// assert_eq!(error.to_apdu(), apdu);
+/// ```
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ErrorPDU {
+    pub invoke_id: u8,
+    pub service_choice: u8,
+    pub error: BACnetError,
+}
+
+impl ErrorPDU {
+    pub fn new(invoke_id: u8, service_choice: ConfirmedServiceChoice, error: BACnetError) -> Self {
+        Self {
+            invoke_id,
+            service_choice: service_choice as u8,
+            error,
+        }
+    }
+
+    /// The service that failed, `None` if it is unknown
+    pub fn service(&self) -> Option<ConfirmedServiceChoice> {
+        ConfirmedServiceChoice::from_u8(self.service_choice)
+    }
+
+    /// The Error PDU with the error as parameters
+    pub fn to_apdu(&self) -> APDU {
+        let data = self.error.encode_vec().unwrap_or_default();
+        APDU::error(self.invoke_id, self.service_choice, data)
+    }
+}
+
+impl TryFrom<&APDU> for ErrorPDU {
+    type Error = crate::Error;
+
+    fn try_from(apdu: &APDU) -> Result<Self, Self::Error> {
+        let (invoke_id, service_choice, data) = match apdu {
+            APDU::Error {
+                invoke_id,
+                service_choice,
+                user_data,
+            } => (*invoke_id, *service_choice, user_data),
+            _ => return Err(ServiceError::Invalid("Not an Error PDU").into()),
+        };
+        let mut reader = Reader::new(data);
+        let error = match reader.is_opening_tag(0) {
+            true => {
+                reader.opening_tag(0)?;
+                BACnetError::decode_slice(reader.raw_until_closing_tag(0)?)?
+            }
+            false => BACnetError::decode_slice(data)?,
+        };
+        Ok(Self {
+            invoke_id,
+            service_choice,
+            error,
+        })
+    }
+}
+
+/// BACnetRejectReason (Clause 21)
+///
+/// Reasons from 64 are proprietary.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash, FromPrimitive, ToPrimitive)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum RejectReason {
+    Other = 0,
+    BufferOverflow = 1,
+    InconsistentParameters = 2,
+    InvalidParameterDataType = 3,
+    InvalidTag = 4,
+    MissingRequiredParameter = 5,
+    ParameterOutOfRange = 6,
+    TooManyArguments = 7,
+    UndefinedEnumeration = 8,
+    UnrecognizedService = 9,
+}
+
+/// BACnetAbortReason (Clause 21)
+///
+/// Reasons from 64 are proprietary.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash, FromPrimitive, ToPrimitive)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum AbortReason {
+    Other = 0,
+    BufferOverflow = 1,
+    InvalidApduInThisState = 2,
+    PreemptedByHigherPriorityTask = 3,
+    SegmentationNotSupported = 4,
+    SecurityError = 5,
+    InsufficientSecurity = 6,
+    WindowSizeOutOfRange = 7,
+    ApplicationExceededReplyTime = 8,
+    OutOfResources = 9,
+    TsmTimeout = 10,
+    ApduTooLong = 11,
+}
+
+/// BACnet-Reject-PDU (20.1.8), sent by the server of a transaction
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RejectPDU {
+    pub invoke_id: u8,
+    pub reject_reason: u8,
+}
+
+impl RejectPDU {
+    pub fn new(invoke_id: u8, reason: RejectReason) -> Self {
+        Self {
+            invoke_id,
+            reject_reason: reason as u8,
+        }
+    }
+
+    /// The standard reason, `None` if it is proprietary
+    pub fn reason(&self) -> Option<RejectReason> {
+        RejectReason::from_u8(self.reject_reason)
+    }
+
+    pub fn to_apdu(&self) -> APDU {
+        APDU::reject(self.invoke_id, self.reject_reason)
+    }
+}
+
+impl TryFrom<&APDU> for RejectPDU {
+    type Error = crate::Error;
+
+    fn try_from(apdu: &APDU) -> Result<Self, Self::Error> {
+        match apdu {
+            APDU::Reject { invoke_id, reason } => Ok(Self {
+                invoke_id: *invoke_id,
+                reject_reason: *reason,
+            }),
+            _ => Err(ServiceError::Invalid("Not a Reject PDU").into()),
+        }
+    }
+}
+
+/// BACnet-Abort-PDU (20.1.9)
+///
+/// Either peer of a transaction may abort it, the server flag tells whether
+/// the server or the client did.
+///
+/// ```
+/// use bacnet::application::{AbortPDU, AbortReason, APDU};
+/// use std::convert::TryFrom;
+///
+/// let apdu = APDU::abort(true, 5, 4);
+/// let abort = AbortPDU::try_from(&apdu).unwrap();
+/// assert_eq!(abort.reason(), Some(AbortReason::SegmentationNotSupported));
+/// assert!(abort.server);
+/// ```
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct AbortPDU {
+    pub server: bool,
+    pub invoke_id: u8,
+    pub abort_reason: u8,
+}
+
+impl AbortPDU {
+    pub fn new(server: bool, invoke_id: u8, reason: AbortReason) -> Self {
+        Self {
+            server,
+            invoke_id,
+            abort_reason: reason as u8,
+        }
+    }
+
+    /// The standard reason, `None` if it is proprietary
+    pub fn reason(&self) -> Option<AbortReason> {
+        AbortReason::from_u8(self.abort_reason)
+    }
+
+    pub fn to_apdu(&self) -> APDU {
+        APDU::abort(self.server, self.invoke_id, self.abort_reason)
+    }
+}
+
+impl TryFrom<&APDU> for AbortPDU {
+    type Error = crate::Error;
+
+    fn try_from(apdu: &APDU) -> Result<Self, Self::Error> {
+        match apdu {
+            APDU::Abort {
+                server,
+                invoke_id,
+                reason,
+            } => Ok(Self {
+                server: *server,
+                invoke_id: *invoke_id,
+                abort_reason: *reason,
+            }),
+            _ => Err(ServiceError::Invalid("Not an Abort PDU").into()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decode_error() {
+        assert_eq!(
+            BACnetError::decode_slice(&[0x91, 0x02, 0x91, 0x20]).unwrap(),
+            BACnetError::property(ErrorCode::UnknownProperty)
+        );
+        assert_eq!(
+            BACnetError::decode_slice(&[0x91, 0x02, 0x92, 0x01, 0x00]).unwrap(),
+            BACnetError::property(ErrorCode::Other)
+        );
+        assert!(BACnetError::decode_slice(&[0x91, 0x02]).is_err());
+    }
+
+    #[test]
+    fn test_encode_error() {
+        let error = BACnetError::object(ErrorCode::UnknownObject);
+        let data = error.encode_vec().unwrap();
+        assert_eq!(data, [0x91, 0x01, 0x91, 0x1f]);
+        assert_eq!(BACnetError::decode_slice(&data).unwrap(), error);
+    }
+
+    #[test]
+    fn test_error_pdu() {
+        let apdu = APDU::error(7, 12, vec![0x91, 0x02, 0x91, 0x20]);
+        let error = ErrorPDU::try_from(&apdu).unwrap();
+        assert_eq!(
+            error,
+            ErrorPDU::new(
+                7,
+                ConfirmedServiceChoice::ReadProperty,
+                BACnetError::property(ErrorCode::UnknownProperty)
+            )
+        );
+        assert_eq!(error.service(), Some(ConfirmedServiceChoice::ReadProperty));
+        assert_eq!(error.to_apdu(), apdu);
+
+        // A CreateObject-Error carries the error in context tag 0
+        let apdu = APDU::error(8, 10, hex::decode("0e910391120f1900").unwrap());
+        let error = ErrorPDU::try_from(&apdu).unwrap();
+        assert_eq!(
+            error.error,
+            BACnetError::new(ErrorClass::Resources, ErrorCode::NoSpaceForObject)
+        );
+
+        // Codes of later revisions
+        let apdu = APDU::error(9, 29, vec![0x91, 0x05, 0x91, 0x4f]);
+        let error = ErrorPDU::try_from(&apdu).unwrap();
+        assert_eq!(error.error.error_code, ErrorCode::UnknownSubscription);
+        let apdu = APDU::error(9, 29, vec![0x91, 0x07, 0x91, 0xcd]);
+        let error = ErrorPDU::try_from(&apdu).unwrap();
+        assert_eq!(error.error.error_code, ErrorCode::ReferencedPortInError);
+
+        assert!(ErrorPDU::try_from(&APDU::simple_ack(7, 15)).is_err());
+        assert!(ErrorPDU::try_from(&APDU::error(7, 15, vec![0x91, 0x02])).is_err());
+    }
+
+    #[test]
+    fn test_reject_and_abort_pdu() {
+        let apdu = APDU::reject(4, 9);
+        let reject = RejectPDU::try_from(&apdu).unwrap();
+        assert_eq!(reject, RejectPDU::new(4, RejectReason::UnrecognizedService));
+        assert_eq!(reject.to_apdu(), apdu);
+        let reject = RejectPDU::try_from(&APDU::reject(4, 70)).unwrap();
+        assert_eq!(reject.reason(), None);
+        assert!(RejectPDU::try_from(&APDU::abort(true, 4, 9)).is_err());
+
+        let apdu = APDU::abort(false, 6, 11);
+        let abort = AbortPDU::try_from(&apdu).unwrap();
+        assert_eq!(abort, AbortPDU::new(false, 6, AbortReason::ApduTooLong));
+        assert_eq!(abort.to_apdu(), apdu);
+        assert!(AbortPDU::try_from(&APDU::reject(6, 11)).is_err());
+    }
+}
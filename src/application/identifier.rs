@@ -0,0 +1,283 @@
+use crate::application::property::kebab_case;
+use crate::error::{EncodingError, Error};
+use crate::{Decode, Encode};
+
+use num_traits::{FromPrimitive, ToPrimitive};
+use std::convert::TryFrom;
+use std::fmt;
+
+/// Define the standard object types with their numbers
+macro_rules! object_types {
+    ($($name:ident = $number:literal,)*) => {
+        /// BACnetObjectType (Clause 21)
+        ///
+        /// Types from 128 to 1023 are proprietary, their meaning is defined
+        /// by the vendor of the device. Standard types added by later
+        /// revisions of the standard decode as reserved.
+        #[derive(Copy, Clone, Debug, Eq, PartialEq, Hash, PartialOrd, Ord)]
+        #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+        pub enum ObjectType {
+            $($name,)*
+            /// 65 to 127, reserved for use by ASHRAE
+            Reserved(u16),
+            /// 128 to 1023
+            Proprietary(u16),
+        }
+
+        impl ObjectType {
+            fn standard(number: u16) -> Option<Self> {
+                match number {
+                    $($number => Some(ObjectType::$name),)*
+                    _ => None,
+                }
+            }
+
+            /// The number of the type, as in the 10 bit object type field
+            /// of object identifiers
+            pub const fn number(self) -> u16 {
+                match self {
+                    $(ObjectType::$name => $number,)*
+                    ObjectType::Reserved(number) | ObjectType::Proprietary(number) => number,
+                }
+            }
+        }
+    };
+}
+
+object_types! {
+    AnalogInput = 0,
+    AnalogOutput = 1,
+    AnalogValue = 2,
+    BinaryInput = 3,
+    BinaryOutput = 4,
+    BinaryValue = 5,
+    Calendar = 6,
+    Command = 7,
+    Device = 8,
+    EventEnrollment = 9,
+    File = 10,
+    Group = 11,
+    Loop = 12,
+    MultiStateInput = 13,
+    MultiStateOutput = 14,
+    NotificationClass = 15,
+    Program = 16,
+    Schedule = 17,
+    Averaging = 18,
+    MultiStateValue = 19,
+    TrendLog = 20,
+    LifeSafetyPoint = 21,
+    LifeSafetyZone = 22,
+    Accumulator = 23,
+    PulseConverter = 24,
+    EventLog = 25,
+    GlobalGroup = 26,
+    TrendLogMultiple = 27,
+    LoadControl = 28,
+    StructuredView = 29,
+    AccessDoor = 30,
+    Timer = 31,
+    AccessCredential = 32,
+    AccessPoint = 33,
+    AccessRights = 34,
+    AccessUser = 35,
+    AccessZone = 36,
+    CredentialDataInput = 37,
+    NetworkSecurity = 38,
+    BitstringValue = 39,
+    CharacterstringValue = 40,
+    DatePatternValue = 41,
+    DateValue = 42,
+    DatetimePatternValue = 43,
+    DatetimeValue = 44,
+    IntegerValue = 45,
+    LargeAnalogValue = 46,
+    OctetstringValue = 47,
+    PositiveIntegerValue = 48,
+    TimePatternValue = 49,
+    TimeValue = 50,
+    NotificationForwarder = 51,
+    AlertEnrollment = 52,
+    Channel = 53,
+    LightingOutput = 54,
+    BinaryLightingOutput = 55,
+    NetworkPort = 56,
+    ElevatorGroup = 57,
+    Escalator = 58,
+    Lift = 59,
+    Staging = 60,
+    AuditLog = 61,
+    AuditReporter = 62,
+    Color = 63,
+    ColorTemperature = 64,
+}
+
+impl FromPrimitive for ObjectType {
+    fn from_i64(n: i64) -> Option<Self> {
+        u64::try_from(n).ok().and_then(Self::from_u64)
+    }
+
+    fn from_u64(n: u64) -> Option<Self> {
+        match u16::try_from(n).ok()? {
+            n @ 0..=127 => Self::standard(n).or(Some(ObjectType::Reserved(n))),
+            n @ 128..=1023 => Some(ObjectType::Proprietary(n)),
+            _ => None,
+        }
+    }
+}
+
+impl ToPrimitive for ObjectType {
+    fn to_i64(&self) -> Option<i64> {
+        Some(self.number() as i64)
+    }
+
+    fn to_u64(&self) -> Option<u64> {
+        Some(self.number() as u64)
+    }
+}
+
+/// The name of the type in the standard, e.g. `analog-input`, reserved and
+/// proprietary types by their number
+impl fmt::Display for ObjectType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let ObjectType::Reserved(number) | ObjectType::Proprietary(number) = self {
+            return write!(f, "{}", number);
+        }
+        f.write_str(&kebab_case(&format!("{:?}", self)))
+    }
+}
+
+/// BACnetObjectIdentifier (20.2.14)
+///
+/// Encoded as the four octets packing the object type into the upper 10
+/// bits and the instance into the lower 22 bits, without a tag. Encoding
+/// fails for instances above [`MAX_INSTANCE`](Self::MAX_INSTANCE) and for
+/// reserved or proprietary types with a number outside of their range.
+///
+/// ```
+/// use bacnet::application::{ObjectIdentifier, ObjectType};
+/// use bacnet::{Decode, Encode};
+///
+/// let object = ObjectIdentifier::new(ObjectType::AnalogInput, 5);
+/// assert_eq!(object.to_string(), "analog-input,5");
+/// assert_eq!(object.encode_vec().unwrap(), [0x00, 0x00, 0x00, 0x05]);
+///
+/// let proprietary = ObjectIdentifier::decode_slice(&[0x20, 0x80, 0x00, 0x01]).unwrap();
+/// assert_eq!(proprietary.object_type, ObjectType::Proprietary(130));
+/// assert_eq!(proprietary.to_string(), "130,1");
+/// ```
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ObjectIdentifier {
+    pub object_type: ObjectType,
+    pub instance: u32,
+}
+
+impl ObjectIdentifier {
+    /// Largest instance number that fits the 22 bit instance field
+    pub const MAX_INSTANCE: u32 = 0x3F_FFFF;
+
+    pub fn new(object_type: ObjectType, instance: u32) -> Self {
+        Self {
+            object_type,
+            instance,
+        }
+    }
+}
+
+impl TryFrom<ObjectIdentifier> for u32 {
+    type Error = Error;
+
+    /// Pack an identifier, failing if the type or instance do not fit
+    fn try_from(object: ObjectIdentifier) -> Result<Self, Self::Error> {
+        let number = object.object_type.number();
+        if ObjectType::from_u16(number) != Some(object.object_type) {
+            return Err(EncodingError::Invalid("Object type out of range").into());
+        }
+        if object.instance > ObjectIdentifier::MAX_INSTANCE {
+            return Err(EncodingError::Invalid("Object instance out of range").into());
+        }
+        Ok((number as u32) << 22 | object.instance)
+    }
+}
+
+impl From<u32> for ObjectIdentifier {
+    fn from(id: u32) -> Self {
+        let object_type = ObjectType::from_u32(id >> 22).expect("10 bit object type");
+        Self::new(object_type, id & Self::MAX_INSTANCE)
+    }
+}
+
+impl Decode for ObjectIdentifier {
+    fn decode<T: std::io::Read + Sized>(reader: &mut T) -> crate::error::Result<Self> {
+        let mut data = [0; 4];
+        reader.read_exact(&mut data)?;
+        Ok(Self::from(u32::from_be_bytes(data)))
+    }
+}
+
+impl Encode for ObjectIdentifier {
+    fn encode<T: std::io::Write + Sized>(&self, writer: &mut T) -> crate::error::Result<()> {
+        writer.write_all(&u32::try_from(*self)?.to_be_bytes())?;
+        Ok(())
+    }
+
+    fn len(&self) -> usize {
+        4
+    }
+}
+
+impl fmt::Display for ObjectIdentifier {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{},{}", self.object_type, self.instance)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_object_type() {
+        assert_eq!(ObjectType::from_u32(19), Some(ObjectType::MultiStateValue));
+        assert_eq!(ObjectType::from_u32(65), Some(ObjectType::Reserved(65)));
+        assert_eq!(ObjectType::Reserved(127).to_string(), "127");
+        assert_eq!(
+            ObjectType::from_u32(1023),
+            Some(ObjectType::Proprietary(1023))
+        );
+        assert_eq!(ObjectType::from_u32(1024), None);
+        assert_eq!(ObjectType::NetworkPort.to_u32(), Some(56));
+        assert_eq!(ObjectType::MultiStateValue.to_string(), "multi-state-value");
+        assert_eq!(
+            ObjectType::TrendLogMultiple.to_string(),
+            "trend-log-multiple"
+        );
+        assert!(ObjectType::ColorTemperature < ObjectType::Proprietary(128));
+    }
+
+    #[test]
+    fn test_object_identifier() {
+        let object = ObjectIdentifier::new(ObjectType::Device, ObjectIdentifier::MAX_INSTANCE);
+        assert_eq!(u32::try_from(object).unwrap(), 0x023F_FFFF);
+        assert_eq!(ObjectIdentifier::from(0x023F_FFFF), object);
+        assert_eq!(object.to_string(), "device,4194303");
+
+        // Instances beyond 22 bits and types outside of their range
+        let invalid = [
+            ObjectIdentifier::new(ObjectType::AnalogValue, 0x40_0001),
+            ObjectIdentifier::new(ObjectType::Proprietary(1024), 1),
+            ObjectIdentifier::new(ObjectType::Proprietary(5), 1),
+            ObjectIdentifier::new(ObjectType::Reserved(128), 1),
+        ];
+        for object in invalid {
+            assert!(object.encode_vec().is_err(), "{:?}", object);
+        }
+
+        // Reserved standard object types
+        let reserved = ObjectIdentifier::decode_slice(&[0x10, 0x40, 0x00, 0x02]).unwrap();
+        assert_eq!(reserved, ObjectIdentifier::new(ObjectType::Reserved(65), 2));
+        assert_eq!(reserved.encode_vec().unwrap(), [0x10, 0x40, 0x00, 0x02]);
+        assert!(ObjectIdentifier::decode_slice(&[0x00, 0x00, 0x01]).is_err());
+    }
+}
@@ -0,0 +1,48 @@
+use crate::application::{BACnetValue, ObjectIdentifier, PropertyIdentifier};
+
+/// BACnetDeviceObjectPropertyReference (Clause 21)
+///
+/// Without a device identifier the object is in the local device.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub struct DeviceObjectPropertyReference {
+    pub object_identifier: ObjectIdentifier,
+    pub property_identifier: PropertyIdentifier,
+    pub property_array_index: Option<u32>,
+    pub device_identifier: Option<ObjectIdentifier>,
+}
+
+impl DeviceObjectPropertyReference {
+    pub fn new(
+        object_identifier: ObjectIdentifier,
+        property_identifier: PropertyIdentifier,
+    ) -> Self {
+        Self {
+            object_identifier,
+            property_identifier,
+            property_array_index: None,
+            device_identifier: None,
+        }
+    }
+}
+
+impl From<DeviceObjectPropertyReference> for BACnetValue {
+    fn from(reference: DeviceObjectPropertyReference) -> Self {
+        let mut elements = vec![
+            (
+                0,
+                BACnetValue::ObjectIdentifier(reference.object_identifier),
+            ),
+            (
+                1,
+                BACnetValue::Enumerated(reference.property_identifier.number()),
+            ),
+        ];
+        if let Some(index) = reference.property_array_index {
+            elements.push((2, BACnetValue::Unsigned(index)));
+        }
+        if let Some(device) = reference.device_identifier {
+            elements.push((3, BACnetValue::ObjectIdentifier(device)));
+        }
+        BACnetValue::Constructed(elements)
+    }
+}
@@ -0,0 +1,560 @@
+//! Segmented messages (5.2, 5.3)
+//!
+//! A [`SegmentedSender`] splits a confirmed request or complex ACK that is
+//! too large for the peer into segments and sends them a window at a time,
+//! moving on as the peer acknowledges them with SegmentACKs. A
+//! [`SegmentedReceiver`] reassembles the segments the peer sends,
+//! acknowledging every window. Neither does I/O, the PDUs they return are
+//! sent by the caller, who also times out the acknowledgments and segments.
+//!
+//! ```
+//! use bacnet::application::{SegmentedSender, APDU};
+//!
+//! let ack = APDU::complex_ack(3, 14, vec![0u8; 100]);
+//! let mut sender = SegmentedSender::new(&ack, 50, 4, None).unwrap();
+//! assert_eq!(sender.progress().segments, 3);
+//!
+//! // The first segment is sent alone, the ACK sets the window size
+//! assert_eq!(sender.window().len(), 1);
+//! sender.segment_ack(&APDU::segment_ack(false, false, 3, 0, 4)).unwrap();
+//! assert_eq!(sender.window().len(), 2);
+//! sender.segment_ack(&APDU::segment_ack(false, false, 3, 2, 4)).unwrap();
+//! assert!(sender.is_complete());
+//! ```
+
+use crate::application::{BACnetPDU, Segment, APDU};
+use crate::error::ServiceError;
+
+use bytes::Bytes;
+use std::time::{Duration, Instant};
+
+/// Segments sent before the first SegmentACK, the peer may accept more with
+/// the actual window size of its acknowledgment (5.3)
+pub const DEFAULT_WINDOW_SIZE: u8 = 16;
+
+/// Time to wait for a segment or its acknowledgment, the default of the
+/// APDU_Segment_Timeout of devices (12.11.27)
+pub const DEFAULT_SEGMENT_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Largest window size (20.1.2.9)
+const MAX_WINDOW_SIZE: u8 = 127;
+
+/// Header of a segment of a confirmed request, the longer of the two
+const SEGMENT_HEADER_LEN: usize = 6;
+
+/// Segments acknowledged by the peer of a segmented message
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Progress {
+    pub segments_acknowledged: usize,
+    pub segments: usize,
+}
+
+/// State of sending the segments of a message
+#[derive(Clone, Debug)]
+pub struct SegmentedSender {
+    /// The unsegmented message, the header of every segment
+    apdu: APDU,
+    segments: Vec<Bytes>,
+    proposed_window_size: u8,
+    actual_window_size: u8,
+    /// Segments before this one are acknowledged
+    acknowledged: usize,
+}
+
+impl SegmentedSender {
+    /// Split `apdu` into segments of up to `max_apdu` octets, the largest
+    /// APDU the peer accepts, with at most `max_segments` segments
+    ///
+    /// Fails for PDUs that can't be segmented and messages that need more
+    /// segments than the peer accepts.
+    pub fn new(
+        apdu: &APDU,
+        max_apdu: usize,
+        proposed_window_size: u8,
+        max_segments: Option<usize>,
+    ) -> crate::error::Result<Self> {
+        if !matches!(
+            apdu,
+            APDU::ConfirmedRequest { .. } | APDU::ComplexAck { .. }
+        ) {
+            return Err(ServiceError::Invalid("Only requests and ACKs can be segmented").into());
+        }
+        let size = match max_apdu.checked_sub(SEGMENT_HEADER_LEN) {
+            Some(size) if size > 0 => size,
+            _ => return Err(ServiceError::Invalid("Max APDU length too small").into()),
+        };
+        let data = apdu.clone().into_user_data();
+        let segments: Vec<Bytes> = match data.is_empty() {
+            true => vec![data],
+            false => (0..data.len())
+                .step_by(size)
+                .map(|start| data.slice(start..data.len().min(start + size)))
+                .collect(),
+        };
+        if max_segments.is_some_and(|max| segments.len() > max) {
+            return Err(ServiceError::Invalid("Too many segments for the peer").into());
+        }
+        let proposed_window_size = proposed_window_size.clamp(1, MAX_WINDOW_SIZE);
+        Ok(Self {
+            apdu: apdu.clone(),
+            segments,
+            proposed_window_size,
+            // The first segment is sent alone (5.4.4.1, 5.4.5.3)
+            actual_window_size: 1,
+            acknowledged: 0,
+        })
+    }
+
+    pub fn invoke_id(&self) -> u8 {
+        self.apdu.invoke_id().unwrap_or_default()
+    }
+
+    pub fn progress(&self) -> Progress {
+        Progress {
+            segments_acknowledged: self.acknowledged,
+            segments: self.segments.len(),
+        }
+    }
+
+    /// Whether the peer acknowledged all segments
+    pub fn is_complete(&self) -> bool {
+        self.acknowledged == self.segments.len()
+    }
+
+    /// The segments to send, up to the window size after the last one
+    /// acknowledged
+    ///
+    /// Sending them again after a timeout retransmits the window.
+    pub fn window(&self) -> Vec<APDU> {
+        let end = self
+            .segments
+            .len()
+            .min(self.acknowledged + self.actual_window_size as usize);
+        (self.acknowledged..end).map(|i| self.segment(i)).collect()
+    }
+
+    /// Process a SegmentACK of the peer, the next [`window`](Self::window)
+    /// starts after the segment acknowledged
+    ///
+    /// A negative ACK asks for the segments after the one acknowledged
+    /// again. ACKs of segments outside of the window are duplicates and
+    /// are ignored.
+    pub fn segment_ack(&mut self, ack: &APDU) -> crate::error::Result<Progress> {
+        let (sequence_number, actual_window_size) = match ack {
+            APDU::SegmentAck {
+                invoke_id,
+                sequence_number,
+                actual_window_size,
+                ..
+            } if *invoke_id == self.invoke_id() => (*sequence_number, *actual_window_size),
+            _ => return Err(ServiceError::Invalid("Not a SegmentACK of the message").into()),
+        };
+        let end = self
+            .segments
+            .len()
+            .min(self.acknowledged + self.actual_window_size as usize);
+        // Sequence numbers are the index of the segment modulo 256
+        if let Some(i) = (self.acknowledged..end).find(|&i| i as u8 == sequence_number) {
+            self.acknowledged = i + 1;
+            self.actual_window_size = actual_window_size.clamp(1, MAX_WINDOW_SIZE);
+        }
+        Ok(self.progress())
+    }
+
+    fn segment(&self, i: usize) -> APDU {
+        let segment = Some(Segment {
+            more_follows: i + 1 < self.segments.len(),
+            sequence_number: i as u8,
+            proposed_window_size: self.proposed_window_size,
+        });
+        let user_data = self.segments[i].clone();
+        match self.apdu.clone() {
+            APDU::ConfirmedRequest {
+                segmented_response_accepted,
+                max_segments,
+                max_apdu,
+                invoke_id,
+                service_choice,
+                ..
+            } => APDU::ConfirmedRequest {
+                segmented_response_accepted,
+                max_segments,
+                max_apdu,
+                invoke_id,
+                segment,
+                service_choice,
+                user_data,
+            },
+            APDU::ComplexAck {
+                invoke_id,
+                service_choice,
+                ..
+            } => APDU::ComplexAck {
+                invoke_id,
+                segment,
+                service_choice,
+                user_data,
+            },
+            apdu => apdu,
+        }
+    }
+}
+
+/// State of receiving the segments of a message
+///
+/// Segments are accepted in order, a window at a time. Every window is
+/// acknowledged with a SegmentACK, segments out of order with a negative
+/// one asking for the segments after the last one received. The message is
+/// given up when no segment arrives within four times the segment timeout
+/// (5.4.4.3, 5.4.5.3).
+#[derive(Clone, Debug)]
+pub struct SegmentedReceiver {
+    /// The first segment, the header of the message
+    first: Option<APDU>,
+    data: Vec<u8>,
+    window_size: u8,
+    segment_timeout: Duration,
+    actual_window_size: u8,
+    last_sequence_number: u8,
+    /// The last segment of the previous window
+    acknowledged_sequence_number: u8,
+    last_received: Option<Instant>,
+    complete: bool,
+}
+
+impl SegmentedReceiver {
+    /// Accept windows of up to `window_size` segments, or the smaller window
+    /// proposed by the sender
+    pub fn new(window_size: u8, segment_timeout: Duration) -> Self {
+        Self {
+            first: None,
+            data: Vec::new(),
+            window_size: window_size.clamp(1, MAX_WINDOW_SIZE),
+            segment_timeout,
+            actual_window_size: 1,
+            last_sequence_number: 0,
+            acknowledged_sequence_number: 0,
+            last_received: None,
+            complete: false,
+        }
+    }
+
+    /// Process a segment received at `now`, returning the SegmentACK to
+    /// send
+    ///
+    /// The first segment has to be the segment 0 of a message, the others
+    /// segments of the same message.
+    pub fn receive(&mut self, segment: &APDU, now: Instant) -> crate::error::Result<Option<APDU>> {
+        let header = match segment.segment() {
+            Some(header) => header,
+            None => return Err(ServiceError::Invalid("Not a segment").into()),
+        };
+        let first = match &self.first {
+            Some(first) => first,
+            None if header.sequence_number == 0 => {
+                self.actual_window_size = header.proposed_window_size.clamp(1, self.window_size);
+                self.data.extend_from_slice(segment.user_data());
+                self.first = Some(segment.clone());
+                self.last_received = Some(now);
+                self.complete = !header.more_follows;
+                return Ok(Some(self.ack(false)));
+            }
+            None => return Err(ServiceError::Invalid("Not the first segment").into()),
+        };
+        if first.pdu_type() != segment.pdu_type()
+            || first.invoke_id() != segment.invoke_id()
+            || first.service_choice() != segment.service_choice()
+        {
+            return Err(ServiceError::Invalid("Segment of another message").into());
+        }
+        self.last_received = Some(now);
+        // The acknowledgment of the last window was lost
+        if self.complete {
+            return Ok(Some(self.ack(false)));
+        }
+        if header.sequence_number != self.last_sequence_number.wrapping_add(1) {
+            return Ok(Some(self.ack(true)));
+        }
+        self.data.extend_from_slice(segment.user_data());
+        self.last_sequence_number = header.sequence_number;
+        self.complete = !header.more_follows;
+        let window_end = self
+            .acknowledged_sequence_number
+            .wrapping_add(self.actual_window_size);
+        match self.complete || header.sequence_number == window_end {
+            true => Ok(Some(self.ack(false))),
+            false => Ok(None),
+        }
+    }
+
+    /// Whether the last segment was received
+    pub fn is_complete(&self) -> bool {
+        self.complete
+    }
+
+    /// The time the message is given up at if no segment arrives
+    pub fn deadline(&self) -> Option<Instant> {
+        self.last_received.map(|at| at + self.segment_timeout * 4)
+    }
+
+    pub fn is_timed_out(&self, now: Instant) -> bool {
+        self.deadline().is_some_and(|deadline| now >= deadline)
+    }
+
+    /// The reassembled message, once it is complete
+    pub fn into_apdu(self) -> Option<APDU> {
+        let user_data = Bytes::from(self.data);
+        match (self.complete, self.first?) {
+            (
+                true,
+                APDU::ConfirmedRequest {
+                    segmented_response_accepted,
+                    max_segments,
+                    max_apdu,
+                    invoke_id,
+                    service_choice,
+                    ..
+                },
+            ) => Some(APDU::ConfirmedRequest {
+                segmented_response_accepted,
+                max_segments,
+                max_apdu,
+                invoke_id,
+                segment: None,
+                service_choice,
+                user_data,
+            }),
+            (
+                true,
+                APDU::ComplexAck {
+                    invoke_id,
+                    service_choice,
+                    ..
+                },
+            ) => Some(APDU::complex_ack(invoke_id, service_choice, user_data)),
+            _ => None,
+        }
+    }
+
+    /// Acknowledge the segments up to the last one received in order
+    fn ack(&mut self, negative: bool) -> APDU {
+        self.acknowledged_sequence_number = self.last_sequence_number;
+        let (server, invoke_id) = match &self.first {
+            Some(first) => (
+                first.pdu_type() == BACnetPDU::ConfirmedRequest,
+                first.invoke_id(),
+            ),
+            None => (false, None),
+        };
+        APDU::segment_ack(
+            negative,
+            server,
+            invoke_id.unwrap_or_default(),
+            self.last_sequence_number,
+            self.actual_window_size,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::application::ReadPropertyMultipleAck;
+    use crate::{Decode, Encode};
+
+    /// Receive the segments, checking the SegmentACK sent after each, `""`
+    /// for none
+    fn exchange(receiver: &mut SegmentedReceiver, exchange: &[(&str, &str)], now: Instant) {
+        for (segment, ack) in exchange {
+            let segment = APDU::decode_slice(&hex::decode(segment).unwrap()).unwrap();
+            let sent = receiver.receive(&segment, now).unwrap();
+            let sent = sent.map(|ack| hex::encode(ack.encode_vec().unwrap()));
+            assert_eq!(sent.as_deref().unwrap_or(""), *ack);
+        }
+    }
+
+    #[test]
+    fn test_segments() {
+        let data: Vec<u8> = (0..=255).collect();
+        let ack = APDU::complex_ack(7, 14, data.clone());
+        let mut sender = SegmentedSender::new(&ack, 106, 2, None).unwrap();
+        assert_eq!(sender.progress().segments, 3);
+        assert_eq!(sender.invoke_id(), 7);
+
+        let first = sender.window();
+        assert_eq!(first.len(), 1);
+        assert_eq!(first[0].user_data(), &data[..100]);
+        assert!(first[0].encode_vec().unwrap().len() <= 106);
+        assert!(first[0].segment().unwrap().more_follows);
+
+        // The window size of the peer is taken over
+        let progress = sender
+            .segment_ack(&APDU::segment_ack(false, false, 7, 0, 2))
+            .unwrap();
+        assert_eq!(progress.segments_acknowledged, 1);
+        let window = sender.window();
+        assert_eq!(window.len(), 2);
+        assert_eq!(window[1].user_data(), &data[200..]);
+        assert_eq!(
+            window[1].segment(),
+            Some(Segment {
+                more_follows: false,
+                sequence_number: 2,
+                proposed_window_size: 2,
+            })
+        );
+
+        // A duplicate, then a negative ACK of the second segment
+        sender
+            .segment_ack(&APDU::segment_ack(false, false, 7, 0, 2))
+            .unwrap();
+        assert_eq!(sender.progress().segments_acknowledged, 1);
+        sender
+            .segment_ack(&APDU::segment_ack(true, false, 7, 1, 2))
+            .unwrap();
+        assert_eq!(sender.window().len(), 1);
+        assert_eq!(sender.window()[0].user_data(), &data[200..]);
+        sender
+            .segment_ack(&APDU::segment_ack(false, false, 7, 2, 2))
+            .unwrap();
+        assert!(sender.is_complete());
+        assert!(sender.window().is_empty());
+
+        assert!(sender
+            .segment_ack(&APDU::segment_ack(false, false, 8, 2, 2))
+            .is_err());
+    }
+
+    #[test]
+    fn test_segmented_request() {
+        let request = APDU::confirmed_request(4, 16, vec![1, 2, 3, 4, 5]);
+        let sender = SegmentedSender::new(&request, 9, 1, Some(2)).unwrap();
+        let segment = &sender.window()[0];
+        assert_eq!(
+            segment.encode_vec().unwrap(),
+            [0x0c, 0x05, 0x04, 0, 1, 16, 1, 2, 3]
+        );
+
+        // Too many segments, PDUs without service parameters
+        assert!(SegmentedSender::new(&request, 8, 1, Some(2)).is_err());
+        assert!(SegmentedSender::new(&request, 6, 1, None).is_err());
+        assert!(SegmentedSender::new(&APDU::simple_ack(4, 16), 50, 1, None).is_err());
+    }
+
+    #[test]
+    fn test_sequence_numbers_wrap() {
+        let ack = APDU::complex_ack(1, 14, vec![0; 300]);
+        let mut sender = SegmentedSender::new(&ack, 7, 127, None).unwrap();
+        sender
+            .segment_ack(&APDU::segment_ack(false, false, 1, 0, 127))
+            .unwrap();
+        for _ in 0..2 {
+            let last = sender.window().last().unwrap().segment().unwrap();
+            let ack = APDU::segment_ack(false, false, 1, last.sequence_number, 127);
+            sender.segment_ack(&ack).unwrap();
+        }
+        assert_eq!(sender.progress().segments_acknowledged, 255);
+        assert_eq!(sender.window()[1].segment().unwrap().sequence_number, 0);
+        sender
+            .segment_ack(&APDU::segment_ack(false, false, 1, 43, 127))
+            .unwrap();
+        assert!(sender.is_complete());
+    }
+
+    /// A ReadPropertyMultiple-ACK in three segments, proposing windows of
+    /// two
+    const SEGMENTS: [&str; 3] = [
+        "3c0500020e0c000000011e",
+        "3c0501020e29554e44422800004f",
+        "380502020e296f4e8204004f1f",
+    ];
+
+    #[test]
+    fn test_reassembly() {
+        let now = Instant::now();
+        let mut receiver = SegmentedReceiver::new(DEFAULT_WINDOW_SIZE, DEFAULT_SEGMENT_TIMEOUT);
+        exchange(
+            &mut receiver,
+            &[
+                (SEGMENTS[0], "40050002"),
+                (SEGMENTS[1], ""),
+                (SEGMENTS[2], "40050202"),
+            ],
+            now,
+        );
+        assert!(receiver.is_complete());
+        // The acknowledgment of the last window is repeated
+        exchange(&mut receiver, &[(SEGMENTS[2], "40050202")], now);
+
+        let ack = receiver.into_apdu().unwrap();
+        assert_eq!(ack.segment(), None);
+        assert_eq!(ack.service_choice(), Some(14));
+        let ack = ReadPropertyMultipleAck::decode_slice(ack.user_data()).unwrap();
+        assert_eq!(ack.results().count(), 2);
+    }
+
+    #[test]
+    fn test_reassembly_out_of_order() {
+        let now = Instant::now();
+        let mut receiver = SegmentedReceiver::new(1, DEFAULT_SEGMENT_TIMEOUT);
+        assert!(receiver.clone().into_apdu().is_none());
+        exchange(
+            &mut receiver,
+            &[
+                // The own window is smaller than the one proposed
+                (SEGMENTS[0], "40050001"),
+                (SEGMENTS[2], "42050001"),
+                (SEGMENTS[1], "40050101"),
+                (SEGMENTS[1], "42050101"),
+                (SEGMENTS[2], "40050201"),
+            ],
+            now,
+        );
+        assert!(receiver.into_apdu().is_some());
+
+        // Segments of other messages, starting in the middle
+        let mut receiver = SegmentedReceiver::new(2, DEFAULT_SEGMENT_TIMEOUT);
+        let segment = APDU::decode_slice(&hex::decode(SEGMENTS[1]).unwrap()).unwrap();
+        assert!(receiver.receive(&segment, now).is_err());
+        let segment = APDU::decode_slice(&hex::decode(SEGMENTS[0]).unwrap()).unwrap();
+        receiver.receive(&segment, now).unwrap();
+        let other = APDU::decode_slice(&hex::decode("3c0601020e2955").unwrap()).unwrap();
+        assert!(receiver.receive(&other, now).is_err());
+        assert!(receiver
+            .receive(&APDU::complex_ack(5, 14, vec![]), now)
+            .is_err());
+    }
+
+    #[test]
+    fn test_reassembly_timeout() {
+        let now = Instant::now();
+        let mut receiver = SegmentedReceiver::new(2, Duration::from_secs(1));
+        assert_eq!(receiver.deadline(), None);
+        let segment = APDU::decode_slice(&hex::decode(SEGMENTS[0]).unwrap()).unwrap();
+        receiver.receive(&segment, now).unwrap();
+        assert!(!receiver.is_timed_out(now + Duration::from_secs(3)));
+        assert!(receiver.is_timed_out(now + Duration::from_secs(4)));
+    }
+
+    #[test]
+    fn test_segmented_request_reassembly() {
+        // Segments sent by a client are acknowledged by the server
+        let request = APDU::confirmed_request(9, 16, (0..20).collect::<Vec<u8>>());
+        let mut sender = SegmentedSender::new(&request, 12, 4, None).unwrap();
+        let mut receiver = SegmentedReceiver::new(3, DEFAULT_SEGMENT_TIMEOUT);
+        let now = Instant::now();
+        while !sender.is_complete() {
+            let mut acks: Vec<_> = sender
+                .window()
+                .iter()
+                .filter_map(|segment| receiver.receive(segment, now).unwrap())
+                .collect();
+            let ack = acks.pop().unwrap();
+            assert!(acks.is_empty());
+            assert!(matches!(ack, APDU::SegmentAck { server: true, .. }));
+            sender.segment_ack(&ack).unwrap();
+        }
+        assert_eq!(receiver.into_apdu(), Some(request));
+    }
+}
@@ -1,73 +1,749 @@
+use crate::application::{BACnetValue, ObjectIdentifier};
+use crate::encoding::codec::{unsigned_len, write_unsigned};
+use crate::encoding::{encode_application, Reader};
+use crate::error::{EncodingError, ServiceError};
 use crate::{Decode, Encode};
-use byteorder::{BigEndian, ReadBytesExt, WriteBytesExt};
+use byteorder::ReadBytesExt;
+use std::convert::TryFrom;
+
+use num_derive::{FromPrimitive, ToPrimitive};
+use num_traits::FromPrimitive;
+
+pub mod acknowledge_alarm;
+pub mod atomic_file;
+pub mod cov_notification;
+pub mod device_communication_control;
+pub mod event_notification;
+pub mod get_alarm_summary;
+pub mod get_enrollment_summary;
+pub mod get_event_information;
+pub mod private_transfer;
+pub mod read_property;
+pub mod read_property_multiple;
+pub mod read_range;
+pub mod reinitialize_device;
+pub mod subscribe_cov;
+pub mod text_message;
+pub mod time_synchronization;
+pub mod virtual_terminal;
+pub mod who_has;
+pub mod who_is;
+pub mod write_group;
+pub mod write_property;
+pub use acknowledge_alarm::*;
+pub use atomic_file::*;
+pub use cov_notification::*;
+pub use device_communication_control::*;
+pub use event_notification::*;
+pub use get_alarm_summary::*;
+pub use get_enrollment_summary::*;
+pub use get_event_information::*;
+pub use private_transfer::*;
+pub use read_property::*;
+pub use read_property_multiple::*;
+pub use read_range::*;
+pub use reinitialize_device::*;
+pub use subscribe_cov::*;
+pub use text_message::*;
+pub use time_synchronization::*;
+pub use virtual_terminal::*;
+pub use who_has::*;
+pub use who_is::*;
+pub use write_group::*;
+pub use write_property::*;
 
 #[derive(Clone, Debug, Eq, PartialEq)]
 pub enum Service {}
 
-#[derive(Clone, Debug, Eq, PartialEq)]
+/// BACnetConfirmedServiceChoice (Clause 21)
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash, FromPrimitive, ToPrimitive)]
+pub enum ConfirmedServiceChoice {
+    AcknowledgeAlarm = 0,
+    ConfirmedCovNotification = 1,
+    ConfirmedEventNotification = 2,
+    GetAlarmSummary = 3,
+    GetEnrollmentSummary = 4,
+    SubscribeCov = 5,
+    AtomicReadFile = 6,
+    AtomicWriteFile = 7,
+    AddListElement = 8,
+    RemoveListElement = 9,
+    CreateObject = 10,
+    DeleteObject = 11,
+    ReadProperty = 12,
+    ReadPropertyMultiple = 14,
+    WriteProperty = 15,
+    WritePropertyMultiple = 16,
+    DeviceCommunicationControl = 17,
+    ConfirmedPrivateTransfer = 18,
+    ConfirmedTextMessage = 19,
+    ReinitializeDevice = 20,
+    VtOpen = 21,
+    VtClose = 22,
+    VtData = 23,
+    ReadRange = 26,
+    LifeSafetyOperation = 27,
+    SubscribeCovProperty = 28,
+    GetEventInformation = 29,
+    SubscribeCovPropertyMultiple = 30,
+    ConfirmedCovNotificationMultiple = 31,
+    ConfirmedAuditNotification = 32,
+    AuditLogQuery = 33,
+}
+
+/// BACnetUnconfirmedServiceChoice (Clause 21)
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash, FromPrimitive, ToPrimitive)]
+pub enum UnconfirmedServiceChoice {
+    IAm = 0,
+    IHave = 1,
+    UnconfirmedCovNotification = 2,
+    UnconfirmedEventNotification = 3,
+    UnconfirmedPrivateTransfer = 4,
+    UnconfirmedTextMessage = 5,
+    TimeSynchronization = 6,
+    WhoHas = 7,
+    WhoIs = 8,
+    UtcTimeSynchronization = 9,
+    WriteGroup = 10,
+    UnconfirmedCovNotificationMultiple = 11,
+    UnconfirmedAuditNotification = 12,
+    WhoAmI = 13,
+    YouAre = 14,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum ConfirmedService {
+    AcknowledgeAlarm(AcknowledgeAlarm),                     // = 0;
+    ConfirmedCovNotification(CovNotification),              // = 1;
+    ConfirmedEventNotification(EventNotification),          // = 2;
+    GetAlarmSummary,                                        // = 3;
+    GetEnrollmentSummary(GetEnrollmentSummaryRequest),      // = 4;
+    SubscribeCov(SubscribeCov),                             // = 5;
+    AtomicReadFile(AtomicReadFileRequest),                  // = 6;
+    AtomicWriteFile(AtomicWriteFileRequest),                // = 7;
+    AddListElement,                                         // = 8;
+    RemoveListElement,                                      // = 9;
+    CreateObject,                                           // = 10;
+    DeleteObject,                                           // = 11;
+    ReadProperty(ReadPropertyRequest),                      // = 12;
+    ReadPropertyMultiple(ReadPropertyMultipleRequest),      // = 14;
+    WriteProperty(WritePropertyRequest),                    // = 15;
+    WritePropertyMultiple,                                  // = 16;
+    DeviceCommunicationControl(DeviceCommunicationControl), // = 17;
+    ConfirmedPrivateTransfer(PrivateTransfer),              // = 18;
+    ConfirmedTextMessage(TextMessage),                      // = 19;
+    ReinitializeDevice(ReinitializeDeviceRequest),          // = 20;
+    VtOpen(VtOpenRequest),                                  // = 21;
+    VtClose(VtCloseRequest),                                // = 22;
+    VtData(VtDataRequest),                                  // = 23;
+    ReadRange(ReadRangeRequest),                            // = 26;
+    LifeSafetyOperation,                                    // = 27;
+    SubscribeCovProperty(SubscribeCovProperty),             // = 28;
+    GetEventInformation(GetEventInformationRequest),        // = 29;
+    SubscribeCovPropertyMultiple,                           // = 30;
+    ConfirmedCovNotificationMultiple,                       // = 31;
+    ConfirmedAuditNotification,                             // = 32;
+    AuditLogQuery,                                          // = 33;
+}
+
+impl Decode for ConfirmedService {
+    fn decode<T: std::io::Read + Sized>(reader: &mut T) -> crate::error::Result<Self> {
+        let type_ = reader.read_u8()?;
+
+        match type_ {
+            0x00 => Ok(Self::AcknowledgeAlarm(AcknowledgeAlarm::decode(reader)?)),
+            0x01 => Ok(Self::ConfirmedCovNotification(CovNotification::decode(
+                reader,
+            )?)),
+            0x02 => Ok(Self::ConfirmedEventNotification(EventNotification::decode(
+                reader,
+            )?)),
+            0x03 => Ok(Self::GetAlarmSummary),
+            0x04 => Ok(Self::GetEnrollmentSummary(
+                GetEnrollmentSummaryRequest::decode(reader)?,
+            )),
+            0x05 => Ok(Self::SubscribeCov(SubscribeCov::decode(reader)?)),
+            0x06 => Ok(Self::AtomicReadFile(AtomicReadFileRequest::decode(reader)?)),
+            0x07 => Ok(Self::AtomicWriteFile(AtomicWriteFileRequest::decode(
+                reader,
+            )?)),
+            0x0c => Ok(Self::ReadProperty(ReadPropertyRequest::decode(reader)?)),
+            0x0e => Ok(Self::ReadPropertyMultiple(
+                ReadPropertyMultipleRequest::decode(reader)?,
+            )),
+            0x0f => Ok(Self::WriteProperty(WritePropertyRequest::decode(reader)?)),
+            0x11 => Ok(Self::DeviceCommunicationControl(
+                DeviceCommunicationControl::decode(reader)?,
+            )),
+            0x12 => Ok(Self::ConfirmedPrivateTransfer(PrivateTransfer::decode(
+                reader,
+            )?)),
+            0x13 => Ok(Self::ConfirmedTextMessage(TextMessage::decode(reader)?)),
+            0x14 => Ok(Self::ReinitializeDevice(ReinitializeDeviceRequest::decode(
+                reader,
+            )?)),
+            0x15 => Ok(Self::VtOpen(VtOpenRequest::decode(reader)?)),
+            0x16 => Ok(Self::VtClose(VtCloseRequest::decode(reader)?)),
+            0x17 => Ok(Self::VtData(VtDataRequest::decode(reader)?)),
+            0x1a => Ok(Self::ReadRange(ReadRangeRequest::decode(reader)?)),
+            0x1c => Ok(Self::SubscribeCovProperty(SubscribeCovProperty::decode(
+                reader,
+            )?)),
+            0x1d => Ok(Self::GetEventInformation(
+                GetEventInformationRequest::decode(reader)?,
+            )),
+            _ => Err(ServiceError::UnsupportedService(type_).into()),
+        }
+    }
+}
+
+impl Encode for ConfirmedService {
+    fn encode<T: std::io::Write + Sized>(&self, writer: &mut T) -> crate::error::Result<()> {
+        match self {
+            Self::AcknowledgeAlarm(a) => a.encode(writer),
+            Self::ConfirmedCovNotification(n) => n.encode(writer),
+            Self::ConfirmedEventNotification(n) => n.encode(writer),
+            // GetAlarmSummary has no parameters
+            Self::GetAlarmSummary => Ok(()),
+            Self::GetEnrollmentSummary(g) => g.encode(writer),
+            Self::SubscribeCov(s) => s.encode(writer),
+            Self::AtomicReadFile(a) => a.encode(writer),
+            Self::AtomicWriteFile(a) => a.encode(writer),
+            Self::ReadProperty(r) => r.encode(writer),
+            Self::ReadPropertyMultiple(r) => r.encode(writer),
+            Self::WriteProperty(w) => w.encode(writer),
+            Self::DeviceCommunicationControl(d) => d.encode(writer),
+            Self::ConfirmedPrivateTransfer(p) => p.encode(writer),
+            Self::ConfirmedTextMessage(m) => m.encode(writer),
+            Self::ReinitializeDevice(r) => r.encode(writer),
+            Self::VtOpen(v) => v.encode(writer),
+            Self::VtClose(v) => v.encode(writer),
+            Self::VtData(v) => v.encode(writer),
+            Self::ReadRange(r) => r.encode(writer),
+            Self::SubscribeCovProperty(s) => s.encode(writer),
+            Self::GetEventInformation(g) => g.encode(writer),
+            _ => Err(ServiceError::UnsupportedEncoding(format!("{:?}", self)).into()),
+        }
+    }
+
+    /// Services without an encoding, which fail to encode, have length 0
+    fn len(&self) -> usize {
+        match self {
+            Self::AcknowledgeAlarm(a) => a.len(),
+            Self::ConfirmedCovNotification(n) => n.len(),
+            Self::ConfirmedEventNotification(n) => n.len(),
+            Self::GetEnrollmentSummary(g) => g.len(),
+            Self::SubscribeCov(s) => s.len(),
+            Self::AtomicReadFile(a) => a.len(),
+            Self::AtomicWriteFile(a) => a.len(),
+            Self::ReadProperty(r) => r.len(),
+            Self::ReadPropertyMultiple(r) => r.len(),
+            Self::WriteProperty(w) => w.len(),
+            Self::DeviceCommunicationControl(d) => d.len(),
+            Self::ConfirmedPrivateTransfer(p) => p.len(),
+            Self::ConfirmedTextMessage(m) => m.len(),
+            Self::ReinitializeDevice(r) => r.len(),
+            Self::VtOpen(v) => v.len(),
+            Self::VtClose(v) => v.len(),
+            Self::VtData(v) => v.len(),
+            Self::ReadRange(r) => r.len(),
+            Self::SubscribeCovProperty(s) => s.len(),
+            Self::GetEventInformation(g) => g.len(),
+            _ => 0,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
 pub enum UnconfirmedService {
-    IAm(IAm),                           // = 0;
-    IHave,                              // = 1;
-    UnconfirmedCovNotification,         // = 2;
-    UnconfirmedEventNotification,       // = 3;
-    UnconfirmedPrivateTransfer,         // = 4;
-    UnconfirmedTextMessage,             // = 5;
-    TimeSynchronization,                // = 6;
-    WhoHas,                             // = 7;
-    WhoIs(),                            // = 8;
-    UtcTimeSynchronization,             // = 9;
-    WriteGroup,                         // = 10;
-    UnconfirmedCovNotificationMultiple, // = 11;
+    IAm(IAm),                                        // = 0;
+    IHave(IHave),                                    // = 1;
+    UnconfirmedCovNotification(CovNotification),     // = 2;
+    UnconfirmedEventNotification(EventNotification), // = 3;
+    UnconfirmedPrivateTransfer(PrivateTransfer),     // = 4;
+    UnconfirmedTextMessage(TextMessage),             // = 5;
+    TimeSynchronization(TimeSynchronization),        // = 6;
+    WhoHas(WhoHas),                                  // = 7;
+    WhoIs(WhoIs),                                    // = 8;
+    UtcTimeSynchronization(TimeSynchronization),     // = 9;
+    WriteGroup(WriteGroup),                          // = 10;
+    UnconfirmedCovNotificationMultiple,              // = 11;
 }
 
 impl Decode for UnconfirmedService {
-    fn decode<T: std::io::Read + Sized>(reader: &mut T) -> std::io::Result<Self> {
+    fn decode<T: std::io::Read + Sized>(reader: &mut T) -> crate::error::Result<Self> {
         // TODO: Add checks
         let type_ = reader.read_u8()?;
 
         match type_ {
             0x00 => Ok(Self::IAm(IAm::decode(reader)?)),
-            0x08 => Ok(Self::WhoIs()),
-            t => unimplemented!(),
+            0x01 => Ok(Self::IHave(IHave::decode(reader)?)),
+            0x02 => Ok(Self::UnconfirmedCovNotification(CovNotification::decode(
+                reader,
+            )?)),
+            0x03 => Ok(Self::UnconfirmedEventNotification(
+                EventNotification::decode(reader)?,
+            )),
+            0x04 => Ok(Self::UnconfirmedPrivateTransfer(PrivateTransfer::decode(
+                reader,
+            )?)),
+            0x05 => Ok(Self::UnconfirmedTextMessage(TextMessage::decode(reader)?)),
+            0x06 => Ok(Self::TimeSynchronization(TimeSynchronization::decode(
+                reader,
+            )?)),
+            0x07 => Ok(Self::WhoHas(WhoHas::decode(reader)?)),
+            0x08 => Ok(Self::WhoIs(WhoIs::decode(reader)?)),
+            0x09 => Ok(Self::UtcTimeSynchronization(TimeSynchronization::decode(
+                reader,
+            )?)),
+            0x0a => Ok(Self::WriteGroup(WriteGroup::decode(reader)?)),
+            _ => Err(ServiceError::UnsupportedService(type_).into()),
         }
     }
 }
 
 impl Encode for UnconfirmedService {
-    fn encode<T: std::io::Write + Sized>(&self, writer: &mut T) -> std::io::Result<()> {
+    fn encode<T: std::io::Write + Sized>(&self, writer: &mut T) -> crate::error::Result<()> {
         match self {
             Self::IAm(a) => a.encode(writer),
-            Self::WhoIs() => Ok(()),
-            _ => unimplemented!(),
+            Self::IHave(i) => i.encode(writer),
+            Self::UnconfirmedCovNotification(n) => n.encode(writer),
+            Self::UnconfirmedEventNotification(n) => n.encode(writer),
+            Self::UnconfirmedPrivateTransfer(p) => p.encode(writer),
+            Self::UnconfirmedTextMessage(m) => m.encode(writer),
+            Self::TimeSynchronization(t) | Self::UtcTimeSynchronization(t) => t.encode(writer),
+            Self::WhoHas(w) => w.encode(writer),
+            Self::WhoIs(w) => w.encode(writer),
+            Self::WriteGroup(w) => w.encode(writer),
+            _ => Err(ServiceError::UnsupportedEncoding(format!("{:?}", self)).into()),
         }
     }
 
+    /// Services without an encoding, which fail to encode, have length 0
     fn len(&self) -> usize {
         match self {
             Self::IAm(a) => a.len(),
-            Self::WhoIs() => 0,
-            _ => unimplemented!(),
+            Self::IHave(i) => i.len(),
+            Self::UnconfirmedCovNotification(n) => n.len(),
+            Self::UnconfirmedEventNotification(n) => n.len(),
+            Self::UnconfirmedPrivateTransfer(p) => p.len(),
+            Self::UnconfirmedTextMessage(m) => m.len(),
+            Self::TimeSynchronization(t) | Self::UtcTimeSynchronization(t) => t.len(),
+            Self::WhoHas(w) => w.len(),
+            Self::WhoIs(w) => w.len(),
+            Self::WriteGroup(w) => w.len(),
+            _ => 0,
         }
     }
 }
 
-#[derive(Clone, Debug, Eq, PartialEq)]
-pub struct IAm {}
+/// BACnetSegmentation (Clause 21)
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash, FromPrimitive, ToPrimitive)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Segmentation {
+    SegmentedBoth = 0,
+    SegmentedTransmit = 1,
+    SegmentedReceive = 2,
+    NoSegmentation = 3,
+}
+
+/// I-Am-Request (16.10)
+#[derive(Clone, Debug, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct IAm {
+    pub device_identifier: ObjectIdentifier,
+    pub max_apdu_length_accepted: u32,
+    pub segmentation_supported: Segmentation,
+    pub vendor_id: u16,
+}
 
 impl Decode for IAm {
-    fn decode<T: std::io::Read + Sized>(_reader: &mut T) -> std::io::Result<Self> {
-        Ok(Self {})
+    fn decode<T: std::io::Read + Sized>(reader: &mut T) -> crate::error::Result<Self> {
+        let mut data = Vec::new();
+        reader.read_to_end(&mut data)?;
+        let mut reader = Reader::new(&data);
+        let values = (
+            reader.application_value()?,
+            reader.application_value()?,
+            reader.application_value()?,
+            reader.application_value()?,
+        );
+        let invalid = || ServiceError::Invalid("Invalid I-Am");
+        match values {
+            (
+                BACnetValue::ObjectIdentifier(device_identifier),
+                BACnetValue::Unsigned(max_apdu_length_accepted),
+                BACnetValue::Enumerated(segmentation),
+                BACnetValue::Unsigned(vendor_id),
+            ) => Ok(Self {
+                device_identifier,
+                max_apdu_length_accepted,
+                segmentation_supported: Segmentation::from_u32(segmentation).ok_or_else(invalid)?,
+                vendor_id: u16::try_from(vendor_id)
+                    .map_err(|_| EncodingError::Invalid("Vendor ID out of range"))?,
+            }),
+            _ => Err(invalid().into()),
+        }
     }
 }
 
+/// Encoded without allocating, devices answer Who-Is without a heap, see
+/// [`crate::fixed`]
 impl Encode for IAm {
-    fn encode<T: std::io::Write + Sized>(&self, writer: &mut T) -> std::io::Result<()> {
-        let data = vec![196, 2, 0, 2, 87, 34, 4, 0, 145, 0, 33, 15];
-        writer.write(&data)?;
+    fn encode<T: std::io::Write + Sized>(&self, writer: &mut T) -> crate::error::Result<()> {
+        writer.write_all(&[0xC4])?;
+        writer.write_all(&u32::try_from(self.device_identifier)?.to_be_bytes())?;
+        write_unsigned(writer, 2, self.max_apdu_length_accepted)?;
+        write_unsigned(writer, 9, self.segmentation_supported as u32)?;
+        write_unsigned(writer, 2, self.vendor_id as u32)?;
+        Ok(())
+    }
+
+    fn len(&self) -> usize {
+        5 + 1
+            + unsigned_len(self.max_apdu_length_accepted)
+            + 1
+            + unsigned_len(self.segmentation_supported as u32)
+            + 1
+            + unsigned_len(self.vendor_id as u32)
+    }
+}
+
+/// I-Have-Request (16.9.2), the answer to a Who-Has
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct IHave {
+    pub device_identifier: ObjectIdentifier,
+    pub object_identifier: ObjectIdentifier,
+    pub object_name: String,
+}
+
+impl IHave {
+    fn values(&self) -> [BACnetValue; 3] {
+        [
+            BACnetValue::ObjectIdentifier(self.device_identifier),
+            BACnetValue::ObjectIdentifier(self.object_identifier),
+            BACnetValue::CharacterString(self.object_name.as_str().into()),
+        ]
+    }
+}
+
+impl Decode for IHave {
+    fn decode<T: std::io::Read + Sized>(reader: &mut T) -> crate::error::Result<Self> {
+        let mut data = Vec::new();
+        reader.read_to_end(&mut data)?;
+        let mut reader = Reader::new(&data);
+        let values = (
+            reader.application_value()?,
+            reader.application_value()?,
+            reader.application_value()?,
+        );
+        match values {
+            (
+                BACnetValue::ObjectIdentifier(device_identifier),
+                BACnetValue::ObjectIdentifier(object_identifier),
+                BACnetValue::CharacterString(object_name),
+            ) => Ok(Self {
+                device_identifier,
+                object_identifier,
+                object_name: object_name.into_string()?,
+            }),
+            _ => Err(ServiceError::Invalid("Invalid I-Have").into()),
+        }
+    }
+}
+
+impl Encode for IHave {
+    fn encode<T: std::io::Write + Sized>(&self, writer: &mut T) -> crate::error::Result<()> {
+        let mut data = Vec::new();
+        for v in self.values() {
+            encode_application(&mut data, &v)?;
+        }
+        writer.write_all(&data)?;
         Ok(())
     }
 
     fn len(&self) -> usize {
-        12
+        let mut data = Vec::new();
+        self.values()
+            .iter()
+            .try_for_each(|v| encode_application(&mut data, v))
+            .map_or(0, |_| data.len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::application::{
+        BACnetDate, BACnetDateTime, BACnetTime, ObjectType, PropertyIdentifier,
+    };
+
+    #[test]
+    fn test_i_am() {
+        let data = vec![196, 2, 0, 2, 87, 34, 4, 0, 145, 0, 33, 15];
+        let i_am = IAm::decode_slice(&data).expect("Decode I-Am");
+
+        assert_eq!(
+            i_am,
+            IAm {
+                device_identifier: ObjectIdentifier::new(ObjectType::Device, 599),
+                max_apdu_length_accepted: 1024,
+                segmentation_supported: Segmentation::SegmentedBoth,
+                vendor_id: 15,
+            }
+        );
+        assert_eq!(i_am.len(), data.len());
+        assert_eq!(i_am.encode_vec().unwrap(), data);
+    }
+
+    #[test]
+    fn test_i_am_invalid() {
+        assert!(IAm::decode_slice(&[196, 2, 0, 2, 87, 34, 4, 0]).is_err());
+        assert!(IAm::decode_slice(&[196, 2, 0, 2, 87, 34, 4, 0, 145, 7, 33, 15]).is_err());
+        // Vendor ID above 65535
+        assert!(IAm::decode_slice(&[196, 2, 0, 2, 87, 34, 4, 0, 145, 0, 35, 1, 0, 0]).is_err());
+        assert!(UnconfirmedService::decode_slice(&[0x09, 0x01]).is_err());
+    }
+
+    #[test]
+    fn test_unconfirmed_service_unsupported() {
+        let service = UnconfirmedService::UnconfirmedCovNotificationMultiple;
+        let err = service.encode_vec().unwrap_err();
+        assert!(matches!(
+            err,
+            crate::Error::Service(ServiceError::UnsupportedEncoding(_))
+        ));
+        assert_eq!(service.len(), 0);
+        let who_is = UnconfirmedService::WhoIs(WhoIs::new());
+        assert!(who_is.encode_vec().unwrap().is_empty());
+        let who_is = UnconfirmedService::decode_slice(&[0x08, 0x09, 0x07, 0x19, 0x07]).unwrap();
+        assert_eq!(who_is, UnconfirmedService::WhoIs(WhoIs::device(7)));
+        assert_eq!(who_is.len(), 4);
+    }
+
+    #[test]
+    fn test_confirmed_service() {
+        let data = hex::decode("0c0c000000011955").unwrap();
+        let service = ConfirmedService::decode_slice(&data).unwrap();
+        let object = ObjectIdentifier::new(ObjectType::AnalogInput, 1);
+        assert_eq!(
+            service,
+            ConfirmedService::ReadProperty(ReadPropertyRequest::new(
+                object,
+                PropertyIdentifier::PresentValue
+            ))
+        );
+        assert_eq!(service.len(), data.len() - 1);
+        assert_eq!(service.encode_vec().unwrap(), data[1..]);
+
+        let err = ConfirmedService::decode_slice(&[0x08]).unwrap_err();
+        assert!(matches!(
+            err,
+            crate::Error::Service(ServiceError::UnsupportedService(8))
+        ));
+        let data = hex::decode("0e0c020000081e09081f").unwrap();
+        let service = ConfirmedService::decode_slice(&data).unwrap();
+        assert_eq!(
+            service,
+            ConfirmedService::ReadPropertyMultiple(ReadPropertyMultipleRequest::new(vec![
+                ReadAccessSpecification::all(ObjectIdentifier::new(ObjectType::Device, 8))
+            ]))
+        );
+        assert_eq!(service.encode_vec().unwrap(), data[1..]);
+
+        let service = ConfirmedService::decode_slice(&[0x14, 0x09, 0x02]).unwrap();
+        assert_eq!(
+            service,
+            ConfirmedService::ReinitializeDevice(ReinitializeDeviceRequest::new(
+                ReinitializedState::StartBackup
+            ))
+        );
+        assert_eq!(service.len(), 2);
+
+        let service = ConfirmedService::decode_slice(&[0x15, 0x91, 0x03, 0x21, 0x05]).unwrap();
+        assert_eq!(
+            service,
+            ConfirmedService::VtOpen(VtOpenRequest::new(VtClass::DecVt100, 5))
+        );
+        assert_eq!(service.encode_vec().unwrap(), [0x91, 0x03, 0x21, 0x05]);
+
+        let data = hex::decode("0f0c0080000119553e4441ac00003f4908").unwrap();
+        let service = ConfirmedService::decode_slice(&data).unwrap();
+        assert!(matches!(
+            &service,
+            ConfirmedService::WriteProperty(w) if w.priority == Some(8)
+        ));
+        assert_eq!(service.len(), data.len() - 1);
+        assert_eq!(service.encode_vec().unwrap(), data[1..]);
+
+        let data = hex::decode("1a0c020000081983").unwrap();
+        let service = ConfirmedService::decode_slice(&data).unwrap();
+        assert!(matches!(&service, ConfirmedService::ReadRange(r) if r.range.is_none()));
+        assert_eq!(service.encode_vec().unwrap(), data[1..]);
+
+        let service = ConfirmedService::AddListElement;
+        assert!(service.encode_vec().is_err());
+        assert_eq!(service.len(), 0);
+    }
+
+    #[test]
+    fn test_cov_services() {
+        let data = hex::decode("0509121c0000000a29013900").unwrap();
+        let service = ConfirmedService::decode_slice(&data).unwrap();
+        assert!(matches!(
+            &service,
+            ConfirmedService::SubscribeCov(s) if s.lifetime == Some(0) && !s.is_cancellation()
+        ));
+        assert_eq!(service.encode_vec().unwrap(), data[1..]);
+
+        let data = hex::decode(
+            "020912 1c02000004 2c0000000a 3900 4e 0955 2e4442820000 2f 4f".replace(' ', ""),
+        )
+        .unwrap();
+        let service = UnconfirmedService::decode_slice(&data).unwrap();
+        let notification = match &service {
+            UnconfirmedService::UnconfirmedCovNotification(n) => n.clone(),
+            service => panic!("Unexpected service {:?}", service),
+        };
+        assert_eq!(notification.values[0].value, BACnetValue::Real(65.0));
+        assert_eq!(service.len(), data.len() - 1);
+        assert_eq!(service.encode_vec().unwrap(), data[1..]);
+
+        let mut data = data;
+        data[0] = 0x01;
+        assert_eq!(
+            ConfirmedService::decode_slice(&data).unwrap(),
+            ConfirmedService::ConfirmedCovNotification(notification)
+        );
+    }
+
+    #[test]
+    fn test_event_services() {
+        let data = hex::decode(
+            "030901 1c02000004 2c00000002 3e19103f 4904 5964 6905 8900 a900 b903".replace(' ', ""),
+        )
+        .unwrap();
+        let service = UnconfirmedService::decode_slice(&data).unwrap();
+        let notification = match &service {
+            UnconfirmedService::UnconfirmedEventNotification(n) => n.clone(),
+            service => panic!("Unexpected service {:?}", service),
+        };
+        assert_eq!(notification.to_state, EventState::HighLimit);
+        assert_eq!(service.len(), data.len() - 1);
+        assert_eq!(service.encode_vec().unwrap(), data[1..]);
+
+        let mut data = data;
+        data[0] = 0x02;
+        let service = ConfirmedService::decode_slice(&data).unwrap();
+        assert_eq!(
+            service,
+            ConfirmedService::ConfirmedEventNotification(notification)
+        );
+        assert_eq!(service.encode_vec().unwrap(), data[1..]);
+
+        let service = ConfirmedService::decode_slice(&[0x03]).unwrap();
+        assert_eq!(service, ConfirmedService::GetAlarmSummary);
+        assert!(service.encode_vec().unwrap().is_empty());
+        let data = hex::decode("0409022904").unwrap();
+        let service = ConfirmedService::decode_slice(&data).unwrap();
+        assert_eq!(
+            service,
+            ConfirmedService::GetEnrollmentSummary(
+                GetEnrollmentSummaryRequest::new(AcknowledgmentFilter::NotAcked)
+                    .event_state(EventStateFilter::Active)
+            )
+        );
+        assert_eq!(service.len(), data.len() - 1);
+    }
+
+    #[test]
+    fn test_text_message_services() {
+        let data = hex::decode("050c020000052900 3b004869".replace(' ', "")).unwrap();
+        let service = UnconfirmedService::decode_slice(&data).unwrap();
+        let device = ObjectIdentifier::new(ObjectType::Device, 5);
+        let message = TextMessage::new(device, "Hi");
+        assert_eq!(
+            service,
+            UnconfirmedService::UnconfirmedTextMessage(message.clone())
+        );
+        assert_eq!(service.len(), data.len() - 1);
+        assert_eq!(service.encode_vec().unwrap(), data[1..]);
+
+        let mut data = data;
+        data[0] = 0x13;
+        let service = ConfirmedService::decode_slice(&data).unwrap();
+        assert_eq!(service, ConfirmedService::ConfirmedTextMessage(message));
+        assert_eq!(service.encode_vec().unwrap(), data[1..]);
+    }
+
+    #[test]
+    fn test_time_synchronization_services() {
+        let data = hex::decode("09a479011901b408000000").unwrap();
+        let service = UnconfirmedService::decode_slice(&data).unwrap();
+        let time = BACnetDateTime::new(BACnetDate::new(2021, 1, 25), BACnetTime::new(8, 0, 0, 0));
+        assert_eq!(
+            service,
+            UnconfirmedService::UtcTimeSynchronization(time.into())
+        );
+        assert_eq!(service.len(), data.len() - 1);
+        assert_eq!(service.encode_vec().unwrap(), data[1..]);
+
+        let mut data = data;
+        data[0] = 0x06;
+        let service = UnconfirmedService::decode_slice(&data).unwrap();
+        assert_eq!(
+            service,
+            UnconfirmedService::TimeSynchronization(time.into())
+        );
+    }
+
+    #[test]
+    fn test_private_transfer_services() {
+        let data = hex::decode("0409191908").unwrap();
+        let service = UnconfirmedService::decode_slice(&data).unwrap();
+        let transfer = PrivateTransfer::new(25, 8);
+        assert_eq!(
+            service,
+            UnconfirmedService::UnconfirmedPrivateTransfer(transfer.clone())
+        );
+        assert_eq!(service.encode_vec().unwrap(), data[1..]);
+
+        let mut data = data;
+        data[0] = 0x12;
+        let service = ConfirmedService::decode_slice(&data).unwrap();
+        assert_eq!(
+            service,
+            ConfirmedService::ConfirmedPrivateTransfer(transfer)
+        );
+        assert_eq!(service.len(), data.len() - 1);
+    }
+
+    #[test]
+    fn test_i_have() {
+        let i_have = IHave {
+            device_identifier: ObjectIdentifier::new(ObjectType::Device, 8),
+            object_identifier: ObjectIdentifier::new(ObjectType::AnalogInput, 2),
+            object_name: "OATemp".into(),
+        };
+        let data = i_have.encode_vec().unwrap();
+        assert_eq!(
+            data,
+            hex::decode("c402000008c4000000027507004f4154656d70").unwrap()
+        );
+        assert_eq!(IHave::decode_slice(&data).unwrap(), i_have);
+        assert!(IHave::decode_slice(&data[..10]).is_err());
+
+        let mut frame = vec![0x01];
+        frame.extend_from_slice(&data);
+        let service = UnconfirmedService::decode_slice(&frame).unwrap();
+        assert_eq!(service, UnconfirmedService::IHave(i_have));
+        assert_eq!(service.len(), data.len());
+        let service =
+            UnconfirmedService::decode_slice(&hex::decode("073d07004f4154656d70").unwrap());
+        assert_eq!(
+            service.unwrap(),
+            UnconfirmedService::WhoHas(WhoHas::new("OATemp"))
+        );
     }
 }
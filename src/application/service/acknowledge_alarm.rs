@@ -0,0 +1,95 @@
+use crate::application::{EventState, ObjectIdentifier, TimeStamp};
+use crate::encoding::*;
+use crate::error::{Error, ServiceError};
+use crate::{Decode, Encode};
+
+use num_traits::FromPrimitive;
+
+/// AcknowledgeAlarm-Request (13.5.1)
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct AcknowledgeAlarm {
+    pub acknowledging_process_identifier: u32,
+    pub event_object_identifier: ObjectIdentifier,
+    pub event_state_acknowledged: EventState,
+    /// Time stamp of the transition acknowledged
+    pub time_stamp: TimeStamp,
+    pub acknowledgment_source: String,
+    pub time_of_acknowledgment: TimeStamp,
+}
+
+impl AcknowledgeAlarm {
+    fn encode_data(&self) -> crate::error::Result<Vec<u8>> {
+        let mut data = Vec::new();
+        encode_context_unsigned(&mut data, 0, self.acknowledging_process_identifier);
+        encode_context_object_identifier(&mut data, 1, self.event_object_identifier)?;
+        encode_context_enumerated(&mut data, 2, self.event_state_acknowledged as u32);
+        self.time_stamp.encode_context(&mut data, 3);
+        encode_context_character_string(&mut data, 4, &self.acknowledgment_source);
+        self.time_of_acknowledgment.encode_context(&mut data, 5);
+        Ok(data)
+    }
+}
+
+impl Decode for AcknowledgeAlarm {
+    fn decode<T: std::io::Read + Sized>(reader: &mut T) -> crate::error::Result<Self> {
+        let mut data = Vec::new();
+        reader.read_to_end(&mut data)?;
+        let mut reader = Reader::new(&data);
+        let acknowledging_process_identifier = reader.context_unsigned(0)?;
+        let event_object_identifier = reader.context_object_identifier(1)?;
+        let event_state_acknowledged = EventState::from_u32(reader.context_enumerated(2)?)
+            .ok_or_else(|| Error::from(ServiceError::Invalid("Invalid event state")))?;
+        let time_stamp = TimeStamp::decode_context(&mut reader, 3)?;
+        let acknowledgment_source = reader.context_character_string(4)?;
+        let time_of_acknowledgment = TimeStamp::decode_context(&mut reader, 5)?;
+        Ok(Self {
+            acknowledging_process_identifier,
+            event_object_identifier,
+            event_state_acknowledged,
+            time_stamp,
+            acknowledgment_source,
+            time_of_acknowledgment,
+        })
+    }
+}
+
+impl Encode for AcknowledgeAlarm {
+    fn encode<T: std::io::Write + Sized>(&self, writer: &mut T) -> crate::error::Result<()> {
+        writer.write_all(&self.encode_data()?)?;
+        Ok(())
+    }
+
+    fn len(&self) -> usize {
+        self.encode_data().map_or(0, |data| data.len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::application::{BACnetDate, BACnetDateTime, BACnetTime, ObjectType};
+
+    #[test]
+    fn test_acknowledge_alarm() {
+        let data = hex::decode("09011c0000000229033e19103f4c004d444c5e2ea45c061507b40d0329092f5f")
+            .unwrap();
+        let request = AcknowledgeAlarm::decode_slice(&data).unwrap();
+        assert_eq!(
+            request,
+            AcknowledgeAlarm {
+                acknowledging_process_identifier: 1,
+                event_object_identifier: ObjectIdentifier::new(ObjectType::AnalogInput, 2),
+                event_state_acknowledged: EventState::HighLimit,
+                time_stamp: TimeStamp::SequenceNumber(16),
+                acknowledgment_source: "MDL".into(),
+                time_of_acknowledgment: TimeStamp::DateTime(BACnetDateTime::new(
+                    BACnetDate::new(1992, 6, 21),
+                    BACnetTime::new(13, 3, 41, 9)
+                )),
+            }
+        );
+        assert_eq!(request.encode_vec().unwrap(), data);
+        assert!(AcknowledgeAlarm::decode_slice(&data[..20]).is_err());
+    }
+}
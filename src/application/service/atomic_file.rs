@@ -0,0 +1,409 @@
+use crate::application::{BACnetValue, ObjectIdentifier};
+use crate::encoding::*;
+use crate::error::{Error, ServiceError};
+use crate::{Decode, Encode};
+
+/// accessMethod of AtomicReadFile-Request (15.1.1.1.2), the octets or
+/// records requested
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum FileReadAccess {
+    Stream {
+        file_start_position: i32,
+        requested_octet_count: u32,
+    },
+    Record {
+        file_start_record: i32,
+        requested_record_count: u32,
+    },
+}
+
+/// The octets or records of a file returned by AtomicReadFile (15.1.1.3.1)
+/// or written by AtomicWriteFile (15.2.1.1.2)
+///
+/// A start of -1 appends the data written to the end of the file.
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum FileData {
+    Stream {
+        file_start_position: i32,
+        file_data: Vec<u8>,
+    },
+    Record {
+        file_start_record: i32,
+        file_record_data: Vec<Vec<u8>>,
+    },
+}
+
+/// AtomicReadFile-Request (15.1.1)
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct AtomicReadFileRequest {
+    pub file_identifier: ObjectIdentifier,
+    pub access: FileReadAccess,
+}
+
+/// AtomicReadFile-ACK (15.1.1.3)
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct AtomicReadFileAck {
+    pub end_of_file: bool,
+    pub data: FileData,
+}
+
+/// AtomicWriteFile-Request (15.2.1)
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct AtomicWriteFileRequest {
+    pub file_identifier: ObjectIdentifier,
+    pub data: FileData,
+}
+
+/// AtomicWriteFile-ACK (15.2.1.3), where the data was written
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum AtomicWriteFileAck {
+    FileStartPosition(i32),
+    FileStartRecord(i32),
+}
+
+impl FileData {
+    fn encode_data(&self, data: &mut Vec<u8>) {
+        match self {
+            FileData::Stream {
+                file_start_position,
+                file_data,
+            } => {
+                encode_opening_tag(data, 0);
+                encode_signed(data, *file_start_position);
+                encode_octet_string(data, file_data);
+                encode_closing_tag(data, 0);
+            }
+            FileData::Record {
+                file_start_record,
+                file_record_data,
+            } => {
+                encode_opening_tag(data, 1);
+                encode_signed(data, *file_start_record);
+                let count = file_record_data.len() as u32;
+                encode_unsigned(data, count);
+                for record in file_record_data {
+                    encode_octet_string(data, record);
+                }
+                encode_closing_tag(data, 1);
+            }
+        }
+    }
+
+    fn decode_data(reader: &mut Reader) -> crate::error::Result<Self> {
+        if reader.is_opening_tag(0) {
+            reader.opening_tag(0)?;
+            let file_start_position = reader.application_signed()?;
+            let file_data = reader.application_octet_string()?.to_vec();
+            reader.closing_tag(0)?;
+            return Ok(FileData::Stream {
+                file_start_position,
+                file_data,
+            });
+        }
+        reader.opening_tag(1)?;
+        let file_start_record = reader.application_signed()?;
+        let count = reader.application_unsigned()?;
+        let mut file_record_data = Vec::new();
+        while !reader.is_closing_tag(1) {
+            file_record_data.push(reader.application_octet_string()?.to_vec());
+        }
+        reader.closing_tag(1)?;
+        if file_record_data.len() != count as usize {
+            return Err(Error::from(ServiceError::Invalid(
+                "Record count does not match the records",
+            )));
+        }
+        Ok(FileData::Record {
+            file_start_record,
+            file_record_data,
+        })
+    }
+
+    /// The number of octets or records
+    pub fn len(&self) -> usize {
+        match self {
+            FileData::Stream { file_data, .. } => file_data.len(),
+            FileData::Record {
+                file_record_data, ..
+            } => file_record_data.len(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl AtomicReadFileRequest {
+    fn encode_data(&self) -> crate::error::Result<Vec<u8>> {
+        let mut data = Vec::new();
+        encode_application(
+            &mut data,
+            &BACnetValue::ObjectIdentifier(self.file_identifier),
+        )?;
+        let (tag, start, count) = match self.access {
+            FileReadAccess::Stream {
+                file_start_position,
+                requested_octet_count,
+            } => (0, file_start_position, requested_octet_count),
+            FileReadAccess::Record {
+                file_start_record,
+                requested_record_count,
+            } => (1, file_start_record, requested_record_count),
+        };
+        encode_opening_tag(&mut data, tag);
+        encode_signed(&mut data, start);
+        encode_unsigned(&mut data, count);
+        encode_closing_tag(&mut data, tag);
+        Ok(data)
+    }
+}
+
+impl Decode for AtomicReadFileRequest {
+    fn decode<T: std::io::Read + Sized>(reader: &mut T) -> crate::error::Result<Self> {
+        let mut data = Vec::new();
+        reader.read_to_end(&mut data)?;
+        let mut reader = Reader::new(&data);
+        let file_identifier = reader.application_object_identifier()?;
+        let tag = match reader.is_opening_tag(0) {
+            true => 0,
+            false => 1,
+        };
+        reader.opening_tag(tag)?;
+        let start = reader.application_signed()?;
+        let count = reader.application_unsigned()?;
+        reader.closing_tag(tag)?;
+        let access = match tag {
+            0 => FileReadAccess::Stream {
+                file_start_position: start,
+                requested_octet_count: count,
+            },
+            _ => FileReadAccess::Record {
+                file_start_record: start,
+                requested_record_count: count,
+            },
+        };
+        Ok(Self {
+            file_identifier,
+            access,
+        })
+    }
+}
+
+impl Encode for AtomicReadFileRequest {
+    fn encode<T: std::io::Write + Sized>(&self, writer: &mut T) -> crate::error::Result<()> {
+        writer.write_all(&self.encode_data()?)?;
+        Ok(())
+    }
+
+    fn len(&self) -> usize {
+        self.encode_data().map_or(0, |data| data.len())
+    }
+}
+
+impl AtomicReadFileAck {
+    fn encode_data(&self) -> Vec<u8> {
+        let mut data = Vec::new();
+        encode_boolean(&mut data, self.end_of_file);
+        self.data.encode_data(&mut data);
+        data
+    }
+}
+
+impl Decode for AtomicReadFileAck {
+    fn decode<T: std::io::Read + Sized>(reader: &mut T) -> crate::error::Result<Self> {
+        let mut data = Vec::new();
+        reader.read_to_end(&mut data)?;
+        let mut reader = Reader::new(&data);
+        let end_of_file = reader.application_boolean()?;
+        let data = FileData::decode_data(&mut reader)?;
+        Ok(Self { end_of_file, data })
+    }
+}
+
+impl Encode for AtomicReadFileAck {
+    fn encode<T: std::io::Write + Sized>(&self, writer: &mut T) -> crate::error::Result<()> {
+        writer.write_all(&self.encode_data())?;
+        Ok(())
+    }
+
+    fn len(&self) -> usize {
+        self.encode_data().len()
+    }
+}
+
+impl AtomicWriteFileRequest {
+    fn encode_data(&self) -> crate::error::Result<Vec<u8>> {
+        let mut data = Vec::new();
+        encode_application(
+            &mut data,
+            &BACnetValue::ObjectIdentifier(self.file_identifier),
+        )?;
+        self.data.encode_data(&mut data);
+        Ok(data)
+    }
+}
+
+impl Decode for AtomicWriteFileRequest {
+    fn decode<T: std::io::Read + Sized>(reader: &mut T) -> crate::error::Result<Self> {
+        let mut data = Vec::new();
+        reader.read_to_end(&mut data)?;
+        let mut reader = Reader::new(&data);
+        let file_identifier = reader.application_object_identifier()?;
+        let data = FileData::decode_data(&mut reader)?;
+        Ok(Self {
+            file_identifier,
+            data,
+        })
+    }
+}
+
+impl Encode for AtomicWriteFileRequest {
+    fn encode<T: std::io::Write + Sized>(&self, writer: &mut T) -> crate::error::Result<()> {
+        writer.write_all(&self.encode_data()?)?;
+        Ok(())
+    }
+
+    fn len(&self) -> usize {
+        self.encode_data().map_or(0, |data| data.len())
+    }
+}
+
+impl AtomicWriteFileAck {
+    fn encode_data(&self) -> Vec<u8> {
+        let (tag, start) = match self {
+            AtomicWriteFileAck::FileStartPosition(start) => (0, *start),
+            AtomicWriteFileAck::FileStartRecord(start) => (1, *start),
+        };
+        let mut data = Vec::new();
+        encode_context_signed(&mut data, tag, start);
+        data
+    }
+}
+
+impl Decode for AtomicWriteFileAck {
+    fn decode<T: std::io::Read + Sized>(reader: &mut T) -> crate::error::Result<Self> {
+        let mut data = Vec::new();
+        reader.read_to_end(&mut data)?;
+        let mut reader = Reader::new(&data);
+        match reader.is_context_tag(0) {
+            true => Ok(AtomicWriteFileAck::FileStartPosition(
+                reader.context_signed(0)?,
+            )),
+            false => Ok(AtomicWriteFileAck::FileStartRecord(
+                reader.context_signed(1)?,
+            )),
+        }
+    }
+}
+
+impl Encode for AtomicWriteFileAck {
+    fn encode<T: std::io::Write + Sized>(&self, writer: &mut T) -> crate::error::Result<()> {
+        writer.write_all(&self.encode_data())?;
+        Ok(())
+    }
+
+    fn len(&self) -> usize {
+        self.encode_data().len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::application::ObjectType;
+
+    #[test]
+    fn test_atomic_read_file() {
+        // Example from F.1.1
+        let request = AtomicReadFileRequest {
+            file_identifier: ObjectIdentifier::new(ObjectType::File, 1),
+            access: FileReadAccess::Stream {
+                file_start_position: 0,
+                requested_octet_count: 27,
+            },
+        };
+        let data = hex::decode("c4028000010e3100211b0f").unwrap();
+        assert_eq!(request.encode_vec().unwrap(), data);
+        assert_eq!(AtomicReadFileRequest::decode_slice(&data).unwrap(), request);
+
+        let ack = AtomicReadFileAck {
+            end_of_file: false,
+            data: FileData::Stream {
+                file_start_position: 0,
+                file_data: b"Chiller01 On-Time=4.3 Hours".to_vec(),
+            },
+        };
+        let data =
+            hex::decode("100e3100651b4368696c6c65723031204f6e2d54696d653d342e3320486f7572730f")
+                .unwrap();
+        assert_eq!(ack.encode_vec().unwrap(), data);
+        assert_eq!(AtomicReadFileAck::decode_slice(&data).unwrap(), ack);
+
+        // Example from F.1.1 reading records
+        let request = AtomicReadFileRequest {
+            file_identifier: ObjectIdentifier::new(ObjectType::File, 2),
+            access: FileReadAccess::Record {
+                file_start_record: 14,
+                requested_record_count: 3,
+            },
+        };
+        let data = hex::decode("c4028000021e310e21031f").unwrap();
+        assert_eq!(request.encode_vec().unwrap(), data);
+        assert_eq!(AtomicReadFileRequest::decode_slice(&data).unwrap(), request);
+
+        let ack = AtomicReadFileAck {
+            end_of_file: true,
+            data: FileData::Record {
+                file_start_record: 14,
+                file_record_data: vec![b"12:00,45.6".to_vec(), b"12:15,44.8".to_vec()],
+            },
+        };
+        let data =
+            hex::decode("111e310e2102650a31323a30302c34352e36650a31323a31352c34342e381f").unwrap();
+        assert_eq!(ack.encode_vec().unwrap(), data);
+        assert_eq!(AtomicReadFileAck::decode_slice(&data).unwrap(), ack);
+
+        // The record count must match the records
+        let data = hex::decode("111e310e2103650a31323a30302c34352e361f").unwrap();
+        assert!(AtomicReadFileAck::decode_slice(&data).is_err());
+    }
+
+    #[test]
+    fn test_atomic_write_file() {
+        // Example from F.1.2
+        let request = AtomicWriteFileRequest {
+            file_identifier: ObjectIdentifier::new(ObjectType::File, 1),
+            data: FileData::Stream {
+                file_start_position: 30,
+                file_data: b"Chiller01 On-Time=4.3 Hours".to_vec(),
+            },
+        };
+        let data = hex::decode(
+            "c4028000010e311e651b4368696c6c65723031204f6e2d54696d653d342e3320486f7572730f",
+        )
+        .unwrap();
+        assert_eq!(request.encode_vec().unwrap(), data);
+        assert_eq!(
+            AtomicWriteFileRequest::decode_slice(&data).unwrap(),
+            request
+        );
+
+        let ack = AtomicWriteFileAck::FileStartPosition(30);
+        let data = hex::decode("091e").unwrap();
+        assert_eq!(ack.encode_vec().unwrap(), data);
+        assert_eq!(AtomicWriteFileAck::decode_slice(&data).unwrap(), ack);
+
+        // Appending records
+        let ack = AtomicWriteFileAck::FileStartRecord(-1);
+        let data = hex::decode("19ff").unwrap();
+        assert_eq!(ack.encode_vec().unwrap(), data);
+        assert_eq!(AtomicWriteFileAck::decode_slice(&data).unwrap(), ack);
+    }
+}
@@ -0,0 +1,152 @@
+use crate::application::{BACnetValue, ObjectIdentifier, PropertyIdentifier};
+use crate::encoding::*;
+use crate::{Decode, Encode};
+
+use num_traits::FromPrimitive;
+use tracing::trace;
+
+/// BACnetPropertyValue (Clause 21)
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PropertyValue {
+    pub property_identifier: PropertyIdentifier,
+    pub property_array_index: Option<u32>,
+    pub value: BACnetValue,
+    pub priority: Option<u8>,
+}
+
+impl PropertyValue {
+    pub fn new(property_identifier: PropertyIdentifier, value: BACnetValue) -> Self {
+        Self {
+            property_identifier,
+            property_array_index: None,
+            value,
+            priority: None,
+        }
+    }
+}
+
+/// COV notification parameters (13.14, 13.15)
+///
+/// Confirmed and unconfirmed COV notifications share their parameters.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CovNotification {
+    pub subscriber_process_identifier: u32,
+    pub initiating_device_identifier: ObjectIdentifier,
+    pub monitored_object_identifier: ObjectIdentifier,
+    /// Seconds until the subscription ends, 0 for indefinite subscriptions
+    pub time_remaining: u32,
+    pub values: Vec<PropertyValue>,
+}
+
+impl CovNotification {
+    fn encode_data(&self) -> crate::error::Result<Vec<u8>> {
+        let mut data = Vec::new();
+        encode_context_unsigned(&mut data, 0, self.subscriber_process_identifier);
+        encode_context_object_identifier(&mut data, 1, self.initiating_device_identifier)?;
+        encode_context_object_identifier(&mut data, 2, self.monitored_object_identifier)?;
+        encode_context_unsigned(&mut data, 3, self.time_remaining);
+        encode_opening_tag(&mut data, 4);
+        for value in &self.values {
+            encode_context_enumerated(&mut data, 0, value.property_identifier.number());
+            if let Some(index) = value.property_array_index {
+                encode_context_unsigned(&mut data, 1, index);
+            }
+            encode_opening_tag(&mut data, 2);
+            encode_application(&mut data, &value.value)?;
+            encode_closing_tag(&mut data, 2);
+            if let Some(priority) = value.priority {
+                encode_context_unsigned(&mut data, 3, priority as u32);
+            }
+        }
+        encode_closing_tag(&mut data, 4);
+        Ok(data)
+    }
+}
+
+impl Decode for CovNotification {
+    /// Values of properties unknown to this crate are skipped
+    fn decode<T: std::io::Read + Sized>(reader: &mut T) -> crate::error::Result<Self> {
+        let mut data = Vec::new();
+        reader.read_to_end(&mut data)?;
+        let mut reader = Reader::new(&data);
+        let subscriber_process_identifier = reader.context_unsigned(0)?;
+        let initiating_device_identifier = reader.context_object_identifier(1)?;
+        let monitored_object_identifier = reader.context_object_identifier(2)?;
+        let time_remaining = reader.context_unsigned(3)?;
+
+        let mut values = Vec::new();
+        reader.opening_tag(4)?;
+        while !reader.is_closing_tag(4) {
+            let property = reader.context_enumerated(0)?;
+            let property_array_index = reader.optional_context_unsigned(1)?;
+            reader.opening_tag(2)?;
+            let mut value = reader.values_until_closing_tag(2)?;
+            let priority = reader.optional_context_unsigned(3)?;
+            let value = match value.len() {
+                1 => value.remove(0),
+                _ => BACnetValue::Array(value),
+            };
+            match PropertyIdentifier::from_u32(property) {
+                Some(property_identifier) => values.push(PropertyValue {
+                    property_identifier,
+                    property_array_index,
+                    value,
+                    priority: priority.map(|p| p as u8),
+                }),
+                None => trace!("Skipping value of unknown property {}", property),
+            }
+        }
+        reader.closing_tag(4)?;
+
+        Ok(Self {
+            subscriber_process_identifier,
+            initiating_device_identifier,
+            monitored_object_identifier,
+            time_remaining,
+            values,
+        })
+    }
+}
+
+impl Encode for CovNotification {
+    fn encode<T: std::io::Write + Sized>(&self, writer: &mut T) -> crate::error::Result<()> {
+        writer.write_all(&self.encode_data()?)?;
+        Ok(())
+    }
+
+    fn len(&self) -> usize {
+        self.encode_data().map_or(0, |data| data.len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::application::ObjectType;
+
+    #[test]
+    fn test_cov_notification() {
+        let data = hex::decode("09121c020000042c0000000a39004e09552e44428200002f096f2e8204002f4f")
+            .unwrap();
+        let notification = CovNotification::decode_slice(&data).unwrap();
+        assert_eq!(
+            notification,
+            CovNotification {
+                subscriber_process_identifier: 18,
+                initiating_device_identifier: ObjectIdentifier::new(ObjectType::Device, 4),
+                monitored_object_identifier: ObjectIdentifier::new(ObjectType::AnalogInput, 10),
+                time_remaining: 0,
+                values: vec![
+                    PropertyValue::new(PropertyIdentifier::PresentValue, BACnetValue::Real(65.0)),
+                    PropertyValue::new(
+                        PropertyIdentifier::StatusFlags,
+                        BACnetValue::BitString(vec![false; 4].into())
+                    ),
+                ],
+            }
+        );
+        assert_eq!(notification.encode_vec().unwrap(), data);
+    }
+}
@@ -0,0 +1,103 @@
+use crate::encoding::*;
+use crate::error::{Error, ServiceError};
+use crate::{Decode, Encode};
+
+use num_derive::{FromPrimitive, ToPrimitive};
+use num_traits::FromPrimitive;
+
+/// enable-disable parameter of DeviceCommunicationControl (16.1.1.1.2)
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash, FromPrimitive, ToPrimitive)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum EnableDisable {
+    Enable = 0,
+    /// Stop responding to and initiating any services but
+    /// DeviceCommunicationControl and ReinitializeDevice
+    Disable = 1,
+    /// Stop initiating services, I-Am is still sent in response to Who-Is
+    DisableInitiation = 2,
+}
+
+/// DeviceCommunicationControl-Request (16.1.1)
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DeviceCommunicationControl {
+    /// Minutes until communication is enabled again, indefinitely without
+    pub time_duration: Option<u16>,
+    pub enable_disable: EnableDisable,
+    pub password: Option<String>,
+}
+
+impl DeviceCommunicationControl {
+    fn encode_data(&self) -> Vec<u8> {
+        let mut data = Vec::new();
+        if let Some(duration) = self.time_duration {
+            encode_context_unsigned(&mut data, 0, duration as u32);
+        }
+        encode_context_enumerated(&mut data, 1, self.enable_disable as u32);
+        if let Some(password) = &self.password {
+            encode_context_character_string(&mut data, 2, password);
+        }
+        data
+    }
+}
+
+impl Decode for DeviceCommunicationControl {
+    fn decode<T: std::io::Read + Sized>(reader: &mut T) -> crate::error::Result<Self> {
+        let mut data = Vec::new();
+        reader.read_to_end(&mut data)?;
+        let mut reader = Reader::new(&data);
+        let invalid = |msg| Error::from(ServiceError::Invalid(msg));
+        let time_duration = match reader.optional_context_unsigned(0)? {
+            Some(d) if d <= u16::MAX as u32 => Some(d as u16),
+            Some(_) => return Err(invalid("Invalid time duration")),
+            None => None,
+        };
+        let enable_disable = EnableDisable::from_u32(reader.context_enumerated(1)?)
+            .ok_or_else(|| invalid("Invalid enable-disable"))?;
+        let password = match reader.is_context_tag(2) {
+            true => Some(reader.context_character_string(2)?),
+            false => None,
+        };
+        Ok(Self {
+            time_duration,
+            enable_disable,
+            password,
+        })
+    }
+}
+
+impl Encode for DeviceCommunicationControl {
+    fn encode<T: std::io::Write + Sized>(&self, writer: &mut T) -> crate::error::Result<()> {
+        writer.write_all(&self.encode_data())?;
+        Ok(())
+    }
+
+    fn len(&self) -> usize {
+        self.encode_data().len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_device_communication_control() {
+        let data = hex::decode("090519012d080023656762646621").unwrap();
+        let request = DeviceCommunicationControl::decode_slice(&data).unwrap();
+        assert_eq!(
+            request,
+            DeviceCommunicationControl {
+                time_duration: Some(5),
+                enable_disable: EnableDisable::Disable,
+                password: Some("#egbdf!".into()),
+            }
+        );
+        assert_eq!(request.encode_vec().unwrap(), data);
+
+        let enable = DeviceCommunicationControl::decode_slice(&[0x19, 0x00]).unwrap();
+        assert_eq!(enable.enable_disable, EnableDisable::Enable);
+        assert!(enable.time_duration.is_none() && enable.password.is_none());
+        assert!(DeviceCommunicationControl::decode_slice(&[0x19, 0x03]).is_err());
+    }
+}
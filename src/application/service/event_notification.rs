@@ -0,0 +1,643 @@
+use crate::application::{BACnetValue, ObjectIdentifier, StatusFlags, TimeStamp};
+use crate::encoding::*;
+use crate::error::{Error, ServiceError};
+use crate::{Decode, Encode};
+
+use num_derive::{FromPrimitive, ToPrimitive};
+use num_traits::FromPrimitive;
+
+/// BACnetEventState (Clause 21)
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash, FromPrimitive, ToPrimitive)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum EventState {
+    Normal = 0,
+    Fault = 1,
+    Offnormal = 2,
+    HighLimit = 3,
+    LowLimit = 4,
+    LifeSafetyAlarm = 5,
+}
+
+/// BACnetEventType (Clause 21)
+///
+/// Event types from 64 on are proprietary.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash, FromPrimitive, ToPrimitive)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum EventType {
+    ChangeOfBitstring = 0,
+    ChangeOfState = 1,
+    ChangeOfValue = 2,
+    CommandFailure = 3,
+    FloatingLimit = 4,
+    OutOfRange = 5,
+    ChangeOfLifeSafety = 8,
+    Extended = 9,
+    BufferReady = 10,
+    UnsignedRange = 11,
+    AccessEvent = 13,
+    DoubleOutOfRange = 14,
+    SignedOutOfRange = 15,
+    UnsignedOutOfRange = 16,
+    ChangeOfCharacterstring = 17,
+    ChangeOfStatusFlags = 18,
+    ChangeOfReliability = 19,
+    None = 20,
+    ChangeOfDiscreteValue = 21,
+    ChangeOfTimer = 22,
+}
+
+/// BACnetNotifyType (Clause 21)
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash, FromPrimitive, ToPrimitive)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum NotifyType {
+    Alarm = 0,
+    Event = 1,
+    AckNotification = 2,
+}
+
+fn invalid() -> Error {
+    Error::from(ServiceError::Invalid("Invalid event notification"))
+}
+
+/// BACnetPropertyStates (Clause 21), the choice and its value
+///
+/// Boolean choices have the value 0 or 1, enumerated and unsigned ones
+/// their number.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PropertyState {
+    pub choice: u8,
+    pub value: u32,
+}
+
+/// New value of a CHANGE_OF_VALUE event (13.3.2)
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ChangedValue {
+    ChangedBits(Vec<bool>),
+    ChangedValue(f32),
+}
+
+/// BACnetNotificationParameters (Clause 21), the event values of an event
+/// notification
+///
+/// The choices are numbered like the [`EventType`] they report, choices
+/// without a variant are kept as [`Other`](Self::Other).
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum NotificationParameters {
+    ChangeOfBitstring {
+        referenced_bitstring: Vec<bool>,
+        status_flags: [bool; 4],
+    },
+    ChangeOfState {
+        new_state: PropertyState,
+        status_flags: [bool; 4],
+    },
+    ChangeOfValue {
+        new_value: ChangedValue,
+        status_flags: [bool; 4],
+    },
+    CommandFailure {
+        command_value: BACnetValue,
+        status_flags: [bool; 4],
+        feedback_value: BACnetValue,
+    },
+    FloatingLimit {
+        reference_value: f32,
+        status_flags: [bool; 4],
+        setpoint_value: f32,
+        error_limit: f32,
+    },
+    OutOfRange {
+        exceeding_value: f32,
+        status_flags: [bool; 4],
+        deadband: f32,
+        exceeded_limit: f32,
+    },
+    ChangeOfLifeSafety {
+        new_state: u32,
+        new_mode: u32,
+        status_flags: [bool; 4],
+        operation_expected: u32,
+    },
+    UnsignedRange {
+        exceeding_value: u32,
+        status_flags: [bool; 4],
+        exceeded_limit: u32,
+    },
+    /// Any other choice, as returned by
+    /// [`Reader::values_until_closing_tag`]
+    Other(BACnetValue),
+}
+
+fn encode_status_flags(buf: &mut Vec<u8>, tag_number: u8, flags: &[bool; 4]) {
+    encode_context_bit_string(buf, tag_number, flags);
+}
+
+/// Encode an ABSTRACT-SYNTAX value enclosed in the context tag
+fn encode_abstract(
+    buf: &mut Vec<u8>,
+    tag_number: u8,
+    value: &BACnetValue,
+) -> crate::error::Result<()> {
+    encode_opening_tag(buf, tag_number);
+    encode_application(buf, value)?;
+    encode_closing_tag(buf, tag_number);
+    Ok(())
+}
+
+/// Read BACnetStatusFlags, flags missing in the bit string are false
+fn decode_status_flags(reader: &mut Reader, tag_number: u8) -> crate::error::Result<[bool; 4]> {
+    let bits = reader.context_bit_string(tag_number)?;
+    Ok(StatusFlags::from(&bits).into())
+}
+
+/// Read an ABSTRACT-SYNTAX value enclosed in the context tag
+fn decode_abstract(reader: &mut Reader, tag_number: u8) -> crate::error::Result<BACnetValue> {
+    reader.opening_tag(tag_number)?;
+    let mut values = reader.values_until_closing_tag(tag_number)?;
+    Ok(match values.len() {
+        1 => values.remove(0),
+        _ => BACnetValue::Array(values),
+    })
+}
+
+impl NotificationParameters {
+    /// The event type of the choice, `None` for [`Other`](Self::Other)
+    pub fn event_type(&self) -> Option<EventType> {
+        Some(match self {
+            Self::ChangeOfBitstring { .. } => EventType::ChangeOfBitstring,
+            Self::ChangeOfState { .. } => EventType::ChangeOfState,
+            Self::ChangeOfValue { .. } => EventType::ChangeOfValue,
+            Self::CommandFailure { .. } => EventType::CommandFailure,
+            Self::FloatingLimit { .. } => EventType::FloatingLimit,
+            Self::OutOfRange { .. } => EventType::OutOfRange,
+            Self::ChangeOfLifeSafety { .. } => EventType::ChangeOfLifeSafety,
+            Self::UnsignedRange { .. } => EventType::UnsignedRange,
+            Self::Other(_) => return None,
+        })
+    }
+
+    /// Append the parameters enclosed in the context tag
+    pub fn encode_context(&self, buf: &mut Vec<u8>, tag_number: u8) -> crate::error::Result<()> {
+        encode_opening_tag(buf, tag_number);
+        if let Self::Other(values) = self {
+            encode_application(buf, values)?;
+            encode_closing_tag(buf, tag_number);
+            return Ok(());
+        }
+        // All other choices have an event type
+        let choice = self.event_type().map_or(0, |t| t as u8);
+        encode_opening_tag(buf, choice);
+        match self {
+            Self::ChangeOfBitstring {
+                referenced_bitstring,
+                status_flags,
+            } => {
+                encode_context_bit_string(buf, 0, referenced_bitstring);
+                encode_status_flags(buf, 1, status_flags);
+            }
+            Self::ChangeOfState {
+                new_state,
+                status_flags,
+            } => {
+                encode_opening_tag(buf, 0);
+                encode_context_unsigned(buf, new_state.choice, new_state.value);
+                encode_closing_tag(buf, 0);
+                encode_status_flags(buf, 1, status_flags);
+            }
+            Self::ChangeOfValue {
+                new_value,
+                status_flags,
+            } => {
+                encode_opening_tag(buf, 0);
+                match new_value {
+                    ChangedValue::ChangedBits(bits) => encode_context_bit_string(buf, 0, bits),
+                    ChangedValue::ChangedValue(value) => encode_context_real(buf, 1, *value),
+                }
+                encode_closing_tag(buf, 0);
+                encode_status_flags(buf, 1, status_flags);
+            }
+            Self::CommandFailure {
+                command_value,
+                status_flags,
+                feedback_value,
+            } => {
+                encode_abstract(buf, 0, command_value)?;
+                encode_status_flags(buf, 1, status_flags);
+                encode_abstract(buf, 2, feedback_value)?;
+            }
+            Self::FloatingLimit {
+                reference_value,
+                status_flags,
+                setpoint_value,
+                error_limit,
+            } => {
+                encode_context_real(buf, 0, *reference_value);
+                encode_status_flags(buf, 1, status_flags);
+                encode_context_real(buf, 2, *setpoint_value);
+                encode_context_real(buf, 3, *error_limit);
+            }
+            Self::OutOfRange {
+                exceeding_value,
+                status_flags,
+                deadband,
+                exceeded_limit,
+            } => {
+                encode_context_real(buf, 0, *exceeding_value);
+                encode_status_flags(buf, 1, status_flags);
+                encode_context_real(buf, 2, *deadband);
+                encode_context_real(buf, 3, *exceeded_limit);
+            }
+            Self::ChangeOfLifeSafety {
+                new_state,
+                new_mode,
+                status_flags,
+                operation_expected,
+            } => {
+                encode_context_enumerated(buf, 0, *new_state);
+                encode_context_enumerated(buf, 1, *new_mode);
+                encode_status_flags(buf, 2, status_flags);
+                encode_context_enumerated(buf, 3, *operation_expected);
+            }
+            Self::UnsignedRange {
+                exceeding_value,
+                status_flags,
+                exceeded_limit,
+            } => {
+                encode_context_unsigned(buf, 0, *exceeding_value);
+                encode_status_flags(buf, 1, status_flags);
+                encode_context_unsigned(buf, 2, *exceeded_limit);
+            }
+            // Encoded above
+            Self::Other(_) => {}
+        }
+        encode_closing_tag(buf, choice);
+        encode_closing_tag(buf, tag_number);
+        Ok(())
+    }
+
+    /// Read parameters enclosed in the context tag
+    pub fn decode_context(reader: &mut Reader, tag_number: u8) -> crate::error::Result<Self> {
+        reader.opening_tag(tag_number)?;
+        let choice = reader.header()?.tag_number;
+        let event_type = match reader.is_opening_tag(choice) {
+            true => EventType::from_u8(choice),
+            false => None,
+        };
+        let parameters = match event_type {
+            Some(EventType::ChangeOfBitstring) => {
+                reader.opening_tag(choice)?;
+                Self::ChangeOfBitstring {
+                    referenced_bitstring: reader.context_bit_string(0)?.into(),
+                    status_flags: decode_status_flags(reader, 1)?,
+                }
+            }
+            Some(EventType::ChangeOfState) => {
+                reader.opening_tag(choice)?;
+                reader.opening_tag(0)?;
+                let state = reader.header()?.tag_number;
+                let new_state = PropertyState {
+                    choice: state,
+                    value: reader.context_unsigned(state)?,
+                };
+                reader.closing_tag(0)?;
+                Self::ChangeOfState {
+                    new_state,
+                    status_flags: decode_status_flags(reader, 1)?,
+                }
+            }
+            Some(EventType::ChangeOfValue) => {
+                reader.opening_tag(choice)?;
+                reader.opening_tag(0)?;
+                let new_value = match reader.is_context_tag(0) {
+                    true => ChangedValue::ChangedBits(reader.context_bit_string(0)?.into()),
+                    false => ChangedValue::ChangedValue(reader.context_real(1)?),
+                };
+                reader.closing_tag(0)?;
+                Self::ChangeOfValue {
+                    new_value,
+                    status_flags: decode_status_flags(reader, 1)?,
+                }
+            }
+            Some(EventType::CommandFailure) => {
+                reader.opening_tag(choice)?;
+                Self::CommandFailure {
+                    command_value: decode_abstract(reader, 0)?,
+                    status_flags: decode_status_flags(reader, 1)?,
+                    feedback_value: decode_abstract(reader, 2)?,
+                }
+            }
+            Some(EventType::FloatingLimit) => {
+                reader.opening_tag(choice)?;
+                Self::FloatingLimit {
+                    reference_value: reader.context_real(0)?,
+                    status_flags: decode_status_flags(reader, 1)?,
+                    setpoint_value: reader.context_real(2)?,
+                    error_limit: reader.context_real(3)?,
+                }
+            }
+            Some(EventType::OutOfRange) => {
+                reader.opening_tag(choice)?;
+                Self::OutOfRange {
+                    exceeding_value: reader.context_real(0)?,
+                    status_flags: decode_status_flags(reader, 1)?,
+                    deadband: reader.context_real(2)?,
+                    exceeded_limit: reader.context_real(3)?,
+                }
+            }
+            Some(EventType::ChangeOfLifeSafety) => {
+                reader.opening_tag(choice)?;
+                Self::ChangeOfLifeSafety {
+                    new_state: reader.context_enumerated(0)?,
+                    new_mode: reader.context_enumerated(1)?,
+                    status_flags: decode_status_flags(reader, 2)?,
+                    operation_expected: reader.context_enumerated(3)?,
+                }
+            }
+            Some(EventType::UnsignedRange) => {
+                reader.opening_tag(choice)?;
+                Self::UnsignedRange {
+                    exceeding_value: reader.context_unsigned(0)?,
+                    status_flags: decode_status_flags(reader, 1)?,
+                    exceeded_limit: reader.context_unsigned(2)?,
+                }
+            }
+            _ => {
+                let mut values = reader.values_until_closing_tag(tag_number)?;
+                return Ok(Self::Other(match values.len() {
+                    1 => values.remove(0),
+                    _ => BACnetValue::Array(values),
+                }));
+            }
+        };
+        reader.closing_tag(choice)?;
+        reader.closing_tag(tag_number)?;
+        Ok(parameters)
+    }
+}
+
+/// Event notification parameters (13.8, 13.9)
+///
+/// Confirmed and unconfirmed event notifications share their parameters.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct EventNotification {
+    pub process_identifier: u32,
+    pub initiating_device_identifier: ObjectIdentifier,
+    pub event_object_identifier: ObjectIdentifier,
+    pub time_stamp: TimeStamp,
+    pub notification_class: u32,
+    pub priority: u8,
+    /// BACnetEventType (Clause 21), see [`EventType`]
+    pub event_type: u32,
+    pub message_text: Option<String>,
+    pub notify_type: NotifyType,
+    pub ack_required: Option<bool>,
+    pub from_state: Option<EventState>,
+    pub to_state: EventState,
+    pub event_values: Option<NotificationParameters>,
+}
+
+impl EventNotification {
+    fn encode_data(&self) -> crate::error::Result<Vec<u8>> {
+        let mut data = Vec::new();
+        encode_context_unsigned(&mut data, 0, self.process_identifier);
+        encode_context_object_identifier(&mut data, 1, self.initiating_device_identifier)?;
+        encode_context_object_identifier(&mut data, 2, self.event_object_identifier)?;
+        self.time_stamp.encode_context(&mut data, 3);
+        encode_context_unsigned(&mut data, 4, self.notification_class);
+        encode_context_unsigned(&mut data, 5, self.priority as u32);
+        encode_context_enumerated(&mut data, 6, self.event_type);
+        if let Some(text) = &self.message_text {
+            encode_context_character_string(&mut data, 7, text);
+        }
+        encode_context_enumerated(&mut data, 8, self.notify_type as u32);
+        if let Some(ack_required) = self.ack_required {
+            encode_context_boolean(&mut data, 9, ack_required);
+        }
+        if let Some(from_state) = self.from_state {
+            encode_context_enumerated(&mut data, 10, from_state as u32);
+        }
+        encode_context_enumerated(&mut data, 11, self.to_state as u32);
+        if let Some(values) = &self.event_values {
+            values.encode_context(&mut data, 12)?;
+        }
+        Ok(data)
+    }
+}
+
+impl Decode for EventNotification {
+    fn decode<T: std::io::Read + Sized>(reader: &mut T) -> crate::error::Result<Self> {
+        let mut data = Vec::new();
+        reader.read_to_end(&mut data)?;
+        let mut reader = Reader::new(&data);
+        let state = |s: u32| EventState::from_u32(s).ok_or_else(invalid);
+
+        let process_identifier = reader.context_unsigned(0)?;
+        let initiating_device_identifier = reader.context_object_identifier(1)?;
+        let event_object_identifier = reader.context_object_identifier(2)?;
+        let time_stamp = TimeStamp::decode_context(&mut reader, 3)?;
+        let notification_class = reader.context_unsigned(4)?;
+        let priority = reader.context_unsigned(5)? as u8;
+        let event_type = reader.context_enumerated(6)?;
+        let message_text = match reader.is_context_tag(7) {
+            true => Some(reader.context_character_string(7)?),
+            false => None,
+        };
+        let notify_type =
+            NotifyType::from_u32(reader.context_enumerated(8)?).ok_or_else(invalid)?;
+        let ack_required = match reader.is_context_tag(9) {
+            true => Some(reader.context_boolean(9)?),
+            false => None,
+        };
+        let from_state = match reader.optional_context_unsigned(10)? {
+            Some(s) => Some(state(s)?),
+            None => None,
+        };
+        let to_state = state(reader.context_enumerated(11)?)?;
+        let event_values = match reader.is_opening_tag(12) {
+            true => Some(NotificationParameters::decode_context(&mut reader, 12)?),
+            false => None,
+        };
+
+        Ok(Self {
+            process_identifier,
+            initiating_device_identifier,
+            event_object_identifier,
+            time_stamp,
+            notification_class,
+            priority,
+            event_type,
+            message_text,
+            notify_type,
+            ack_required,
+            from_state,
+            to_state,
+            event_values,
+        })
+    }
+}
+
+impl Encode for EventNotification {
+    fn encode<T: std::io::Write + Sized>(&self, writer: &mut T) -> crate::error::Result<()> {
+        writer.write_all(&self.encode_data()?)?;
+        Ok(())
+    }
+
+    fn len(&self) -> usize {
+        self.encode_data().map_or(0, |data| data.len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::application::{BACnetDate, BACnetDateTime, BACnetTime, ObjectType};
+
+    #[test]
+    fn test_event_notification() {
+        let notification = EventNotification {
+            process_identifier: 1,
+            initiating_device_identifier: ObjectIdentifier::new(ObjectType::Device, 4),
+            event_object_identifier: ObjectIdentifier::new(ObjectType::AnalogInput, 2),
+            time_stamp: TimeStamp::DateTime(BACnetDateTime::new(
+                BACnetDate::new(2021, 1, 25),
+                BACnetTime::new(12, 0, 0, 0),
+            )),
+            notification_class: 4,
+            priority: 100,
+            event_type: 5,
+            message_text: Some("High temperature".into()),
+            notify_type: NotifyType::Alarm,
+            ack_required: Some(true),
+            from_state: Some(EventState::Normal),
+            to_state: EventState::HighLimit,
+            event_values: None,
+        };
+        let data = notification.encode_vec().unwrap();
+        assert_eq!(
+            &data[..22],
+            &hex::decode("09011c020000042c000000023e2ea479011901b40c0000").unwrap()[..22]
+        );
+        assert_eq!(
+            EventNotification::decode_slice(&data).unwrap(),
+            notification
+        );
+        assert!(EventNotification::decode_slice(&data[..data.len() - 2]).is_err());
+    }
+
+    #[test]
+    fn test_notification_parameters() {
+        let flags = [true, false, false, false];
+        let out_of_range = NotificationParameters::OutOfRange {
+            exceeding_value: 80.0,
+            status_flags: flags,
+            deadband: 1.0,
+            exceeded_limit: 75.0,
+        };
+        let mut data = Vec::new();
+        out_of_range.encode_context(&mut data, 12).unwrap();
+        assert_eq!(
+            hex::encode(&data),
+            "ce5e0c42a000001a04802c3f8000003c429600005fcf"
+        );
+        assert_eq!(out_of_range.event_type(), Some(EventType::OutOfRange));
+
+        for parameters in vec![
+            out_of_range,
+            NotificationParameters::ChangeOfBitstring {
+                referenced_bitstring: vec![true, false, true],
+                status_flags: flags,
+            },
+            NotificationParameters::ChangeOfState {
+                new_state: PropertyState {
+                    choice: 1,
+                    value: 1,
+                },
+                status_flags: flags,
+            },
+            NotificationParameters::ChangeOfValue {
+                new_value: ChangedValue::ChangedValue(21.5),
+                status_flags: flags,
+            },
+            NotificationParameters::ChangeOfValue {
+                new_value: ChangedValue::ChangedBits(vec![false, true]),
+                status_flags: flags,
+            },
+            NotificationParameters::CommandFailure {
+                command_value: BACnetValue::Enumerated(1),
+                status_flags: flags,
+                feedback_value: BACnetValue::Enumerated(0),
+            },
+            NotificationParameters::FloatingLimit {
+                reference_value: 30.0,
+                status_flags: flags,
+                setpoint_value: 21.0,
+                error_limit: 5.0,
+            },
+            NotificationParameters::ChangeOfLifeSafety {
+                new_state: 2,
+                new_mode: 0,
+                status_flags: flags,
+                operation_expected: 1,
+            },
+            NotificationParameters::UnsignedRange {
+                exceeding_value: 120,
+                status_flags: flags,
+                exceeded_limit: 100,
+            },
+        ] {
+            let mut data = Vec::new();
+            parameters.encode_context(&mut data, 12).unwrap();
+            let mut reader = Reader::new(&data);
+            assert_eq!(
+                NotificationParameters::decode_context(&mut reader, 12).unwrap(),
+                parameters
+            );
+            assert!(reader.is_empty());
+        }
+
+        // Shorter status flags are padded, unknown choices are kept
+        let data = hex::decode("cebe09781a06802964bfcf").unwrap();
+        let mut reader = Reader::new(&data);
+        assert_eq!(
+            NotificationParameters::decode_context(&mut reader, 12).unwrap(),
+            NotificationParameters::UnsignedRange {
+                exceeding_value: 120,
+                status_flags: flags,
+                exceeded_limit: 100,
+            }
+        );
+        let data = hex::decode("ceee0921efcf").unwrap();
+        let mut reader = Reader::new(&data);
+        let parameters = NotificationParameters::decode_context(&mut reader, 12).unwrap();
+        assert!(matches!(parameters, NotificationParameters::Other(_)));
+        assert_eq!(parameters.event_type(), None);
+        let mut encoded = Vec::new();
+        parameters.encode_context(&mut encoded, 12).unwrap();
+        assert_eq!(encoded, data);
+
+        // Missing status flags
+        let data = hex::decode("ce5e0c42a000005fcf").unwrap();
+        assert!(NotificationParameters::decode_context(&mut Reader::new(&data), 12).is_err());
+    }
+
+    #[test]
+    fn test_time_stamp() {
+        for time_stamp in &[
+            TimeStamp::Time(BACnetTime::new(1, 2, 3, 4)),
+            TimeStamp::SequenceNumber(300),
+        ] {
+            let mut data = Vec::new();
+            time_stamp.encode_context(&mut data, 3);
+            let mut reader = Reader::new(&data);
+            assert_eq!(
+                TimeStamp::decode_context(&mut reader, 3).unwrap(),
+                *time_stamp
+            );
+            assert!(reader.is_empty());
+        }
+    }
+}
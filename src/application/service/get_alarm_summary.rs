@@ -0,0 +1,129 @@
+use crate::application::{EventState, ObjectIdentifier};
+use crate::encoding::*;
+use crate::error::{Error, ServiceError};
+use crate::{Decode, Encode};
+
+use num_traits::FromPrimitive;
+
+fn invalid() -> Error {
+    Error::from(ServiceError::Invalid("Invalid alarm summary"))
+}
+
+/// An object in alarm as returned by GetAlarmSummary (13.10)
+///
+/// The transitions are indexed by [`Transition`](crate::objects::Transition).
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct AlarmSummary {
+    pub object_identifier: ObjectIdentifier,
+    pub alarm_state: EventState,
+    pub acknowledged_transitions: [bool; 3],
+}
+
+impl AlarmSummary {
+    fn encode(&self, buf: &mut Vec<u8>) -> crate::error::Result<()> {
+        encode_object_identifier(buf, self.object_identifier)?;
+        encode_enumerated(buf, self.alarm_state as u32);
+        encode_bit_string(buf, &self.acknowledged_transitions);
+        Ok(())
+    }
+
+    fn decode(reader: &mut Reader) -> crate::error::Result<Self> {
+        let object_identifier = reader.application_object_identifier()?;
+        let alarm_state =
+            EventState::from_u32(reader.application_enumerated()?).ok_or_else(invalid)?;
+        let acknowledged_transitions = match reader.application_bit_string()? {
+            bits if bits.bit_len() >= 3 => [bits[0], bits[1], bits[2]],
+            _ => return Err(invalid()),
+        };
+        Ok(Self {
+            object_identifier,
+            alarm_state,
+            acknowledged_transitions,
+        })
+    }
+}
+
+/// GetAlarmSummary-ACK (13.10.1.2)
+///
+/// The request has no parameters. Newer devices list their events with
+/// [`GetEventInformation`](super::GetEventInformationRequest) instead.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct GetAlarmSummaryAck {
+    pub list_of_alarm_summaries: Vec<AlarmSummary>,
+}
+
+impl GetAlarmSummaryAck {
+    fn encode_data(&self) -> crate::error::Result<Vec<u8>> {
+        let mut data = Vec::new();
+        for summary in &self.list_of_alarm_summaries {
+            summary.encode(&mut data)?;
+        }
+        Ok(data)
+    }
+}
+
+impl Decode for GetAlarmSummaryAck {
+    fn decode<T: std::io::Read + Sized>(reader: &mut T) -> crate::error::Result<Self> {
+        let mut data = Vec::new();
+        reader.read_to_end(&mut data)?;
+        let mut reader = Reader::new(&data);
+        let mut list_of_alarm_summaries = Vec::new();
+        while !reader.is_empty() {
+            list_of_alarm_summaries.push(AlarmSummary::decode(&mut reader)?);
+        }
+        Ok(Self {
+            list_of_alarm_summaries,
+        })
+    }
+}
+
+impl Encode for GetAlarmSummaryAck {
+    fn encode<T: std::io::Write + Sized>(&self, writer: &mut T) -> crate::error::Result<()> {
+        writer.write_all(&self.encode_data()?)?;
+        Ok(())
+    }
+
+    fn len(&self) -> usize {
+        self.encode_data().map_or(0, |data| data.len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::application::ObjectType;
+
+    #[test]
+    fn test_get_alarm_summary_ack() {
+        let data = hex::decode("c4000000019103820560c4000000029104820520").unwrap();
+        let ack = GetAlarmSummaryAck::decode_slice(&data).unwrap();
+        assert_eq!(
+            ack.list_of_alarm_summaries,
+            vec![
+                AlarmSummary {
+                    object_identifier: ObjectIdentifier::new(ObjectType::AnalogInput, 1),
+                    alarm_state: EventState::HighLimit,
+                    acknowledged_transitions: [false, true, true],
+                },
+                AlarmSummary {
+                    object_identifier: ObjectIdentifier::new(ObjectType::AnalogInput, 2),
+                    alarm_state: EventState::LowLimit,
+                    acknowledged_transitions: [false, false, true],
+                },
+            ]
+        );
+        assert_eq!(ack.len(), data.len());
+        assert_eq!(ack.encode_vec().unwrap(), data);
+
+        // An empty list
+        let ack = GetAlarmSummaryAck::decode_slice(&[]).unwrap();
+        assert!(ack.list_of_alarm_summaries.is_empty());
+
+        // Missing acknowledged transitions, unknown alarm state
+        assert!(GetAlarmSummaryAck::decode_slice(&data[..7]).is_err());
+        let data = hex::decode("c40000000191ff820560").unwrap();
+        assert!(GetAlarmSummaryAck::decode_slice(&data).is_err());
+    }
+}
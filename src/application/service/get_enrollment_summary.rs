@@ -0,0 +1,417 @@
+use crate::application::{BACnetValue, EventState, ObjectIdentifier};
+use crate::encoding::*;
+use crate::error::{Error, ServiceError};
+use crate::network::Address;
+use crate::objects::Recipient;
+use crate::{Decode, Encode};
+
+use num_derive::{FromPrimitive, ToPrimitive};
+use num_traits::FromPrimitive;
+
+fn invalid() -> Error {
+    Error::from(ServiceError::Invalid("Invalid enrollment summary"))
+}
+
+/// Acknowledgment state of the objects listed (13.11.1.1.1)
+#[derive(Copy, Clone, Debug, Eq, PartialEq, FromPrimitive, ToPrimitive)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum AcknowledgmentFilter {
+    All = 0,
+    Acked = 1,
+    NotAcked = 2,
+}
+
+/// Event state of the objects listed (13.11.1.1.3), active is any state
+/// other than normal
+#[derive(Copy, Clone, Debug, Eq, PartialEq, FromPrimitive, ToPrimitive)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum EventStateFilter {
+    Offnormal = 0,
+    Fault = 1,
+    Normal = 2,
+    All = 3,
+    Active = 4,
+}
+
+/// BACnetRecipientProcess (Clause 21)
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RecipientProcess {
+    pub recipient: Recipient,
+    pub process_identifier: u32,
+}
+
+impl RecipientProcess {
+    fn encode(&self, buf: &mut Vec<u8>) -> crate::error::Result<()> {
+        encode_context(buf, 0, &BACnetValue::from(self.recipient.clone()))?;
+        encode_context_unsigned(buf, 1, self.process_identifier);
+        Ok(())
+    }
+
+    fn decode(reader: &mut Reader) -> crate::error::Result<Self> {
+        reader.opening_tag(0)?;
+        let recipient = match reader.is_context_tag(0) {
+            true => Recipient::Device(reader.context_object_identifier(0)?),
+            false => {
+                reader.opening_tag(1)?;
+                // Network number 0 is the local network
+                let net = match reader.application_value()? {
+                    BACnetValue::Unsigned(0) => None,
+                    BACnetValue::Unsigned(net) if net <= u16::MAX as u32 => Some(net as u16),
+                    _ => return Err(invalid()),
+                };
+                let mac = match reader.application_value()? {
+                    BACnetValue::OctetString(mac) => mac,
+                    _ => return Err(invalid()),
+                };
+                reader.closing_tag(1)?;
+                Recipient::Address(Address { net, mac })
+            }
+        };
+        reader.closing_tag(0)?;
+        let process_identifier = reader.context_unsigned(1)?;
+        Ok(Self {
+            recipient,
+            process_identifier,
+        })
+    }
+}
+
+/// GetEnrollmentSummary-Request (13.11.1.1)
+///
+/// Only objects passing all the filters given are listed.
+///
+/// ```
+/// use bacnet::application::{AcknowledgmentFilter, EventStateFilter};
+/// use bacnet::application::GetEnrollmentSummaryRequest;
+/// use bacnet::Encode;
+///
+/// let request = GetEnrollmentSummaryRequest::new(AcknowledgmentFilter::NotAcked)
+///     .event_state(EventStateFilter::Active)
+///     .priorities(0, 127);
+/// assert_eq!(
+///     request.encode_vec().unwrap(),
+///     [0x09, 0x02, 0x29, 0x04, 0x4e, 0x09, 0x00, 0x19, 0x7f, 0x4f]
+/// );
+/// ```
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct GetEnrollmentSummaryRequest {
+    pub acknowledgment_filter: AcknowledgmentFilter,
+    /// Objects notifying the process of the recipient
+    pub enrollment_filter: Option<RecipientProcess>,
+    pub event_state_filter: Option<EventStateFilter>,
+    /// BACnetEventType (Clause 21), see [`EventType`](super::EventType)
+    pub event_type_filter: Option<u32>,
+    /// Minimum and maximum priority
+    pub priority_filter: Option<(u8, u8)>,
+    pub notification_class_filter: Option<u32>,
+}
+
+impl GetEnrollmentSummaryRequest {
+    pub fn new(acknowledgment_filter: AcknowledgmentFilter) -> Self {
+        Self {
+            acknowledgment_filter,
+            enrollment_filter: None,
+            event_state_filter: None,
+            event_type_filter: None,
+            priority_filter: None,
+            notification_class_filter: None,
+        }
+    }
+
+    /// List only objects notifying the process of the recipient
+    pub fn enrollment(mut self, recipient: Recipient, process_identifier: u32) -> Self {
+        self.enrollment_filter = Some(RecipientProcess {
+            recipient,
+            process_identifier,
+        });
+        self
+    }
+
+    pub fn event_state(mut self, filter: EventStateFilter) -> Self {
+        self.event_state_filter = Some(filter);
+        self
+    }
+
+    pub fn event_type(mut self, event_type: u32) -> Self {
+        self.event_type_filter = Some(event_type);
+        self
+    }
+
+    /// List only objects with a priority from the minimum to the maximum,
+    /// both included
+    pub fn priorities(mut self, min_priority: u8, max_priority: u8) -> Self {
+        self.priority_filter = Some((min_priority, max_priority));
+        self
+    }
+
+    pub fn notification_class(mut self, notification_class: u32) -> Self {
+        self.notification_class_filter = Some(notification_class);
+        self
+    }
+
+    fn encode_data(&self) -> crate::error::Result<Vec<u8>> {
+        let mut data = Vec::new();
+        encode_context_enumerated(&mut data, 0, self.acknowledgment_filter as u32);
+        if let Some(enrollment) = &self.enrollment_filter {
+            encode_opening_tag(&mut data, 1);
+            enrollment.encode(&mut data)?;
+            encode_closing_tag(&mut data, 1);
+        }
+        if let Some(filter) = self.event_state_filter {
+            encode_context_enumerated(&mut data, 2, filter as u32);
+        }
+        if let Some(event_type) = self.event_type_filter {
+            encode_context_enumerated(&mut data, 3, event_type);
+        }
+        if let Some((min, max)) = self.priority_filter {
+            encode_opening_tag(&mut data, 4);
+            encode_context_unsigned(&mut data, 0, min as u32);
+            encode_context_unsigned(&mut data, 1, max as u32);
+            encode_closing_tag(&mut data, 4);
+        }
+        if let Some(notification_class) = self.notification_class_filter {
+            encode_context_unsigned(&mut data, 5, notification_class);
+        }
+        Ok(data)
+    }
+}
+
+fn priority(value: u32) -> crate::error::Result<u8> {
+    match value {
+        p if p <= u8::MAX as u32 => Ok(p as u8),
+        _ => Err(invalid()),
+    }
+}
+
+impl Decode for GetEnrollmentSummaryRequest {
+    fn decode<T: std::io::Read + Sized>(reader: &mut T) -> crate::error::Result<Self> {
+        let mut data = Vec::new();
+        reader.read_to_end(&mut data)?;
+        let mut reader = Reader::new(&data);
+        let acknowledgment_filter =
+            AcknowledgmentFilter::from_u32(reader.context_enumerated(0)?).ok_or_else(invalid)?;
+        let enrollment_filter = match reader.is_opening_tag(1) {
+            true => {
+                reader.opening_tag(1)?;
+                let enrollment = RecipientProcess::decode(&mut reader)?;
+                reader.closing_tag(1)?;
+                Some(enrollment)
+            }
+            false => None,
+        };
+        let event_state_filter = match reader.is_context_tag(2) {
+            true => Some(
+                EventStateFilter::from_u32(reader.context_enumerated(2)?).ok_or_else(invalid)?,
+            ),
+            false => None,
+        };
+        let event_type_filter = match reader.is_context_tag(3) {
+            true => Some(reader.context_enumerated(3)?),
+            false => None,
+        };
+        let priority_filter = match reader.is_opening_tag(4) {
+            true => {
+                reader.opening_tag(4)?;
+                let min = priority(reader.context_unsigned(0)?)?;
+                let max = priority(reader.context_unsigned(1)?)?;
+                reader.closing_tag(4)?;
+                Some((min, max))
+            }
+            false => None,
+        };
+        let notification_class_filter = reader.optional_context_unsigned(5)?;
+        Ok(Self {
+            acknowledgment_filter,
+            enrollment_filter,
+            event_state_filter,
+            event_type_filter,
+            priority_filter,
+            notification_class_filter,
+        })
+    }
+}
+
+impl Encode for GetEnrollmentSummaryRequest {
+    fn encode<T: std::io::Write + Sized>(&self, writer: &mut T) -> crate::error::Result<()> {
+        writer.write_all(&self.encode_data()?)?;
+        Ok(())
+    }
+
+    fn len(&self) -> usize {
+        self.encode_data().map_or(0, |data| data.len())
+    }
+}
+
+/// An object passing the filters as returned by GetEnrollmentSummary
+/// (13.11.1.2)
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct EnrollmentSummary {
+    pub object_identifier: ObjectIdentifier,
+    /// BACnetEventType (Clause 21), see [`EventType`](super::EventType)
+    pub event_type: u32,
+    pub event_state: EventState,
+    pub priority: u8,
+    pub notification_class: Option<u32>,
+}
+
+impl EnrollmentSummary {
+    fn encode(&self, buf: &mut Vec<u8>) -> crate::error::Result<()> {
+        encode_object_identifier(buf, self.object_identifier)?;
+        encode_enumerated(buf, self.event_type);
+        encode_enumerated(buf, self.event_state as u32);
+        encode_unsigned(buf, self.priority as u32);
+        if let Some(notification_class) = self.notification_class {
+            encode_unsigned(buf, notification_class);
+        }
+        Ok(())
+    }
+
+    /// The notification class is told apart from the next summary, which
+    /// starts with an object identifier, by its application tag
+    fn decode(reader: &mut Reader) -> crate::error::Result<Self> {
+        let object_identifier = match reader.application_value()? {
+            BACnetValue::ObjectIdentifier(object) => object,
+            _ => return Err(invalid()),
+        };
+        let event_type = match reader.application_value()? {
+            BACnetValue::Enumerated(event_type) => event_type,
+            _ => return Err(invalid()),
+        };
+        let event_state = match reader.application_value()? {
+            BACnetValue::Enumerated(state) => EventState::from_u32(state).ok_or_else(invalid)?,
+            _ => return Err(invalid()),
+        };
+        let priority = match reader.application_value()? {
+            BACnetValue::Unsigned(p) => priority(p)?,
+            _ => return Err(invalid()),
+        };
+        let notification_class = match !reader.is_empty() && reader.header()?.tag_number == 2 {
+            true => match reader.application_value()? {
+                BACnetValue::Unsigned(notification_class) => Some(notification_class),
+                _ => return Err(invalid()),
+            },
+            false => None,
+        };
+        Ok(Self {
+            object_identifier,
+            event_type,
+            event_state,
+            priority,
+            notification_class,
+        })
+    }
+}
+
+/// GetEnrollmentSummary-ACK (13.11.1.2)
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct GetEnrollmentSummaryAck {
+    pub list_of_enrollment_summaries: Vec<EnrollmentSummary>,
+}
+
+impl GetEnrollmentSummaryAck {
+    fn encode_data(&self) -> crate::error::Result<Vec<u8>> {
+        let mut data = Vec::new();
+        for summary in &self.list_of_enrollment_summaries {
+            summary.encode(&mut data)?;
+        }
+        Ok(data)
+    }
+}
+
+impl Decode for GetEnrollmentSummaryAck {
+    fn decode<T: std::io::Read + Sized>(reader: &mut T) -> crate::error::Result<Self> {
+        let mut data = Vec::new();
+        reader.read_to_end(&mut data)?;
+        let mut reader = Reader::new(&data);
+        let mut list_of_enrollment_summaries = Vec::new();
+        while !reader.is_empty() {
+            list_of_enrollment_summaries.push(EnrollmentSummary::decode(&mut reader)?);
+        }
+        Ok(Self {
+            list_of_enrollment_summaries,
+        })
+    }
+}
+
+impl Encode for GetEnrollmentSummaryAck {
+    fn encode<T: std::io::Write + Sized>(&self, writer: &mut T) -> crate::error::Result<()> {
+        writer.write_all(&self.encode_data()?)?;
+        Ok(())
+    }
+
+    fn len(&self) -> usize {
+        self.encode_data().map_or(0, |data| data.len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::application::ObjectType;
+
+    #[test]
+    fn test_get_enrollment_summary() {
+        let device = ObjectIdentifier::new(ObjectType::Device, 99);
+        let request = GetEnrollmentSummaryRequest::new(AcknowledgmentFilter::All)
+            .enrollment(Recipient::Device(device), 7)
+            .event_type(5)
+            .notification_class(3);
+        let data = request.encode_vec().unwrap();
+        assert_eq!(hex::encode(&data), "09001e0e0c020000630f19071f39055903");
+        assert_eq!(request.len(), data.len());
+        assert_eq!(
+            GetEnrollmentSummaryRequest::decode_slice(&data).unwrap(),
+            request
+        );
+
+        let request = GetEnrollmentSummaryRequest::new(AcknowledgmentFilter::Acked)
+            .enrollment(Recipient::Address(Address::remote(5, vec![0x0a, 0x0b])), 0);
+        let data = request.encode_vec().unwrap();
+        assert_eq!(hex::encode(&data), "09011e0e1e2105620a0b1f0f19001f");
+        assert_eq!(
+            GetEnrollmentSummaryRequest::decode_slice(&data).unwrap(),
+            request
+        );
+
+        // Unknown acknowledgment filter, unclosed priority filter
+        assert!(GetEnrollmentSummaryRequest::decode_slice(&[0x09, 0x03]).is_err());
+        let data = hex::decode("09004e09001964").unwrap();
+        assert!(GetEnrollmentSummaryRequest::decode_slice(&data).is_err());
+    }
+
+    #[test]
+    fn test_get_enrollment_summary_ack() {
+        let data = hex::decode("c400000001910591032164210ac4000000029101910021c8").unwrap();
+        let ack = GetEnrollmentSummaryAck::decode_slice(&data).unwrap();
+        assert_eq!(
+            ack.list_of_enrollment_summaries,
+            vec![
+                EnrollmentSummary {
+                    object_identifier: ObjectIdentifier::new(ObjectType::AnalogInput, 1),
+                    event_type: 5,
+                    event_state: EventState::HighLimit,
+                    priority: 100,
+                    notification_class: Some(10),
+                },
+                EnrollmentSummary {
+                    object_identifier: ObjectIdentifier::new(ObjectType::AnalogInput, 2),
+                    event_type: 1,
+                    event_state: EventState::Normal,
+                    priority: 200,
+                    notification_class: None,
+                },
+            ]
+        );
+        assert_eq!(ack.len(), data.len());
+        assert_eq!(ack.encode_vec().unwrap(), data);
+
+        // Missing priority, priority out of range
+        assert!(GetEnrollmentSummaryAck::decode_slice(&data[..9]).is_err());
+        let data = hex::decode("c40000000191059103220100").unwrap();
+        assert!(GetEnrollmentSummaryAck::decode_slice(&data).is_err());
+    }
+}
@@ -0,0 +1,278 @@
+use crate::application::{
+    BACnetDate, BACnetDateTime, BACnetTime, BACnetValue, EventState, NotifyType, ObjectIdentifier,
+    TimeStamp, UNSPECIFIED,
+};
+use crate::encoding::*;
+use crate::error::{Error, ServiceError};
+use crate::{Decode, Encode};
+
+use num_traits::FromPrimitive;
+
+fn invalid() -> Error {
+    Error::from(ServiceError::Invalid("Invalid event summary"))
+}
+
+/// Event state of an object as returned by GetEventInformation (13.12)
+///
+/// The arrays are indexed by [`Transition`](crate::objects::Transition).
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct EventSummary {
+    pub object_identifier: ObjectIdentifier,
+    pub event_state: EventState,
+    pub acked_transitions: [bool; 3],
+    pub event_time_stamps: [TimeStamp; 3],
+    pub notify_type: NotifyType,
+    pub event_enable: [bool; 3],
+    pub event_priorities: [u8; 3],
+}
+
+impl EventSummary {
+    /// An object in the normal state without any transitions
+    pub fn new(object_identifier: ObjectIdentifier) -> Self {
+        // Transitions that did not occur have an unspecified time stamp
+        let unspecified = TimeStamp::DateTime(BACnetDateTime::new(
+            BACnetDate {
+                year: UNSPECIFIED,
+                month: UNSPECIFIED,
+                day: UNSPECIFIED,
+                weekday: UNSPECIFIED,
+            },
+            BACnetTime::new(UNSPECIFIED, UNSPECIFIED, UNSPECIFIED, UNSPECIFIED),
+        ));
+        Self {
+            object_identifier,
+            event_state: EventState::Normal,
+            acked_transitions: [true; 3],
+            event_time_stamps: [unspecified; 3],
+            notify_type: NotifyType::Alarm,
+            event_enable: [true; 3],
+            event_priorities: [255; 3],
+        }
+    }
+
+    /// Whether the object is in an event state other than normal or has
+    /// unacknowledged transitions
+    pub fn is_active(&self) -> bool {
+        self.event_state != EventState::Normal || self.acked_transitions.contains(&false)
+    }
+
+    /// Append the summary as an element of listOfEventSummaries (13.12.1.2)
+    pub(crate) fn encode(&self, buf: &mut Vec<u8>) -> crate::error::Result<()> {
+        encode_context_object_identifier(buf, 0, self.object_identifier)?;
+        encode_context_enumerated(buf, 1, self.event_state as u32);
+        encode_context_bit_string(buf, 2, &self.acked_transitions);
+        encode_opening_tag(buf, 3);
+        self.event_time_stamps.iter().for_each(|t| t.encode(buf));
+        encode_closing_tag(buf, 3);
+        encode_context_enumerated(buf, 4, self.notify_type as u32);
+        encode_context_bit_string(buf, 5, &self.event_enable);
+        encode_opening_tag(buf, 6);
+        for priority in &self.event_priorities {
+            encode_unsigned(buf, *priority as u32);
+        }
+        encode_closing_tag(buf, 6);
+        Ok(())
+    }
+
+    /// Read an element of listOfEventSummaries
+    pub(crate) fn decode(reader: &mut Reader) -> crate::error::Result<Self> {
+        let transitions = |reader: &mut Reader, tag_number| match reader
+            .context_value(tag_number, 8)?
+        {
+            BACnetValue::BitString(bits) if bits.bit_len() >= 3 => Ok([bits[0], bits[1], bits[2]]),
+            _ => Err(invalid()),
+        };
+        let object_identifier = reader.context_object_identifier(0)?;
+        let event_state =
+            EventState::from_u32(reader.context_enumerated(1)?).ok_or_else(invalid)?;
+        let acked_transitions = transitions(reader, 2)?;
+        reader.opening_tag(3)?;
+        let event_time_stamps = [
+            TimeStamp::decode(reader)?,
+            TimeStamp::decode(reader)?,
+            TimeStamp::decode(reader)?,
+        ];
+        reader.closing_tag(3)?;
+        let notify_type =
+            NotifyType::from_u32(reader.context_enumerated(4)?).ok_or_else(invalid)?;
+        let event_enable = transitions(reader, 5)?;
+        reader.opening_tag(6)?;
+        let mut event_priorities = [0; 3];
+        for priority in event_priorities.iter_mut() {
+            *priority = match reader.application_value()? {
+                BACnetValue::Unsigned(p) if p <= u8::MAX as u32 => p as u8,
+                _ => return Err(invalid()),
+            };
+        }
+        reader.closing_tag(6)?;
+        Ok(Self {
+            object_identifier,
+            event_state,
+            acked_transitions,
+            event_time_stamps,
+            notify_type,
+            event_enable,
+            event_priorities,
+        })
+    }
+}
+
+/// GetEventInformation-Request (13.12.1.1)
+///
+/// Without the last object received the listing starts at the beginning.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct GetEventInformationRequest {
+    pub last_received_object_identifier: Option<ObjectIdentifier>,
+}
+
+impl GetEventInformationRequest {
+    /// Continue the listing after the last summary of an ACK
+    pub fn after(ack: &GetEventInformationAck) -> Self {
+        Self {
+            last_received_object_identifier: ack
+                .list_of_event_summaries
+                .last()
+                .map(|s| s.object_identifier),
+        }
+    }
+
+    fn encode_data(&self) -> crate::error::Result<Vec<u8>> {
+        let mut data = Vec::new();
+        if let Some(object) = self.last_received_object_identifier {
+            encode_context_object_identifier(&mut data, 0, object)?;
+        }
+        Ok(data)
+    }
+}
+
+impl Decode for GetEventInformationRequest {
+    fn decode<T: std::io::Read + Sized>(reader: &mut T) -> crate::error::Result<Self> {
+        let mut data = Vec::new();
+        reader.read_to_end(&mut data)?;
+        let mut reader = Reader::new(&data);
+        let last_received_object_identifier = match reader.is_context_tag(0) {
+            true => Some(reader.context_object_identifier(0)?),
+            false => None,
+        };
+        Ok(Self {
+            last_received_object_identifier,
+        })
+    }
+}
+
+impl Encode for GetEventInformationRequest {
+    fn encode<T: std::io::Write + Sized>(&self, writer: &mut T) -> crate::error::Result<()> {
+        writer.write_all(&self.encode_data()?)?;
+        Ok(())
+    }
+
+    fn len(&self) -> usize {
+        self.encode_data().map_or(0, |data| data.len())
+    }
+}
+
+/// GetEventInformation-ACK (13.12.1.2)
+///
+/// With `more_events` the summaries did not fit into the ACK, the next ones
+/// are requested with [`GetEventInformationRequest::after`] this ACK.
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct GetEventInformationAck {
+    pub list_of_event_summaries: Vec<EventSummary>,
+    pub more_events: bool,
+}
+
+impl GetEventInformationAck {
+    fn encode_data(&self) -> crate::error::Result<Vec<u8>> {
+        let mut data = Vec::new();
+        encode_opening_tag(&mut data, 0);
+        for summary in &self.list_of_event_summaries {
+            summary.encode(&mut data)?;
+        }
+        encode_closing_tag(&mut data, 0);
+        encode_context_boolean(&mut data, 1, self.more_events);
+        Ok(data)
+    }
+}
+
+impl Decode for GetEventInformationAck {
+    fn decode<T: std::io::Read + Sized>(reader: &mut T) -> crate::error::Result<Self> {
+        let mut data = Vec::new();
+        reader.read_to_end(&mut data)?;
+        let mut reader = Reader::new(&data);
+        let mut list_of_event_summaries = Vec::new();
+        reader.opening_tag(0)?;
+        while !reader.is_closing_tag(0) {
+            list_of_event_summaries.push(EventSummary::decode(&mut reader)?);
+        }
+        reader.closing_tag(0)?;
+        let more_events = reader.context_boolean(1)?;
+        Ok(Self {
+            list_of_event_summaries,
+            more_events,
+        })
+    }
+}
+
+impl Encode for GetEventInformationAck {
+    fn encode<T: std::io::Write + Sized>(&self, writer: &mut T) -> crate::error::Result<()> {
+        writer.write_all(&self.encode_data()?)?;
+        Ok(())
+    }
+
+    fn len(&self) -> usize {
+        self.encode_data().map_or(0, |data| data.len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::application::ObjectType;
+
+    fn summary(instance: u32) -> EventSummary {
+        let mut summary =
+            EventSummary::new(ObjectIdentifier::new(ObjectType::AnalogInput, instance));
+        summary.event_state = EventState::HighLimit;
+        summary.acked_transitions = [false, true, true];
+        summary.event_time_stamps[0] = TimeStamp::SequenceNumber(16);
+        summary.event_priorities = [100, 100, 200];
+        summary
+    }
+
+    #[test]
+    fn test_get_event_information() {
+        let request = GetEventInformationRequest::default();
+        assert!(request.encode_vec().unwrap().is_empty());
+        assert_eq!(
+            GetEventInformationRequest::decode_slice(&[]).unwrap(),
+            request
+        );
+
+        let ack = GetEventInformationAck {
+            list_of_event_summaries: vec![summary(1), summary(2)],
+            more_events: true,
+        };
+        let data = ack.encode_vec().unwrap();
+        assert!(data.starts_with(&hex::decode("0e0c000000011903").unwrap()));
+        assert!(data.ends_with(&hex::decode("6e2164216421c86f0f1901").unwrap()));
+        assert_eq!(ack.len(), data.len());
+        assert_eq!(GetEventInformationAck::decode_slice(&data).unwrap(), ack);
+
+        let request = GetEventInformationRequest::after(&ack);
+        let data = request.encode_vec().unwrap();
+        assert_eq!(data, hex::decode("0c00000002").unwrap());
+        assert_eq!(
+            GetEventInformationRequest::decode_slice(&data).unwrap(),
+            request
+        );
+
+        // Missing more events flag, priorities
+        let data = ack.encode_vec().unwrap();
+        assert!(GetEventInformationAck::decode_slice(&data[..data.len() - 2]).is_err());
+        let data = hex::decode("0e0c000000011903").unwrap();
+        assert!(GetEventInformationAck::decode_slice(&data).is_err());
+    }
+}
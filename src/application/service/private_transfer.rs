@@ -0,0 +1,344 @@
+use crate::application::vendor::decode_values;
+use crate::application::{BACnetError, BACnetValue, VendorId, VendorRegistry};
+use crate::encoding::*;
+use crate::error::{Error, ServiceError};
+use crate::{Decode, Encode};
+
+/// Private transfer parameters (16.2, 16.3)
+///
+/// Confirmed and unconfirmed private transfers share their parameters.
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PrivateTransfer {
+    pub vendor_id: VendorId,
+    pub service_number: u32,
+    /// The encoded service parameters, see [`parameters`](Self::parameters)
+    pub service_parameters: Option<Vec<u8>>,
+}
+
+impl PrivateTransfer {
+    /// A transfer without service parameters
+    pub fn new<V: Into<VendorId>>(vendor_id: V, service_number: u32) -> Self {
+        Self {
+            vendor_id: vendor_id.into(),
+            service_number,
+            service_parameters: None,
+        }
+    }
+
+    /// Send the encoded `parameters`, e.g. application tagged values
+    pub fn service_parameters(mut self, parameters: Vec<u8>) -> Self {
+        self.service_parameters = Some(parameters);
+        self
+    }
+
+    /// The service parameters decoded by the decoder of the vendor, or as
+    /// application tagged values
+    pub fn parameters(
+        &self,
+        vendors: &VendorRegistry,
+    ) -> crate::error::Result<Option<BACnetValue>> {
+        self.service_parameters
+            .as_ref()
+            .map(|data| vendors.decode_private_transfer(self.vendor_id, self.service_number, data))
+            .transpose()
+    }
+
+    fn encode_data(&self) -> Vec<u8> {
+        let mut data = Vec::new();
+        encode_context_unsigned(&mut data, 0, self.vendor_id.0 as u32);
+        encode_context_unsigned(&mut data, 1, self.service_number);
+        encode_block(&mut data, 2, &self.service_parameters);
+        data
+    }
+}
+
+fn decode_vendor_id(reader: &mut Reader, tag_number: u8) -> crate::error::Result<VendorId> {
+    match reader.context_unsigned(tag_number)? {
+        id if id <= u16::MAX as u32 => Ok(VendorId(id as u16)),
+        _ => Err(Error::from(ServiceError::Invalid("Invalid vendor ID"))),
+    }
+}
+
+/// The encoded values enclosed in the opening and closing tag, if present
+fn decode_block(reader: &mut Reader, tag_number: u8) -> crate::error::Result<Option<Vec<u8>>> {
+    match reader.is_opening_tag(tag_number) {
+        true => {
+            reader.opening_tag(tag_number)?;
+            Ok(Some(reader.raw_until_closing_tag(tag_number)?.to_vec()))
+        }
+        false => Ok(None),
+    }
+}
+
+fn encode_block(data: &mut Vec<u8>, tag_number: u8, block: &Option<Vec<u8>>) {
+    if let Some(block) = block {
+        encode_opening_tag(data, tag_number);
+        data.extend_from_slice(block);
+        encode_closing_tag(data, tag_number);
+    }
+}
+
+impl Decode for PrivateTransfer {
+    fn decode<T: std::io::Read + Sized>(reader: &mut T) -> crate::error::Result<Self> {
+        let mut data = Vec::new();
+        reader.read_to_end(&mut data)?;
+        let mut reader = Reader::new(&data);
+        let vendor_id = decode_vendor_id(&mut reader, 0)?;
+        let service_number = reader.context_unsigned(1)?;
+        let service_parameters = decode_block(&mut reader, 2)?;
+        Ok(Self {
+            vendor_id,
+            service_number,
+            service_parameters,
+        })
+    }
+}
+
+impl Encode for PrivateTransfer {
+    fn encode<T: std::io::Write + Sized>(&self, writer: &mut T) -> crate::error::Result<()> {
+        writer.write_all(&self.encode_data())?;
+        Ok(())
+    }
+
+    fn len(&self) -> usize {
+        self.encode_data().len()
+    }
+}
+
+/// ConfirmedPrivateTransfer-ACK (16.2.1.2)
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PrivateTransferAck {
+    pub vendor_id: VendorId,
+    pub service_number: u32,
+    /// The encoded result, see [`result`](Self::result)
+    pub result_block: Option<Vec<u8>>,
+}
+
+impl PrivateTransferAck {
+    /// The answer to the transfer with the encoded result
+    pub fn new(request: &PrivateTransfer, result_block: Option<Vec<u8>>) -> Self {
+        Self {
+            vendor_id: request.vendor_id,
+            service_number: request.service_number,
+            result_block,
+        }
+    }
+
+    /// The result block decoded as application tagged values
+    pub fn result(&self) -> crate::error::Result<Option<BACnetValue>> {
+        self.result_block.as_deref().map(decode_values).transpose()
+    }
+
+    fn encode_data(&self) -> Vec<u8> {
+        let mut data = Vec::new();
+        encode_context_unsigned(&mut data, 0, self.vendor_id.0 as u32);
+        encode_context_unsigned(&mut data, 1, self.service_number);
+        encode_block(&mut data, 2, &self.result_block);
+        data
+    }
+}
+
+impl Decode for PrivateTransferAck {
+    fn decode<T: std::io::Read + Sized>(reader: &mut T) -> crate::error::Result<Self> {
+        let mut data = Vec::new();
+        reader.read_to_end(&mut data)?;
+        let mut reader = Reader::new(&data);
+        let vendor_id = decode_vendor_id(&mut reader, 0)?;
+        let service_number = reader.context_unsigned(1)?;
+        let result_block = decode_block(&mut reader, 2)?;
+        Ok(Self {
+            vendor_id,
+            service_number,
+            result_block,
+        })
+    }
+}
+
+impl Encode for PrivateTransferAck {
+    fn encode<T: std::io::Write + Sized>(&self, writer: &mut T) -> crate::error::Result<()> {
+        writer.write_all(&self.encode_data())?;
+        Ok(())
+    }
+
+    fn len(&self) -> usize {
+        self.encode_data().len()
+    }
+}
+
+/// ConfirmedPrivateTransfer-Error (16.2.1.3), the parameters of the Error
+/// PDU answering a failed transfer
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PrivateTransferError {
+    pub error: BACnetError,
+    pub vendor_id: VendorId,
+    pub service_number: u32,
+    /// The encoded details of the error
+    pub error_parameters: Option<Vec<u8>>,
+}
+
+impl PrivateTransferError {
+    pub fn new(request: &PrivateTransfer, error: BACnetError) -> Self {
+        Self {
+            error,
+            vendor_id: request.vendor_id,
+            service_number: request.service_number,
+            error_parameters: None,
+        }
+    }
+
+    fn encode_data(&self) -> Vec<u8> {
+        let mut data = Vec::new();
+        encode_opening_tag(&mut data, 0);
+        data.extend_from_slice(&self.error.encode_vec().unwrap_or_default());
+        encode_closing_tag(&mut data, 0);
+        encode_context_unsigned(&mut data, 1, self.vendor_id.0 as u32);
+        encode_context_unsigned(&mut data, 2, self.service_number);
+        encode_block(&mut data, 3, &self.error_parameters);
+        data
+    }
+}
+
+impl std::fmt::Display for PrivateTransferError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} in service {} of vendor {}",
+            self.error, self.service_number, self.vendor_id.0
+        )
+    }
+}
+
+impl Decode for PrivateTransferError {
+    fn decode<T: std::io::Read + Sized>(reader: &mut T) -> crate::error::Result<Self> {
+        let mut data = Vec::new();
+        reader.read_to_end(&mut data)?;
+        let mut reader = Reader::new(&data);
+        reader.opening_tag(0)?;
+        let error = BACnetError::decode_slice(reader.raw_until_closing_tag(0)?)?;
+        let vendor_id = decode_vendor_id(&mut reader, 1)?;
+        let service_number = reader.context_unsigned(2)?;
+        let error_parameters = decode_block(&mut reader, 3)?;
+        Ok(Self {
+            error,
+            vendor_id,
+            service_number,
+            error_parameters,
+        })
+    }
+}
+
+impl Encode for PrivateTransferError {
+    fn encode<T: std::io::Write + Sized>(&self, writer: &mut T) -> crate::error::Result<()> {
+        writer.write_all(&self.encode_data())?;
+        Ok(())
+    }
+
+    fn len(&self) -> usize {
+        self.encode_data().len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::application::{ErrorClass, ErrorCode};
+
+    #[test]
+    fn test_private_transfer() {
+        let data = hex::decode("091919082e444290cccd21022f").unwrap();
+        let request = PrivateTransfer::decode_slice(&data).unwrap();
+        assert_eq!(request.vendor_id, VendorId(25));
+        assert_eq!(request.service_number, 8);
+        assert_eq!(
+            request.service_parameters.as_deref(),
+            Some(&data[5..data.len() - 1])
+        );
+        assert_eq!(request.encode_vec().unwrap(), data);
+        assert_eq!(
+            request.parameters(&VendorRegistry::new()).unwrap(),
+            Some(BACnetValue::Array(vec![
+                BACnetValue::Real(72.4),
+                BACnetValue::Unsigned(2)
+            ]))
+        );
+
+        let mut vendors = VendorRegistry::new();
+        vendors.register_private_transfer(VendorId(25), 8, |data| {
+            Ok(BACnetValue::Unsigned(data.len() as u32))
+        });
+        assert_eq!(
+            request.parameters(&vendors).unwrap(),
+            Some(BACnetValue::Unsigned(7))
+        );
+
+        let request = PrivateTransfer::decode_slice(&data[..4]).unwrap();
+        assert_eq!(request.service_parameters, None);
+        assert_eq!(request.parameters(&vendors).unwrap(), None);
+        assert!(PrivateTransfer::decode_slice(&data[..data.len() - 1]).is_err());
+        assert!(PrivateTransfer::decode_slice(&hex::decode("0b0100001908").unwrap()).is_err());
+        assert_eq!(
+            PrivateTransfer::new(25, 8).service_parameters(data[5..data.len() - 1].to_vec()),
+            PrivateTransfer::decode_slice(&data).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_private_transfer_extended_tags() {
+        // Opening and closing tag 2 with the tag number in the extended form,
+        // which is encoded again in the one octet form
+        let data = hex::decode("09191908fe02444290cccd2102ff02").unwrap();
+        let request = PrivateTransfer::decode_slice(&data).unwrap();
+        assert_eq!(
+            request.service_parameters.as_deref(),
+            Some(&data[6..data.len() - 2])
+        );
+        let encoded = request.encode_vec().unwrap();
+        assert_eq!(hex::encode(&encoded), "091919082e444290cccd21022f");
+        assert_eq!(request.len(), encoded.len());
+        assert_eq!(PrivateTransfer::decode_slice(&encoded).unwrap(), request);
+    }
+
+    #[test]
+    fn test_private_transfer_ack() {
+        let request = PrivateTransfer::new(25, 8);
+        let data = hex::decode("091919082e21052f").unwrap();
+        let ack = PrivateTransferAck::decode_slice(&data).unwrap();
+        assert_eq!(
+            ack,
+            PrivateTransferAck::new(&request, Some(vec![0x21, 0x05]))
+        );
+        assert_eq!(ack.result().unwrap(), Some(BACnetValue::Unsigned(5)));
+        assert_eq!(ack.len(), data.len());
+        assert_eq!(ack.encode_vec().unwrap(), data);
+
+        let ack = PrivateTransferAck::decode_slice(&data[..4]).unwrap();
+        assert_eq!(ack.result().unwrap(), None);
+        assert!(PrivateTransferAck::decode_slice(&data[..data.len() - 1]).is_err());
+    }
+
+    #[test]
+    fn test_private_transfer_error() {
+        let request = PrivateTransfer::new(25, 8);
+        let data = hex::decode("0e910591000f191929083e21053f").unwrap();
+        let error = PrivateTransferError::decode_slice(&data).unwrap();
+        let mut expected = PrivateTransferError::new(
+            &request,
+            BACnetError::new(ErrorClass::Services, ErrorCode::Other),
+        );
+        expected.error_parameters = Some(vec![0x21, 0x05]);
+        assert_eq!(error, expected);
+        assert_eq!(error.len(), data.len());
+        assert_eq!(error.encode_vec().unwrap(), data);
+        assert_eq!(
+            error.to_string(),
+            "Services: Other in service 8 of vendor 25"
+        );
+
+        // A plain error, missing service number
+        assert!(PrivateTransferError::decode_slice(&data[1..5]).is_err());
+        assert!(PrivateTransferError::decode_slice(&data[..8]).is_err());
+    }
+}
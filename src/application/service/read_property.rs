@@ -0,0 +1,261 @@
+use crate::application::{BACnetValue, ObjectIdentifier, PropertyIdentifier};
+use crate::encoding::*;
+use crate::error::{Error, ServiceError};
+use crate::{Decode, Encode};
+
+use num_traits::FromPrimitive;
+
+/// Read the object, property and array index ReadProperty-Request and
+/// ReadProperty-ACK share, and WriteProperty-Request starts with
+pub(super) fn decode_reference(
+    reader: &mut Reader,
+) -> crate::error::Result<(ObjectIdentifier, PropertyIdentifier, Option<u32>)> {
+    let object_identifier = reader.context_object_identifier(0)?;
+    let property_identifier = PropertyIdentifier::from_u32(reader.context_enumerated(1)?)
+        .ok_or_else(|| Error::from(ServiceError::Invalid("Property identifier out of range")))?;
+    let property_array_index = reader.optional_context_unsigned(2)?;
+    Ok((object_identifier, property_identifier, property_array_index))
+}
+
+pub(super) fn encode_reference(
+    data: &mut Vec<u8>,
+    object_identifier: ObjectIdentifier,
+    property_identifier: PropertyIdentifier,
+    property_array_index: Option<u32>,
+) -> crate::error::Result<()> {
+    encode_context_object_identifier(data, 0, object_identifier)?;
+    encode_context_enumerated(data, 1, property_identifier.number());
+    if let Some(index) = property_array_index {
+        encode_context_unsigned(data, 2, index);
+    }
+    Ok(())
+}
+
+/// ReadProperty-Request (15.5.1.1)
+///
+/// Index 0 of an array property is the number of elements, without an
+/// index the whole array is read.
+///
+/// ```
+/// use bacnet::application::{ObjectIdentifier, ObjectType, PropertyIdentifier};
+/// use bacnet::application::ReadPropertyRequest;
+/// use bacnet::Encode;
+///
+/// let object = ObjectIdentifier::new(ObjectType::AnalogInput, 1);
+/// let request = ReadPropertyRequest::new(object, PropertyIdentifier::PresentValue);
+/// assert_eq!(
+///     request.encode_vec().unwrap(),
+///     [0x0c, 0x00, 0x00, 0x00, 0x01, 0x19, 0x55]
+/// );
+/// ```
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ReadPropertyRequest {
+    pub object_identifier: ObjectIdentifier,
+    pub property_identifier: PropertyIdentifier,
+    pub property_array_index: Option<u32>,
+}
+
+impl ReadPropertyRequest {
+    pub fn new(
+        object_identifier: ObjectIdentifier,
+        property_identifier: PropertyIdentifier,
+    ) -> Self {
+        Self {
+            object_identifier,
+            property_identifier,
+            property_array_index: None,
+        }
+    }
+
+    /// Read a single element of an array property
+    pub fn array_index(mut self, index: u32) -> Self {
+        self.property_array_index = Some(index);
+        self
+    }
+
+    fn encode_data(&self) -> crate::error::Result<Vec<u8>> {
+        let mut data = Vec::new();
+        encode_reference(
+            &mut data,
+            self.object_identifier,
+            self.property_identifier,
+            self.property_array_index,
+        )?;
+        Ok(data)
+    }
+}
+
+impl Decode for ReadPropertyRequest {
+    fn decode<T: std::io::Read + Sized>(reader: &mut T) -> crate::error::Result<Self> {
+        let mut data = Vec::new();
+        reader.read_to_end(&mut data)?;
+        let mut reader = Reader::new(&data);
+        let (object_identifier, property_identifier, property_array_index) =
+            decode_reference(&mut reader)?;
+        Ok(Self {
+            object_identifier,
+            property_identifier,
+            property_array_index,
+        })
+    }
+}
+
+impl Encode for ReadPropertyRequest {
+    fn encode<T: std::io::Write + Sized>(&self, writer: &mut T) -> crate::error::Result<()> {
+        writer.write_all(&self.encode_data()?)?;
+        Ok(())
+    }
+
+    fn len(&self) -> usize {
+        self.encode_data().map_or(0, |data| data.len())
+    }
+}
+
+/// ReadProperty-ACK (15.5.1.3)
+///
+/// A value of several application tagged elements, like a whole array or a
+/// list, is an [`Array`](BACnetValue::Array) of them.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ReadPropertyAck {
+    pub object_identifier: ObjectIdentifier,
+    pub property_identifier: PropertyIdentifier,
+    pub property_array_index: Option<u32>,
+    pub property_value: BACnetValue,
+}
+
+impl ReadPropertyAck {
+    /// The answer to the request with the value read
+    pub fn new(request: &ReadPropertyRequest, property_value: BACnetValue) -> Self {
+        Self {
+            object_identifier: request.object_identifier,
+            property_identifier: request.property_identifier,
+            property_array_index: request.property_array_index,
+            property_value,
+        }
+    }
+
+    /// Whether this answers the request
+    pub fn answers(&self, request: &ReadPropertyRequest) -> bool {
+        self.object_identifier == request.object_identifier
+            && self.property_identifier == request.property_identifier
+            && self.property_array_index == request.property_array_index
+    }
+
+    fn encode_data(&self) -> crate::error::Result<Vec<u8>> {
+        let mut data = Vec::new();
+        encode_reference(
+            &mut data,
+            self.object_identifier,
+            self.property_identifier,
+            self.property_array_index,
+        )?;
+        encode_opening_tag(&mut data, 3);
+        encode_application(&mut data, &self.property_value)?;
+        encode_closing_tag(&mut data, 3);
+        Ok(data)
+    }
+}
+
+impl Decode for ReadPropertyAck {
+    fn decode<T: std::io::Read + Sized>(reader: &mut T) -> crate::error::Result<Self> {
+        let mut data = Vec::new();
+        reader.read_to_end(&mut data)?;
+        let mut reader = Reader::new(&data);
+        let (object_identifier, property_identifier, property_array_index) =
+            decode_reference(&mut reader)?;
+        reader.opening_tag(3)?;
+        let mut values = reader.values_until_closing_tag(3)?;
+        let property_value = match values.len() {
+            1 => values.remove(0),
+            _ => BACnetValue::Array(values),
+        };
+        Ok(Self {
+            object_identifier,
+            property_identifier,
+            property_array_index,
+            property_value,
+        })
+    }
+}
+
+impl Encode for ReadPropertyAck {
+    fn encode<T: std::io::Write + Sized>(&self, writer: &mut T) -> crate::error::Result<()> {
+        writer.write_all(&self.encode_data()?)?;
+        Ok(())
+    }
+
+    fn len(&self) -> usize {
+        self.encode_data().map_or(0, |data| data.len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::application::ObjectType;
+
+    #[test]
+    fn test_read_property() {
+        let object = ObjectIdentifier::new(ObjectType::AnalogInput, 1);
+        let data = hex::decode("0c000000011955").unwrap();
+        let request = ReadPropertyRequest::decode_slice(&data).unwrap();
+        assert_eq!(
+            request,
+            ReadPropertyRequest::new(object, PropertyIdentifier::PresentValue)
+        );
+        assert_eq!(request.len(), data.len());
+        assert_eq!(request.encode_vec().unwrap(), data);
+
+        let request =
+            ReadPropertyRequest::new(object, PropertyIdentifier::PriorityArray).array_index(16);
+        let data = request.encode_vec().unwrap();
+        assert_eq!(hex::encode(&data), "0c0000000119572910");
+        assert_eq!(ReadPropertyRequest::decode_slice(&data).unwrap(), request);
+
+        // Missing property identifier, reserved property
+        assert!(ReadPropertyRequest::decode_slice(&data[..5]).is_err());
+        let request = ReadPropertyRequest::decode_slice(&hex::decode("0c000000011912").unwrap());
+        assert_eq!(
+            request.unwrap().property_identifier,
+            PropertyIdentifier::Reserved(18)
+        );
+
+        // Proprietary property
+        let request = ReadPropertyRequest::decode_slice(&hex::decode("0c000000011a0200").unwrap());
+        assert_eq!(
+            request.unwrap().property_identifier,
+            PropertyIdentifier::Proprietary(512)
+        );
+    }
+
+    #[test]
+    fn test_read_property_ack() {
+        let object = ObjectIdentifier::new(ObjectType::AnalogInput, 1);
+        let request = ReadPropertyRequest::new(object, PropertyIdentifier::PresentValue);
+        let data = hex::decode("0c0000000119553e4441ac00003f").unwrap();
+        let ack = ReadPropertyAck::decode_slice(&data).unwrap();
+        assert_eq!(ack, ReadPropertyAck::new(&request, BACnetValue::Real(21.5)));
+        assert!(ack.answers(&request));
+        assert!(!ack.answers(&request.array_index(1)));
+        assert_eq!(ack.len(), data.len());
+        assert_eq!(ack.encode_vec().unwrap(), data);
+
+        // Several elements are an array
+        let data = hex::decode("0c0000000119553e2101210221023f").unwrap();
+        let ack = ReadPropertyAck::decode_slice(&data).unwrap();
+        assert_eq!(
+            ack.property_value,
+            BACnetValue::Array(vec![
+                BACnetValue::Unsigned(1),
+                BACnetValue::Unsigned(2),
+                BACnetValue::Unsigned(2)
+            ])
+        );
+        assert_eq!(ack.encode_vec().unwrap(), data);
+
+        // Missing closing tag
+        assert!(ReadPropertyAck::decode_slice(&data[..data.len() - 1]).is_err());
+    }
+}
@@ -0,0 +1,391 @@
+use crate::application::{BACnetError, BACnetValue, ObjectIdentifier, PropertyIdentifier};
+use crate::encoding::*;
+use crate::error::{Error, ServiceError};
+use crate::{Decode, Encode};
+
+use num_traits::FromPrimitive;
+use tracing::trace;
+
+/// BACnetPropertyReference (Clause 21)
+///
+/// In a ReadPropertyMultiple-Request the property can also be
+/// [`All`](PropertyIdentifier::All), [`Required`](PropertyIdentifier::Required)
+/// or [`Optional`](PropertyIdentifier::Optional), which read every property
+/// of the object in that group.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PropertyReference {
+    pub property_identifier: PropertyIdentifier,
+    pub property_array_index: Option<u32>,
+}
+
+impl PropertyReference {
+    pub fn new(property_identifier: PropertyIdentifier) -> Self {
+        Self {
+            property_identifier,
+            property_array_index: None,
+        }
+    }
+
+    /// Reference a single element of an array property
+    pub fn array_index(mut self, index: u32) -> Self {
+        self.property_array_index = Some(index);
+        self
+    }
+}
+
+impl From<PropertyIdentifier> for PropertyReference {
+    fn from(property_identifier: PropertyIdentifier) -> Self {
+        Self::new(property_identifier)
+    }
+}
+
+/// ReadAccessSpecification (Clause 21), the properties to read of an object
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ReadAccessSpecification {
+    pub object_identifier: ObjectIdentifier,
+    pub list_of_property_references: Vec<PropertyReference>,
+}
+
+impl ReadAccessSpecification {
+    pub fn new<P: Into<PropertyReference>>(
+        object_identifier: ObjectIdentifier,
+        properties: impl IntoIterator<Item = P>,
+    ) -> Self {
+        Self {
+            object_identifier,
+            list_of_property_references: properties.into_iter().map(Into::into).collect(),
+        }
+    }
+
+    /// Read all properties of the object
+    pub fn all(object_identifier: ObjectIdentifier) -> Self {
+        Self::new(object_identifier, [PropertyIdentifier::All])
+    }
+
+    /// Read the required properties of the object
+    pub fn required(object_identifier: ObjectIdentifier) -> Self {
+        Self::new(object_identifier, [PropertyIdentifier::Required])
+    }
+
+    /// Read the optional properties the object supports
+    pub fn optional(object_identifier: ObjectIdentifier) -> Self {
+        Self::new(object_identifier, [PropertyIdentifier::Optional])
+    }
+}
+
+/// ReadPropertyMultiple-Request (15.7.1.1)
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ReadPropertyMultipleRequest {
+    pub list_of_read_access_specs: Vec<ReadAccessSpecification>,
+}
+
+impl ReadPropertyMultipleRequest {
+    pub fn new(list_of_read_access_specs: Vec<ReadAccessSpecification>) -> Self {
+        Self {
+            list_of_read_access_specs,
+        }
+    }
+
+    fn encode_data(&self) -> crate::error::Result<Vec<u8>> {
+        let mut data = Vec::new();
+        for spec in &self.list_of_read_access_specs {
+            encode_context_object_identifier(&mut data, 0, spec.object_identifier)?;
+            encode_opening_tag(&mut data, 1);
+            for reference in &spec.list_of_property_references {
+                encode_context_enumerated(&mut data, 0, reference.property_identifier.number());
+                if let Some(index) = reference.property_array_index {
+                    encode_context_unsigned(&mut data, 1, index);
+                }
+            }
+            encode_closing_tag(&mut data, 1);
+        }
+        Ok(data)
+    }
+}
+
+impl Decode for ReadPropertyMultipleRequest {
+    fn decode<T: std::io::Read + Sized>(reader: &mut T) -> crate::error::Result<Self> {
+        let mut data = Vec::new();
+        reader.read_to_end(&mut data)?;
+        let mut reader = Reader::new(&data);
+        let invalid = |msg| Error::from(ServiceError::Invalid(msg));
+        if reader.is_empty() {
+            return Err(invalid("Missing read access specification"));
+        }
+        let mut list_of_read_access_specs = Vec::new();
+        while !reader.is_empty() {
+            let object_identifier = reader.context_object_identifier(0)?;
+            reader.opening_tag(1)?;
+            let mut list_of_property_references = Vec::new();
+            while !reader.is_closing_tag(1) {
+                let property_identifier =
+                    PropertyIdentifier::from_u32(reader.context_enumerated(0)?)
+                        .ok_or_else(|| invalid("Property identifier out of range"))?;
+                let property_array_index = reader.optional_context_unsigned(1)?;
+                list_of_property_references.push(PropertyReference {
+                    property_identifier,
+                    property_array_index,
+                });
+            }
+            reader.closing_tag(1)?;
+            if list_of_property_references.is_empty() {
+                return Err(invalid("Missing property reference"));
+            }
+            list_of_read_access_specs.push(ReadAccessSpecification {
+                object_identifier,
+                list_of_property_references,
+            });
+        }
+        Ok(Self {
+            list_of_read_access_specs,
+        })
+    }
+}
+
+impl Encode for ReadPropertyMultipleRequest {
+    fn encode<T: std::io::Write + Sized>(&self, writer: &mut T) -> crate::error::Result<()> {
+        writer.write_all(&self.encode_data()?)?;
+        Ok(())
+    }
+
+    fn len(&self) -> usize {
+        self.encode_data().map_or(0, |data| data.len())
+    }
+}
+
+/// The value read of a property, or why it could not be read
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ReadResult {
+    pub property_identifier: PropertyIdentifier,
+    pub property_array_index: Option<u32>,
+    pub read_result: Result<BACnetValue, BACnetError>,
+}
+
+/// ReadAccessResult (Clause 21), the results of reading an object
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ReadAccessResult {
+    pub object_identifier: ObjectIdentifier,
+    pub list_of_results: Vec<ReadResult>,
+}
+
+/// ReadPropertyMultiple-ACK (15.7.1.3)
+///
+/// The results of an `All`, `Required` or `Optional` reference list every
+/// property read in its place.
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ReadPropertyMultipleAck {
+    pub list_of_read_access_results: Vec<ReadAccessResult>,
+}
+
+impl ReadPropertyMultipleAck {
+    /// The results of all objects with the identifier of their object
+    pub fn results(&self) -> impl Iterator<Item = (ObjectIdentifier, &ReadResult)> {
+        self.list_of_read_access_results.iter().flat_map(|access| {
+            access
+                .list_of_results
+                .iter()
+                .map(move |result| (access.object_identifier, result))
+        })
+    }
+
+    /// The result of reading the property of the object
+    pub fn get(
+        &self,
+        object_identifier: ObjectIdentifier,
+        property_identifier: PropertyIdentifier,
+    ) -> Option<&Result<BACnetValue, BACnetError>> {
+        self.results()
+            .find(|(object, result)| {
+                *object == object_identifier && result.property_identifier == property_identifier
+            })
+            .map(|(_, result)| &result.read_result)
+    }
+
+    fn encode_data(&self) -> crate::error::Result<Vec<u8>> {
+        let mut data = Vec::new();
+        for access in &self.list_of_read_access_results {
+            encode_context_object_identifier(&mut data, 0, access.object_identifier)?;
+            encode_opening_tag(&mut data, 1);
+            for result in &access.list_of_results {
+                encode_context_enumerated(&mut data, 2, result.property_identifier.number());
+                if let Some(index) = result.property_array_index {
+                    encode_context_unsigned(&mut data, 3, index);
+                }
+                match &result.read_result {
+                    Ok(value) => {
+                        encode_opening_tag(&mut data, 4);
+                        encode_application(&mut data, value)?;
+                        encode_closing_tag(&mut data, 4);
+                    }
+                    Err(error) => {
+                        encode_opening_tag(&mut data, 5);
+                        error.encode(&mut data)?;
+                        encode_closing_tag(&mut data, 5);
+                    }
+                }
+            }
+            encode_closing_tag(&mut data, 1);
+        }
+        Ok(data)
+    }
+}
+
+impl Decode for ReadPropertyMultipleAck {
+    /// Results of properties unknown to this crate are skipped
+    fn decode<T: std::io::Read + Sized>(reader: &mut T) -> crate::error::Result<Self> {
+        let mut data = Vec::new();
+        reader.read_to_end(&mut data)?;
+        let mut reader = Reader::new(&data);
+        let mut list_of_read_access_results = Vec::new();
+        while !reader.is_empty() {
+            let object_identifier = reader.context_object_identifier(0)?;
+            reader.opening_tag(1)?;
+            let mut list_of_results = Vec::new();
+            while !reader.is_closing_tag(1) {
+                let property = reader.context_enumerated(2)?;
+                let property_array_index = reader.optional_context_unsigned(3)?;
+                let read_result = if reader.is_opening_tag(4) {
+                    reader.opening_tag(4)?;
+                    let mut values = reader.values_until_closing_tag(4)?;
+                    Ok(match values.len() {
+                        1 => values.remove(0),
+                        _ => BACnetValue::Array(values),
+                    })
+                } else {
+                    reader.opening_tag(5)?;
+                    let error = BACnetError::decode_slice(reader.remaining())?;
+                    reader.application_value()?;
+                    reader.application_value()?;
+                    reader.closing_tag(5)?;
+                    Err(error)
+                };
+                match PropertyIdentifier::from_u32(property) {
+                    Some(property_identifier) => list_of_results.push(ReadResult {
+                        property_identifier,
+                        property_array_index,
+                        read_result,
+                    }),
+                    None => trace!("Skipping result of unknown property {}", property),
+                }
+            }
+            reader.closing_tag(1)?;
+            list_of_read_access_results.push(ReadAccessResult {
+                object_identifier,
+                list_of_results,
+            });
+        }
+        Ok(Self {
+            list_of_read_access_results,
+        })
+    }
+}
+
+impl Encode for ReadPropertyMultipleAck {
+    fn encode<T: std::io::Write + Sized>(&self, writer: &mut T) -> crate::error::Result<()> {
+        writer.write_all(&self.encode_data()?)?;
+        Ok(())
+    }
+
+    fn len(&self) -> usize {
+        self.encode_data().map_or(0, |data| data.len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::application::{ErrorCode, ObjectType};
+
+    #[test]
+    fn test_read_property_multiple() {
+        let object = ObjectIdentifier::new(ObjectType::AnalogInput, 16);
+        let data = hex::decode("0c000000101e095509671f0c020000081e09081f").unwrap();
+        let request = ReadPropertyMultipleRequest::decode_slice(&data).unwrap();
+        assert_eq!(
+            request,
+            ReadPropertyMultipleRequest::new(vec![
+                ReadAccessSpecification::new(
+                    object,
+                    [
+                        PropertyIdentifier::PresentValue,
+                        PropertyIdentifier::Reliability
+                    ]
+                ),
+                ReadAccessSpecification::all(ObjectIdentifier::new(ObjectType::Device, 8)),
+            ])
+        );
+        assert_eq!(request.len(), data.len());
+        assert_eq!(request.encode_vec().unwrap(), data);
+
+        let request = ReadPropertyMultipleRequest::new(vec![ReadAccessSpecification::new(
+            object,
+            [PropertyReference::new(PropertyIdentifier::PriorityArray).array_index(8)],
+        )]);
+        let data = request.encode_vec().unwrap();
+        assert_eq!(hex::encode(&data), "0c000000101e095719081f");
+        assert_eq!(
+            ReadPropertyMultipleRequest::decode_slice(&data).unwrap(),
+            request
+        );
+
+        // Nothing to read
+        assert!(ReadPropertyMultipleRequest::decode_slice(&[]).is_err());
+        assert!(ReadPropertyMultipleRequest::decode_slice(&data[..6]).is_err());
+        assert!(
+            ReadPropertyMultipleRequest::decode_slice(&hex::decode("0c000000101e1f").unwrap())
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_read_property_multiple_ack() {
+        let object = ObjectIdentifier::new(ObjectType::AnalogInput, 16);
+        let data =
+            hex::decode("0c000000101e29554e4441ac00004f29675e910291205f29124e21014f1f").unwrap();
+        let ack = ReadPropertyMultipleAck::decode_slice(&data).unwrap();
+        assert_eq!(
+            ack,
+            ReadPropertyMultipleAck {
+                list_of_read_access_results: vec![ReadAccessResult {
+                    object_identifier: object,
+                    list_of_results: vec![
+                        ReadResult {
+                            property_identifier: PropertyIdentifier::PresentValue,
+                            property_array_index: None,
+                            read_result: Ok(BACnetValue::Real(21.5)),
+                        },
+                        ReadResult {
+                            property_identifier: PropertyIdentifier::Reliability,
+                            property_array_index: None,
+                            read_result: Err(BACnetError::property(ErrorCode::UnknownProperty)),
+                        },
+                        ReadResult {
+                            property_identifier: PropertyIdentifier::Reserved(18),
+                            property_array_index: None,
+                            read_result: Ok(BACnetValue::Unsigned(1)),
+                        },
+                    ],
+                }],
+            }
+        );
+        assert_eq!(
+            ack.get(object, PropertyIdentifier::PresentValue),
+            Some(&Ok(BACnetValue::Real(21.5)))
+        );
+        assert_eq!(ack.get(object, PropertyIdentifier::ObjectName), None);
+        assert_eq!(ack.results().count(), 3);
+
+        let encoded = ack.encode_vec().unwrap();
+        assert_eq!(ack.len(), encoded.len());
+        assert_eq!(encoded, data);
+
+        // Missing closing tag
+        assert!(ReadPropertyMultipleAck::decode_slice(&data[..data.len() - 1]).is_err());
+    }
+}
@@ -0,0 +1,206 @@
+use crate::application::{BACnetDateTime, BACnetValue, ObjectIdentifier};
+use crate::encoding::*;
+use crate::error::{Error, ServiceError};
+use crate::{Decode, Encode};
+
+use std::convert::TryFrom;
+
+/// Range of items requested by ReadRange (15.8.1.1.4)
+///
+/// A positive `count` selects items following the reference, a negative
+/// `count` items preceding it, the reference itself included.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Range {
+    ByPosition {
+        reference_index: u32,
+        count: i16,
+    },
+    BySequenceNumber {
+        reference_sequence_number: u32,
+        count: i16,
+    },
+    ByTime {
+        reference_time: BACnetDateTime,
+        count: i16,
+    },
+}
+
+/// BACnetResultFlags (15.8.1.3.4)
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub struct ResultFlags {
+    pub first_item: bool,
+    pub last_item: bool,
+    pub more_items: bool,
+}
+
+/// ReadRange-Request (15.8.1.1)
+///
+/// Without a range all items of the list are requested. Properties are
+/// identified by their number, which may be one this crate does not know.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ReadRangeRequest {
+    pub object_identifier: ObjectIdentifier,
+    pub property_identifier: u32,
+    pub property_array_index: Option<u32>,
+    pub range: Option<Range>,
+}
+
+impl ReadRangeRequest {
+    fn encode_data(&self) -> crate::error::Result<Vec<u8>> {
+        let mut data = Vec::new();
+        encode_context_object_identifier(&mut data, 0, self.object_identifier)?;
+        encode_context_enumerated(&mut data, 1, self.property_identifier);
+        if let Some(index) = self.property_array_index {
+            encode_context_unsigned(&mut data, 2, index);
+        }
+        let (tag, count) = match &self.range {
+            None => return Ok(data),
+            Some(Range::ByPosition {
+                reference_index,
+                count,
+            }) => {
+                encode_opening_tag(&mut data, 3);
+                encode_unsigned(&mut data, *reference_index);
+                (3, count)
+            }
+            Some(Range::BySequenceNumber {
+                reference_sequence_number,
+                count,
+            }) => {
+                encode_opening_tag(&mut data, 6);
+                encode_unsigned(&mut data, *reference_sequence_number);
+                (6, count)
+            }
+            Some(Range::ByTime {
+                reference_time,
+                count,
+            }) => {
+                encode_opening_tag(&mut data, 7);
+                reference_time.encode(&mut data);
+                (7, count)
+            }
+        };
+        encode_signed(&mut data, *count as i32);
+        encode_closing_tag(&mut data, tag);
+        Ok(data)
+    }
+}
+
+fn invalid_range() -> Error {
+    Error::from(ServiceError::Invalid("Invalid range"))
+}
+
+/// Read the count closing a range
+fn decode_count(reader: &mut Reader, tag: u8) -> crate::error::Result<i16> {
+    let count = match reader.application_value()? {
+        BACnetValue::Signed(count) if i16::try_from(count).is_ok() => count as i16,
+        _ => return Err(invalid_range()),
+    };
+    reader.closing_tag(tag)?;
+    Ok(count)
+}
+
+fn decode_range(reader: &mut Reader) -> crate::error::Result<Option<Range>> {
+    let tag = match [3, 6, 7].iter().find(|tag| reader.is_opening_tag(**tag)) {
+        Some(tag) => *tag,
+        None if reader.is_empty() => return Ok(None),
+        None => return Err(invalid_range()),
+    };
+    reader.opening_tag(tag)?;
+    let range = match (tag, reader.application_value()?) {
+        (3, BACnetValue::Unsigned(reference_index)) => Range::ByPosition {
+            reference_index,
+            count: decode_count(reader, tag)?,
+        },
+        (6, BACnetValue::Unsigned(reference_sequence_number)) => Range::BySequenceNumber {
+            reference_sequence_number,
+            count: decode_count(reader, tag)?,
+        },
+        (7, BACnetValue::Date(date)) => match reader.application_value()? {
+            BACnetValue::Time(time) => Range::ByTime {
+                reference_time: BACnetDateTime::new(date, time),
+                count: decode_count(reader, tag)?,
+            },
+            _ => return Err(invalid_range()),
+        },
+        _ => return Err(invalid_range()),
+    };
+    Ok(Some(range))
+}
+
+impl Decode for ReadRangeRequest {
+    fn decode<T: std::io::Read + Sized>(reader: &mut T) -> crate::error::Result<Self> {
+        let mut data = Vec::new();
+        reader.read_to_end(&mut data)?;
+        let mut reader = Reader::new(&data);
+        let object_identifier = reader.context_object_identifier(0)?;
+        let property_identifier = reader.context_enumerated(1)?;
+        let property_array_index = reader.optional_context_unsigned(2)?;
+        let range = decode_range(&mut reader)?;
+        Ok(Self {
+            object_identifier,
+            property_identifier,
+            property_array_index,
+            range,
+        })
+    }
+}
+
+impl Encode for ReadRangeRequest {
+    fn encode<T: std::io::Write + Sized>(&self, writer: &mut T) -> crate::error::Result<()> {
+        writer.write_all(&self.encode_data()?)?;
+        Ok(())
+    }
+
+    fn len(&self) -> usize {
+        self.encode_data().map_or(0, |data| data.len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::application::{BACnetDate, BACnetTime, ObjectType, PropertyIdentifier};
+
+    #[test]
+    fn test_read_range_request() {
+        let object = ObjectIdentifier::new(ObjectType::TrendLog, 1);
+        let mut request = ReadRangeRequest {
+            object_identifier: object,
+            property_identifier: PropertyIdentifier::LogBuffer.number(),
+            property_array_index: None,
+            range: Some(Range::BySequenceNumber {
+                reference_sequence_number: 5,
+                count: -2,
+            }),
+        };
+        let data = hex::decode("0c0500000119836e210531fe6f").unwrap();
+        assert_eq!(request.encode_vec().unwrap(), data);
+        assert_eq!(ReadRangeRequest::decode_slice(&data).unwrap(), request);
+
+        // A request for all items has no range
+        assert_eq!(
+            ReadRangeRequest::decode_slice(&data[..7]).unwrap(),
+            ReadRangeRequest {
+                range: None,
+                ..request.clone()
+            }
+        );
+
+        request.range = Some(Range::ByTime {
+            reference_time: BACnetDateTime::new(
+                BACnetDate::new(2021, 1, 25),
+                BACnetTime::new(12, 0, 0, 0),
+            ),
+            count: 10,
+        });
+        let data = request.encode_vec().unwrap();
+        assert_eq!(ReadRangeRequest::decode_slice(&data).unwrap(), request);
+
+        // The count is a signed value
+        let data = hex::decode("0c0500000119833e2105210a3f").unwrap();
+        assert!(ReadRangeRequest::decode_slice(&data).is_err());
+    }
+}
@@ -0,0 +1,130 @@
+use crate::encoding::*;
+use crate::error::{Error, ServiceError};
+use crate::{Decode, Encode};
+
+use num_derive::{FromPrimitive, ToPrimitive};
+use num_traits::FromPrimitive;
+
+/// reinitializedStateOfDevice parameter of ReinitializeDevice (16.4.1.1.1)
+///
+/// A backup (19.1.2) is started with [`StartBackup`](Self::StartBackup), the
+/// configuration files of the device are read and the backup is ended with
+/// [`EndBackup`](Self::EndBackup). A restore (19.1.3) writes them between
+/// [`StartRestore`](Self::StartRestore) and [`EndRestore`](Self::EndRestore),
+/// or gives up with [`AbortRestore`](Self::AbortRestore).
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash, FromPrimitive, ToPrimitive)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ReinitializedState {
+    Coldstart = 0,
+    Warmstart = 1,
+    StartBackup = 2,
+    EndBackup = 3,
+    StartRestore = 4,
+    EndRestore = 5,
+    AbortRestore = 6,
+    ActivateChanges = 7,
+}
+
+/// ReinitializeDevice-Request (16.4.1)
+///
+/// ```
+/// use bacnet::application::{ReinitializeDeviceRequest, ReinitializedState};
+/// use bacnet::Encode;
+///
+/// let request = ReinitializeDeviceRequest::new(ReinitializedState::StartBackup).password("ab");
+/// assert_eq!(
+///     request.encode_vec().unwrap(),
+///     [0x09, 0x02, 0x1b, 0x00, 0x61, 0x62]
+/// );
+/// ```
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ReinitializeDeviceRequest {
+    pub reinitialized_state: ReinitializedState,
+    /// Up to 20 characters, required by devices protected by a password
+    pub password: Option<String>,
+}
+
+impl ReinitializeDeviceRequest {
+    pub fn new(reinitialized_state: ReinitializedState) -> Self {
+        Self {
+            reinitialized_state,
+            password: None,
+        }
+    }
+
+    pub fn password<P: Into<String>>(mut self, password: P) -> Self {
+        self.password = Some(password.into());
+        self
+    }
+
+    fn encode_data(&self) -> Vec<u8> {
+        let mut data = Vec::new();
+        encode_context_enumerated(&mut data, 0, self.reinitialized_state as u32);
+        if let Some(password) = &self.password {
+            encode_context_character_string(&mut data, 1, password);
+        }
+        data
+    }
+}
+
+impl Decode for ReinitializeDeviceRequest {
+    fn decode<T: std::io::Read + Sized>(reader: &mut T) -> crate::error::Result<Self> {
+        let mut data = Vec::new();
+        reader.read_to_end(&mut data)?;
+        let mut reader = Reader::new(&data);
+        let reinitialized_state = ReinitializedState::from_u32(reader.context_enumerated(0)?)
+            .ok_or_else(|| Error::from(ServiceError::Invalid("Invalid reinitialized state")))?;
+        let password = match reader.is_context_tag(1) {
+            true => Some(reader.context_character_string(1)?),
+            false => None,
+        };
+        Ok(Self {
+            reinitialized_state,
+            password,
+        })
+    }
+}
+
+impl Encode for ReinitializeDeviceRequest {
+    fn encode<T: std::io::Write + Sized>(&self, writer: &mut T) -> crate::error::Result<()> {
+        writer.write_all(&self.encode_data())?;
+        Ok(())
+    }
+
+    fn len(&self) -> usize {
+        self.encode_data().len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reinitialize_device() {
+        let data = hex::decode("09011d09004162436445664768").unwrap();
+        let request = ReinitializeDeviceRequest::decode_slice(&data).unwrap();
+        assert_eq!(
+            request,
+            ReinitializeDeviceRequest {
+                reinitialized_state: ReinitializedState::Warmstart,
+                password: Some("AbCdEfGh".into()),
+            }
+        );
+        assert_eq!(request.encode_vec().unwrap(), data);
+        assert_eq!(
+            request,
+            ReinitializeDeviceRequest::new(ReinitializedState::Warmstart).password("AbCdEfGh")
+        );
+        assert!(ReinitializeDeviceRequest::decode_slice(&[0x09, 0x08]).is_err());
+
+        let request = ReinitializeDeviceRequest::new(ReinitializedState::AbortRestore);
+        let data = request.encode_vec().unwrap();
+        assert_eq!(data, [0x09, 0x06]);
+        assert_eq!(
+            ReinitializeDeviceRequest::decode_slice(&data).unwrap(),
+            request
+        );
+    }
+}
@@ -0,0 +1,229 @@
+use crate::application::{BACnetValue, ObjectIdentifier};
+use crate::encoding::*;
+use crate::error::{Error, ServiceError};
+use crate::{Decode, Encode};
+
+/// SubscribeCOV-Request (13.14.1)
+///
+/// Without `issue_confirmed_notifications` and `lifetime` the subscription
+/// is cancelled.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SubscribeCov {
+    pub subscriber_process_identifier: u32,
+    pub monitored_object_identifier: ObjectIdentifier,
+    pub issue_confirmed_notifications: Option<bool>,
+    /// Seconds until the subscription ends, 0 for indefinite subscriptions
+    pub lifetime: Option<u32>,
+}
+
+impl SubscribeCov {
+    pub fn is_cancellation(&self) -> bool {
+        self.issue_confirmed_notifications.is_none() && self.lifetime.is_none()
+    }
+
+    fn encode_data(&self) -> crate::error::Result<Vec<u8>> {
+        let mut data = Vec::new();
+        encode_context_unsigned(&mut data, 0, self.subscriber_process_identifier);
+        encode_context_object_identifier(&mut data, 1, self.monitored_object_identifier)?;
+        if let Some(confirmed) = self.issue_confirmed_notifications {
+            encode_context_boolean(&mut data, 2, confirmed);
+        }
+        if let Some(lifetime) = self.lifetime {
+            encode_context_unsigned(&mut data, 3, lifetime);
+        }
+        Ok(data)
+    }
+}
+
+/// Read the parameters SubscribeCOV and SubscribeCOVProperty share
+fn decode_subscription(reader: &mut Reader) -> crate::error::Result<SubscribeCov> {
+    let subscriber_process_identifier = reader.context_unsigned(0)?;
+    let monitored_object_identifier = reader.context_object_identifier(1)?;
+    let issue_confirmed_notifications = match reader.is_context_tag(2) {
+        true => Some(reader.context_boolean(2)?),
+        false => None,
+    };
+    let lifetime = reader.optional_context_unsigned(3)?;
+    Ok(SubscribeCov {
+        subscriber_process_identifier,
+        monitored_object_identifier,
+        issue_confirmed_notifications,
+        lifetime,
+    })
+}
+
+impl Decode for SubscribeCov {
+    fn decode<T: std::io::Read + Sized>(reader: &mut T) -> crate::error::Result<Self> {
+        let mut data = Vec::new();
+        reader.read_to_end(&mut data)?;
+        let mut reader = Reader::new(&data);
+        decode_subscription(&mut reader)
+    }
+}
+
+impl Encode for SubscribeCov {
+    fn encode<T: std::io::Write + Sized>(&self, writer: &mut T) -> crate::error::Result<()> {
+        writer.write_all(&self.encode_data()?)?;
+        Ok(())
+    }
+
+    fn len(&self) -> usize {
+        self.encode_data().map_or(0, |data| data.len())
+    }
+}
+
+/// SubscribeCOVProperty-Request (13.15.1)
+///
+/// Properties are identified by their number, which may be one this crate
+/// does not know.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SubscribeCovProperty {
+    pub subscription: SubscribeCov,
+    pub monitored_property_identifier: u32,
+    pub monitored_property_array_index: Option<u32>,
+    pub cov_increment: Option<f32>,
+}
+
+impl SubscribeCovProperty {
+    /// Subscribe to a whole property, notified on every change of value
+    pub fn new(subscription: SubscribeCov, property: u32) -> Self {
+        Self {
+            subscription,
+            monitored_property_identifier: property,
+            monitored_property_array_index: None,
+            cov_increment: None,
+        }
+    }
+
+    /// Subscribe to a single element of an array property
+    pub fn array_index(mut self, index: u32) -> Self {
+        self.monitored_property_array_index = Some(index);
+        self
+    }
+
+    /// Only notify about changes of a REAL value by at least `increment`,
+    /// instead of the COV_Increment of the object
+    pub fn cov_increment(mut self, increment: f32) -> Self {
+        self.cov_increment = Some(increment);
+        self
+    }
+
+    fn encode_data(&self) -> crate::error::Result<Vec<u8>> {
+        let mut data = self.subscription.encode_data()?;
+        encode_opening_tag(&mut data, 4);
+        encode_context_enumerated(&mut data, 0, self.monitored_property_identifier);
+        if let Some(index) = self.monitored_property_array_index {
+            encode_context_unsigned(&mut data, 1, index);
+        }
+        encode_closing_tag(&mut data, 4);
+        if let Some(increment) = self.cov_increment {
+            encode_context_real(&mut data, 5, increment);
+        }
+        Ok(data)
+    }
+}
+
+impl Decode for SubscribeCovProperty {
+    fn decode<T: std::io::Read + Sized>(reader: &mut T) -> crate::error::Result<Self> {
+        let mut data = Vec::new();
+        reader.read_to_end(&mut data)?;
+        let mut reader = Reader::new(&data);
+        let subscription = decode_subscription(&mut reader)?;
+        reader.opening_tag(4)?;
+        let monitored_property_identifier = reader.context_enumerated(0)?;
+        let monitored_property_array_index = reader.optional_context_unsigned(1)?;
+        reader.closing_tag(4)?;
+        let cov_increment = match reader.is_context_tag(5) {
+            // Real is application tag 4
+            true => match reader.context_value(5, 4)? {
+                BACnetValue::Real(increment) => Some(increment),
+                _ => return Err(Error::from(ServiceError::Invalid("Invalid COV increment"))),
+            },
+            false => None,
+        };
+        Ok(Self {
+            subscription,
+            monitored_property_identifier,
+            monitored_property_array_index,
+            cov_increment,
+        })
+    }
+}
+
+impl Encode for SubscribeCovProperty {
+    fn encode<T: std::io::Write + Sized>(&self, writer: &mut T) -> crate::error::Result<()> {
+        writer.write_all(&self.encode_data()?)?;
+        Ok(())
+    }
+
+    fn len(&self) -> usize {
+        self.encode_data().map_or(0, |data| data.len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::application::ObjectType;
+
+    fn subscription() -> SubscribeCov {
+        SubscribeCov {
+            subscriber_process_identifier: 18,
+            monitored_object_identifier: ObjectIdentifier::new(ObjectType::AnalogInput, 10),
+            issue_confirmed_notifications: Some(true),
+            lifetime: Some(0),
+        }
+    }
+
+    #[test]
+    fn test_subscribe_cov() {
+        let data = hex::decode("09121c0000000a29013900").unwrap();
+        let request = SubscribeCov::decode_slice(&data).unwrap();
+        assert_eq!(request, subscription());
+        assert!(!request.is_cancellation());
+        assert_eq!(request.encode_vec().unwrap(), data);
+
+        let cancellation = SubscribeCov::decode_slice(&data[..7]).unwrap();
+        assert!(cancellation.is_cancellation());
+    }
+
+    #[test]
+    fn test_subscribe_cov_property() {
+        let data = hex::decode("09121c0000000a2901393c4e09554f5c3f800000").unwrap();
+        let request = SubscribeCovProperty::decode_slice(&data).unwrap();
+        assert_eq!(
+            request,
+            SubscribeCovProperty {
+                subscription: SubscribeCov {
+                    lifetime: Some(60),
+                    ..subscription()
+                },
+                monitored_property_identifier: 85,
+                monitored_property_array_index: None,
+                cov_increment: Some(1.0),
+            }
+        );
+        assert_eq!(request.encode_vec().unwrap(), data);
+        assert!(SubscribeCovProperty::decode_slice(&data[..11]).is_err());
+
+        let subscription = SubscribeCov {
+            lifetime: Some(60),
+            ..subscription()
+        };
+        let request = SubscribeCovProperty::new(subscription, 87)
+            .array_index(8)
+            .cov_increment(0.5);
+        let data = request.encode_vec().unwrap();
+        assert_eq!(
+            hex::encode(&data),
+            "09121c0000000a2901393c4e095719084f5c3f000000"
+        );
+        assert_eq!(SubscribeCovProperty::decode_slice(&data).unwrap(), request);
+
+        // The increment is a REAL
+        let data = [&data[..17], &hex::decode("5a0100").unwrap()].concat();
+        assert!(SubscribeCovProperty::decode_slice(&data).is_err());
+    }
+}
@@ -0,0 +1,149 @@
+use crate::application::ObjectIdentifier;
+use crate::encoding::*;
+use crate::error::{Error, ServiceError};
+use crate::{Decode, Encode};
+
+/// messageClass of a text message (16.13.1.1.2)
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum MessageClass {
+    Numeric(u32),
+    Character(String),
+}
+
+/// Text message parameters (16.12, 16.13)
+///
+/// Confirmed and unconfirmed text messages share their parameters.
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TextMessage {
+    pub source_device: ObjectIdentifier,
+    pub message_class: Option<MessageClass>,
+    pub urgent: bool,
+    pub message: String,
+}
+
+impl TextMessage {
+    /// A message of normal priority without a class
+    pub fn new<M: Into<String>>(source_device: ObjectIdentifier, message: M) -> Self {
+        Self {
+            source_device,
+            message_class: None,
+            urgent: false,
+            message: message.into(),
+        }
+    }
+
+    pub fn class(mut self, message_class: MessageClass) -> Self {
+        self.message_class = Some(message_class);
+        self
+    }
+
+    pub fn urgent(mut self) -> Self {
+        self.urgent = true;
+        self
+    }
+
+    fn encode_data(&self) -> crate::error::Result<Vec<u8>> {
+        let mut data = Vec::new();
+        encode_context_object_identifier(&mut data, 0, self.source_device)?;
+        if let Some(class) = &self.message_class {
+            encode_opening_tag(&mut data, 1);
+            match class {
+                MessageClass::Numeric(n) => encode_context_unsigned(&mut data, 0, *n),
+                MessageClass::Character(s) => encode_context_character_string(&mut data, 1, s),
+            }
+            encode_closing_tag(&mut data, 1);
+        }
+        encode_context_enumerated(&mut data, 2, self.urgent as u32);
+        encode_context_character_string(&mut data, 3, &self.message);
+        Ok(data)
+    }
+}
+
+impl Decode for TextMessage {
+    fn decode<T: std::io::Read + Sized>(reader: &mut T) -> crate::error::Result<Self> {
+        let mut data = Vec::new();
+        reader.read_to_end(&mut data)?;
+        let mut reader = Reader::new(&data);
+        let source_device = reader.context_object_identifier(0)?;
+        let message_class = match reader.is_opening_tag(1) {
+            true => {
+                reader.opening_tag(1)?;
+                let class = match reader.is_context_tag(0) {
+                    true => MessageClass::Numeric(reader.context_unsigned(0)?),
+                    false => MessageClass::Character(reader.context_character_string(1)?),
+                };
+                reader.closing_tag(1)?;
+                Some(class)
+            }
+            false => None,
+        };
+        // messagePriority is normal (0) or urgent (1)
+        let urgent = match reader.context_enumerated(2)? {
+            0 => false,
+            1 => true,
+            _ => {
+                return Err(Error::from(ServiceError::Invalid(
+                    "Invalid message priority",
+                )))
+            }
+        };
+        let message = reader.context_character_string(3)?;
+        Ok(Self {
+            source_device,
+            message_class,
+            urgent,
+            message,
+        })
+    }
+}
+
+impl Encode for TextMessage {
+    fn encode<T: std::io::Write + Sized>(&self, writer: &mut T) -> crate::error::Result<()> {
+        writer.write_all(&self.encode_data()?)?;
+        Ok(())
+    }
+
+    fn len(&self) -> usize {
+        self.encode_data().map_or(0, |data| data.len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::application::ObjectType;
+
+    #[test]
+    fn test_text_message() {
+        let data =
+            hex::decode("0c020000051e09051f29003d10005048323030204973204f6e6c696e65").unwrap();
+        let message = TextMessage::decode_slice(&data).unwrap();
+        assert_eq!(
+            message,
+            TextMessage {
+                source_device: ObjectIdentifier::new(ObjectType::Device, 5),
+                message_class: Some(MessageClass::Numeric(5)),
+                urgent: false,
+                message: "PH200 Is Online".into(),
+            }
+        );
+        assert_eq!(message.encode_vec().unwrap(), data);
+
+        let device = ObjectIdentifier::new(ObjectType::Device, 5);
+        let message = TextMessage::new(device, "Hi")
+            .class(MessageClass::Character("Ops".into()))
+            .urgent();
+        let data = message.encode_vec().unwrap();
+        assert_eq!(hex::encode(&data), "0c020000051e1c004f70731f29013b004869");
+        assert_eq!(message.len(), data.len());
+        assert_eq!(TextMessage::decode_slice(&data).unwrap(), message);
+
+        // Invalid message priority, missing message
+        let data = hex::decode("0c0200000529023b004869").unwrap();
+        assert!(TextMessage::decode_slice(&data).is_err());
+        let data = hex::decode("0c020000052900").unwrap();
+        assert!(TextMessage::decode_slice(&data).is_err());
+    }
+}
@@ -0,0 +1,136 @@
+use crate::application::{BACnetDate, BACnetDateTime, BACnetTime, BACnetValue};
+use crate::encoding::*;
+use crate::error::{Error, ServiceError};
+use crate::{Decode, Encode};
+
+use std::time::{Duration, SystemTime};
+
+/// TimeSynchronization-Request (16.7) and UTCTimeSynchronization-Request
+/// (16.8)
+///
+/// Both services share their parameters, a TimeSynchronization carries the
+/// local time of the recipient and a UTCTimeSynchronization the time in UTC.
+///
+/// ```
+/// use bacnet::application::TimeSynchronization;
+/// use bacnet::Encode;
+/// use std::time::{Duration, UNIX_EPOCH};
+///
+/// let time = UNIX_EPOCH + Duration::from_secs(1_611_532_800);
+/// let request = TimeSynchronization::from(time);
+/// assert_eq!(request.date.day, 25);
+/// assert_eq!(
+///     request.encode_vec().unwrap(),
+///     [0xa4, 0x79, 0x01, 0x19, 0x01, 0xb4, 0x00, 0x00, 0x00, 0x00]
+/// );
+/// // West of Greenwich the local time is behind UTC
+/// let local = TimeSynchronization::local(time, 300);
+/// assert_eq!(local.time.hour, 19);
+/// ```
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TimeSynchronization {
+    pub date: BACnetDate,
+    pub time: BACnetTime,
+}
+
+impl TimeSynchronization {
+    /// The current system time, for a UTCTimeSynchronization
+    pub fn now() -> Self {
+        SystemTime::now().into()
+    }
+
+    /// The local time at `time` for a TimeSynchronization, with the UTC
+    /// offset in minutes as in the UTC_Offset property of the device, which
+    /// is positive west of Greenwich
+    pub fn local(time: SystemTime, utc_offset: i16) -> Self {
+        let offset = Duration::from_secs(utc_offset.unsigned_abs() as u64 * 60);
+        let local = match utc_offset {
+            o if o > 0 => time.checked_sub(offset),
+            _ => time.checked_add(offset),
+        };
+        local.unwrap_or(time).into()
+    }
+
+    pub fn date_time(&self) -> BACnetDateTime {
+        BACnetDateTime::new(self.date, self.time)
+    }
+
+    fn encode_data(&self) -> Vec<u8> {
+        let mut data = Vec::new();
+        self.date_time().encode(&mut data);
+        data
+    }
+}
+
+impl From<BACnetDateTime> for TimeSynchronization {
+    fn from(time: BACnetDateTime) -> Self {
+        Self {
+            date: time.date,
+            time: time.time,
+        }
+    }
+}
+
+impl From<SystemTime> for TimeSynchronization {
+    fn from(time: SystemTime) -> Self {
+        BACnetDateTime::from(time).into()
+    }
+}
+
+impl Decode for TimeSynchronization {
+    fn decode<T: std::io::Read + Sized>(reader: &mut T) -> crate::error::Result<Self> {
+        let mut data = Vec::new();
+        reader.read_to_end(&mut data)?;
+        let mut reader = Reader::new(&data);
+        match (reader.application_value()?, reader.application_value()?) {
+            (BACnetValue::Date(date), BACnetValue::Time(time)) => Ok(Self { date, time }),
+            _ => Err(Error::from(ServiceError::Invalid(
+                "Expected a date and a time",
+            ))),
+        }
+    }
+}
+
+impl Encode for TimeSynchronization {
+    fn encode<T: std::io::Write + Sized>(&self, writer: &mut T) -> crate::error::Result<()> {
+        writer.write_all(&self.encode_data())?;
+        Ok(())
+    }
+
+    fn len(&self) -> usize {
+        self.encode_data().len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_time_synchronization() {
+        let data = hex::decode("a479011901b408000000").unwrap();
+        let request = TimeSynchronization::decode_slice(&data).unwrap();
+        let time = BACnetDateTime::new(BACnetDate::new(2021, 1, 25), BACnetTime::new(8, 0, 0, 0));
+        assert_eq!(request.date_time(), time);
+        assert_eq!(request, TimeSynchronization::from(time));
+        assert_eq!(request.len(), data.len());
+        assert_eq!(request.encode_vec().unwrap(), data);
+
+        let system_time = time.system_time().unwrap();
+        assert_eq!(TimeSynchronization::from(system_time), request);
+        // East of Greenwich the local time is ahead of UTC
+        let local = TimeSynchronization::local(system_time, -90);
+        assert_eq!(local.time, BACnetTime::new(9, 30, 0, 0));
+        let local = TimeSynchronization::local(system_time, 540);
+        assert_eq!(
+            local.date_time(),
+            BACnetDateTime::new(BACnetDate::new(2021, 1, 24), BACnetTime::new(23, 0, 0, 0))
+        );
+
+        // Time before the date, missing time
+        let data = hex::decode("b408000000a479011901").unwrap();
+        assert!(TimeSynchronization::decode_slice(&data).is_err());
+        assert!(TimeSynchronization::decode_slice(&data[5..]).is_err());
+    }
+}
@@ -0,0 +1,450 @@
+use crate::application::{BACnetError, BACnetValue};
+use crate::encoding::*;
+use crate::error::{Error, ServiceError};
+use crate::{Decode, Encode};
+
+use num_derive::{FromPrimitive, ToPrimitive};
+use num_traits::FromPrimitive;
+
+fn invalid(msg: &'static str) -> Error {
+    Error::from(ServiceError::Invalid(msg))
+}
+
+/// Read an application tagged Unsigned8 session identifier
+fn decode_session(reader: &mut Reader) -> crate::error::Result<u8> {
+    match reader.application_value()? {
+        BACnetValue::Unsigned(id) if id <= u8::MAX as u32 => Ok(id as u8),
+        _ => Err(invalid("Invalid VT session identifier")),
+    }
+}
+
+fn encode_session(buf: &mut Vec<u8>, id: u8) {
+    encode_unsigned(buf, id as u32);
+}
+
+/// BACnetVTClass (Clause 21)
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash, FromPrimitive, ToPrimitive)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum VtClass {
+    DefaultTerminal = 0,
+    AnsiX364 = 1,
+    DecVt52 = 2,
+    DecVt100 = 3,
+    DecVt220 = 4,
+    Hp70094 = 5,
+    Ibm3130 = 6,
+}
+
+/// VT-Open-Request (17.1)
+///
+/// ```
+/// use bacnet::application::{VtClass, VtOpenRequest};
+/// use bacnet::Encode;
+///
+/// let request = VtOpenRequest::new(VtClass::DecVt100, 5);
+/// assert_eq!(request.encode_vec().unwrap(), [0x91, 0x03, 0x21, 0x05]);
+/// ```
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct VtOpenRequest {
+    pub vt_class: VtClass,
+    /// The session identifier the client uses for the session
+    pub local_vt_session_identifier: u8,
+}
+
+impl VtOpenRequest {
+    pub fn new(vt_class: VtClass, local_vt_session_identifier: u8) -> Self {
+        Self {
+            vt_class,
+            local_vt_session_identifier,
+        }
+    }
+
+    fn encode_data(&self) -> Vec<u8> {
+        let mut data = Vec::new();
+        encode_enumerated(&mut data, self.vt_class as u32);
+        encode_session(&mut data, self.local_vt_session_identifier);
+        data
+    }
+}
+
+impl Decode for VtOpenRequest {
+    fn decode<T: std::io::Read + Sized>(reader: &mut T) -> crate::error::Result<Self> {
+        let mut data = Vec::new();
+        reader.read_to_end(&mut data)?;
+        let mut reader = Reader::new(&data);
+        let vt_class = match reader.application_value()? {
+            BACnetValue::Enumerated(class) => VtClass::from_u32(class),
+            _ => None,
+        }
+        .ok_or_else(|| invalid("Invalid VT class"))?;
+        let local_vt_session_identifier = decode_session(&mut reader)?;
+        Ok(Self {
+            vt_class,
+            local_vt_session_identifier,
+        })
+    }
+}
+
+impl Encode for VtOpenRequest {
+    fn encode<T: std::io::Write + Sized>(&self, writer: &mut T) -> crate::error::Result<()> {
+        writer.write_all(&self.encode_data())?;
+        Ok(())
+    }
+
+    fn len(&self) -> usize {
+        self.encode_data().len()
+    }
+}
+
+/// VT-Open-ACK (17.1.1.2)
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct VtOpenAck {
+    /// The session identifier the device uses for the session, to address it
+    /// in VT-Data and VT-Close requests
+    pub remote_vt_session_identifier: u8,
+}
+
+impl Decode for VtOpenAck {
+    fn decode<T: std::io::Read + Sized>(reader: &mut T) -> crate::error::Result<Self> {
+        let mut data = Vec::new();
+        reader.read_to_end(&mut data)?;
+        let remote_vt_session_identifier = decode_session(&mut Reader::new(&data))?;
+        Ok(Self {
+            remote_vt_session_identifier,
+        })
+    }
+}
+
+impl Encode for VtOpenAck {
+    fn encode<T: std::io::Write + Sized>(&self, writer: &mut T) -> crate::error::Result<()> {
+        let mut data = Vec::new();
+        encode_session(&mut data, self.remote_vt_session_identifier);
+        writer.write_all(&data)?;
+        Ok(())
+    }
+
+    fn len(&self) -> usize {
+        2
+    }
+}
+
+/// VT-Close-Request (17.2), answered with a Simple-ACK
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct VtCloseRequest {
+    pub list_of_remote_vt_session_identifiers: Vec<u8>,
+}
+
+impl VtCloseRequest {
+    pub fn new(list_of_remote_vt_session_identifiers: Vec<u8>) -> Self {
+        Self {
+            list_of_remote_vt_session_identifiers,
+        }
+    }
+
+    fn encode_data(&self) -> Vec<u8> {
+        let mut data = Vec::new();
+        self.list_of_remote_vt_session_identifiers
+            .iter()
+            .for_each(|id| encode_session(&mut data, *id));
+        data
+    }
+}
+
+impl Decode for VtCloseRequest {
+    fn decode<T: std::io::Read + Sized>(reader: &mut T) -> crate::error::Result<Self> {
+        let mut data = Vec::new();
+        reader.read_to_end(&mut data)?;
+        let mut reader = Reader::new(&data);
+        let mut list_of_remote_vt_session_identifiers = Vec::new();
+        while !reader.is_empty() {
+            list_of_remote_vt_session_identifiers.push(decode_session(&mut reader)?);
+        }
+        match list_of_remote_vt_session_identifiers.is_empty() {
+            true => Err(invalid("Expected a VT session identifier")),
+            false => Ok(Self {
+                list_of_remote_vt_session_identifiers,
+            }),
+        }
+    }
+}
+
+impl Encode for VtCloseRequest {
+    fn encode<T: std::io::Write + Sized>(&self, writer: &mut T) -> crate::error::Result<()> {
+        writer.write_all(&self.encode_data())?;
+        Ok(())
+    }
+
+    fn len(&self) -> usize {
+        self.encode_data().len()
+    }
+}
+
+/// VT-Close-Error (17.2.1.3), the parameters of the Error PDU answering a
+/// failed close
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct VtCloseError {
+    pub error: BACnetError,
+    /// The sessions of the request that were not closed
+    pub list_of_vt_session_identifiers: Option<Vec<u8>>,
+}
+
+impl VtCloseError {
+    fn encode_data(&self) -> Vec<u8> {
+        let mut data = Vec::new();
+        encode_opening_tag(&mut data, 0);
+        data.extend_from_slice(&self.error.encode_vec().unwrap_or_default());
+        encode_closing_tag(&mut data, 0);
+        if let Some(sessions) = &self.list_of_vt_session_identifiers {
+            encode_opening_tag(&mut data, 1);
+            sessions
+                .iter()
+                .for_each(|id| encode_session(&mut data, *id));
+            encode_closing_tag(&mut data, 1);
+        }
+        data
+    }
+}
+
+impl std::fmt::Display for VtCloseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.list_of_vt_session_identifiers {
+            Some(sessions) => write!(f, "{} closing sessions {:?}", self.error, sessions),
+            None => write!(f, "{}", self.error),
+        }
+    }
+}
+
+impl Decode for VtCloseError {
+    fn decode<T: std::io::Read + Sized>(reader: &mut T) -> crate::error::Result<Self> {
+        let mut data = Vec::new();
+        reader.read_to_end(&mut data)?;
+        let mut reader = Reader::new(&data);
+        reader.opening_tag(0)?;
+        let error = BACnetError::decode_slice(reader.raw_until_closing_tag(0)?)?;
+        let list_of_vt_session_identifiers = match reader.is_opening_tag(1) {
+            true => {
+                reader.opening_tag(1)?;
+                let mut sessions = Vec::new();
+                while !reader.is_closing_tag(1) {
+                    sessions.push(decode_session(&mut reader)?);
+                }
+                reader.closing_tag(1)?;
+                Some(sessions)
+            }
+            false => None,
+        };
+        Ok(Self {
+            error,
+            list_of_vt_session_identifiers,
+        })
+    }
+}
+
+impl Encode for VtCloseError {
+    fn encode<T: std::io::Write + Sized>(&self, writer: &mut T) -> crate::error::Result<()> {
+        writer.write_all(&self.encode_data())?;
+        Ok(())
+    }
+
+    fn len(&self) -> usize {
+        self.encode_data().len()
+    }
+}
+
+/// VT-Data-Request (17.3)
+///
+/// ```
+/// use bacnet::application::VtDataRequest;
+/// use bacnet::Encode;
+///
+/// let request = VtDataRequest::new(29, b"ls\r".to_vec());
+/// assert_eq!(
+///     request.encode_vec().unwrap(),
+///     [0x21, 0x1d, 0x63, 0x6c, 0x73, 0x0d, 0x21, 0x00]
+/// );
+/// ```
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct VtDataRequest {
+    /// The session identifier of the peer
+    pub vt_session_identifier: u8,
+    pub vt_new_data: Vec<u8>,
+    /// The vtDataFlag, encoded as Unsigned 0 or 1
+    pub vt_data_flag: bool,
+}
+
+impl VtDataRequest {
+    /// Send `data` on the session without the data flag
+    pub fn new(vt_session_identifier: u8, vt_new_data: Vec<u8>) -> Self {
+        Self {
+            vt_session_identifier,
+            vt_new_data,
+            vt_data_flag: false,
+        }
+    }
+
+    fn encode_data(&self) -> Vec<u8> {
+        let mut data = Vec::new();
+        encode_session(&mut data, self.vt_session_identifier);
+        encode_octet_string(&mut data, &self.vt_new_data);
+        encode_unsigned(&mut data, self.vt_data_flag as u32);
+        data
+    }
+}
+
+impl Decode for VtDataRequest {
+    fn decode<T: std::io::Read + Sized>(reader: &mut T) -> crate::error::Result<Self> {
+        let mut data = Vec::new();
+        reader.read_to_end(&mut data)?;
+        let mut reader = Reader::new(&data);
+        let vt_session_identifier = decode_session(&mut reader)?;
+        let vt_new_data = match reader.application_value()? {
+            BACnetValue::OctetString(data) => data,
+            _ => return Err(invalid("Expected VT data")),
+        };
+        let vt_data_flag = match reader.application_value()? {
+            BACnetValue::Unsigned(0) => false,
+            BACnetValue::Unsigned(1) => true,
+            _ => return Err(invalid("Invalid VT data flag")),
+        };
+        Ok(Self {
+            vt_session_identifier,
+            vt_new_data,
+            vt_data_flag,
+        })
+    }
+}
+
+impl Encode for VtDataRequest {
+    fn encode<T: std::io::Write + Sized>(&self, writer: &mut T) -> crate::error::Result<()> {
+        writer.write_all(&self.encode_data())?;
+        Ok(())
+    }
+
+    fn len(&self) -> usize {
+        self.encode_data().len()
+    }
+}
+
+/// VT-Data-ACK (17.3.1.2)
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct VtDataAck {
+    pub all_new_data_accepted: bool,
+    /// The number of octets accepted when not all data was accepted, the
+    /// rest is to be sent again
+    pub accepted_octet_count: Option<u32>,
+}
+
+impl VtDataAck {
+    fn encode_data(&self) -> Vec<u8> {
+        let mut data = Vec::new();
+        encode_context_boolean(&mut data, 0, self.all_new_data_accepted);
+        if let Some(count) = self.accepted_octet_count {
+            encode_context_unsigned(&mut data, 1, count);
+        }
+        data
+    }
+}
+
+impl Decode for VtDataAck {
+    fn decode<T: std::io::Read + Sized>(reader: &mut T) -> crate::error::Result<Self> {
+        let mut data = Vec::new();
+        reader.read_to_end(&mut data)?;
+        let mut reader = Reader::new(&data);
+        let all_new_data_accepted = reader.context_boolean(0)?;
+        let accepted_octet_count = reader.optional_context_unsigned(1)?;
+        Ok(Self {
+            all_new_data_accepted,
+            accepted_octet_count,
+        })
+    }
+}
+
+impl Encode for VtDataAck {
+    fn encode<T: std::io::Write + Sized>(&self, writer: &mut T) -> crate::error::Result<()> {
+        writer.write_all(&self.encode_data())?;
+        Ok(())
+    }
+
+    fn len(&self) -> usize {
+        self.encode_data().len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::application::{ErrorClass, ErrorCode};
+
+    #[test]
+    fn test_vt_open() {
+        let data = hex::decode("91002105").unwrap();
+        let request = VtOpenRequest::decode_slice(&data).unwrap();
+        assert_eq!(request, VtOpenRequest::new(VtClass::DefaultTerminal, 5));
+        assert_eq!(request.len(), data.len());
+        assert_eq!(request.encode_vec().unwrap(), data);
+
+        let ack = VtOpenAck::decode_slice(&[0x21, 0x1d]).unwrap();
+        assert_eq!(ack.remote_vt_session_identifier, 29);
+        assert_eq!(ack.encode_vec().unwrap(), [0x21, 0x1d]);
+        assert_eq!(ack.len(), 2);
+
+        // Unknown class, session identifier above 255
+        assert!(VtOpenRequest::decode_slice(&hex::decode("91072105").unwrap()).is_err());
+        assert!(VtOpenRequest::decode_slice(&hex::decode("9100220100").unwrap()).is_err());
+    }
+
+    #[test]
+    fn test_vt_close() {
+        let data = hex::decode("21092117").unwrap();
+        let request = VtCloseRequest::decode_slice(&data).unwrap();
+        assert_eq!(request, VtCloseRequest::new(vec![9, 23]));
+        assert_eq!(request.encode_vec().unwrap(), data);
+        assert!(VtCloseRequest::decode_slice(&[]).is_err());
+
+        let data = hex::decode("0e910691270f1e21171f").unwrap();
+        let error = VtCloseError::decode_slice(&data).unwrap();
+        assert_eq!(
+            error.error,
+            BACnetError::new(ErrorClass::VT, ErrorCode::VTSessionTerminationFailure)
+        );
+        assert_eq!(error.list_of_vt_session_identifiers, Some(vec![23]));
+        assert_eq!(error.len(), data.len());
+        assert_eq!(error.encode_vec().unwrap(), data);
+        assert!(VtCloseError::decode_slice(&data[..6])
+            .unwrap()
+            .list_of_vt_session_identifiers
+            .is_none());
+    }
+
+    #[test]
+    fn test_vt_data() {
+        let data = hex::decode("211d 63 6c730d 2101".replace(' ', "")).unwrap();
+        let request = VtDataRequest::decode_slice(&data).unwrap();
+        assert_eq!(request.vt_new_data, b"ls\r");
+        assert!(request.vt_data_flag);
+        assert_eq!(request.len(), data.len());
+        assert_eq!(request.encode_vec().unwrap(), data);
+        // Data flag 2
+        let mut data = data;
+        data[6] = 0x02;
+        assert!(VtDataRequest::decode_slice(&data).is_err());
+
+        let ack = VtDataAck::decode_slice(&[0x09, 0x00, 0x19, 0x02]).unwrap();
+        assert_eq!(
+            ack,
+            VtDataAck {
+                all_new_data_accepted: false,
+                accepted_octet_count: Some(2),
+            }
+        );
+        assert_eq!(ack.encode_vec().unwrap(), [0x09, 0x00, 0x19, 0x02]);
+        let ack = VtDataAck::decode_slice(&[0x09, 0x01]).unwrap();
+        assert!(ack.all_new_data_accepted && ack.accepted_octet_count.is_none());
+    }
+}
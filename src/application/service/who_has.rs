@@ -0,0 +1,171 @@
+use crate::application::{ObjectIdentifier, UnconfirmedServiceChoice, APDU};
+use crate::encoding::*;
+use crate::error::ServiceError;
+use crate::{Decode, Encode};
+
+/// Object searched by a Who-Has-Request (16.9)
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum WhoHasObject {
+    Identifier(ObjectIdentifier),
+    Name(String),
+}
+
+impl From<ObjectIdentifier> for WhoHasObject {
+    fn from(identifier: ObjectIdentifier) -> Self {
+        Self::Identifier(identifier)
+    }
+}
+
+impl From<&str> for WhoHasObject {
+    fn from(name: &str) -> Self {
+        Self::Name(name.into())
+    }
+}
+
+impl From<String> for WhoHasObject {
+    fn from(name: String) -> Self {
+        Self::Name(name)
+    }
+}
+
+/// Who-Has-Request parameters (16.9.1)
+///
+/// The devices serving the object answer with an I-Have, only those with an
+/// instance number within the (inclusive) limits if there are any. The
+/// limits are present together or not at all.
+///
+/// ```
+/// use bacnet::application::WhoHas;
+/// use bacnet::Encode;
+///
+/// let who_has = WhoHas::new("OATemp").range(3, 1000);
+/// assert!(who_has.matches(599));
+/// assert_eq!(
+///     who_has.encode_vec().unwrap(),
+///     [0x09, 0x03, 0x1a, 0x03, 0xe8, 0x3d, 0x07, 0x00, 0x4f, 0x41, 0x54, 0x65, 0x6d, 0x70]
+/// );
+/// ```
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct WhoHas {
+    pub low_limit: Option<u32>,
+    pub high_limit: Option<u32>,
+    pub object: WhoHasObject,
+}
+
+impl WhoHas {
+    /// Answered by all devices serving the object
+    pub fn new<O: Into<WhoHasObject>>(object: O) -> Self {
+        Self {
+            low_limit: None,
+            high_limit: None,
+            object: object.into(),
+        }
+    }
+
+    /// Only answered by the devices with an instance number from `low` to
+    /// `high`
+    pub fn range(mut self, low: u32, high: u32) -> Self {
+        self.low_limit = Some(low);
+        self.high_limit = Some(high);
+        self
+    }
+
+    /// Whether the device with the instance number answers, if it serves
+    /// the object
+    pub fn matches(&self, instance: u32) -> bool {
+        let low = self.low_limit.unwrap_or(0);
+        let high = self.high_limit.unwrap_or(u32::MAX);
+        (low..=high).contains(&instance)
+    }
+
+    /// The unconfirmed request, failing for an object identifier out of
+    /// range
+    pub fn to_apdu(&self) -> crate::error::Result<APDU> {
+        let data = self.encode_data()?;
+        Ok(APDU::unconfirmed_request(
+            UnconfirmedServiceChoice::WhoHas as u8,
+            data,
+        ))
+    }
+
+    fn encode_data(&self) -> crate::error::Result<Vec<u8>> {
+        let mut data = Vec::new();
+        if let (Some(low), Some(high)) = (self.low_limit, self.high_limit) {
+            encode_context_unsigned(&mut data, 0, low);
+            encode_context_unsigned(&mut data, 1, high);
+        }
+        match &self.object {
+            WhoHasObject::Identifier(identifier) => {
+                encode_context_object_identifier(&mut data, 2, *identifier)?
+            }
+            WhoHasObject::Name(name) => encode_context_character_string(&mut data, 3, name),
+        }
+        Ok(data)
+    }
+}
+
+impl Decode for WhoHas {
+    fn decode<T: std::io::Read + Sized>(reader: &mut T) -> crate::error::Result<Self> {
+        let mut data = Vec::new();
+        reader.read_to_end(&mut data)?;
+        let mut reader = Reader::new(&data);
+        let low_limit = reader.optional_context_unsigned(0)?;
+        let high_limit = reader.optional_context_unsigned(1)?;
+        if low_limit.is_some() != high_limit.is_some() {
+            return Err(ServiceError::Invalid("Incomplete device range").into());
+        }
+        let object = match reader.is_context_tag(2) {
+            true => WhoHasObject::Identifier(reader.context_object_identifier(2)?),
+            false => WhoHasObject::Name(reader.context_character_string(3)?),
+        };
+        Ok(Self {
+            low_limit,
+            high_limit,
+            object,
+        })
+    }
+}
+
+impl Encode for WhoHas {
+    fn encode<T: std::io::Write + Sized>(&self, writer: &mut T) -> crate::error::Result<()> {
+        writer.write_all(&self.encode_data()?)?;
+        Ok(())
+    }
+
+    fn len(&self) -> usize {
+        self.encode_data().map_or(0, |data| data.len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::application::ObjectType;
+
+    #[test]
+    fn test_who_has() {
+        let data = hex::decode("2c00000002").unwrap();
+        let who_has = WhoHas::decode_slice(&data).unwrap();
+        let object = ObjectIdentifier::new(ObjectType::AnalogInput, 2);
+        assert_eq!(who_has, WhoHas::new(object));
+        assert!(who_has.matches(4194303));
+        assert_eq!(who_has.len(), data.len());
+        assert_eq!(who_has.encode_vec().unwrap(), data);
+        assert_eq!(
+            who_has.to_apdu().unwrap().service_choice(),
+            Some(UnconfirmedServiceChoice::WhoHas as u8)
+        );
+
+        let data = hex::decode("09031a03e83d07004f4154656d70").unwrap();
+        let who_has = WhoHas::decode_slice(&data).unwrap();
+        assert_eq!(who_has.object, WhoHasObject::Name("OATemp".into()));
+        assert!(!who_has.matches(2) && !who_has.matches(1001));
+        assert_eq!(who_has.encode_vec().unwrap(), data);
+
+        // Incomplete range, missing object
+        assert!(WhoHas::decode_slice(&data[2..]).is_err());
+        assert!(WhoHas::decode_slice(&data[..5]).is_err());
+    }
+}
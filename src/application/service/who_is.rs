@@ -0,0 +1,178 @@
+use crate::application::{UnconfirmedServiceChoice, APDU};
+use crate::encoding::*;
+use crate::error::ServiceError;
+use crate::{Decode, Encode};
+
+/// Who-Is-Request parameters (16.10.1)
+///
+/// Only devices with an instance number within the (inclusive) limits
+/// answer, all devices if there are none. The limits are present together
+/// or not at all.
+///
+/// ```
+/// use bacnet::application::WhoIs;
+/// use bacnet::Encode;
+///
+/// let who_is = WhoIs::range(3, 1000);
+/// assert!(who_is.matches(599));
+/// assert_eq!(who_is.encode_vec().unwrap(), [0x09, 0x03, 0x1a, 0x03, 0xe8]);
+/// ```
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct WhoIs {
+    pub low_limit: Option<u32>,
+    pub high_limit: Option<u32>,
+}
+
+impl WhoIs {
+    /// Answered by all devices
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Answered by the devices with an instance number from `low` to `high`
+    pub fn range(low: u32, high: u32) -> Self {
+        Self {
+            low_limit: Some(low),
+            high_limit: Some(high),
+        }
+    }
+
+    /// Answered by the device with the instance number
+    pub fn device(instance: u32) -> Self {
+        Self::range(instance, instance)
+    }
+
+    /// Whether the device with the instance number answers
+    pub fn matches(&self, instance: u32) -> bool {
+        let low = self.low_limit.unwrap_or(0);
+        let high = self.high_limit.unwrap_or(u32::MAX);
+        (low..=high).contains(&instance)
+    }
+
+    /// The unconfirmed request
+    pub fn to_apdu(&self) -> APDU {
+        APDU::unconfirmed_request(UnconfirmedServiceChoice::WhoIs as u8, self.encode_data())
+    }
+
+    fn encode_data(&self) -> Vec<u8> {
+        let mut data = Vec::new();
+        if let Some(low) = self.low_limit {
+            encode_context_unsigned(&mut data, 0, low);
+        }
+        if let Some(high) = self.high_limit {
+            encode_context_unsigned(&mut data, 1, high);
+        }
+        data
+    }
+
+    /// Decode the parameters without copying them
+    pub fn from_slice(data: &[u8]) -> crate::error::Result<Self> {
+        let mut reader = Reader::new(data);
+        let low_limit = reader.optional_context_unsigned(0)?;
+        let high_limit = reader.optional_context_unsigned(1)?;
+        if low_limit.is_some() != high_limit.is_some() {
+            return Err(ServiceError::Invalid("Incomplete device range").into());
+        }
+        Ok(Self {
+            low_limit,
+            high_limit,
+        })
+    }
+}
+
+impl Decode for WhoIs {
+    fn decode<T: std::io::Read + Sized>(reader: &mut T) -> crate::error::Result<Self> {
+        let mut data = Vec::new();
+        reader.read_to_end(&mut data)?;
+        Self::from_slice(&data)
+    }
+}
+
+impl Encode for WhoIs {
+    fn encode<T: std::io::Write + Sized>(&self, writer: &mut T) -> crate::error::Result<()> {
+        writer.write_all(&self.encode_data())?;
+        Ok(())
+    }
+
+    fn len(&self) -> usize {
+        self.encode_data().len()
+    }
+}
+
+/// Builder of a Who-Is-Request (16.10.1), answered by all devices unless
+/// limited to a range
+///
+/// ```
+/// use bacnet::application::WhoIsBuilder;
+/// use bacnet::network::NPDU;
+/// use bacnet::transport::bacnetip::BVLC;
+///
+/// let npdu = NPDU::builder().global_broadcast().build(WhoIsBuilder::new().build());
+/// let frame = BVLC::original_broadcast(npdu);
+/// ```
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub struct WhoIsBuilder {
+    who_is: WhoIs,
+}
+
+impl WhoIsBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Only devices with an instance number within the (inclusive) limits
+    /// answer
+    pub fn range(mut self, low: u32, high: u32) -> Self {
+        self.who_is = WhoIs::range(low, high);
+        self
+    }
+
+    /// Only the device with the instance number answers
+    pub fn device(self, instance: u32) -> Self {
+        self.range(instance, instance)
+    }
+
+    pub fn build(&self) -> APDU {
+        self.who_is.to_apdu()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_who_is() {
+        let who_is = WhoIsBuilder::new().build();
+        assert_eq!(
+            who_is.service_choice(),
+            Some(UnconfirmedServiceChoice::WhoIs as u8)
+        );
+        assert!(who_is.user_data().is_empty());
+
+        let who_is = WhoIsBuilder::new().range(3, 1000).build();
+        assert_eq!(who_is.user_data(), hex::decode("09031a03e8").unwrap());
+        assert_eq!(
+            WhoIsBuilder::new().device(7).build().user_data(),
+            [0x09, 7, 0x19, 7]
+        );
+    }
+
+    #[test]
+    fn test_decode_who_is() {
+        let who_is = WhoIs::decode_slice(&hex::decode("09031a03e8").unwrap()).unwrap();
+        assert_eq!(who_is, WhoIs::range(3, 1000));
+        assert!(who_is.matches(3) && who_is.matches(1000));
+        assert!(!who_is.matches(2) && !who_is.matches(1001));
+
+        let who_is = WhoIs::decode_slice(&[]).unwrap();
+        assert_eq!(who_is, WhoIs::new());
+        assert!(who_is.matches(4194303));
+        assert!(who_is.encode_vec().unwrap().is_empty());
+
+        // Both limits or none
+        assert!(WhoIs::decode_slice(&[0x09, 0x03]).is_err());
+        assert!(WhoIs::decode_slice(&[0x19, 0x03]).is_err());
+    }
+}
@@ -0,0 +1,218 @@
+use crate::application::BACnetValue;
+use crate::encoding::*;
+use crate::error::{Error, ServiceError};
+use crate::{Decode, Encode};
+
+fn invalid(msg: &'static str) -> Error {
+    Error::from(ServiceError::Invalid(msg))
+}
+
+/// Read an Unsigned (1..16) priority
+fn decode_priority(reader: &mut Reader, tag_number: u8) -> crate::error::Result<u8> {
+    match reader.context_unsigned(tag_number)? {
+        p @ 1..=16 => Ok(p as u8),
+        _ => Err(invalid("Invalid priority")),
+    }
+}
+
+/// Elements of a BACnetLightingCommand with their application datatype
+const LIGHTING_COMMAND: [(u8, u8); 6] = [(0, 9), (1, 4), (2, 4), (3, 4), (4, 2), (5, 2)];
+
+/// BACnetGroupChannelValue (Clause 21)
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct GroupChannelValue {
+    pub channel: u16,
+    pub overriding_priority: Option<u8>,
+    /// BACnetChannelValue, a lighting command is carried as constructed value
+    pub value: BACnetValue,
+}
+
+impl GroupChannelValue {
+    fn encode(&self, buf: &mut Vec<u8>) -> crate::error::Result<()> {
+        encode_context_unsigned(buf, 0, self.channel as u32);
+        if let Some(priority) = self.overriding_priority {
+            encode_context_unsigned(buf, 1, priority as u32);
+        }
+        match &self.value {
+            // The lighting command is the context tagged choice 0
+            command @ BACnetValue::Constructed(_) => encode_context(buf, 0, command),
+            value => encode_application(buf, value),
+        }
+    }
+
+    fn decode(reader: &mut Reader) -> crate::error::Result<Self> {
+        let channel = match reader.context_unsigned(0)? {
+            c if c <= u16::MAX as u32 => c as u16,
+            _ => return Err(invalid("Invalid channel")),
+        };
+        let overriding_priority = match reader.is_context_tag(1) {
+            true => Some(decode_priority(reader, 1)?),
+            false => None,
+        };
+        let value = match reader.is_opening_tag(0) {
+            true => {
+                reader.opening_tag(0)?;
+                let mut elements = Vec::new();
+                for (tag_number, datatype) in LIGHTING_COMMAND {
+                    if reader.is_context_tag(tag_number) {
+                        elements.push((tag_number, reader.context_value(tag_number, datatype)?));
+                    }
+                }
+                reader.closing_tag(0)?;
+                BACnetValue::Constructed(elements)
+            }
+            // Color commands, choices 1 and 2, are not supported
+            false => reader.application_value()?,
+        };
+        Ok(Self {
+            channel,
+            overriding_priority,
+            value,
+        })
+    }
+}
+
+/// WriteGroup-Request (16.10.9)
+///
+/// ```
+/// use bacnet::application::{BACnetValue, WriteGroup};
+/// use bacnet::Encode;
+///
+/// let request = WriteGroup::new(3, 12).change(7, BACnetValue::Real(1.0));
+/// assert_eq!(
+///     request.encode_vec().unwrap(),
+///     [0x09, 0x03, 0x19, 0x0c, 0x2e, 0x09, 0x07, 0x44, 0x3f, 0x80, 0x00, 0x00, 0x2f]
+/// );
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct WriteGroup {
+    pub group_number: u32,
+    pub write_priority: u8,
+    pub change_list: Vec<GroupChannelValue>,
+    /// Whether the channels skip the delays of their members, if they allow
+    /// it
+    pub inhibit_delay: Option<bool>,
+}
+
+impl WriteGroup {
+    /// A request without changes
+    pub fn new(group_number: u32, write_priority: u8) -> Self {
+        Self {
+            group_number,
+            write_priority,
+            change_list: Vec::new(),
+            inhibit_delay: None,
+        }
+    }
+
+    /// Write `value` to the channel at the write priority of the request
+    pub fn change(mut self, channel: u16, value: BACnetValue) -> Self {
+        self.change_list.push(GroupChannelValue {
+            channel,
+            overriding_priority: None,
+            value,
+        });
+        self
+    }
+
+    fn encode_data(&self) -> crate::error::Result<Vec<u8>> {
+        let mut data = Vec::new();
+        encode_context_unsigned(&mut data, 0, self.group_number);
+        encode_context_unsigned(&mut data, 1, self.write_priority as u32);
+        encode_opening_tag(&mut data, 2);
+        for change in &self.change_list {
+            change.encode(&mut data)?;
+        }
+        encode_closing_tag(&mut data, 2);
+        if let Some(inhibit_delay) = self.inhibit_delay {
+            encode_context_boolean(&mut data, 3, inhibit_delay);
+        }
+        Ok(data)
+    }
+}
+
+impl Decode for WriteGroup {
+    fn decode<T: std::io::Read + Sized>(reader: &mut T) -> crate::error::Result<Self> {
+        let mut data = Vec::new();
+        reader.read_to_end(&mut data)?;
+        let mut reader = Reader::new(&data);
+        let group_number = reader.context_unsigned(0)?;
+        let write_priority = decode_priority(&mut reader, 1)?;
+        reader.opening_tag(2)?;
+        let mut change_list = Vec::new();
+        while !reader.is_closing_tag(2) {
+            change_list.push(GroupChannelValue::decode(&mut reader)?);
+        }
+        reader.closing_tag(2)?;
+        let inhibit_delay = match reader.is_context_tag(3) {
+            true => Some(reader.context_boolean(3)?),
+            false => None,
+        };
+        Ok(Self {
+            group_number,
+            write_priority,
+            change_list,
+            inhibit_delay,
+        })
+    }
+}
+
+impl Encode for WriteGroup {
+    fn encode<T: std::io::Write + Sized>(&self, writer: &mut T) -> crate::error::Result<()> {
+        writer.write_all(&self.encode_data()?)?;
+        Ok(())
+    }
+
+    fn len(&self) -> usize {
+        self.encode_data().map_or(0, |data| data.len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::objects::{LightingCommand, LightingOperation};
+    use std::convert::TryFrom;
+
+    #[test]
+    fn test_write_group() {
+        let data =
+            hex::decode("09031908 2e 0907 4442c80000 0908 1905 9101 2f 3901".replace(' ', ""))
+                .unwrap();
+        let request = WriteGroup::decode_slice(&data).unwrap();
+        let mut expected = WriteGroup::new(3, 8)
+            .change(7, BACnetValue::Real(100.0))
+            .change(8, BACnetValue::Enumerated(1));
+        expected.change_list[1].overriding_priority = Some(5);
+        expected.inhibit_delay = Some(true);
+        assert_eq!(request, expected);
+        assert_eq!(request.len(), data.len());
+        assert_eq!(request.encode_vec().unwrap(), data);
+
+        // Write priority 0, unclosed change list
+        let data = hex::decode("090319002e2f").unwrap();
+        assert!(WriteGroup::decode_slice(&data).is_err());
+        let data = hex::decode("090319082e09074442c80000").unwrap();
+        assert!(WriteGroup::decode_slice(&data).is_err());
+    }
+
+    #[test]
+    fn test_write_group_lighting_command() {
+        let command = LightingCommand::fade_to(80.0, Some(2000));
+        let request = WriteGroup::new(1, 16).change(2, command.into());
+        let data = request.encode_vec().unwrap();
+        assert_eq!(
+            hex::encode(&data),
+            "090119102e09020e09011c42a000004a07d00f2f"
+        );
+        let decoded = WriteGroup::decode_slice(&data).unwrap();
+        assert_eq!(decoded, request);
+        let value = decoded.change_list[0].value.clone();
+        assert_eq!(
+            LightingCommand::try_from(value).unwrap().operation,
+            LightingOperation::FadeTo
+        );
+    }
+}
@@ -0,0 +1,158 @@
+use super::read_property::{decode_reference, encode_reference};
+use crate::application::{BACnetValue, ObjectIdentifier, PropertyIdentifier};
+use crate::encoding::*;
+use crate::error::ServiceError;
+use crate::{Decode, Encode};
+
+use std::convert::TryFrom;
+
+/// WriteProperty-Request (15.9.1.1)
+///
+/// The priority is only used for commandable properties, writing NULL
+/// with a priority relinquishes it. WriteProperty is answered with a
+/// Simple-ACK.
+///
+/// ```
+/// use bacnet::application::{BACnetValue, ObjectIdentifier, ObjectType, PropertyIdentifier};
+/// use bacnet::application::WritePropertyRequest;
+/// use bacnet::Encode;
+///
+/// let object = ObjectIdentifier::new(ObjectType::AnalogValue, 1);
+/// let value = BACnetValue::Real(21.5);
+/// let request =
+///     WritePropertyRequest::new(object, PropertyIdentifier::PresentValue, value).priority(8);
+/// assert_eq!(
+///     hex::encode(request.encode_vec().unwrap()),
+///     "0c0080000119553e4441ac00003f4908"
+/// );
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct WritePropertyRequest {
+    pub object_identifier: ObjectIdentifier,
+    pub property_identifier: PropertyIdentifier,
+    pub property_array_index: Option<u32>,
+    /// The value written, decoded as the one value sent or as a
+    /// [`BACnetValue::Array`] of several values sent, like the value of a
+    /// ReadProperty-ACK. An array of one element is decoded as the element.
+    pub property_value: BACnetValue,
+    pub priority: Option<u8>,
+}
+
+impl WritePropertyRequest {
+    pub fn new(
+        object_identifier: ObjectIdentifier,
+        property_identifier: PropertyIdentifier,
+        property_value: BACnetValue,
+    ) -> Self {
+        Self {
+            object_identifier,
+            property_identifier,
+            property_array_index: None,
+            property_value,
+            priority: None,
+        }
+    }
+
+    /// Write a single element of an array property
+    pub fn array_index(mut self, index: u32) -> Self {
+        self.property_array_index = Some(index);
+        self
+    }
+
+    /// Write at a priority of a commandable property, from 1 (highest) to 16
+    pub fn priority(mut self, priority: u8) -> Self {
+        self.priority = Some(priority);
+        self
+    }
+
+    fn encode_data(&self) -> crate::error::Result<Vec<u8>> {
+        let mut data = Vec::new();
+        encode_reference(
+            &mut data,
+            self.object_identifier,
+            self.property_identifier,
+            self.property_array_index,
+        )?;
+        encode_opening_tag(&mut data, 3);
+        encode_application(&mut data, &self.property_value)?;
+        encode_closing_tag(&mut data, 3);
+        if let Some(priority) = self.priority {
+            encode_context_unsigned(&mut data, 4, priority as u32);
+        }
+        Ok(data)
+    }
+}
+
+impl Decode for WritePropertyRequest {
+    fn decode<T: std::io::Read + Sized>(reader: &mut T) -> crate::error::Result<Self> {
+        let mut data = Vec::new();
+        reader.read_to_end(&mut data)?;
+        let mut reader = Reader::new(&data);
+        let (object_identifier, property_identifier, property_array_index) =
+            decode_reference(&mut reader)?;
+        reader.opening_tag(3)?;
+        let mut values = reader.values_until_closing_tag(3)?;
+        let property_value = match values.len() {
+            1 => values.remove(0),
+            _ => BACnetValue::Array(values),
+        };
+        // Whether the priority is in range is left to the device written to
+        let priority = reader
+            .optional_context_unsigned(4)?
+            .map(|p| u8::try_from(p).map_err(|_| ServiceError::Invalid("Priority out of range")))
+            .transpose()?;
+        Ok(Self {
+            object_identifier,
+            property_identifier,
+            property_array_index,
+            property_value,
+            priority,
+        })
+    }
+}
+
+impl Encode for WritePropertyRequest {
+    fn encode<T: std::io::Write + Sized>(&self, writer: &mut T) -> crate::error::Result<()> {
+        writer.write_all(&self.encode_data()?)?;
+        Ok(())
+    }
+
+    fn len(&self) -> usize {
+        self.encode_data().map_or(0, |data| data.len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::application::ObjectType;
+
+    #[test]
+    fn test_write_property() {
+        let object = ObjectIdentifier::new(ObjectType::AnalogValue, 1);
+        let request = WritePropertyRequest::new(
+            object,
+            PropertyIdentifier::PresentValue,
+            BACnetValue::Real(21.5),
+        )
+        .priority(8);
+        let data = request.encode_vec().unwrap();
+        assert_eq!(hex::encode(&data), "0c0080000119553e4441ac00003f4908");
+        assert_eq!(request.len(), data.len());
+        assert_eq!(WritePropertyRequest::decode_slice(&data).unwrap(), request);
+
+        // Relinquishing an element of an array, without a priority
+        let request =
+            WritePropertyRequest::new(object, PropertyIdentifier::PriorityArray, BACnetValue::Null)
+                .array_index(8);
+        let data = request.encode_vec().unwrap();
+        assert_eq!(hex::encode(&data), "0c00800001195729083e003f");
+        assert_eq!(WritePropertyRequest::decode_slice(&data).unwrap(), request);
+
+        // Missing value, priority above 255
+        assert!(WritePropertyRequest::decode_slice(&data[..9]).is_err());
+        let data = hex::decode("0c0080000119553e003f4a0100").unwrap();
+        assert!(WritePropertyRequest::decode_slice(&data).is_err());
+    }
+}
@@ -0,0 +1,704 @@
+use crate::application::BACnetValue;
+use crate::encoding::*;
+use crate::error::{EncodingError, Error};
+use crate::{Decode, Encode};
+
+use std::convert::TryFrom;
+use std::fmt;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Value of a date or time field that is unspecified (wildcard)
+pub const UNSPECIFIED: u8 = 0xFF;
+/// Month field matching the odd months
+pub const ODD_MONTHS: u8 = 13;
+/// Month field matching the even months
+pub const EVEN_MONTHS: u8 = 14;
+/// Day field matching the last day of the month
+pub const LAST_DAY_OF_MONTH: u8 = 32;
+/// Day field matching the odd days of the month
+pub const ODD_DAYS: u8 = 33;
+/// Day field matching the even days of the month
+pub const EVEN_DAYS: u8 = 34;
+
+/// BACnet Date (20.2.12)
+///
+/// Fields are stored as they appear on the wire: `year` counts from 1900 and
+/// `weekday` runs from 1 (Monday) to 7 (Sunday). Any field may be
+/// [`UNSPECIFIED`], the month may also be [`ODD_MONTHS`] or [`EVEN_MONTHS`]
+/// and the day [`LAST_DAY_OF_MONTH`], [`ODD_DAYS`] or [`EVEN_DAYS`]. Such a
+/// date is a pattern that [`matches`](Self::matches) concrete dates.
+///
+/// ```
+/// use bacnet::application::{BACnetDate, EVEN_MONTHS, LAST_DAY_OF_MONTH};
+///
+/// let pattern = BACnetDate {
+///     month: EVEN_MONTHS,
+///     day: LAST_DAY_OF_MONTH,
+///     ..BACnetDate::ANY
+/// };
+/// assert_eq!(pattern.to_string(), "*-even-last");
+/// assert!(pattern.matches(&BACnetDate::new(2024, 2, 29)));
+/// assert!(!pattern.matches(&BACnetDate::new(2023, 2, 27)));
+/// ```
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct BACnetDate {
+    pub year: u8,
+    pub month: u8,
+    pub day: u8,
+    pub weekday: u8,
+}
+
+impl BACnetDate {
+    /// A date with every field unspecified, matching any date
+    pub const ANY: Self = Self {
+        year: UNSPECIFIED,
+        month: UNSPECIFIED,
+        day: UNSPECIFIED,
+        weekday: UNSPECIFIED,
+    };
+
+    /// Create a concrete date, calculating the day of week
+    ///
+    /// # Panics
+    ///
+    /// If the date does not exist or is out of the years 1900 to 2154, see
+    /// [`try_new`](Self::try_new).
+    pub fn new(year: u16, month: u8, day: u8) -> Self {
+        Self::try_new(year, month, day).expect("Invalid date")
+    }
+
+    /// Create a concrete date, if it exists and its year can be encoded
+    ///
+    /// The year field counts from 1900 and 255 is unspecified, so only the
+    /// years 1900 to 2154 can be encoded.
+    pub fn try_new(year: u16, month: u8, day: u8) -> Option<Self> {
+        if !(1900..=2154).contains(&year)
+            || !(1..=12).contains(&month)
+            || !(1..=days_in_month(year, month)).contains(&day)
+        {
+            return None;
+        }
+        Some(Self {
+            year: (year - 1900) as u8,
+            month,
+            day,
+            weekday: weekday(year, month, day),
+        })
+    }
+
+    /// The year in the common era, if specified
+    pub fn year(&self) -> Option<u16> {
+        match self.year {
+            UNSPECIFIED => None,
+            y => Some(1900 + y as u16),
+        }
+    }
+
+    /// Whether every field holds a single value, no wildcard or pattern
+    pub fn is_concrete(&self) -> bool {
+        self.year != UNSPECIFIED
+            && (1..=12).contains(&self.month)
+            && (1..=31).contains(&self.day)
+            && (1..=7).contains(&self.weekday)
+    }
+
+    /// Whether this date, read as a pattern, matches a concrete date
+    pub fn matches(&self, date: &BACnetDate) -> bool {
+        let day = match self.day {
+            UNSPECIFIED => true,
+            LAST_DAY_OF_MONTH => date.day == days_in_month(date.year().unwrap_or(1900), date.month),
+            ODD_DAYS => date.day % 2 == 1,
+            EVEN_DAYS => date.day.is_multiple_of(2),
+            day => day == date.day,
+        };
+        (self.year == UNSPECIFIED || self.year == date.year)
+            && month_matches(self.month, date.month)
+            && day
+            && (self.weekday == UNSPECIFIED || self.weekday == date.weekday)
+    }
+}
+
+/// Number of days of a month
+pub(crate) fn days_in_month(year: u16, month: u8) -> u8 {
+    match month {
+        2 if year.is_multiple_of(4) && (!year.is_multiple_of(100) || year.is_multiple_of(400)) => {
+            29
+        }
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+/// Whether a month field matches, with the odd and even month patterns
+pub(crate) fn month_matches(pattern: u8, month: u8) -> bool {
+    match pattern {
+        UNSPECIFIED => true,
+        ODD_MONTHS => month % 2 == 1,
+        EVEN_MONTHS => month.is_multiple_of(2),
+        pattern => pattern == month,
+    }
+}
+
+impl From<[u8; 4]> for BACnetDate {
+    fn from([year, month, day, weekday]: [u8; 4]) -> Self {
+        Self {
+            year,
+            month,
+            day,
+            weekday,
+        }
+    }
+}
+
+impl From<BACnetDate> for [u8; 4] {
+    fn from(date: BACnetDate) -> Self {
+        [date.year, date.month, date.day, date.weekday]
+    }
+}
+
+impl Decode for BACnetDate {
+    fn decode<T: std::io::Read + Sized>(reader: &mut T) -> crate::error::Result<Self> {
+        let mut data = [0; 4];
+        reader.read_exact(&mut data)?;
+        Ok(data.into())
+    }
+}
+
+impl Encode for BACnetDate {
+    fn encode<T: std::io::Write + Sized>(&self, writer: &mut T) -> crate::error::Result<()> {
+        writer.write_all(&<[u8; 4]>::from(*self))?;
+        Ok(())
+    }
+
+    fn len(&self) -> usize {
+        4
+    }
+}
+
+/// Write a field, `*` if unspecified
+fn field(f: &mut fmt::Formatter<'_>, value: u8, width: usize) -> fmt::Result {
+    match value {
+        UNSPECIFIED => f.write_str("*"),
+        value => write!(f, "{:0width$}", value, width = width),
+    }
+}
+
+/// ISO 8601 like `2021-01-24`, with `*` for unspecified fields, the names
+/// of the month and day patterns and the day of week appended if it is
+/// specified but not the other fields, e.g. `*-*-*(7)` for any Sunday
+impl fmt::Display for BACnetDate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.year() {
+            Some(year) => write!(f, "{}-", year)?,
+            None => f.write_str("*-")?,
+        }
+        match self.month {
+            ODD_MONTHS => f.write_str("odd-")?,
+            EVEN_MONTHS => f.write_str("even-")?,
+            month => {
+                field(f, month, 2)?;
+                f.write_str("-")?;
+            }
+        }
+        match self.day {
+            LAST_DAY_OF_MONTH => f.write_str("last")?,
+            ODD_DAYS => f.write_str("odd")?,
+            EVEN_DAYS => f.write_str("even")?,
+            day => field(f, day, 2)?,
+        }
+        if self.weekday != UNSPECIFIED && !self.is_concrete() {
+            write!(f, "({})", self.weekday)?;
+        }
+        Ok(())
+    }
+}
+
+/// Day of week (1 = Monday) using Sakamoto's method
+fn weekday(year: u16, month: u8, day: u8) -> u8 {
+    const OFFSETS: [u16; 12] = [0, 3, 2, 5, 0, 3, 5, 1, 4, 6, 2, 4];
+    let y = if month < 3 { year - 1 } else { year };
+    let w = (y + y / 4 - y / 100 + y / 400 + OFFSETS[(month as usize - 1) % 12] + day as u16) % 7;
+    // 0 = Sunday
+    match w {
+        0 => 7,
+        w => w as u8,
+    }
+}
+
+/// BACnet Time (20.2.13)
+///
+/// Any field may be [`UNSPECIFIED`], such a time is a pattern that
+/// [`matches`](Self::matches) concrete times.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct BACnetTime {
+    pub hour: u8,
+    pub minute: u8,
+    pub second: u8,
+    pub hundredths: u8,
+}
+
+impl BACnetTime {
+    pub fn new(hour: u8, minute: u8, second: u8, hundredths: u8) -> Self {
+        Self {
+            hour,
+            minute,
+            second,
+            hundredths,
+        }
+    }
+
+    /// A time with every field unspecified, matching any time
+    pub const ANY: Self = Self {
+        hour: UNSPECIFIED,
+        minute: UNSPECIFIED,
+        second: UNSPECIFIED,
+        hundredths: UNSPECIFIED,
+    };
+
+    /// Whether no field is unspecified
+    pub fn is_concrete(&self) -> bool {
+        ![self.hour, self.minute, self.second, self.hundredths].contains(&UNSPECIFIED)
+    }
+
+    /// Whether this time, read as a pattern, matches a concrete time
+    pub fn matches(&self, time: &BACnetTime) -> bool {
+        let fields = [
+            (self.hour, time.hour),
+            (self.minute, time.minute),
+            (self.second, time.second),
+            (self.hundredths, time.hundredths),
+        ];
+        fields
+            .iter()
+            .all(|(pattern, value)| *pattern == UNSPECIFIED || pattern == value)
+    }
+}
+
+impl From<[u8; 4]> for BACnetTime {
+    fn from([hour, minute, second, hundredths]: [u8; 4]) -> Self {
+        Self::new(hour, minute, second, hundredths)
+    }
+}
+
+impl From<BACnetTime> for [u8; 4] {
+    fn from(time: BACnetTime) -> Self {
+        [time.hour, time.minute, time.second, time.hundredths]
+    }
+}
+
+impl Decode for BACnetTime {
+    fn decode<T: std::io::Read + Sized>(reader: &mut T) -> crate::error::Result<Self> {
+        let mut data = [0; 4];
+        reader.read_exact(&mut data)?;
+        Ok(data.into())
+    }
+}
+
+impl Encode for BACnetTime {
+    fn encode<T: std::io::Write + Sized>(&self, writer: &mut T) -> crate::error::Result<()> {
+        writer.write_all(&<[u8; 4]>::from(*self))?;
+        Ok(())
+    }
+
+    fn len(&self) -> usize {
+        4
+    }
+}
+
+/// Like `17:35:45.17`, with `*` for unspecified fields
+impl fmt::Display for BACnetTime {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        field(f, self.hour, 2)?;
+        f.write_str(":")?;
+        field(f, self.minute, 2)?;
+        f.write_str(":")?;
+        field(f, self.second, 2)?;
+        f.write_str(".")?;
+        field(f, self.hundredths, 2)
+    }
+}
+
+/// BACnetDateTime (Clause 21)
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct BACnetDateTime {
+    pub date: BACnetDate,
+    pub time: BACnetTime,
+}
+
+impl BACnetDateTime {
+    pub fn new(date: BACnetDate, time: BACnetTime) -> Self {
+        Self { date, time }
+    }
+
+    /// The current system time in UTC
+    ///
+    /// Panics on `wasm32-unknown-unknown`, which has no system time. The
+    /// time of a JavaScript `Date` is converted from its milliseconds:
+    ///
+    /// ```
+    /// # use bacnet::application::BACnetDateTime;
+    /// # use std::time::{Duration, UNIX_EPOCH};
+    /// let millis = 1_611_532_800_000; // Date.now()
+    /// let time = BACnetDateTime::from(UNIX_EPOCH + Duration::from_millis(millis));
+    /// assert_eq!(time.date.day, 25);
+    /// ```
+    pub fn now() -> Self {
+        SystemTime::now().into()
+    }
+
+    /// The system time of a date and time in UTC, `None` if a field is
+    /// unspecified or the time precedes 1970
+    pub fn system_time(&self) -> Option<SystemTime> {
+        let (date, time) = (&self.date, &self.time);
+        let fields = [date.month, date.day, time.hour, time.minute, time.second];
+        if fields.contains(&UNSPECIFIED) {
+            return None;
+        }
+        let days = days_from_civil(date.year()?, date.month, date.day);
+        let secs =
+            days * 86400 + time.hour as i64 * 3600 + time.minute as i64 * 60 + time.second as i64;
+        let millis = match time.hundredths {
+            UNSPECIFIED => 0,
+            hundredths => hundredths as u64 * 10,
+        };
+        let since_epoch = Duration::from_secs(u64::try_from(secs).ok()?);
+        Some(UNIX_EPOCH + since_epoch + Duration::from_millis(millis))
+    }
+
+    /// Append the date and the time, application tagged
+    pub fn encode(&self, buf: &mut Vec<u8>) {
+        encode_date(buf, self.date);
+        encode_time(buf, self.time);
+    }
+
+    /// Append the date and the time enclosed in the context tag
+    pub fn encode_context(&self, buf: &mut Vec<u8>, tag_number: u8) {
+        encode_opening_tag(buf, tag_number);
+        self.encode(buf);
+        encode_closing_tag(buf, tag_number);
+    }
+
+    /// Read an application tagged date followed by a time
+    pub fn decode(reader: &mut Reader) -> crate::error::Result<Self> {
+        match (reader.application_value()?, reader.application_value()?) {
+            (BACnetValue::Date(date), BACnetValue::Time(time)) => Ok(Self::new(date, time)),
+            _ => Err(Error::from(EncodingError::Invalid(
+                "Expected a date and a time",
+            ))),
+        }
+    }
+
+    /// Read a date and a time enclosed in the context tag
+    pub fn decode_context(reader: &mut Reader, tag_number: u8) -> crate::error::Result<Self> {
+        reader.opening_tag(tag_number)?;
+        let datetime = Self::decode(reader)?;
+        reader.closing_tag(tag_number)?;
+        Ok(datetime)
+    }
+}
+
+/// Joined with a `T`, like `1991-01-24T17:35:45.17`
+impl fmt::Display for BACnetDateTime {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}T{}", self.date, self.time)
+    }
+}
+
+impl From<SystemTime> for BACnetDateTime {
+    fn from(time: SystemTime) -> Self {
+        let since_epoch = time.duration_since(UNIX_EPOCH).unwrap_or_default();
+        let secs = since_epoch.as_secs();
+        let (year, month, day) = civil_from_days((secs / 86400) as i64);
+        let secs = secs % 86400;
+        Self {
+            // Years after 2154 can't be encoded and are left unspecified
+            date: BACnetDate::try_new(year, month, day).unwrap_or(BACnetDate {
+                month,
+                day,
+                ..BACnetDate::ANY
+            }),
+            time: BACnetTime::new(
+                (secs / 3600) as u8,
+                (secs % 3600 / 60) as u8,
+                (secs % 60) as u8,
+                (since_epoch.subsec_millis() / 10) as u8,
+            ),
+        }
+    }
+}
+
+/// BACnetTimeStamp (Clause 21), when an event happened or was
+/// acknowledged
+///
+/// ```
+/// use bacnet::application::{BACnetTime, TimeStamp};
+/// use bacnet::encoding::Reader;
+///
+/// let mut buf = Vec::new();
+/// TimeStamp::from(BACnetTime::new(13, 0, 0, 0)).encode_context(&mut buf, 3);
+/// assert_eq!(buf, [0x3E, 0x0C, 0x0D, 0x00, 0x00, 0x00, 0x3F]);
+/// let time_stamp = TimeStamp::decode_context(&mut Reader::new(&buf), 3).unwrap();
+/// assert_eq!(time_stamp, TimeStamp::Time(BACnetTime::new(13, 0, 0, 0)));
+/// ```
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum TimeStamp {
+    Time(BACnetTime),
+    SequenceNumber(u32),
+    DateTime(BACnetDateTime),
+}
+
+impl TimeStamp {
+    /// Append the time stamp, tagged as the choice it is
+    pub fn encode(&self, buf: &mut Vec<u8>) {
+        match self {
+            Self::Time(time) => encode_context_time(buf, 0, *time),
+            Self::SequenceNumber(n) => encode_context_unsigned(buf, 1, *n),
+            Self::DateTime(datetime) => datetime.encode_context(buf, 2),
+        }
+    }
+
+    /// Append the time stamp enclosed in the context tag
+    pub fn encode_context(&self, buf: &mut Vec<u8>, tag_number: u8) {
+        encode_opening_tag(buf, tag_number);
+        self.encode(buf);
+        encode_closing_tag(buf, tag_number);
+    }
+
+    /// Read a time stamp enclosed in the context tag
+    pub fn decode_context(reader: &mut Reader, tag_number: u8) -> crate::error::Result<Self> {
+        reader.opening_tag(tag_number)?;
+        let time_stamp = Self::decode(reader)?;
+        reader.closing_tag(tag_number)?;
+        Ok(time_stamp)
+    }
+
+    /// Read a time stamp tagged as the choice it is
+    pub fn decode(reader: &mut Reader) -> crate::error::Result<Self> {
+        Ok(if reader.is_context_tag(0) {
+            Self::Time(reader.context_time(0)?)
+        } else if reader.is_context_tag(1) {
+            Self::SequenceNumber(reader.context_unsigned(1)?)
+        } else {
+            Self::DateTime(BACnetDateTime::decode_context(reader, 2)?)
+        })
+    }
+}
+
+impl From<BACnetTime> for TimeStamp {
+    fn from(time: BACnetTime) -> Self {
+        Self::Time(time)
+    }
+}
+
+impl From<BACnetDateTime> for TimeStamp {
+    fn from(datetime: BACnetDateTime) -> Self {
+        Self::DateTime(datetime)
+    }
+}
+
+/// Convert days since 1970-01-01 into a (year, month, day) triple
+fn civil_from_days(days: i64) -> (u16, u8, u8) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z - era * 146_097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year as u16, month as u8, day as u8)
+}
+
+/// Convert a (year, month, day) triple into days since 1970-01-01
+fn days_from_civil(year: u16, month: u8, day: u8) -> i64 {
+    let year = year as i64 - if month <= 2 { 1 } else { 0 };
+    let era = year.div_euclid(400);
+    let yoe = year - era * 400;
+    let mp = (month as i64 + 9) % 12;
+    let doy = (153 * mp + 2) / 5 + day as i64 - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146_097 + doe - 719_468
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_date_weekday() {
+        // 2021-01-24 was a Sunday, 2021-03-01 a Monday
+        assert_eq!(BACnetDate::new(2021, 1, 24).weekday, 7);
+        assert_eq!(BACnetDate::new(2021, 3, 1).weekday, 1);
+        assert_eq!(BACnetDate::new(1991, 1, 24).weekday, 4);
+    }
+
+    #[test]
+    fn test_date_year() {
+        let date = BACnetDate::new(1991, 1, 24);
+        assert_eq!(date.year, 91);
+        assert_eq!(date.year(), Some(1991));
+
+        assert_eq!(BACnetDate::new(1900, 1, 1).year, 0);
+        assert_eq!(BACnetDate::new(2154, 12, 31).year, 254);
+        assert_eq!(BACnetDate::new(2154, 12, 31).year(), Some(2154));
+        assert_eq!(BACnetDate::try_new(1899, 12, 31), None);
+        assert_eq!(BACnetDate::try_new(2155, 1, 1), None);
+        assert_eq!(BACnetDate::try_new(2021, 0, 1), None);
+        assert_eq!(BACnetDate::try_new(2021, 2, 29), None);
+        assert!(BACnetDate::try_new(2024, 2, 29).is_some());
+    }
+
+    #[test]
+    #[should_panic(expected = "Invalid date")]
+    fn test_date_before_1900() {
+        BACnetDate::new(1899, 12, 31);
+    }
+
+    #[test]
+    fn test_date_pattern() {
+        let date = BACnetDate::new(2021, 1, 31);
+        assert!(date.is_concrete());
+        assert!(date.matches(&date));
+        assert!(BACnetDate::ANY.matches(&date));
+        assert!(!BACnetDate::ANY.is_concrete());
+
+        let odd_months = BACnetDate {
+            month: ODD_MONTHS,
+            ..BACnetDate::ANY
+        };
+        assert!(odd_months.matches(&date));
+        assert!(!odd_months.matches(&BACnetDate::new(2021, 2, 1)));
+
+        let pattern = |day, weekday| BACnetDate {
+            day,
+            weekday,
+            ..BACnetDate::ANY
+        };
+        assert!(pattern(LAST_DAY_OF_MONTH, UNSPECIFIED).matches(&date));
+        assert!(pattern(ODD_DAYS, UNSPECIFIED).matches(&date));
+        assert!(!pattern(EVEN_DAYS, UNSPECIFIED).matches(&date));
+        assert!(pattern(UNSPECIFIED, date.weekday).matches(&date));
+        assert!(!pattern(UNSPECIFIED, date.weekday % 7 + 1).matches(&date));
+        // Leap years
+        assert!(pattern(LAST_DAY_OF_MONTH, UNSPECIFIED).matches(&BACnetDate::new(2000, 2, 29)));
+        assert!(pattern(LAST_DAY_OF_MONTH, UNSPECIFIED).matches(&BACnetDate::new(2100, 2, 28)));
+    }
+
+    #[test]
+    fn test_date_encoding() {
+        let date = BACnetDate::new(1991, 1, 24);
+        assert_eq!(date.encode_vec().unwrap(), [0x5B, 0x01, 0x18, 0x04]);
+        assert_eq!(
+            BACnetDate::decode_slice(&[0x5B, 0x01, 0x18, 0x04]).unwrap(),
+            date
+        );
+        assert_eq!(date.to_string(), "1991-01-24");
+
+        let any = BACnetDate::decode_slice(&[0xFF; 4]).unwrap();
+        assert_eq!(any, BACnetDate::ANY);
+        assert_eq!(any.to_string(), "*-*-*");
+        let sundays = BACnetDate::from([0xFF, 0x0D, 0xFF, 0x07]);
+        assert_eq!(sundays.to_string(), "*-odd-*(7)");
+        assert!(BACnetDate::decode_slice(&[0x5B, 0x01]).is_err());
+    }
+
+    #[test]
+    fn test_time_pattern() {
+        let time = BACnetTime::new(17, 35, 45, 17);
+        assert_eq!(time.encode_vec().unwrap(), [0x11, 0x23, 0x2D, 0x11]);
+        assert_eq!(
+            BACnetTime::decode_slice(&[0x11, 0x23, 0x2D, 0x11]).unwrap(),
+            time
+        );
+        assert_eq!(time.to_string(), "17:35:45.17");
+        assert!(time.is_concrete());
+
+        let every_hour = BACnetTime {
+            hour: UNSPECIFIED,
+            ..BACnetTime::new(0, 35, 45, 17)
+        };
+        assert_eq!(every_hour.to_string(), "*:35:45.17");
+        assert!(!every_hour.is_concrete());
+        assert!(every_hour.matches(&time));
+        assert!(!every_hour.matches(&BACnetTime::new(17, 36, 45, 17)));
+        assert!(BACnetTime::ANY.matches(&time));
+    }
+
+    #[test]
+    fn test_datetime_from_system_time() {
+        let time = UNIX_EPOCH + Duration::from_millis(1_611_532_800_120);
+        let datetime = BACnetDateTime::from(time);
+        assert_eq!(datetime.date, BACnetDate::new(2021, 1, 25));
+        assert_eq!(datetime.time, BACnetTime::new(0, 0, 0, 12));
+        assert_eq!(datetime.system_time(), Some(time));
+
+        let leap = BACnetDateTime::new(BACnetDate::new(2024, 2, 29), BACnetTime::new(13, 5, 9, 0));
+        assert_eq!(BACnetDateTime::from(leap.system_time().unwrap()), leap);
+        let mut unspecified = leap;
+        unspecified.time.hour = UNSPECIFIED;
+        assert_eq!(unspecified.system_time(), None);
+        let old = BACnetDateTime::new(BACnetDate::new(1969, 12, 31), BACnetTime::new(0, 0, 0, 0));
+        assert_eq!(old.system_time(), None);
+
+        // 2155-01-01, after the last year that can be encoded
+        let late = BACnetDateTime::from(UNIX_EPOCH + Duration::from_secs(5_838_048_000));
+        assert_eq!(late.date.year, UNSPECIFIED);
+        assert_eq!((late.date.month, late.date.day), (1, 1));
+    }
+
+    #[test]
+    fn test_datetime_encoding() {
+        let datetime = BACnetDateTime::new(
+            BACnetDate::new(1991, 1, 24),
+            BACnetTime::new(17, 35, 45, 17),
+        );
+        let mut buf = Vec::new();
+        datetime.encode_context(&mut buf, 2);
+        assert_eq!(hex::encode(&buf), "2ea45b011804b411232d112f");
+        let mut reader = Reader::new(&buf);
+        assert_eq!(
+            BACnetDateTime::decode_context(&mut reader, 2).unwrap(),
+            datetime
+        );
+        assert!(reader.is_empty());
+        assert_eq!(datetime.to_string(), "1991-01-24T17:35:45.17");
+
+        let mut reader = Reader::new(&buf[1..6]);
+        assert!(BACnetDateTime::decode(&mut reader).is_err());
+    }
+
+    #[test]
+    fn test_time_stamp() {
+        let datetime = BACnetDateTime::new(
+            BACnetDate::new(1991, 1, 24),
+            BACnetTime::new(17, 35, 45, 17),
+        );
+        for (time_stamp, encoded) in [
+            (TimeStamp::Time(datetime.time), "0c11232d11"),
+            (TimeStamp::SequenceNumber(300), "1a012c"),
+            (TimeStamp::from(datetime), "2ea45b011804b411232d112f"),
+        ] {
+            let mut buf = Vec::new();
+            time_stamp.encode(&mut buf);
+            assert_eq!(hex::encode(&buf), encoded);
+            assert_eq!(
+                TimeStamp::decode(&mut Reader::new(&buf)).unwrap(),
+                time_stamp
+            );
+        }
+        assert!(TimeStamp::decode(&mut Reader::new(&[0x0C, 0x11])).is_err());
+    }
+
+    #[test]
+    fn test_datetime_order() {
+        let a = BACnetDateTime::new(BACnetDate::new(2021, 1, 24), BACnetTime::new(23, 59, 0, 0));
+        let b = BACnetDateTime::new(BACnetDate::new(2021, 1, 25), BACnetTime::new(0, 0, 0, 0));
+        assert!(a < b);
+    }
+}
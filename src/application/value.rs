@@ -0,0 +1,179 @@
+use crate::application::{
+    BACnetDate, BACnetError, BACnetTime, BitString, CharacterString, ErrorCode, ObjectIdentifier,
+};
+
+use std::convert::TryFrom;
+
+/// A property value as carried by the application layer (20.2)
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum BACnetValue {
+    Null,
+    Boolean(bool),
+    Unsigned(u32),
+    Signed(i32),
+    Real(f32),
+    Double(f64),
+    OctetString(Vec<u8>),
+    /// A string with its character set, so that it is encoded again in it
+    CharacterString(CharacterString),
+    BitString(BitString),
+    Enumerated(u32),
+    Date(BACnetDate),
+    Time(BACnetTime),
+    ObjectIdentifier(ObjectIdentifier),
+    /// BACnetARRAY, elements are indexed starting from 1
+    Array(Vec<BACnetValue>),
+    /// BACnetLIST, encoded like an array but without an index, decoded
+    /// lists are arrays as the encoding does not tell them apart
+    List(Vec<BACnetValue>),
+    /// SEQUENCE of context tagged elements, as (tag number, value) pairs
+    Constructed(Vec<(u8, BACnetValue)>),
+}
+
+impl BACnetValue {
+    /// Look up the element with the given context tag of a constructed value
+    pub fn context(&self, tag: u8) -> Option<&BACnetValue> {
+        match self {
+            Self::Constructed(elements) => elements.iter().find(|(t, _)| *t == tag).map(|(_, v)| v),
+            _ => None,
+        }
+    }
+
+    /// Apply an optional array index to a BACnetARRAY (12.1.5)
+    ///
+    /// Index 0 yields the number of elements, `None` the whole array.
+    pub fn array_element(self, array_index: Option<u32>) -> Result<Self, BACnetError> {
+        match (self, array_index) {
+            (value, None) => Ok(value),
+            (Self::Array(elements), Some(0)) => Ok(Self::Unsigned(elements.len() as u32)),
+            (Self::Array(mut elements), Some(i)) if (i as usize) <= elements.len() => {
+                Ok(elements.swap_remove(i as usize - 1))
+            }
+            (Self::Array(_), Some(_)) => Err(BACnetError::property(ErrorCode::InvalidArrayIndex)),
+            (_, Some(_)) => Err(BACnetError::property(ErrorCode::PropertyIsNotAnArray)),
+        }
+    }
+}
+
+/// Conversion of a value of the given variant into its Rust type, other
+/// variants are an `invalid-data-type` error
+macro_rules! impl_try_from_value {
+    ($type:ty, $variant:ident) => {
+        impl TryFrom<BACnetValue> for $type {
+            type Error = BACnetError;
+
+            fn try_from(value: BACnetValue) -> Result<Self, Self::Error> {
+                match value {
+                    BACnetValue::$variant(v) => Ok(v),
+                    _ => Err(BACnetError::property(ErrorCode::InvalidDataType)),
+                }
+            }
+        }
+    };
+}
+
+impl_try_from_value!(bool, Boolean);
+impl_try_from_value!(u32, Unsigned);
+impl_try_from_value!(i32, Signed);
+impl_try_from_value!(f32, Real);
+impl_try_from_value!(f64, Double);
+impl_try_from_value!(CharacterString, CharacterString);
+impl_try_from_value!(BACnetDate, Date);
+impl_try_from_value!(BACnetTime, Time);
+impl_try_from_value!(ObjectIdentifier, ObjectIdentifier);
+
+/// The decoded string of a character string, strings in a character set
+/// that is not decoded are a `character-set-not-supported` error
+impl TryFrom<BACnetValue> for String {
+    type Error = BACnetError;
+
+    fn try_from(value: BACnetValue) -> Result<Self, Self::Error> {
+        CharacterString::try_from(value)?
+            .into_string()
+            .map_err(|_| BACnetError::property(ErrorCode::CharacterSetNotSupported))
+    }
+}
+
+/// Elements of an array or list, a single value is taken as a list of one
+impl<T: TryFrom<BACnetValue, Error = BACnetError>> TryFrom<BACnetValue> for Vec<T> {
+    type Error = BACnetError;
+
+    fn try_from(value: BACnetValue) -> Result<Self, Self::Error> {
+        match value {
+            BACnetValue::Array(elements) | BACnetValue::List(elements) => {
+                elements.into_iter().map(T::try_from).collect()
+            }
+            value => Ok(vec![T::try_from(value)?]),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_context_element() {
+        let value = BACnetValue::Constructed(vec![
+            (0, BACnetValue::Enumerated(1)),
+            (2, BACnetValue::Real(1.5)),
+        ]);
+        assert_eq!(value.context(2), Some(&BACnetValue::Real(1.5)));
+        assert_eq!(value.context(1), None);
+        assert_eq!(BACnetValue::Null.context(0), None);
+    }
+
+    #[test]
+    fn test_array_element() {
+        let array = BACnetValue::Array(vec![BACnetValue::Unsigned(1), BACnetValue::Unsigned(2)]);
+
+        assert_eq!(array.clone().array_element(None), Ok(array.clone()));
+        assert_eq!(
+            array.clone().array_element(Some(0)),
+            Ok(BACnetValue::Unsigned(2))
+        );
+        assert_eq!(
+            array.clone().array_element(Some(2)),
+            Ok(BACnetValue::Unsigned(2))
+        );
+        assert_eq!(
+            array.array_element(Some(3)),
+            Err(BACnetError::property(ErrorCode::InvalidArrayIndex))
+        );
+        assert_eq!(
+            BACnetValue::Null.array_element(Some(1)),
+            Err(BACnetError::property(ErrorCode::PropertyIsNotAnArray))
+        );
+        // Lists have no index
+        let list = BACnetValue::List(vec![BACnetValue::Unsigned(1)]);
+        assert_eq!(
+            list.array_element(Some(0)),
+            Err(BACnetError::property(ErrorCode::PropertyIsNotAnArray))
+        );
+    }
+
+    #[test]
+    fn test_try_from() {
+        assert_eq!(f32::try_from(BACnetValue::Real(1.5)), Ok(1.5));
+        assert_eq!(
+            u32::try_from(BACnetValue::Enumerated(1)),
+            Err(BACnetError::property(ErrorCode::InvalidDataType))
+        );
+        assert_eq!(
+            Vec::<u32>::try_from(BACnetValue::Array(vec![
+                BACnetValue::Unsigned(1),
+                BACnetValue::Unsigned(2)
+            ])),
+            Ok(vec![1, 2])
+        );
+        assert_eq!(
+            Vec::<u32>::try_from(BACnetValue::List(vec![BACnetValue::Unsigned(3)])),
+            Ok(vec![3])
+        );
+        assert_eq!(
+            Vec::<String>::try_from(BACnetValue::CharacterString("a".into())),
+            Ok(vec!["a".to_string()])
+        );
+    }
+}
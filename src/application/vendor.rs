@@ -0,0 +1,260 @@
+use crate::application::BACnetValue;
+use crate::encoding::Reader;
+
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::Arc;
+
+/// Vendor identifier assigned by ASHRAE (12.11.6)
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(transparent))]
+pub struct VendorId(pub u16);
+
+/// A selection of the vendors registered with ASHRAE, the complete list is
+/// published at bacnet.org
+#[cfg(feature = "vendors")]
+const VENDORS: &[(u16, &str)] = &[
+    (0, "ASHRAE"),
+    (1, "NIST"),
+    (2, "The Trane Company"),
+    (3, "Daikin Applied Americas"),
+    (4, "PolarSoft"),
+    (5, "Johnson Controls, Inc."),
+    (6, "ABB (Formerly American Auto-Matrix)"),
+    (
+        7,
+        "Siemens Schweiz AG (Formerly: Landis & Staefa Division Europe)",
+    ),
+    (8, "Delta Controls"),
+    (9, "Siemens Schweiz AG"),
+    (10, "Schneider Electric"),
+    (11, "TAC"),
+    (12, "Orion Analysis Corporation"),
+    (13, "Teletrol Systems Inc."),
+    (14, "Cimetrics Technology"),
+    (15, "Cornell University"),
+    (16, "United Technologies Carrier"),
+    (17, "Honeywell Inc."),
+    (18, "Alerton / Honeywell"),
+    (19, "TAC AB"),
+    (20, "Hewlett-Packard Company"),
+    (21, "Dorsette's Inc."),
+    (22, "Siemens Schweiz AG (Formerly: Cerberus AG)"),
+    (23, "York Controls Group"),
+    (24, "Automated Logic Corporation"),
+    (25, "CSI Control Systems International"),
+    (26, "Phoenix Controls Corporation"),
+    (27, "Innovex Technologies, Inc."),
+    (28, "KMC Controls, Inc."),
+    (29, "Xn Technologies, Inc."),
+    (30, "Hyundai Information Technology Co., Ltd."),
+    (31, "Tokimec Inc."),
+    (32, "Simplex"),
+    (33, "North Building Technologies Limited"),
+    (34, "Notifier"),
+    (35, "Reliable Controls Corporation"),
+    (36, "Tridium Inc."),
+];
+
+impl VendorId {
+    /// The name the vendor is registered with, only known with the
+    /// `vendors` feature
+    pub fn name(self) -> Option<&'static str> {
+        #[cfg(feature = "vendors")]
+        if let Ok(i) = VENDORS.binary_search_by_key(&self.0, |(id, _)| *id) {
+            return Some(VENDORS[i].1);
+        }
+        None
+    }
+}
+
+impl From<u16> for VendorId {
+    fn from(id: u16) -> Self {
+        Self(id)
+    }
+}
+
+impl From<VendorId> for u16 {
+    fn from(id: VendorId) -> Self {
+        id.0
+    }
+}
+
+impl fmt::Display for VendorId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.name() {
+            Some(name) => write!(f, "{} ({})", name, self.0),
+            None => write!(f, "vendor {}", self.0),
+        }
+    }
+}
+
+/// Decoder of the encoded octets of a proprietary value
+pub type VendorDecoder = Arc<dyn Fn(&[u8]) -> crate::error::Result<BACnetValue> + Send + Sync>;
+
+/// The proprietary extensions of vendors, used by the stack to name and
+/// decode what it has no definition of
+///
+/// ```
+/// use bacnet::application::{BACnetValue, VendorId, VendorRegistry};
+///
+/// let mut vendors = VendorRegistry::new();
+/// vendors.register_private_transfer(VendorId(25), 8, |data| {
+///     Ok(BACnetValue::Unsigned(data.len() as u32))
+/// });
+/// let value = vendors.decode_private_transfer(VendorId(25), 8, &[0x21, 0x02]);
+/// assert_eq!(value.unwrap(), BACnetValue::Unsigned(2));
+/// ```
+#[derive(Clone, Default)]
+pub struct VendorRegistry {
+    object_types: HashMap<(VendorId, u16), String>,
+    properties: HashMap<(VendorId, u32), VendorDecoder>,
+    private_transfers: HashMap<(VendorId, u32), VendorDecoder>,
+}
+
+impl VendorRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Name a proprietary object type (128 to 1023)
+    pub fn register_object_type<S: Into<String>>(
+        &mut self,
+        vendor: VendorId,
+        object_type: u16,
+        name: S,
+    ) {
+        self.object_types.insert((vendor, object_type), name.into());
+    }
+
+    /// Decode the values of a property of the objects of the vendor's
+    /// devices, e.g. a proprietary property (512 and above)
+    pub fn register_property<F>(&mut self, vendor: VendorId, property: u32, decoder: F)
+    where
+        F: Fn(&[u8]) -> crate::error::Result<BACnetValue> + Send + Sync + 'static,
+    {
+        self.properties
+            .insert((vendor, property), Arc::new(decoder));
+    }
+
+    /// Decode the service parameters of a private transfer of the vendor
+    pub fn register_private_transfer<F>(
+        &mut self,
+        vendor: VendorId,
+        service_number: u32,
+        decoder: F,
+    ) where
+        F: Fn(&[u8]) -> crate::error::Result<BACnetValue> + Send + Sync + 'static,
+    {
+        self.private_transfers
+            .insert((vendor, service_number), Arc::new(decoder));
+    }
+
+    pub fn object_type_name(&self, vendor: VendorId, object_type: u16) -> Option<&str> {
+        self.object_types
+            .get(&(vendor, object_type))
+            .map(String::as_str)
+    }
+
+    /// Whether a decoder for the property of the vendor is registered
+    pub fn has_property(&self, vendor: VendorId, property: u32) -> bool {
+        self.properties.contains_key(&(vendor, property))
+    }
+
+    /// Decode the encoded value of a property, with the decoder of the
+    /// vendor or as application tagged values
+    pub fn decode_property(
+        &self,
+        vendor: VendorId,
+        property: u32,
+        data: &[u8],
+    ) -> crate::error::Result<BACnetValue> {
+        match self.properties.get(&(vendor, property)) {
+            Some(decoder) => decoder(data),
+            None => decode_values(data),
+        }
+    }
+
+    /// Decode the service parameters of a private transfer, with the decoder
+    /// of the vendor or as application tagged values
+    pub fn decode_private_transfer(
+        &self,
+        vendor: VendorId,
+        service_number: u32,
+        data: &[u8],
+    ) -> crate::error::Result<BACnetValue> {
+        match self.private_transfers.get(&(vendor, service_number)) {
+            Some(decoder) => decoder(data),
+            None => decode_values(data),
+        }
+    }
+}
+
+impl fmt::Debug for VendorRegistry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("VendorRegistry")
+            .field("object_types", &self.object_types)
+            .field("properties", &self.properties.keys().collect::<Vec<_>>())
+            .field(
+                "private_transfers",
+                &self.private_transfers.keys().collect::<Vec<_>>(),
+            )
+            .finish()
+    }
+}
+
+/// Application tagged values, a single value or several as an array
+pub(crate) fn decode_values(data: &[u8]) -> crate::error::Result<BACnetValue> {
+    let mut values = Reader::new(data).values_to_end()?;
+    match values.len() {
+        1 => Ok(values.remove(0)),
+        _ => Ok(BACnetValue::Array(values)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_vendor_id() {
+        assert_eq!(u16::from(VendorId::from(15)), 15);
+        assert_eq!(VendorId(900).name(), None);
+        assert_eq!(VendorId(900).to_string(), "vendor 900");
+        #[cfg(feature = "vendors")]
+        assert_eq!(VendorId(8).to_string(), "Delta Controls (8)");
+    }
+
+    #[test]
+    fn test_vendor_registry() {
+        let mut vendors = VendorRegistry::new();
+        vendors.register_object_type(VendorId(8), 130, "Schedule Group");
+        vendors.register_property(VendorId(8), 600, |data| {
+            Ok(BACnetValue::OctetString(
+                data.iter().rev().copied().collect(),
+            ))
+        });
+        assert_eq!(
+            vendors.object_type_name(VendorId(8), 130),
+            Some("Schedule Group")
+        );
+        assert_eq!(vendors.object_type_name(VendorId(9), 130), None);
+        assert!(vendors.has_property(VendorId(8), 600));
+
+        let value = vendors.decode_property(VendorId(8), 600, &[1, 2]).unwrap();
+        assert_eq!(value, BACnetValue::OctetString(vec![2, 1]));
+        // Without a decoder of the vendor the values are decoded generically
+        let data = [0x21, 0x02, 0x21, 0x03];
+        assert_eq!(
+            vendors.decode_property(VendorId(9), 600, &data).unwrap(),
+            BACnetValue::Array(vec![BACnetValue::Unsigned(2), BACnetValue::Unsigned(3)])
+        );
+        assert_eq!(
+            vendors
+                .decode_private_transfer(VendorId(8), 1, &data[..2])
+                .unwrap(),
+            BACnetValue::Unsigned(2)
+        );
+    }
+}
@@ -0,0 +1,568 @@
+//! Offline decoding of captured BACnet/IP traffic
+//!
+//! A [`CaptureReader`] reads pcap and pcapng files, e.g. recorded with
+//! Wireshark or tcpdump, and yields the BACnet/IP frames of UDP port 47808
+//! decoded into a [`Record`]. Ethernet (with VLAN tags), raw IPv4, Linux
+//! cooked and loopback captures are supported, IPv4 fragments are skipped.
+//!
+//! ```no_run
+//! # use bacnet::capture::CaptureReader;
+//! for record in CaptureReader::open("site.pcapng")? {
+//!     let record = record?;
+//!     println!("{:?} {} -> {}: {:?}", record.timestamp, record.source, record.destination, record.bvlc);
+//! }
+//! # Ok::<(), std::io::Error>(())
+//! ```
+
+#[cfg(feature = "serde")]
+use crate::json;
+use crate::transport::bacnetip::{BVLC, DEFAULT_PORT};
+use crate::Decode;
+
+#[cfg(feature = "serde")]
+use serde::ser::{Serialize, SerializeStruct, Serializer};
+use std::fs::File;
+use std::io::{BufReader, Error, ErrorKind, Read};
+use std::net::SocketAddrV4;
+use std::path::Path;
+use std::time::Duration;
+
+/// Magic number of pcap files with microsecond time stamps
+const PCAP_MAGIC: u32 = 0xa1b2_c3d4;
+/// Magic number of pcap files with nanosecond time stamps
+const PCAP_MAGIC_NANOS: u32 = 0xa1b2_3c4d;
+
+/// pcapng block types
+const SECTION_HEADER_BLOCK: u32 = 0x0A0D_0D0A;
+const INTERFACE_DESCRIPTION_BLOCK: u32 = 0x0000_0001;
+const SIMPLE_PACKET_BLOCK: u32 = 0x0000_0003;
+const ENHANCED_PACKET_BLOCK: u32 = 0x0000_0006;
+/// Byte-order magic of a pcapng section header
+const BYTE_ORDER_MAGIC: u32 = 0x1A2B_3C4D;
+/// if_tsresol option of an interface description
+const OPTION_TSRESOL: u16 = 9;
+
+/// Link-layer header types (LINKTYPE_*)
+const LINKTYPE_NULL: u16 = 0;
+const LINKTYPE_ETHERNET: u16 = 1;
+const LINKTYPE_RAW: u16 = 101;
+const LINKTYPE_LINUX_SLL: u16 = 113;
+
+const ETHERTYPE_IPV4: u16 = 0x0800;
+const ETHERTYPE_VLAN: u16 = 0x8100;
+const IP_PROTOCOL_UDP: u8 = 17;
+
+/// Largest block or packet accepted, larger ones mean a corrupt file
+const MAX_BLOCK: usize = 16 * 1024 * 1024;
+
+fn invalid(msg: &str) -> Error {
+    Error::new(ErrorKind::InvalidData, msg.to_string())
+}
+
+/// A BACnet/IP frame of a capture
+#[derive(Debug)]
+pub struct Record {
+    /// Time the frame was captured, since the Unix epoch
+    pub timestamp: Duration,
+    pub source: SocketAddrV4,
+    pub destination: SocketAddrV4,
+    /// The UDP payload
+    pub data: Vec<u8>,
+    /// The decoded frame, the error if it is not valid BACnet/IP
+    pub bvlc: crate::error::Result<BVLC>,
+}
+
+#[cfg(feature = "serde")]
+impl Record {
+    /// The record as a JSON object, e.g. one per line for `jq`
+    ///
+    /// The time stamp is in seconds, frames that could not be decoded
+    /// have an `error` instead of the `bvlc`.
+    pub fn to_json(&self) -> String {
+        json::to_string(self).expect("Records serialize without errors")
+    }
+}
+
+#[cfg(feature = "serde")]
+impl Serialize for Record {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut record = serializer.serialize_struct("Record", 5)?;
+        record.serialize_field("timestamp", &self.timestamp.as_secs_f64())?;
+        record.serialize_field("source", &self.source)?;
+        record.serialize_field("destination", &self.destination)?;
+        match &self.bvlc {
+            Ok(bvlc) => record.serialize_field("bvlc", bvlc)?,
+            Err(e) => record.serialize_field("error", &e.to_string())?,
+        }
+        record.serialize_field("data", &hex::encode(&self.data))?;
+        record.end()
+    }
+}
+
+/// A capture interface, pcap files have exactly one
+struct Interface {
+    linktype: u16,
+    /// Time stamp units per second
+    units_per_second: u64,
+}
+
+enum Format {
+    Pcap,
+    Pcapng,
+}
+
+/// Reads the BACnet/IP frames of a pcap or pcapng capture
+pub struct CaptureReader<R> {
+    reader: R,
+    format: Format,
+    big_endian: bool,
+    interfaces: Vec<Interface>,
+    port: u16,
+}
+
+impl CaptureReader<BufReader<File>> {
+    pub fn open<P: AsRef<Path>>(path: P) -> std::io::Result<Self> {
+        Self::new(BufReader::new(File::open(path)?))
+    }
+}
+
+impl<R: Read> CaptureReader<R> {
+    /// Read the file header, detecting the format
+    pub fn new(mut reader: R) -> std::io::Result<Self> {
+        let mut magic = [0; 4];
+        reader.read_exact(&mut magic)?;
+        let mut capture = Self {
+            reader,
+            format: Format::Pcap,
+            big_endian: false,
+            interfaces: Vec::new(),
+            port: DEFAULT_PORT,
+        };
+        if u32::from_le_bytes(magic) == SECTION_HEADER_BLOCK {
+            capture.format = Format::Pcapng;
+            capture.section_header()?;
+            return Ok(capture);
+        }
+
+        let nanos = match (u32::from_le_bytes(magic), u32::from_be_bytes(magic)) {
+            (PCAP_MAGIC, _) => false,
+            (PCAP_MAGIC_NANOS, _) => true,
+            (_, PCAP_MAGIC) => {
+                capture.big_endian = true;
+                false
+            }
+            (_, PCAP_MAGIC_NANOS) => {
+                capture.big_endian = true;
+                true
+            }
+            _ => return Err(invalid("Not a pcap or pcapng file")),
+        };
+        let mut header = [0; 20];
+        capture.reader.read_exact(&mut header)?;
+        capture.interfaces.push(Interface {
+            linktype: capture.u32(&header[16..20]) as u16,
+            units_per_second: if nanos { 1_000_000_000 } else { 1_000_000 },
+        });
+        Ok(capture)
+    }
+
+    /// Only frames from or to the UDP port are read, 47808 by default
+    pub fn set_port(&mut self, port: u16) {
+        self.port = port;
+    }
+
+    fn u16(&self, bytes: &[u8]) -> u16 {
+        let bytes = [bytes[0], bytes[1]];
+        match self.big_endian {
+            true => u16::from_be_bytes(bytes),
+            false => u16::from_le_bytes(bytes),
+        }
+    }
+
+    fn u32(&self, bytes: &[u8]) -> u32 {
+        let bytes = [bytes[0], bytes[1], bytes[2], bytes[3]];
+        match self.big_endian {
+            true => u32::from_be_bytes(bytes),
+            false => u32::from_le_bytes(bytes),
+        }
+    }
+
+    /// Read `len` octets, `None` at the end of the file
+    fn read(&mut self, len: usize) -> std::io::Result<Option<Vec<u8>>> {
+        if len > MAX_BLOCK {
+            return Err(invalid("Block too large"));
+        }
+        let mut data = vec![0; len];
+        let mut read = 0;
+        while read < len {
+            match self.reader.read(&mut data[read..])? {
+                0 if read == 0 => return Ok(None),
+                0 => return Err(Error::new(ErrorKind::UnexpectedEof, "Truncated capture")),
+                n => read += n,
+            }
+        }
+        Ok(Some(data))
+    }
+
+    /// The rest of a section header block after its type, which starts a
+    /// new section with its own byte order and interfaces
+    fn section_header(&mut self) -> std::io::Result<()> {
+        let header = self.read(8)?.ok_or_else(|| invalid("Truncated capture"))?;
+        self.big_endian = match u32::from_le_bytes([header[4], header[5], header[6], header[7]]) {
+            BYTE_ORDER_MAGIC => false,
+            _ if u32::from_be_bytes([header[4], header[5], header[6], header[7]])
+                == BYTE_ORDER_MAGIC =>
+            {
+                true
+            }
+            _ => return Err(invalid("Invalid byte-order magic")),
+        };
+        let length = self.u32(&header[..4]) as usize;
+        if length < 12 {
+            return Err(invalid("Invalid block length"));
+        }
+        self.read(length - 12)?
+            .ok_or_else(|| invalid("Truncated capture"))?;
+        self.interfaces.clear();
+        Ok(())
+    }
+
+    /// The next packet with its time stamp and link type
+    fn packet(&mut self) -> std::io::Result<Option<(Duration, u16, Vec<u8>)>> {
+        match self.format {
+            Format::Pcap => {
+                let header = match self.read(16)? {
+                    Some(header) => header,
+                    None => return Ok(None),
+                };
+                let (seconds, fraction) = (self.u32(&header[..4]), self.u32(&header[4..8]));
+                let data = self
+                    .read(self.u32(&header[8..12]) as usize)?
+                    .ok_or_else(|| invalid("Truncated capture"))?;
+                let interface = &self.interfaces[0];
+                let timestamp = Duration::from_secs(seconds as u64)
+                    + Duration::from_nanos(
+                        fraction as u64 * 1_000_000_000 / interface.units_per_second,
+                    );
+                Ok(Some((timestamp, interface.linktype, data)))
+            }
+            Format::Pcapng => loop {
+                let block_type = match self.read(4)? {
+                    Some(block_type) => self.u32(&block_type),
+                    None => return Ok(None),
+                };
+                if block_type == SECTION_HEADER_BLOCK {
+                    self.section_header()?;
+                    continue;
+                }
+                let length = self.read(4)?.ok_or_else(|| invalid("Truncated capture"))?;
+                let length = self.u32(&length) as usize;
+                if length < 12 || !length.is_multiple_of(4) {
+                    return Err(invalid("Invalid block length"));
+                }
+                let body = self
+                    .read(length - 8)?
+                    .ok_or_else(|| invalid("Truncated capture"))?;
+                let body = &body[..length - 12];
+                match block_type {
+                    INTERFACE_DESCRIPTION_BLOCK if body.len() >= 8 => {
+                        let interface = Interface {
+                            linktype: self.u16(&body[..2]),
+                            units_per_second: self.tsresol(&body[8..]),
+                        };
+                        self.interfaces.push(interface);
+                    }
+                    ENHANCED_PACKET_BLOCK if body.len() >= 20 => {
+                        let interface = self
+                            .interfaces
+                            .get(self.u32(&body[..4]) as usize)
+                            .ok_or_else(|| invalid("Unknown interface"))?;
+                        let units =
+                            ((self.u32(&body[4..8]) as u64) << 32) | self.u32(&body[8..12]) as u64;
+                        let per_second = interface.units_per_second;
+                        let nanos =
+                            (units % per_second) as u128 * 1_000_000_000 / per_second as u128;
+                        let timestamp = Duration::from_secs(units / per_second)
+                            + Duration::from_nanos(nanos as u64);
+                        let captured = self.u32(&body[12..16]) as usize;
+                        let data = body
+                            .get(20..20 + captured)
+                            .ok_or_else(|| invalid("Invalid packet length"))?;
+                        return Ok(Some((timestamp, interface.linktype, data.to_vec())));
+                    }
+                    SIMPLE_PACKET_BLOCK if body.len() >= 4 => {
+                        let interface = self
+                            .interfaces
+                            .first()
+                            .ok_or_else(|| invalid("Unknown interface"))?;
+                        let captured = (self.u32(&body[..4]) as usize).min(body.len() - 4);
+                        let data = body[4..4 + captured].to_vec();
+                        return Ok(Some((Duration::ZERO, interface.linktype, data)));
+                    }
+                    // Other blocks, e.g. statistics, are skipped
+                    _ => {}
+                }
+            },
+        }
+    }
+
+    /// Time stamp units per second from the options of an interface
+    /// description
+    fn tsresol(&self, mut options: &[u8]) -> u64 {
+        while options.len() >= 4 {
+            let (code, length) = (self.u16(&options[..2]), self.u16(&options[2..4]) as usize);
+            let value = match options.get(4..4 + length) {
+                Some(value) => value,
+                None => break,
+            };
+            if code == OPTION_TSRESOL && length == 1 {
+                let exponent = (value[0] & 0x7F) as u32;
+                let base: u64 = if value[0] & 0x80 == 0 { 10 } else { 2 };
+                return base.checked_pow(exponent).unwrap_or(1_000_000);
+            }
+            options = &options[(4 + length).div_ceil(4) * 4..];
+        }
+        1_000_000
+    }
+}
+
+/// The IPv4 packet of a link-layer frame
+fn ipv4(linktype: u16, frame: &[u8]) -> Option<&[u8]> {
+    match linktype {
+        LINKTYPE_ETHERNET => {
+            let mut offset = 12;
+            let mut ethertype = u16::from_be_bytes([*frame.get(offset)?, *frame.get(offset + 1)?]);
+            while ethertype == ETHERTYPE_VLAN {
+                offset += 4;
+                ethertype = u16::from_be_bytes([*frame.get(offset)?, *frame.get(offset + 1)?]);
+            }
+            match ethertype {
+                ETHERTYPE_IPV4 => frame.get(offset + 2..),
+                _ => None,
+            }
+        }
+        LINKTYPE_RAW => Some(frame),
+        LINKTYPE_LINUX_SLL => match u16::from_be_bytes([*frame.get(14)?, *frame.get(15)?]) {
+            ETHERTYPE_IPV4 => frame.get(16..),
+            _ => None,
+        },
+        // Address family in host byte order, 2 is AF_INET everywhere
+        LINKTYPE_NULL => match frame.get(..4)? {
+            [2, 0, 0, 0] | [0, 0, 0, 2] => frame.get(4..),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// Source, destination and payload of a UDP datagram in an IPv4 packet
+fn udp(packet: &[u8]) -> Option<(SocketAddrV4, SocketAddrV4, &[u8])> {
+    let header = (*packet.first()? & 0x0F) as usize * 4;
+    if *packet.first()? >> 4 != 4 || header < 20 || packet.len() < header {
+        return None;
+    }
+    // Fragments other than a first one without further fragments
+    let fragment = u16::from_be_bytes([packet[6], packet[7]]);
+    if fragment & 0x3FFF != 0 || packet[9] != IP_PROTOCOL_UDP {
+        return None;
+    }
+    let total = (u16::from_be_bytes([packet[2], packet[3]]) as usize).min(packet.len());
+    let ip = |o: usize| [packet[o], packet[o + 1], packet[o + 2], packet[o + 3]];
+    let datagram = packet.get(header..total)?;
+    let port = |o: usize| u16::from_be_bytes([datagram[o], datagram[o + 1]]);
+    if datagram.len() < 8 {
+        return None;
+    }
+    let source = SocketAddrV4::new(ip(12).into(), port(0));
+    let destination = SocketAddrV4::new(ip(16).into(), port(2));
+    let length = (port(4) as usize).clamp(8, datagram.len());
+    Some((source, destination, &datagram[8..length]))
+}
+
+impl<R: Read> Iterator for CaptureReader<R> {
+    type Item = std::io::Result<Record>;
+
+    /// The next BACnet/IP frame, other packets are skipped
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let (timestamp, linktype, frame) = match self.packet() {
+                Ok(Some(packet)) => packet,
+                Ok(None) => return None,
+                Err(e) => return Some(Err(e)),
+            };
+            let (source, destination, data) = match ipv4(linktype, &frame).and_then(udp) {
+                Some(datagram) => datagram,
+                None => continue,
+            };
+            if source.port() != self.port && destination.port() != self.port {
+                continue;
+            }
+            return Some(Ok(Record {
+                timestamp,
+                source,
+                destination,
+                data: data.to_vec(),
+                bvlc: BVLC::decode_slice(data),
+            }));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::application::APDU;
+    use crate::network::NPDU;
+    use crate::transport::bacnetip::BVLCFunction;
+
+    /// Who-Is as Original-Broadcast-NPDU
+    const WHO_IS: &str = "810b000801001008";
+
+    /// An Ethernet frame with an IPv4/UDP datagram from and to `port`
+    fn ethernet(port: u16, payload: &[u8]) -> Vec<u8> {
+        let mut frame = vec![0xFF; 6];
+        frame.extend_from_slice(&[0x00, 0x11, 0x22, 0x33, 0x44, 0x55]);
+        // VLAN tag
+        frame.extend_from_slice(&[0x81, 0x00, 0x00, 0x05, 0x08, 0x00]);
+        let total = (20 + 8 + payload.len()) as u16;
+        frame.extend_from_slice(&[0x45, 0x00]);
+        frame.extend_from_slice(&total.to_be_bytes());
+        frame.extend_from_slice(&[0, 0, 0x40, 0, 64, IP_PROTOCOL_UDP, 0, 0]);
+        frame.extend_from_slice(&[192, 168, 1, 10, 192, 168, 1, 255]);
+        frame.extend_from_slice(&port.to_be_bytes());
+        frame.extend_from_slice(&port.to_be_bytes());
+        frame.extend_from_slice(&((8 + payload.len()) as u16).to_be_bytes());
+        frame.extend_from_slice(&[0, 0]);
+        frame.extend_from_slice(payload);
+        frame
+    }
+
+    fn check(records: Vec<Record>) {
+        assert_eq!(records.len(), 2);
+        let record = &records[0];
+        assert_eq!(
+            record.source,
+            SocketAddrV4::new([192, 168, 1, 10].into(), DEFAULT_PORT)
+        );
+        assert_eq!(record.destination.ip().octets(), [192, 168, 1, 255]);
+        let npdu = NPDU::new(
+            APDU::unconfirmed_request(8, vec![]),
+            None,
+            None,
+            Default::default(),
+        );
+        match &record.bvlc.as_ref().unwrap().function {
+            BVLCFunction::OriginalBroadcastNPDU(n) => assert_eq!(n, &npdu),
+            function => panic!("Unexpected {:?}", function),
+        }
+        assert!(records[1].bvlc.is_err());
+    }
+
+    #[test]
+    fn test_pcap() {
+        let mut data = Vec::new();
+        data.extend_from_slice(&PCAP_MAGIC.to_be_bytes());
+        data.extend_from_slice(&[0, 2, 0, 4, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0xFF, 0xFF]);
+        data.extend_from_slice(&(LINKTYPE_ETHERNET as u32).to_be_bytes());
+        let payloads = [
+            (DEFAULT_PORT, hex::decode(WHO_IS).unwrap()),
+            // Not BACnet/IP
+            (53, vec![1, 2, 3]),
+            (DEFAULT_PORT, vec![0x81]),
+        ];
+        for (i, (port, payload)) in payloads.iter().enumerate() {
+            let frame = ethernet(*port, payload);
+            data.extend_from_slice(&(1_600_000_000 + i as u32).to_be_bytes());
+            data.extend_from_slice(&500_000u32.to_be_bytes());
+            data.extend_from_slice(&(frame.len() as u32).to_be_bytes());
+            data.extend_from_slice(&(frame.len() as u32).to_be_bytes());
+            data.extend_from_slice(&frame);
+        }
+
+        let records: Vec<_> = CaptureReader::new(&data[..])
+            .unwrap()
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(
+            records[0].timestamp,
+            Duration::from_millis(1_600_000_000_500)
+        );
+        check(records);
+
+        // Truncated files fail
+        let mut capture = CaptureReader::new(&data[..data.len() - 1]).unwrap();
+        assert!(capture.nth(1).unwrap().is_err());
+        assert!(CaptureReader::new(&[0u8; 24][..]).is_err());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_to_json() {
+        let data = hex::decode(WHO_IS).unwrap();
+        let mut record = Record {
+            timestamp: Duration::from_millis(1500),
+            source: SocketAddrV4::new([192, 168, 1, 10].into(), DEFAULT_PORT),
+            destination: SocketAddrV4::new([192, 168, 1, 255].into(), DEFAULT_PORT),
+            bvlc: BVLC::decode_slice(&data),
+            data,
+        };
+        assert_eq!(
+            record.to_json(),
+            concat!(
+                r#"{"timestamp":1.5,"source":"192.168.1.10:47808","#,
+                r#""destination":"192.168.1.255:47808","bvlc":{"bvlc_type":129,"#,
+                r#""function":{"OriginalBroadcastNPDU":{"version":1,"destination":null,"#,
+                r#""source":null,"data_expecting_reply":false,"priority":"Normal","#,
+                r#""content":{"APDU":{"pdu_type":"UnconfirmedRequest","invoke_id":null,"#,
+                r#""service_choice":8,"service":"WhoIs","values":[],"data":""}}}}},"#,
+                r#""data":"810b000801001008"}"#
+            )
+        );
+
+        record.data = vec![0x81];
+        record.bvlc = BVLC::decode_slice(&record.data);
+        assert!(record.to_json().contains(r#""error":"#));
+    }
+
+    #[test]
+    fn test_pcapng() {
+        let block = |block_type: u32, body: &[u8]| {
+            let mut body = body.to_vec();
+            body.resize(body.len().div_ceil(4) * 4, 0);
+            let length = (body.len() + 12) as u32;
+            let mut block = block_type.to_le_bytes().to_vec();
+            block.extend_from_slice(&length.to_le_bytes());
+            block.extend_from_slice(&body);
+            block.extend_from_slice(&length.to_le_bytes());
+            block
+        };
+        let mut data = block(
+            SECTION_HEADER_BLOCK,
+            &[
+                0x4D, 0x3C, 0x2B, 0x1A, 1, 0, 0, 0, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF,
+            ],
+        );
+        // Time stamps in milliseconds
+        data.extend(block(
+            INTERFACE_DESCRIPTION_BLOCK,
+            &[1, 0, 0, 0, 0, 0, 0, 0, 9, 0, 1, 0, 3, 0, 0, 0, 0, 0, 0, 0],
+        ));
+        for payload in [hex::decode(WHO_IS).unwrap(), vec![0x81]] {
+            let frame = ethernet(DEFAULT_PORT, &payload);
+            let mut body = vec![0; 4];
+            body.extend_from_slice(&0u32.to_le_bytes());
+            body.extend_from_slice(&1_500u32.to_le_bytes());
+            body.extend_from_slice(&(frame.len() as u32).to_le_bytes());
+            body.extend_from_slice(&(frame.len() as u32).to_le_bytes());
+            body.extend_from_slice(&frame);
+            data.extend(block(ENHANCED_PACKET_BLOCK, &body));
+        }
+
+        let records: Vec<_> = CaptureReader::new(&data[..])
+            .unwrap()
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(records[0].timestamp, Duration::from_millis(1_500));
+        check(records);
+    }
+}
@@ -0,0 +1,2071 @@
+//! High level client API
+//!
+//! A [`BacnetClient`] runs the network and application layer on top of a
+//! [`DataLink`]: it assigns invoke IDs, matches responses to requests and
+//! remembers the addresses of devices that announced themselves with I-Am,
+//! so devices are simply addressed by their instance number. Devices that
+//! are not known yet are looked for with a Who-Is.
+//!
+//! ```no_run
+//! # use bacnet::application::{ObjectIdentifier, ObjectType, PropertyIdentifier};
+//! # use bacnet::client::BacnetClient;
+//! # use bacnet::transport::bacnetip::{BacnetIp, DEFAULT_PORT};
+//! # use std::net::{Ipv4Addr, SocketAddrV4};
+//! # use std::time::Duration;
+//! # async_std::task::block_on(async {
+//! let link = BacnetIp::bind(
+//!     SocketAddrV4::new(Ipv4Addr::UNSPECIFIED, DEFAULT_PORT),
+//!     SocketAddrV4::new(Ipv4Addr::BROADCAST, DEFAULT_PORT),
+//! )
+//! .await?;
+//! let client = BacnetClient::new(link);
+//! client.who_is(None, Duration::from_secs(1)).await?;
+//! let value = client
+//!     .read(
+//!         1234,
+//!         ObjectIdentifier::new(ObjectType::AnalogInput, 1),
+//!         PropertyIdentifier::PresentValue,
+//!     )
+//!     .await?;
+//! # Ok::<(), bacnet::client::ClientError>(())
+//! # });
+//! ```
+
+use crate::application::*;
+use crate::clock::Clock;
+use crate::encoding::*;
+use crate::network::*;
+use crate::objects::{MINIMUM_ON_OFF_PRIORITY, PRIORITIES};
+use crate::station::{frame_span, Station};
+use crate::transport::bacnetip::BacnetIp;
+use crate::transport::{BoxFuture, DataLink};
+use crate::{Decode, Encode};
+
+pub use crate::station::Statistics;
+
+use async_std::channel::{self, Receiver, Sender};
+use async_std::task::{self, JoinHandle};
+use bytes::Bytes;
+use futures_lite::future::{self, Future};
+use std::collections::hash_map::Entry;
+use std::collections::{HashMap, HashSet};
+use std::convert::TryFrom;
+use std::net::SocketAddrV4;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::task::Poll;
+use std::time::{Duration, Instant};
+
+use tracing::{trace, warn};
+
+pub mod address_cache;
+pub use address_cache::*;
+pub mod discovery;
+pub use discovery::*;
+pub mod batch;
+pub use batch::*;
+pub mod trend_log;
+pub use trend_log::*;
+pub mod epics;
+pub use epics::*;
+pub mod notifications;
+pub use notifications::*;
+pub mod topology;
+pub use topology::*;
+
+/// Time to wait for the response to a confirmed request (12.11.27)
+pub const DEFAULT_APDU_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// Times a confirmed request is sent again when it is not answered in time
+/// (12.11.28)
+pub const DEFAULT_APDU_RETRIES: usize = 3;
+
+/// Time in which identical I-Am of a device are reported once, see
+/// [`BacnetClient::set_i_am_window`]
+pub const DEFAULT_I_AM_WINDOW: Duration = Duration::from_secs(1);
+
+/// Errors returned by [`BacnetClient`]
+#[derive(Debug)]
+pub enum ClientError {
+    Io(std::io::Error),
+    /// No response within the APDU timeout
+    Timeout,
+    /// The address of the device is not known, see [`BacnetClient::who_is`]
+    UnknownDevice(u32),
+    /// All invoke IDs for the device are in use
+    InvokeIdExhausted,
+    /// The device answered with an Error PDU
+    Error(BACnetError),
+    /// The device answered a ConfirmedPrivateTransfer with an Error PDU
+    PrivateTransfer(PrivateTransferError),
+    /// The device answered a VT-Close with an Error PDU
+    VtClose(VtCloseError),
+    /// The device rejected the request, with the [`RejectReason`]
+    Reject(u8),
+    /// The device aborted the transaction, with the [`AbortReason`]
+    Abort(u8),
+    /// The response does not belong to the request
+    UnexpectedResponse,
+    /// The value read does not have the requested datatype
+    UnexpectedDatatype(BACnetValue),
+    /// The priority is not one that can be written
+    InvalidPriority(u8),
+}
+
+impl std::fmt::Display for ClientError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Io(e) => write!(f, "I/O error: {}", e),
+            Self::Timeout => write!(f, "Request timed out"),
+            Self::UnknownDevice(d) => write!(f, "Address of device {} is unknown", d),
+            Self::InvokeIdExhausted => write!(f, "No invoke ID available"),
+            Self::Error(e) => write!(f, "Error: {}", e),
+            Self::PrivateTransfer(e) => write!(f, "Private transfer error: {}", e),
+            Self::VtClose(e) => write!(f, "VT close error: {}", e),
+            Self::Reject(r) => write!(f, "Request rejected: reason {}", r),
+            Self::Abort(r) => write!(f, "Request aborted: reason {}", r),
+            Self::UnexpectedResponse => write!(f, "Unexpected response"),
+            Self::UnexpectedDatatype(v) => write!(f, "Unexpected datatype: {:?}", v),
+            Self::InvalidPriority(p) => write!(f, "Invalid priority: {}", p),
+        }
+    }
+}
+
+impl std::error::Error for ClientError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Io(e) => Some(e),
+            Self::Error(e) => Some(e),
+            Self::PrivateTransfer(e) => Some(&e.error),
+            Self::VtClose(e) => Some(&e.error),
+            _ => None,
+        }
+    }
+}
+
+impl From<std::io::Error> for ClientError {
+    fn from(e: std::io::Error) -> Self {
+        Self::Io(e)
+    }
+}
+
+impl From<crate::Error> for ClientError {
+    /// Keeps the error of the stack as the inner error of an I/O error
+    fn from(e: crate::Error) -> Self {
+        Self::Io(e.into())
+    }
+}
+
+impl From<BACnetError> for ClientError {
+    fn from(e: BACnetError) -> Self {
+        Self::Error(e)
+    }
+}
+
+/// I-Am received within the window, to report bursts of identical ones
+/// once
+struct RecentIAm {
+    window: Duration,
+    received: HashMap<(Address, IAm), Instant>,
+}
+
+impl RecentIAm {
+    /// Whether the I-Am does not repeat one received within the window
+    fn is_new(&mut self, address: &Address, i_am: &IAm, now: Instant) -> bool {
+        let window = self.window;
+        self.received
+            .retain(|_, received| now.duration_since(*received) < window);
+        match self.received.entry((address.clone(), i_am.clone())) {
+            Entry::Occupied(_) => false,
+            Entry::Vacant(entry) => {
+                entry.insert(now);
+                true
+            }
+        }
+    }
+}
+
+struct Inner<D> {
+    station: Station<D>,
+    /// Device instance to address bindings learned from I-Am
+    devices: Mutex<AddressCache>,
+    /// Listeners of I-Am requests, see [`BacnetClient::who_is`]
+    i_am: Mutex<Vec<Sender<(Address, IAm)>>>,
+    recent_i_am: Mutex<RecentIAm>,
+    /// Listeners of I-Have requests, see [`BacnetClient::find_object`]
+    i_have: Mutex<Vec<Sender<(Address, IHave)>>>,
+    /// Listeners of unsolicited requests, see [`BacnetClient::notifications`]
+    notifications: Mutex<Vec<Sender<(Address, Notification)>>>,
+    /// Decoders of proprietary properties and private transfers
+    vendors: Mutex<VendorRegistry>,
+    /// Listeners of I-Am-Router-To-Network messages, see
+    /// [`BacnetClient::who_is_router_to_network`]
+    i_am_router: Mutex<Vec<Sender<Router>>>,
+}
+
+impl<D: DataLink> Inner<D> {
+    /// Process a received NPDU, returning the response to send if it
+    /// carried a confirmed request
+    fn receive(&self, mac: Vec<u8>, npdu: NPDU) -> Option<(Address, APDU)> {
+        let address = self.station.source(0, mac.clone(), &npdu);
+        let _frame = frame_span("received", &address, &npdu.content).entered();
+        let apdu = match npdu.content {
+            NPDUContent::APDU(apdu) => apdu,
+            NPDUContent::Message(message) => {
+                self.network_message(address, &mac, message);
+                return None;
+            }
+        };
+
+        match apdu {
+            APDU::UnconfirmedRequest {
+                service_choice,
+                ref user_data,
+            } => {
+                let vendors = self.vendors.lock().unwrap();
+                let notification =
+                    Notification::unconfirmed_with(service_choice, user_data, &vendors);
+                drop(vendors);
+                match notification {
+                    Ok(notification) => self.notify(address, notification),
+                    Err(e) => {
+                        trace!("Invalid request from {:?}: {}", address, e);
+                        self.station.decode_error();
+                    }
+                }
+            }
+            APDU::ConfirmedRequest {
+                invoke_id,
+                segment: Some(_),
+                ..
+            } => {
+                let reason = AbortReason::SegmentationNotSupported;
+                return Some((address, AbortPDU::new(true, invoke_id, reason).to_apdu()));
+            }
+            APDU::ConfirmedRequest {
+                invoke_id,
+                service_choice: service,
+                ref user_data,
+                ..
+            } => {
+                let response = match Notification::confirmed(service, user_data) {
+                    Some(Ok(notification)) => {
+                        self.notify(address.clone(), notification);
+                        APDU::simple_ack(invoke_id, service)
+                    }
+                    Some(Err(e)) => {
+                        trace!("Invalid request from {:?}: {}", address, e);
+                        self.station.decode_error();
+                        APDU::reject(invoke_id, RejectReason::InvalidTag as u8)
+                    }
+                    None => APDU::reject(invoke_id, RejectReason::UnrecognizedService as u8),
+                };
+                return Some((address, response));
+            }
+            APDU::SimpleAck { .. }
+            | APDU::ComplexAck { .. }
+            | APDU::Error { .. }
+            | APDU::Reject { .. }
+            | APDU::Abort { .. } => self.station.complete(address, apdu),
+            APDU::SegmentAck { .. } => trace!("Ignoring APDU from {:?}: {:?}", address, apdu),
+        }
+        None
+    }
+
+    /// Learn the routers to remote networks from I-Am-Router-To-Network
+    fn network_message(&self, address: Address, mac: &[u8], message: NetworkMessage) {
+        match message.networks() {
+            _ if message.message_type != NPDUMessage::IAmRouterToNetwork => {
+                trace!("Ignoring message from {:?}: {:?}", address, message)
+            }
+            Ok(networks) => {
+                self.station.add_routes(0, mac, &networks);
+                self.i_am_router.lock().unwrap().retain(|l| {
+                    let router = Router {
+                        address: address.clone(),
+                        networks: networks.clone(),
+                    };
+                    l.try_send(router).is_ok()
+                });
+            }
+            Err(e) => trace!("Invalid message from {:?}: {}", address, e),
+        }
+    }
+
+    fn notify(&self, address: Address, notification: Notification) {
+        match &notification {
+            Notification::IAm(i_am) => {
+                self.i_am(address.clone(), i_am.clone());
+                let mut recent = self.recent_i_am.lock().unwrap();
+                if !recent.is_new(&address, i_am, self.station.clock().now()) {
+                    trace!("Repeated I-Am from {:?}", address);
+                    return;
+                }
+            }
+            Notification::IHave(i_have) => self
+                .i_have
+                .lock()
+                .unwrap()
+                .retain(|l| l.try_send((address.clone(), i_have.clone())).is_ok()),
+            _ => {}
+        }
+        // Listeners that are behind miss the notification, closed ones are
+        // removed
+        self.notifications.lock().unwrap().retain(|listener| {
+            match listener.try_send((address.clone(), notification.clone())) {
+                Err(channel::TrySendError::Full(_)) => {
+                    trace!("Notification listener is behind");
+                    true
+                }
+                result => result.is_ok(),
+            }
+        });
+    }
+
+    fn i_am(&self, address: Address, i_am: IAm) {
+        trace!("I-Am from {:?}: {:?}", address, i_am);
+        let now = self.station.clock().now();
+        self.devices
+            .lock()
+            .unwrap()
+            .learn(address.clone(), &i_am, now);
+        self.i_am
+            .lock()
+            .unwrap()
+            .retain(|listener| listener.try_send((address.clone(), i_am.clone())).is_ok());
+    }
+}
+
+async fn run<D: DataLink>(inner: Arc<Inner<D>>) {
+    loop {
+        match inner.station.recv(0).await {
+            Ok((mac, npdu)) => {
+                if let Some((address, response)) = inner.receive(mac, npdu) {
+                    if let Err(e) = inner.station.send(&address, response).await {
+                        warn!("Failed to respond to {:?}: {}", address, e);
+                    }
+                }
+            }
+            Err(e) => {
+                warn!("Data link failed: {}", e);
+                break;
+            }
+        }
+    }
+}
+
+/// BACnet client on a single data link
+///
+/// Received frames are processed by a background task which is stopped
+/// when the client is dropped.
+pub struct BacnetClient<D: DataLink + 'static> {
+    inner: Arc<Inner<D>>,
+    task: Option<JoinHandle<()>>,
+    apdu_timeout: Duration,
+    apdu_retries: usize,
+    discovery_chunk_size: Option<u32>,
+}
+
+impl<D: DataLink + 'static> BacnetClient<D> {
+    pub fn new(link: D) -> Self {
+        let inner = Arc::new(Inner {
+            station: Station::new(link),
+            devices: Mutex::new(AddressCache::default()),
+            i_am: Mutex::new(Vec::new()),
+            recent_i_am: Mutex::new(RecentIAm {
+                window: DEFAULT_I_AM_WINDOW,
+                received: HashMap::new(),
+            }),
+            i_have: Mutex::new(Vec::new()),
+            notifications: Mutex::new(Vec::new()),
+            vendors: Mutex::new(VendorRegistry::new()),
+            i_am_router: Mutex::new(Vec::new()),
+        });
+        let task = task::spawn(run(inner.clone()));
+        Self {
+            inner,
+            task: Some(task),
+            apdu_timeout: DEFAULT_APDU_TIMEOUT,
+            apdu_retries: DEFAULT_APDU_RETRIES,
+            discovery_chunk_size: None,
+        }
+    }
+
+    pub fn apdu_timeout(&self) -> Duration {
+        self.apdu_timeout
+    }
+
+    pub fn set_apdu_timeout(&mut self, timeout: Duration) {
+        self.apdu_timeout = timeout;
+    }
+
+    pub fn apdu_retries(&self) -> usize {
+        self.apdu_retries
+    }
+
+    /// Send requests that are not answered within the APDU timeout again,
+    /// up to `retries` times
+    pub fn set_apdu_retries(&mut self, retries: usize) {
+        self.apdu_retries = retries;
+    }
+
+    pub fn request_window(&self) -> Option<usize> {
+        self.inner.station.window()
+    }
+
+    /// Limit the confirmed requests outstanding to a device at a time
+    ///
+    /// Requests to the same device are sent concurrently, up to the 256
+    /// invoke IDs available without a window. Requests beyond the window
+    /// wait for an earlier one to complete.
+    pub fn set_request_window(&mut self, window: Option<usize>) {
+        self.inner.station.set_window(window);
+    }
+
+    pub fn i_am_window(&self) -> Duration {
+        self.inner.recent_i_am.lock().unwrap().window
+    }
+
+    /// Report identical I-Am of a device received within `window` once in
+    /// the [`notifications`](Self::notifications), zero reports all
+    pub fn set_i_am_window(&mut self, window: Duration) {
+        self.inner.recent_i_am.lock().unwrap().window = window;
+    }
+
+    /// Read the time from `clock` instead of the system clock, for the APDU
+    /// timeout and the I-Am window
+    pub fn set_clock<C: Clock + 'static>(&mut self, clock: C) {
+        self.inner.station.set_clock(Arc::new(clock));
+    }
+
+    /// Decode proprietary properties of the devices of a vendor and the
+    /// private transfers of the vendor with the decoders of `vendors`
+    pub fn set_vendor_registry(&mut self, vendors: VendorRegistry) {
+        *self.inner.vendors.lock().unwrap() = vendors;
+    }
+
+    /// Counters of the frames sent and received so far
+    pub fn statistics(&self) -> Statistics {
+        self.inner.station.statistics()
+    }
+
+    /// The address of a device, if known
+    pub fn address(&self, device: u32) -> Option<Address> {
+        self.binding(device).map(|b| b.address)
+    }
+
+    /// Add a device that does not announce itself
+    pub fn add_device(&self, device: u32, address: Address) {
+        self.inner.devices.lock().unwrap().insert(device, address);
+    }
+
+    /// The addresses of the devices known, requests wait while they are
+    /// borrowed
+    pub fn address_cache(&self) -> MutexGuard<'_, AddressCache> {
+        self.inner.devices.lock().unwrap()
+    }
+
+    fn binding(&self, device: u32) -> Option<Binding> {
+        let now = self.inner.station.clock().now();
+        self.inner.devices.lock().unwrap().get(device, now).cloned()
+    }
+
+    /// The binding of a device, looked for with a Who-Is for the device if
+    /// it is not known, waiting up to the APDU timeout for its I-Am
+    async fn bind(&self, device: u32) -> Result<Binding, ClientError> {
+        if let Some(binding) = self.binding(device) {
+            return Ok(binding);
+        }
+        trace!("Looking for device {}", device);
+        let (sender, receiver) = channel::unbounded();
+        self.inner.i_am.lock().unwrap().push(sender);
+        let request = WhoIsBuilder::new().range(device, device).build();
+        self.inner
+            .station
+            .send(&Address::global_broadcast(), request)
+            .await?;
+        let answer = async {
+            while let Ok((_, i_am)) = receiver.recv().await {
+                if i_am.device_identifier.instance == device {
+                    break;
+                }
+            }
+        };
+        future::or(answer, self.inner.station.clock().sleep(self.apdu_timeout)).await;
+        self.binding(device)
+            .ok_or(ClientError::UnknownDevice(device))
+    }
+
+    async fn resolve(&self, device: u32) -> Result<Address, ClientError> {
+        Ok(self.bind(device).await?.address)
+    }
+
+    /// Broadcast a Who-Is and collect the I-Am answers received within `wait`
+    ///
+    /// With a range only devices with an instance number within the
+    /// (inclusive) limits answer.
+    pub async fn who_is(
+        &self,
+        range: Option<(u32, u32)>,
+        wait: Duration,
+    ) -> Result<Vec<(Address, IAm)>, ClientError> {
+        let who_is = match range {
+            Some((low, high)) => WhoIsBuilder::new().range(low, high),
+            None => WhoIsBuilder::new(),
+        };
+
+        let (sender, receiver) = channel::unbounded();
+        self.inner.i_am.lock().unwrap().push(sender);
+        let request = who_is.build();
+        self.inner
+            .station
+            .send(&Address::global_broadcast(), request)
+            .await?;
+
+        let mut devices = Vec::new();
+        collect(
+            &receiver,
+            &mut devices,
+            self.inner.station.clock().sleep(wait),
+        )
+        .await;
+        let in_range = |i_am: &IAm| {
+            range.is_none_or(|(low, high)| (low..=high).contains(&i_am.device_identifier.instance))
+        };
+        // Devices answering more than once are reported once
+        let mut answered = HashSet::new();
+        devices.retain(|device| in_range(&device.1) && answered.insert(device.clone()));
+        Ok(devices)
+    }
+
+    /// Broadcast a Who-Has and collect the I-Have answers received within
+    /// `wait`
+    ///
+    /// The object is searched by identifier or name, with a range only in
+    /// the devices with an instance number within the (inclusive) limits.
+    pub async fn find_object<O: Into<WhoHasObject>>(
+        &self,
+        object: O,
+        range: Option<(u32, u32)>,
+        wait: Duration,
+    ) -> Result<Vec<(Address, IHave)>, ClientError> {
+        let mut who_has = WhoHas::new(object);
+        if let Some((low, high)) = range {
+            who_has = who_has.range(low, high);
+        }
+
+        let (sender, receiver) = channel::unbounded();
+        self.inner.i_have.lock().unwrap().push(sender);
+        self.unconfirmed_request(
+            &Address::global_broadcast(),
+            UnconfirmedServiceChoice::WhoHas,
+            who_has.encode_vec()?,
+        )
+        .await?;
+
+        let mut found = Vec::new();
+        collect(
+            &receiver,
+            &mut found,
+            self.inner.station.clock().sleep(wait),
+        )
+        .await;
+        found.retain(|(_, i_have): &(Address, IHave)| {
+            range
+                .is_none_or(|(low, high)| (low..=high).contains(&i_have.device_identifier.instance))
+        });
+        found.dedup();
+        Ok(found)
+    }
+
+    /// Read a property (15.5)
+    ///
+    /// Properties with a single value are returned as that value, lists and
+    /// arrays as [`BACnetValue::Array`].
+    pub async fn read(
+        &self,
+        device: u32,
+        object: ObjectIdentifier,
+        property: PropertyIdentifier,
+    ) -> Result<BACnetValue, ClientError> {
+        self.read_property(device, object, property, None).await
+    }
+
+    /// Read a property and convert it into a Rust type
+    ///
+    /// ```no_run
+    /// # async fn f(client: bacnet::client::BacnetClient<bacnet::transport::bacnetip::BacnetIp>)
+    /// # -> Result<(), bacnet::client::ClientError> {
+    /// # use bacnet::application::*;
+    /// let object = ObjectIdentifier::new(ObjectType::AnalogInput, 1);
+    /// let value: f32 = client.read_as(1234, object, PropertyIdentifier::PresentValue).await?;
+    /// # Ok(()) }
+    /// ```
+    pub async fn read_as<T>(
+        &self,
+        device: u32,
+        object: ObjectIdentifier,
+        property: PropertyIdentifier,
+    ) -> Result<T, ClientError>
+    where
+        T: TryFrom<BACnetValue, Error = BACnetError>,
+    {
+        let value = self.read(device, object, property).await?;
+        T::try_from(value.clone()).map_err(|_| ClientError::UnexpectedDatatype(value))
+    }
+
+    /// Read a property, or with an index a single element of an array
+    /// property (index 0 is the number of elements)
+    pub async fn read_property(
+        &self,
+        device: u32,
+        object: ObjectIdentifier,
+        property: PropertyIdentifier,
+        array_index: Option<u32>,
+    ) -> Result<BACnetValue, ClientError> {
+        let binding = self.bind(device).await?;
+        let address = binding.address.clone();
+        let request = ReadPropertyRequest {
+            object_identifier: object,
+            property_identifier: property,
+            property_array_index: array_index,
+        };
+
+        let ack = self
+            .confirmed_request(
+                &address,
+                ConfirmedServiceChoice::ReadProperty,
+                request.encode_vec()?,
+            )
+            .await?;
+        let mut reader = Reader::new(&ack);
+        if reader.context_object_identifier(0)? != object
+            || reader.context_enumerated(1)? != property.number()
+            || reader.optional_context_unsigned(2)? != array_index
+        {
+            return Err(ClientError::UnexpectedResponse);
+        }
+        reader.opening_tag(3)?;
+        let value = reader.raw_until_closing_tag(3)?;
+        Ok(match binding.vendor_id {
+            Some(vendor) => {
+                let vendors = self.inner.vendors.lock().unwrap();
+                vendors.decode_property(vendor, property.number(), value)?
+            }
+            None => decode_values(value)?,
+        })
+    }
+
+    /// Write a property (15.9), optionally with a priority for commandable
+    /// properties
+    ///
+    /// Priorities range from 1 (highest) to 16, priority 6 is reserved for
+    /// minimum on/off times and cannot be written (19.2.1.3).
+    pub async fn write(
+        &self,
+        device: u32,
+        object: ObjectIdentifier,
+        property: PropertyIdentifier,
+        value: BACnetValue,
+        priority: Option<u8>,
+    ) -> Result<(), ClientError> {
+        self.write_property(device, object, property, None, value, priority)
+            .await
+    }
+
+    /// Relinquish a command of a commandable property by writing NULL at
+    /// the priority, 16 if none is given
+    pub async fn relinquish(
+        &self,
+        device: u32,
+        object: ObjectIdentifier,
+        property: PropertyIdentifier,
+        priority: Option<u8>,
+    ) -> Result<(), ClientError> {
+        let priority = priority.unwrap_or(PRIORITIES as u8);
+        self.write(device, object, property, BACnetValue::Null, Some(priority))
+            .await
+    }
+
+    /// Write a property, or with an index a single element of an array
+    /// property
+    pub async fn write_property(
+        &self,
+        device: u32,
+        object: ObjectIdentifier,
+        property: PropertyIdentifier,
+        array_index: Option<u32>,
+        value: BACnetValue,
+        priority: Option<u8>,
+    ) -> Result<(), ClientError> {
+        if let Some(priority) = priority {
+            if !(1..=PRIORITIES as u8).contains(&priority) || priority == MINIMUM_ON_OFF_PRIORITY {
+                return Err(ClientError::InvalidPriority(priority));
+            }
+        }
+        let address = self.resolve(device).await?;
+        let request = WritePropertyRequest {
+            property_array_index: array_index,
+            priority,
+            ..WritePropertyRequest::new(object, property, value)
+        };
+        let data = request.encode_vec()?;
+
+        let ack = self
+            .confirmed_request(&address, ConfirmedServiceChoice::WriteProperty, data)
+            .await?;
+        // WriteProperty is answered with a Simple-ACK
+        match ack.is_empty() {
+            true => Ok(()),
+            false => Err(ClientError::UnexpectedResponse),
+        }
+    }
+
+    /// Subscribe to COV notifications of an object (13.14)
+    ///
+    /// The lifetime is in seconds, without one the subscription is
+    /// indefinite.
+    pub async fn subscribe_cov(
+        &self,
+        device: u32,
+        process_id: u32,
+        object: ObjectIdentifier,
+        confirmed: bool,
+        lifetime: Option<u32>,
+    ) -> Result<(), ClientError> {
+        self.send_subscribe_cov(
+            device,
+            SubscribeCov {
+                subscriber_process_identifier: process_id,
+                monitored_object_identifier: object,
+                issue_confirmed_notifications: Some(confirmed),
+                lifetime,
+            },
+        )
+        .await
+    }
+
+    /// Subscribe to COV notifications of a property of an object (13.15),
+    /// optionally with its own COV increment
+    ///
+    /// Without `issue_confirmed_notifications` and `lifetime` in the
+    /// subscription of the request the subscription is cancelled.
+    pub async fn subscribe_cov_property(
+        &self,
+        device: u32,
+        request: SubscribeCovProperty,
+    ) -> Result<(), ClientError> {
+        let address = self.resolve(device).await?;
+        let ack = self
+            .confirmed_request(
+                &address,
+                ConfirmedServiceChoice::SubscribeCovProperty,
+                request.encode_vec()?,
+            )
+            .await?;
+        // SubscribeCOVProperty is answered with a Simple-ACK
+        match ack.is_empty() {
+            true => Ok(()),
+            false => Err(ClientError::UnexpectedResponse),
+        }
+    }
+
+    /// Cancel the subscription to COV notifications of an object (13.14)
+    pub async fn unsubscribe_cov(
+        &self,
+        device: u32,
+        process_id: u32,
+        object: ObjectIdentifier,
+    ) -> Result<(), ClientError> {
+        self.send_subscribe_cov(
+            device,
+            SubscribeCov {
+                subscriber_process_identifier: process_id,
+                monitored_object_identifier: object,
+                issue_confirmed_notifications: None,
+                lifetime: None,
+            },
+        )
+        .await
+    }
+
+    async fn send_subscribe_cov(
+        &self,
+        device: u32,
+        request: SubscribeCov,
+    ) -> Result<(), ClientError> {
+        let address = self.resolve(device).await?;
+        let ack = self
+            .confirmed_request(
+                &address,
+                ConfirmedServiceChoice::SubscribeCov,
+                request.encode_vec()?,
+            )
+            .await?;
+        // SubscribeCOV is answered with a Simple-ACK
+        match ack.is_empty() {
+            true => Ok(()),
+            false => Err(ClientError::UnexpectedResponse),
+        }
+    }
+
+    /// The objects of a device in alarm or with unacknowledged transitions
+    /// (13.12)
+    ///
+    /// As many GetEventInformation requests are sent as it takes to list
+    /// them all.
+    pub async fn get_event_information(
+        &self,
+        device: u32,
+    ) -> Result<Vec<EventSummary>, ClientError> {
+        let address = self.resolve(device).await?;
+        let mut summaries = Vec::new();
+        let mut request = GetEventInformationRequest::default();
+        loop {
+            let ack = self
+                .confirmed_request(
+                    &address,
+                    ConfirmedServiceChoice::GetEventInformation,
+                    request.encode_vec()?,
+                )
+                .await?;
+            let ack = GetEventInformationAck::decode_slice(&ack)?;
+            let next = GetEventInformationRequest::after(&ack);
+            let more_events = ack.more_events;
+            summaries.extend(ack.list_of_event_summaries);
+            if !more_events {
+                return Ok(summaries);
+            }
+            // A device that does not move on would be asked forever
+            if next.last_received_object_identifier.is_none() || next == request {
+                return Err(ClientError::UnexpectedResponse);
+            }
+            request = next;
+        }
+    }
+
+    /// The objects of a device in alarm (13.10), for devices not supporting
+    /// GetEventInformation
+    pub async fn get_alarm_summary(&self, device: u32) -> Result<Vec<AlarmSummary>, ClientError> {
+        let address = self.resolve(device).await?;
+        let ack = self
+            .confirmed_request(&address, ConfirmedServiceChoice::GetAlarmSummary, vec![])
+            .await?;
+        Ok(GetAlarmSummaryAck::decode_slice(&ack)?.list_of_alarm_summaries)
+    }
+
+    /// The event enrollments of a device passing the filters of the request
+    /// (13.11)
+    pub async fn get_enrollment_summary(
+        &self,
+        device: u32,
+        request: &GetEnrollmentSummaryRequest,
+    ) -> Result<Vec<EnrollmentSummary>, ClientError> {
+        let address = self.resolve(device).await?;
+        let ack = self
+            .confirmed_request(
+                &address,
+                ConfirmedServiceChoice::GetEnrollmentSummary,
+                request.encode_vec()?,
+            )
+            .await?;
+        Ok(GetEnrollmentSummaryAck::decode_slice(&ack)?.list_of_enrollment_summaries)
+    }
+
+    /// Read octets or records of a File object (15.1)
+    pub async fn atomic_read_file(
+        &self,
+        device: u32,
+        request: &AtomicReadFileRequest,
+    ) -> Result<AtomicReadFileAck, ClientError> {
+        let address = self.resolve(device).await?;
+        let ack = self
+            .confirmed_request(
+                &address,
+                ConfirmedServiceChoice::AtomicReadFile,
+                request.encode_vec()?,
+            )
+            .await?;
+        Ok(AtomicReadFileAck::decode_slice(&ack)?)
+    }
+
+    /// Write octets or records to a File object (15.2), returning where
+    /// they were written
+    pub async fn atomic_write_file(
+        &self,
+        device: u32,
+        request: &AtomicWriteFileRequest,
+    ) -> Result<AtomicWriteFileAck, ClientError> {
+        let address = self.resolve(device).await?;
+        let ack = self
+            .confirmed_request(
+                &address,
+                ConfirmedServiceChoice::AtomicWriteFile,
+                request.encode_vec()?,
+            )
+            .await?;
+        Ok(AtomicWriteFileAck::decode_slice(&ack)?)
+    }
+
+    /// Restart a device or step it through backup and restore (16.4)
+    pub async fn reinitialize_device(
+        &self,
+        device: u32,
+        request: &ReinitializeDeviceRequest,
+    ) -> Result<(), ClientError> {
+        let address = self.resolve(device).await?;
+        let ack = self
+            .confirmed_request(
+                &address,
+                ConfirmedServiceChoice::ReinitializeDevice,
+                request.encode_vec()?,
+            )
+            .await?;
+        // ReinitializeDevice is answered with a Simple-ACK
+        match ack.is_empty() {
+            true => Ok(()),
+            false => Err(ClientError::UnexpectedResponse),
+        }
+    }
+
+    /// Send a text message to a device (16.12, 16.13), waiting for it to be
+    /// acknowledged if `confirmed`
+    pub async fn send_text_message(
+        &self,
+        device: u32,
+        message: &TextMessage,
+        confirmed: bool,
+    ) -> Result<(), ClientError> {
+        let address = self.resolve(device).await?;
+        let data = message.encode_vec()?;
+        if !confirmed {
+            let service = UnconfirmedServiceChoice::UnconfirmedTextMessage;
+            return self.unconfirmed_request(&address, service, data).await;
+        }
+        let ack = self
+            .confirmed_request(&address, ConfirmedServiceChoice::ConfirmedTextMessage, data)
+            .await?;
+        // ConfirmedTextMessage is answered with a Simple-ACK
+        match ack.is_empty() {
+            true => Ok(()),
+            false => Err(ClientError::UnexpectedResponse),
+        }
+    }
+
+    /// Set the clocks of the devices at `address`, e.g. a broadcast, to the
+    /// time of the client with a UTCTimeSynchronization (16.8)
+    pub async fn synchronize_time(&self, address: &Address) -> Result<(), ClientError> {
+        let request = TimeSynchronization::from(self.inner.station.clock().date_time());
+        let service = UnconfirmedServiceChoice::UtcTimeSynchronization;
+        self.unconfirmed_request(address, service, request.encode_vec()?)
+            .await
+    }
+
+    /// Execute a vendor specific service on a device (16.2)
+    ///
+    /// A failed transfer returns the error parameters of the vendor with
+    /// [`ClientError::PrivateTransfer`].
+    pub async fn private_transfer(
+        &self,
+        device: u32,
+        transfer: &PrivateTransfer,
+    ) -> Result<PrivateTransferAck, ClientError> {
+        let address = self.resolve(device).await?;
+        let ack = self
+            .confirmed_request(
+                &address,
+                ConfirmedServiceChoice::ConfirmedPrivateTransfer,
+                transfer.encode_vec()?,
+            )
+            .await?;
+        Ok(PrivateTransferAck::decode_slice(&ack)?)
+    }
+
+    /// Send a vendor specific unconfirmed request to `address`, e.g. a
+    /// broadcast (16.3)
+    pub async fn unconfirmed_private_transfer(
+        &self,
+        address: &Address,
+        transfer: &PrivateTransfer,
+    ) -> Result<(), ClientError> {
+        let service = UnconfirmedServiceChoice::UnconfirmedPrivateTransfer;
+        self.unconfirmed_request(address, service, transfer.encode_vec()?)
+            .await
+    }
+
+    /// Open a virtual terminal session of `vt_class` on a device (17.1),
+    /// returning the session identifier of the device
+    pub async fn vt_open(&self, device: u32, request: &VtOpenRequest) -> Result<u8, ClientError> {
+        let address = self.resolve(device).await?;
+        let ack = self
+            .confirmed_request(
+                &address,
+                ConfirmedServiceChoice::VtOpen,
+                request.encode_vec()?,
+            )
+            .await?;
+        Ok(VtOpenAck::decode_slice(&ack)?.remote_vt_session_identifier)
+    }
+
+    /// Close virtual terminal sessions of a device (17.2)
+    ///
+    /// Sessions that could not be closed are listed by
+    /// [`ClientError::VtClose`].
+    pub async fn vt_close(&self, device: u32, request: &VtCloseRequest) -> Result<(), ClientError> {
+        let address = self.resolve(device).await?;
+        let ack = self
+            .confirmed_request(
+                &address,
+                ConfirmedServiceChoice::VtClose,
+                request.encode_vec()?,
+            )
+            .await?;
+        // VT-Close is answered with a Simple-ACK
+        match ack.is_empty() {
+            true => Ok(()),
+            false => Err(ClientError::UnexpectedResponse),
+        }
+    }
+
+    /// Send data on a virtual terminal session of a device (17.3)
+    ///
+    /// Data the device did not accept is to be sent again, starting after
+    /// the accepted octets.
+    pub async fn vt_data(
+        &self,
+        device: u32,
+        request: &VtDataRequest,
+    ) -> Result<VtDataAck, ClientError> {
+        let address = self.resolve(device).await?;
+        let ack = self
+            .confirmed_request(
+                &address,
+                ConfirmedServiceChoice::VtData,
+                request.encode_vec()?,
+            )
+            .await?;
+        Ok(VtDataAck::decode_slice(&ack)?)
+    }
+
+    /// Write the channels of a control group in the devices at `address`,
+    /// e.g. a broadcast (16.10.9)
+    pub async fn write_group(
+        &self,
+        address: &Address,
+        request: &WriteGroup,
+    ) -> Result<(), ClientError> {
+        let service = UnconfirmedServiceChoice::WriteGroup;
+        self.unconfirmed_request(address, service, request.encode_vec()?)
+            .await
+    }
+
+    /// Send a confirmed request and wait for the response, returning the
+    /// service ACK parameters
+    pub async fn confirmed_request(
+        &self,
+        address: &Address,
+        service: ConfirmedServiceChoice,
+        data: Vec<u8>,
+    ) -> Result<Bytes, ClientError> {
+        self.inner
+            .station
+            .confirmed_request(address, service, data, self.apdu_timeout, self.apdu_retries)
+            .await
+    }
+
+    pub async fn unconfirmed_request(
+        &self,
+        address: &Address,
+        service: UnconfirmedServiceChoice,
+        data: Vec<u8>,
+    ) -> Result<(), ClientError> {
+        let request = APDU::unconfirmed_request(service as u8, data);
+        self.inner.station.send(address, request).await?;
+        Ok(())
+    }
+}
+
+impl BacnetClient<BacnetIp> {
+    /// A client on BACnet/IP in another IP subnet than the devices,
+    /// registered as foreign device with the BBMD at `bbmd` for `ttl`
+    ///
+    /// Discovery and other broadcasts reach the network of the BBMD, see
+    /// [`BacnetIp::bind_foreign`].
+    pub async fn foreign_device(
+        local: SocketAddrV4,
+        bbmd: SocketAddrV4,
+        ttl: Duration,
+    ) -> Result<Self, ClientError> {
+        Ok(Self::new(BacnetIp::bind_foreign(local, bbmd, ttl).await?))
+    }
+}
+
+impl<D: DataLink + 'static> Drop for BacnetClient<D> {
+    fn drop(&mut self) {
+        if let Some(task) = self.task.take() {
+            task::spawn(task.cancel());
+        }
+    }
+}
+
+/// Run futures concurrently, returning their outputs in order
+pub(crate) async fn join_all<F: Future>(futures: Vec<F>) -> Vec<F::Output> {
+    let mut futures: Vec<_> = futures.into_iter().map(|f| Some(Box::pin(f))).collect();
+    let mut outputs: Vec<Option<F::Output>> = futures.iter().map(|_| None).collect();
+    future::poll_fn(|cx| {
+        let mut done = true;
+        for (future, output) in futures.iter_mut().zip(outputs.iter_mut()) {
+            if let Some(f) = future {
+                match f.as_mut().poll(cx) {
+                    Poll::Ready(o) => {
+                        *output = Some(o);
+                        *future = None;
+                    }
+                    Poll::Pending => done = false,
+                }
+            }
+        }
+        match done {
+            true => Poll::Ready(()),
+            false => Poll::Pending,
+        }
+    })
+    .await;
+    outputs.into_iter().flatten().collect()
+}
+
+/// Collect the items received until `wait` ends
+async fn collect<T>(receiver: &Receiver<T>, items: &mut Vec<T>, wait: BoxFuture<'static, ()>) {
+    let receive = async {
+        while let Ok(item) = receiver.recv().await {
+            items.push(item);
+        }
+    };
+    future::or(receive, wait).await;
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    /// One end of an in-memory link between two stations
+    pub(crate) struct MockLink {
+        mac: Vec<u8>,
+        sender: Sender<(Vec<u8>, NPDU)>,
+        receiver: Receiver<(Vec<u8>, NPDU)>,
+    }
+
+    /// Two connected links, with MAC address 1 and 2
+    pub(crate) fn link_pair() -> (MockLink, MockLink) {
+        let (a_sender, a_receiver) = channel::unbounded();
+        let (b_sender, b_receiver) = channel::unbounded();
+        let a = MockLink {
+            mac: vec![1],
+            sender: b_sender,
+            receiver: a_receiver,
+        };
+        let b = MockLink {
+            mac: vec![2],
+            sender: a_sender,
+            receiver: b_receiver,
+        };
+        (a, b)
+    }
+
+    impl DataLink for MockLink {
+        fn send<'a>(
+            &'a self,
+            _mac: &'a [u8],
+            npdu: &'a NPDU,
+        ) -> BoxFuture<'a, std::io::Result<()>> {
+            Box::pin(async move {
+                let _ = self.sender.send((self.mac.clone(), npdu.clone())).await;
+                Ok(())
+            })
+        }
+
+        fn recv(&self) -> BoxFuture<'_, std::io::Result<(Vec<u8>, NPDU)>> {
+            Box::pin(async move {
+                self.receiver
+                    .recv()
+                    .await
+                    .map_err(|_| std::io::Error::new(std::io::ErrorKind::BrokenPipe, "Link closed"))
+            })
+        }
+    }
+
+    pub(crate) fn apdu(npdu: NPDU) -> APDU {
+        match npdu.content {
+            NPDUContent::APDU(apdu) => apdu,
+            NPDUContent::Message(_) => panic!("Not an APDU"),
+        }
+    }
+
+    pub(crate) async fn reply(link: &MockLink, apdu: APDU) {
+        let npdu = NPDU::new(apdu, None, None, NPDUPriority::Normal);
+        link.send(&[1], &npdu).await.unwrap();
+    }
+
+    pub(crate) fn i_am(instance: u32) -> APDU {
+        let i_am = IAm {
+            device_identifier: ObjectIdentifier::new(ObjectType::Device, instance),
+            max_apdu_length_accepted: 1476,
+            segmentation_supported: Segmentation::NoSegmentation,
+            vendor_id: 15,
+        };
+        let data = crate::Encode::encode_vec(&i_am).unwrap();
+        APDU::unconfirmed_request(UnconfirmedServiceChoice::IAm as u8, data)
+    }
+
+    fn analog_input() -> ObjectIdentifier {
+        ObjectIdentifier::new(ObjectType::AnalogInput, 1)
+    }
+
+    #[test]
+    fn test_who_is() {
+        task::block_on(async {
+            let (link, device) = link_pair();
+            let client = BacnetClient::new(link);
+
+            let devices = async {
+                client
+                    .who_is(Some((10, 20)), Duration::from_millis(100))
+                    .await
+                    .unwrap()
+            };
+            let respond = task::spawn(async move {
+                let (_, npdu) = device.recv().await.unwrap();
+                assert_eq!(npdu.destination.as_ref().unwrap().net, GLOBAL_BROADCAST);
+                let request = apdu(npdu);
+                assert_eq!(
+                    request.service_choice().unwrap(),
+                    UnconfirmedServiceChoice::WhoIs as u8
+                );
+                assert_eq!(request.user_data(), &[0x09, 10, 0x19, 20]);
+                reply(&device, i_am(12)).await;
+                reply(&device, i_am(12)).await;
+                // Not in the requested range
+                reply(&device, i_am(30)).await;
+            });
+            let devices = devices.await;
+            respond.await;
+
+            assert_eq!(devices.len(), 1);
+            assert_eq!(devices[0].0, Address::local(vec![2]));
+            assert_eq!(devices[0].1.device_identifier.instance, 12);
+            assert_eq!(client.address(12), Some(Address::local(vec![2])));
+        });
+    }
+
+    #[test]
+    fn test_i_am_window() {
+        use futures_lite::StreamExt;
+
+        task::block_on(async {
+            let (link, device) = link_pair();
+            let mut client = BacnetClient::new(link);
+            assert_eq!(client.i_am_window(), DEFAULT_I_AM_WINDOW);
+            let mut notifications = client.notifications();
+            let next = |notifications| {
+                async_std::future::timeout(Duration::from_millis(100), notifications)
+            };
+
+            // A burst of identical I-Am is reported once
+            reply(&device, i_am(12)).await;
+            reply(&device, i_am(12)).await;
+            reply(&device, i_am(13)).await;
+            let (_, first) = notifications.next().await.unwrap();
+            let (_, second) = notifications.next().await.unwrap();
+            assert!(matches!(first, Notification::IAm(i) if i.device_identifier.instance == 12));
+            assert!(matches!(second, Notification::IAm(i) if i.device_identifier.instance == 13));
+            assert!(next(notifications.next()).await.is_err());
+
+            client.set_i_am_window(Duration::ZERO);
+            reply(&device, i_am(12)).await;
+            reply(&device, i_am(12)).await;
+            assert!(notifications.next().await.is_some());
+            assert!(notifications.next().await.is_some());
+        });
+    }
+
+    #[test]
+    fn test_find_object() {
+        task::block_on(async {
+            let (link, device) = link_pair();
+            let client = BacnetClient::new(link);
+
+            let found = async {
+                client
+                    .find_object("OATemp", None, Duration::from_millis(100))
+                    .await
+                    .unwrap()
+            };
+            let respond = task::spawn(async move {
+                let request = apdu(device.recv().await.unwrap().1);
+                assert_eq!(
+                    request.service_choice().unwrap(),
+                    UnconfirmedServiceChoice::WhoHas as u8
+                );
+                assert_eq!(
+                    request.user_data(),
+                    &hex::decode("3d07004f4154656d70").unwrap()[..]
+                );
+                let i_have = IHave {
+                    device_identifier: ObjectIdentifier::new(ObjectType::Device, 8),
+                    object_identifier: analog_input(),
+                    object_name: "OATemp".into(),
+                };
+                let data = crate::Encode::encode_vec(&i_have).unwrap();
+                let service = UnconfirmedServiceChoice::IHave as u8;
+                reply(&device, APDU::unconfirmed_request(service, data)).await;
+            });
+            let found = found.await;
+            respond.await;
+
+            assert_eq!(found.len(), 1);
+            assert_eq!(found[0].0, Address::local(vec![2]));
+            assert_eq!(found[0].1.object_identifier, analog_input());
+        });
+    }
+
+    #[test]
+    fn test_read() {
+        task::block_on(async {
+            let (link, device) = link_pair();
+            let client = BacnetClient::new(link);
+            client.add_device(12, Address::local(vec![2]));
+
+            let read = client.read(12, analog_input(), PropertyIdentifier::PresentValue);
+            let respond = task::spawn(async move {
+                let (_, npdu) = device.recv().await.unwrap();
+                assert!(npdu.data_expecting_reply);
+                let request = apdu(npdu);
+                assert_eq!(request.pdu_type(), BACnetPDU::ConfirmedRequest);
+                assert_eq!(
+                    request.user_data(),
+                    &hex::decode("0c000000011955").unwrap()[..]
+                );
+                let mut ack = request.user_data().to_vec();
+                ack.extend_from_slice(&hex::decode("3e4441a000003f").unwrap());
+                reply(
+                    &device,
+                    APDU::complex_ack(request.invoke_id().unwrap(), 12, ack),
+                )
+                .await;
+            });
+            let value = read.await;
+            respond.await;
+            assert_eq!(value.unwrap(), BACnetValue::Real(20.0));
+        });
+    }
+
+    #[test]
+    fn test_read_unknown_device() {
+        task::block_on(async {
+            let (link, device) = link_pair();
+            let client = BacnetClient::new(link);
+
+            // The device is looked for with a Who-Is for its instance
+            let read = client.read(12, analog_input(), PropertyIdentifier::PresentValue);
+            let respond = task::spawn(async move {
+                let (_, npdu) = device.recv().await.unwrap();
+                assert_eq!(npdu.destination.as_ref().unwrap().net, GLOBAL_BROADCAST);
+                let request = apdu(npdu);
+                assert_eq!(
+                    request.service_choice().unwrap(),
+                    UnconfirmedServiceChoice::WhoIs as u8
+                );
+                assert_eq!(request.user_data(), &[0x09, 12, 0x19, 12]);
+                reply(&device, i_am(12)).await;
+
+                let request = apdu(device.recv().await.unwrap().1);
+                let mut ack = request.user_data().to_vec();
+                ack.extend_from_slice(&hex::decode("3e4441a000003f").unwrap());
+                reply(
+                    &device,
+                    APDU::complex_ack(request.invoke_id().unwrap(), 12, ack),
+                )
+                .await;
+            });
+            let value = read.await;
+            respond.await;
+            assert_eq!(value.unwrap(), BACnetValue::Real(20.0));
+            assert_eq!(client.address_cache().len(), 1);
+        });
+    }
+
+    #[test]
+    fn test_abort_by_client_ignored() {
+        task::block_on(async {
+            let (link, device) = link_pair();
+            let client = BacnetClient::new(link);
+            client.add_device(12, Address::local(vec![2]));
+
+            let read = client.read(12, analog_input(), PropertyIdentifier::PresentValue);
+            let respond = task::spawn(async move {
+                let request = apdu(device.recv().await.unwrap().1);
+                let invoke_id = request.invoke_id().unwrap();
+                // Only an abort by the server ends the transaction
+                let abort = AbortPDU::new(false, invoke_id, AbortReason::Other);
+                reply(&device, abort.to_apdu()).await;
+                let abort = AbortPDU::new(true, invoke_id, AbortReason::OutOfResources);
+                reply(&device, abort.to_apdu()).await;
+            });
+            let value = read.await;
+            respond.await;
+            assert!(matches!(
+                value,
+                Err(ClientError::Abort(r)) if r == AbortReason::OutOfResources as u8
+            ));
+        });
+    }
+
+    #[test]
+    fn test_segmented_response() {
+        task::block_on(async {
+            let (link, device) = link_pair();
+            let client = BacnetClient::new(link);
+            client.add_device(12, Address::local(vec![2]));
+
+            let read = client.read(12, analog_input(), PropertyIdentifier::PresentValue);
+            let respond = task::spawn(async move {
+                let request = apdu(device.recv().await.unwrap().1);
+                assert!(matches!(
+                    request,
+                    APDU::ConfirmedRequest {
+                        segmented_response_accepted: true,
+                        ..
+                    }
+                ));
+                let mut ack = request.user_data().to_vec();
+                ack.extend_from_slice(&hex::decode("3e4441a000003f").unwrap());
+                let ack = APDU::complex_ack(request.invoke_id().unwrap(), 12, ack);
+                // Three segments, in windows of two after the first
+                let mut sender = SegmentedSender::new(&ack, 12, 2, None).unwrap();
+                let mut windows = 0;
+                while !sender.is_complete() {
+                    for segment in sender.window() {
+                        reply(&device, segment).await;
+                    }
+                    let ack = apdu(device.recv().await.unwrap().1);
+                    assert!(matches!(ack, APDU::SegmentAck { server: false, .. }));
+                    sender.segment_ack(&ack).unwrap();
+                    windows += 1;
+                }
+                windows
+            });
+            let value = read.await;
+            assert_eq!(respond.await, 2);
+            assert_eq!(value.unwrap(), BACnetValue::Real(20.0));
+        });
+    }
+
+    #[test]
+    fn test_segmented_response_aborted() {
+        task::block_on(async {
+            let (link, device) = link_pair();
+            let client = BacnetClient::new(link);
+            client.add_device(12, Address::local(vec![2]));
+
+            let read = client.read(12, analog_input(), PropertyIdentifier::PresentValue);
+            let respond = task::spawn(async move {
+                let request = apdu(device.recv().await.unwrap().1);
+                let invoke_id = request.invoke_id().unwrap();
+                let segment = APDU::ComplexAck {
+                    invoke_id,
+                    segment: Some(Segment {
+                        more_follows: true,
+                        sequence_number: 0,
+                        proposed_window_size: 1,
+                    }),
+                    service_choice: 12,
+                    user_data: request.into_user_data(),
+                };
+                reply(&device, segment).await;
+                let ack = apdu(device.recv().await.unwrap().1);
+                // The server gives up after the first segment
+                let reason = AbortReason::ApplicationExceededReplyTime;
+                reply(&device, AbortPDU::new(true, invoke_id, reason).to_apdu()).await;
+                ack
+            });
+            let value = read.await;
+            assert_eq!(respond.await.pdu_type(), BACnetPDU::SegmentACK);
+            assert!(matches!(value, Err(ClientError::Abort(8))));
+        });
+    }
+
+    #[test]
+    fn test_read_array_element() {
+        task::block_on(async {
+            let (link, device) = link_pair();
+            let client = BacnetClient::new(link);
+            client.add_device(12, Address::local(vec![2]));
+
+            let respond = task::spawn(async move {
+                for _ in 0..2 {
+                    let request = apdu(device.recv().await.unwrap().1);
+                    // Echo object, property and index, then a Null
+                    let mut ack = request.user_data().to_vec();
+                    ack.extend_from_slice(&[0x3e, 0x00, 0x3f]);
+                    reply(
+                        &device,
+                        APDU::complex_ack(request.invoke_id().unwrap(), 12, ack),
+                    )
+                    .await;
+                }
+            });
+            let read = client
+                .read_property(
+                    12,
+                    analog_input(),
+                    PropertyIdentifier::PriorityArray,
+                    Some(8),
+                )
+                .await;
+            assert_eq!(read.unwrap(), BACnetValue::Null);
+            let read = client
+                .read_as::<f32>(12, analog_input(), PropertyIdentifier::PresentValue)
+                .await;
+            assert!(matches!(
+                read,
+                Err(ClientError::UnexpectedDatatype(BACnetValue::Null))
+            ));
+            respond.await;
+        });
+    }
+
+    #[test]
+    fn test_read_vendor_property() {
+        use futures_lite::StreamExt;
+
+        task::block_on(async {
+            let (link, device) = link_pair();
+            let mut client = BacnetClient::new(link);
+            let mut vendors = VendorRegistry::new();
+            vendors.register_property(
+                VendorId(15),
+                PropertyIdentifier::Description.number(),
+                |data| Ok(BACnetValue::OctetString(data.to_vec())),
+            );
+            client.set_vendor_registry(vendors);
+
+            // The vendor of the device is learned from its I-Am
+            let mut notifications = client.notifications();
+            reply(&device, i_am(12)).await;
+            notifications.next().await.unwrap();
+
+            let respond = task::spawn(async move {
+                for _ in 0..2 {
+                    let request = apdu(device.recv().await.unwrap().1);
+                    let mut ack = request.user_data().to_vec();
+                    ack.extend_from_slice(&[0x3e, 0x21, 0x02, 0x3f]);
+                    reply(
+                        &device,
+                        APDU::complex_ack(request.invoke_id().unwrap(), 12, ack),
+                    )
+                    .await;
+                }
+            });
+            let read = client
+                .read_property(12, analog_input(), PropertyIdentifier::Description, None)
+                .await;
+            assert_eq!(read.unwrap(), BACnetValue::OctetString(vec![0x21, 0x02]));
+            let read = client
+                .read_property(12, analog_input(), PropertyIdentifier::PresentValue, None)
+                .await;
+            assert_eq!(read.unwrap(), BACnetValue::Unsigned(2));
+            respond.await;
+        });
+    }
+
+    #[test]
+    fn test_write_error() {
+        task::block_on(async {
+            let (link, device) = link_pair();
+            let client = BacnetClient::new(link);
+            client.add_device(12, Address::local(vec![2]));
+
+            let write = client.write(
+                12,
+                analog_input(),
+                PropertyIdentifier::PresentValue,
+                BACnetValue::Real(1.0),
+                Some(8),
+            );
+            let respond = task::spawn(async move {
+                let request = apdu(device.recv().await.unwrap().1);
+                assert_eq!(
+                    request.user_data(),
+                    &hex::decode("0c0000000119553e443f8000003f4908").unwrap()[..]
+                );
+                let error = vec![0x91, 0x02, 0x91, 0x28]; // property, write-access-denied
+                reply(
+                    &device,
+                    APDU::error(request.invoke_id().unwrap(), 15, error),
+                )
+                .await;
+            });
+            let result = write.await;
+            respond.await;
+            assert!(matches!(
+                result,
+                Err(ClientError::Error(e)) if e == BACnetError::property(ErrorCode::WriteAccessDenied)
+            ));
+        });
+    }
+
+    #[test]
+    fn test_relinquish() {
+        task::block_on(async {
+            let (link, device) = link_pair();
+            let client = BacnetClient::new(link);
+            client.add_device(12, Address::local(vec![2]));
+
+            let respond = task::spawn(async move {
+                let request = apdu(device.recv().await.unwrap().1);
+                assert_eq!(
+                    request.user_data(),
+                    &hex::decode("0c0000000119553e003f4910").unwrap()[..]
+                );
+                reply(&device, APDU::simple_ack(request.invoke_id().unwrap(), 15)).await;
+            });
+            client
+                .relinquish(12, analog_input(), PropertyIdentifier::PresentValue, None)
+                .await
+                .unwrap();
+            respond.await;
+
+            for priority in [0, 6, 17] {
+                let write = client.write(
+                    12,
+                    analog_input(),
+                    PropertyIdentifier::PresentValue,
+                    BACnetValue::Real(1.0),
+                    Some(priority),
+                );
+                assert!(matches!(
+                    write.await,
+                    Err(ClientError::InvalidPriority(p)) if p == priority
+                ));
+            }
+        });
+    }
+
+    #[test]
+    fn test_subscribe_cov() {
+        task::block_on(async {
+            let (link, device) = link_pair();
+            let client = BacnetClient::new(link);
+            client.add_device(12, Address::local(vec![2]));
+
+            let respond = task::spawn(async move {
+                let mut requests = Vec::new();
+                for _ in 0..2 {
+                    let request = apdu(device.recv().await.unwrap().1);
+                    requests.push(SubscribeCov::decode_slice(request.user_data()).unwrap());
+                    let ack = APDU::simple_ack(request.invoke_id().unwrap(), 5);
+                    reply(&device, ack).await;
+                }
+                requests
+            });
+            client
+                .subscribe_cov(12, 1, analog_input(), true, Some(300))
+                .await
+                .unwrap();
+            client.unsubscribe_cov(12, 1, analog_input()).await.unwrap();
+            let requests = respond.await;
+            assert_eq!(requests[0].issue_confirmed_notifications, Some(true));
+            assert_eq!(requests[0].lifetime, Some(300));
+            assert!(!requests[0].is_cancellation());
+            assert!(requests[1].is_cancellation());
+            assert_eq!(requests[1].monitored_object_identifier, analog_input());
+        });
+    }
+
+    #[test]
+    fn test_write_group() {
+        task::block_on(async {
+            let (link, device) = link_pair();
+            let client = BacnetClient::new(link);
+
+            let request = WriteGroup::new(3, 8).change(7, BACnetValue::Real(100.0));
+            client
+                .write_group(&Address::broadcast(), &request)
+                .await
+                .unwrap();
+            let sent = apdu(device.recv().await.unwrap().1);
+            assert_eq!(
+                sent.service_choice(),
+                Some(UnconfirmedServiceChoice::WriteGroup as u8)
+            );
+            assert_eq!(WriteGroup::decode_slice(sent.user_data()).unwrap(), request);
+        });
+    }
+
+    #[test]
+    fn test_send_text_message() {
+        task::block_on(async {
+            let (link, device) = link_pair();
+            let client = BacnetClient::new(link);
+            client.add_device(12, Address::local(vec![2]));
+
+            let respond = task::spawn(async move {
+                let mut requests = Vec::new();
+                for _ in 0..2 {
+                    let request = apdu(device.recv().await.unwrap().1);
+                    requests.push((
+                        request.invoke_id(),
+                        TextMessage::decode_slice(request.user_data()).unwrap(),
+                    ));
+                    if let Some(invoke_id) = request.invoke_id() {
+                        reply(&device, APDU::simple_ack(invoke_id, 19)).await;
+                    }
+                }
+                requests
+            });
+            let source = ObjectIdentifier::new(ObjectType::Device, 99);
+            let message = TextMessage::new(source, "Filter change due").urgent();
+            client.send_text_message(12, &message, true).await.unwrap();
+            client.send_text_message(12, &message, false).await.unwrap();
+            let requests = respond.await;
+            assert!(requests[0].0.is_some());
+            assert_eq!(requests[1].0, None);
+            assert_eq!(requests[0].1, message);
+            assert_eq!(requests[1].1, message);
+        });
+    }
+
+    #[test]
+    fn test_subscribe_cov_property() {
+        task::block_on(async {
+            let (link, device) = link_pair();
+            let client = BacnetClient::new(link);
+            client.add_device(12, Address::local(vec![2]));
+
+            let subscription = SubscribeCov {
+                subscriber_process_identifier: 1,
+                monitored_object_identifier: analog_input(),
+                issue_confirmed_notifications: Some(false),
+                lifetime: Some(60),
+            };
+            let request =
+                SubscribeCovProperty::new(subscription, PropertyIdentifier::PresentValue.number())
+                    .cov_increment(0.5);
+            let respond = task::spawn(async move {
+                let request = apdu(device.recv().await.unwrap().1);
+                assert_eq!(request.service_choice(), Some(28));
+                let ack = APDU::simple_ack(request.invoke_id().unwrap(), 28);
+                reply(&device, ack).await;
+                SubscribeCovProperty::decode_slice(request.user_data()).unwrap()
+            });
+            client
+                .subscribe_cov_property(12, request.clone())
+                .await
+                .unwrap();
+            assert_eq!(respond.await, request);
+        });
+    }
+
+    #[test]
+    fn test_get_event_information() {
+        task::block_on(async {
+            let (link, device) = link_pair();
+            let client = BacnetClient::new(link);
+            client.add_device(12, Address::local(vec![2]));
+
+            let summary = |instance| {
+                let object = ObjectIdentifier::new(ObjectType::AnalogInput, instance);
+                EventSummary {
+                    event_state: EventState::HighLimit,
+                    ..EventSummary::new(object)
+                }
+            };
+            let respond = task::spawn(async move {
+                let mut requests = Vec::new();
+                for ack in [
+                    GetEventInformationAck {
+                        list_of_event_summaries: vec![summary(1), summary(2)],
+                        more_events: true,
+                    },
+                    GetEventInformationAck {
+                        list_of_event_summaries: vec![summary(3)],
+                        more_events: false,
+                    },
+                    // Does not move on
+                    GetEventInformationAck {
+                        list_of_event_summaries: vec![],
+                        more_events: true,
+                    },
+                ] {
+                    let request = apdu(device.recv().await.unwrap().1);
+                    requests.push(
+                        GetEventInformationRequest::decode_slice(request.user_data()).unwrap(),
+                    );
+                    let invoke_id = request.invoke_id().unwrap();
+                    let ack = APDU::complex_ack(invoke_id, 29, ack.encode_vec().unwrap());
+                    reply(&device, ack).await;
+                }
+                requests
+            });
+            let summaries = client.get_event_information(12).await.unwrap();
+            assert_eq!(summaries, vec![summary(1), summary(2), summary(3)]);
+            assert!(matches!(
+                client.get_event_information(12).await,
+                Err(ClientError::UnexpectedResponse)
+            ));
+            let requests = respond.await;
+            assert_eq!(requests[0].last_received_object_identifier, None);
+            assert_eq!(
+                requests[1].last_received_object_identifier,
+                Some(summary(2).object_identifier)
+            );
+        });
+    }
+
+    #[test]
+    fn test_alarm_and_enrollment_summary() {
+        task::block_on(async {
+            let (link, device) = link_pair();
+            let client = BacnetClient::new(link);
+            client.add_device(12, Address::local(vec![2]));
+
+            let alarm = AlarmSummary {
+                object_identifier: analog_input(),
+                alarm_state: EventState::HighLimit,
+                acknowledged_transitions: [false, true, true],
+            };
+            let enrollment = EnrollmentSummary {
+                object_identifier: analog_input(),
+                event_type: 5,
+                event_state: EventState::HighLimit,
+                priority: 100,
+                notification_class: Some(3),
+            };
+            let acks = (
+                GetAlarmSummaryAck {
+                    list_of_alarm_summaries: vec![alarm.clone()],
+                },
+                GetEnrollmentSummaryAck {
+                    list_of_enrollment_summaries: vec![enrollment.clone()],
+                },
+            );
+            let respond = task::spawn(async move {
+                let request = apdu(device.recv().await.unwrap().1);
+                assert!(request.user_data().is_empty());
+                let invoke_id = request.invoke_id().unwrap();
+                let ack = APDU::complex_ack(invoke_id, 3, acks.0.encode_vec().unwrap());
+                reply(&device, ack).await;
+
+                let request = apdu(device.recv().await.unwrap().1);
+                let invoke_id = request.invoke_id().unwrap();
+                let ack = APDU::complex_ack(invoke_id, 4, acks.1.encode_vec().unwrap());
+                reply(&device, ack).await;
+                GetEnrollmentSummaryRequest::decode_slice(request.user_data()).unwrap()
+            });
+            assert_eq!(client.get_alarm_summary(12).await.unwrap(), vec![alarm]);
+            let request = GetEnrollmentSummaryRequest::new(AcknowledgmentFilter::NotAcked)
+                .event_state(EventStateFilter::Active);
+            let enrollments = client.get_enrollment_summary(12, &request).await.unwrap();
+            assert_eq!(enrollments, vec![enrollment]);
+            assert_eq!(respond.await, request);
+        });
+    }
+
+    #[test]
+    fn test_timeout() {
+        task::block_on(async {
+            let (link, _device) = link_pair();
+            let mut client = BacnetClient::new(link);
+            client.set_apdu_timeout(Duration::from_millis(10));
+            client.add_device(12, Address::local(vec![2]));
+
+            let result = client
+                .subscribe_cov(12, 1, analog_input(), false, None)
+                .await;
+            assert!(matches!(result, Err(ClientError::Timeout)));
+            assert_eq!(client.inner.station.transactions.len(), 0);
+            assert_eq!(client.statistics().transaction_timeouts, 1);
+            assert!(matches!(
+                client
+                    .read(13, analog_input(), PropertyIdentifier::PresentValue)
+                    .await,
+                Err(ClientError::UnknownDevice(13))
+            ));
+        });
+    }
+
+    #[test]
+    fn test_retries() {
+        task::block_on(async {
+            let (link, device) = link_pair();
+            let mut client = BacnetClient::new(link);
+            client.set_apdu_timeout(Duration::from_millis(20));
+            client.set_apdu_retries(1);
+            client.add_device(12, Address::local(vec![2]));
+
+            let read = client.read(12, analog_input(), PropertyIdentifier::PresentValue);
+            let respond = task::spawn(async move {
+                // The first request is lost, the retry is answered
+                let lost = apdu(device.recv().await.unwrap().1);
+                let request = apdu(device.recv().await.unwrap().1);
+                assert_eq!(request, lost);
+                let mut ack = request.user_data().to_vec();
+                ack.extend_from_slice(&hex::decode("3e4441a000003f").unwrap());
+                let invoke_id = request.invoke_id().unwrap();
+                reply(&device, APDU::complex_ack(invoke_id, 12, ack.clone())).await;
+                // A late answer to the lost request is dropped
+                reply(&device, APDU::complex_ack(invoke_id, 12, ack)).await;
+                device
+            });
+            assert_eq!(read.await.unwrap(), BACnetValue::Real(20.0));
+            let device = respond.await;
+            assert_eq!(client.statistics().transaction_timeouts, 0);
+
+            // Not answered at all
+            let read = client.read(12, analog_input(), PropertyIdentifier::PresentValue);
+            assert!(matches!(read.await, Err(ClientError::Timeout)));
+            assert_eq!(device.receiver.len(), 2);
+            assert_eq!(client.statistics().transaction_timeouts, 1);
+        });
+    }
+
+    #[test]
+    fn test_clock() {
+        task::block_on(async {
+            let (link, device) = link_pair();
+            let mut client = BacnetClient::new(link);
+            let clock = crate::clock::MockClock::new(std::time::UNIX_EPOCH);
+            client.set_clock(clock.clone());
+            client.add_device(12, Address::local(vec![2]));
+
+            let read = client.read(12, analog_input(), PropertyIdentifier::PresentValue);
+            // The request is sent again after every timeout
+            let timeout = async {
+                for _ in 0..=DEFAULT_APDU_RETRIES {
+                    device.recv().await.unwrap();
+                    clock.advance(DEFAULT_APDU_TIMEOUT - Duration::from_millis(1));
+                    task::sleep(Duration::from_millis(10)).await;
+                    assert!(device.receiver.is_empty());
+                    clock.advance(Duration::from_millis(1));
+                }
+            };
+            let (result, ()) = future::zip(read, timeout).await;
+            assert!(matches!(result, Err(ClientError::Timeout)));
+
+            // The wait for answers to a Who-Is passes on the clock as well
+            let who_is = client.who_is(None, Duration::from_secs(60));
+            let answer = async {
+                device.recv().await.unwrap();
+                reply(&device, i_am(12)).await;
+                task::sleep(Duration::from_millis(10)).await;
+                clock.advance(Duration::from_secs(60));
+            };
+            let (devices, ()) = future::zip(who_is, answer).await;
+            assert_eq!(devices.unwrap().len(), 1);
+
+            // Devices are synchronized to the time of the clock
+            client
+                .synchronize_time(&Address::broadcast())
+                .await
+                .unwrap();
+            let request = apdu(device.recv().await.unwrap().1);
+            let request = TimeSynchronization::decode_slice(request.user_data()).unwrap();
+            assert_eq!(request.date_time(), clock.date_time());
+        });
+    }
+
+    #[test]
+    fn test_statistics() {
+        task::block_on(async {
+            let (link, device) = link_pair();
+            let client = BacnetClient::new(link);
+            client.add_device(12, Address::local(vec![2]));
+
+            let read = client.read(12, analog_input(), PropertyIdentifier::PresentValue);
+            let respond = task::spawn(async move {
+                let request = apdu(device.recv().await.unwrap().1);
+                let mut ack = request.user_data().to_vec();
+                ack.extend_from_slice(&hex::decode("3e4441a000003f").unwrap());
+                reply(
+                    &device,
+                    APDU::complex_ack(request.invoke_id().unwrap(), 12, ack),
+                )
+                .await;
+                device
+            });
+            read.await.unwrap();
+            let device = respond.await;
+            let expected = Statistics {
+                frames_received: 1,
+                frames_sent: 1,
+                decode_errors: 0,
+                outstanding_transactions: 0,
+                transaction_timeouts: 0,
+            };
+            assert_eq!(client.statistics(), expected);
+
+            let service = ConfirmedServiceChoice::ConfirmedCovNotification as u8;
+            reply(&device, APDU::confirmed_request(7, service, vec![0xff])).await;
+            let reject = apdu(device.recv().await.unwrap().1);
+            assert_eq!(reject.pdu_type(), BACnetPDU::Reject);
+            let statistics = client.statistics();
+            assert_eq!(statistics.frames_received, 2);
+            assert_eq!(statistics.decode_errors, 1);
+        });
+    }
+
+    /// The events of `f` at any level, with the spans they are in
+    fn traced(f: impl FnOnce()) -> String {
+        #[derive(Clone, Default)]
+        struct Buffer(Arc<Mutex<Vec<u8>>>);
+
+        impl std::io::Write for Buffer {
+            fn write(&mut self, data: &[u8]) -> std::io::Result<usize> {
+                self.0.lock().unwrap().write(data)
+            }
+
+            fn flush(&mut self) -> std::io::Result<()> {
+                Ok(())
+            }
+        }
+
+        let buffer = Buffer::default();
+        let writer = buffer.clone();
+        let subscriber = tracing_subscriber::fmt()
+            .with_max_level(tracing::Level::TRACE)
+            .with_span_events(tracing_subscriber::fmt::format::FmtSpan::CLOSE)
+            .with_ansi(false)
+            .with_writer(move || writer.clone())
+            .finish();
+        tracing::subscriber::with_default(subscriber, f);
+        let output = buffer.0.lock().unwrap().clone();
+        String::from_utf8(output).unwrap()
+    }
+
+    #[test]
+    fn test_frame_spans() {
+        let (link, _device) = link_pair();
+        let client = BacnetClient::new(link);
+        let output = traced(|| {
+            let service = ConfirmedServiceChoice::ConfirmedCovNotification as u8;
+            let request = APDU::confirmed_request(7, service, vec![0xff]);
+            let npdu = NPDU::new(request, None, None, NPDUPriority::Normal);
+            let (address, response) = client.inner.receive(vec![2], npdu).unwrap();
+            task::block_on(client.inner.station.send(&address, response)).unwrap();
+        });
+
+        // Events while handling a frame are in its span
+        let received = output
+            .lines()
+            .find(|l| l.contains("Invalid request"))
+            .unwrap();
+        let peer = "peer=Address { net: None, mac: [2] }";
+        assert!(received.contains(&format!(
+            r#"frame{{direction="received" {} pdu_type=ConfirmedRequest invoke_id=7 service="ConfirmedCovNotification"}}"#,
+            peer
+        )));
+        assert!(output.contains(&format!(
+            r#"frame{{direction="sent" {} pdu_type=Reject invoke_id=7 reason=4}}"#,
+            peer
+        )));
+    }
+}
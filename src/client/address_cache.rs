@@ -0,0 +1,177 @@
+use crate::application::{IAm, VendorId};
+use crate::network::Address;
+
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+/// Max APDU length assumed for devices added with
+/// [`AddressCache::insert`], the largest an MS/TP device can accept
+pub(crate) const DEFAULT_MAX_APDU: u32 = 480;
+
+/// What the client knows about a device
+#[derive(Clone, Debug)]
+pub(crate) struct Binding {
+    pub(crate) address: Address,
+    pub(crate) max_apdu_length_accepted: u32,
+    /// The device does not execute ReadPropertyMultiple
+    pub(crate) rpm_unsupported: bool,
+    /// Vendor of the device, from its I-Am
+    pub(crate) vendor_id: Option<VendorId>,
+    /// When the binding was learned from an I-Am, `None` for bindings that
+    /// were added
+    learned: Option<Instant>,
+}
+
+/// Addresses of devices by instance number, learned from their I-Am
+///
+/// Learned bindings expire after the time to live, if there is one, so
+/// that devices that moved are looked for again. Bindings added with
+/// [`insert`](Self::insert) don't expire.
+///
+/// ```
+/// use bacnet::client::AddressCache;
+/// use bacnet::network::Address;
+/// use std::time::{Duration, Instant};
+///
+/// let mut cache = AddressCache::new(Some(Duration::from_secs(600)));
+/// cache.insert(12, Address::local(vec![2]));
+/// assert_eq!(cache.resolve(12, Instant::now()), Some(Address::local(vec![2])));
+/// assert_eq!(cache.resolve(13, Instant::now()), None);
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct AddressCache {
+    bindings: HashMap<u32, Binding>,
+    ttl: Option<Duration>,
+}
+
+impl AddressCache {
+    pub fn new(ttl: Option<Duration>) -> Self {
+        Self {
+            bindings: HashMap::new(),
+            ttl,
+        }
+    }
+
+    pub fn ttl(&self) -> Option<Duration> {
+        self.ttl
+    }
+
+    /// Forget learned bindings `ttl` after their I-Am, `None` to keep them
+    pub fn set_ttl(&mut self, ttl: Option<Duration>) {
+        self.ttl = ttl;
+    }
+
+    /// The address of a device, if its binding is known and did not expire
+    /// at `now`
+    pub fn resolve(&self, device: u32, now: Instant) -> Option<Address> {
+        self.get(device, now).map(|b| b.address.clone())
+    }
+
+    /// Add the binding of a device that does not announce itself
+    pub fn insert(&mut self, device: u32, address: Address) {
+        let binding = Binding {
+            address,
+            max_apdu_length_accepted: DEFAULT_MAX_APDU,
+            rpm_unsupported: false,
+            vendor_id: None,
+            learned: None,
+        };
+        self.bindings.insert(device, binding);
+    }
+
+    /// Learn the binding of a device from an I-Am received at `now`
+    pub fn learn(&mut self, address: Address, i_am: &IAm, now: Instant) {
+        let binding = self
+            .bindings
+            .entry(i_am.device_identifier.instance)
+            .or_insert_with(|| Binding {
+                address: address.clone(),
+                max_apdu_length_accepted: i_am.max_apdu_length_accepted,
+                rpm_unsupported: false,
+                vendor_id: None,
+                learned: None,
+            });
+        binding.address = address;
+        binding.max_apdu_length_accepted = i_am.max_apdu_length_accepted;
+        binding.vendor_id = Some(VendorId(i_am.vendor_id));
+        binding.learned = Some(now);
+    }
+
+    pub fn remove(&mut self, device: u32) {
+        self.bindings.remove(&device);
+    }
+
+    /// Forget the learned bindings that expired at `now`
+    pub fn expire(&mut self, now: Instant) {
+        let ttl = self.ttl;
+        self.bindings
+            .retain(|_, binding| !is_expired(binding, ttl, now));
+    }
+
+    pub fn len(&self) -> usize {
+        self.bindings.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.bindings.is_empty()
+    }
+
+    pub(crate) fn get(&self, device: u32, now: Instant) -> Option<&Binding> {
+        self.bindings
+            .get(&device)
+            .filter(|binding| !is_expired(binding, self.ttl, now))
+    }
+
+    pub(crate) fn get_mut(&mut self, device: u32) -> Option<&mut Binding> {
+        self.bindings.get_mut(&device)
+    }
+}
+
+fn is_expired(binding: &Binding, ttl: Option<Duration>, now: Instant) -> bool {
+    match (binding.learned, ttl) {
+        (Some(learned), Some(ttl)) => now.duration_since(learned) >= ttl,
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::application::{ObjectIdentifier, ObjectType, Segmentation};
+
+    fn i_am(instance: u32) -> IAm {
+        IAm {
+            device_identifier: ObjectIdentifier::new(ObjectType::Device, instance),
+            max_apdu_length_accepted: 1476,
+            segmentation_supported: Segmentation::NoSegmentation,
+            vendor_id: 15,
+        }
+    }
+
+    #[test]
+    fn test_address_cache() {
+        let mut cache = AddressCache::new(Some(Duration::from_secs(60)));
+        let now = Instant::now();
+        let later = now + Duration::from_secs(60);
+        cache.learn(Address::remote(5, vec![1]), &i_am(12), now);
+        cache.insert(13, Address::local(vec![2]));
+        assert_eq!(cache.resolve(12, now), Some(Address::remote(5, vec![1])));
+        assert_eq!(cache.get(12, now).unwrap().max_apdu_length_accepted, 1476);
+        assert_eq!(cache.get(13, now).unwrap().max_apdu_length_accepted, 480);
+
+        // Learned bindings expire, added ones don't
+        assert_eq!(cache.resolve(12, later), None);
+        assert_eq!(cache.resolve(13, later), Some(Address::local(vec![2])));
+        cache.expire(later);
+        assert_eq!(cache.len(), 1);
+
+        // An I-Am renews and moves the binding
+        cache.learn(Address::remote(5, vec![1]), &i_am(13), later);
+        assert_eq!(cache.resolve(13, later), Some(Address::remote(5, vec![1])));
+        assert_eq!(cache.resolve(13, later + Duration::from_secs(60)), None);
+        cache.set_ttl(None);
+        assert!(cache.resolve(13, later + Duration::from_secs(60)).is_some());
+        cache.remove(13);
+        assert!(cache.is_empty());
+    }
+}
@@ -0,0 +1,447 @@
+use crate::application::*;
+use crate::client::{join_all, BacnetClient, Binding, ClientError};
+use crate::encoding::*;
+use crate::transport::DataLink;
+use crate::{Decode, Encode};
+
+use num_traits::FromPrimitive;
+use std::collections::VecDeque;
+
+/// Largest APDU this client accepts, as announced in confirmed requests
+const MAX_APDU: usize = 1476;
+
+/// Octets of a Complex-ACK header
+const ACK_HEADER: usize = 3;
+
+/// Guess of the octets a property value takes in a ReadPropertyMultiple-ACK,
+/// requests that turn out too large are split
+const VALUE_ESTIMATE: usize = 16;
+
+/// Result of a single property read
+pub type PropertyResult = Result<BACnetValue, BACnetError>;
+
+/// Outcome of a ReadPropertyMultiple request
+enum Batch {
+    Done(Vec<PropertyResult>),
+    /// The response did not fit into an APDU
+    TooLarge,
+    /// The device does not support ReadPropertyMultiple
+    Unsupported,
+}
+
+/// Encode ReadPropertyMultiple-Request parameters (15.7.1.1), consecutive
+/// reads of the same object share a ReadAccessSpecification
+pub(crate) fn encode_request(reads: &[(ObjectIdentifier, PropertyIdentifier)]) -> Vec<u8> {
+    let mut specs: Vec<ReadAccessSpecification> = Vec::new();
+    for (object, property) in reads {
+        match specs.last_mut() {
+            Some(spec) if spec.object_identifier == *object => spec
+                .list_of_property_references
+                .push(PropertyReference::new(*property)),
+            _ => specs.push(ReadAccessSpecification::new(*object, [*property])),
+        }
+    }
+    ReadPropertyMultipleRequest::new(specs)
+        .encode_vec()
+        .unwrap_or_default()
+}
+
+/// Decode ReadPropertyMultiple-ACK parameters (15.7.1.3.1) into the object,
+/// property identifier and result of each property read
+pub(crate) fn decode_results(
+    data: &[u8],
+) -> Result<Vec<(ObjectIdentifier, u32, PropertyResult)>, ClientError> {
+    let mut reader = Reader::new(data);
+    let mut results = Vec::new();
+    while !reader.is_empty() {
+        let object = reader.context_object_identifier(0)?;
+        reader.opening_tag(1)?;
+        while !reader.is_closing_tag(1) {
+            let property = reader.context_enumerated(2)?;
+            reader.optional_context_unsigned(3)?;
+            let result = if reader.is_opening_tag(4) {
+                reader.opening_tag(4)?;
+                let mut values = reader.values_until_closing_tag(4)?;
+                Ok(match values.len() {
+                    1 => values.remove(0),
+                    _ => BACnetValue::Array(values),
+                })
+            } else {
+                reader.opening_tag(5)?;
+                let error = BACnetError::decode_slice(reader.remaining());
+                let _ = reader.application_value()?;
+                let _ = reader.application_value()?;
+                reader.closing_tag(5)?;
+                Err(error?)
+            };
+            results.push((object, property, result));
+        }
+        reader.closing_tag(1)?;
+    }
+    Ok(results)
+}
+
+/// Decode ReadPropertyMultiple-ACK parameters into the results of `reads`,
+/// which have to come in the order they were requested
+fn decode_ack(
+    data: &[u8],
+    reads: &[(ObjectIdentifier, PropertyIdentifier)],
+) -> Result<Vec<PropertyResult>, ClientError> {
+    let results = decode_results(data)?;
+    if results.len() != reads.len() {
+        return Err(ClientError::UnexpectedResponse);
+    }
+    results
+        .into_iter()
+        .zip(reads)
+        .map(
+            |((object, property, result), (o, p))| match *o == object && p.number() == property {
+                true => Ok(result),
+                false => Err(ClientError::UnexpectedResponse),
+            },
+        )
+        .collect()
+}
+
+/// Estimated size of the ReadPropertyMultiple-ACK for `reads`
+fn estimate_ack(reads: &[(ObjectIdentifier, PropertyIdentifier)]) -> usize {
+    let objects = 1 + reads.windows(2).filter(|w| w[0].0 != w[1].0).count();
+    // Object identifier and list tags per object, property identifier and
+    // value tags per property
+    ACK_HEADER + objects * (5 + 2) + reads.len() * (3 + 2 + VALUE_ESTIMATE)
+}
+
+/// Split reads into requests whose request and estimated response fit into
+/// `max_apdu` octets
+fn plan(reads: &[(ObjectIdentifier, PropertyIdentifier)], max_apdu: usize) -> Vec<(usize, usize)> {
+    let mut batches = Vec::new();
+    let mut start = 0;
+    while start < reads.len() {
+        let mut end = start + 1;
+        while end < reads.len() {
+            let candidate = &reads[start..=end];
+            // Header of a confirmed request is 4 octets
+            if 4 + encode_request(candidate).len() > max_apdu || estimate_ack(candidate) > max_apdu
+            {
+                break;
+            }
+            end += 1;
+        }
+        batches.push((start, end));
+        start = end;
+    }
+    batches
+}
+
+impl<D: DataLink + 'static> BacnetClient<D> {
+    /// Read many properties with as few ReadPropertyMultiple requests as
+    /// possible
+    ///
+    /// Reads are packed into requests fitting the APDU size of the device,
+    /// requests the device can't answer in one APDU are split. Devices that
+    /// don't support ReadPropertyMultiple are read property by property.
+    /// Results are in the order of `reads`, errors accessing a single
+    /// property don't fail the others.
+    pub async fn read_multiple(
+        &self,
+        device: u32,
+        reads: &[(ObjectIdentifier, PropertyIdentifier)],
+    ) -> Result<Vec<PropertyResult>, ClientError> {
+        let binding = self.bind(device).await?;
+        let mut results: Vec<Option<PropertyResult>> = vec![None; reads.len()];
+
+        let max_apdu = (binding.max_apdu_length_accepted as usize).min(MAX_APDU);
+        let mut pending: VecDeque<_> = match binding.rpm_unsupported {
+            true => VecDeque::new(),
+            false => plan(reads, max_apdu).into(),
+        };
+        let mut single: Vec<usize> = match binding.rpm_unsupported {
+            true => (0..reads.len()).collect(),
+            false => Vec::new(),
+        };
+
+        while let Some((start, end)) = pending.pop_front() {
+            match self.read_batch(&binding, &reads[start..end]).await? {
+                Batch::Done(batch) => {
+                    for (i, result) in batch.into_iter().enumerate() {
+                        results[start + i] = Some(result);
+                    }
+                }
+                Batch::TooLarge if end - start > 1 => {
+                    let middle = start + (end - start) / 2;
+                    pending.push_front((middle, end));
+                    pending.push_front((start, middle));
+                }
+                Batch::TooLarge => single.push(start),
+                Batch::Unsupported => {
+                    if let Some(b) = self.inner.devices.lock().unwrap().get_mut(device) {
+                        b.rpm_unsupported = true;
+                    }
+                    single.extend(start..end);
+                    single.extend(pending.drain(..).flat_map(|(s, e)| s..e));
+                }
+            }
+        }
+
+        for i in single {
+            let (object, property) = reads[i];
+            results[i] = Some(match self.read(device, object, property).await {
+                Ok(value) => Ok(value),
+                Err(ClientError::Error(e)) => Err(e),
+                Err(e) => return Err(e),
+            });
+        }
+        Ok(results.into_iter().flatten().collect())
+    }
+
+    /// [`read_multiple`](Self::read_multiple) on many devices concurrently
+    ///
+    /// Requests to all devices are outstanding at the same time, limited by
+    /// the [request window](Self::set_request_window). Results are in the
+    /// order of `reads`.
+    pub async fn read_devices(
+        &self,
+        reads: &[(u32, Vec<(ObjectIdentifier, PropertyIdentifier)>)],
+    ) -> Vec<Result<Vec<PropertyResult>, ClientError>> {
+        let requests = reads
+            .iter()
+            .map(|(device, reads)| self.read_multiple(*device, reads))
+            .collect();
+        join_all(requests).await
+    }
+
+    async fn read_batch(
+        &self,
+        binding: &Binding,
+        reads: &[(ObjectIdentifier, PropertyIdentifier)],
+    ) -> Result<Batch, ClientError> {
+        let response = self
+            .confirmed_request(
+                &binding.address,
+                ConfirmedServiceChoice::ReadPropertyMultiple,
+                encode_request(reads),
+            )
+            .await;
+        match response {
+            Ok(ack) => decode_ack(&ack, reads).map(Batch::Done),
+            Err(ClientError::Abort(reason))
+                if matches!(
+                    AbortReason::from_u8(reason),
+                    Some(AbortReason::BufferOverflow | AbortReason::SegmentationNotSupported)
+                ) =>
+            {
+                Ok(Batch::TooLarge)
+            }
+            Err(ClientError::Reject(reason)) => match RejectReason::from_u8(reason) {
+                Some(RejectReason::BufferOverflow) => Ok(Batch::TooLarge),
+                Some(RejectReason::UnrecognizedService) => Ok(Batch::Unsupported),
+                _ => Err(ClientError::Reject(reason)),
+            },
+            Err(ClientError::Error(e)) if e.error_class == ErrorClass::Services => {
+                Ok(Batch::Unsupported)
+            }
+            Err(e) => Err(e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::tests::*;
+    use crate::network::Address;
+
+    use async_std::task;
+
+    fn reads(count: u32) -> Vec<(ObjectIdentifier, PropertyIdentifier)> {
+        (0..count)
+            .flat_map(|i| {
+                let object = ObjectIdentifier::new(ObjectType::AnalogInput, i);
+                vec![
+                    (object, PropertyIdentifier::PresentValue),
+                    (object, PropertyIdentifier::StatusFlags),
+                ]
+            })
+            .collect()
+    }
+
+    /// Answer a ReadPropertyMultiple request with Real(instance) for every
+    /// property, or an unknown-property error for Status_Flags
+    fn ack(request: &[u8]) -> Vec<u8> {
+        let mut reader = Reader::new(request);
+        let mut data = Vec::new();
+        while !reader.is_empty() {
+            let object = reader.context_object_identifier(0).unwrap();
+            reader.opening_tag(1).unwrap();
+            encode_context_object_identifier(&mut data, 0, object).unwrap();
+            encode_opening_tag(&mut data, 1);
+            while !reader.is_closing_tag(1) {
+                let property = reader.context_enumerated(0).unwrap();
+                encode_context_enumerated(&mut data, 2, property);
+                if property == PropertyIdentifier::StatusFlags.number() {
+                    encode_opening_tag(&mut data, 5);
+                    data.extend_from_slice(&[0x91, 0x02, 0x91, 0x20]);
+                    encode_closing_tag(&mut data, 5);
+                } else {
+                    encode_opening_tag(&mut data, 4);
+                    encode_real(&mut data, object.instance as f32);
+                    encode_closing_tag(&mut data, 4);
+                }
+            }
+            reader.closing_tag(1).unwrap();
+            encode_closing_tag(&mut data, 1);
+        }
+        data
+    }
+
+    #[test]
+    fn test_encode_request() {
+        let data = encode_request(&reads(1));
+        assert_eq!(data, hex::decode("0c000000001e0955096f1f").unwrap());
+    }
+
+    #[test]
+    fn test_plan() {
+        let reads = reads(40);
+        let batches = plan(&reads, 480);
+        assert!(batches.len() > 1);
+        assert_eq!(batches.first().unwrap().0, 0);
+        assert_eq!(batches.last().unwrap().1, reads.len());
+        assert!(batches.windows(2).all(|w| w[0].1 == w[1].0));
+        for (start, end) in batches {
+            assert!(estimate_ack(&reads[start..end]) <= 480);
+        }
+        assert_eq!(plan(&reads, 50).len(), reads.len());
+    }
+
+    #[test]
+    fn test_read_multiple_splits() {
+        task::block_on(async {
+            let (link, device) = link_pair();
+            let client = BacnetClient::new(link);
+            client.add_device(12, Address::local(vec![2]));
+
+            let respond = task::spawn(async move {
+                // The first request is too large for the device
+                let request = apdu(device.recv().await.unwrap().1);
+                reply(&device, APDU::abort(true, request.invoke_id().unwrap(), 4)).await;
+                for _ in 0..2 {
+                    let request = apdu(device.recv().await.unwrap().1);
+                    assert_eq!(request.service_choice(), Some(14));
+                    let data = ack(request.user_data());
+                    reply(
+                        &device,
+                        APDU::complex_ack(request.invoke_id().unwrap(), 14, data),
+                    )
+                    .await;
+                }
+            });
+            let results = client.read_multiple(12, &reads(4)).await.unwrap();
+            respond.await;
+
+            assert_eq!(results.len(), 8);
+            assert_eq!(results[6], Ok(BACnetValue::Real(3.0)));
+            assert_eq!(
+                results[7],
+                Err(BACnetError::property(ErrorCode::UnknownProperty))
+            );
+        });
+    }
+
+    #[test]
+    fn test_read_multiple_fallback() {
+        task::block_on(async {
+            let (link, device) = link_pair();
+            let client = BacnetClient::new(link);
+            client.add_device(12, Address::local(vec![2]));
+            let reads = reads(1);
+
+            let respond = task::spawn(async move {
+                let request = apdu(device.recv().await.unwrap().1);
+                reply(&device, APDU::reject(request.invoke_id().unwrap(), 9)).await;
+                for _ in 0..2 {
+                    let request = apdu(device.recv().await.unwrap().1);
+                    assert_eq!(request.service_choice(), Some(12));
+                    let mut data = request.user_data().to_vec();
+                    data.extend_from_slice(&[0x3e, 0x44, 0x3f, 0x80, 0x00, 0x00, 0x3f]);
+                    reply(
+                        &device,
+                        APDU::complex_ack(request.invoke_id().unwrap(), 12, data),
+                    )
+                    .await;
+                }
+            });
+            let results = client.read_multiple(12, &reads).await.unwrap();
+            respond.await;
+
+            assert_eq!(results, vec![Ok(BACnetValue::Real(1.0)); 2]);
+            assert!(client.binding(12).unwrap().rpm_unsupported);
+        });
+    }
+
+    #[test]
+    fn test_read_devices() {
+        task::block_on(async {
+            let (link, device) = link_pair();
+            let client = BacnetClient::new(link);
+            client.add_device(12, Address::local(vec![2]));
+            client.add_device(13, Address::local(vec![2]));
+
+            let respond = task::spawn(async move {
+                // Both requests are outstanding, answered in reverse
+                let first = apdu(device.recv().await.unwrap().1);
+                let second = apdu(device.recv().await.unwrap().1);
+                assert_ne!(first.invoke_id().unwrap(), second.invoke_id().unwrap());
+                for request in [second, first] {
+                    let data = ack(request.user_data());
+                    reply(
+                        &device,
+                        APDU::complex_ack(request.invoke_id().unwrap(), 14, data),
+                    )
+                    .await;
+                }
+            });
+            let results = client
+                .read_devices(&[(12, reads(1)), (13, reads(2)), (14, reads(1))])
+                .await;
+            respond.await;
+
+            assert_eq!(results[0].as_ref().unwrap().len(), 2);
+            assert_eq!(results[1].as_ref().unwrap().len(), 4);
+            assert!(matches!(results[2], Err(ClientError::UnknownDevice(14))));
+        });
+    }
+
+    #[test]
+    fn test_request_window() {
+        task::block_on(async {
+            let (link, device) = link_pair();
+            let mut client = BacnetClient::new(link);
+            client.add_device(12, Address::local(vec![2]));
+            client.set_request_window(Some(1));
+            assert_eq!(client.request_window(), Some(1));
+
+            let respond = task::spawn(async move {
+                for _ in 0..2 {
+                    let request = apdu(device.recv().await.unwrap().1);
+                    // The second request waits for the first to complete
+                    let next = async_std::future::timeout(
+                        std::time::Duration::from_millis(50),
+                        device.recv(),
+                    );
+                    assert!(next.await.is_err());
+                    let data = ack(request.user_data());
+                    reply(
+                        &device,
+                        APDU::complex_ack(request.invoke_id().unwrap(), 14, data),
+                    )
+                    .await;
+                }
+            });
+            let results = client.read_devices(&[(12, reads(1)), (12, reads(1))]).await;
+            respond.await;
+
+            assert!(results.iter().all(Result::is_ok));
+        });
+    }
+}
@@ -0,0 +1,257 @@
+use crate::application::{IAm, ObjectIdentifier, Segmentation, WhoIsBuilder};
+use crate::client::BacnetClient;
+use crate::network::Address;
+use crate::transport::{BoxFuture, DataLink};
+
+use async_std::channel::{self, Receiver};
+use futures_lite::{future, stream, Stream};
+use std::collections::HashSet;
+use std::time::Duration;
+
+use tracing::warn;
+
+/// A device that answered a Who-Is
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct DiscoveredDevice {
+    pub instance: u32,
+    pub address: Address,
+    pub vendor_id: u16,
+    pub max_apdu_length_accepted: u32,
+    pub segmentation_supported: Segmentation,
+}
+
+impl DiscoveredDevice {
+    pub fn new(address: Address, i_am: IAm) -> Self {
+        Self {
+            instance: i_am.device_identifier.instance,
+            address,
+            vendor_id: i_am.vendor_id,
+            max_apdu_length_accepted: i_am.max_apdu_length_accepted,
+            segmentation_supported: i_am.segmentation_supported,
+        }
+    }
+}
+
+/// Split an inclusive instance range into ranges of at most `size` instances
+fn chunks(low: u32, high: u32, size: u32) -> Vec<(u32, u32)> {
+    let size = size.max(1);
+    let mut chunks = Vec::new();
+    let mut start = low;
+    while start <= high {
+        let end = start.saturating_add(size - 1).min(high);
+        chunks.push((start, end));
+        match end.checked_add(1) {
+            Some(next) => start = next,
+            None => break,
+        }
+    }
+    chunks
+}
+
+impl<D: DataLink + 'static> BacnetClient<D> {
+    /// Split discovery sweeps into Who-Is requests for at most `size`
+    /// instances, so large sites don't answer with a burst of I-Am
+    pub fn set_discovery_chunk_size(&mut self, size: Option<u32>) {
+        self.discovery_chunk_size = size;
+    }
+
+    /// Find the devices with an instance number within the (inclusive)
+    /// range, or all devices, as they answer
+    ///
+    /// Each Who-Is of the sweep waits `timeout` for answers, the stream ends
+    /// after the last one. Devices that answer more than once are reported
+    /// once.
+    ///
+    /// ```no_run
+    /// # use bacnet::client::BacnetClient;
+    /// # use futures_lite::StreamExt;
+    /// # use std::time::Duration;
+    /// # async fn f<D: bacnet::transport::DataLink>(client: BacnetClient<D>) {
+    /// let devices = client.discover(None, Duration::from_secs(3));
+    /// futures_lite::pin!(devices);
+    /// while let Some(device) = devices.next().await {
+    ///     println!("Device {} at {:?}", device.instance, device.address);
+    /// }
+    /// # }
+    /// ```
+    pub fn discover(
+        &self,
+        range: Option<(u32, u32)>,
+        timeout: Duration,
+    ) -> impl Stream<Item = DiscoveredDevice> + '_ {
+        let sweep = match (range, self.discovery_chunk_size) {
+            (range, None) => vec![range],
+            (range, Some(size)) => {
+                let (low, high) = range.unwrap_or((0, ObjectIdentifier::MAX_INSTANCE));
+                chunks(low, high, size).into_iter().map(Some).collect()
+            }
+        };
+        let sweep = Sweep {
+            client: self,
+            ranges: sweep.into_iter(),
+            timeout,
+            current: None,
+            reported: HashSet::new(),
+        };
+        stream::unfold(sweep, |mut sweep| async move {
+            let device = sweep.next().await?;
+            Some((device, sweep))
+        })
+    }
+}
+
+/// A Who-Is of a discovery sweep
+struct WhoIsRequest {
+    range: Option<(u32, u32)>,
+    answers: Receiver<(Address, IAm)>,
+    /// Ends when the answers are no longer waited for
+    timeout: BoxFuture<'static, ()>,
+}
+
+/// The Who-Is requests of a discovery sweep
+struct Sweep<'a, D: DataLink + 'static> {
+    client: &'a BacnetClient<D>,
+    ranges: std::vec::IntoIter<Option<(u32, u32)>>,
+    timeout: Duration,
+    current: Option<WhoIsRequest>,
+    reported: HashSet<u32>,
+}
+
+impl<D: DataLink + 'static> Sweep<'_, D> {
+    async fn next(&mut self) -> Option<DiscoveredDevice> {
+        loop {
+            let WhoIsRequest {
+                range,
+                answers,
+                timeout,
+            } = match &mut self.current {
+                Some(current) => current,
+                None => {
+                    let range = self.ranges.next()?;
+                    let answers = self.who_is(range).await?;
+                    let timeout = self.client.inner.station.clock().sleep(self.timeout);
+                    self.current.insert(WhoIsRequest {
+                        range,
+                        answers,
+                        timeout,
+                    })
+                }
+            };
+            let answer = future::or(async { answers.recv().await.ok() }, async {
+                timeout.await;
+                None
+            });
+            match answer.await {
+                Some((address, i_am)) => {
+                    let instance = i_am.device_identifier.instance;
+                    let in_range = range.is_none_or(|(low, high)| (low..=high).contains(&instance));
+                    if in_range && self.reported.insert(instance) {
+                        return Some(DiscoveredDevice::new(address, i_am));
+                    }
+                }
+                None => self.current = None,
+            }
+        }
+    }
+
+    /// Broadcast a Who-Is, returning the receiver of the I-Am answers
+    async fn who_is(&self, range: Option<(u32, u32)>) -> Option<Receiver<(Address, IAm)>> {
+        let who_is = match range {
+            Some((low, high)) => WhoIsBuilder::new().range(low, high),
+            None => WhoIsBuilder::new(),
+        };
+        let (sender, receiver) = channel::unbounded();
+        let inner = &self.client.inner;
+        inner.i_am.lock().unwrap().push(sender);
+        match inner
+            .station
+            .send(&Address::global_broadcast(), who_is.build())
+            .await
+        {
+            Ok(()) => Some(receiver),
+            Err(e) => {
+                warn!("Failed to send Who-Is: {}", e);
+                None
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::application::*;
+    use crate::client::tests::*;
+    use crate::encoding::Reader;
+
+    use async_std::task;
+    use futures_lite::StreamExt;
+
+    #[test]
+    fn test_chunks() {
+        assert_eq!(chunks(0, 9, 5), vec![(0, 4), (5, 9)]);
+        assert_eq!(chunks(3, 10, 5), vec![(3, 7), (8, 10)]);
+        assert_eq!(chunks(7, 7, 100), vec![(7, 7)]);
+        assert_eq!(chunks(8, 7, 100), vec![]);
+        assert_eq!(chunks(u32::MAX - 1, u32::MAX, 0).len(), 2);
+    }
+
+    #[test]
+    fn test_discover() {
+        task::block_on(async {
+            let (link, device) = link_pair();
+            let mut client = BacnetClient::new(link);
+            client.set_discovery_chunk_size(Some(10));
+
+            let respond = task::spawn(async move {
+                for _ in 0..2 {
+                    let request = apdu(device.recv().await.unwrap().1);
+                    let mut reader = Reader::new(request.user_data());
+                    let low = reader.context_unsigned(0).unwrap();
+                    let high = reader.context_unsigned(1).unwrap();
+                    // Devices 5 and 15, the first one answering twice
+                    for instance in [5, 5, 15] {
+                        if (low..=high).contains(&instance) {
+                            reply(&device, i_am(instance)).await;
+                        }
+                    }
+                }
+            });
+            let devices: Vec<_> = client
+                .discover(Some((0, 19)), Duration::from_millis(50))
+                .collect()
+                .await;
+            respond.await;
+
+            let instances: Vec<_> = devices.iter().map(|d| d.instance).collect();
+            assert_eq!(instances, vec![5, 15]);
+            assert_eq!(devices[0].address, Address::local(vec![2]));
+            assert_eq!(devices[0].vendor_id, 15);
+            assert_eq!(
+                devices[0].segmentation_supported,
+                Segmentation::NoSegmentation
+            );
+        });
+    }
+
+    #[test]
+    fn test_discover_stream() {
+        task::block_on(async {
+            let (link, device) = link_pair();
+            let client = BacnetClient::new(link);
+
+            // Devices are reported as they answer, before the timeout
+            let devices = client.discover(None, Duration::from_secs(60));
+            futures_lite::pin!(devices);
+            let answer = async {
+                device.recv().await.unwrap();
+                reply(&device, i_am(5)).await;
+                reply(&device, i_am(5)).await;
+                reply(&device, i_am(7)).await;
+            };
+            let (first, ()) = futures_lite::future::zip(devices.next(), answer).await;
+            assert_eq!(first.unwrap().instance, 5);
+            assert_eq!(devices.next().await.unwrap().instance, 7);
+        });
+    }
+}
@@ -0,0 +1,495 @@
+use crate::application::*;
+use crate::client::{decode_results, encode_request, BacnetClient, ClientError, PropertyResult};
+use crate::transport::DataLink;
+
+use num_traits::FromPrimitive;
+use std::collections::BTreeMap;
+use std::convert::TryFrom;
+use std::fmt;
+
+/// Properties of an object that are not part of its Property_List
+/// (12.1.1.4.1)
+const LISTED_IMPLICITLY: [PropertyIdentifier; 4] = [
+    PropertyIdentifier::ObjectIdentifier,
+    PropertyIdentifier::ObjectName,
+    PropertyIdentifier::ObjectType,
+    PropertyIdentifier::PropertyList,
+];
+
+/// The properties of an object as read from a device
+#[derive(Clone, Debug, PartialEq)]
+pub struct ObjectDescription {
+    pub object_identifier: ObjectIdentifier,
+    pub properties: Vec<(PropertyIdentifier, PropertyResult)>,
+}
+
+/// All objects of a device, formatted like the object list of an EPICS
+/// (Annex A) with [`Display`](fmt::Display)
+#[derive(Clone, Debug, PartialEq)]
+pub struct DeviceDescription {
+    pub device_identifier: ObjectIdentifier,
+    pub objects: Vec<ObjectDescription>,
+}
+
+/// Convert a Rust identifier into the EPICS form, e.g. `ObjectName` into
+/// `object-name`
+pub(crate) fn epics_name<T: fmt::Debug>(identifier: T) -> String {
+    kebab_case(&format!("{:?}", identifier))
+}
+
+/// A date or time field, `*` if unspecified
+struct Field(u8);
+
+impl fmt::Display for Field {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.0 {
+            UNSPECIFIED => write!(f, "*"),
+            v => write!(f, "{:02}", v),
+        }
+    }
+}
+
+pub(crate) struct Value<'a>(pub(crate) &'a BACnetValue);
+
+impl fmt::Display for Value<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let list = |f: &mut fmt::Formatter<'_>, values: &mut dyn Iterator<Item = &BACnetValue>| {
+            write!(f, "{{")?;
+            for (i, value) in values.enumerate() {
+                if i > 0 {
+                    write!(f, ", ")?;
+                }
+                write!(f, "{}", Value(value))?;
+            }
+            write!(f, "}}")
+        };
+        match self.0 {
+            BACnetValue::Null => write!(f, "NULL"),
+            BACnetValue::Boolean(true) => write!(f, "TRUE"),
+            BACnetValue::Boolean(false) => write!(f, "FALSE"),
+            BACnetValue::Unsigned(v) | BACnetValue::Enumerated(v) => write!(f, "{}", v),
+            BACnetValue::Signed(v) => write!(f, "{}", v),
+            BACnetValue::Real(v) => write!(f, "{:?}", v),
+            BACnetValue::Double(v) => write!(f, "{:?}", v),
+            BACnetValue::OctetString(octets) => write!(f, "X'{}'", hex::encode_upper(octets)),
+            BACnetValue::CharacterString(s) => write!(f, "\"{}\"", s),
+            BACnetValue::BitString(bits) => {
+                let bits: Vec<_> = bits
+                    .bits()
+                    .iter()
+                    .map(|b| if *b { "T" } else { "F" })
+                    .collect();
+                write!(f, "{{{}}}", bits.join(","))
+            }
+            BACnetValue::Date(d) => {
+                let year = d.year().map(|y| y.to_string());
+                write!(
+                    f,
+                    "({}/{}/{})",
+                    Field(d.month),
+                    Field(d.day),
+                    year.as_deref().unwrap_or("*")
+                )
+            }
+            BACnetValue::Time(t) => write!(
+                f,
+                "{}:{}:{}.{}",
+                Field(t.hour),
+                Field(t.minute),
+                Field(t.second),
+                Field(t.hundredths)
+            ),
+            BACnetValue::ObjectIdentifier(o) => {
+                write!(f, "({}, {})", o.object_type, o.instance)
+            }
+            BACnetValue::Array(values) | BACnetValue::List(values) => list(f, &mut values.iter()),
+            BACnetValue::Constructed(elements) => list(f, &mut elements.iter().map(|(_, v)| v)),
+        }
+    }
+}
+
+impl fmt::Display for DeviceDescription {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "List of Objects in Test Device:")?;
+        writeln!(f, "{{")?;
+        for (i, object) in self.objects.iter().enumerate() {
+            writeln!(f, "  {{")?;
+            for (property, result) in &object.properties {
+                match result {
+                    Ok(value) => writeln!(f, "    {}: {}", property, Value(value))?,
+                    // Values that can't be read are marked as unknown
+                    Err(e) => writeln!(f, "    {}: ? -- {}", property, e)?,
+                }
+            }
+            match i + 1 == self.objects.len() {
+                true => writeln!(f, "  }}")?,
+                false => writeln!(f, "  }},")?,
+            }
+        }
+        writeln!(f, "}}")
+    }
+}
+
+impl<D: DataLink + 'static> BacnetClient<D> {
+    /// Read all objects of a device with all their properties
+    ///
+    /// Objects are taken from the Object_List of the device object, read
+    /// element by element if the list is too long for one APDU. Properties
+    /// are taken from the Property_List of each object, devices without it
+    /// are described by the required properties every object has.
+    /// Properties unknown to this crate are left out.
+    pub async fn describe_device(&self, device: u32) -> Result<DeviceDescription, ClientError> {
+        let device_identifier = ObjectIdentifier::new(ObjectType::Device, device);
+        let mut objects = Vec::new();
+        for object in self.object_list(device_identifier).await? {
+            objects.push(self.describe_object(device, object).await?);
+        }
+        Ok(DeviceDescription {
+            device_identifier,
+            objects,
+        })
+    }
+
+    async fn object_list(
+        &self,
+        device: ObjectIdentifier,
+    ) -> Result<Vec<ObjectIdentifier>, ClientError> {
+        let property = PropertyIdentifier::ObjectList;
+        match self.read_as(device.instance, device, property).await {
+            Err(ClientError::Abort(_)) | Err(ClientError::Reject(_)) => {}
+            result => return result,
+        }
+        let count = self
+            .read_property(device.instance, device, property, Some(0))
+            .await?;
+        let count =
+            u32::try_from(count.clone()).map_err(|_| ClientError::UnexpectedDatatype(count))?;
+        let mut objects = Vec::new();
+        for index in 1..=count {
+            let value = self
+                .read_property(device.instance, device, property, Some(index))
+                .await?;
+            match value {
+                BACnetValue::ObjectIdentifier(object) => objects.push(object),
+                value => return Err(ClientError::UnexpectedDatatype(value)),
+            }
+        }
+        Ok(objects)
+    }
+
+    /// Read all properties of an object
+    ///
+    /// Properties are read at once with ReadPropertyMultiple for the ALL
+    /// property. Devices that can't answer it in one APDU, or don't support
+    /// ReadPropertyMultiple, are read property by property as listed in the
+    /// Property_List of the object. Properties unknown to this crate are
+    /// left out.
+    pub async fn read_object(
+        &self,
+        device: u32,
+        object: ObjectIdentifier,
+    ) -> Result<BTreeMap<PropertyIdentifier, PropertyResult>, ClientError> {
+        let binding = self
+            .binding(device)
+            .ok_or(ClientError::UnknownDevice(device))?;
+        if !binding.rpm_unsupported {
+            let response = self
+                .confirmed_request(
+                    &binding.address,
+                    ConfirmedServiceChoice::ReadPropertyMultiple,
+                    encode_request(&[(object, PropertyIdentifier::All)]),
+                )
+                .await;
+            match response {
+                Ok(ack) => {
+                    let mut properties = BTreeMap::new();
+                    for (o, property, result) in decode_results(&ack)? {
+                        if o != object {
+                            return Err(ClientError::UnexpectedResponse);
+                        }
+                        if let Some(property) = PropertyIdentifier::from_u32(property) {
+                            properties.insert(property, result);
+                        }
+                    }
+                    return Ok(properties);
+                }
+                Err(ClientError::Abort(_)) | Err(ClientError::Reject(_)) => {}
+                Err(ClientError::Error(e)) if e.error_class == ErrorClass::Services => {}
+                Err(e) => return Err(e),
+            }
+        }
+        let properties = self.properties(device, object).await?;
+        let reads: Vec<_> = properties.iter().map(|p| (object, *p)).collect();
+        let results = self.read_multiple(device, &reads).await?;
+        Ok(properties.into_iter().zip(results).collect())
+    }
+
+    async fn describe_object(
+        &self,
+        device: u32,
+        object: ObjectIdentifier,
+    ) -> Result<ObjectDescription, ClientError> {
+        let properties = self.properties(device, object).await?;
+        let reads: Vec<_> = properties.iter().map(|p| (object, *p)).collect();
+        let results = self.read_multiple(device, &reads).await?;
+        Ok(ObjectDescription {
+            object_identifier: object,
+            properties: properties.into_iter().zip(results).collect(),
+        })
+    }
+
+    /// The properties of an object, from its Property_List or the required
+    /// properties of every object
+    async fn properties(
+        &self,
+        device: u32,
+        object: ObjectIdentifier,
+    ) -> Result<Vec<PropertyIdentifier>, ClientError> {
+        let mut properties = LISTED_IMPLICITLY.to_vec();
+        match self
+            .read(device, object, PropertyIdentifier::PropertyList)
+            .await
+        {
+            Ok(list) => {
+                let list = match list {
+                    BACnetValue::Array(values) => values,
+                    value => vec![value],
+                };
+                for value in list {
+                    match value {
+                        BACnetValue::Enumerated(p) => {
+                            properties.extend(PropertyIdentifier::from_u32(p))
+                        }
+                        value => return Err(ClientError::UnexpectedDatatype(value)),
+                    }
+                }
+            }
+            Err(ClientError::Error(_)) => properties.truncate(3),
+            Err(e) => return Err(e),
+        }
+        Ok(properties)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::tests::*;
+    use crate::encoding::*;
+    use crate::network::Address;
+
+    use async_std::task;
+
+    fn analog_input() -> ObjectIdentifier {
+        ObjectIdentifier::new(ObjectType::AnalogInput, 1)
+    }
+
+    #[test]
+    fn test_epics_name() {
+        assert_eq!(epics_name(PropertyIdentifier::ObjectName), "object-name");
+        assert_eq!(epics_name(ObjectType::AnalogInput), "analog-input");
+        assert_eq!(epics_name(ObjectType::Device), "device");
+    }
+
+    #[test]
+    fn test_display() {
+        let description = DeviceDescription {
+            device_identifier: ObjectIdentifier::new(ObjectType::Device, 12),
+            objects: vec![ObjectDescription {
+                object_identifier: analog_input(),
+                properties: vec![
+                    (
+                        PropertyIdentifier::ObjectIdentifier,
+                        Ok(BACnetValue::ObjectIdentifier(analog_input())),
+                    ),
+                    (
+                        PropertyIdentifier::ObjectName,
+                        Ok(BACnetValue::CharacterString("Outside Air".into())),
+                    ),
+                    (
+                        PropertyIdentifier::PresentValue,
+                        Ok(BACnetValue::Real(21.5)),
+                    ),
+                    (
+                        PropertyIdentifier::StatusFlags,
+                        Ok(BACnetValue::BitString(
+                            vec![false, true, false, false].into(),
+                        )),
+                    ),
+                    (
+                        PropertyIdentifier::Description,
+                        Err(BACnetError::property(ErrorCode::UnknownProperty)),
+                    ),
+                ],
+            }],
+        };
+        assert_eq!(
+            description.to_string(),
+            "List of Objects in Test Device:\n\
+             {\n  {\n\
+             \x20   object-identifier: (analog-input, 1)\n\
+             \x20   object-name: \"Outside Air\"\n\
+             \x20   present-value: 21.5\n\
+             \x20   status-flags: {F,T,F,F}\n\
+             \x20   description: ? -- Property: UnknownProperty\n\
+             \x20 }\n}\n"
+        );
+    }
+
+    #[test]
+    fn test_describe_device() {
+        task::block_on(async {
+            let (link, device) = link_pair();
+            let client = BacnetClient::new(link);
+            client.add_device(12, Address::local(vec![2]));
+
+            let respond = task::spawn(async move {
+                let answer = |value: BACnetValue| {
+                    let mut data = Vec::new();
+                    encode_opening_tag(&mut data, 3);
+                    encode_application(&mut data, &value).unwrap();
+                    encode_closing_tag(&mut data, 3);
+                    data
+                };
+                let object_list =
+                    BACnetValue::Array(vec![BACnetValue::ObjectIdentifier(analog_input())]);
+                let property_list = BACnetValue::Array(vec![
+                    BACnetValue::Enumerated(PropertyIdentifier::PresentValue.number()),
+                    // Numbers out of range are skipped
+                    BACnetValue::Enumerated(4_194_304),
+                ]);
+                for value in [object_list, property_list].iter().cloned() {
+                    let request = apdu(device.recv().await.unwrap().1);
+                    assert_eq!(request.service_choice(), Some(12));
+                    let mut data = request.user_data().to_vec();
+                    data.extend(answer(value));
+                    reply(
+                        &device,
+                        APDU::complex_ack(request.invoke_id().unwrap(), 12, data),
+                    )
+                    .await;
+                }
+
+                let request = apdu(device.recv().await.unwrap().1);
+                assert_eq!(request.service_choice(), Some(14));
+                let mut data = Vec::new();
+                encode_context_object_identifier(&mut data, 0, analog_input()).unwrap();
+                encode_opening_tag(&mut data, 1);
+                let values = vec![
+                    (75, BACnetValue::ObjectIdentifier(analog_input())),
+                    (77, BACnetValue::CharacterString("AI 1".into())),
+                    (79, BACnetValue::Enumerated(0)),
+                    (371, BACnetValue::Enumerated(85)),
+                    (85, BACnetValue::Real(1.0)),
+                ];
+                for (property, value) in values {
+                    encode_context_enumerated(&mut data, 2, property);
+                    encode_opening_tag(&mut data, 4);
+                    encode_application(&mut data, &value).unwrap();
+                    encode_closing_tag(&mut data, 4);
+                }
+                encode_closing_tag(&mut data, 1);
+                reply(
+                    &device,
+                    APDU::complex_ack(request.invoke_id().unwrap(), 14, data),
+                )
+                .await;
+            });
+            let description = client.describe_device(12).await.unwrap();
+            respond.await;
+
+            assert_eq!(description.objects.len(), 1);
+            let properties = &description.objects[0].properties;
+            assert_eq!(properties.len(), 5);
+            assert_eq!(
+                properties[4],
+                (PropertyIdentifier::PresentValue, Ok(BACnetValue::Real(1.0)))
+            );
+        });
+    }
+
+    #[test]
+    fn test_read_object() {
+        task::block_on(async {
+            let (link, device) = link_pair();
+            let client = BacnetClient::new(link);
+            client.add_device(12, Address::local(vec![2]));
+
+            let respond = task::spawn(async move {
+                let request = apdu(device.recv().await.unwrap().1);
+                assert_eq!(request.service_choice(), Some(14));
+                assert_eq!(
+                    request.user_data(),
+                    &encode_request(&[(analog_input(), PropertyIdentifier::All)])[..]
+                );
+                let mut data = Vec::new();
+                encode_context_object_identifier(&mut data, 0, analog_input()).unwrap();
+                encode_opening_tag(&mut data, 1);
+                let values = vec![
+                    (77, BACnetValue::CharacterString("AI 1".into())),
+                    (85, BACnetValue::Real(1.0)),
+                    // Numbers out of range are skipped
+                    (4_194_304, BACnetValue::Null),
+                ];
+                for (property, value) in values {
+                    encode_context_enumerated(&mut data, 2, property);
+                    encode_opening_tag(&mut data, 4);
+                    encode_application(&mut data, &value).unwrap();
+                    encode_closing_tag(&mut data, 4);
+                }
+                encode_closing_tag(&mut data, 1);
+                reply(
+                    &device,
+                    APDU::complex_ack(request.invoke_id().unwrap(), 14, data),
+                )
+                .await;
+
+                // Too large for an APDU, read as listed in the Property_List
+                let request = apdu(device.recv().await.unwrap().1);
+                reply(&device, APDU::abort(true, request.invoke_id().unwrap(), 1)).await;
+                let request = apdu(device.recv().await.unwrap().1);
+                assert_eq!(request.service_choice(), Some(12));
+                let mut data = request.user_data().to_vec();
+                encode_opening_tag(&mut data, 3);
+                encode_enumerated(&mut data, 85);
+                encode_closing_tag(&mut data, 3);
+                reply(
+                    &device,
+                    APDU::complex_ack(request.invoke_id().unwrap(), 12, data),
+                )
+                .await;
+
+                let request = apdu(device.recv().await.unwrap().1);
+                assert_eq!(request.service_choice(), Some(14));
+                let mut data = Vec::new();
+                encode_context_object_identifier(&mut data, 0, analog_input()).unwrap();
+                encode_opening_tag(&mut data, 1);
+                for property in [75, 77, 79, 371, 85] {
+                    encode_context_enumerated(&mut data, 2, property);
+                    encode_opening_tag(&mut data, 4);
+                    encode_unsigned(&mut data, property);
+                    encode_closing_tag(&mut data, 4);
+                }
+                encode_closing_tag(&mut data, 1);
+                reply(
+                    &device,
+                    APDU::complex_ack(request.invoke_id().unwrap(), 14, data),
+                )
+                .await;
+            });
+            let properties = client.read_object(12, analog_input()).await.unwrap();
+            assert_eq!(properties.len(), 2);
+            assert_eq!(
+                properties[&PropertyIdentifier::PresentValue],
+                Ok(BACnetValue::Real(1.0))
+            );
+            let properties = client.read_object(12, analog_input()).await.unwrap();
+            respond.await;
+            assert_eq!(properties.len(), 5);
+            assert_eq!(
+                properties[&PropertyIdentifier::PresentValue],
+                Ok(BACnetValue::Unsigned(85))
+            );
+        });
+    }
+}
@@ -0,0 +1,244 @@
+use crate::application::*;
+use crate::client::BacnetClient;
+use crate::network::Address;
+use crate::transport::DataLink;
+use crate::Decode;
+
+use async_std::channel;
+use futures_lite::Stream;
+use num_traits::FromPrimitive;
+
+/// Notifications queued per listener, further ones are dropped until the
+/// listener catches up
+const NOTIFICATION_QUEUE: usize = 256;
+
+/// An unsolicited request received by the client
+#[derive(Clone, Debug, PartialEq)]
+pub enum Notification {
+    /// A COV notification, `confirmed` if it was acknowledged
+    Cov {
+        notification: CovNotification,
+        confirmed: bool,
+    },
+    /// An event notification, `confirmed` if it was acknowledged
+    Event {
+        notification: EventNotification,
+        confirmed: bool,
+    },
+    IAm(IAm),
+    IHave(IHave),
+    /// A text message, `confirmed` if it was acknowledged
+    TextMessage {
+        message: TextMessage,
+        confirmed: bool,
+    },
+    /// An unconfirmed private transfer, with its service parameters decoded
+    /// by the [`VendorRegistry`]
+    PrivateTransfer {
+        transfer: PrivateTransfer,
+        parameters: Option<BACnetValue>,
+    },
+    /// Any other unconfirmed service, with its service choice and
+    /// parameters
+    Other(u8, Vec<u8>),
+}
+
+impl Notification {
+    /// Decode the parameters of an unconfirmed request
+    pub fn unconfirmed(service_choice: u8, data: &[u8]) -> crate::error::Result<Self> {
+        Self::unconfirmed_with(service_choice, data, &VendorRegistry::new())
+    }
+
+    /// Decode the parameters of an unconfirmed request, private transfers
+    /// with the decoders of `vendors`
+    pub fn unconfirmed_with(
+        service_choice: u8,
+        data: &[u8],
+        vendors: &VendorRegistry,
+    ) -> crate::error::Result<Self> {
+        use UnconfirmedServiceChoice as S;
+        Ok(match S::from_u8(service_choice) {
+            Some(S::UnconfirmedCovNotification) => Self::Cov {
+                notification: CovNotification::decode_slice(data)?,
+                confirmed: false,
+            },
+            Some(S::UnconfirmedEventNotification) => Self::Event {
+                notification: EventNotification::decode_slice(data)?,
+                confirmed: false,
+            },
+            Some(S::IAm) => Self::IAm(IAm::decode_slice(data)?),
+            Some(S::IHave) => Self::IHave(IHave::decode_slice(data)?),
+            Some(S::UnconfirmedTextMessage) => Self::TextMessage {
+                message: TextMessage::decode_slice(data)?,
+                confirmed: false,
+            },
+            Some(S::UnconfirmedPrivateTransfer) => {
+                let transfer = PrivateTransfer::decode_slice(data)?;
+                let parameters = transfer.parameters(vendors)?;
+                Self::PrivateTransfer {
+                    transfer,
+                    parameters,
+                }
+            }
+            _ => Self::Other(service_choice, data.to_vec()),
+        })
+    }
+
+    /// Decode the parameters of a confirmed request, `None` for services
+    /// that aren't notifications
+    pub fn confirmed(service_choice: u8, data: &[u8]) -> Option<crate::error::Result<Self>> {
+        use ConfirmedServiceChoice as S;
+        Some(match S::from_u8(service_choice)? {
+            S::ConfirmedCovNotification => {
+                CovNotification::decode_slice(data).map(|notification| Self::Cov {
+                    notification,
+                    confirmed: true,
+                })
+            }
+            S::ConfirmedEventNotification => {
+                EventNotification::decode_slice(data).map(|notification| Self::Event {
+                    notification,
+                    confirmed: true,
+                })
+            }
+            S::ConfirmedTextMessage => {
+                TextMessage::decode_slice(data).map(|message| Self::TextMessage {
+                    message,
+                    confirmed: true,
+                })
+            }
+            _ => return None,
+        })
+    }
+}
+
+impl<D: DataLink + 'static> BacnetClient<D> {
+    /// Stream of unsolicited requests received from now on, with the
+    /// address of their sender
+    ///
+    /// Confirmed COV and event notifications and text messages are
+    /// acknowledged by the client whether they are listened to or not. A
+    /// listener that falls more than 256 notifications behind misses the
+    /// following ones.
+    pub fn notifications(&self) -> impl Stream<Item = (Address, Notification)> {
+        let (sender, receiver) = channel::bounded(NOTIFICATION_QUEUE);
+        self.inner.notifications.lock().unwrap().push(sender);
+        receiver
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::tests::*;
+    use crate::error::ServiceError;
+    use crate::network::{NPDUPriority, NPDU};
+    use crate::Encode;
+
+    use async_std::task;
+    use futures_lite::StreamExt;
+
+    fn cov_notification() -> CovNotification {
+        CovNotification {
+            subscriber_process_identifier: 1,
+            initiating_device_identifier: ObjectIdentifier::new(ObjectType::Device, 12),
+            monitored_object_identifier: ObjectIdentifier::new(ObjectType::AnalogInput, 1),
+            time_remaining: 60,
+            values: vec![PropertyValue::new(
+                PropertyIdentifier::PresentValue,
+                BACnetValue::Real(1.0),
+            )],
+        }
+    }
+
+    #[test]
+    fn test_unconfirmed() {
+        let data = cov_notification().encode_vec().unwrap();
+        assert_eq!(
+            Notification::unconfirmed(2, &data).unwrap(),
+            Notification::Cov {
+                notification: cov_notification(),
+                confirmed: false
+            }
+        );
+        assert!(Notification::unconfirmed(2, &data[1..]).is_err());
+        assert_eq!(
+            Notification::unconfirmed(6, &[1]).unwrap(),
+            Notification::Other(6, vec![1])
+        );
+        assert!(Notification::confirmed(12, &data).is_none());
+    }
+
+    #[test]
+    fn test_private_transfer() {
+        let data = hex::decode("091919082e21022f").unwrap();
+        let transfer = PrivateTransfer {
+            vendor_id: VendorId(25),
+            service_number: 8,
+            service_parameters: Some(vec![0x21, 0x02]),
+        };
+        assert_eq!(
+            Notification::unconfirmed(4, &data).unwrap(),
+            Notification::PrivateTransfer {
+                transfer: transfer.clone(),
+                parameters: Some(BACnetValue::Unsigned(2)),
+            }
+        );
+
+        let mut vendors = VendorRegistry::new();
+        vendors.register_private_transfer(VendorId(25), 8, |data| {
+            Ok(BACnetValue::OctetString(data.to_vec()))
+        });
+        assert_eq!(
+            Notification::unconfirmed_with(4, &data, &vendors).unwrap(),
+            Notification::PrivateTransfer {
+                transfer,
+                parameters: Some(BACnetValue::OctetString(vec![0x21, 0x02])),
+            }
+        );
+        vendors.register_private_transfer(VendorId(25), 8, |_| {
+            Err(ServiceError::Invalid("Invalid parameters").into())
+        });
+        assert!(Notification::unconfirmed_with(4, &data, &vendors).is_err());
+    }
+
+    #[test]
+    fn test_notifications() {
+        task::block_on(async {
+            let (link, device) = link_pair();
+            let client = BacnetClient::new(link);
+            let mut notifications = client.notifications();
+
+            let data = cov_notification().encode_vec().unwrap();
+            let service = UnconfirmedServiceChoice::UnconfirmedCovNotification as u8;
+            reply(&device, APDU::unconfirmed_request(service, data.clone())).await;
+            let request = APDU::confirmed_request(7, 1, data);
+            let npdu = NPDU::new(request, None, None, NPDUPriority::Normal);
+            device.send(&[1], &npdu).await.unwrap();
+
+            let (address, notification) = notifications.next().await.unwrap();
+            assert_eq!(address, Address::local(vec![2]));
+            assert!(matches!(
+                notification,
+                Notification::Cov {
+                    confirmed: false,
+                    ..
+                }
+            ));
+            let (_, notification) = notifications.next().await.unwrap();
+            assert!(matches!(
+                notification,
+                Notification::Cov {
+                    confirmed: true,
+                    ..
+                }
+            ));
+
+            // The confirmed notification is acknowledged
+            let ack = apdu(device.recv().await.unwrap().1);
+            assert_eq!(ack.pdu_type(), BACnetPDU::SimpleACK);
+            assert_eq!(ack.invoke_id().unwrap(), 7);
+            assert_eq!(ack.service_choice(), Some(1));
+        });
+    }
+}
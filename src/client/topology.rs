@@ -0,0 +1,153 @@
+use crate::client::{collect, BacnetClient, ClientError};
+use crate::network::*;
+use crate::transport::DataLink;
+
+use async_std::channel;
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+/// A router as announced with I-Am-Router-To-Network
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Router {
+    pub address: Address,
+    /// The networks reachable through the router
+    pub networks: Vec<u16>,
+}
+
+/// The networks and devices reachable from the client
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Topology {
+    /// Routers on the local network
+    pub routers: Vec<Router>,
+    /// Instance numbers and addresses of the devices on each network, the
+    /// local network is `None`
+    pub networks: BTreeMap<Option<u16>, Vec<(u32, Address)>>,
+}
+
+impl Topology {
+    /// The router to a remote network
+    pub fn router(&self, network: u16) -> Option<&Address> {
+        self.routers
+            .iter()
+            .find(|router| router.networks.contains(&network))
+            .map(|router| &router.address)
+    }
+}
+
+impl<D: DataLink + 'static> BacnetClient<D> {
+    /// Broadcast a Who-Is-Router-To-Network and collect the routers and the
+    /// networks they announce within `wait`
+    ///
+    /// Without a network all routers answer with all networks they reach.
+    /// The routers are remembered to send to their networks.
+    pub async fn who_is_router_to_network(
+        &self,
+        network: Option<u16>,
+        wait: Duration,
+    ) -> Result<Vec<Router>, ClientError> {
+        let (sender, receiver) = channel::unbounded();
+        self.inner.i_am_router.lock().unwrap().push(sender);
+        let message = NetworkMessage::who_is_router_to_network(network);
+        self.inner
+            .station
+            .send_message(&Address::broadcast(), message)
+            .await?;
+
+        let mut routers = Vec::new();
+        collect(
+            &receiver,
+            &mut routers,
+            self.inner.station.clock().sleep(wait),
+        )
+        .await;
+        Ok(routers)
+    }
+
+    /// Discover the routers on the local network and the devices on all
+    /// reachable networks, waiting `wait` for each
+    ///
+    /// Networks announced by a router are listed even without devices
+    /// answering on them.
+    pub async fn scan_topology(&self, wait: Duration) -> Result<Topology, ClientError> {
+        let routers = self.who_is_router_to_network(None, wait).await?;
+        let mut networks: BTreeMap<_, Vec<_>> = routers
+            .iter()
+            .flat_map(|router| router.networks.iter().map(|n| (Some(*n), Vec::new())))
+            .collect();
+        for (address, i_am) in self.who_is(None, wait).await? {
+            let devices = networks.entry(address.net).or_default();
+            devices.push((i_am.device_identifier.instance, address));
+        }
+        for devices in networks.values_mut() {
+            devices.sort();
+            devices.dedup();
+        }
+        Ok(Topology { routers, networks })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::tests::*;
+
+    use async_std::task;
+
+    #[test]
+    fn test_scan_topology() {
+        task::block_on(async {
+            let (link, router) = link_pair();
+            let client = BacnetClient::new(link);
+
+            let respond = task::spawn(async move {
+                let (_, npdu) = router.recv().await.unwrap();
+                assert_eq!(
+                    npdu.content,
+                    NPDUContent::Message(NetworkMessage::who_is_router_to_network(None))
+                );
+                let message = NetworkMessage::i_am_router_to_network(&[5, 6]);
+                let npdu = NPDU::new(
+                    NPDUContent::Message(message),
+                    None,
+                    None,
+                    Default::default(),
+                );
+                router.send(&[], &npdu).await.unwrap();
+
+                // A device on the local network and one behind the router
+                router.recv().await.unwrap();
+                reply(&router, i_am(1)).await;
+                let source = NPDUSource {
+                    net: 5,
+                    adr: vec![7],
+                };
+                let npdu = NPDU::new(i_am(2), None, Some(source), Default::default());
+                router.send(&[], &npdu).await.unwrap();
+            });
+            let topology = client
+                .scan_topology(Duration::from_millis(100))
+                .await
+                .unwrap();
+            respond.await;
+
+            assert_eq!(
+                topology.routers,
+                vec![Router {
+                    address: Address::local(vec![2]),
+                    networks: vec![5, 6]
+                }]
+            );
+            assert_eq!(topology.router(6), Some(&Address::local(vec![2])));
+            assert_eq!(topology.router(7), None);
+            assert_eq!(
+                topology.networks.get(&None),
+                Some(&vec![(1, Address::local(vec![2]))])
+            );
+            assert_eq!(
+                topology.networks.get(&Some(5)),
+                Some(&vec![(2, Address::remote(5, vec![7]))])
+            );
+            assert_eq!(topology.networks.get(&Some(6)), Some(&vec![]));
+        });
+    }
+}
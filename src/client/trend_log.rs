@@ -0,0 +1,425 @@
+use crate::application::*;
+use crate::client::{BacnetClient, ClientError};
+use crate::encoding::*;
+use crate::objects::{LogDatum, LogStatus};
+use crate::transport::DataLink;
+use crate::{Decode, Encode};
+
+use futures_lite::stream::{self, Stream};
+use std::collections::VecDeque;
+
+/// Estimated octets of a log record holding a primitive value
+const RECORD_ESTIMATE: usize = 24;
+
+/// Octets of a ReadRange-ACK besides the records
+const ACK_OVERHEAD: usize = 24;
+
+/// BACnetLogRecord (Clause 21) with its sequence number
+#[derive(Clone, Debug, PartialEq)]
+pub struct TrendLogRecord {
+    pub sequence_number: u32,
+    pub timestamp: BACnetDateTime,
+    pub datum: LogDatum,
+    pub status_flags: Option<Vec<bool>>,
+}
+
+/// Where to start reading a log buffer
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum LogStart {
+    /// The record with this sequence number and all newer ones
+    SequenceNumber(u32),
+    /// All records newer than this time
+    Time(BACnetDateTime),
+}
+
+/// Records returned by a ReadRange request (15.8.1.3)
+#[derive(Clone, Debug, PartialEq)]
+pub struct LogPage {
+    pub result_flags: ResultFlags,
+    pub records: Vec<TrendLogRecord>,
+}
+
+/// Encode ReadRange-Request parameters (15.8.1.1) for Log_Buffer
+fn encode_request(object: ObjectIdentifier, range: &Range) -> crate::error::Result<Vec<u8>> {
+    let request = ReadRangeRequest {
+        object_identifier: object,
+        property_identifier: PropertyIdentifier::LogBuffer.number(),
+        property_array_index: None,
+        range: Some(*range),
+    };
+    request.encode_vec()
+}
+
+fn bits(value: BACnetValue) -> std::io::Result<Vec<bool>> {
+    match value {
+        BACnetValue::BitString(bits) => Ok(bits.into()),
+        _ => Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidData,
+            "Expected a bit string",
+        )),
+    }
+}
+
+fn real(value: BACnetValue) -> std::io::Result<f32> {
+    match value {
+        BACnetValue::Real(v) => Ok(v),
+        _ => Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidData,
+            "Expected a real",
+        )),
+    }
+}
+
+/// Decode the log-datum CHOICE of a BACnetLogRecord
+fn decode_datum(reader: &mut Reader) -> Result<LogDatum, ClientError> {
+    // Context tag numbers of the primitive choices and their datatypes
+    const PRIMITIVES: [(u8, u8); 7] = [(1, 1), (2, 4), (3, 9), (4, 2), (5, 3), (6, 8), (7, 0)];
+
+    if reader.is_context_tag(0) {
+        let bit = |bits: &[bool], i: usize| bits.get(i).copied().unwrap_or(false);
+        let status = bits(reader.context_value(0, 8)?)?;
+        return Ok(LogDatum::LogStatus(LogStatus {
+            log_disabled: bit(&status, 0),
+            buffer_purged: bit(&status, 1),
+            log_interrupted: bit(&status, 2),
+        }));
+    }
+    for (tag, datatype) in PRIMITIVES.iter() {
+        if reader.is_context_tag(*tag) {
+            return Ok(LogDatum::Value(reader.context_value(*tag, *datatype)?));
+        }
+    }
+    if reader.is_opening_tag(8) {
+        reader.opening_tag(8)?;
+        let error = BACnetError::decode_slice(reader.remaining())?;
+        let _ = reader.application_value()?;
+        let _ = reader.application_value()?;
+        reader.closing_tag(8)?;
+        return Ok(LogDatum::Failure(error));
+    }
+    if reader.is_context_tag(9) {
+        return Ok(LogDatum::TimeChange(real(reader.context_value(9, 4)?)?));
+    }
+    reader.opening_tag(10)?;
+    let mut values = reader.values_until_closing_tag(10)?;
+    Ok(LogDatum::Value(match values.len() {
+        1 => values.remove(0),
+        _ => BACnetValue::Array(values),
+    }))
+}
+
+/// Decode a BACnetLogRecord (Clause 21)
+fn decode_record(reader: &mut Reader, sequence_number: u32) -> Result<TrendLogRecord, ClientError> {
+    let timestamp = BACnetDateTime::decode_context(reader, 0)?;
+    reader.opening_tag(1)?;
+    let datum = decode_datum(reader)?;
+    reader.closing_tag(1)?;
+    let status_flags = match reader.is_context_tag(2) {
+        true => Some(bits(reader.context_value(2, 8)?)?),
+        false => None,
+    };
+    Ok(TrendLogRecord {
+        sequence_number,
+        timestamp,
+        datum,
+        status_flags,
+    })
+}
+
+/// Decode ReadRange-ACK parameters (15.8.1.3)
+fn decode_ack(data: &[u8], object: ObjectIdentifier) -> Result<LogPage, ClientError> {
+    let mut reader = Reader::new(data);
+    if reader.context_object_identifier(0)? != object
+        || reader.context_enumerated(1)? != PropertyIdentifier::LogBuffer.number()
+        || reader.optional_context_unsigned(2)?.is_some()
+    {
+        return Err(ClientError::UnexpectedResponse);
+    }
+    let flags = bits(reader.context_value(3, 8)?)?;
+    let flag = |i: usize| flags.get(i).copied().unwrap_or(false);
+    let result_flags = ResultFlags {
+        first_item: flag(0),
+        last_item: flag(1),
+        more_items: flag(2),
+    };
+    let item_count = reader.context_unsigned(4)?;
+
+    // The sequence number of the first item follows the items
+    reader.opening_tag(5)?;
+    let mut items = reader.clone();
+    while !reader.is_closing_tag(5) {
+        reader.opening_tag(0)?;
+        reader.values_until_closing_tag(0)?;
+        reader.opening_tag(1)?;
+        reader.values_until_closing_tag(1)?;
+        if reader.is_context_tag(2) {
+            reader.context_value(2, 8)?;
+        }
+    }
+    reader.closing_tag(5)?;
+    let first = match (reader.optional_context_unsigned(6)?, item_count) {
+        (Some(first), _) => first,
+        (None, 0) => 0,
+        (None, _) => return Err(ClientError::UnexpectedResponse),
+    };
+
+    let mut records = Vec::new();
+    for i in 0..item_count {
+        records.push(decode_record(&mut items, first.wrapping_add(i))?);
+    }
+    if !items.is_closing_tag(5) {
+        return Err(ClientError::UnexpectedResponse);
+    }
+    Ok(LogPage {
+        result_flags,
+        records,
+    })
+}
+
+/// Sequence number following `sequence_number`, wrapping to 1 (12.25.16)
+fn next_sequence_number(sequence_number: u32) -> u32 {
+    match sequence_number {
+        u32::MAX => 1,
+        n => n + 1,
+    }
+}
+
+struct Paging {
+    range: Option<Range>,
+    records: VecDeque<TrendLogRecord>,
+    counted: bool,
+}
+
+impl<D: DataLink + 'static> BacnetClient<D> {
+    /// Read a range of a log object's Log_Buffer (15.8)
+    pub async fn read_log_range(
+        &self,
+        device: u32,
+        object: ObjectIdentifier,
+        range: Range,
+    ) -> Result<LogPage, ClientError> {
+        let address = self.resolve(device).await?;
+        let ack = self
+            .confirmed_request(
+                &address,
+                ConfirmedServiceChoice::ReadRange,
+                encode_request(object, &range)?,
+            )
+            .await?;
+        decode_ack(&ack, object)
+    }
+
+    /// Stream all records of a Trend Log from `start` on
+    ///
+    /// The log buffer is read page by page with ReadRange, each page as
+    /// large as the APDU size of the device allows. An empty log, as
+    /// reported by Record_Count, ends the stream without reading the buffer.
+    /// The stream ends after the newest record or the first error.
+    pub fn trend_log_records(
+        &self,
+        device: u32,
+        object: ObjectIdentifier,
+        start: LogStart,
+    ) -> impl Stream<Item = Result<TrendLogRecord, ClientError>> + '_ {
+        let max_apdu = self
+            .binding(device)
+            .map(|b| b.max_apdu_length_accepted as usize)
+            .unwrap_or(super::address_cache::DEFAULT_MAX_APDU as usize);
+        let count = (max_apdu.saturating_sub(ACK_OVERHEAD) / RECORD_ESTIMATE)
+            .clamp(1, i16::MAX as usize) as i16;
+        let range = match start {
+            LogStart::SequenceNumber(reference_sequence_number) => Range::BySequenceNumber {
+                reference_sequence_number,
+                count,
+            },
+            LogStart::Time(reference_time) => Range::ByTime {
+                reference_time,
+                count,
+            },
+        };
+        let paging = Paging {
+            range: Some(range),
+            records: VecDeque::new(),
+            counted: false,
+        };
+
+        stream::unfold(paging, move |mut paging| async move {
+            loop {
+                if let Some(record) = paging.records.pop_front() {
+                    return Some((Ok(record), paging));
+                }
+                let range = paging.range.take()?;
+                if !paging.counted {
+                    paging.counted = true;
+                    match self
+                        .read_as::<u32>(device, object, PropertyIdentifier::RecordCount)
+                        .await
+                    {
+                        Ok(0) => return None,
+                        Ok(_) => {}
+                        Err(e) => return Some((Err(e), paging)),
+                    }
+                }
+                let page = match self.read_log_range(device, object, range).await {
+                    Ok(page) => page,
+                    Err(e) => return Some((Err(e), paging)),
+                };
+                if !page.result_flags.last_item {
+                    if let Some(last) = page.records.last() {
+                        paging.range = Some(Range::BySequenceNumber {
+                            reference_sequence_number: next_sequence_number(last.sequence_number),
+                            count,
+                        });
+                    }
+                }
+                paging.records = page.records.into();
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::tests::*;
+    use crate::network::Address;
+
+    use async_std::task;
+    use futures_lite::StreamExt;
+
+    fn timestamp(minute: u8) -> BACnetDateTime {
+        BACnetDateTime::new(
+            BACnetDate::new(2021, 1, 25),
+            BACnetTime::new(12, minute, 0, 0),
+        )
+    }
+
+    fn encode_record(data: &mut Vec<u8>, minute: u8, value: f32) {
+        encode_opening_tag(data, 0);
+        encode_date(data, timestamp(minute).date);
+        encode_time(data, timestamp(minute).time);
+        encode_closing_tag(data, 0);
+        encode_opening_tag(data, 1);
+        encode_context_real(data, 2, value);
+        encode_closing_tag(data, 1);
+        encode_context_bit_string(data, 2, &[false; 4]);
+    }
+
+    /// ReadRange-ACK for records `first..=last` of a log holding 1..=total
+    fn ack(object: ObjectIdentifier, first: u32, last: u32, total: u32) -> Vec<u8> {
+        let mut data = Vec::new();
+        encode_context_object_identifier(&mut data, 0, object).unwrap();
+        encode_context_enumerated(&mut data, 1, PropertyIdentifier::LogBuffer.number());
+        let flags = vec![first == 1, last == total, false];
+        encode_context_bit_string(&mut data, 3, &flags);
+        encode_context_unsigned(&mut data, 4, last + 1 - first);
+        encode_opening_tag(&mut data, 5);
+        for i in first..=last {
+            encode_record(&mut data, i as u8, i as f32);
+        }
+        encode_closing_tag(&mut data, 5);
+        encode_context_unsigned(&mut data, 6, first);
+        data
+    }
+
+    #[test]
+    fn test_encode_request() {
+        let object = ObjectIdentifier::new(ObjectType::TrendLog, 1);
+        let range = Range::BySequenceNumber {
+            reference_sequence_number: 5,
+            count: -2,
+        };
+        assert_eq!(
+            encode_request(object, &range).unwrap(),
+            hex::decode("0c0500000119836e210531fe6f").unwrap()
+        );
+    }
+
+    #[test]
+    fn test_decode_datum() {
+        let mut data = Vec::new();
+        encode_context_bit_string(&mut data, 0, &[false, true]);
+        encode_opening_tag(&mut data, 8);
+        data.extend_from_slice(&[0x91, 0x02, 0x91, 0x20]);
+        encode_closing_tag(&mut data, 8);
+        encode_context_real(&mut data, 9, -60.0);
+
+        let mut reader = Reader::new(&data);
+        assert_eq!(
+            decode_datum(&mut reader).unwrap(),
+            LogDatum::LogStatus(LogStatus {
+                buffer_purged: true,
+                ..Default::default()
+            })
+        );
+        assert_eq!(
+            decode_datum(&mut reader).unwrap(),
+            LogDatum::Failure(BACnetError::property(ErrorCode::UnknownProperty))
+        );
+        assert_eq!(
+            decode_datum(&mut reader).unwrap(),
+            LogDatum::TimeChange(-60.0)
+        );
+        assert!(reader.is_empty());
+    }
+
+    #[test]
+    fn test_trend_log_records() {
+        task::block_on(async {
+            let (link, device) = link_pair();
+            let client = BacnetClient::new(link);
+            client.add_device(12, Address::local(vec![2]));
+            let object = ObjectIdentifier::new(ObjectType::TrendLog, 1);
+
+            let respond = task::spawn(async move {
+                let request = apdu(device.recv().await.unwrap().1);
+                assert_eq!(request.service_choice(), Some(12));
+                let mut data = request.user_data().to_vec();
+                data.extend_from_slice(&[0x3e, 0x21, 0x20, 0x3f]);
+                reply(
+                    &device,
+                    APDU::complex_ack(request.invoke_id().unwrap(), 12, data),
+                )
+                .await;
+
+                // 480 octets fit 19 records per page
+                for (first, last) in [(1, 19), (20, 32)].iter() {
+                    let request = apdu(device.recv().await.unwrap().1);
+                    assert_eq!(request.service_choice(), Some(26));
+                    let expected = match first {
+                        1 => Range::ByTime {
+                            reference_time: timestamp(0),
+                            count: 19,
+                        },
+                        _ => Range::BySequenceNumber {
+                            reference_sequence_number: 20,
+                            count: 19,
+                        },
+                    };
+                    assert_eq!(
+                        request.user_data(),
+                        &encode_request(object, &expected).unwrap()[..]
+                    );
+                    let data = ack(object, *first, *last, 32);
+                    reply(
+                        &device,
+                        APDU::complex_ack(request.invoke_id().unwrap(), 26, data),
+                    )
+                    .await;
+                }
+            });
+            let records: Vec<_> = client
+                .trend_log_records(12, object, LogStart::Time(timestamp(0)))
+                .collect()
+                .await;
+            respond.await;
+
+            assert_eq!(records.len(), 32);
+            let last = records.last().unwrap().as_ref().unwrap();
+            assert_eq!(last.sequence_number, 32);
+            assert_eq!(last.timestamp, timestamp(32));
+            assert_eq!(last.datum, LogDatum::Value(BACnetValue::Real(32.0)));
+            assert_eq!(last.status_flags, Some(vec![false; 4]));
+        });
+    }
+}
@@ -0,0 +1,228 @@
+//! Time as seen by the stack
+//!
+//! The client and the server read the time from a [`Clock`]: transaction
+//! timeouts, COV subscription lifetimes, event notification retries,
+//! DeviceCommunicationControl durations and the timestamps of notifications.
+//! [`SystemClock`] is the default, a [`MockClock`] only moves when it is
+//! advanced, so time dependent behavior can be tested without waiting:
+//!
+//! ```
+//! use bacnet::clock::{Clock, MockClock};
+//! use std::time::{Duration, UNIX_EPOCH};
+//!
+//! let clock = MockClock::new(UNIX_EPOCH + Duration::from_secs(1_611_532_800));
+//! let start = clock.now();
+//! clock.advance(Duration::from_secs(90));
+//! assert_eq!(clock.now() - start, Duration::from_secs(90));
+//! assert_eq!(clock.date_time().time.minute, 1);
+//! ```
+
+use crate::application::BACnetDateTime;
+use crate::transport::BoxFuture;
+
+use std::collections::HashMap;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll, Waker};
+use std::time::{Duration, Instant, SystemTime};
+
+/// Source of the time
+pub trait Clock: Send + Sync {
+    /// Monotonic time, for timeouts and lifetimes
+    fn now(&self) -> Instant;
+
+    /// Current date and time in UTC, for timestamps
+    fn date_time(&self) -> BACnetDateTime;
+
+    /// Wait until `duration` passed on the clock
+    fn sleep(&self, duration: Duration) -> BoxFuture<'static, ()>;
+
+    /// Set the date and time, as requested by a UTCTimeSynchronization
+    /// (16.8)
+    ///
+    /// Clocks that can't be set ignore it, which is the default.
+    fn set_date_time(&self, _time: BACnetDateTime) {}
+}
+
+/// The time of the system, sleeping on the timers of async-std
+#[derive(Copy, Clone, Debug, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+
+    fn date_time(&self) -> BACnetDateTime {
+        BACnetDateTime::now()
+    }
+
+    fn sleep(&self, duration: Duration) -> BoxFuture<'static, ()> {
+        Box::pin(async_std::task::sleep(duration))
+    }
+}
+
+struct MockState {
+    start: Instant,
+    elapsed: Duration,
+    date_time: SystemTime,
+    /// Pending sleeps, by ID, with the elapsed time they end at
+    sleeps: HashMap<u64, (Duration, Waker)>,
+    next_sleep: u64,
+}
+
+/// A clock that only moves when it is advanced
+///
+/// Clones share the time, one is handed to the stack and the test advances
+/// another.
+#[derive(Clone)]
+pub struct MockClock {
+    state: Arc<Mutex<MockState>>,
+}
+
+impl MockClock {
+    /// A clock starting at the date and time of `time`
+    pub fn new(time: SystemTime) -> Self {
+        Self {
+            state: Arc::new(Mutex::new(MockState {
+                start: Instant::now(),
+                elapsed: Duration::ZERO,
+                date_time: time,
+                sleeps: HashMap::new(),
+                next_sleep: 0,
+            })),
+        }
+    }
+
+    /// Move the time forward, ending the sleeps that are due
+    pub fn advance(&self, duration: Duration) {
+        let mut state = self.state.lock().unwrap();
+        state.elapsed += duration;
+        state.date_time += duration;
+        let elapsed = state.elapsed;
+        let due: Vec<_> = state
+            .sleeps
+            .iter()
+            .filter(|(_, (until, _))| *until <= elapsed)
+            .map(|(id, _)| *id)
+            .collect();
+        let wakers: Vec<_> = due
+            .into_iter()
+            .filter_map(|id| state.sleeps.remove(&id))
+            .map(|(_, waker)| waker)
+            .collect();
+        drop(state);
+        wakers.into_iter().for_each(Waker::wake);
+    }
+
+    /// Time passed since the clock was created
+    pub fn elapsed(&self) -> Duration {
+        self.state.lock().unwrap().elapsed
+    }
+}
+
+impl std::fmt::Debug for MockClock {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let state = self.state.lock().unwrap();
+        f.debug_struct("MockClock")
+            .field("elapsed", &state.elapsed)
+            .field("date_time", &state.date_time)
+            .finish()
+    }
+}
+
+impl Clock for MockClock {
+    fn now(&self) -> Instant {
+        let state = self.state.lock().unwrap();
+        state.start + state.elapsed
+    }
+
+    fn date_time(&self) -> BACnetDateTime {
+        self.state.lock().unwrap().date_time.into()
+    }
+
+    fn sleep(&self, duration: Duration) -> BoxFuture<'static, ()> {
+        let mut state = self.state.lock().unwrap();
+        let id = state.next_sleep;
+        state.next_sleep += 1;
+        Box::pin(MockSleep {
+            state: self.state.clone(),
+            id,
+            until: state.elapsed + duration,
+        })
+    }
+
+    fn set_date_time(&self, time: BACnetDateTime) {
+        if let Some(time) = time.system_time() {
+            self.state.lock().unwrap().date_time = time;
+        }
+    }
+}
+
+struct MockSleep {
+    state: Arc<Mutex<MockState>>,
+    id: u64,
+    until: Duration,
+}
+
+impl Future for MockSleep {
+    type Output = ();
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        let mut state = self.state.lock().unwrap();
+        if state.elapsed >= self.until {
+            return Poll::Ready(());
+        }
+        state
+            .sleeps
+            .insert(self.id, (self.until, cx.waker().clone()));
+        Poll::Pending
+    }
+}
+
+impl Drop for MockSleep {
+    fn drop(&mut self) {
+        self.state.lock().unwrap().sleeps.remove(&self.id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::application::{BACnetDate, BACnetTime};
+
+    use async_std::task;
+    use std::time::UNIX_EPOCH;
+
+    #[test]
+    fn test_mock_clock_sleep() {
+        task::block_on(async {
+            let clock = MockClock::new(UNIX_EPOCH);
+            let sleep = task::spawn(clock.sleep(Duration::from_secs(10)));
+            let cancelled = clock.sleep(Duration::from_secs(5));
+            task::yield_now().await;
+            clock.advance(Duration::from_secs(5));
+            drop(cancelled);
+            clock.advance(Duration::from_secs(5));
+            sleep.await;
+            assert_eq!(clock.elapsed(), Duration::from_secs(10));
+            assert!(clock.state.lock().unwrap().sleeps.is_empty());
+            // A sleep that is already due ends right away
+            clock.sleep(Duration::ZERO).await;
+        });
+    }
+
+    #[test]
+    fn test_mock_clock_date_time() {
+        let clock = MockClock::new(UNIX_EPOCH);
+        let time = BACnetDateTime::new(BACnetDate::new(2021, 1, 25), BACnetTime::new(8, 30, 0, 0));
+        let now = clock.now();
+        clock.set_date_time(time);
+        assert_eq!(clock.date_time(), time);
+        // Setting the date and time does not move the monotonic time
+        assert_eq!(clock.now(), now);
+        clock.advance(Duration::from_secs(60));
+        assert_eq!(clock.date_time().time, BACnetTime::new(8, 31, 0, 0));
+    }
+}
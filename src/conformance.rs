@@ -0,0 +1,233 @@
+//! Conformance test suite of reference frames
+//!
+//! The [`fixtures`](crate::testing::fixtures) in `tests/vectors`, grouped in
+//! directories by layer, are decoded with the decoder they name, encoded
+//! again and compared to the frame octet for octet, with the
+//! [`Encode::len`] and [`Encode::encode_into`] of the decoded frame checked
+//! against the encoding. Expected field values are checked against the
+//! [`json`](crate::json) representation of the decoded frame.
+
+use crate::application::*;
+use crate::network::NPDU;
+use crate::testing::{fixtures, Fixture, FIXTURE_FILES};
+use crate::transport::bacnetip::BVLC;
+use crate::{json, Decode, Encode};
+
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Decode a frame, returning its encoding again and its JSON
+type Decoder = fn(&[u8]) -> Result<(Vec<u8>, String), String>;
+
+fn round_trip<T: Decode + Encode + Serialize>(frame: &[u8]) -> Result<(Vec<u8>, String), String> {
+    let decoded = T::decode_slice(frame).map_err(|e| e.to_string())?;
+    let encoded = decoded.encode_vec().map_err(|e| e.to_string())?;
+    if decoded.len() != encoded.len() {
+        return Err(format!(
+            "length {} of {} octets",
+            decoded.len(),
+            encoded.len()
+        ));
+    }
+    let mut buf = vec![0; encoded.len()];
+    match decoded.encode_into(&mut buf) {
+        Ok(n) if n == encoded.len() && buf == encoded => {}
+        result => return Err(format!("encode_into returned {:?}", result)),
+    }
+    let json = json::to_string(&decoded).map_err(|e| e.to_string())?;
+    Ok((encoded, json))
+}
+
+/// The decoder a vector names, new decoders get an entry here
+fn decoder(name: &str) -> Option<Decoder> {
+    Some(match name {
+        "bvlc" => round_trip::<BVLC>,
+        "npdu" => round_trip::<NPDU>,
+        "apdu" => round_trip::<APDU>,
+        "error" => round_trip::<BACnetError>,
+        "i-am" => round_trip::<IAm>,
+        "i-have" => round_trip::<IHave>,
+        "who-has" => round_trip::<WhoHas>,
+        "who-is" => round_trip::<WhoIs>,
+        "private-transfer" => round_trip::<PrivateTransfer>,
+        "private-transfer-ack" => round_trip::<PrivateTransferAck>,
+        "private-transfer-error" => round_trip::<PrivateTransferError>,
+        "read-property" => round_trip::<ReadPropertyRequest>,
+        "read-property-ack" => round_trip::<ReadPropertyAck>,
+        "read-property-multiple" => round_trip::<ReadPropertyMultipleRequest>,
+        "read-property-multiple-ack" => round_trip::<ReadPropertyMultipleAck>,
+        "cov-notification" => round_trip::<CovNotification>,
+        "event-notification" => round_trip::<EventNotification>,
+        "subscribe-cov" => round_trip::<SubscribeCov>,
+        "subscribe-cov-property" => round_trip::<SubscribeCovProperty>,
+        "acknowledge-alarm" => round_trip::<AcknowledgeAlarm>,
+        "get-alarm-summary-ack" => round_trip::<GetAlarmSummaryAck>,
+        "get-enrollment-summary" => round_trip::<GetEnrollmentSummaryRequest>,
+        "get-enrollment-summary-ack" => round_trip::<GetEnrollmentSummaryAck>,
+        "device-communication-control" => round_trip::<DeviceCommunicationControl>,
+        "reinitialize-device" => round_trip::<ReinitializeDeviceRequest>,
+        "text-message" => round_trip::<TextMessage>,
+        "time-synchronization" => round_trip::<TimeSynchronization>,
+        "vt-open" => round_trip::<VtOpenRequest>,
+        "vt-close-error" => round_trip::<VtCloseError>,
+        "vt-data" => round_trip::<VtDataRequest>,
+        "vt-data-ack" => round_trip::<VtDataAck>,
+        "write-group" => round_trip::<WriteGroup>,
+        _ => return None,
+    })
+}
+
+/// Every `.vectors` file below a directory
+fn files(dir: &Path, found: &mut Vec<PathBuf>) {
+    for entry in fs::read_dir(dir).unwrap() {
+        let path = entry.unwrap().path();
+        if path.is_dir() {
+            files(&path, found);
+        } else if path.extension() == Some("vectors".as_ref()) {
+            found.push(path);
+        }
+    }
+}
+
+/// Every value of a JSON document as its JSON text, by path
+fn flatten(json: &str) -> BTreeMap<String, String> {
+    let mut values = BTreeMap::new();
+    let end = value(json.as_bytes(), 0, "", &mut values);
+    assert_eq!(end, json.len(), "Trailing JSON: {}", json);
+    values
+}
+
+/// Record the value starting at `pos` and its descendants, returning the
+/// position after it
+fn value(json: &[u8], pos: usize, path: &str, values: &mut BTreeMap<String, String>) -> usize {
+    let member = |key: &str| match path {
+        "" => key.to_string(),
+        path => format!("{}.{}", path, key),
+    };
+    let end = match json[pos] {
+        b'{' | b'[' => {
+            let object = json[pos] == b'{';
+            let mut pos = pos + 1;
+            let mut index = 0;
+            while json[pos] != b'}' && json[pos] != b']' {
+                let key = match object {
+                    true => {
+                        let end = string(json, pos);
+                        let key = String::from_utf8_lossy(&json[pos + 1..end - 1]).into_owned();
+                        pos = end + 1; // The colon
+                        key
+                    }
+                    false => index.to_string(),
+                };
+                pos = value(json, pos, &member(&key), values);
+                index += 1;
+                if json[pos] == b',' {
+                    pos += 1;
+                }
+            }
+            pos + 1
+        }
+        b'"' => string(json, pos),
+        _ => {
+            let mut end = pos;
+            while end < json.len() && !matches!(json[end], b',' | b'}' | b']') {
+                end += 1;
+            }
+            end
+        }
+    };
+    let text = String::from_utf8_lossy(&json[pos..end]).into_owned();
+    values.insert(path.to_string(), text);
+    end
+}
+
+/// The position after the string starting at `pos`
+fn string(json: &[u8], pos: usize) -> usize {
+    let mut end = pos + 1;
+    while json[end] != b'"' {
+        end += if json[end] == b'\\' { 2 } else { 1 };
+    }
+    end + 1
+}
+
+/// The failures of a fixture
+fn check(vector: &Fixture) -> Vec<String> {
+    let decode = match decoder(&vector.decode) {
+        Some(decode) => decode,
+        None => return vec![format!("unknown decoder {:?}", vector.decode)],
+    };
+    let (encoded, json) = match (decode(&vector.frame), vector.invalid) {
+        (Ok(_), true) => return vec!["decoded an invalid frame".into()],
+        (Err(_), true) => return Vec::new(),
+        (Err(e), false) => return vec![format!("decoding failed: {}", e)],
+        (Ok(decoded), false) => decoded,
+    };
+
+    let mut failures = Vec::new();
+    let expected = vector.expected();
+    if encoded != expected {
+        failures.push(format!(
+            "encoded as {}, expected {}",
+            hex::encode(&encoded),
+            hex::encode(expected)
+        ));
+    }
+    let values = flatten(&json);
+    for (path, expected) in &vector.fields {
+        match values.get(path) {
+            Some(value) if value == expected => {}
+            Some(value) => failures.push(format!("{} is {}, expected {}", path, value, expected)),
+            None => failures.push(format!("no {} in {}", path, json)),
+        }
+    }
+    failures
+}
+
+#[test]
+fn test_flatten() {
+    let values = flatten(r#"{"a":[1,{"b":"x\"y"}],"c":null}"#);
+    assert_eq!(values["a.0"], "1");
+    assert_eq!(values["a.1.b"], r#""x\"y""#);
+    assert_eq!(values["a.1"], r#"{"b":"x\"y"}"#);
+    assert_eq!(values["c"], "null");
+    assert_eq!(values.len(), 6);
+}
+
+#[test]
+fn test_vectors() {
+    let mut count = 0;
+    let mut failures = Vec::new();
+    for vector in fixtures() {
+        count += 1;
+        for failure in check(&vector) {
+            failures.push(format!("{}: {}", vector.origin, failure));
+        }
+    }
+    assert!(count > 0, "No fixtures");
+    assert!(failures.is_empty(), "\n{}", failures.join("\n"));
+}
+
+#[test]
+fn test_fixture_files() {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/vectors");
+    let mut paths = Vec::new();
+    files(&dir, &mut paths);
+    let mut on_disk: Vec<String> = paths
+        .iter()
+        .map(|p| {
+            p.strip_prefix(&dir)
+                .unwrap()
+                .to_string_lossy()
+                .replace('\\', "/")
+        })
+        .collect();
+    on_disk.sort();
+    let mut embedded: Vec<String> = FIXTURE_FILES.iter().map(|(f, _)| f.to_string()).collect();
+    embedded.sort();
+    assert_eq!(
+        on_disk, embedded,
+        "New fixture files get an entry in FIXTURE_FILES"
+    );
+}
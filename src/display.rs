@@ -0,0 +1,17 @@
+//! Rendering of decoded frames for people, as the tree of [`dissect`]
+//!
+//! The same [`Dissection`] prints as an indented tree with `Display` and,
+//! with the `serde` feature, serializes as nested fields, e.g. as JSON with
+//! `json::to_string`.
+//!
+//! ```
+//! use bacnet::display::dissect_bvlc;
+//!
+//! let frame = [0x81, 0x0b, 0x00, 0x08, 0x01, 0x00, 0x10, 0x08];
+//! let tree = dissect_bvlc(&frame).to_string();
+//! assert!(tree.starts_with("     0..4  BACnet Virtual Link Control"));
+//! ```
+//!
+//! [`dissect`]: crate::dissect
+
+pub use crate::dissect::{dissect_bvlc, dissect_npdu, Dissection, Field};
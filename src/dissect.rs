@@ -0,0 +1,1078 @@
+//! Rendering of frames as an indented tree of their fields, like the packet
+//! details of a protocol analyzer
+//!
+//! Every field is listed with the octets it covers, so the output can be
+//! held against a hex dump of the frame. Decoding stops at the first
+//! malformed field, which is reported along with everything decoded up to
+//! it.
+//!
+//! ```
+//! use bacnet::dissect::dissect_bvlc;
+//!
+//! let frame = [0x81, 0x0b, 0x00, 0x08, 0x01, 0x00, 0x10, 0x08];
+//! println!("{}", dissect_bvlc(&frame));
+//! ```
+//!
+//! With the `serde` feature the same tree serializes as nested fields, e.g.
+//! as JSON with `json::to_string`:
+//!
+//! ```
+//! # #[cfg(feature = "serde")] {
+//! # use bacnet::dissect::dissect_bvlc;
+//! # let frame = [0x81, 0x0b, 0x00, 0x08, 0x01, 0x00, 0x10, 0x08];
+//! let json = bacnet::json::to_string(&dissect_bvlc(&frame)).unwrap();
+//! assert!(json.starts_with(r#"[{"name":"BACnet Virtual Link Control","offset":0,"length":4,"#));
+//! # }
+//! ```
+
+use crate::application::*;
+use crate::client::epics::{epics_name, Value};
+use crate::encoding::codec::{Header, MAX_DEPTH};
+use crate::encoding::{LengthValueType, Reader};
+use crate::network::NPDUMessage;
+
+use num_traits::FromPrimitive;
+#[cfg(feature = "serde")]
+use serde::ser::{Serialize, SerializeSeq, SerializeStruct, Serializer};
+use std::fmt;
+use std::io::{Error, ErrorKind, Result};
+use std::net::{Ipv4Addr, SocketAddrV4};
+
+/// BVLC type of BACnet/IP (J.2)
+const BVLC_TYPE_BACNET_IP: u8 = 0x81;
+
+/// BVLC functions by their code (J.2)
+const BVLC_FUNCTIONS: [&str; 13] = [
+    "bvlc-result",
+    "write-broadcast-distribution-table",
+    "read-broadcast-distribution-table",
+    "read-broadcast-distribution-table-ack",
+    "forwarded-npdu",
+    "register-foreign-device",
+    "read-foreign-device-table",
+    "read-foreign-device-table-ack",
+    "delete-foreign-device-table-entry",
+    "distribute-broadcast-to-network",
+    "original-unicast-npdu",
+    "original-broadcast-npdu",
+    "secure-bvll",
+];
+
+/// Network priorities of the NPCI control octet (6.2.2)
+const PRIORITIES: [&str; 4] = ["normal", "urgent", "critical-equipment", "life-safety"];
+
+/// PDU types of the first APDU octet (20.1)
+const PDU_TYPES: [&str; 8] = [
+    "confirmed-request",
+    "unconfirmed-request",
+    "simple-ack",
+    "complex-ack",
+    "segment-ack",
+    "error",
+    "reject",
+    "abort",
+];
+
+/// BACnetRejectReason (Clause 21)
+const REJECT_REASONS: [&str; 10] = [
+    "other",
+    "buffer-overflow",
+    "inconsistent-parameters",
+    "invalid-parameter-data-type",
+    "invalid-tag",
+    "missing-required-parameter",
+    "parameter-out-of-range",
+    "too-many-arguments",
+    "undefined-enumeration",
+    "unrecognized-service",
+];
+
+/// BACnetAbortReason (Clause 21)
+const ABORT_REASONS: [&str; 12] = [
+    "other",
+    "buffer-overflow",
+    "invalid-apdu-in-this-state",
+    "preempted-by-higher-priority-task",
+    "segmentation-not-supported",
+    "security-error",
+    "insufficient-security",
+    "window-size-out-of-range",
+    "application-exceeded-reply-time",
+    "out-of-resources",
+    "tsm-timeout",
+    "apdu-too-long",
+];
+
+/// Max APDU length accepted by its code (20.1.2.5)
+const MAX_APDU_LENGTHS: [u32; 6] = [50, 128, 206, 480, 1024, 1476];
+
+/// Application datatypes by their tag number (20.2.1.4)
+const DATATYPES: [&str; 13] = [
+    "Null",
+    "Boolean",
+    "Unsigned",
+    "Signed",
+    "Real",
+    "Double",
+    "Octet string",
+    "Character string",
+    "Bit string",
+    "Enumerated",
+    "Date",
+    "Time",
+    "Object identifier",
+];
+
+/// Name of a value of an enumeration
+fn named<T: FromPrimitive + fmt::Debug>(value: u32) -> Option<String> {
+    T::from_u32(value).map(epics_name)
+}
+
+/// Name and number of an entry of a table, `unknown` beyond its end
+fn lookup(table: &[&str], value: u8) -> String {
+    let name = table.get(value as usize).copied().unwrap_or("unknown");
+    format!("{} ({})", name, value)
+}
+
+/// Datatype of a service parameter, determining how its value is shown
+#[derive(Copy, Clone)]
+enum Kind {
+    /// Contents octets in hex, or constructed of any values
+    Any,
+    Unsigned,
+    Boolean,
+    CharacterString,
+    ObjectIdentifier,
+    /// Enumeration with the names of its values
+    Enumerated(fn(u32) -> Option<String>),
+    /// Constructed of the given parameters
+    Sequence(&'static [Parameter]),
+}
+
+/// A parameter of a service, context tagged or, without a tag number,
+/// the next application tagged value
+struct Parameter {
+    tag: Option<u8>,
+    name: &'static str,
+    kind: Kind,
+}
+
+const fn context(tag: u8, name: &'static str, kind: Kind) -> Parameter {
+    Parameter {
+        tag: Some(tag),
+        name,
+        kind,
+    }
+}
+
+const fn application(name: &'static str, kind: Kind) -> Parameter {
+    Parameter {
+        tag: None,
+        name,
+        kind,
+    }
+}
+
+/// Name of a standard property identifier, proprietary ones are only shown
+/// by their number
+fn property_name(value: u32) -> Option<String> {
+    match PropertyIdentifier::from_u32(value)? {
+        PropertyIdentifier::Proprietary(_) => None,
+        property => Some(property.to_string()),
+    }
+}
+
+const PROPERTY: Kind = Kind::Enumerated(property_name);
+
+const READ_PROPERTY: &[Parameter] = &[
+    context(0, "object-identifier", Kind::ObjectIdentifier),
+    context(1, "property-identifier", PROPERTY),
+    context(2, "property-array-index", Kind::Unsigned),
+    context(3, "property-value", Kind::Any),
+];
+
+const WRITE_PROPERTY: &[Parameter] = &[
+    context(0, "object-identifier", Kind::ObjectIdentifier),
+    context(1, "property-identifier", PROPERTY),
+    context(2, "property-array-index", Kind::Unsigned),
+    context(3, "property-value", Kind::Any),
+    context(4, "priority", Kind::Unsigned),
+];
+
+const READ_PROPERTY_MULTIPLE: &[Parameter] = &[
+    context(0, "object-identifier", Kind::ObjectIdentifier),
+    context(
+        1,
+        "list-of-property-references",
+        Kind::Sequence(&[
+            context(0, "property-identifier", PROPERTY),
+            context(1, "property-array-index", Kind::Unsigned),
+        ]),
+    ),
+];
+
+const READ_PROPERTY_MULTIPLE_ACK: &[Parameter] = &[
+    context(0, "object-identifier", Kind::ObjectIdentifier),
+    context(
+        1,
+        "list-of-results",
+        Kind::Sequence(&[
+            context(2, "property-identifier", PROPERTY),
+            context(3, "property-array-index", Kind::Unsigned),
+            context(4, "property-value", Kind::Any),
+            context(5, "property-access-error", Kind::Sequence(ERROR)),
+        ]),
+    ),
+];
+
+const SUBSCRIBE_COV: &[Parameter] = &[
+    context(0, "subscriber-process-identifier", Kind::Unsigned),
+    context(1, "monitored-object-identifier", Kind::ObjectIdentifier),
+    context(2, "issue-confirmed-notifications", Kind::Boolean),
+    context(3, "lifetime", Kind::Unsigned),
+];
+
+const COV_NOTIFICATION: &[Parameter] = &[
+    context(0, "subscriber-process-identifier", Kind::Unsigned),
+    context(1, "initiating-device-identifier", Kind::ObjectIdentifier),
+    context(2, "monitored-object-identifier", Kind::ObjectIdentifier),
+    context(3, "time-remaining", Kind::Unsigned),
+    context(
+        4,
+        "list-of-values",
+        Kind::Sequence(&[
+            context(0, "property-identifier", PROPERTY),
+            context(1, "property-array-index", Kind::Unsigned),
+            context(2, "property-value", Kind::Any),
+            context(3, "priority", Kind::Unsigned),
+        ]),
+    ),
+];
+
+const DEVICE_COMMUNICATION_CONTROL: &[Parameter] = &[
+    context(0, "time-duration", Kind::Unsigned),
+    context(
+        1,
+        "enable-disable",
+        Kind::Enumerated(named::<EnableDisable>),
+    ),
+    context(2, "password", Kind::CharacterString),
+];
+
+const REINITIALIZE_DEVICE: &[Parameter] = &[
+    context(
+        0,
+        "reinitialized-state-of-device",
+        Kind::Enumerated(named::<ReinitializedState>),
+    ),
+    context(1, "password", Kind::CharacterString),
+];
+
+const ERROR: &[Parameter] = &[
+    application("error-class", Kind::Enumerated(named::<ErrorClass>)),
+    application("error-code", Kind::Enumerated(named::<ErrorCode>)),
+];
+
+const I_AM: &[Parameter] = &[
+    application("i-am-device-identifier", Kind::ObjectIdentifier),
+    application("max-apdu-length-accepted", Kind::Unsigned),
+    application(
+        "segmentation-supported",
+        Kind::Enumerated(named::<Segmentation>),
+    ),
+    application("vendor-id", Kind::Unsigned),
+];
+
+const I_HAVE: &[Parameter] = &[
+    application("device-identifier", Kind::ObjectIdentifier),
+    application("object-identifier", Kind::ObjectIdentifier),
+    application("object-name", Kind::CharacterString),
+];
+
+const WHO_HAS: &[Parameter] = &[
+    context(0, "device-instance-range-low-limit", Kind::Unsigned),
+    context(1, "device-instance-range-high-limit", Kind::Unsigned),
+    context(2, "object-identifier", Kind::ObjectIdentifier),
+    context(3, "object-name", Kind::CharacterString),
+];
+
+const WHO_IS: &[Parameter] = &[
+    context(0, "device-instance-range-low-limit", Kind::Unsigned),
+    context(1, "device-instance-range-high-limit", Kind::Unsigned),
+];
+
+const TIME_SYNCHRONIZATION: &[Parameter] = &[
+    application("date", Kind::Any),
+    application("time", Kind::Any),
+];
+
+/// Parameters of a confirmed service request
+fn confirmed_parameters(service: ConfirmedServiceChoice) -> &'static [Parameter] {
+    match service {
+        ConfirmedServiceChoice::ConfirmedCovNotification => COV_NOTIFICATION,
+        ConfirmedServiceChoice::SubscribeCov => SUBSCRIBE_COV,
+        ConfirmedServiceChoice::ReadProperty => READ_PROPERTY,
+        ConfirmedServiceChoice::ReadPropertyMultiple => READ_PROPERTY_MULTIPLE,
+        ConfirmedServiceChoice::WriteProperty => WRITE_PROPERTY,
+        ConfirmedServiceChoice::DeviceCommunicationControl => DEVICE_COMMUNICATION_CONTROL,
+        ConfirmedServiceChoice::ReinitializeDevice => REINITIALIZE_DEVICE,
+        _ => &[],
+    }
+}
+
+/// Parameters of the complex ACK of a confirmed service
+fn ack_parameters(service: ConfirmedServiceChoice) -> &'static [Parameter] {
+    match service {
+        ConfirmedServiceChoice::ReadProperty => READ_PROPERTY,
+        ConfirmedServiceChoice::ReadPropertyMultiple => READ_PROPERTY_MULTIPLE_ACK,
+        _ => &[],
+    }
+}
+
+/// Parameters of an unconfirmed service request
+fn unconfirmed_parameters(service: UnconfirmedServiceChoice) -> &'static [Parameter] {
+    match service {
+        UnconfirmedServiceChoice::IAm => I_AM,
+        UnconfirmedServiceChoice::IHave => I_HAVE,
+        UnconfirmedServiceChoice::UnconfirmedCovNotification => COV_NOTIFICATION,
+        UnconfirmedServiceChoice::TimeSynchronization
+        | UnconfirmedServiceChoice::UtcTimeSynchronization => TIME_SYNCHRONIZATION,
+        UnconfirmedServiceChoice::WhoHas => WHO_HAS,
+        UnconfirmedServiceChoice::WhoIs => WHO_IS,
+        _ => &[],
+    }
+}
+
+/// A field of a frame, covering `length` octets from `offset`
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Field {
+    /// Nesting below the layers, which are at depth 0
+    pub depth: usize,
+    pub offset: usize,
+    pub length: usize,
+    /// Name and value of the field, e.g. `Invoke ID: 3`
+    pub name: String,
+}
+
+/// The fields of a frame in order, formatted as a tree with
+/// [`Display`](fmt::Display)
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct Dissection {
+    pub fields: Vec<Field>,
+}
+
+impl Dissection {
+    /// Whether decoding stopped at a malformed field
+    pub fn is_malformed(&self) -> bool {
+        self.fields.iter().any(|f| f.name.starts_with("Malformed"))
+    }
+}
+
+impl fmt::Display for Dissection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for field in &self.fields {
+            let range = format!("{}..{}", field.offset, field.offset + field.length);
+            let indent = field.depth * 2;
+            writeln!(
+                f,
+                "{:>9}  {:indent$}{}",
+                range,
+                "",
+                field.name,
+                indent = indent
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// The layers, each with the fields it contains as `fields`
+#[cfg(feature = "serde")]
+impl Serialize for Dissection {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        Level(&self.fields).serialize(serializer)
+    }
+}
+
+/// Fields of the same depth, each followed by the deeper ones it contains
+#[cfg(feature = "serde")]
+struct Level<'a>(&'a [Field]);
+
+#[cfg(feature = "serde")]
+impl Serialize for Level<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        let mut seq = serializer.serialize_seq(None)?;
+        let mut rest = self.0;
+        while let Some((field, tail)) = rest.split_first() {
+            let end = tail
+                .iter()
+                .position(|f| f.depth <= field.depth)
+                .unwrap_or(tail.len());
+            let (contained, tail) = tail.split_at(end);
+            seq.serialize_element(&Node(field, Level(contained)))?;
+            rest = tail;
+        }
+        seq.end()
+    }
+}
+
+#[cfg(feature = "serde")]
+struct Node<'a>(&'a Field, Level<'a>);
+
+#[cfg(feature = "serde")]
+impl Serialize for Node<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        let Node(field, contained) = self;
+        let mut node = serializer.serialize_struct("Field", 4)?;
+        node.serialize_field("name", &field.name)?;
+        node.serialize_field("offset", &field.offset)?;
+        node.serialize_field("length", &field.length)?;
+        if !contained.0.is_empty() {
+            node.serialize_field("fields", contained)?;
+        }
+        node.end()
+    }
+}
+
+/// Dissect a BACnet/IP frame, from the BVLC header (Annex J) down to the
+/// service parameters
+pub fn dissect_bvlc(data: &[u8]) -> Dissection {
+    let mut dissector = Dissector::new(data);
+    let result = dissector.bvlc();
+    dissector.finish(result)
+}
+
+/// Dissect an NPDU (6.2) down to the service parameters, e.g. as received
+/// from a [`DataLink`](crate::transport::DataLink)
+pub fn dissect_npdu(data: &[u8]) -> Dissection {
+    let mut dissector = Dissector::new(data);
+    let result = dissector.npdu();
+    dissector.finish(result)
+}
+
+fn invalid(msg: &str) -> Error {
+    Error::new(ErrorKind::InvalidData, msg)
+}
+
+fn truncated() -> Error {
+    Error::new(ErrorKind::UnexpectedEof, "Frame ends within the field")
+}
+
+/// Cursor over a frame collecting its fields
+struct Dissector<'a> {
+    data: &'a [u8],
+    pos: usize,
+    /// Fields containing the current one, their length is known once
+    /// they are closed
+    open: Vec<usize>,
+    fields: Vec<Field>,
+}
+
+impl<'a> Dissector<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Self {
+            data,
+            pos: 0,
+            open: Vec::new(),
+            fields: Vec::new(),
+        }
+    }
+
+    fn finish(mut self, result: Result<()>) -> Dissection {
+        match result {
+            Err(e) => {
+                let name = format!("Malformed: {}", e);
+                self.note(self.data.len() - self.pos, name);
+                self.pos = self.data.len();
+            }
+            Ok(()) if self.pos < self.data.len() => {
+                let name = format!("Trailing data: {} octets", self.data.len() - self.pos);
+                self.note(self.data.len() - self.pos, name);
+                self.pos = self.data.len();
+            }
+            Ok(()) => {}
+        }
+        while !self.open.is_empty() {
+            self.end();
+        }
+        Dissection {
+            fields: self.fields,
+        }
+    }
+
+    /// The next octets without consuming them
+    fn peek(&self, length: usize) -> Result<&'a [u8]> {
+        self.data
+            .get(self.pos..self.pos + length)
+            .ok_or_else(truncated)
+    }
+
+    fn remaining(&self) -> usize {
+        self.data.len() - self.pos
+    }
+
+    /// Add a field without consuming its octets, e.g. a bit of a flags
+    /// octet
+    fn note(&mut self, length: usize, name: String) {
+        self.fields.push(Field {
+            depth: self.open.len(),
+            offset: self.pos,
+            length,
+            name,
+        });
+    }
+
+    /// Add a field consuming its octets
+    fn add(&mut self, length: usize, name: String) -> Result<()> {
+        self.peek(length)?;
+        self.note(length, name);
+        self.pos += length;
+        Ok(())
+    }
+
+    /// Start a field containing those added up to the matching
+    /// [`end`](Self::end)
+    fn begin(&mut self, name: String) {
+        self.note(0, name);
+        self.open.push(self.fields.len() - 1);
+    }
+
+    fn end(&mut self) {
+        if let Some(index) = self.open.pop() {
+            let field = &mut self.fields[index];
+            field.length = self.pos - field.offset;
+        }
+    }
+
+    fn byte(&mut self, name: &str) -> Result<u8> {
+        let value = self.peek(1)?[0];
+        self.add(1, format!("{}: {}", name, value))?;
+        Ok(value)
+    }
+
+    fn word(&mut self, name: &str) -> Result<u16> {
+        let value = u16::from_be_bytes([self.peek(2)?[0], self.peek(2)?[1]]);
+        self.add(2, format!("{}: {}", name, value))?;
+        Ok(value)
+    }
+
+    /// A flags octet, with a field for each of the given bits
+    fn flags(&mut self, name: String, bits: &[(u8, &str)]) -> Result<u8> {
+        let value = self.begin_flags(name, bits)?;
+        self.end_flags();
+        Ok(value)
+    }
+
+    /// Start a flags octet, further fields of its bits can be noted up to
+    /// [`end_flags`](Self::end_flags)
+    fn begin_flags(&mut self, name: String, bits: &[(u8, &str)]) -> Result<u8> {
+        let value = self.peek(1)?[0];
+        self.begin(name);
+        for (mask, name) in bits {
+            self.note(1, format!("{}: {}", name, value & mask != 0));
+        }
+        Ok(value)
+    }
+
+    fn end_flags(&mut self) {
+        self.pos += 1;
+        self.end();
+    }
+
+    /// A MAC address of the given length, empty for a broadcast
+    fn mac(&mut self, name: &str, length: usize) -> Result<()> {
+        let mac = self.peek(length)?;
+        let value = match mac {
+            [] => "broadcast".to_string(),
+            [a, b, c, d, p1, p2] => {
+                let ip = Ipv4Addr::new(*a, *b, *c, *d);
+                let port = u16::from_be_bytes([*p1, *p2]);
+                SocketAddrV4::new(ip, port).to_string()
+            }
+            mac => hex::encode(mac),
+        };
+        self.add(length, format!("{}: {}", name, value))
+    }
+
+    /// The rest of the frame as opaque data
+    fn rest(&mut self, name: &str) -> Result<()> {
+        match self.remaining() {
+            0 => Ok(()),
+            length => self.add(length, format!("{}: {} octets", name, length)),
+        }
+    }
+
+    fn bvlc(&mut self) -> Result<()> {
+        self.begin("BACnet Virtual Link Control".into());
+        let bvlc_type = self.peek(1)?[0];
+        match bvlc_type {
+            BVLC_TYPE_BACNET_IP => self.add(1, "Type: BACnet/IP (0x81)".into())?,
+            t => return Err(invalid(&format!("Unknown BVLC type 0x{:02x}", t))),
+        }
+        let function = self.peek(1)?[0];
+        self.add(
+            1,
+            format!("Function: {}", lookup(&BVLC_FUNCTIONS, function)),
+        )?;
+        self.word("Length")?;
+        match function {
+            0x00 => {
+                self.word("Result code")?;
+            }
+            0x04 => self.mac("Original source address", 6)?,
+            0x05 => {
+                self.word("Time to live")?;
+            }
+            _ => {}
+        }
+        self.end();
+        match function {
+            0x04 | 0x09 | 0x0a | 0x0b => self.npdu(),
+            _ => self.rest("Data"),
+        }
+    }
+
+    fn npdu(&mut self) -> Result<()> {
+        self.begin("Network Layer".into());
+        self.byte("Version")?;
+        let control = format!("Control: 0x{:02x}", self.peek(1)?[0]);
+        let control = self.begin_flags(
+            control,
+            &[
+                (0x80, "Network layer message"),
+                (0x20, "Destination specified"),
+                (0x08, "Source specified"),
+                (0x04, "Data expecting reply"),
+            ],
+        )?;
+        self.note(1, format!("Priority: {}", PRIORITIES[control as usize & 3]));
+        self.end_flags();
+        if control & 0x20 != 0 {
+            self.word("Destination network")?;
+            let length = self.byte("Destination MAC length")?;
+            self.mac("Destination MAC", length as usize)?;
+        }
+        if control & 0x08 != 0 {
+            self.word("Source network")?;
+            let length = self.byte("Source MAC length")?;
+            self.mac("Source MAC", length as usize)?;
+        }
+        if control & 0x20 != 0 {
+            self.byte("Hop count")?;
+        }
+        self.end();
+        match control & 0x80 != 0 {
+            true => self.network_message(),
+            false => self.apdu(),
+        }
+    }
+
+    fn network_message(&mut self) -> Result<()> {
+        self.begin("Network Layer Message".into());
+        let message_type = self.peek(1)?[0];
+        let message = NPDUMessage::from(message_type);
+        let name = match message {
+            NPDUMessage::Proprietary(_) => "proprietary".to_string(),
+            NPDUMessage::Reserved(_) => "reserved".to_string(),
+            message => epics_name(message),
+        };
+        self.add(
+            1,
+            format!("Message type: {} (0x{:02x})", name, message_type),
+        )?;
+        match message {
+            NPDUMessage::WhoIsRouterToNetwork if self.remaining() >= 2 => {
+                self.word("Network")?;
+            }
+            NPDUMessage::IAmRouterToNetwork
+            | NPDUMessage::RouterBusyToNetwork
+            | NPDUMessage::RouterAvailableToNetwork => {
+                while self.remaining() > 0 {
+                    self.word("Network")?;
+                }
+            }
+            NPDUMessage::RejectMessageToNetwork => {
+                self.byte("Reject reason")?;
+                self.word("Network")?;
+            }
+            NPDUMessage::NetworkNumberIs => {
+                self.word("Network")?;
+                self.byte("Configured")?;
+            }
+            NPDUMessage::Proprietary(_) => {
+                self.word("Vendor ID")?;
+                self.rest("Data")?;
+            }
+            _ => self.rest("Data")?,
+        }
+        self.end();
+        Ok(())
+    }
+
+    fn apdu(&mut self) -> Result<()> {
+        self.begin("Application Layer".into());
+        let pdu_type = self.peek(1)?[0] >> 4;
+        let name = format!("PDU type: {}", lookup(&PDU_TYPES, pdu_type));
+        let flags = match pdu_type {
+            0 => self.flags(
+                name,
+                &[
+                    (0x08, "Segmented message"),
+                    (0x04, "More follows"),
+                    (0x02, "Segmented response accepted"),
+                ],
+            )?,
+            3 => self.flags(name, &[(0x08, "Segmented message"), (0x04, "More follows")])?,
+            4 => self.flags(name, &[(0x02, "Negative ACK"), (0x01, "Sent by server")])?,
+            7 => self.flags(name, &[(0x01, "Sent by server")])?,
+            _ => {
+                self.add(1, name)?;
+                0
+            }
+        };
+        let segmented = flags & 0x08 != 0;
+        match pdu_type {
+            0 => {
+                let octet = self.peek(1)?[0];
+                self.begin(format!("Max response: 0x{:02x}", octet));
+                let segments = match octet >> 4 & 0x07 {
+                    0 => "unspecified".to_string(),
+                    7 => "more than 64".to_string(),
+                    n => (1 << n).to_string(),
+                };
+                self.note(1, format!("Max segments accepted: {}", segments));
+                let length = match MAX_APDU_LENGTHS.get(octet as usize & 0x0F) {
+                    Some(length) => length.to_string(),
+                    None => "reserved".to_string(),
+                };
+                self.note(1, format!("Max APDU length accepted: {}", length));
+                self.pos += 1;
+                self.end();
+                self.byte("Invoke ID")?;
+                self.segment(segmented)?;
+                let service = self.confirmed_service()?;
+                self.end();
+                match (segmented, service) {
+                    (true, _) => self.rest("Segment"),
+                    (false, Some(service)) => self.parameters(confirmed_parameters(service)),
+                    (false, None) => self.parameters(&[]),
+                }
+            }
+            1 => {
+                let choice = self.peek(1)?[0];
+                let service = UnconfirmedServiceChoice::from_u8(choice);
+                let name = service.map(epics_name).unwrap_or_else(|| "unknown".into());
+                self.add(1, format!("Service choice: {} ({})", name, choice))?;
+                self.end();
+                self.parameters(service.map(unconfirmed_parameters).unwrap_or(&[]))
+            }
+            2 => {
+                self.byte("Invoke ID")?;
+                self.confirmed_service()?;
+                self.end();
+                Ok(())
+            }
+            3 => {
+                self.byte("Invoke ID")?;
+                self.segment(segmented)?;
+                let service = self.confirmed_service()?;
+                self.end();
+                match (segmented, service) {
+                    (true, _) => self.rest("Segment"),
+                    (false, Some(service)) => self.parameters(ack_parameters(service)),
+                    (false, None) => self.parameters(&[]),
+                }
+            }
+            4 => {
+                self.byte("Invoke ID")?;
+                self.byte("Sequence number")?;
+                self.byte("Actual window size")?;
+                self.end();
+                Ok(())
+            }
+            5 => {
+                self.byte("Invoke ID")?;
+                self.confirmed_service()?;
+                self.end();
+                self.parameters(ERROR)
+            }
+            6 | 7 => {
+                self.byte("Invoke ID")?;
+                let reason = self.peek(1)?[0];
+                let table: &[&str] = match pdu_type {
+                    6 => &REJECT_REASONS,
+                    _ => &ABORT_REASONS,
+                };
+                self.add(1, format!("Reason: {}", lookup(table, reason)))?;
+                self.end();
+                Ok(())
+            }
+            _ => {
+                self.end();
+                self.rest("Data")
+            }
+        }
+    }
+
+    /// Sequence number and window size of a segmented message
+    fn segment(&mut self, segmented: bool) -> Result<()> {
+        if segmented {
+            self.byte("Sequence number")?;
+            self.byte("Proposed window size")?;
+        }
+        Ok(())
+    }
+
+    fn confirmed_service(&mut self) -> Result<Option<ConfirmedServiceChoice>> {
+        let choice = self.peek(1)?[0];
+        let service = ConfirmedServiceChoice::from_u8(choice);
+        let name = service.map(epics_name).unwrap_or_else(|| "unknown".into());
+        self.add(1, format!("Service choice: {} ({})", name, choice))?;
+        Ok(service)
+    }
+
+    /// The tagged parameters making up the rest of the frame
+    fn parameters(&mut self, parameters: &[Parameter]) -> Result<()> {
+        if self.remaining() == 0 {
+            return Ok(());
+        }
+        self.begin("Service Parameters".into());
+        self.tags(parameters, None, 0)?;
+        self.end();
+        Ok(())
+    }
+
+    /// Tags up to the closing tag with the given number, or the end of the
+    /// frame
+    fn tags(&mut self, parameters: &[Parameter], closing: Option<u8>, depth: usize) -> Result<()> {
+        if depth > MAX_DEPTH {
+            return Err(invalid("Constructed value nested too deeply"));
+        }
+        let mut positional = parameters.iter().filter(|p| p.tag.is_none());
+        while self.remaining() > 0 {
+            let header = Reader::new(&self.data[self.pos..]).header()?;
+            let tag = header.tag_number;
+            let parameter = match header.context {
+                true => parameters.iter().find(|p| p.tag == Some(tag)),
+                false => positional.next(),
+            };
+            match header.lvt {
+                LengthValueType::Closing if Some(tag) == closing => return Ok(()),
+                LengthValueType::Closing => return Err(invalid("Unbalanced closing tag")),
+                LengthValueType::Opening => {
+                    let name = parameter
+                        .map(|p| p.name.to_string())
+                        .unwrap_or_else(|| format!("Context tag {}", tag));
+                    self.begin(name);
+                    self.add(header.len, format!("Opening tag {}", tag))?;
+                    let inner = match parameter.map(|p| p.kind) {
+                        Some(Kind::Sequence(inner)) => inner,
+                        _ => &[],
+                    };
+                    self.tags(inner, Some(tag), depth + 1)?;
+                    self.add(header.len, format!("Closing tag {}", tag))?;
+                    self.end();
+                }
+                _ => self.primitive(header, parameter)?,
+            }
+        }
+        match closing {
+            Some(_) => Err(truncated()),
+            None => Ok(()),
+        }
+    }
+
+    fn primitive(&mut self, header: Header, parameter: Option<&Parameter>) -> Result<()> {
+        let length = match header.lvt {
+            LengthValueType::Length(l) => header.len + l as usize,
+            _ => header.len,
+        };
+        let tag = header.tag_number;
+        let mut reader = Reader::new(self.peek(length)?);
+        let kind = parameter.map(|p| p.kind).unwrap_or(Kind::Any);
+        let value = match header.context {
+            false => {
+                let value = reader.application_value()?;
+                match (kind, &value) {
+                    (Kind::Enumerated(names), BACnetValue::Enumerated(v))
+                    | (Kind::Enumerated(names), BACnetValue::Unsigned(v)) => enumerated(names, *v),
+                    _ => Value(&value).to_string(),
+                }
+            }
+            true => match kind {
+                Kind::Unsigned => reader.context_unsigned(tag)?.to_string(),
+                Kind::Boolean => reader.context_boolean(tag)?.to_string(),
+                Kind::CharacterString => format!("\"{}\"", reader.context_character_string(tag)?),
+                Kind::ObjectIdentifier => {
+                    let object = reader.context_object_identifier(tag)?;
+                    Value(&BACnetValue::ObjectIdentifier(object)).to_string()
+                }
+                Kind::Enumerated(names) => enumerated(names, reader.context_enumerated(tag)?),
+                Kind::Any | Kind::Sequence(_) => {
+                    format!(
+                        "X'{}'",
+                        hex::encode_upper(&self.peek(length)?[header.len..])
+                    )
+                }
+            },
+        };
+        let name = match (parameter, header.context) {
+            (Some(p), _) => p.name.to_string(),
+            (None, true) => format!("Context tag {}", tag),
+            (None, false) => DATATYPES
+                .get(tag as usize)
+                .map(|d| d.to_string())
+                .unwrap_or_else(|| format!("Application tag {}", tag)),
+        };
+        self.add(length, format!("{}: {}", name, value))
+    }
+}
+
+/// An enumerated value with its name if known
+fn enumerated(names: fn(u32) -> Option<String>, value: u32) -> String {
+    match names(value) {
+        Some(name) => format!("{} ({})", name, value),
+        None => value.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn names(dissection: &Dissection) -> Vec<(usize, usize, usize, &str)> {
+        dissection
+            .fields
+            .iter()
+            .map(|f| (f.depth, f.offset, f.length, f.name.as_str()))
+            .collect()
+    }
+
+    #[test]
+    fn test_who_is() {
+        let frame = [
+            0x81, 0x0b, 0x00, 0x0c, 0x01, 0x20, 0xff, 0xff, 0x00, 0xff, 0x10, 0x08,
+        ];
+        let dissection = dissect_bvlc(&frame);
+        assert_eq!(
+            names(&dissection),
+            vec![
+                (0, 0, 4, "BACnet Virtual Link Control"),
+                (1, 0, 1, "Type: BACnet/IP (0x81)"),
+                (1, 1, 1, "Function: original-broadcast-npdu (11)"),
+                (1, 2, 2, "Length: 12"),
+                (0, 4, 6, "Network Layer"),
+                (1, 4, 1, "Version: 1"),
+                (1, 5, 1, "Control: 0x20"),
+                (2, 5, 1, "Network layer message: false"),
+                (2, 5, 1, "Destination specified: true"),
+                (2, 5, 1, "Source specified: false"),
+                (2, 5, 1, "Data expecting reply: false"),
+                (2, 5, 1, "Priority: normal"),
+                (1, 6, 2, "Destination network: 65535"),
+                (1, 8, 1, "Destination MAC length: 0"),
+                (1, 9, 0, "Destination MAC: broadcast"),
+                (1, 9, 1, "Hop count: 255"),
+                (0, 10, 2, "Application Layer"),
+                (1, 10, 1, "PDU type: unconfirmed-request (1)"),
+                (1, 11, 1, "Service choice: who-is (8)"),
+            ]
+        );
+        assert!(!dissection.is_malformed());
+    }
+
+    #[test]
+    fn test_read_property_ack() {
+        // ReadProperty-ACK of analog-input:1 present-value 21.5
+        let npdu = [
+            0x01, 0x00, 0x30, 0x07, 0x0c, 0x0c, 0x00, 0x00, 0x00, 0x01, 0x19, 0x55, 0x3e, 0x44,
+            0x41, 0xac, 0x00, 0x00, 0x3f,
+        ];
+        let dissection = dissect_npdu(&npdu);
+        let fields = names(&dissection);
+        assert_eq!(
+            fields[8..],
+            [
+                (0, 2, 3, "Application Layer"),
+                (1, 2, 1, "PDU type: complex-ack (3)"),
+                (2, 2, 1, "Segmented message: false"),
+                (2, 2, 1, "More follows: false"),
+                (1, 3, 1, "Invoke ID: 7"),
+                (1, 4, 1, "Service choice: read-property (12)"),
+                (0, 5, 14, "Service Parameters"),
+                (1, 5, 5, "object-identifier: (analog-input, 1)"),
+                (1, 10, 2, "property-identifier: present-value (85)"),
+                (1, 12, 7, "property-value"),
+                (2, 12, 1, "Opening tag 3"),
+                (2, 13, 5, "Real: 21.5"),
+                (2, 18, 1, "Closing tag 3"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_error_and_malformed() {
+        let npdu = [0x01, 0x00, 0x50, 0x01, 0x0c, 0x91, 0x02, 0x91, 0x20];
+        let dissection = dissect_npdu(&npdu);
+        let fields = names(&dissection);
+        assert_eq!(
+            fields[fields.len() - 2..],
+            [
+                (1, 5, 2, "error-class: property (2)"),
+                (1, 7, 2, "error-code: unknown-property (32)"),
+            ]
+        );
+
+        // Context tag exceeding the frame
+        let dissection = dissect_npdu(&[0x01, 0x00, 0x10, 0x08, 0x0a, 0x01]);
+        assert!(dissection.is_malformed());
+        let last = dissection.fields.last().unwrap();
+        assert_eq!((last.offset, last.length), (4, 2));
+        assert_eq!(dissection.fields[0].length, 2);
+        assert!(dissection.to_string().contains("     4..6    Malformed: "));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serialize() {
+        let dissection = dissect_npdu(&[0x01, 0x00, 0x40, 0x03, 0x01, 0x04]);
+        assert_eq!(
+            crate::json::to_string(&dissection).unwrap(),
+            concat!(
+                r#"[{"name":"Network Layer","offset":0,"length":2,"fields":["#,
+                r#"{"name":"Version: 1","offset":0,"length":1},"#,
+                r#"{"name":"Control: 0x00","offset":1,"length":1,"fields":["#,
+                r#"{"name":"Network layer message: false","offset":1,"length":1},"#,
+                r#"{"name":"Destination specified: false","offset":1,"length":1},"#,
+                r#"{"name":"Source specified: false","offset":1,"length":1},"#,
+                r#"{"name":"Data expecting reply: false","offset":1,"length":1},"#,
+                r#"{"name":"Priority: normal","offset":1,"length":1}]}]},"#,
+                r#"{"name":"Application Layer","offset":2,"length":4,"fields":["#,
+                r#"{"name":"PDU type: segment-ack (4)","offset":2,"length":1,"fields":["#,
+                r#"{"name":"Negative ACK: false","offset":2,"length":1},"#,
+                r#"{"name":"Sent by server: false","offset":2,"length":1}]},"#,
+                r#"{"name":"Invoke ID: 3","offset":3,"length":1},"#,
+                r#"{"name":"Sequence number: 1","offset":4,"length":1},"#,
+                r#"{"name":"Actual window size: 4","offset":5,"length":1}]}]"#,
+            )
+        );
+    }
+
+    #[test]
+    fn test_network_message() {
+        let frame = [
+            0x81, 0x0a, 0x00, 0x0b, 0x01, 0x80, 0x01, 0x00, 0x05, 0x00, 0x06,
+        ];
+        let dissection = dissect_bvlc(&frame);
+        let fields = names(&dissection);
+        assert_eq!(
+            fields[fields.len() - 4..],
+            [
+                (0, 6, 5, "Network Layer Message"),
+                (1, 6, 1, "Message type: i-am-router-to-network (0x01)"),
+                (1, 7, 2, "Network: 5"),
+                (1, 9, 2, "Network: 6"),
+            ]
+        );
+    }
+}
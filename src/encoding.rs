@@ -1,16 +1,22 @@
+pub mod codec;
 mod parse;
+pub mod tree;
+pub use codec::*;
+pub use parse::{decode_buf, encode_buf, parse_apdu, parse_bacnet_tag, parse_bvlc, parse_npdu};
+pub use tree::*;
 
-use nom::IResult;
-
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
 pub struct Tag<'a> {
-    tag_number: TagNumber,
-    lvt: LengthValueType,
-    data: &'a [u8],
+    pub tag_number: TagNumber,
+    pub lvt: LengthValueType,
+    pub data: &'a [u8],
 }
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
 pub enum TagNumber {
     Application(ApplicationTag),
     Context(ContextTag),
 }
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
 pub enum LengthValueType {
     Length(u32),
     Value(u8),
@@ -18,6 +24,7 @@ pub enum LengthValueType {
     Closing,
 }
 
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
 pub enum ApplicationTag {
     Null,                   //= 0,
     Boolean,                //= 1,
@@ -58,9 +65,9 @@ impl From<u8> for ApplicationTag {
     }
 }
 
-impl Into<u8> for ApplicationTag {
-    fn into(self) -> u8 {
-        match self {
+impl From<ApplicationTag> for u8 {
+    fn from(tag: ApplicationTag) -> u8 {
+        match tag {
             ApplicationTag::Null => 0,
             ApplicationTag::Boolean => 1,
             ApplicationTag::UnsignedInteger => 2,
@@ -80,21 +87,20 @@ impl Into<u8> for ApplicationTag {
     }
 }
 
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
 pub enum ContextTag {
     Other(u8),
 }
 
 impl From<u8> for ContextTag {
     fn from(tag_number: u8) -> Self {
-        match tag_number {
-            t => ContextTag::Other(t),
-        }
+        ContextTag::Other(tag_number)
     }
 }
 
-impl Into<u8> for ContextTag {
-    fn into(self) -> u8 {
-        match self {
+impl From<ContextTag> for u8 {
+    fn from(tag: ContextTag) -> u8 {
+        match tag {
             ContextTag::Other(t) => t,
         }
     }
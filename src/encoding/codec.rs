@@ -0,0 +1,1052 @@
+//! Encoding and decoding of tagged values (20.2)
+//!
+//! Service parameters are sequences of application and context tagged
+//! values. [`encode_application`] and [`encode_context`] append a
+//! [`BACnetValue`] to a buffer, a [`Reader`] takes them apart again.
+
+use crate::application::{
+    BACnetDate, BACnetTime, BACnetValue, BitString, CharacterString, ObjectIdentifier,
+};
+use crate::encoding::{encode_buf, LengthValueType};
+
+use crate::error::{EncodingError, Error, Result};
+use std::convert::TryFrom;
+
+/// Nesting of constructed values accepted by [`Reader`]
+pub(crate) const MAX_DEPTH: usize = 16;
+
+/// Character set of a CharacterString (20.2.9)
+const CHARSET_UTF8: u8 = 0;
+
+/// Append the initial octets of a tag with the given length
+pub fn encode_tag(buf: &mut Vec<u8>, tag_number: u8, context: bool, length: u32) {
+    // encode_buf never fails, every tag number and length has an encoding
+    if let Ok(tag) = encode_buf(tag_number, context, length) {
+        buf.extend_from_slice(&tag);
+    }
+}
+
+fn encode_tag_octet(buf: &mut Vec<u8>, tag_number: u8, lvt: u8) {
+    match tag_number {
+        t @ 0..=14 => buf.push(t << 4 | 0b1000 | lvt),
+        t => buf.extend_from_slice(&[0xF8 | lvt, t]),
+    }
+}
+
+/// Append an opening tag (20.2.1.3.2)
+pub fn encode_opening_tag(buf: &mut Vec<u8>, tag_number: u8) {
+    encode_tag_octet(buf, tag_number, 0b110);
+}
+
+/// Append a closing tag (20.2.1.3.2)
+pub fn encode_closing_tag(buf: &mut Vec<u8>, tag_number: u8) {
+    encode_tag_octet(buf, tag_number, 0b111);
+}
+
+/// Append an application tagged value
+///
+/// Arrays and lists are encoded as their elements one after the other,
+/// constructed values as their context tagged elements.
+///
+/// Fails for object identifiers out of range, see
+/// [`ObjectIdentifier`].
+pub fn encode_application(buf: &mut Vec<u8>, value: &BACnetValue) -> Result<()> {
+    match value {
+        BACnetValue::Boolean(b) => encode_tag(buf, 1, false, *b as u32),
+        BACnetValue::Array(elements) | BACnetValue::List(elements) => {
+            for e in elements {
+                encode_application(buf, e)?;
+            }
+        }
+        BACnetValue::Constructed(elements) => {
+            for (tag, e) in elements {
+                encode_context(buf, *tag, e)?;
+            }
+        }
+        primitive => {
+            let (tag_number, data) = primitive_content(primitive)?;
+            encode_primitive(buf, tag_number, &data);
+        }
+    }
+    Ok(())
+}
+
+/// Append an application tagged primitive with its contents octets
+fn encode_primitive(buf: &mut Vec<u8>, tag_number: u8, data: &[u8]) {
+    encode_tag(buf, tag_number, false, data.len() as u32);
+    buf.extend_from_slice(data);
+}
+
+pub fn encode_null(buf: &mut Vec<u8>) {
+    encode_tag(buf, 0, false, 0);
+}
+
+pub fn encode_boolean(buf: &mut Vec<u8>, value: bool) {
+    encode_tag(buf, 1, false, value as u32);
+}
+
+/// Append an application tagged unsigned integer in the fewest octets
+pub fn encode_unsigned(buf: &mut Vec<u8>, value: u32) {
+    encode_primitive(buf, 2, &unsigned_octets(value));
+}
+
+/// Append an application tagged signed integer in the fewest octets
+pub fn encode_signed(buf: &mut Vec<u8>, value: i32) {
+    encode_primitive(buf, 3, &signed_octets(value));
+}
+
+pub fn encode_real(buf: &mut Vec<u8>, value: f32) {
+    encode_primitive(buf, 4, &value.to_be_bytes());
+}
+
+pub fn encode_double(buf: &mut Vec<u8>, value: f64) {
+    encode_primitive(buf, 5, &value.to_be_bytes());
+}
+
+pub fn encode_octet_string(buf: &mut Vec<u8>, value: &[u8]) {
+    encode_primitive(buf, 6, value);
+}
+
+/// Append an application tagged character string in UTF-8
+pub fn encode_character_string(buf: &mut Vec<u8>, value: &str) {
+    encode_tag(buf, 7, false, value.len() as u32 + 1);
+    buf.push(CHARSET_UTF8);
+    buf.extend_from_slice(value.as_bytes());
+}
+
+pub fn encode_bit_string(buf: &mut Vec<u8>, value: &[bool]) {
+    encode_primitive(buf, 8, &BitString::from(value.to_vec()).to_octets());
+}
+
+/// Append an application tagged enumerated value in the fewest octets
+pub fn encode_enumerated(buf: &mut Vec<u8>, value: u32) {
+    encode_primitive(buf, 9, &unsigned_octets(value));
+}
+
+pub fn encode_date(buf: &mut Vec<u8>, value: BACnetDate) {
+    encode_primitive(buf, 10, &<[u8; 4]>::from(value));
+}
+
+pub fn encode_time(buf: &mut Vec<u8>, value: BACnetTime) {
+    encode_primitive(buf, 11, &<[u8; 4]>::from(value));
+}
+
+pub fn encode_object_identifier(buf: &mut Vec<u8>, value: ObjectIdentifier) -> Result<()> {
+    encode_primitive(buf, 12, &u32::try_from(value)?.to_be_bytes());
+    Ok(())
+}
+
+/// Append a context tagged value, arrays, lists and constructed values are
+/// enclosed in opening and closing tags
+pub fn encode_context(buf: &mut Vec<u8>, tag_number: u8, value: &BACnetValue) -> Result<()> {
+    match value {
+        BACnetValue::Array(_) | BACnetValue::List(_) | BACnetValue::Constructed(_) => {
+            encode_opening_tag(buf, tag_number);
+            encode_application(buf, value)?;
+            encode_closing_tag(buf, tag_number);
+        }
+        primitive => {
+            let (_, data) = primitive_content(primitive)?;
+            encode_context_primitive(buf, tag_number, &data);
+        }
+    }
+    Ok(())
+}
+
+pub fn encode_context_unsigned(buf: &mut Vec<u8>, tag_number: u8, value: u32) {
+    encode_context_primitive(buf, tag_number, &unsigned_octets(value));
+}
+
+pub fn encode_context_enumerated(buf: &mut Vec<u8>, tag_number: u8, value: u32) {
+    encode_context_primitive(buf, tag_number, &unsigned_octets(value));
+}
+
+pub fn encode_context_boolean(buf: &mut Vec<u8>, tag_number: u8, value: bool) {
+    encode_context_primitive(buf, tag_number, &[value as u8]);
+}
+
+pub fn encode_context_object_identifier(
+    buf: &mut Vec<u8>,
+    tag_number: u8,
+    value: ObjectIdentifier,
+) -> Result<()> {
+    encode_context_primitive(buf, tag_number, &u32::try_from(value)?.to_be_bytes());
+    Ok(())
+}
+
+/// Append a context tagged primitive with its contents octets
+pub(crate) fn encode_context_primitive(buf: &mut Vec<u8>, tag_number: u8, data: &[u8]) {
+    encode_tag(buf, tag_number, true, data.len() as u32);
+    buf.extend_from_slice(data);
+}
+
+pub fn encode_context_signed(buf: &mut Vec<u8>, tag_number: u8, value: i32) {
+    encode_context_primitive(buf, tag_number, &signed_octets(value));
+}
+
+pub fn encode_context_real(buf: &mut Vec<u8>, tag_number: u8, value: f32) {
+    encode_context_primitive(buf, tag_number, &value.to_be_bytes());
+}
+
+pub fn encode_context_double(buf: &mut Vec<u8>, tag_number: u8, value: f64) {
+    encode_context_primitive(buf, tag_number, &value.to_be_bytes());
+}
+
+pub fn encode_context_octet_string(buf: &mut Vec<u8>, tag_number: u8, value: &[u8]) {
+    encode_context_primitive(buf, tag_number, value);
+}
+
+/// Append a context tagged character string in UTF-8
+pub fn encode_context_character_string(buf: &mut Vec<u8>, tag_number: u8, value: &str) {
+    encode_tag(buf, tag_number, true, value.len() as u32 + 1);
+    buf.push(CHARSET_UTF8);
+    buf.extend_from_slice(value.as_bytes());
+}
+
+pub fn encode_context_bit_string(buf: &mut Vec<u8>, tag_number: u8, value: &[bool]) {
+    let data = BitString::from(value.to_vec()).to_octets();
+    encode_context_primitive(buf, tag_number, &data);
+}
+
+pub fn encode_context_date(buf: &mut Vec<u8>, tag_number: u8, value: BACnetDate) {
+    encode_context_primitive(buf, tag_number, &<[u8; 4]>::from(value));
+}
+
+pub fn encode_context_time(buf: &mut Vec<u8>, tag_number: u8, value: BACnetTime) {
+    encode_context_primitive(buf, tag_number, &<[u8; 4]>::from(value));
+}
+
+/// Application tag number and contents octets of a primitive value
+fn primitive_content(value: &BACnetValue) -> Result<(u8, Vec<u8>)> {
+    Ok(match value {
+        BACnetValue::Null => (0, vec![]),
+        BACnetValue::Boolean(b) => (1, vec![*b as u8]),
+        BACnetValue::Unsigned(u) => (2, unsigned_octets(*u)),
+        BACnetValue::Signed(i) => (3, signed_octets(*i)),
+        BACnetValue::Real(r) => (4, r.to_be_bytes().to_vec()),
+        BACnetValue::Double(d) => (5, d.to_be_bytes().to_vec()),
+        BACnetValue::OctetString(o) => (6, o.clone()),
+        BACnetValue::CharacterString(s) => (7, s.to_octets()?),
+        BACnetValue::BitString(bits) => (8, bits.to_octets()),
+        BACnetValue::Enumerated(e) => (9, unsigned_octets(*e)),
+        BACnetValue::Date(d) => (10, <[u8; 4]>::from(*d).to_vec()),
+        BACnetValue::Time(t) => (11, <[u8; 4]>::from(*t).to_vec()),
+        BACnetValue::ObjectIdentifier(o) => (12, u32::try_from(*o)?.to_be_bytes().to_vec()),
+        // Not primitive, handled by the callers
+        BACnetValue::Array(_) | BACnetValue::List(_) | BACnetValue::Constructed(_) => (0, vec![]),
+    })
+}
+
+/// Unsigned integer in the fewest octets (20.2.4)
+/// Number of octets of an unsigned integer in the fewest octets
+pub(crate) fn unsigned_len(value: u32) -> usize {
+    4 - (value.leading_zeros() / 8).min(3) as usize
+}
+
+/// Write an application tagged primitive of up to 4 octets, e.g. an
+/// Unsigned or Enumerated, in the fewest octets without allocating
+pub(crate) fn write_unsigned<W: std::io::Write>(
+    writer: &mut W,
+    tag_number: u8,
+    value: u32,
+) -> std::io::Result<()> {
+    let len = unsigned_len(value);
+    writer.write_all(&[tag_number << 4 | len as u8])?;
+    writer.write_all(&value.to_be_bytes()[4 - len..])
+}
+
+fn unsigned_octets(value: u32) -> Vec<u8> {
+    let skip = (value.leading_zeros() / 8).min(3) as usize;
+    value.to_be_bytes()[skip..].to_vec()
+}
+
+/// Signed integer in the fewest octets (20.2.5)
+fn signed_octets(value: i32) -> Vec<u8> {
+    let bytes = value.to_be_bytes();
+    let mut skip = 0;
+    while skip < 3 {
+        let redundant = match bytes[skip] {
+            0x00 => bytes[skip + 1] & 0x80 == 0,
+            0xFF => bytes[skip + 1] & 0x80 != 0,
+            _ => false,
+        };
+        if !redundant {
+            break;
+        }
+        skip += 1;
+    }
+    bytes[skip..].to_vec()
+}
+
+fn invalid(msg: &'static str) -> Error {
+    EncodingError::Invalid(msg).into()
+}
+
+fn invalid_tag(msg: &'static str) -> Error {
+    EncodingError::InvalidTag(msg).into()
+}
+
+fn invalid_length(ty: &'static str) -> Error {
+    EncodingError::InvalidLength(ty).into()
+}
+
+fn truncated() -> Error {
+    EncodingError::Truncated.into()
+}
+
+/// Initial octets of a tag
+#[derive(Copy, Clone, Debug)]
+pub(crate) struct Header {
+    pub(crate) tag_number: u8,
+    pub(crate) context: bool,
+    pub(crate) lvt: LengthValueType,
+    /// Number of octets of the tag itself
+    pub(crate) len: usize,
+}
+
+/// Sequential reader over tagged values
+///
+/// Every read checks the data is long enough and fails with
+/// [`EncodingError::Truncated`] otherwise, so a reader can be used on data
+/// received from the network.
+#[derive(Clone, Debug)]
+pub struct Reader<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    pub fn new(data: &'a [u8]) -> Self {
+        Self { data }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
+    /// The data that has not been read yet
+    pub fn remaining(&self) -> &'a [u8] {
+        self.data
+    }
+
+    /// Decode the initial octets of the next tag without consuming it
+    pub(crate) fn header(&self) -> Result<Header> {
+        let byte = |i: usize| self.data.get(i).copied().ok_or_else(truncated);
+        let first = byte(0)?;
+        let mut len = 1;
+
+        // 20.2.1.2 Tag Number
+        let tag_number = match first >> 4 {
+            15 => {
+                len += 1;
+                byte(1)?
+            }
+            t => t,
+        };
+        // 20.2.1.1 Class
+        let context = first & 0b1000 != 0;
+        // 20.2.1.3 Length/Value/Type
+        let lvt = match first & 0b111 {
+            v if !context && tag_number == 1 => LengthValueType::Value(v),
+            l @ 0..=4 => LengthValueType::Length(l as u32),
+            0b101 => {
+                let length = match byte(len)? {
+                    254 => {
+                        let b = [byte(len + 1)?, byte(len + 2)?];
+                        len += 2;
+                        u16::from_be_bytes(b) as u32
+                    }
+                    255 => {
+                        let b = [
+                            byte(len + 1)?,
+                            byte(len + 2)?,
+                            byte(len + 3)?,
+                            byte(len + 4)?,
+                        ];
+                        len += 4;
+                        u32::from_be_bytes(b)
+                    }
+                    l => l as u32,
+                };
+                len += 1;
+                LengthValueType::Length(length)
+            }
+            0b110 if context => LengthValueType::Opening,
+            0b111 if context => LengthValueType::Closing,
+            _ => return Err(invalid_tag("Opening or closing tag of application class")),
+        };
+        if let LengthValueType::Length(l) = lvt {
+            if self.data.len() - len < l as usize {
+                return Err(truncated());
+            }
+        }
+
+        Ok(Header {
+            tag_number,
+            context,
+            lvt,
+            len,
+        })
+    }
+
+    /// Consume the next tag, returning its header and contents octets
+    pub(crate) fn next(&mut self) -> Result<(Header, &'a [u8])> {
+        let header = self.header()?;
+        let length = match header.lvt {
+            LengthValueType::Length(l) => l as usize,
+            _ => 0,
+        };
+        let (tag, rest) = self.data.split_at(header.len + length);
+        self.data = rest;
+        Ok((header, &tag[header.len..]))
+    }
+
+    fn peek_matches(&self, tag_number: u8, lvt: fn(LengthValueType) -> bool) -> bool {
+        matches!(self.header(), Ok(h) if h.context && h.tag_number == tag_number && lvt(h.lvt))
+    }
+
+    /// Whether the next tag is a primitive context tag with the given number
+    pub fn is_context_tag(&self, tag_number: u8) -> bool {
+        self.peek_matches(tag_number, |lvt| matches!(lvt, LengthValueType::Length(_)))
+    }
+
+    pub fn is_opening_tag(&self, tag_number: u8) -> bool {
+        self.peek_matches(tag_number, |lvt| lvt == LengthValueType::Opening)
+    }
+
+    pub fn is_closing_tag(&self, tag_number: u8) -> bool {
+        self.peek_matches(tag_number, |lvt| lvt == LengthValueType::Closing)
+    }
+
+    fn context_data(&mut self, tag_number: u8) -> Result<&'a [u8]> {
+        if !self.is_context_tag(tag_number) {
+            self.header()?;
+            return Err(invalid_tag("Unexpected tag"));
+        }
+        Ok(self.next()?.1)
+    }
+
+    pub fn opening_tag(&mut self, tag_number: u8) -> Result<()> {
+        match self.is_opening_tag(tag_number) {
+            true => self.next().map(|_| ()),
+            false => Err(invalid_tag("Expected an opening tag")),
+        }
+    }
+
+    pub fn closing_tag(&mut self, tag_number: u8) -> Result<()> {
+        match self.is_closing_tag(tag_number) {
+            true => self.next().map(|_| ()),
+            false => Err(invalid_tag("Expected a closing tag")),
+        }
+    }
+
+    pub fn context_unsigned(&mut self, tag_number: u8) -> Result<u32> {
+        decode_unsigned(self.context_data(tag_number)?)
+    }
+
+    /// Read a context tagged unsigned integer if the next tag has the number
+    pub fn optional_context_unsigned(&mut self, tag_number: u8) -> Result<Option<u32>> {
+        match self.is_context_tag(tag_number) {
+            true => self.context_unsigned(tag_number).map(Some),
+            false => Ok(None),
+        }
+    }
+
+    pub fn context_enumerated(&mut self, tag_number: u8) -> Result<u32> {
+        self.context_unsigned(tag_number)
+    }
+
+    pub fn context_boolean(&mut self, tag_number: u8) -> Result<bool> {
+        match self.context_data(tag_number)? {
+            [b] => Ok(*b != 0),
+            _ => Err(invalid_length("boolean")),
+        }
+    }
+
+    pub fn context_character_string(&mut self, tag_number: u8) -> Result<String> {
+        match self.context_value(tag_number, 7)? {
+            BACnetValue::CharacterString(s) => s.into_string(),
+            _ => Err(invalid_tag("Expected a character string")),
+        }
+    }
+
+    pub fn context_object_identifier(&mut self, tag_number: u8) -> Result<ObjectIdentifier> {
+        decode_object_identifier(self.context_data(tag_number)?)
+    }
+
+    pub fn context_signed(&mut self, tag_number: u8) -> Result<i32> {
+        decode_signed(self.context_data(tag_number)?)
+    }
+
+    pub fn context_real(&mut self, tag_number: u8) -> Result<f32> {
+        decode_real(self.context_data(tag_number)?)
+    }
+
+    pub fn context_double(&mut self, tag_number: u8) -> Result<f64> {
+        decode_double(self.context_data(tag_number)?)
+    }
+
+    pub fn context_octet_string(&mut self, tag_number: u8) -> Result<&'a [u8]> {
+        self.context_data(tag_number)
+    }
+
+    pub fn context_bit_string(&mut self, tag_number: u8) -> Result<BitString> {
+        BitString::from_octets(self.context_data(tag_number)?)
+    }
+
+    pub fn context_date(&mut self, tag_number: u8) -> Result<BACnetDate> {
+        decode_four(self.context_data(tag_number)?, "date").map(BACnetDate::from)
+    }
+
+    pub fn context_time(&mut self, tag_number: u8) -> Result<BACnetTime> {
+        decode_four(self.context_data(tag_number)?, "time").map(BACnetTime::from)
+    }
+
+    /// Read a context tagged primitive holding a value of the application
+    /// datatype with the given tag number, e.g. 4 for REAL
+    pub fn context_value(&mut self, tag_number: u8, datatype: u8) -> Result<BACnetValue> {
+        match datatype {
+            1 => self.context_boolean(tag_number).map(BACnetValue::Boolean),
+            _ => decode_primitive(datatype, self.context_data(tag_number)?),
+        }
+    }
+
+    /// Read one application tagged value
+    pub fn application_value(&mut self) -> Result<BACnetValue> {
+        let (header, data) = self.next()?;
+        match header.lvt {
+            _ if header.context => Err(invalid_tag("Expected an application tag")),
+            LengthValueType::Value(v) => Ok(BACnetValue::Boolean(v != 0)),
+            _ => decode_primitive(header.tag_number, data),
+        }
+    }
+
+    /// Contents octets of the next tag, which must be an application tag
+    /// with the given number
+    fn application_data(&mut self, tag_number: u8) -> Result<&'a [u8]> {
+        let header = self.header()?;
+        if header.context || header.tag_number != tag_number {
+            return Err(invalid_tag("Unexpected tag"));
+        }
+        Ok(self.next()?.1)
+    }
+
+    pub fn application_null(&mut self) -> Result<()> {
+        match self.application_data(0)? {
+            [] => Ok(()),
+            _ => Err(invalid_length("null")),
+        }
+    }
+
+    pub fn application_boolean(&mut self) -> Result<bool> {
+        let header = self.header()?;
+        match header.lvt {
+            LengthValueType::Value(v) if !header.context && header.tag_number == 1 => {
+                self.next()?;
+                Ok(v != 0)
+            }
+            _ => Err(invalid_tag("Unexpected tag")),
+        }
+    }
+
+    pub fn application_unsigned(&mut self) -> Result<u32> {
+        decode_unsigned(self.application_data(2)?)
+    }
+
+    pub fn application_signed(&mut self) -> Result<i32> {
+        decode_signed(self.application_data(3)?)
+    }
+
+    pub fn application_real(&mut self) -> Result<f32> {
+        decode_real(self.application_data(4)?)
+    }
+
+    pub fn application_double(&mut self) -> Result<f64> {
+        decode_double(self.application_data(5)?)
+    }
+
+    pub fn application_octet_string(&mut self) -> Result<&'a [u8]> {
+        self.application_data(6)
+    }
+
+    pub fn application_character_string(&mut self) -> Result<CharacterString> {
+        CharacterString::from_octets(self.application_data(7)?)
+    }
+
+    pub fn application_bit_string(&mut self) -> Result<BitString> {
+        BitString::from_octets(self.application_data(8)?)
+    }
+
+    pub fn application_enumerated(&mut self) -> Result<u32> {
+        decode_unsigned(self.application_data(9)?)
+    }
+
+    pub fn application_date(&mut self) -> Result<BACnetDate> {
+        decode_four(self.application_data(10)?, "date").map(BACnetDate::from)
+    }
+
+    pub fn application_time(&mut self) -> Result<BACnetTime> {
+        decode_four(self.application_data(11)?, "time").map(BACnetTime::from)
+    }
+
+    pub fn application_object_identifier(&mut self) -> Result<ObjectIdentifier> {
+        decode_object_identifier(self.application_data(12)?)
+    }
+
+    /// Read values up to and including the closing tag with the given number
+    ///
+    /// Runs of context tagged elements are collected into
+    /// [`BACnetValue::Constructed`], a new one is started whenever the tag
+    /// numbers stop increasing. As the datatype of context tagged primitives
+    /// is only known from the ASN.1 definition they are returned as
+    /// [`BACnetValue::OctetString`] of their contents.
+    pub fn values_until_closing_tag(&mut self, tag_number: u8) -> Result<Vec<BACnetValue>> {
+        let values = self.values(Some(tag_number), 0)?;
+        self.next()?;
+        Ok(values)
+    }
+
+    /// The encoded octets up to the closing tag with the given number,
+    /// reading past it, e.g. of an ABSTRACT-SYNTAX parameter decoded later
+    pub fn raw_until_closing_tag(&mut self, tag_number: u8) -> Result<&'a [u8]> {
+        let start = self.remaining();
+        self.values(Some(tag_number), 0)?;
+        let raw = &start[..start.len() - self.remaining().len()];
+        self.next()?;
+        Ok(raw)
+    }
+
+    /// Read values up to the end of the data, like
+    /// [`values_until_closing_tag`](Self::values_until_closing_tag)
+    pub fn values_to_end(&mut self) -> Result<Vec<BACnetValue>> {
+        self.values(None, 0)
+    }
+
+    fn values(&mut self, closing: Option<u8>, depth: usize) -> Result<Vec<BACnetValue>> {
+        if depth > MAX_DEPTH {
+            return Err(invalid("Constructed value nested too deeply"));
+        }
+        let mut values = Vec::new();
+        let mut elements: Vec<(u8, BACnetValue)> = Vec::new();
+        loop {
+            if self.is_empty() {
+                match closing {
+                    Some(_) => return Err(truncated()),
+                    None => break,
+                }
+            }
+            let header = self.header()?;
+            if !header.context {
+                if !elements.is_empty() {
+                    values.push(BACnetValue::Constructed(std::mem::take(&mut elements)));
+                }
+                values.push(self.application_value()?);
+                continue;
+            }
+            let tag = header.tag_number;
+            let element = match header.lvt {
+                // The closing tag is left to the caller
+                LengthValueType::Closing if Some(tag) == closing => break,
+                LengthValueType::Closing => return Err(invalid_tag("Unbalanced closing tag")),
+                LengthValueType::Opening => {
+                    self.next()?;
+                    let mut inner = self.values(Some(tag), depth + 1)?;
+                    self.next()?;
+                    match inner.len() {
+                        1 => inner.remove(0),
+                        _ => BACnetValue::Array(inner),
+                    }
+                }
+                _ => BACnetValue::OctetString(self.next()?.1.to_vec()),
+            };
+            if matches!(elements.last(), Some((last, _)) if *last >= tag) {
+                values.push(BACnetValue::Constructed(std::mem::take(&mut elements)));
+            }
+            elements.push((tag, element));
+        }
+        if !elements.is_empty() {
+            values.push(BACnetValue::Constructed(elements));
+        }
+        Ok(values)
+    }
+}
+
+fn decode_unsigned(data: &[u8]) -> Result<u32> {
+    match data.len() {
+        1..=4 => Ok(data.iter().fold(0, |v, b| v << 8 | *b as u32)),
+        _ => Err(invalid_length("unsigned integer")),
+    }
+}
+
+fn decode_signed(data: &[u8]) -> Result<i32> {
+    match data.len() {
+        1..=4 => {
+            let sign = if data[0] & 0x80 != 0 { -1 } else { 0 };
+            Ok(data.iter().fold(sign, |v, b| v << 8 | *b as i32))
+        }
+        _ => Err(invalid_length("signed integer")),
+    }
+}
+
+/// The contents octets of a value of four octets, with its type
+fn decode_four(data: &[u8], ty: &'static str) -> Result<[u8; 4]> {
+    <[u8; 4]>::try_from(data).map_err(|_| invalid_length(ty))
+}
+
+fn decode_real(data: &[u8]) -> Result<f32> {
+    decode_four(data, "real").map(f32::from_be_bytes)
+}
+
+fn decode_double(data: &[u8]) -> Result<f64> {
+    <[u8; 8]>::try_from(data)
+        .map(f64::from_be_bytes)
+        .map_err(|_| invalid_length("double"))
+}
+
+fn decode_object_identifier(data: &[u8]) -> Result<ObjectIdentifier> {
+    let id = match data {
+        [a, b, c, d] => u32::from_be_bytes([*a, *b, *c, *d]),
+        _ => return Err(invalid_length("object identifier")),
+    };
+    Ok(ObjectIdentifier::from(id))
+}
+
+pub(crate) fn decode_primitive(tag_number: u8, data: &[u8]) -> Result<BACnetValue> {
+    let four = || match data {
+        [a, b, c, d] => Ok([*a, *b, *c, *d]),
+        _ => Err(invalid_length("primitive value")),
+    };
+    let value = match tag_number {
+        0 => BACnetValue::Null,
+        2 => BACnetValue::Unsigned(decode_unsigned(data)?),
+        3 => BACnetValue::Signed(decode_signed(data)?),
+        4 => BACnetValue::Real(f32::from_be_bytes(four()?)),
+        5 => {
+            let mut b = [0; 8];
+            match data.len() {
+                8 => b.copy_from_slice(data),
+                _ => return Err(invalid_length("double")),
+            }
+            BACnetValue::Double(f64::from_be_bytes(b))
+        }
+        6 => BACnetValue::OctetString(data.to_vec()),
+        7 => BACnetValue::CharacterString(CharacterString::from_octets(data)?),
+        8 => BACnetValue::BitString(BitString::from_octets(data)?),
+        9 => BACnetValue::Enumerated(decode_unsigned(data)?),
+        10 => BACnetValue::Date(four()?.into()),
+        11 => BACnetValue::Time(four()?.into()),
+        12 => BACnetValue::ObjectIdentifier(decode_object_identifier(data)?),
+        _ => return Err(invalid_tag("Reserved application tag")),
+    };
+    Ok(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::application::{CharacterSet, ObjectType};
+
+    fn encoded(value: &BACnetValue) -> Vec<u8> {
+        let mut buf = Vec::new();
+        encode_application(&mut buf, value).unwrap();
+        buf
+    }
+
+    fn decoded(data: &[u8]) -> BACnetValue {
+        let mut reader = Reader::new(data);
+        let value = reader.application_value().unwrap();
+        assert!(reader.is_empty());
+        value
+    }
+
+    #[test]
+    fn test_application_examples() {
+        // Examples of 20.2
+        let examples = vec![
+            (BACnetValue::Null, "00"),
+            (BACnetValue::Boolean(true), "11"),
+            (BACnetValue::Unsigned(72), "2148"),
+            (BACnetValue::Signed(72), "3148"),
+            (BACnetValue::Signed(-1), "31ff"),
+            (BACnetValue::Signed(-129), "32ff7f"),
+            (BACnetValue::Real(72.0), "4442900000"),
+            (BACnetValue::Double(72.0), "55084052000000000000"),
+            (BACnetValue::OctetString(vec![0x12, 0x34, 0xFF]), "631234ff"),
+            (
+                BACnetValue::CharacterString("This is a BACnet string!".into()),
+                "751900546869732069732061204241436e657420737472696e6721",
+            ),
+            // Decoded and encoded again in their character set
+            (
+                BACnetValue::CharacterString(CharacterString::with_character_set(
+                    "Bä",
+                    CharacterSet::Latin1,
+                )),
+                "730542e4",
+            ),
+            (
+                BACnetValue::CharacterString(CharacterString::with_character_set(
+                    "Bä",
+                    CharacterSet::Ucs2,
+                )),
+                "750504004200e4",
+            ),
+            (
+                BACnetValue::CharacterString(
+                    CharacterString::from_octets(&[0x02, 0x30, 0x21]).unwrap(),
+                ),
+                "73023021",
+            ),
+            (
+                BACnetValue::BitString(vec![true, false, true, false, true].into()),
+                "8203a8",
+            ),
+            (BACnetValue::BitString(BitString::new(10)), "83060000"),
+            (BACnetValue::Enumerated(0), "9100"),
+            (
+                BACnetValue::Date(BACnetDate::new(1991, 1, 24)),
+                "a45b011804",
+            ),
+            (
+                BACnetValue::Time(BACnetTime::new(17, 35, 45, 17)),
+                "b411232d11",
+            ),
+            (
+                BACnetValue::ObjectIdentifier(ObjectIdentifier::new(ObjectType::BinaryInput, 15)),
+                "c400c0000f",
+            ),
+        ];
+        for (value, data) in examples {
+            let data = hex::decode(data).unwrap();
+            assert_eq!(encoded(&value), data, "{:?}", value);
+            assert_eq!(decoded(&data), value);
+        }
+    }
+
+    #[test]
+    fn test_typed_application_values() {
+        let date = BACnetDate::new(1991, 1, 24);
+        let time = BACnetTime::new(17, 35, 45, 17);
+        let object = ObjectIdentifier::new(ObjectType::BinaryInput, 15);
+        let bits = BitString::from(vec![true, false, true, false, true]);
+        let mut buf = Vec::new();
+        encode_null(&mut buf);
+        encode_boolean(&mut buf, true);
+        encode_unsigned(&mut buf, 72);
+        encode_signed(&mut buf, -129);
+        encode_real(&mut buf, 72.0);
+        encode_double(&mut buf, 72.0);
+        encode_octet_string(&mut buf, &[0x12, 0x34, 0xFF]);
+        encode_character_string(&mut buf, "This is a BACnet string!");
+        encode_bit_string(&mut buf, bits.bits());
+        encode_enumerated(&mut buf, 0);
+        encode_date(&mut buf, date);
+        encode_time(&mut buf, time);
+        encode_object_identifier(&mut buf, object).unwrap();
+        assert_eq!(
+            hex::encode(&buf),
+            concat!(
+                "00",
+                "11",
+                "2148",
+                "32ff7f",
+                "4442900000",
+                "55084052000000000000",
+                "631234ff",
+                "751900546869732069732061204241436e657420737472696e6721",
+                "8203a8",
+                "9100",
+                "a45b011804",
+                "b411232d11",
+                "c400c0000f",
+            )
+        );
+
+        let mut reader = Reader::new(&buf);
+        reader.application_null().unwrap();
+        assert!(reader.application_boolean().unwrap());
+        assert_eq!(reader.application_unsigned().unwrap(), 72);
+        assert_eq!(reader.application_signed().unwrap(), -129);
+        assert_eq!(reader.application_real().unwrap(), 72.0);
+        assert_eq!(reader.application_double().unwrap(), 72.0);
+        assert_eq!(
+            reader.application_octet_string().unwrap(),
+            [0x12, 0x34, 0xFF]
+        );
+        assert_eq!(
+            reader.application_character_string().unwrap().value,
+            "This is a BACnet string!"
+        );
+        assert_eq!(reader.application_bit_string().unwrap(), bits);
+        // Not an unsigned, the enumerated is left to read
+        assert!(reader.application_unsigned().is_err());
+        assert_eq!(reader.application_enumerated().unwrap(), 0);
+        assert_eq!(reader.application_date().unwrap(), date);
+        assert_eq!(reader.application_time().unwrap(), time);
+        assert_eq!(reader.application_object_identifier().unwrap(), object);
+        assert!(reader.is_empty());
+
+        let mut reader = Reader::new(&[0x43, 0x42, 0x90, 0x00]);
+        assert!(reader.application_real().is_err());
+        let mut reader = Reader::new(&[0x09, 0x01]);
+        assert!(reader.application_enumerated().is_err());
+    }
+
+    #[test]
+    fn test_list_encoding() {
+        let elements = vec![BACnetValue::Unsigned(1), BACnetValue::Real(2.0)];
+        let list = BACnetValue::List(elements.clone());
+        assert_eq!(
+            encoded(&list),
+            encoded(&BACnetValue::Array(elements.clone()))
+        );
+        let mut buf = Vec::new();
+        encode_context(&mut buf, 3, &list).unwrap();
+        assert_eq!(hex::encode(&buf), "3e210144400000003f");
+
+        // Decoded as an array, the encoding does not tell them apart
+        let mut reader = Reader::new(&buf);
+        reader.opening_tag(3).unwrap();
+        assert_eq!(reader.values_until_closing_tag(3).unwrap(), elements);
+    }
+
+    #[test]
+    fn test_context_examples() {
+        let mut buf = Vec::new();
+        encode_context_unsigned(&mut buf, 0, 256);
+        encode_context_boolean(&mut buf, 2, false);
+        encode_context(&mut buf, 33, &BACnetValue::Signed(72)).unwrap();
+        assert_eq!(buf, hex::decode("0a01002900f92148").unwrap());
+    }
+
+    #[test]
+    fn test_constructed_round_trip() {
+        let value = BACnetValue::Constructed(vec![
+            (0, BACnetValue::Enumerated(1)),
+            (
+                3,
+                BACnetValue::Array(vec![BACnetValue::Real(1.0), BACnetValue::Real(2.0)]),
+            ),
+        ]);
+        let mut buf = Vec::new();
+        encode_context(&mut buf, 3, &value).unwrap();
+        assert_eq!(
+            buf,
+            hex::decode("3e09013e443f80000044400000003f3f").unwrap()
+        );
+
+        let mut reader = Reader::new(&buf);
+        reader.opening_tag(3).unwrap();
+        let values = reader.values_until_closing_tag(3).unwrap();
+        assert!(reader.is_empty());
+        assert_eq!(
+            values,
+            vec![BACnetValue::Constructed(vec![
+                (0, BACnetValue::OctetString(vec![1])),
+                (
+                    3,
+                    BACnetValue::Array(vec![BACnetValue::Real(1.0), BACnetValue::Real(2.0)])
+                ),
+            ])]
+        );
+    }
+
+    #[test]
+    fn test_reader_expectations() {
+        let mut buf = Vec::new();
+        encode_context_object_identifier(
+            &mut buf,
+            0,
+            ObjectIdentifier::new(ObjectType::AnalogInput, 1),
+        )
+        .unwrap();
+        encode_context_enumerated(&mut buf, 1, 85);
+
+        let mut reader = Reader::new(&buf);
+        assert!(matches!(
+            reader.context_unsigned(1),
+            Err(Error::Encoding(EncodingError::InvalidTag("Unexpected tag")))
+        ));
+        assert_eq!(
+            reader.context_object_identifier(0).unwrap(),
+            ObjectIdentifier::new(ObjectType::AnalogInput, 1)
+        );
+        assert_eq!(reader.optional_context_unsigned(2).unwrap(), None);
+        assert_eq!(reader.context_enumerated(1).unwrap(), 85);
+        assert!(reader.is_empty());
+
+        let mut buf = Vec::new();
+        encode_context(&mut buf, 2, &BACnetValue::Real(1.5)).unwrap();
+        let mut reader = Reader::new(&buf);
+        assert_eq!(reader.context_value(2, 4).unwrap(), BACnetValue::Real(1.5));
+    }
+
+    #[test]
+    fn test_typed_context_values() {
+        let date = BACnetDate::new(1991, 1, 24);
+        let time = BACnetTime::new(17, 35, 45, 17);
+        let mut buf = Vec::new();
+        encode_opening_tag(&mut buf, 2);
+        encode_context_signed(&mut buf, 0, -129);
+        encode_context_real(&mut buf, 1, 72.0);
+        encode_context_double(&mut buf, 2, 72.0);
+        encode_context_octet_string(&mut buf, 3, &[0x12]);
+        encode_context_character_string(&mut buf, 4, "abc");
+        encode_context_bit_string(&mut buf, 5, &[true, false]);
+        encode_context_date(&mut buf, 6, date);
+        encode_context_time(&mut buf, 17, time);
+        encode_closing_tag(&mut buf, 2);
+        assert_eq!(
+            hex::encode(&buf),
+            concat!(
+                "2e",
+                "0aff7f",
+                "1c42900000",
+                "2d08405200000000000",
+                "0",
+                "3912",
+                "4c00616263",
+                "5a0680",
+                "6c5b011804",
+                "fc1111232d11",
+                "2f",
+            )
+        );
+
+        let mut reader = Reader::new(&buf);
+        reader.opening_tag(2).unwrap();
+        assert_eq!(reader.context_signed(0).unwrap(), -129);
+        assert_eq!(reader.context_real(1).unwrap(), 72.0);
+        assert_eq!(reader.context_double(2).unwrap(), 72.0);
+        assert_eq!(reader.context_octet_string(3).unwrap(), [0x12]);
+        assert_eq!(reader.context_character_string(4).unwrap(), "abc");
+        assert_eq!(
+            reader.context_bit_string(5).unwrap(),
+            BitString::from(vec![true, false])
+        );
+        assert_eq!(reader.context_date(6).unwrap(), date);
+        assert!(reader.context_time(16).is_err());
+        assert_eq!(reader.context_time(17).unwrap(), time);
+        reader.closing_tag(2).unwrap();
+        assert!(reader.is_empty());
+    }
+
+    #[test]
+    fn test_reader_truncated() {
+        for data in &["", "21", "f5", "7506", "55fe00", "3e21"] {
+            let data = hex::decode(data).unwrap();
+            let mut reader = Reader::new(&data);
+            assert!(reader.application_value().is_err());
+            let mut reader = Reader::new(&data);
+            assert!(reader.values_until_closing_tag(3).is_err());
+        }
+        assert!(matches!(
+            Reader::new(&[0x21]).application_value(),
+            Err(Error::Encoding(EncodingError::Truncated))
+        ));
+        // A context tagged boolean is a single octet (20.2.3)
+        assert!(matches!(
+            Reader::new(&[0x0a, 0x01, 0x00]).context_boolean(0),
+            Err(Error::Encoding(EncodingError::InvalidLength("boolean")))
+        ));
+    }
+}
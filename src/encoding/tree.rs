@@ -0,0 +1,235 @@
+use crate::application::BACnetValue;
+use crate::encoding::codec::{
+    decode_primitive, encode_application, encode_closing_tag, encode_context_primitive,
+    encode_opening_tag, Reader, MAX_DEPTH,
+};
+use crate::encoding::LengthValueType;
+use crate::error::{EncodingError, Result};
+
+/// A tagged element of a constructed value (20.2.1.3.2)
+///
+/// Unlike [`Reader::values_until_closing_tag`] the tree keeps every context
+/// tag, context tagged primitives are kept as their contents octets until
+/// they are read as the datatype known from the ASN.1 definition.
+#[derive(Clone, Debug, PartialEq)]
+pub enum TagTree<'a> {
+    /// Application tagged primitive
+    Application(BACnetValue),
+    /// Context tagged primitive with its contents octets
+    Context { tag_number: u8, data: &'a [u8] },
+    /// Elements enclosed in an opening and closing tag with the number
+    Constructed {
+        tag_number: u8,
+        elements: SequenceOf<'a>,
+    },
+}
+
+impl<'a> TagTree<'a> {
+    /// The context tag number, `None` for application tagged values
+    pub fn tag_number(&self) -> Option<u8> {
+        match self {
+            Self::Application(_) => None,
+            Self::Context { tag_number, .. } | Self::Constructed { tag_number, .. } => {
+                Some(*tag_number)
+            }
+        }
+    }
+
+    /// The value of a primitive, context tagged primitives read as the
+    /// application datatype with the given tag number, e.g. 2 for Unsigned
+    pub fn value(&self, datatype: u8) -> Result<BACnetValue> {
+        match self {
+            Self::Application(value) => Ok(value.clone()),
+            Self::Context { data: [b], .. } if datatype == 1 => Ok(BACnetValue::Boolean(*b != 0)),
+            Self::Context { data, .. } => decode_primitive(datatype, data),
+            Self::Constructed { .. } => Err(EncodingError::Invalid("Expected a primitive").into()),
+        }
+    }
+
+    /// The elements of a constructed value, `None` for primitives
+    pub fn elements(&self) -> Option<&SequenceOf<'a>> {
+        match self {
+            Self::Constructed { elements, .. } => Some(elements),
+            _ => None,
+        }
+    }
+
+    /// Append the element as it was encoded
+    pub fn encode(&self, buf: &mut Vec<u8>) -> Result<()> {
+        match self {
+            Self::Application(value) => encode_application(buf, value)?,
+            Self::Context { tag_number, data } => encode_context_primitive(buf, *tag_number, data),
+            Self::Constructed {
+                tag_number,
+                elements,
+            } => {
+                encode_opening_tag(buf, *tag_number);
+                elements.encode(buf)?;
+                encode_closing_tag(buf, *tag_number);
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The elements of a SEQUENCE or SEQUENCE OF, in the order encoded
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct SequenceOf<'a>(pub Vec<TagTree<'a>>);
+
+impl<'a> SequenceOf<'a> {
+    /// The first element with the context tag number
+    pub fn context(&self, tag_number: u8) -> Option<&TagTree<'a>> {
+        self.0.iter().find(|e| e.tag_number() == Some(tag_number))
+    }
+
+    pub fn iter(&self) -> std::slice::Iter<'_, TagTree<'a>> {
+        self.0.iter()
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Append the elements as they were encoded
+    pub fn encode(&self, buf: &mut Vec<u8>) -> Result<()> {
+        self.0.iter().try_for_each(|e| e.encode(buf))
+    }
+}
+
+impl<'a> Reader<'a> {
+    /// Read the elements up to and including the closing tag with the given
+    /// number into a tree
+    ///
+    /// Constructed values may nest up to 16 levels, deeper nesting is an
+    /// error instead of exhausting the stack.
+    ///
+    /// ```
+    /// use bacnet::application::BACnetValue;
+    /// use bacnet::encoding::Reader;
+    ///
+    /// // [1] { [0] 5, [2] { 72 } }
+    /// let data = [0x1E, 0x09, 0x05, 0x2E, 0x21, 0x48, 0x2F, 0x1F];
+    /// let mut reader = Reader::new(&data);
+    /// reader.opening_tag(1).unwrap();
+    /// let tree = reader.tree_until_closing_tag(1).unwrap();
+    /// assert_eq!(tree.context(0).unwrap().value(2).unwrap(), BACnetValue::Unsigned(5));
+    /// assert_eq!(tree.context(2).unwrap().elements().unwrap().len(), 1);
+    /// assert!(reader.is_empty());
+    /// ```
+    pub fn tree_until_closing_tag(&mut self, tag_number: u8) -> Result<SequenceOf<'a>> {
+        self.tree(Some(tag_number), 0)
+    }
+
+    /// Read the elements up to the end of the data into a tree, like
+    /// [`tree_until_closing_tag`](Self::tree_until_closing_tag)
+    pub fn tree_to_end(&mut self) -> Result<SequenceOf<'a>> {
+        self.tree(None, 0)
+    }
+
+    fn tree(&mut self, closing: Option<u8>, depth: usize) -> Result<SequenceOf<'a>> {
+        if depth > MAX_DEPTH {
+            return Err(EncodingError::Invalid("Constructed value nested too deeply").into());
+        }
+        let mut elements = Vec::new();
+        loop {
+            if self.is_empty() {
+                match closing {
+                    Some(_) => return Err(EncodingError::Truncated.into()),
+                    None => break,
+                }
+            }
+            let header = self.header()?;
+            if !header.context {
+                elements.push(TagTree::Application(self.application_value()?));
+                continue;
+            }
+            let tag_number = header.tag_number;
+            let element = match header.lvt {
+                LengthValueType::Closing if Some(tag_number) == closing => {
+                    self.next()?;
+                    break;
+                }
+                LengthValueType::Closing => {
+                    return Err(EncodingError::InvalidTag("Unbalanced closing tag").into())
+                }
+                LengthValueType::Opening => {
+                    self.next()?;
+                    TagTree::Constructed {
+                        tag_number,
+                        elements: self.tree(Some(tag_number), depth + 1)?,
+                    }
+                }
+                _ => TagTree::Context {
+                    tag_number,
+                    data: self.next()?.1,
+                },
+            };
+            elements.push(element);
+        }
+        Ok(SequenceOf(elements))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::application::{ObjectIdentifier, ObjectType};
+
+    #[test]
+    fn test_tag_tree() {
+        // ReadPropertyMultiple request of F.3.7
+        let data = hex::decode("0c000000101e095519021f").unwrap();
+        let tree = Reader::new(&data).tree_to_end().unwrap();
+        assert_eq!(tree.len(), 2);
+        let object = ObjectIdentifier::new(ObjectType::AnalogInput, 16);
+        assert_eq!(
+            tree.context(0).unwrap().value(12).unwrap(),
+            BACnetValue::ObjectIdentifier(object)
+        );
+        let properties = tree.context(1).unwrap().elements().unwrap();
+        let ids: Vec<_> = properties.iter().map(|p| p.value(9).unwrap()).collect();
+        assert_eq!(
+            ids,
+            [BACnetValue::Enumerated(85), BACnetValue::Enumerated(2)]
+        );
+        assert!(tree.context(2).is_none());
+
+        let mut buf = Vec::new();
+        tree.encode(&mut buf).unwrap();
+        assert_eq!(buf, data);
+    }
+
+    #[test]
+    fn test_tag_tree_application() {
+        let data = hex::decode("3e2101440000000009013f").unwrap();
+        let mut reader = Reader::new(&data);
+        reader.opening_tag(3).unwrap();
+        let tree = reader.tree_until_closing_tag(3).unwrap();
+        assert_eq!(tree.0[0], TagTree::Application(BACnetValue::Unsigned(1)));
+        assert_eq!(tree.0[1], TagTree::Application(BACnetValue::Real(0.0)));
+        assert_eq!(tree.0[2].tag_number(), Some(0));
+        assert_eq!(tree.0[2].value(1).unwrap(), BACnetValue::Boolean(true));
+        assert!(tree.0[1].elements().is_none());
+    }
+
+    #[test]
+    fn test_tag_tree_invalid() {
+        // Unbalanced and missing closing tags
+        for data in ["1f", "0e1f", "0e2e2f", "0e0900"] {
+            let data = hex::decode(data).unwrap();
+            assert!(Reader::new(&data).tree_to_end().is_err(), "{:?}", data);
+        }
+
+        let nested = |depth: usize| {
+            let mut data = vec![0x0E; depth];
+            data.extend(vec![0x0F; depth]);
+            Reader::new(&data).tree_to_end().map(|_| ())
+        };
+        assert!(nested(MAX_DEPTH).is_ok());
+        assert!(nested(MAX_DEPTH + 2).is_err());
+    }
+}
@@ -0,0 +1,334 @@
+//! Errors of the layers of the stack
+//!
+//! [`Decode`](crate::Decode), [`Encode`](crate::Encode) and the
+//! [`Reader`](crate::encoding::Reader) return an [`Error`] with the error of
+//! the layer that failed, so callers can match on the cause:
+//!
+//! ```
+//! use bacnet::error::{EncodingError, TransportError};
+//! use bacnet::transport::bacnetip::BVLC;
+//! use bacnet::{Decode, Error};
+//!
+//! let err = BVLC::decode_slice(&[0x82, 0x0a, 0x00, 0x04]).unwrap_err();
+//! assert!(matches!(
+//!     err,
+//!     Error::Transport(TransportError::UnsupportedBvlcType(0x82))
+//! ));
+//!
+//! let err = BVLC::decode_slice(&[0x81, 0x0a, 0x00]).unwrap_err();
+//! assert!(matches!(err, Error::Encoding(EncodingError::Truncated)));
+//! ```
+//!
+//! The asynchronous data links and the client keep returning
+//! [`std::io::Error`]. Errors of the stack convert into it and carry the
+//! [`Error`] as its inner error, converting back recovers it.
+
+use std::fmt;
+use std::io;
+
+/// Result of the stack, with an [`Error`] by default
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+/// Errors of the tag encoding (Clause 20.2)
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum EncodingError {
+    /// A tag or its data exceeds the available data
+    Truncated,
+    /// A tag is not the one expected at its position, with what was expected
+    InvalidTag(&'static str),
+    /// The length of a value does not fit its type, with the type
+    InvalidLength(&'static str),
+    /// A buffer is too short for the encoded value
+    BufferTooShort { len: usize, required: usize },
+    /// The tags do not match the encoding, with what is wrong
+    Invalid(&'static str),
+}
+
+impl EncodingError {
+    fn kind(&self) -> io::ErrorKind {
+        match self {
+            Self::Truncated => io::ErrorKind::UnexpectedEof,
+            Self::BufferTooShort { .. } => io::ErrorKind::WriteZero,
+            _ => io::ErrorKind::InvalidData,
+        }
+    }
+}
+
+impl fmt::Display for EncodingError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Truncated => write!(f, "Tag exceeds the available data"),
+            Self::InvalidTag(msg) => write!(f, "{}", msg),
+            Self::InvalidLength(ty) => write!(f, "Invalid length of {}", ty),
+            Self::BufferTooShort { len, required } => {
+                write!(f, "Buffer of {} octets is too short for {}", len, required)
+            }
+            Self::Invalid(msg) => write!(f, "{}", msg),
+        }
+    }
+}
+
+impl std::error::Error for EncodingError {}
+
+/// Errors of the network layer (Clause 6)
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum NetworkError {
+    /// The list of networks of a network layer message is malformed
+    InvalidNetworkList,
+}
+
+impl NetworkError {
+    fn kind(&self) -> io::ErrorKind {
+        io::ErrorKind::InvalidData
+    }
+}
+
+impl fmt::Display for NetworkError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidNetworkList => write!(f, "Invalid list of networks"),
+        }
+    }
+}
+
+impl std::error::Error for NetworkError {}
+
+/// Errors of the data links
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum TransportError {
+    /// The BVLC type is not BACnet/IP (J.2)
+    UnsupportedBvlcType(u8),
+    /// The BVLC function is not supported
+    UnsupportedBvlcFunction(u8),
+    /// The BBMD did not answer the Register-Foreign-Device
+    RegistrationTimeout,
+    /// The BBMD answered the Register-Foreign-Device with the result code
+    RegistrationRejected(u16),
+    /// The MAC address is not one of the data link
+    InvalidAddress(Vec<u8>),
+}
+
+impl TransportError {
+    fn kind(&self) -> io::ErrorKind {
+        match self {
+            Self::UnsupportedBvlcType(_) | Self::UnsupportedBvlcFunction(_) => {
+                io::ErrorKind::InvalidData
+            }
+            Self::RegistrationTimeout => io::ErrorKind::TimedOut,
+            Self::RegistrationRejected(_) => io::ErrorKind::ConnectionRefused,
+            Self::InvalidAddress(_) => io::ErrorKind::InvalidInput,
+        }
+    }
+}
+
+impl fmt::Display for TransportError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnsupportedBvlcType(t) => write!(f, "BVLC type not supported: {}", t),
+            Self::UnsupportedBvlcFunction(t) => write!(f, "BVLC Function not supported: {}", t),
+            Self::RegistrationTimeout => write!(f, "No answer from the BBMD"),
+            Self::RegistrationRejected(r) => {
+                write!(f, "Foreign device registration rejected: {:#06x}", r)
+            }
+            Self::InvalidAddress(mac) => write!(f, "Not a BACnet/IP address: {:02x?}", mac),
+        }
+    }
+}
+
+impl std::error::Error for TransportError {}
+
+/// Errors of the application layer and its services (Clauses 20, 21)
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum ServiceError {
+    /// No decoder for the service choice
+    UnsupportedService(u8),
+    /// The service has no encoding, with its name
+    UnsupportedEncoding(String),
+    /// The PDU type is reserved (20.1.1)
+    UnsupportedPduType(u8),
+    /// Segmented messages are not supported
+    Segmented,
+    /// The parameters do not match the service, with what is wrong
+    Invalid(&'static str),
+}
+
+impl ServiceError {
+    fn kind(&self) -> io::ErrorKind {
+        match self {
+            Self::UnsupportedEncoding(_) => io::ErrorKind::InvalidInput,
+            _ => io::ErrorKind::InvalidData,
+        }
+    }
+}
+
+impl fmt::Display for ServiceError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnsupportedService(s) => write!(f, "Unsupported service: {}", s),
+            Self::UnsupportedEncoding(s) => write!(f, "Unsupported service: {}", s),
+            Self::UnsupportedPduType(t) => write!(f, "PDU type not supported: {}", t),
+            Self::Segmented => write!(f, "Segmented messages are not supported"),
+            Self::Invalid(msg) => write!(f, "{}", msg),
+        }
+    }
+}
+
+impl std::error::Error for ServiceError {}
+
+impl From<EncodingError> for io::Error {
+    fn from(e: EncodingError) -> Self {
+        io::Error::new(e.kind(), e)
+    }
+}
+
+impl From<NetworkError> for io::Error {
+    fn from(e: NetworkError) -> Self {
+        io::Error::new(e.kind(), e)
+    }
+}
+
+impl From<TransportError> for io::Error {
+    fn from(e: TransportError) -> Self {
+        io::Error::new(e.kind(), e)
+    }
+}
+
+impl From<ServiceError> for io::Error {
+    fn from(e: ServiceError) -> Self {
+        io::Error::new(e.kind(), e)
+    }
+}
+
+/// Errors of the stack, by layer
+#[derive(Debug)]
+pub enum Error {
+    /// An I/O error, or an error of a data link that is not one of the stack
+    Io(io::Error),
+    Encoding(EncodingError),
+    Network(NetworkError),
+    Transport(TransportError),
+    Service(ServiceError),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(e) => write!(f, "I/O error: {}", e),
+            Self::Encoding(e) => write!(f, "Encoding error: {}", e),
+            Self::Network(e) => write!(f, "Network error: {}", e),
+            Self::Transport(e) => write!(f, "Transport error: {}", e),
+            Self::Service(e) => write!(f, "Service error: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Io(e) => Some(e),
+            Self::Encoding(e) => Some(e),
+            Self::Network(e) => Some(e),
+            Self::Transport(e) => Some(e),
+            Self::Service(e) => Some(e),
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    /// Recovers the error of the layer the I/O error carries
+    ///
+    /// Reads that end early, like the `read_exact` of a decoder on a short
+    /// frame, are truncated input.
+    fn from(e: io::Error) -> Self {
+        if e.kind() == io::ErrorKind::UnexpectedEof && e.get_ref().is_none() {
+            return Self::Encoding(EncodingError::Truncated);
+        }
+        let e = match e.downcast::<EncodingError>() {
+            Ok(e) => return Self::Encoding(e),
+            Err(e) => e,
+        };
+        let e = match e.downcast::<NetworkError>() {
+            Ok(e) => return Self::Network(e),
+            Err(e) => e,
+        };
+        let e = match e.downcast::<TransportError>() {
+            Ok(e) => return Self::Transport(e),
+            Err(e) => e,
+        };
+        match e.downcast::<ServiceError>() {
+            Ok(e) => Self::Service(e),
+            Err(e) => Self::Io(e),
+        }
+    }
+}
+
+impl From<Error> for io::Error {
+    fn from(e: Error) -> Self {
+        match e {
+            Error::Io(e) => e,
+            Error::Encoding(e) => e.into(),
+            Error::Network(e) => e.into(),
+            Error::Transport(e) => e.into(),
+            Error::Service(e) => e.into(),
+        }
+    }
+}
+
+impl From<EncodingError> for Error {
+    fn from(e: EncodingError) -> Self {
+        Self::Encoding(e)
+    }
+}
+
+impl From<NetworkError> for Error {
+    fn from(e: NetworkError) -> Self {
+        Self::Network(e)
+    }
+}
+
+impl From<TransportError> for Error {
+    fn from(e: TransportError) -> Self {
+        Self::Transport(e)
+    }
+}
+
+impl From<ServiceError> for Error {
+    fn from(e: ServiceError) -> Self {
+        Self::Service(e)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_io_error_round_trip() {
+        let io: io::Error = TransportError::RegistrationRejected(0x30).into();
+        assert_eq!(io.kind(), io::ErrorKind::ConnectionRefused);
+        assert_eq!(
+            io.to_string(),
+            "Foreign device registration rejected: 0x0030"
+        );
+        assert!(matches!(
+            Error::from(io),
+            Error::Transport(TransportError::RegistrationRejected(0x30))
+        ));
+
+        let io: io::Error = Error::Encoding(EncodingError::Truncated).into();
+        assert_eq!(io.kind(), io::ErrorKind::UnexpectedEof);
+        assert!(matches!(
+            Error::from(io),
+            Error::Encoding(EncodingError::Truncated)
+        ));
+
+        let io = io::Error::from(io::ErrorKind::UnexpectedEof);
+        assert!(matches!(
+            Error::from(io),
+            Error::Encoding(EncodingError::Truncated)
+        ));
+
+        let io = io::Error::from(io::ErrorKind::BrokenPipe);
+        assert!(matches!(Error::from(io), Error::Io(e) if e.kind() == io::ErrorKind::BrokenPipe));
+    }
+}
@@ -0,0 +1,446 @@
+//! PDUs of a fixed size, encoded and decoded without the heap
+//!
+//! Most PDUs of the crate own their addresses and service parameters. A
+//! device that only answers Who-Is with I-Am can do without any allocation:
+//! the received frame is read in place with [`NPDUSlice`] and
+//! [`APDUSlice`], the answer is encoded into the send buffer with
+//! [`UnconfirmedRequest`], whose parameters are encoded in place or kept in
+//! a [`FixedBuf`] of a capacity known at compile time, or an [`OctetBuf`]
+//! in any other [`Storage`].
+//!
+//! ```
+//! use bacnet::application::{IAm, ObjectIdentifier, ObjectType, Segmentation};
+//! use bacnet::fixed::answer_who_is;
+//!
+//! let i_am = IAm {
+//!     device_identifier: ObjectIdentifier::new(ObjectType::Device, 599),
+//!     max_apdu_length_accepted: 480,
+//!     segmentation_supported: Segmentation::NoSegmentation,
+//!     vendor_id: 15,
+//! };
+//! let who_is = [0x81, 0x0b, 0x00, 0x08, 0x01, 0x00, 0x10, 0x08];
+//! let mut buf = [0; 64];
+//! let len = answer_who_is(&who_is, &i_am, &mut buf).unwrap().unwrap();
+//! assert_eq!(&buf[..6], [0x81, 0x0b, 0x00, 0x14, 0x01, 0x00]);
+//! assert_eq!(len, 20);
+//! ```
+
+use crate::application::{APDUSlice, BACnetPDU, IAm, UnconfirmedServiceChoice, WhoIs};
+use crate::error::{EncodingError, Result};
+use crate::network::{NPDUDest, NPDUPriority, NPDUSlice, GLOBAL_BROADCAST, NPDU};
+use crate::transport::bacnetip::{AsU8, BACNETIP, BVLC};
+use crate::Encode;
+
+use core::convert::TryFrom;
+use core::fmt;
+use core::ops::Deref;
+
+/// Memory of a fixed capacity holding the octets of an [`OctetBuf`]
+///
+/// Only `core` is needed, implemented for arrays and for mutable slices of
+/// memory owned elsewhere, e.g. a static send buffer. Other storage, like a
+/// `heapless::Vec` or a DMA buffer, implements it by handing out its octets.
+pub trait Storage {
+    fn octets(&self) -> &[u8];
+
+    fn octets_mut(&mut self) -> &mut [u8];
+}
+
+impl<const N: usize> Storage for [u8; N] {
+    fn octets(&self) -> &[u8] {
+        self
+    }
+
+    fn octets_mut(&mut self) -> &mut [u8] {
+        self
+    }
+}
+
+impl Storage for &mut [u8] {
+    fn octets(&self) -> &[u8] {
+        self
+    }
+
+    fn octets_mut(&mut self) -> &mut [u8] {
+        self
+    }
+}
+
+/// Octets of up to `N`, stored inline
+pub type FixedBuf<const N: usize> = OctetBuf<[u8; N]>;
+
+/// Octets of up to the capacity of a [`Storage`]
+///
+/// Written to as a [`std::io::Write`], writes beyond the capacity are short
+/// and `write_all` fails.
+#[derive(Copy, Clone)]
+pub struct OctetBuf<S> {
+    data: S,
+    len: usize,
+}
+
+impl<const N: usize> FixedBuf<N> {
+    pub const fn new() -> Self {
+        Self {
+            data: [0; N],
+            len: 0,
+        }
+    }
+
+    /// The encoding of a value, fails if it is longer than `N`
+    pub fn encode_from<E: Encode>(value: &E) -> Result<Self> {
+        let mut buf = Self::new();
+        buf.encode_value(value)?;
+        Ok(buf)
+    }
+}
+
+impl<S: Storage> OctetBuf<S> {
+    /// An empty buffer in the given storage
+    pub const fn with_storage(data: S) -> Self {
+        Self { data, len: 0 }
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.data.octets().len()
+    }
+
+    pub fn as_slice(&self) -> &[u8] {
+        &self.data.octets()[..self.len]
+    }
+
+    pub fn clear(&mut self) {
+        self.len = 0;
+    }
+
+    /// Append the octets, nothing is appended if they do not all fit
+    pub fn extend_from_slice(&mut self, octets: &[u8]) -> Result<()> {
+        let end = self.len + octets.len();
+        if end > self.capacity() {
+            return Err(EncodingError::BufferTooShort {
+                len: self.capacity() - self.len,
+                required: octets.len(),
+            }
+            .into());
+        }
+        self.data.octets_mut()[self.len..end].copy_from_slice(octets);
+        self.len = end;
+        Ok(())
+    }
+
+    /// Replace the content with the encoding of a value, fails if it is
+    /// longer than the capacity
+    pub fn encode_value<E: Encode>(&mut self, value: &E) -> Result<()> {
+        self.len = 0;
+        self.len = value.encode_into(self.data.octets_mut())?;
+        Ok(())
+    }
+
+    /// The storage, of which the first [`Encode::len`] octets are used
+    pub fn into_storage(self) -> S {
+        self.data
+    }
+}
+
+impl<const N: usize> Default for FixedBuf<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<S: Storage> Deref for OctetBuf<S> {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        self.as_slice()
+    }
+}
+
+impl<S: Storage> fmt::Debug for OctetBuf<S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.as_slice().fmt(f)
+    }
+}
+
+impl<S: Storage, R: Storage> PartialEq<OctetBuf<R>> for OctetBuf<S> {
+    fn eq(&self, other: &OctetBuf<R>) -> bool {
+        self.as_slice() == other.as_slice()
+    }
+}
+
+impl<S: Storage> Eq for OctetBuf<S> {}
+
+impl<const N: usize> TryFrom<&[u8]> for FixedBuf<N> {
+    type Error = crate::Error;
+
+    fn try_from(octets: &[u8]) -> Result<Self> {
+        let mut buf = Self::new();
+        buf.extend_from_slice(octets)?;
+        Ok(buf)
+    }
+}
+
+impl<S: Storage> std::io::Write for OctetBuf<S> {
+    fn write(&mut self, octets: &[u8]) -> std::io::Result<usize> {
+        let start = self.len;
+        let free = &mut self.data.octets_mut()[start..];
+        let n = octets.len().min(free.len());
+        free[..n].copy_from_slice(&octets[..n]);
+        self.len += n;
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+impl<S: Storage> Encode for OctetBuf<S> {
+    fn encode<T: std::io::Write + Sized>(&self, writer: &mut T) -> Result<()> {
+        writer.write_all(self.as_slice())?;
+        Ok(())
+    }
+
+    fn len(&self) -> usize {
+        self.len
+    }
+}
+
+/// BACnet-Unconfirmed-Request-PDU (20.1.3) with service parameters of any
+/// type, e.g. [`IAm`] or a [`FixedBuf`]
+///
+/// Unlike [`APDU::UnconfirmedRequest`](crate::application::APDU) the
+/// parameters are encoded along with the header instead of into a buffer of
+/// their own.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct UnconfirmedRequest<S> {
+    pub service_choice: u8,
+    pub parameters: S,
+}
+
+impl<S: Encode> Encode for UnconfirmedRequest<S> {
+    fn encode<T: std::io::Write + Sized>(&self, writer: &mut T) -> Result<()> {
+        writer.write_all(&[
+            BACnetPDU::UnconfirmedRequest.as_u8() << 4,
+            self.service_choice,
+        ])?;
+        self.parameters.encode(writer)
+    }
+
+    fn len(&self) -> usize {
+        2 + self.parameters.len()
+    }
+}
+
+/// Original-Broadcast-NPDU (J.2.12) of an NPDU of any content
+struct OriginalBroadcast<A: Encode>(NPDU<A>);
+
+impl<A: Encode> AsU8 for OriginalBroadcast<A> {
+    fn as_u8(&self) -> u8 {
+        0x0b
+    }
+}
+
+impl<A: Encode> Encode for OriginalBroadcast<A> {
+    fn encode<T: std::io::Write + Sized>(&self, writer: &mut T) -> Result<()> {
+        self.0.encode(writer)
+    }
+
+    fn len(&self) -> usize {
+        self.0.len()
+    }
+}
+
+/// Answer a Who-Is in a BACnet/IP frame with the broadcast of the I-Am
+/// (16.10.2), to all networks if the Who-Is came through a router
+///
+/// Returns the length of the answer written to the start of `buf`, `None`
+/// if the frame is not a Who-Is the device answers. Nothing is allocated.
+pub fn answer_who_is(frame: &[u8], i_am: &IAm, buf: &mut [u8]) -> Result<Option<usize>> {
+    let npdu = match frame {
+        [BACNETIP, 0x09..=0x0b, ..] => frame.get(4..),
+        [BACNETIP, 0x04, ..] => frame.get(10..),
+        _ => None,
+    };
+    let npdu = match npdu {
+        Some(npdu) => NPDUSlice::from_slice(npdu)?,
+        None => return Ok(None),
+    };
+    let who_is = match npdu.apdu()? {
+        Some(apdu) if is_who_is(&apdu) => WhoIs::from_slice(apdu.user_data())?,
+        _ => return Ok(None),
+    };
+    if !who_is.matches(i_am.device_identifier.instance) {
+        return Ok(None);
+    }
+
+    let apdu = UnconfirmedRequest {
+        service_choice: UnconfirmedServiceChoice::IAm as u8,
+        parameters: i_am.clone(),
+    };
+    let destination = npdu.source().map(|_| NPDUDest {
+        net: GLOBAL_BROADCAST,
+        adr: Vec::new(),
+        hops: 255,
+    });
+    let npdu: NPDU<_> = NPDU::new(apdu, destination, None, NPDUPriority::Normal);
+    BVLC::new(OriginalBroadcast(npdu))
+        .encode_into(buf)
+        .map(Some)
+}
+
+fn is_who_is(apdu: &APDUSlice) -> bool {
+    apdu.pdu_type() == BACnetPDU::UnconfirmedRequest
+        && apdu.service_choice() == Some(UnconfirmedServiceChoice::WhoIs as u8)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::application::{ObjectIdentifier, ObjectType, Segmentation, APDU};
+    use crate::network::NPDUContent;
+    use crate::transport::bacnetip::BVLCFunction;
+    use crate::Decode;
+    use std::alloc::{GlobalAlloc, Layout, System};
+    use std::cell::Cell;
+
+    /// Counts the allocations of the current thread, tests run in threads of
+    /// their own
+    struct CountingAllocator;
+
+    thread_local! {
+        static ALLOCATIONS: Cell<usize> = const { Cell::new(0) };
+    }
+
+    unsafe impl GlobalAlloc for CountingAllocator {
+        unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+            let _ = ALLOCATIONS.try_with(|a| a.set(a.get() + 1));
+            System.alloc(layout)
+        }
+
+        unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+            System.dealloc(ptr, layout)
+        }
+    }
+
+    #[global_allocator]
+    static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+    fn allocations<T>(f: impl FnOnce() -> T) -> (T, usize) {
+        let before = ALLOCATIONS.with(Cell::get);
+        let result = f();
+        (result, ALLOCATIONS.with(Cell::get) - before)
+    }
+
+    fn i_am() -> IAm {
+        IAm {
+            device_identifier: ObjectIdentifier::new(ObjectType::Device, 599),
+            max_apdu_length_accepted: 1476,
+            segmentation_supported: Segmentation::SegmentedBoth,
+            vendor_id: 260,
+        }
+    }
+
+    #[test]
+    fn test_answer_who_is() {
+        let i_am = i_am();
+        let mut buf = [0; 64];
+        let frames = [
+            "810b000801001008",
+            "810a000d0100100809021a0257",
+            // Through a router, answered to all networks
+            "81040017c0a8010abac00108000106c0a8010abac01008",
+        ];
+        for frame in frames {
+            let frame = hex::decode(frame).unwrap();
+            let (len, count) = allocations(|| answer_who_is(&frame, &i_am, &mut buf));
+            assert_eq!(count, 0);
+            let bvlc = BVLC::decode_slice(&buf[..len.unwrap().unwrap()]).unwrap();
+            let npdu = match bvlc.function {
+                BVLCFunction::OriginalBroadcastNPDU(npdu) => npdu,
+                function => panic!("Not a broadcast: {:?}", function),
+            };
+            assert_eq!(npdu.destination.is_some(), frame[1] == 0x04);
+            let apdu = match npdu.content {
+                NPDUContent::APDU(apdu) => apdu,
+                content => panic!("Not an APDU: {:?}", content),
+            };
+            assert_eq!(
+                apdu.service_choice(),
+                Some(UnconfirmedServiceChoice::IAm as u8)
+            );
+            assert_eq!(IAm::decode_slice(apdu.user_data()).unwrap(), i_am);
+        }
+
+        // Other devices, services and data links
+        for frame in [
+            "810a000c010010080901190a",
+            "810a000801001007",
+            "810b000601801200",
+            "820b000801001008",
+        ] {
+            let frame = hex::decode(frame).unwrap();
+            assert_eq!(answer_who_is(&frame, &i_am, &mut buf).unwrap(), None);
+        }
+        let frame = hex::decode("810b000801001008").unwrap();
+        assert!(answer_who_is(&frame, &i_am, &mut buf[..20]).is_err());
+    }
+
+    #[test]
+    fn test_unconfirmed_request() {
+        let i_am = i_am();
+        let apdu = APDU::unconfirmed_request(0, i_am.encode_vec().unwrap());
+        let (fixed, count) = allocations(|| {
+            let request = UnconfirmedRequest {
+                service_choice: 0,
+                parameters: i_am.clone(),
+            };
+            FixedBuf::<32>::encode_from(&request)
+        });
+        assert_eq!(count, 0);
+        assert_eq!(fixed.unwrap().as_slice(), apdu.encode_vec().unwrap());
+
+        // Parameters kept in a buffer of their own
+        let parameters = FixedBuf::<18>::encode_from(&i_am).unwrap();
+        assert_eq!(parameters.len(), i_am.len());
+        let request = UnconfirmedRequest {
+            service_choice: 0,
+            parameters,
+        };
+        assert_eq!(request.encode_vec().unwrap(), apdu.encode_vec().unwrap());
+        assert!(FixedBuf::<8>::encode_from(&i_am).is_err());
+    }
+
+    #[test]
+    fn test_fixed_buf() {
+        let mut buf = FixedBuf::<4>::try_from(&[1, 2][..]).unwrap();
+        assert_eq!(buf.capacity(), 4);
+        assert!(buf.extend_from_slice(&[3, 4, 5]).is_err());
+        assert_eq!(&buf[..], [1, 2]);
+        buf.extend_from_slice(&[3, 4]).unwrap();
+        assert_eq!(buf, FixedBuf::<8>::try_from(&[1, 2, 3, 4][..]).unwrap());
+        assert!(std::io::Write::write_all(&mut buf, &[5]).is_err());
+        buf.clear();
+        assert!(buf.is_empty());
+        assert!(FixedBuf::<1>::try_from(&[1, 2][..]).is_err());
+    }
+
+    #[test]
+    fn test_octet_buf_in_slice() {
+        let i_am = i_am();
+        let mut memory = [0xff; 24];
+        let mut buf = OctetBuf::with_storage(&mut memory[..]);
+        assert_eq!(buf.capacity(), 24);
+        let (result, count) = allocations(|| buf.encode_value(&i_am));
+        result.unwrap();
+        assert_eq!(count, 0);
+        assert_eq!(buf, FixedBuf::<32>::encode_from(&i_am).unwrap());
+        let len = buf.len();
+        assert_eq!(&buf.into_storage()[..len], i_am.encode_vec().unwrap());
+
+        let mut memory = [0; 8];
+        let mut buf = OctetBuf::with_storage(&mut memory[..]);
+        assert!(buf.encode_value(&i_am).is_err());
+        assert!(buf.is_empty());
+    }
+}
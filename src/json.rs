@@ -0,0 +1,433 @@
+//! Minimal JSON serializer for the [`Serialize`] representations of decoded
+//! frames, built with the `serde` feature
+//!
+//! Frames and their values can also be used with any other serde data
+//! format, this is for exporting them e.g. to `jq` without further
+//! dependencies. Enums are externally tagged and byte arrays are arrays of
+//! numbers, like serde_json does.
+//!
+//! ```
+//! use bacnet::application::BACnetValue;
+//!
+//! let json = bacnet::json::to_string(&BACnetValue::Real(21.5)).unwrap();
+//! assert_eq!(json, r#"{"Real":21.5}"#);
+//! ```
+//!
+//! The representations also implement `Deserialize`, [`from_str`] reads
+//! them back.
+
+use serde::ser::{self, Serialize};
+use std::fmt::{self, Display, Write};
+
+/// Error raised by a [`Serialize`] implementation
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Error(String);
+
+impl Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl ser::Error for Error {
+    fn custom<T: Display>(msg: T) -> Self {
+        Error(msg.to_string())
+    }
+}
+
+type Result<T = ()> = std::result::Result<T, Error>;
+
+mod de;
+pub use de::from_str;
+
+/// Serialize a value as compact JSON
+pub fn to_string<T: Serialize + ?Sized>(value: &T) -> Result<String> {
+    let mut serializer = Serializer { out: String::new() };
+    value.serialize(&mut serializer)?;
+    Ok(serializer.out)
+}
+
+struct Serializer {
+    out: String,
+}
+
+impl Serializer {
+    fn string(&mut self, s: &str) {
+        self.out.push('"');
+        for c in s.chars() {
+            match c {
+                '"' => self.out.push_str("\\\""),
+                '\\' => self.out.push_str("\\\\"),
+                '\n' => self.out.push_str("\\n"),
+                '\r' => self.out.push_str("\\r"),
+                '\t' => self.out.push_str("\\t"),
+                c if (c as u32) < 0x20 => {
+                    let _ = write!(self.out, "\\u{:04x}", c as u32);
+                }
+                c => self.out.push(c),
+            }
+        }
+        self.out.push('"');
+    }
+
+    fn number<T: Display>(&mut self, v: T) -> Result {
+        let _ = write!(self.out, "{}", v);
+        Ok(())
+    }
+
+    /// Start an object with a single member named after an enum variant
+    fn variant(&mut self, variant: &str) {
+        self.out.push('{');
+        self.string(variant);
+        self.out.push(':');
+    }
+
+    fn compound(&mut self, open: char, close: &'static str) -> Compound<'_> {
+        self.out.push(open);
+        Compound {
+            ser: self,
+            first: true,
+            close,
+        }
+    }
+}
+
+/// An array or object being serialized, closed by `close`
+struct Compound<'a> {
+    ser: &'a mut Serializer,
+    first: bool,
+    close: &'static str,
+}
+
+impl Compound<'_> {
+    fn separator(&mut self) {
+        if !self.first {
+            self.ser.out.push(',');
+        }
+        self.first = false;
+    }
+
+    fn element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result {
+        self.separator();
+        value.serialize(&mut *self.ser)
+    }
+
+    fn member<T: Serialize + ?Sized>(&mut self, key: &str, value: &T) -> Result {
+        self.separator();
+        self.ser.string(key);
+        self.ser.out.push(':');
+        value.serialize(&mut *self.ser)
+    }
+
+    fn close(self) -> Result {
+        self.ser.out.push_str(self.close);
+        Ok(())
+    }
+}
+
+impl<'a> ser::Serializer for &'a mut Serializer {
+    type Ok = ();
+    type Error = Error;
+    type SerializeSeq = Compound<'a>;
+    type SerializeTuple = Compound<'a>;
+    type SerializeTupleStruct = Compound<'a>;
+    type SerializeTupleVariant = Compound<'a>;
+    type SerializeMap = Compound<'a>;
+    type SerializeStruct = Compound<'a>;
+    type SerializeStructVariant = Compound<'a>;
+
+    fn serialize_bool(self, v: bool) -> Result {
+        self.number(v)
+    }
+
+    fn serialize_i8(self, v: i8) -> Result {
+        self.number(v)
+    }
+
+    fn serialize_i16(self, v: i16) -> Result {
+        self.number(v)
+    }
+
+    fn serialize_i32(self, v: i32) -> Result {
+        self.number(v)
+    }
+
+    fn serialize_i64(self, v: i64) -> Result {
+        self.number(v)
+    }
+
+    fn serialize_u8(self, v: u8) -> Result {
+        self.number(v)
+    }
+
+    fn serialize_u16(self, v: u16) -> Result {
+        self.number(v)
+    }
+
+    fn serialize_u32(self, v: u32) -> Result {
+        self.number(v)
+    }
+
+    fn serialize_u64(self, v: u64) -> Result {
+        self.number(v)
+    }
+
+    fn serialize_f32(self, v: f32) -> Result {
+        self.serialize_f64(v as f64)
+    }
+
+    // JSON has no representation of NaN and infinity
+    fn serialize_f64(self, v: f64) -> Result {
+        match v.is_finite() {
+            true => self.number(v),
+            false => self.serialize_unit(),
+        }
+    }
+
+    fn serialize_char(self, v: char) -> Result {
+        self.string(v.encode_utf8(&mut [0; 4]));
+        Ok(())
+    }
+
+    fn serialize_str(self, v: &str) -> Result {
+        self.string(v);
+        Ok(())
+    }
+
+    fn serialize_bytes(self, v: &[u8]) -> Result {
+        let mut seq = self.compound('[', "]");
+        for b in v {
+            seq.element(b)?;
+        }
+        seq.close()
+    }
+
+    fn serialize_none(self) -> Result {
+        self.serialize_unit()
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> Result {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result {
+        self.out.push_str("null");
+        Ok(())
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result {
+        self.serialize_unit()
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+    ) -> Result {
+        self.serialize_str(variant)
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> Result {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+        value: &T,
+    ) -> Result {
+        self.variant(variant);
+        value.serialize(&mut *self)?;
+        self.out.push('}');
+        Ok(())
+    }
+
+    fn serialize_seq(self, _len: Option<usize>) -> Result<Compound<'a>> {
+        Ok(self.compound('[', "]"))
+    }
+
+    fn serialize_tuple(self, _len: usize) -> Result<Compound<'a>> {
+        Ok(self.compound('[', "]"))
+    }
+
+    fn serialize_tuple_struct(self, _name: &'static str, _len: usize) -> Result<Compound<'a>> {
+        Ok(self.compound('[', "]"))
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+        _len: usize,
+    ) -> Result<Compound<'a>> {
+        self.variant(variant);
+        Ok(self.compound('[', "]}"))
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<Compound<'a>> {
+        Ok(self.compound('{', "}"))
+    }
+
+    fn serialize_struct(self, _name: &'static str, _len: usize) -> Result<Compound<'a>> {
+        Ok(self.compound('{', "}"))
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+        _len: usize,
+    ) -> Result<Compound<'a>> {
+        self.variant(variant);
+        Ok(self.compound('{', "}}"))
+    }
+}
+
+impl ser::SerializeSeq for Compound<'_> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result {
+        self.element(value)
+    }
+
+    fn end(self) -> Result {
+        self.close()
+    }
+}
+
+impl ser::SerializeTuple for Compound<'_> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result {
+        self.element(value)
+    }
+
+    fn end(self) -> Result {
+        self.close()
+    }
+}
+
+impl ser::SerializeTupleStruct for Compound<'_> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result {
+        self.element(value)
+    }
+
+    fn end(self) -> Result {
+        self.close()
+    }
+}
+
+impl ser::SerializeTupleVariant for Compound<'_> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result {
+        self.element(value)
+    }
+
+    fn end(self) -> Result {
+        self.close()
+    }
+}
+
+impl ser::SerializeMap for Compound<'_> {
+    type Ok = ();
+    type Error = Error;
+
+    // Keys that are not strings, e.g. numbers, are quoted
+    fn serialize_key<T: Serialize + ?Sized>(&mut self, key: &T) -> Result {
+        self.separator();
+        let key = to_string(key)?;
+        match key.starts_with('"') {
+            true => self.ser.out.push_str(&key),
+            false => self.ser.string(&key),
+        }
+        self.ser.out.push(':');
+        Ok(())
+    }
+
+    fn serialize_value<T: Serialize + ?Sized>(&mut self, value: &T) -> Result {
+        value.serialize(&mut *self.ser)
+    }
+
+    fn end(self) -> Result {
+        self.close()
+    }
+}
+
+impl ser::SerializeStruct for Compound<'_> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, key: &'static str, value: &T) -> Result {
+        self.member(key, value)
+    }
+
+    fn end(self) -> Result {
+        self.close()
+    }
+}
+
+impl ser::SerializeStructVariant for Compound<'_> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, key: &'static str, value: &T) -> Result {
+        self.member(key, value)
+    }
+
+    fn end(self) -> Result {
+        self.close()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeMap;
+
+    #[derive(serde::Serialize)]
+    enum Shape {
+        Empty,
+        Point(i32, i32),
+        Named { name: String },
+    }
+
+    #[test]
+    fn test_to_string() {
+        assert_eq!(to_string(&Shape::Empty).unwrap(), r#""Empty""#);
+        assert_eq!(
+            to_string(&Shape::Point(1, -2)).unwrap(),
+            r#"{"Point":[1,-2]}"#
+        );
+        let named = Shape::Named {
+            name: "a \"b\"\n".into(),
+        };
+        assert_eq!(
+            to_string(&named).unwrap(),
+            r#"{"Named":{"name":"a \"b\"\n"}}"#
+        );
+
+        let map: BTreeMap<u8, Option<f32>> = vec![(1, Some(0.5)), (2, None), (3, Some(f32::NAN))]
+            .into_iter()
+            .collect();
+        assert_eq!(to_string(&map).unwrap(), r#"{"1":0.5,"2":null,"3":null}"#);
+        assert_eq!(to_string(&vec![(); 2]).unwrap(), "[null,null]");
+    }
+}
@@ -1,27 +1,105 @@
 pub mod application;
+#[cfg(feature = "capture")]
+pub mod capture;
+#[cfg(feature = "runtime")]
+pub mod client;
+#[cfg(feature = "runtime")]
+pub mod clock;
+#[cfg(all(test, feature = "serde"))]
+mod conformance;
+#[cfg(feature = "runtime")]
+pub mod display;
+#[cfg(feature = "runtime")]
+pub mod dissect;
 pub mod encoding;
+pub mod error;
+pub mod fixed;
+#[cfg(feature = "serde")]
+pub mod json;
+#[cfg(feature = "metrics")]
+pub mod metrics;
+#[cfg(feature = "mqtt")]
+pub mod mqtt;
 pub mod network;
+pub mod objects;
+#[cfg(feature = "runtime")]
+pub mod replay;
+#[cfg(feature = "runtime")]
+pub mod server;
+#[cfg(feature = "runtime")]
+mod station;
+pub mod testing;
 pub mod transport;
 
+pub use error::Error;
+
 pub trait Decode<S: Decode = Self> {
-    fn decode<T: std::io::Read + Sized>(reader: &mut T) -> std::io::Result<S>;
+    fn decode<T: std::io::Read + Sized>(reader: &mut T) -> error::Result<S>;
 
-    fn decode_slice(slice: &[u8]) -> std::io::Result<S> {
+    fn decode_slice(slice: &[u8]) -> error::Result<S> {
         let mut reader = std::io::Cursor::new(slice);
         S::decode(&mut reader)
     }
+
+    /// Decode a frame, data that is kept of it may share its buffer
+    /// instead of being copied
+    fn decode_bytes(data: bytes::Bytes) -> error::Result<S> {
+        S::decode_slice(&data)
+    }
 }
 
 pub trait Encode {
-    fn encode<T: std::io::Write + Sized>(&self, writer: &mut T) -> std::io::Result<()>;
+    fn encode<T: std::io::Write + Sized>(&self, writer: &mut T) -> error::Result<()>;
 
-    fn encode_vec(&self) -> std::io::Result<Vec<u8>> {
+    fn encode_vec(&self) -> error::Result<Vec<u8>> {
         let mut v = Vec::with_capacity(self.len());
         self.encode(&mut v)?;
         Ok(v)
     }
 
+    /// Encode into a buffer, e.g. a [`bytes::BytesMut`] reused for many
+    /// frames or a fixed `&mut [u8]`
+    ///
+    /// Fails before writing anything if the buffer has less room than
+    /// [`len`](Self::len), buffers that grow always have room.
+    fn encode_buf<B: bytes::BufMut>(&self, buf: &mut B) -> error::Result<()> {
+        let len = self.len();
+        if buf.remaining_mut() < len {
+            return Err(error::EncodingError::BufferTooShort {
+                len: buf.remaining_mut(),
+                required: len,
+            }
+            .into());
+        }
+        self.encode(&mut bytes::BufMut::writer(buf))
+    }
+
+    /// Encode into the start of `buf`, returning the number of octets
+    /// written, which is [`len`](Self::len)
+    ///
+    /// Fails if `buf` is shorter, frames can be encoded into a buffer of
+    /// their length without reallocation.
+    fn encode_into(&self, buf: &mut [u8]) -> error::Result<usize> {
+        let len = self.len();
+        if buf.len() < len {
+            return Err(error::EncodingError::BufferTooShort {
+                len: buf.len(),
+                required: len,
+            }
+            .into());
+        }
+        let mut writer = &mut buf[..len];
+        self.encode(&mut writer)?;
+        debug_assert!(writer.is_empty(), "Encoded fewer octets than the length");
+        Ok(len)
+    }
+
+    /// The number of octets [`encode`](Self::encode) writes, exactly
     fn len(&self) -> usize;
+
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
 }
 
 #[cfg(test)]
@@ -32,7 +110,7 @@ mod tests {
     pub struct Dummy {}
 
     impl Encode for Dummy {
-        fn encode<T: std::io::Write + Sized>(&self, _writer: &mut T) -> std::io::Result<()> {
+        fn encode<T: std::io::Write + Sized>(&self, _writer: &mut T) -> crate::error::Result<()> {
             Ok(())
         }
 
@@ -42,7 +120,7 @@ mod tests {
     }
 
     impl Decode for Dummy {
-        fn decode<T: std::io::Read + Sized>(_reader: &mut T) -> std::io::Result<Self> {
+        fn decode<T: std::io::Read + Sized>(_reader: &mut T) -> crate::error::Result<Self> {
             Ok(Self {})
         }
     }
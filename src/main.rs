@@ -1,73 +1,502 @@
 use bacnet::application::*;
-use bacnet::network::*;
+use bacnet::client::{BacnetClient, Notification};
+use bacnet::dissect::dissect_npdu;
+use bacnet::network::NPDU;
 use bacnet::transport::bacnetip::*;
-use bacnet::{Decode, Encode};
+use bacnet::transport::{BoxFuture, DataLink};
+use bacnet::Encode;
 
-use async_std::net::UdpSocket;
 use async_std::task;
+use futures_lite::StreamExt;
+use num_traits::FromPrimitive;
+use std::net::{Ipv4Addr, SocketAddrV4};
+use std::time::Duration;
 
-use tracing::trace;
+const USAGE: &str = "\
+Usage: bacnet [OPTIONS] <COMMAND>
 
-fn main() {
-    tracing_subscriber::fmt::init();
+Commands:
+  whois [<low> <high>]          List the devices answering a Who-Is
+  discover [<low> <high>]       List the devices with their names
+  read <device> <object> <property> [<index>]
+                                Read a property
+  write <device> <object> <property> <value> [<priority>]
+                                Write a property, NULL relinquishes
+  monitor <device> <object> [<lifetime>]
+                                Print the COV notifications of an object
+  scan                          List routers, networks and devices
 
-    task::block_on(async {
-        let socket = UdpSocket::bind(format!("0.0.0.0:{}", 0xBAC0))
-            .await
-            .unwrap();
-        socket.set_broadcast(true).unwrap();
-        let mut buf = vec![0u8; 1024];
-
-        println!("Listening on {}", socket.local_addr().unwrap());
-
-        let addr = format!("192.168.69.255:{}", 0xBAC0);
-        let data_ref = hex::decode("810b000c0120ffff00ff1008").unwrap(); // Who-is
-        let apdu = APDU::new(0x01, 0x08, vec![]);
-        trace!("APDU Len: {}", apdu.len());
-        let dest = NPDUDest::new(0xffff, 0);
-        let npdu = NPDU::new(apdu, Some(dest), None, NPDUPriority::Normal);
-        let bvlc = BVLC::new(BVLCFunction::OriginalBroadcastNPDU(npdu));
-        let data = bvlc.encode_vec().unwrap();
-        println!("Who-Is: {:?}", bvlc);
-        println!("Send: {:02x?}", data.to_vec());
-        println!("Ref : {:02x?}", data_ref);
-        let sent = socket.send_to(&data, &addr).await.unwrap();
-        println!("Sent {} bytes to {}", sent, addr);
-
-        loop {
-            let (n, peer) = socket.recv_from(&mut buf).await.unwrap();
-            // === Data Structure ===
-            trace!("Data: {:02x?}", data);
-
-            let b = BVLC::decode_slice(&data).unwrap();
-            trace!("BVLC: {:02x?}", b);
-            trace!("Function: {:02x?}", b.function);
-            trace!("Length: {:?}", b.len());
-
-            match b.function {
-                BVLCFunction::OriginalBroadcastNPDU(n) | BVLCFunction::OriginalUnicastNPDU(n) => {
-                    trace!("NPDU: {:02x?}", n);
-                    trace!("Version: {}", n.version);
-                    trace!("Priority: {:?}", n.priority);
-                    match n.content {
-                        NPDUContent::APDU(apdu) => {
-                            trace!("APDU: {:02x?}", apdu);
-                            match apdu.service_choice {
-                                08 => {
-                                    trace!("Who-Is received!");
-                                    //let apdu = APDU::new();
-                                    //let sent = socket.send_to().await.unwrap();
-                                }
-                                00 => {
-                                    trace!("I-Am received!");
-                                }
-                                _ => unimplemented!(),
-                            }
-                        }
-                        _ => unimplemented!(),
-                    }
+Options:
+  --broadcast <ip>              Broadcast address of the local network
+  --bbmd <ip[:port]>            Register as foreign device with a BBMD
+  --port <port>                 Local UDP port, 47808 by default
+  --wait <seconds>              Time to wait for answers, 3 by default
+  --dump                        Print every frame sent and received
+  --json                        Print the frames as JSON lines, with --dump,
+                                needs the serde feature
+
+Objects are written as <type>:<instance>, e.g. analog-input:1, properties
+by name, e.g. present-value, both also by number. Values are null, true,
+false or numbers, with a '.' for reals, or typed as real:, double:,
+unsigned:, signed:, enumerated: or string:.";
+
+#[derive(Debug, PartialEq)]
+enum Command {
+    WhoIs(Option<(u32, u32)>),
+    Discover(Option<(u32, u32)>),
+    Read {
+        device: u32,
+        object: ObjectIdentifier,
+        property: PropertyIdentifier,
+        index: Option<u32>,
+    },
+    Write {
+        device: u32,
+        object: ObjectIdentifier,
+        property: PropertyIdentifier,
+        value: BACnetValue,
+        priority: Option<u8>,
+    },
+    Monitor {
+        device: u32,
+        object: ObjectIdentifier,
+        lifetime: Option<u32>,
+    },
+    Scan,
+}
+
+#[derive(Debug, PartialEq)]
+struct Options {
+    broadcast: Ipv4Addr,
+    bbmd: Option<SocketAddrV4>,
+    port: u16,
+    wait: Duration,
+    dump: bool,
+    json: bool,
+    command: Command,
+}
+
+fn number<T: std::str::FromStr>(arg: &str, what: &str) -> Result<T, String> {
+    arg.parse()
+        .map_err(|_| format!("Invalid {}: {}", what, arg))
+}
+
+fn object(arg: &str) -> Result<ObjectIdentifier, String> {
+    let invalid = || format!("Invalid object: {}", arg);
+    let (object_type, instance) = arg.rsplit_once(':').ok_or_else(invalid)?;
+    let object_type = match object_type.parse() {
+        Ok(number) => ObjectType::from_u32(number),
+        Err(_) => (0..=127)
+            .filter_map(ObjectType::from_u32)
+            .find(|t| t.to_string() == object_type),
+    };
+    let object_type = object_type.ok_or_else(invalid)?;
+    let instance = instance.parse().map_err(|_| invalid())?;
+    Ok(ObjectIdentifier::new(object_type, instance))
+}
+
+fn property(arg: &str) -> Result<PropertyIdentifier, String> {
+    arg.parse()
+        .map_err(|_| format!("Invalid property: {}", arg))
+}
+
+fn value(arg: &str) -> Result<BACnetValue, String> {
+    let invalid = || format!("Invalid value: {}", arg);
+    let value = match arg.split_once(':') {
+        Some(("real", v)) => BACnetValue::Real(v.parse().map_err(|_| invalid())?),
+        Some(("double", v)) => BACnetValue::Double(v.parse().map_err(|_| invalid())?),
+        Some(("unsigned", v)) => BACnetValue::Unsigned(v.parse().map_err(|_| invalid())?),
+        Some(("signed", v)) => BACnetValue::Signed(v.parse().map_err(|_| invalid())?),
+        Some(("enumerated", v)) => BACnetValue::Enumerated(v.parse().map_err(|_| invalid())?),
+        Some(("string", v)) => BACnetValue::CharacterString(v.into()),
+        _ => match arg {
+            "null" => BACnetValue::Null,
+            "true" => BACnetValue::Boolean(true),
+            "false" => BACnetValue::Boolean(false),
+            v if v.contains('.') => BACnetValue::Real(v.parse().map_err(|_| invalid())?),
+            v if v.starts_with('-') => BACnetValue::Signed(v.parse().map_err(|_| invalid())?),
+            v => BACnetValue::Unsigned(v.parse().map_err(|_| invalid())?),
+        },
+    };
+    Ok(value)
+}
+
+fn range(args: &[String]) -> Result<Option<(u32, u32)>, String> {
+    match args {
+        [] => Ok(None),
+        [low, high] => Ok(Some((
+            number(low, "low limit")?,
+            number(high, "high limit")?,
+        ))),
+        _ => Err("Expected no range or <low> <high>".into()),
+    }
+}
+
+fn parse(args: &[String]) -> Result<Options, String> {
+    let mut options = Options {
+        broadcast: Ipv4Addr::BROADCAST,
+        bbmd: None,
+        port: DEFAULT_PORT,
+        wait: Duration::from_secs(3),
+        dump: false,
+        json: false,
+        command: Command::Scan,
+    };
+    let mut args = args;
+    loop {
+        let (option, rest) = match args {
+            [option, rest @ ..] if option.starts_with("--") => (option.as_str(), rest),
+            _ => break,
+        };
+        if option == "--json" && !cfg!(feature = "serde") {
+            return Err("--json needs the serde feature".into());
+        }
+        if let "--dump" | "--json" = option {
+            options.dump = true;
+            options.json |= option == "--json";
+            args = rest;
+            continue;
+        }
+        let (arg, rest) = rest
+            .split_first()
+            .ok_or_else(|| format!("Missing value of {}", option))?;
+        match option {
+            "--broadcast" => options.broadcast = number(arg, "broadcast address")?,
+            "--bbmd" => {
+                options.bbmd = Some(match arg.parse() {
+                    Ok(addr) => addr,
+                    Err(_) => SocketAddrV4::new(number(arg, "BBMD address")?, DEFAULT_PORT),
+                })
+            }
+            "--port" => options.port = number(arg, "port")?,
+            "--wait" => {
+                options.wait = Duration::try_from_secs_f64(number(arg, "wait")?)
+                    .map_err(|_| format!("Invalid wait: {}", arg))?
+            }
+            option => return Err(format!("Unknown option: {}", option)),
+        }
+        args = rest;
+    }
+
+    options.command = match args {
+        [command, args @ ..] => match (command.as_str(), args) {
+            ("whois", args) => Command::WhoIs(range(args)?),
+            ("discover", args) => Command::Discover(range(args)?),
+            ("read", [device, o, p, index @ ..]) if index.len() <= 1 => Command::Read {
+                device: number(device, "device")?,
+                object: object(o)?,
+                property: property(p)?,
+                index: index.first().map(|i| number(i, "index")).transpose()?,
+            },
+            ("write", [device, o, p, v, priority @ ..]) if priority.len() <= 1 => Command::Write {
+                device: number(device, "device")?,
+                object: object(o)?,
+                property: property(p)?,
+                value: value(v)?,
+                priority: priority
+                    .first()
+                    .map(|p| number(p, "priority"))
+                    .transpose()?,
+            },
+            ("monitor", [device, o, lifetime @ ..]) if lifetime.len() <= 1 => Command::Monitor {
+                device: number(device, "device")?,
+                object: object(o)?,
+                lifetime: lifetime
+                    .first()
+                    .map(|l| number(l, "lifetime"))
+                    .transpose()?,
+            },
+            ("scan", []) => Command::Scan,
+            (command, _) => return Err(format!("Invalid arguments of {}", command)),
+        },
+        [] => return Err("Missing command".into()),
+    };
+    Ok(options)
+}
+
+/// BACnet/IP link printing the frames it sends and receives if enabled
+struct Dump {
+    link: BacnetIp,
+    enabled: bool,
+    json: bool,
+}
+
+/// A line of the JSON dump, the decoded frame along with its dissection
+#[cfg(feature = "serde")]
+#[derive(serde::Serialize)]
+struct DumpLine<'a> {
+    direction: &'a str,
+    mac: String,
+    npdu: &'a NPDU,
+    dissection: bacnet::dissect::Dissection,
+}
+
+impl Dump {
+    fn print(&self, direction: &str, mac: &[u8], npdu: &NPDU) {
+        let data = match (self.enabled, npdu.encode_vec()) {
+            (true, Ok(data)) => data,
+            _ => return,
+        };
+        let dissection = dissect_npdu(&data);
+        if self.json {
+            #[cfg(feature = "serde")]
+            {
+                let line = DumpLine {
+                    direction,
+                    mac: hex::encode(mac),
+                    npdu,
+                    dissection,
+                };
+                if let Ok(json) = bacnet::json::to_string(&line) {
+                    println!("{}", json);
                 }
             }
+            return;
+        }
+        println!("{} {:02x?}\n{}", direction, mac, dissection);
+    }
+}
+
+impl DataLink for Dump {
+    fn send<'a>(&'a self, mac: &'a [u8], npdu: &'a NPDU) -> BoxFuture<'a, std::io::Result<()>> {
+        self.print("Sent to", mac, npdu);
+        self.link.send(mac, npdu)
+    }
+
+    fn recv(&self) -> BoxFuture<'_, std::io::Result<(Vec<u8>, NPDU)>> {
+        Box::pin(async move {
+            let (mac, npdu) = self.link.recv().await?;
+            self.print("Received from", &mac, &npdu);
+            Ok((mac, npdu))
+        })
+    }
+
+    fn decode_errors(&self) -> u64 {
+        self.link.decode_errors()
+    }
+}
+
+async fn run(options: Options) -> Result<(), Box<dyn std::error::Error>> {
+    let local = SocketAddrV4::new(Ipv4Addr::UNSPECIFIED, options.port);
+    let link = match options.bbmd {
+        Some(bbmd) => BacnetIp::bind_foreign(local, bbmd, Duration::from_secs(300)).await?,
+        None => {
+            let broadcast = SocketAddrV4::new(options.broadcast, DEFAULT_PORT);
+            BacnetIp::bind(local, broadcast).await?
         }
+    };
+    let client = BacnetClient::new(Dump {
+        link,
+        enabled: options.dump,
+        json: options.json,
     });
+    let wait = options.wait;
+
+    // Find the address of the device a command is sent to
+    let locate = |device: u32| {
+        let client = &client;
+        async move {
+            match client
+                .who_is(Some((device, device)), wait)
+                .await?
+                .is_empty()
+            {
+                true => Err(format!("Device {} not found", device).into()),
+                false => Ok::<_, Box<dyn std::error::Error>>(()),
+            }
+        }
+    };
+
+    match options.command {
+        Command::WhoIs(range) => {
+            for (address, i_am) in client.who_is(range, wait).await? {
+                println!(
+                    "Device {} at {:02x?}: vendor {}, max APDU {}, {:?}",
+                    i_am.device_identifier.instance,
+                    address,
+                    i_am.vendor_id,
+                    i_am.max_apdu_length_accepted,
+                    i_am.segmentation_supported
+                );
+            }
+        }
+        Command::Discover(range) => {
+            let devices = client.discover(range, wait);
+            futures_lite::pin!(devices);
+            while let Some(device) = devices.next().await {
+                let object = ObjectIdentifier::new(ObjectType::Device, device.instance);
+                let name = client
+                    .read_as::<String>(device.instance, object, PropertyIdentifier::ObjectName)
+                    .await;
+                println!(
+                    "Device {} at {:02x?}: vendor {}, max APDU {}, name {:?}",
+                    device.instance,
+                    device.address,
+                    device.vendor_id,
+                    device.max_apdu_length_accepted,
+                    name
+                );
+            }
+        }
+        Command::Read {
+            device,
+            object,
+            property,
+            index,
+        } => {
+            locate(device).await?;
+            let value = client
+                .read_property(device, object, property, index)
+                .await?;
+            println!("{:?}", value);
+        }
+        Command::Write {
+            device,
+            object,
+            property,
+            value,
+            priority,
+        } => {
+            locate(device).await?;
+            client
+                .write(device, object, property, value, priority)
+                .await?;
+        }
+        Command::Monitor {
+            device,
+            object,
+            lifetime,
+        } => {
+            locate(device).await?;
+            let mut notifications = client.notifications();
+            client
+                .subscribe_cov(device, std::process::id(), object, false, lifetime)
+                .await?;
+            while let Some((_, notification)) = notifications.next().await {
+                if let Notification::Cov { notification, .. } = notification {
+                    for value in notification.values {
+                        println!("{}: {:?}", value.property_identifier, value.value);
+                    }
+                }
+            }
+        }
+        Command::Scan => {
+            let topology = client.scan_topology(wait).await?;
+            for router in &topology.routers {
+                println!("Router at {:02x?} to {:?}", router.address, router.networks);
+            }
+            for (network, devices) in &topology.networks {
+                match network {
+                    Some(network) => println!("Network {}", network),
+                    None => println!("Local network"),
+                }
+                for (instance, address) in devices {
+                    println!("  Device {} at {:02x?}", instance, address);
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+fn main() {
+    tracing_subscriber::fmt::init();
+
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let options = match parse(&args) {
+        Ok(options) => options,
+        Err(e) => {
+            eprintln!("{}\n\n{}", e, USAGE);
+            std::process::exit(2);
+        }
+    };
+    if let Err(e) = task::block_on(run(options)) {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(args: &str) -> Vec<String> {
+        args.split_whitespace().map(String::from).collect()
+    }
+
+    #[test]
+    fn test_parse() {
+        let options = parse(&args("--wait 1 read 12 analog-input:1 present-value")).unwrap();
+        assert_eq!(options.wait, Duration::from_secs(1));
+        assert_eq!(
+            options.command,
+            Command::Read {
+                device: 12,
+                object: ObjectIdentifier::new(ObjectType::AnalogInput, 1),
+                property: PropertyIdentifier::PresentValue,
+                index: None,
+            }
+        );
+
+        let options = parse(&args("--bbmd 10.0.0.1 write 12 0:1 85 real:21.5 8")).unwrap();
+        assert_eq!(
+            options.bbmd,
+            Some(SocketAddrV4::new([10, 0, 0, 1].into(), DEFAULT_PORT))
+        );
+        assert_eq!(
+            options.command,
+            Command::Write {
+                device: 12,
+                object: ObjectIdentifier::new(ObjectType::AnalogInput, 1),
+                property: PropertyIdentifier::PresentValue,
+                value: BACnetValue::Real(21.5),
+                priority: Some(8),
+            }
+        );
+
+        assert_eq!(
+            parse(&args("whois 1 10")).unwrap().command,
+            Command::WhoIs(Some((1, 10)))
+        );
+        let options = parse(&args("read 12 130:1 5000 2")).unwrap();
+        assert_eq!(
+            options.command,
+            Command::Read {
+                device: 12,
+                object: ObjectIdentifier::new(ObjectType::Proprietary(130), 1),
+                property: PropertyIdentifier::Proprietary(5000),
+                index: Some(2),
+            }
+        );
+
+        assert!(parse(&args("whois 1")).is_err());
+        assert!(parse(&args("read 12 analog-input present-value")).is_err());
+        assert!(parse(&args("--dump scan")).unwrap().dump);
+        #[cfg(feature = "serde")]
+        {
+            let options = parse(&args("--json scan")).unwrap();
+            assert!(options.dump && options.json);
+        }
+        #[cfg(not(feature = "serde"))]
+        assert!(parse(&args("--json scan")).is_err());
+        assert!(parse(&args("--retries 3 scan")).is_err());
+        assert!(parse(&args("--wait -1 scan")).is_err());
+        assert!(parse(&args("--wait inf scan")).is_err());
+        assert!(parse(&[]).is_err());
+    }
+
+    #[test]
+    fn test_value() {
+        assert_eq!(value("null"), Ok(BACnetValue::Null));
+        assert_eq!(value("true"), Ok(BACnetValue::Boolean(true)));
+        assert_eq!(value("1.5"), Ok(BACnetValue::Real(1.5)));
+        assert_eq!(value("-3"), Ok(BACnetValue::Signed(-3)));
+        assert_eq!(value("3"), Ok(BACnetValue::Unsigned(3)));
+        assert_eq!(value("enumerated:1"), Ok(BACnetValue::Enumerated(1)));
+        assert_eq!(
+            value("string:Lobby"),
+            Ok(BACnetValue::CharacterString("Lobby".into()))
+        );
+        assert!(value("real:warm").is_err());
+    }
 }
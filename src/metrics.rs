@@ -0,0 +1,474 @@
+//! Prometheus exporter of the [`Statistics`] of clients and devices and of
+//! present values of selected points
+//!
+//! The [`Exporter`] serves the text exposition format on `GET /metrics`
+//! over plain HTTP, for scraping by Prometheus or compatible agents. Each
+//! scrape collects the metrics of its sources afresh.
+//!
+//! ```no_run
+//! # use bacnet::client::BacnetClient;
+//! # use bacnet::metrics::{Collect, Exporter};
+//! # use bacnet::transport::bacnetip::{BacnetIp, DEFAULT_PORT};
+//! # use std::net::{Ipv4Addr, SocketAddrV4};
+//! # use std::sync::Arc;
+//! # async_std::task::block_on(async {
+//! let link = BacnetIp::bind(
+//!     SocketAddrV4::new(Ipv4Addr::UNSPECIFIED, DEFAULT_PORT),
+//!     SocketAddrV4::new(Ipv4Addr::BROADCAST, DEFAULT_PORT),
+//! )
+//! .await?;
+//! let client: Arc<dyn Collect> = Arc::new(BacnetClient::new(link));
+//! let exporter = Exporter::bind("0.0.0.0:9185", vec![client]).await?;
+//! # Ok::<(), std::io::Error>(())
+//! # });
+//! ```
+
+use crate::application::*;
+use crate::client::epics::epics_name;
+use crate::client::{BacnetClient, Statistics};
+use crate::server::BacnetDevice;
+use crate::transport::DataLink;
+
+use async_std::io::{ReadExt, WriteExt};
+use async_std::net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
+use async_std::task::{self, JoinHandle};
+use std::fmt::{self, Display, Write};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use tracing::{trace, warn};
+
+/// Longest request head accepted, larger requests are refused
+const MAX_REQUEST: usize = 8192;
+
+/// Time a client has to send its request
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Content type of the text exposition format
+const CONTENT_TYPE: &str = "text/plain; version=0.0.4";
+
+/// Samples of one metric, with the same name and type
+struct Family {
+    name: String,
+    help: String,
+    kind: &'static str,
+    /// Rendered labels and value of each sample
+    samples: Vec<(String, f64)>,
+}
+
+/// Metrics being collected for a scrape, rendered in the text exposition
+/// format by [`Display`]
+#[derive(Default)]
+pub struct Metrics {
+    families: Vec<Family>,
+}
+
+impl Metrics {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a sample of a counter, a value that only increases
+    pub fn counter(&mut self, name: &str, help: &str, labels: &[(&str, &str)], value: f64) {
+        self.add("counter", name, help, labels, value);
+    }
+
+    /// Add a sample of a gauge, a value that goes up and down
+    pub fn gauge(&mut self, name: &str, help: &str, labels: &[(&str, &str)], value: f64) {
+        self.add("gauge", name, help, labels, value);
+    }
+
+    /// Add a sample to the family of the name, the help and type of the
+    /// first sample of a name are kept
+    fn add(
+        &mut self,
+        kind: &'static str,
+        name: &str,
+        help: &str,
+        labels: &[(&str, &str)],
+        value: f64,
+    ) {
+        let index = match self.families.iter().position(|f| f.name == name) {
+            Some(index) => index,
+            None => {
+                self.families.push(Family {
+                    name: name.into(),
+                    help: help.into(),
+                    kind,
+                    samples: Vec::new(),
+                });
+                self.families.len() - 1
+            }
+        };
+        let mut rendered = String::new();
+        for (i, (label, value)) in labels.iter().enumerate() {
+            let separator = if i == 0 { '{' } else { ',' };
+            let _ = write!(rendered, "{}{}=\"{}\"", separator, label, escape(value));
+        }
+        if !labels.is_empty() {
+            rendered.push('}');
+        }
+        self.families[index].samples.push((rendered, value));
+    }
+
+    /// Add the counters of a station, with the labels on every sample
+    pub fn statistics(&mut self, statistics: &Statistics, labels: &[(&str, &str)]) {
+        self.counter(
+            "bacnet_frames_received_total",
+            "NPDUs received",
+            labels,
+            statistics.frames_received as f64,
+        );
+        self.counter(
+            "bacnet_frames_sent_total",
+            "NPDUs sent",
+            labels,
+            statistics.frames_sent as f64,
+        );
+        self.counter(
+            "bacnet_decode_errors_total",
+            "Frames and requests that could not be decoded",
+            labels,
+            statistics.decode_errors as f64,
+        );
+        self.gauge(
+            "bacnet_outstanding_transactions",
+            "Confirmed requests waiting for their response",
+            labels,
+            statistics.outstanding_transactions as f64,
+        );
+        self.counter(
+            "bacnet_transaction_timeouts_total",
+            "Confirmed requests that were not answered in time",
+            labels,
+            statistics.transaction_timeouts as f64,
+        );
+    }
+}
+
+impl Display for Metrics {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for family in &self.families {
+            writeln!(
+                f,
+                "# HELP {} {}",
+                family.name,
+                family.help.replace('\n', " ")
+            )?;
+            writeln!(f, "# TYPE {} {}", family.name, family.kind)?;
+            for (labels, value) in &family.samples {
+                write!(f, "{}{} ", family.name, labels)?;
+                match value {
+                    v if v.is_nan() => writeln!(f, "NaN")?,
+                    v if v.is_infinite() && *v > 0.0 => writeln!(f, "+Inf")?,
+                    v if v.is_infinite() => writeln!(f, "-Inf")?,
+                    v => writeln!(f, "{}", v)?,
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Escape a label value
+fn escape(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+/// A source of metrics, collected on every scrape
+pub trait Collect: Send + Sync {
+    fn collect(&self, metrics: &mut Metrics);
+}
+
+impl<D: DataLink + 'static> Collect for BacnetClient<D> {
+    fn collect(&self, metrics: &mut Metrics) {
+        metrics.statistics(&self.statistics(), &[]);
+    }
+}
+
+impl<D: DataLink + 'static> Collect for BacnetDevice<D> {
+    fn collect(&self, metrics: &mut Metrics) {
+        let instance = self.info().instance.to_string();
+        let labels = [("device", instance.as_str())];
+        metrics.statistics(&self.statistics(), &labels);
+        metrics.gauge(
+            "bacnet_cov_subscriptions",
+            "Active COV subscriptions",
+            &labels,
+            self.cov_subscriptions().len() as f64,
+        );
+    }
+}
+
+/// The value of a present value as a sample, if it is numeric
+fn sample(value: &BACnetValue) -> Option<f64> {
+    match value {
+        BACnetValue::Boolean(b) => Some(*b as u8 as f64),
+        BACnetValue::Unsigned(v) | BACnetValue::Enumerated(v) => Some(*v as f64),
+        BACnetValue::Signed(v) => Some(*v as f64),
+        BACnetValue::Real(v) => Some(*v as f64),
+        BACnetValue::Double(v) => Some(*v),
+        _ => None,
+    }
+}
+
+/// Present values of points polled by a client, collected as the gauge
+/// `bacnet_present_value` labelled with device, object type and instance
+///
+/// Points whose last read failed or whose value is not numeric have no
+/// sample. Polling stops when dropped.
+pub struct PresentValues {
+    points: Arc<Vec<(u32, ObjectIdentifier)>>,
+    values: Arc<Mutex<Vec<Option<f64>>>>,
+    task: Option<JoinHandle<()>>,
+}
+
+impl PresentValues {
+    /// Read the present values of the objects of devices every `interval`,
+    /// starting right away
+    pub fn start<D: DataLink + 'static>(
+        client: Arc<BacnetClient<D>>,
+        points: Vec<(u32, ObjectIdentifier)>,
+        interval: Duration,
+    ) -> Self {
+        let points = Arc::new(points);
+        let values = Arc::new(Mutex::new(vec![None; points.len()]));
+        let task = {
+            let (points, values) = (points.clone(), values.clone());
+            task::spawn(async move {
+                loop {
+                    for (i, (device, object)) in points.iter().enumerate() {
+                        let property = PropertyIdentifier::PresentValue;
+                        let value = match client.read(*device, *object, property).await {
+                            Ok(value) => sample(&value),
+                            Err(e) => {
+                                trace!("Reading {:?} of {} failed: {}", object, device, e);
+                                None
+                            }
+                        };
+                        values.lock().unwrap()[i] = value;
+                    }
+                    task::sleep(interval).await;
+                }
+            })
+        };
+        Self {
+            points,
+            values,
+            task: Some(task),
+        }
+    }
+}
+
+impl Collect for PresentValues {
+    fn collect(&self, metrics: &mut Metrics) {
+        let values = self.values.lock().unwrap();
+        for ((device, object), value) in self.points.iter().zip(values.iter()) {
+            if let Some(value) = value {
+                let (device, instance) = (device.to_string(), object.instance.to_string());
+                let object_type = epics_name(object.object_type);
+                let labels = [
+                    ("device", device.as_str()),
+                    ("object_type", object_type.as_str()),
+                    ("instance", instance.as_str()),
+                ];
+                metrics.gauge(
+                    "bacnet_present_value",
+                    "Present value of a point",
+                    &labels,
+                    *value,
+                );
+            }
+        }
+    }
+}
+
+impl Drop for PresentValues {
+    fn drop(&mut self) {
+        if let Some(task) = self.task.take() {
+            task::spawn(task.cancel());
+        }
+    }
+}
+
+/// Render the metrics of the sources in the text exposition format
+pub fn render(sources: &[Arc<dyn Collect>]) -> String {
+    let mut metrics = Metrics::new();
+    for source in sources {
+        source.collect(&mut metrics);
+    }
+    metrics.to_string()
+}
+
+/// HTTP server of the metrics of its sources, stopped when dropped
+pub struct Exporter {
+    local_addr: SocketAddr,
+    task: Option<JoinHandle<()>>,
+}
+
+impl Exporter {
+    /// Serve the metrics on an address, e.g. `0.0.0.0:9185`
+    pub async fn bind<A: ToSocketAddrs>(
+        addr: A,
+        sources: Vec<Arc<dyn Collect>>,
+    ) -> std::io::Result<Self> {
+        let listener = TcpListener::bind(addr).await?;
+        let local_addr = listener.local_addr()?;
+        let sources = Arc::new(sources);
+        let task = task::spawn(async move {
+            loop {
+                match listener.accept().await {
+                    Ok((stream, peer)) => {
+                        let sources = sources.clone();
+                        task::spawn(async move {
+                            if let Err(e) = serve(stream, &sources).await {
+                                trace!("Serving {} failed: {}", peer, e);
+                            }
+                        });
+                    }
+                    Err(e) => {
+                        warn!("Accepting connections failed: {}", e);
+                        break;
+                    }
+                }
+            }
+        });
+        Ok(Self {
+            local_addr,
+            task: Some(task),
+        })
+    }
+
+    pub fn local_addr(&self) -> SocketAddr {
+        self.local_addr
+    }
+}
+
+impl Drop for Exporter {
+    fn drop(&mut self) {
+        if let Some(task) = self.task.take() {
+            task::spawn(task.cancel());
+        }
+    }
+}
+
+/// Answer a request on a connection, which is closed afterwards
+async fn serve(mut stream: TcpStream, sources: &[Arc<dyn Collect>]) -> std::io::Result<()> {
+    let head = async_std::future::timeout(REQUEST_TIMEOUT, read_head(&mut stream))
+        .await
+        .map_err(|_| std::io::Error::new(std::io::ErrorKind::TimedOut, "No request"))??;
+    let mut request_line = head.split_whitespace();
+    let (status, body) = match (request_line.next(), request_line.next()) {
+        _ if head.is_empty() => ("400 Bad Request", String::new()),
+        (Some("GET"), Some(path)) if path.split('?').next() == Some("/metrics") => {
+            ("200 OK", render(sources))
+        }
+        (Some("GET"), _) => ("404 Not Found", String::new()),
+        _ => ("405 Method Not Allowed", String::new()),
+    };
+    let response = format!(
+        "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        CONTENT_TYPE,
+        body.len(),
+        body
+    );
+    stream.write_all(response.as_bytes()).await?;
+    stream.flush().await
+}
+
+/// Read the request line and headers, empty if they exceed
+/// [`MAX_REQUEST`]
+async fn read_head(stream: &mut TcpStream) -> std::io::Result<String> {
+    let mut head = Vec::new();
+    let mut buf = [0u8; 1024];
+    while !head.windows(4).any(|w| w == b"\r\n\r\n") {
+        let n = stream.read(&mut buf).await?;
+        if n == 0 {
+            return Err(std::io::ErrorKind::UnexpectedEof.into());
+        }
+        head.extend_from_slice(&buf[..n]);
+        if head.len() > MAX_REQUEST {
+            return Ok(String::new());
+        }
+    }
+    Ok(String::from_utf8_lossy(&head).into_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::tests::*;
+    use crate::network::Address;
+
+    #[test]
+    fn test_metrics() {
+        let mut metrics = Metrics::new();
+        metrics.gauge("temperature", "Air temperature", &[("room", "a\"1")], 21.5);
+        metrics.gauge("temperature", "Ignored", &[("room", "b")], f64::NAN);
+        metrics.counter("requests_total", "Requests", &[], 3.0);
+        assert_eq!(
+            metrics.to_string(),
+            "# HELP temperature Air temperature\n\
+             # TYPE temperature gauge\n\
+             temperature{room=\"a\\\"1\"} 21.5\n\
+             temperature{room=\"b\"} NaN\n\
+             # HELP requests_total Requests\n\
+             # TYPE requests_total counter\n\
+             requests_total 3\n"
+        );
+    }
+
+    async fn get(addr: SocketAddr, path: &str) -> String {
+        let mut stream = TcpStream::connect(addr).await.unwrap();
+        let request = format!("GET {} HTTP/1.1\r\nHost: localhost\r\n\r\n", path);
+        stream.write_all(request.as_bytes()).await.unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).await.unwrap();
+        response
+    }
+
+    #[test]
+    fn test_exporter() {
+        task::block_on(async {
+            let (link, device) = link_pair();
+            let client = Arc::new(BacnetClient::new(link));
+            client.add_device(12, Address::local(vec![2]));
+            let respond = task::spawn(async move {
+                let request = apdu(device.recv().await.unwrap().1);
+                let mut ack = request.user_data().to_vec();
+                ack.extend_from_slice(&hex::decode("3e4441a000003f").unwrap());
+                reply(
+                    &device,
+                    APDU::complex_ack(request.invoke_id().unwrap(), 12, ack),
+                )
+                .await;
+                device
+            });
+            let object = ObjectIdentifier::new(ObjectType::AnalogInput, 1);
+            let values =
+                PresentValues::start(client.clone(), vec![(12, object)], Duration::from_secs(60));
+            let _device = respond.await;
+            while values.values.lock().unwrap()[0].is_none() {
+                task::sleep(Duration::from_millis(1)).await;
+            }
+
+            let sources: Vec<Arc<dyn Collect>> = vec![client, Arc::new(values)];
+            let exporter = Exporter::bind("127.0.0.1:0", sources).await.unwrap();
+            let response = get(exporter.local_addr(), "/metrics").await;
+            assert!(response.starts_with("HTTP/1.1 200 OK\r\n"));
+            assert!(response.contains("\r\nContent-Type: text/plain; version=0.0.4\r\n"));
+            assert!(response.contains("\nbacnet_frames_sent_total 1\n"));
+            assert!(response.contains("\nbacnet_outstanding_transactions 0\n"));
+            assert!(response.contains(
+                "\nbacnet_present_value{device=\"12\",object_type=\"analog-input\",instance=\"1\"} 20\n"
+            ));
+
+            let response = get(exporter.local_addr(), "/").await;
+            assert!(response.starts_with("HTTP/1.1 404 Not Found\r\n"));
+        });
+    }
+}
@@ -0,0 +1,437 @@
+//! Minimal MQTT 3.1.1 client and a [`CovBridge`] publishing COV
+//! notifications to MQTT
+//!
+//! The client publishes with QoS 0 and accepts messages of subscriptions
+//! with QoS 0 and 1, which is all the bridge needs. TLS is not supported,
+//! use a local broker or a tunnel for remote ones.
+
+pub mod bridge;
+pub use bridge::*;
+
+use async_std::channel::{self, Receiver, Sender};
+use async_std::io::{ReadExt, WriteExt};
+use async_std::net::{TcpStream, ToSocketAddrs};
+use async_std::sync::Mutex;
+use async_std::task::{self, JoinHandle};
+use std::io::{Error, ErrorKind, Result};
+use std::sync::atomic::{AtomicU16, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use tracing::{trace, warn};
+
+/// Time to wait for the broker to accept the connection
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Messages received but not yet taken with [`MqttClient::recv`], further
+/// ones wait
+const INCOMING_QUEUE: usize = 64;
+
+/// Control packet types (MQTT 3.1.1, 2.2.1)
+const CONNECT: u8 = 1;
+const CONNACK: u8 = 2;
+const PUBLISH: u8 = 3;
+const PUBACK: u8 = 4;
+const SUBSCRIBE: u8 = 8;
+const PINGREQ: u8 = 12;
+const DISCONNECT: u8 = 14;
+
+/// Options of the connection to a broker
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct MqttOptions {
+    pub client_id: String,
+    /// Interval of keep alive pings, none are sent if zero
+    pub keep_alive: Duration,
+    pub username: Option<String>,
+    pub password: Option<String>,
+}
+
+impl MqttOptions {
+    /// Options with a keep alive of 60 s and no credentials
+    pub fn new<S: Into<String>>(client_id: S) -> Self {
+        Self {
+            client_id: client_id.into(),
+            keep_alive: Duration::from_secs(60),
+            username: None,
+            password: None,
+        }
+    }
+}
+
+/// A message published to a topic
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Publish {
+    pub topic: String,
+    pub payload: Vec<u8>,
+    pub retain: bool,
+}
+
+/// A connection to an MQTT broker
+pub struct MqttClient {
+    inner: Arc<Inner>,
+    incoming: Receiver<Publish>,
+    tasks: Vec<JoinHandle<()>>,
+}
+
+struct Inner {
+    /// Write half of the connection, packets are written whole
+    stream: Mutex<TcpStream>,
+    packet_id: AtomicU16,
+}
+
+impl Inner {
+    async fn send(&self, packet_type: u8, flags: u8, body: &[u8]) -> Result<()> {
+        let packet = encode_packet(packet_type, flags, body);
+        self.stream.lock().await.write_all(&packet).await
+    }
+
+    /// The identifier of the next packet that is acknowledged, never 0
+    fn next_packet_id(&self) -> u16 {
+        loop {
+            let id = self.packet_id.fetch_add(1, Ordering::Relaxed);
+            if id != 0 {
+                return id;
+            }
+        }
+    }
+}
+
+impl MqttClient {
+    /// Connect to a broker, with a clean session
+    ///
+    /// Fails with [`ErrorKind::ConnectionRefused`] if the broker does not
+    /// accept the connection, e.g. because of wrong credentials.
+    pub async fn connect<A: ToSocketAddrs>(addr: A, options: &MqttOptions) -> Result<Self> {
+        let mut stream = TcpStream::connect(addr).await?;
+        stream.set_nodelay(true)?;
+
+        let mut body = Vec::new();
+        encode_string(&mut body, "MQTT");
+        body.push(4); // Protocol level of 3.1.1
+        let mut flags = 0b10; // Clean session
+        if options.username.is_some() {
+            flags |= 0x80;
+        }
+        if options.password.is_some() {
+            flags |= 0x40;
+        }
+        body.push(flags);
+        let keep_alive = options.keep_alive.as_secs().min(u16::MAX as u64) as u16;
+        body.extend_from_slice(&keep_alive.to_be_bytes());
+        encode_string(&mut body, &options.client_id);
+        for credential in [&options.username, &options.password]
+            .iter()
+            .copied()
+            .flatten()
+        {
+            encode_string(&mut body, credential);
+        }
+        stream.write_all(&encode_packet(CONNECT, 0, &body)).await?;
+
+        let connack = async_std::future::timeout(CONNECT_TIMEOUT, read_packet(&mut stream));
+        let (header, body) = connack
+            .await
+            .map_err(|_| Error::new(ErrorKind::TimedOut, "No CONNACK from the broker"))??;
+        match (header >> 4, body.as_slice()) {
+            (CONNACK, [_, 0]) => {}
+            (CONNACK, [_, code]) => {
+                let msg = format!("Connection refused by the broker: code {}", code);
+                return Err(Error::new(ErrorKind::ConnectionRefused, msg));
+            }
+            _ => return Err(Error::new(ErrorKind::InvalidData, "Expected a CONNACK")),
+        }
+
+        let inner = Arc::new(Inner {
+            stream: Mutex::new(stream.clone()),
+            packet_id: AtomicU16::new(1),
+        });
+        let (sender, incoming) = channel::bounded(INCOMING_QUEUE);
+        let mut tasks = vec![task::spawn(receive(inner.clone(), stream, sender))];
+        if keep_alive > 0 {
+            tasks.push(task::spawn(ping(inner.clone(), options.keep_alive)));
+        }
+        Ok(Self {
+            inner,
+            incoming,
+            tasks,
+        })
+    }
+
+    /// Publish a message with QoS 0
+    pub async fn publish(&self, topic: &str, payload: &[u8], retain: bool) -> Result<()> {
+        let mut body = Vec::with_capacity(topic.len() + payload.len() + 2);
+        encode_string(&mut body, topic);
+        body.extend_from_slice(payload);
+        self.inner.send(PUBLISH, retain as u8, &body).await
+    }
+
+    /// Subscribe to the topics matching a filter, e.g. `bacnet/+/set`, with
+    /// QoS 1
+    pub async fn subscribe(&self, filter: &str) -> Result<()> {
+        let mut body = Vec::with_capacity(filter.len() + 5);
+        body.extend_from_slice(&self.inner.next_packet_id().to_be_bytes());
+        encode_string(&mut body, filter);
+        body.push(1);
+        self.inner.send(SUBSCRIBE, 0b0010, &body).await
+    }
+
+    /// Wait for the next message of a subscription
+    ///
+    /// Fails with [`ErrorKind::BrokenPipe`] once the connection is closed.
+    pub async fn recv(&self) -> Result<Publish> {
+        self.incoming
+            .recv()
+            .await
+            .map_err(|_| Error::new(ErrorKind::BrokenPipe, "Connection to the broker closed"))
+    }
+
+    /// Close the connection gracefully
+    pub async fn disconnect(&self) -> Result<()> {
+        self.inner.send(DISCONNECT, 0, &[]).await
+    }
+}
+
+impl Drop for MqttClient {
+    fn drop(&mut self) {
+        for task in self.tasks.drain(..) {
+            task::spawn(task.cancel());
+        }
+    }
+}
+
+/// Read packets from the broker, passing on the messages
+async fn receive(inner: Arc<Inner>, mut stream: TcpStream, sender: Sender<Publish>) {
+    loop {
+        let (header, body) = match read_packet(&mut stream).await {
+            Ok(packet) => packet,
+            Err(e) => {
+                warn!("MQTT connection lost: {}", e);
+                return;
+            }
+        };
+        trace!("MQTT packet type {}", header >> 4);
+        if header >> 4 != PUBLISH {
+            // CONNACK, SUBACK and PINGRESP need no action
+            continue;
+        }
+        match decode_publish(header, &body) {
+            Some((publish, Some(packet_id))) => {
+                if inner
+                    .send(PUBACK, 0, &packet_id.to_be_bytes())
+                    .await
+                    .is_err()
+                {
+                    return;
+                }
+                let _ = sender.send(publish).await;
+            }
+            Some((publish, None)) => {
+                let _ = sender.send(publish).await;
+            }
+            None => warn!("Dropping malformed MQTT PUBLISH"),
+        }
+    }
+}
+
+async fn ping(inner: Arc<Inner>, interval: Duration) {
+    loop {
+        task::sleep(interval).await;
+        if inner.send(PINGREQ, 0, &[]).await.is_err() {
+            return;
+        }
+    }
+}
+
+fn encode_string(buf: &mut Vec<u8>, s: &str) {
+    buf.extend_from_slice(&(s.len() as u16).to_be_bytes());
+    buf.extend_from_slice(s.as_bytes());
+}
+
+/// Fixed header, with the remaining length (2.2.3), followed by the body
+fn encode_packet(packet_type: u8, flags: u8, body: &[u8]) -> Vec<u8> {
+    let mut packet = Vec::with_capacity(body.len() + 5);
+    packet.push(packet_type << 4 | flags);
+    let mut length = body.len();
+    loop {
+        let byte = (length % 128) as u8;
+        length /= 128;
+        match length {
+            0 => {
+                packet.push(byte);
+                break;
+            }
+            _ => packet.push(byte | 0x80),
+        }
+    }
+    packet.extend_from_slice(body);
+    packet
+}
+
+/// Read a packet, returning the first octet of its fixed header and its body
+async fn read_packet(stream: &mut TcpStream) -> Result<(u8, Vec<u8>)> {
+    let mut byte = [0];
+    stream.read_exact(&mut byte).await?;
+    let header = byte[0];
+    let mut length = 0usize;
+    for i in 0..4 {
+        stream.read_exact(&mut byte).await?;
+        length |= ((byte[0] & 0x7F) as usize) << (7 * i);
+        if byte[0] & 0x80 == 0 {
+            let mut body = vec![0; length];
+            stream.read_exact(&mut body).await?;
+            return Ok((header, body));
+        }
+    }
+    Err(Error::new(
+        ErrorKind::InvalidData,
+        "Invalid remaining length",
+    ))
+}
+
+/// A PUBLISH packet, with the packet identifier to acknowledge for QoS 1
+fn decode_publish(header: u8, body: &[u8]) -> Option<(Publish, Option<u16>)> {
+    let length = u16::from_be_bytes([*body.first()?, *body.get(1)?]) as usize;
+    let topic = String::from_utf8(body.get(2..2 + length)?.to_vec()).ok()?;
+    let mut rest = &body[2 + length..];
+    let packet_id = match header >> 1 & 0b11 {
+        0 => None,
+        _ => {
+            let id = u16::from_be_bytes([*rest.first()?, *rest.get(1)?]);
+            rest = &rest[2..];
+            Some(id)
+        }
+    };
+    let publish = Publish {
+        topic,
+        payload: rest.to_vec(),
+        retain: header & 1 != 0,
+    };
+    Some((publish, packet_id))
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use async_std::net::TcpListener;
+
+    /// A broker accepting one client, for tests
+    pub(crate) struct Broker {
+        pub(crate) addr: std::net::SocketAddr,
+        listener: TcpListener,
+    }
+
+    impl Broker {
+        pub(crate) async fn bind() -> Self {
+            let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+            Self {
+                addr: listener.local_addr().unwrap(),
+                listener,
+            }
+        }
+
+        /// Accept the client, returning the connection and its CONNECT body
+        pub(crate) async fn accept(&self, return_code: u8) -> (TcpStream, Vec<u8>) {
+            let (mut stream, _) = self.listener.accept().await.unwrap();
+            let (header, body) = read_packet(&mut stream).await.unwrap();
+            assert_eq!(header >> 4, CONNECT);
+            let connack = encode_packet(CONNACK, 0, &[0, return_code]);
+            stream.write_all(&connack).await.unwrap();
+            (stream, body)
+        }
+    }
+
+    /// The next PUBLISH sent by the client, skipping other packets
+    pub(crate) async fn next_publish(stream: &mut TcpStream) -> Publish {
+        loop {
+            let (header, body) = read_packet(stream).await.unwrap();
+            if header >> 4 == PUBLISH {
+                return decode_publish(header, &body).unwrap().0;
+            }
+        }
+    }
+
+    /// The topic filter of the next SUBSCRIBE sent by the client
+    pub(crate) async fn next_subscribe(stream: &mut TcpStream) -> String {
+        let (header, body) = read_packet(stream).await.unwrap();
+        assert_eq!(header, SUBSCRIBE << 4 | 0b0010);
+        String::from_utf8(body[4..body.len() - 1].to_vec()).unwrap()
+    }
+
+    pub(crate) async fn send_publish(stream: &mut TcpStream, topic: &str, payload: &[u8]) {
+        let mut body = Vec::new();
+        encode_string(&mut body, topic);
+        body.extend_from_slice(&[0, 9]);
+        body.extend_from_slice(payload);
+        let packet = encode_packet(PUBLISH, 0b0010, &body);
+        stream.write_all(&packet).await.unwrap();
+    }
+
+    #[test]
+    fn test_encode_packet() {
+        assert_eq!(encode_packet(PINGREQ, 0, &[]), [0xC0, 0x00]);
+        let packet = encode_packet(PUBLISH, 1, &[0; 200]);
+        assert_eq!(packet[..3], [0x31, 0xC8, 0x01]);
+        assert_eq!(packet.len(), 203);
+
+        let (publish, id) = decode_publish(0x32, &[0, 1, b'a', 0, 7, b'x']).unwrap();
+        assert_eq!(publish.topic, "a");
+        assert_eq!(publish.payload, b"x");
+        assert_eq!(id, Some(7));
+        assert!(decode_publish(0x30, &[0, 5, b'a']).is_none());
+    }
+
+    #[test]
+    fn test_client() {
+        task::block_on(async {
+            let broker = Broker::bind().await;
+            let mut options = MqttOptions::new("bridge");
+            options.username = Some("user".into());
+            let (client, (mut stream, connect)) =
+                futures_lite::future::zip(MqttClient::connect(broker.addr, &options), async {
+                    broker.accept(0).await
+                })
+                .await;
+            let client = client.unwrap();
+            assert_eq!(connect[6..10], [4, 0x82, 0, 60]);
+            assert_eq!(connect[10..], *b"\x00\x06bridge\x00\x04user");
+
+            client.subscribe("bacnet/#").await.unwrap();
+            assert_eq!(next_subscribe(&mut stream).await, "bacnet/#");
+
+            client.publish("bacnet/1", b"21.5", true).await.unwrap();
+            let publish = next_publish(&mut stream).await;
+            assert_eq!(publish.topic, "bacnet/1");
+            assert_eq!(publish.payload, b"21.5");
+            assert!(publish.retain);
+
+            // Messages with QoS 1 are acknowledged
+            send_publish(&mut stream, "bacnet/1/set", b"1").await;
+            let publish = client.recv().await.unwrap();
+            assert_eq!(publish.topic, "bacnet/1/set");
+            assert_eq!(publish.payload, b"1");
+            let (header, body) = read_packet(&mut stream).await.unwrap();
+            assert_eq!((header, body), (PUBACK << 4, vec![0, 9]));
+
+            drop(stream);
+            assert_eq!(
+                client.recv().await.unwrap_err().kind(),
+                ErrorKind::BrokenPipe
+            );
+        });
+    }
+
+    #[test]
+    fn test_connection_refused() {
+        task::block_on(async {
+            let broker = Broker::bind().await;
+            let options = MqttOptions::new("bridge");
+            let (client, _) =
+                futures_lite::future::zip(MqttClient::connect(broker.addr, &options), async {
+                    broker.accept(5).await
+                })
+                .await;
+            let err = client.err().unwrap();
+            assert_eq!(err.kind(), ErrorKind::ConnectionRefused);
+        });
+    }
+}
@@ -0,0 +1,372 @@
+use crate::application::*;
+use crate::client::{BacnetClient, ClientError, Notification};
+use crate::json;
+use crate::mqtt::MqttClient;
+use crate::network::Address;
+use crate::transport::DataLink;
+
+use async_std::task::{self, JoinHandle};
+use futures_lite::{Stream, StreamExt};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use tracing::warn;
+
+/// Suffix of the topic of a present value that writes it
+const SET: &str = "set";
+
+/// An object whose COV notifications are published
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Point {
+    pub device: u32,
+    pub object: ObjectIdentifier,
+    /// Topic below the prefix, each property has a subtopic
+    pub topic: String,
+    /// Priority of writes of the present value from MQTT, `None` for
+    /// points that are read only
+    pub write_priority: Option<u8>,
+}
+
+impl Point {
+    /// A read only point with the topic `<device>/<object-type>/<instance>`,
+    /// e.g. `12/analog-input/1`
+    pub fn new(device: u32, object: ObjectIdentifier) -> Self {
+        Self {
+            device,
+            object,
+            topic: format!("{}/{}/{}", device, object.object_type, object.instance),
+            write_priority: None,
+        }
+    }
+
+    /// Write messages to `<topic>/present-value/set` to the present value
+    /// with the priority
+    pub fn writable(self, priority: u8) -> Self {
+        Self {
+            write_priority: Some(priority),
+            ..self
+        }
+    }
+}
+
+/// Payload of a value: numbers, `true`, `false` and `null` as in JSON,
+/// character strings as they are and other values as JSON
+pub fn payload(value: &BACnetValue) -> String {
+    match value {
+        BACnetValue::Null => "null".into(),
+        BACnetValue::Boolean(b) => b.to_string(),
+        BACnetValue::Unsigned(v) | BACnetValue::Enumerated(v) => v.to_string(),
+        BACnetValue::Signed(v) => v.to_string(),
+        BACnetValue::Real(v) => v.to_string(),
+        BACnetValue::Double(v) => v.to_string(),
+        BACnetValue::CharacterString(s) => s.to_string(),
+        value => json::to_string(value).unwrap_or_default(),
+    }
+}
+
+/// Parse a payload into a value of the datatype of the last one, or a
+/// REAL if none is known yet
+fn parse(payload: &str, last: Option<&BACnetValue>) -> Option<BACnetValue> {
+    let payload = payload.trim();
+    let value = match (payload, last) {
+        ("null", _) => BACnetValue::Null,
+        (p, Some(BACnetValue::Boolean(_))) => BACnetValue::Boolean(match p {
+            "true" | "1" => true,
+            "false" | "0" => false,
+            _ => return None,
+        }),
+        (p, Some(BACnetValue::Unsigned(_))) => BACnetValue::Unsigned(p.parse().ok()?),
+        (p, Some(BACnetValue::Enumerated(_))) => BACnetValue::Enumerated(p.parse().ok()?),
+        (p, Some(BACnetValue::Signed(_))) => BACnetValue::Signed(p.parse().ok()?),
+        (p, Some(BACnetValue::Double(_))) => BACnetValue::Double(p.parse().ok()?),
+        (p, Some(BACnetValue::CharacterString(s))) => BACnetValue::CharacterString(
+            CharacterString::with_character_set(p, s.character_set).or_utf8(),
+        ),
+        (p, _) => BACnetValue::Real(p.parse().ok()?),
+    };
+    Some(value)
+}
+
+/// Publishes the COV notifications of points to MQTT, and writes messages
+/// of writable points to their present value
+///
+/// Every property in a notification is published retained to
+/// `<prefix>/<topic>/<property>`, e.g.
+/// `bacnet/12/analog-input/1/present-value`, with the [`payload`] of its
+/// value. The subscriptions are renewed at half their lifetime while the
+/// bridge is running, it stops when dropped.
+pub struct CovBridge {
+    tasks: Vec<JoinHandle<()>>,
+}
+
+/// Present values of the points last notified
+type LastValues = Arc<Mutex<HashMap<usize, BACnetValue>>>;
+
+impl CovBridge {
+    /// Subscribe to the points and start bridging
+    ///
+    /// Fails if a subscription fails, the addresses of the devices must be
+    /// known to the client, e.g. from [`BacnetClient::who_is`].
+    pub async fn start<D: DataLink + 'static>(
+        client: Arc<BacnetClient<D>>,
+        mqtt: Arc<MqttClient>,
+        prefix: &str,
+        points: Vec<Point>,
+        lifetime: Duration,
+    ) -> Result<Self, ClientError> {
+        let points = Arc::new(points);
+        let process_id = std::process::id();
+        let lifetime_secs = lifetime.as_secs().max(1) as u32;
+        // Subscribe to notifications before the first one can arrive
+        let notifications = client.notifications();
+        subscribe(&client, &points, process_id, lifetime_secs).await?;
+        for point in points.iter().filter(|p| p.write_priority.is_some()) {
+            let filter = format!("{}/{}/present-value/{}", prefix, point.topic, SET);
+            mqtt.subscribe(&filter).await?;
+        }
+
+        let last: LastValues = Default::default();
+        let publish = publish(
+            notifications,
+            mqtt.clone(),
+            prefix.to_string(),
+            points.clone(),
+            last.clone(),
+        );
+        let renew = {
+            let (client, points) = (client.clone(), points.clone());
+            async move {
+                loop {
+                    task::sleep(lifetime / 2).await;
+                    if let Err(e) = subscribe(&client, &points, process_id, lifetime_secs).await {
+                        warn!("Renewing COV subscriptions failed: {}", e);
+                    }
+                }
+            }
+        };
+        let write = write(client, mqtt, prefix.to_string(), points, last);
+        Ok(Self {
+            tasks: vec![task::spawn(publish), task::spawn(renew), task::spawn(write)],
+        })
+    }
+}
+
+impl Drop for CovBridge {
+    fn drop(&mut self) {
+        for task in self.tasks.drain(..) {
+            task::spawn(task.cancel());
+        }
+    }
+}
+
+async fn subscribe<D: DataLink + 'static>(
+    client: &BacnetClient<D>,
+    points: &[Point],
+    process_id: u32,
+    lifetime: u32,
+) -> Result<(), ClientError> {
+    for point in points {
+        client
+            .subscribe_cov(
+                point.device,
+                process_id,
+                point.object,
+                false,
+                Some(lifetime),
+            )
+            .await?;
+    }
+    Ok(())
+}
+
+async fn publish<S: Stream<Item = (Address, Notification)> + Unpin>(
+    mut notifications: S,
+    mqtt: Arc<MqttClient>,
+    prefix: String,
+    points: Arc<Vec<Point>>,
+    last: LastValues,
+) {
+    while let Some((_, notification)) = notifications.next().await {
+        let notification = match notification {
+            Notification::Cov { notification, .. } => notification,
+            _ => continue,
+        };
+        let device = notification.initiating_device_identifier.instance;
+        let object = notification.monitored_object_identifier;
+        let index = match points
+            .iter()
+            .position(|p| p.device == device && p.object == object)
+        {
+            Some(index) => index,
+            None => continue,
+        };
+        for value in &notification.values {
+            if value.property_identifier == PropertyIdentifier::PresentValue {
+                last.lock().unwrap().insert(index, value.value.clone());
+            }
+            let topic = format!(
+                "{}/{}/{}",
+                prefix, points[index].topic, value.property_identifier
+            );
+            if let Err(e) = mqtt
+                .publish(&topic, payload(&value.value).as_bytes(), true)
+                .await
+            {
+                warn!("Publishing to {} failed: {}", topic, e);
+                return;
+            }
+        }
+    }
+}
+
+async fn write<D: DataLink + 'static>(
+    client: Arc<BacnetClient<D>>,
+    mqtt: Arc<MqttClient>,
+    prefix: String,
+    points: Arc<Vec<Point>>,
+    last: LastValues,
+) {
+    while let Ok(message) = mqtt.recv().await {
+        let point = points.iter().enumerate().find(|(_, p)| {
+            p.write_priority.is_some()
+                && message.topic == format!("{}/{}/present-value/{}", prefix, p.topic, SET)
+        });
+        let (index, point) = match point {
+            Some(point) => point,
+            None => continue,
+        };
+        let payload = String::from_utf8_lossy(&message.payload);
+        let value = parse(&payload, last.lock().unwrap().get(&index));
+        let value = match value {
+            Some(value) => value,
+            None => {
+                warn!("Ignoring invalid value {:?} for {}", payload, message.topic);
+                continue;
+            }
+        };
+        let property = PropertyIdentifier::PresentValue;
+        let result = client
+            .write(
+                point.device,
+                point.object,
+                property,
+                value,
+                point.write_priority,
+            )
+            .await;
+        if let Err(e) = result {
+            warn!("Writing {} failed: {}", point.topic, e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::tests::*;
+    use crate::mqtt::tests::*;
+    use crate::mqtt::MqttOptions;
+    use crate::Encode;
+
+    #[test]
+    fn test_payload() {
+        assert_eq!(payload(&BACnetValue::Real(21.5)), "21.5");
+        assert_eq!(payload(&BACnetValue::Boolean(true)), "true");
+        assert_eq!(payload(&BACnetValue::CharacterString("On".into())), "On");
+        assert_eq!(
+            payload(&BACnetValue::BitString(vec![false, true].into())),
+            r#"{"BitString":[false,true]}"#
+        );
+
+        assert_eq!(parse("22", None), Some(BACnetValue::Real(22.0)));
+        assert_eq!(parse("null", None), Some(BACnetValue::Null));
+        let last = BACnetValue::Enumerated(0);
+        assert_eq!(parse("1", Some(&last)), Some(BACnetValue::Enumerated(1)));
+        let last = BACnetValue::Boolean(false);
+        assert_eq!(parse("true", Some(&last)), Some(BACnetValue::Boolean(true)));
+        assert_eq!(parse("warm", None), None);
+    }
+
+    #[test]
+    fn test_cov_bridge() {
+        task::block_on(async {
+            let (link, device) = link_pair();
+            let client = Arc::new(BacnetClient::new(link));
+            client.add_device(12, Address::local(vec![2]));
+            let broker = Broker::bind().await;
+            let options = MqttOptions::new("bridge");
+            let (mqtt, (mut stream, _)) = futures_lite::future::zip(
+                MqttClient::connect(broker.addr, &options),
+                broker.accept(0),
+            )
+            .await;
+            let mqtt = Arc::new(mqtt.unwrap());
+
+            let object = ObjectIdentifier::new(ObjectType::AnalogValue, 1);
+            let points = vec![Point::new(12, object).writable(8)];
+            let subscribe = task::spawn(async move {
+                let request = apdu(device.recv().await.unwrap().1);
+                assert_eq!(
+                    request.service_choice().unwrap(),
+                    ConfirmedServiceChoice::SubscribeCov as u8
+                );
+                reply(&device, APDU::simple_ack(request.invoke_id().unwrap(), 5)).await;
+                device
+            });
+            let start = CovBridge::start(
+                client.clone(),
+                mqtt,
+                "bacnet",
+                points,
+                Duration::from_secs(300),
+            );
+            let bridge = start.await.unwrap();
+            let device = subscribe.await;
+            assert_eq!(
+                next_subscribe(&mut stream).await,
+                "bacnet/12/analog-value/1/present-value/set"
+            );
+
+            let notification = CovNotification {
+                subscriber_process_identifier: std::process::id(),
+                initiating_device_identifier: ObjectIdentifier::new(ObjectType::Device, 12),
+                monitored_object_identifier: object,
+                time_remaining: 300,
+                values: vec![PropertyValue::new(
+                    PropertyIdentifier::PresentValue,
+                    BACnetValue::Real(21.5),
+                )],
+            };
+            let service = UnconfirmedServiceChoice::UnconfirmedCovNotification as u8;
+            let data = notification.encode_vec().unwrap();
+            reply(&device, APDU::unconfirmed_request(service, data)).await;
+            let publish = next_publish(&mut stream).await;
+            assert_eq!(publish.topic, "bacnet/12/analog-value/1/present-value");
+            assert_eq!(publish.payload, b"21.5");
+            assert!(publish.retain);
+
+            // Written with the datatype of the last value
+            send_publish(
+                &mut stream,
+                "bacnet/12/analog-value/1/present-value/set",
+                b"22",
+            )
+            .await;
+            let request = apdu(device.recv().await.unwrap().1);
+            assert_eq!(
+                request.service_choice().unwrap(),
+                ConfirmedServiceChoice::WriteProperty as u8
+            );
+            let data = request.user_data();
+            assert_eq!(data[data.len() - 2..], [0x49, 8]);
+            let real = BACnetValue::Real(22.0);
+            let mut expected = Vec::new();
+            crate::encoding::encode_application(&mut expected, &real).unwrap();
+            assert!(data.windows(expected.len()).any(|w| w == expected));
+            reply(&device, APDU::simple_ack(request.invoke_id().unwrap(), 15)).await;
+
+            drop(bridge);
+        });
+    }
+}
@@ -1,43 +1,42 @@
 use crate::application::*;
+use crate::error::NetworkError;
+#[cfg(feature = "serde")]
+use crate::json;
 use crate::{Decode, Encode};
 
 use num_derive::{FromPrimitive, ToPrimitive};
-use num_traits::{FromPrimitive, ToPrimitive};
-use std::convert::TryFrom;
+use num_traits::FromPrimitive;
 
 use byteorder::{BigEndian, ReadBytesExt, WriteBytesExt};
-use bytes::BufMut;
+use bytes::Bytes;
 
 use tracing::trace;
 
 /// Network Layer PDU Message Priority (6.2.2)
-#[derive(Copy, Clone, Debug, Eq, PartialEq, FromPrimitive, ToPrimitive)]
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Default, FromPrimitive, ToPrimitive)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum NPDUPriority {
     LifeSafety = 0b11,
     CriticalEquipment = 0b10,
     Urgent = 0b01,
+    #[default]
     Normal = 0b00,
 }
 
-impl Into<u8> for NPDUPriority {
-    fn into(self) -> u8 {
-        match self {
-            Self::LifeSafety => 0b11,
-            Self::CriticalEquipment => 0b10,
-            Self::Urgent => 0b01,
-            Self::Normal => 0b00,
+impl From<NPDUPriority> for u8 {
+    fn from(priority: NPDUPriority) -> u8 {
+        match priority {
+            NPDUPriority::LifeSafety => 0b11,
+            NPDUPriority::CriticalEquipment => 0b10,
+            NPDUPriority::Urgent => 0b01,
+            NPDUPriority::Normal => 0b00,
         }
     }
 }
 
-impl Default for NPDUPriority {
-    fn default() -> Self {
-        Self::Normal
-    }
-}
-
 /// Network Layer PDU Message Type (6.2.4)
-#[derive(Clone, Debug, Eq, PartialEq)]
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum NPDUMessage {
     WhoIsRouterToNetwork,          // = 0x00,
     IAmRouterToNetwork,            // = 0x01,
@@ -63,34 +62,181 @@ pub enum NPDUMessage {
     Reserved(u8),                  // = 0x14 to 0x7F, Reserved for use by ASHRAE
 }
 
-impl TryFrom<u8> for NPDUMessage {
-    type Error = String;
-
-    fn try_from(v: u8) -> Result<Self, Self::Error> {
+/// Every message type is defined, reserved or proprietary
+impl From<u8> for NPDUMessage {
+    fn from(v: u8) -> Self {
         match v {
-            0x00 => Ok(Self::WhoIsRouterToNetwork),
-            // TODO: Implement rest
-            v if (v >= 0x80 && v <= 0xFF) => Ok(Self::Proprietary(v)),
-            v => Err(format!("Unknown Message type: {}", v)),
+            0x00 => Self::WhoIsRouterToNetwork,
+            0x01 => Self::IAmRouterToNetwork,
+            0x02 => Self::ICouldBeRouterToNetwork,
+            0x03 => Self::RejectMessageToNetwork,
+            0x04 => Self::RouterBusyToNetwork,
+            0x05 => Self::RouterAvailableToNetwork,
+            0x06 => Self::InitializeRoutingTable,
+            0x07 => Self::InitializeRoutingTableAck,
+            0x08 => Self::EstablishConnectionToNetwork,
+            0x09 => Self::DisconnectConnectionToNetwork,
+            0x0A => Self::ChallengeRequest,
+            0x0B => Self::SecurityPayload,
+            0x0C => Self::SecurityResponse,
+            0x0D => Self::RequestKeyUpdate,
+            0x0E => Self::UpdateKeySet,
+            0x0F => Self::UpdateDistributionKey,
+            0x10 => Self::RequestMasterKey,
+            0x11 => Self::SetMasterKey,
+            0x12 => Self::WhatIsNetworkNumber,
+            0x13 => Self::NetworkNumberIs,
+            v @ 0x80..=0xFF => Self::Proprietary(v),
+            v => Self::Reserved(v),
+        }
+    }
+}
+
+impl From<NPDUMessage> for u8 {
+    fn from(message: NPDUMessage) -> u8 {
+        match message {
+            NPDUMessage::WhoIsRouterToNetwork => 0x00,
+            NPDUMessage::IAmRouterToNetwork => 0x01,
+            NPDUMessage::ICouldBeRouterToNetwork => 0x02,
+            NPDUMessage::RejectMessageToNetwork => 0x03,
+            NPDUMessage::RouterBusyToNetwork => 0x04,
+            NPDUMessage::RouterAvailableToNetwork => 0x05,
+            NPDUMessage::InitializeRoutingTable => 0x06,
+            NPDUMessage::InitializeRoutingTableAck => 0x07,
+            NPDUMessage::EstablishConnectionToNetwork => 0x08,
+            NPDUMessage::DisconnectConnectionToNetwork => 0x09,
+            NPDUMessage::ChallengeRequest => 0x0A,
+            NPDUMessage::SecurityPayload => 0x0B,
+            NPDUMessage::SecurityResponse => 0x0C,
+            NPDUMessage::RequestKeyUpdate => 0x0D,
+            NPDUMessage::UpdateKeySet => 0x0E,
+            NPDUMessage::UpdateDistributionKey => 0x0F,
+            NPDUMessage::RequestMasterKey => 0x10,
+            NPDUMessage::SetMasterKey => 0x11,
+            NPDUMessage::WhatIsNetworkNumber => 0x12,
+            NPDUMessage::NetworkNumberIs => 0x13,
+            NPDUMessage::Proprietary(v) | NPDUMessage::Reserved(v) => v,
+        }
+    }
+}
+
+/// Network layer message (6.4), the content of an NPDU that is not an APDU
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct NetworkMessage {
+    pub message_type: NPDUMessage,
+    /// Vendor of a proprietary message
+    pub vendor_id: Option<u16>,
+    /// Parameters following the message type
+    pub data: Vec<u8>,
+}
+
+impl NetworkMessage {
+    pub fn new(message_type: NPDUMessage, data: Vec<u8>) -> Self {
+        Self {
+            message_type,
+            vendor_id: None,
+            data,
+        }
+    }
+
+    /// Who-Is-Router-To-Network (6.4.1), without a network for all
+    /// reachable networks
+    pub fn who_is_router_to_network(network: Option<u16>) -> Self {
+        let data = network
+            .map(|n| n.to_be_bytes().to_vec())
+            .unwrap_or_default();
+        Self::new(NPDUMessage::WhoIsRouterToNetwork, data)
+    }
+
+    /// I-Am-Router-To-Network (6.4.2)
+    pub fn i_am_router_to_network(networks: &[u16]) -> Self {
+        let data = networks.iter().flat_map(|n| n.to_be_bytes()).collect();
+        Self::new(NPDUMessage::IAmRouterToNetwork, data)
+    }
+
+    /// The network numbers of messages with a list of networks, like
+    /// Who-Is-Router-To-Network and I-Am-Router-To-Network
+    pub fn networks(&self) -> crate::error::Result<Vec<u16>> {
+        if !self.data.len().is_multiple_of(2) {
+            return Err(NetworkError::InvalidNetworkList.into());
         }
+        Ok(self
+            .data
+            .chunks(2)
+            .map(|n| u16::from_be_bytes([n[0], n[1]]))
+            .collect())
     }
 }
 
-impl Encode for NPDUMessage {
-    fn encode<T: std::io::Write + Sized>(&self, writer: &mut T) -> std::io::Result<()> {
-        unimplemented!();
+impl Encode for NetworkMessage {
+    fn encode<T: std::io::Write + Sized>(&self, writer: &mut T) -> crate::error::Result<()> {
+        writer.write_u8(self.message_type.into())?;
+        if let Some(vendor_id) = self.vendor_id {
+            writer.write_u16::<BigEndian>(vendor_id)?;
+        }
+        writer.write_all(&self.data)?;
+        Ok(())
     }
 
     fn len(&self) -> usize {
-        unimplemented!();
+        1 + self.vendor_id.map(|_| 2).unwrap_or(0) + self.data.len()
+    }
+}
+
+/// Network number addressing all networks (6.2.2)
+pub const GLOBAL_BROADCAST: u16 = 0xFFFF;
+
+/// Address of a BACnet device
+///
+/// Devices on the local network have no network number and are reached
+/// directly by their MAC address, others through a router. An empty MAC
+/// address is the broadcast address of the network.
+#[derive(Clone, Debug, Default, Eq, PartialEq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Address {
+    pub net: Option<u16>,
+    pub mac: Vec<u8>,
+}
+
+impl Address {
+    /// A device on the local network
+    pub fn local<M: Into<Vec<u8>>>(mac: M) -> Self {
+        Self {
+            net: None,
+            mac: mac.into(),
+        }
+    }
+
+    /// A device on a remote network
+    pub fn remote<M: Into<Vec<u8>>>(net: u16, mac: M) -> Self {
+        Self {
+            net: Some(net),
+            mac: mac.into(),
+        }
+    }
+
+    /// All devices on the local network
+    pub fn broadcast() -> Self {
+        Self::default()
+    }
+
+    /// All devices on all networks
+    pub fn global_broadcast() -> Self {
+        Self::remote(GLOBAL_BROADCAST, vec![])
+    }
+
+    pub fn is_broadcast(&self) -> bool {
+        self.mac.is_empty()
     }
 }
 
 #[derive(Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct NPDUDest {
-    net: u16,
-    adr: Vec<u8>,
-    hops: u8,
+    pub net: u16,
+    pub adr: Vec<u8>,
+    pub hops: u8,
 }
 
 impl NPDUDest {
@@ -101,12 +247,22 @@ impl NPDUDest {
             hops: 255,
         }
     }
+
+    /// Destination for a device on another network
+    pub fn to_address(address: &Address) -> Option<Self> {
+        address.net.map(|net| NPDUDest {
+            net,
+            adr: address.mac.clone(),
+            hops: 255,
+        })
+    }
 }
 
 #[derive(Clone, Debug, Eq, PartialEq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct NPDUSource {
-    net: u16,
-    adr: Vec<u8>,
+    pub net: u16,
+    pub adr: Vec<u8>,
 }
 
 impl NPDUSource {
@@ -119,7 +275,8 @@ impl NPDUSource {
 }
 
 #[derive(Clone, Debug, Eq, PartialEq)]
-pub enum NPDUContent<A: Encode = APDU, B: Encode = NPDUMessage> {
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum NPDUContent<A: Encode = APDU, B: Encode = NetworkMessage> {
     APDU(A),
     Message(B),
 }
@@ -131,11 +288,11 @@ impl<A: Encode, B: Encode> From<A> for NPDUContent<A, B> {
 }
 
 impl<A: Encode, B: Encode> Encode for NPDUContent<A, B> {
-    fn encode<T: std::io::Write + Sized>(&self, writer: &mut T) -> std::io::Result<()> {
-        Ok(match self {
-            Self::APDU(apdu) => apdu.encode(writer)?,
-            Self::Message(msg) => msg.encode(writer)?,
-        })
+    fn encode<T: std::io::Write + Sized>(&self, writer: &mut T) -> crate::error::Result<()> {
+        match self {
+            Self::APDU(apdu) => apdu.encode(writer),
+            Self::Message(msg) => msg.encode(writer),
+        }
     }
 
     fn len(&self) -> usize {
@@ -147,7 +304,8 @@ impl<A: Encode, B: Encode> Encode for NPDUContent<A, B> {
 }
 
 #[derive(Clone, Debug, Eq, PartialEq)]
-pub struct NPDU<A: Encode = APDU, B: Encode = NPDUMessage> {
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct NPDU<A: Encode = APDU, B: Encode = NetworkMessage> {
     /// Protocol Version Number (6.2.1)
     pub version: u8,
     pub destination: Option<NPDUDest>,
@@ -175,8 +333,65 @@ impl<A: Encode, B: Encode> NPDU<A, B> {
     }
 }
 
+impl NPDU {
+    /// See [`NPDUBuilder`]
+    pub fn builder() -> NPDUBuilder {
+        NPDUBuilder::default()
+    }
+}
+
+/// Builder of an [`NPDU`], by default to the local network, of normal
+/// priority and not expecting a reply
+#[derive(Clone, Debug, Default)]
+pub struct NPDUBuilder {
+    destination: Option<NPDUDest>,
+    source: Option<NPDUSource>,
+    data_expecting_reply: bool,
+    priority: NPDUPriority,
+}
+
+impl NPDUBuilder {
+    /// The network and MAC address on the local network or beyond a router
+    pub fn to(mut self, address: &Address) -> Self {
+        self.destination = NPDUDest::to_address(address);
+        self
+    }
+
+    /// All devices on all networks
+    pub fn global_broadcast(self) -> Self {
+        self.to(&Address::global_broadcast())
+    }
+
+    /// The network and MAC address a router forwards the NPDU from
+    pub fn source(mut self, net: u16, mac: impl Into<Vec<u8>>) -> Self {
+        self.source = Some(NPDUSource {
+            net,
+            adr: mac.into(),
+        });
+        self
+    }
+
+    /// Expect a reply, as for confirmed requests
+    pub fn expecting_reply(mut self) -> Self {
+        self.data_expecting_reply = true;
+        self
+    }
+
+    pub fn priority(mut self, priority: NPDUPriority) -> Self {
+        self.priority = priority;
+        self
+    }
+
+    pub fn build(self, content: impl Into<NPDUContent>) -> NPDU {
+        NPDU {
+            data_expecting_reply: self.data_expecting_reply,
+            ..NPDU::new(content, self.destination, self.source, self.priority)
+        }
+    }
+}
+
 impl<A: Encode, B: Encode> Encode for NPDU<A, B> {
-    fn encode<T: std::io::Write + Sized>(&self, writer: &mut T) -> std::io::Result<()> {
+    fn encode<T: std::io::Write + Sized>(&self, writer: &mut T) -> crate::error::Result<()> {
         // NPCI
         writer.write_u8(self.version)?;
 
@@ -197,12 +412,12 @@ impl<A: Encode, B: Encode> Encode for NPDU<A, B> {
         if let Some(ref d) = self.destination {
             writer.write_u16::<BigEndian>(d.net)?;
             writer.write_u8(d.adr.len() as u8)?;
-            writer.write(&d.adr)?;
+            writer.write_all(&d.adr)?;
         }
         if let Some(ref s) = self.source {
             writer.write_u16::<BigEndian>(s.net)?;
             writer.write_u8(s.adr.len() as u8)?;
-            writer.write(&s.adr)?;
+            writer.write_all(&s.adr)?;
         }
         if let Some(ref d) = self.destination {
             writer.write_u8(d.hops)?;
@@ -221,26 +436,40 @@ impl<A: Encode, B: Encode> Encode for NPDU<A, B> {
         l += self
             .destination
             .as_ref()
-            .and_then(|d| Some(2 + 1 + d.adr.len() + 1))
-            .unwrap_or(0) as usize; // DNET(2) + DLEN(1) + DADR(*) + HOPS(1)
+            .map(|d| 2 + 1 + d.adr.len() + 1)
+            .unwrap_or(0); // DNET(2) + DLEN(1) + DADR(*) + HOPS(1)
         l += self
             .source
             .as_ref()
-            .and_then(|s| Some(2 + 1 + s.adr.len()))
-            .unwrap_or(0) as usize; // SNET(2) + SLEN(1) + SADR(*)
+            .map(|s| 2 + 1 + s.adr.len())
+            .unwrap_or(0); // SNET(2) + SLEN(1) + SADR(*)
         l += self.content.len();
         l
     }
 }
 
-impl Decode for NPDU {
-    fn decode<T: std::io::Read + Sized>(reader: &mut T) -> std::io::Result<Self> {
+#[cfg(feature = "serde")]
+impl NPDU {
+    /// The NPDU with its decoded service parameters as JSON, see
+    /// [`crate::json`]
+    pub fn to_json(&self) -> String {
+        json::to_string(self).expect("NPDUs serialize without errors")
+    }
+}
+
+impl NPDU {
+    /// Decode an NPDU whose APDU is decoded by `apdu` from the rest of the
+    /// reader
+    fn decode_with<T: std::io::Read + Sized>(
+        reader: &mut T,
+        apdu: impl FnOnce(&mut T) -> crate::error::Result<APDU>,
+    ) -> crate::error::Result<Self> {
         let version = reader.read_u8()?;
         trace!("Version: {:02x}", version);
         // Read and parse the Network Layer Protocol Control Information (6.2.2)
         let control = reader.read_u8()?;
         trace!("Control: {:08b}", control);
-        let priority = NPDUPriority::from_u8(control & 0b0000_00011).unwrap();
+        let priority = NPDUPriority::from_u8(control & 0b0000_00011).unwrap_or_default(); // All four are defined
         let has_apdu = (control & 1 << 7) == 0;
         let has_dest = (control & 1 << 5) != 0;
         let has_source = (control & 1 << 3) != 0;
@@ -250,6 +479,7 @@ impl Decode for NPDU {
             let net = reader.read_u16::<BigEndian>()?;
             let len = reader.read_u8()?;
             let mut dest = NPDUDest::new(net, len as usize);
+            dest.adr.resize(len as usize, 0);
             reader.read_exact(&mut dest.adr)?;
             Some(dest)
         } else {
@@ -260,23 +490,32 @@ impl Decode for NPDU {
             let net = reader.read_u16::<BigEndian>()?;
             let len = reader.read_u8()?;
             let mut source = NPDUSource::new(net, len as usize);
+            source.adr.resize(len as usize, 0);
             reader.read_exact(&mut source.adr)?;
             Some(source)
         } else {
             None
         };
-        println!("{:?}", destination);
+        trace!("Destination: {:?}", destination);
         if let Some(dest) = &mut destination {
             dest.hops = reader.read_u8()?;
         };
 
         let content = if has_apdu {
-            APDU::decode(reader)?.into()
+            apdu(reader)?.into()
         } else {
-            /*Ok(NPDUContentSlice::Message(NPDUMessage::try_from(
-                self.slice[0],
-            )?))*/
-            unimplemented!();
+            let message_type = NPDUMessage::from(reader.read_u8()?);
+            let vendor_id = match message_type {
+                NPDUMessage::Proprietary(_) => Some(reader.read_u16::<BigEndian>()?),
+                _ => None,
+            };
+            let mut data = Vec::new();
+            reader.read_to_end(&mut data)?;
+            NPDUContent::Message(NetworkMessage {
+                message_type,
+                vendor_id,
+                data,
+            })
         };
 
         Ok(Self {
@@ -290,6 +529,145 @@ impl Decode for NPDU {
     }
 }
 
+impl Decode for NPDU {
+    fn decode<T: std::io::Read + Sized>(reader: &mut T) -> crate::error::Result<Self> {
+        Self::decode_with(reader, APDU::decode)
+    }
+
+    fn decode_bytes(data: Bytes) -> crate::error::Result<Self> {
+        let mut reader = std::io::Cursor::new(&data[..]);
+        Self::decode_with(&mut reader, |reader| {
+            APDU::decode_bytes(data.slice(reader.position() as usize..))
+        })
+    }
+}
+
+/// Network number and MAC address following the control octet, borrowed
+/// from the rest of `reader`
+fn read_address_slice<'a>(reader: &mut &'a [u8]) -> std::io::Result<(u16, &'a [u8])> {
+    let net = reader.read_u16::<BigEndian>()?;
+    let len = reader.read_u8()? as usize;
+    if reader.len() < len {
+        return Err(std::io::ErrorKind::UnexpectedEof.into());
+    }
+    let (adr, rest) = reader.split_at(len);
+    *reader = rest;
+    Ok((net, adr))
+}
+
+/// An NPDU borrowing the buffer it was decoded from
+///
+/// Decoding reads the NPCI only, the addresses and the APDU or network
+/// message reference the buffer. Routers forward and packet inspectors look
+/// at frames without copying them, see [`NPDU`] for its owned counterpart.
+///
+/// ```
+/// use bacnet::network::NPDUSlice;
+///
+/// let data = [0x01, 0x20, 0xff, 0xff, 0x00, 0xff, 0x10, 0x08];
+/// let npdu = NPDUSlice::from_slice(&data).unwrap();
+/// assert_eq!(npdu.destination(), Some((0xffff, &[][..])));
+/// assert_eq!(npdu.hops(), Some(255));
+/// assert_eq!(npdu.payload(), [0x10, 0x08]);
+/// assert_eq!(npdu.apdu().unwrap().unwrap().service_choice(), Some(8));
+/// ```
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct NPDUSlice<'a> {
+    data: &'a [u8],
+    destination: Option<(u16, &'a [u8])>,
+    source: Option<(u16, &'a [u8])>,
+    hops: Option<u8>,
+    /// The APDU or network message following the NPCI
+    payload: &'a [u8],
+}
+
+impl<'a> NPDUSlice<'a> {
+    /// Fails if `data` ends within the NPCI
+    pub fn from_slice(data: &'a [u8]) -> crate::error::Result<Self> {
+        let mut reader = data;
+        let _version = reader.read_u8()?;
+        let control = reader.read_u8()?;
+        let destination = match control & 1 << 5 != 0 {
+            true => Some(read_address_slice(&mut reader)?),
+            false => None,
+        };
+        let source = match control & 1 << 3 != 0 {
+            true => Some(read_address_slice(&mut reader)?),
+            false => None,
+        };
+        let hops = match destination {
+            Some(_) => Some(reader.read_u8()?),
+            None => None,
+        };
+        Ok(Self {
+            data,
+            destination,
+            source,
+            hops,
+            payload: reader,
+        })
+    }
+
+    /// Protocol Version Number (6.2.1)
+    pub fn version(&self) -> u8 {
+        self.data[0]
+    }
+
+    pub fn priority(&self) -> NPDUPriority {
+        NPDUPriority::from_u8(self.data[1] & 0b11).unwrap_or_default()
+    }
+
+    pub fn data_expecting_reply(&self) -> bool {
+        self.data[1] & 1 << 2 != 0
+    }
+
+    /// The network number and MAC address of the destination
+    pub fn destination(&self) -> Option<(u16, &'a [u8])> {
+        self.destination
+    }
+
+    /// The network number and MAC address of the source
+    pub fn source(&self) -> Option<(u16, &'a [u8])> {
+        self.source
+    }
+
+    /// The hop count, if there is a destination
+    pub fn hops(&self) -> Option<u8> {
+        self.hops
+    }
+
+    /// The Network Layer Protocol Control Information
+    pub fn header(&self) -> &'a [u8] {
+        &self.data[..self.data.len() - self.payload.len()]
+    }
+
+    /// The APDU or network message following the NPCI
+    pub fn payload(&self) -> &'a [u8] {
+        self.payload
+    }
+
+    /// The type of a network message, `None` for APDUs
+    pub fn message_type(&self) -> Option<NPDUMessage> {
+        match self.data[1] & 1 << 7 != 0 {
+            true => self.payload.first().map(|&t| NPDUMessage::from(t)),
+            false => None,
+        }
+    }
+
+    /// The APDU, `None` for network messages
+    pub fn apdu(&self) -> crate::error::Result<Option<APDUSlice<'a>>> {
+        match self.data[1] & 1 << 7 != 0 {
+            true => Ok(None),
+            false => APDUSlice::from_slice(self.payload).map(Some),
+        }
+    }
+
+    /// The NPDU with its addresses and content copied
+    pub fn to_npdu(&self) -> crate::error::Result<NPDU> {
+        NPDU::decode_slice(self.data)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -308,6 +686,33 @@ mod tests {
         assert_eq!(w.into_inner().to_vec(), vec![1, 0]);
     }
 
+    #[test]
+    fn test_npdu_builder() {
+        let apdu = APDU::unconfirmed_request(8, vec![]);
+        let npdu = NPDU::builder().global_broadcast().build(apdu.clone());
+        assert_eq!(
+            npdu.encode_vec().unwrap(),
+            [1, 0x20, 0xFF, 0xFF, 0, 0xFF, 0x10, 8]
+        );
+
+        let npdu = NPDU::builder()
+            .to(&Address::remote(5, vec![7]))
+            .source(2, vec![9])
+            .expecting_reply()
+            .priority(NPDUPriority::Urgent)
+            .build(apdu.clone());
+        assert_eq!(
+            npdu.encode_vec().unwrap(),
+            [1, 0x2D, 0, 5, 1, 7, 0, 2, 1, 9, 0xFF, 0x10, 8]
+        );
+        assert_eq!(
+            NPDU::builder()
+                .to(&Address::local(vec![7]))
+                .build(apdu.clone()),
+            NPDU::new(apdu, None, None, NPDUPriority::Normal)
+        );
+    }
+
     #[test]
     fn test_encode_npdu_with_dest() {
         let content = NPDUContent::<Dummy, Dummy>::APDU(Dummy::default());
@@ -368,4 +773,84 @@ mod tests {
             ]
         );
     }
+
+    #[test]
+    fn test_decode_npdu_with_source() {
+        let data = hex::decode("0108000106c0a8010abac01008").unwrap();
+        let npdu = NPDU::decode_slice(&data).expect("Decode NPDU");
+
+        let source = npdu.source.expect("Source");
+        assert_eq!(source.net, 1);
+        assert_eq!(source.adr, vec![0xc0, 0xa8, 0x01, 0x0a, 0xba, 0xc0]);
+        assert_eq!(
+            npdu.content,
+            NPDUContent::APDU(APDU::unconfirmed_request(8, vec![]))
+        );
+    }
+
+    #[test]
+    fn test_network_message() {
+        let data = hex::decode("018001000a0014").unwrap();
+        let npdu = NPDU::decode_slice(&data).expect("Decode NPDU");
+        let message = NetworkMessage::i_am_router_to_network(&[10, 20]);
+        assert_eq!(npdu.content, NPDUContent::Message(message.clone()));
+        assert_eq!(message.networks().unwrap(), vec![10, 20]);
+        assert_eq!(npdu.encode_vec().unwrap(), data);
+
+        let data = hex::decode("018080010203").unwrap();
+        let npdu = NPDU::decode_slice(&data).expect("Decode NPDU");
+        match &npdu.content {
+            NPDUContent::Message(message) => {
+                assert_eq!(message.message_type, NPDUMessage::Proprietary(0x80));
+                assert_eq!(message.vendor_id, Some(0x0102));
+                assert_eq!(message.data, vec![0x03]);
+            }
+            content => panic!("Not a message: {:?}", content),
+        }
+        assert_eq!(npdu.encode_vec().unwrap(), data);
+        assert!(NPDU::decode_slice(&[0x01, 0x80, 0x80, 0x01]).is_err());
+    }
+
+    #[test]
+    fn test_decode_bytes() {
+        let data = Bytes::from(hex::decode("01080005010a10080901").unwrap());
+        let npdu = NPDU::decode_bytes(data.clone()).expect("Decode NPDU");
+        assert_eq!(npdu, NPDU::decode_slice(&data).unwrap());
+        // The APDU shares the buffer of the frame
+        match &npdu.content {
+            NPDUContent::APDU(apdu) => assert_eq!(apdu.user_data().as_ptr(), data[8..].as_ptr()),
+            content => panic!("Not an APDU: {:?}", content),
+        }
+    }
+
+    #[test]
+    fn test_npdu_slice() {
+        let data = hex::decode("0128000a02beefffff04c0a8010afe3c0703040c3e").unwrap();
+        let npdu = NPDUSlice::from_slice(&data).unwrap();
+        assert_eq!(npdu.version(), 1);
+        assert_eq!(npdu.destination(), Some((10, &[0xbe, 0xef][..])));
+        assert_eq!(npdu.source(), Some((0xffff, &data[10..14])));
+        assert_eq!(npdu.hops(), Some(0xfe));
+        assert_eq!(npdu.header(), &data[..data.len() - 6]);
+        assert_eq!(npdu.message_type(), None);
+        assert!(!npdu.data_expecting_reply());
+
+        // Nothing is copied, the fields reference the frame
+        let apdu = npdu.apdu().unwrap().unwrap();
+        assert_eq!(apdu.user_data().as_ptr(), data[data.len() - 1..].as_ptr());
+        assert_eq!(npdu.to_npdu().unwrap(), NPDU::decode_slice(&data).unwrap());
+
+        let data = hex::decode("018001000a0014").unwrap();
+        let npdu = NPDUSlice::from_slice(&data).unwrap();
+        assert_eq!(npdu.message_type(), Some(NPDUMessage::IAmRouterToNetwork));
+        assert_eq!(npdu.payload(), &data[2..]);
+        assert!(npdu.apdu().unwrap().is_none());
+        assert_eq!(npdu.destination(), None);
+        assert_eq!(npdu.hops(), None);
+
+        // The NPCI ends within an address and before the hop count
+        assert!(NPDUSlice::from_slice(&data[..1]).is_err());
+        assert!(NPDUSlice::from_slice(&hex::decode("0120000a02be").unwrap()).is_err());
+        assert!(NPDUSlice::from_slice(&hex::decode("0120000a00").unwrap()).is_err());
+    }
 }
@@ -0,0 +1,177 @@
+//! Standard BACnet objects (Clause 12)
+//!
+//! Every object exposes its properties through the [`Object`] trait, which is
+//! what ReadProperty and WriteProperty are dispatched into.
+
+use crate::application::{
+    AtomicReadFileAck, AtomicWriteFileAck, BACnetDateTime, BACnetError, BACnetValue, ErrorClass,
+    ErrorCode, FileData, FileReadAccess, ObjectIdentifier, ObjectType, PropertyIdentifier, Range,
+};
+
+use std::convert::TryFrom;
+
+pub mod audit_log;
+pub mod calendar;
+pub mod channel;
+pub mod device;
+pub mod file;
+pub mod lighting_output;
+pub mod log_buffer;
+pub mod multi_state;
+pub mod notification_class;
+pub mod priority_array;
+pub mod schedule;
+pub mod trend_log;
+pub use audit_log::*;
+pub use calendar::*;
+pub use channel::*;
+pub use device::*;
+pub use file::*;
+pub use lighting_output::*;
+pub use log_buffer::*;
+pub use multi_state::*;
+pub use notification_class::*;
+pub use priority_array::*;
+pub use schedule::*;
+pub use trend_log::*;
+
+pub trait Object {
+    fn object_identifier(&self) -> ObjectIdentifier;
+
+    fn object_name(&self) -> &str;
+
+    fn object_type(&self) -> ObjectType {
+        self.object_identifier().object_type
+    }
+
+    /// Properties of this object, excluding Object_Identifier, Object_Name,
+    /// Object_Type and Property_List (12.1.1.4.1)
+    fn property_list(&self) -> Vec<PropertyIdentifier>;
+
+    fn read_property(
+        &self,
+        property: PropertyIdentifier,
+        array_index: Option<u32>,
+    ) -> Result<BACnetValue, BACnetError>;
+
+    /// Write a property, at `time` for the changes the object logs
+    fn write_property(
+        &mut self,
+        property: PropertyIdentifier,
+        _array_index: Option<u32>,
+        _value: BACnetValue,
+        _priority: Option<u8>,
+        _time: BACnetDateTime,
+    ) -> Result<(), BACnetError> {
+        Err(self.unwritable(property))
+    }
+
+    /// Items of a list property selected by a ReadRange request (15.8),
+    /// without a range all of them
+    fn read_range(
+        &self,
+        property: PropertyIdentifier,
+        _array_index: Option<u32>,
+        _range: Option<&Range>,
+    ) -> Result<RangeItems, BACnetError> {
+        Err(self.not_a_list(property))
+    }
+
+    /// Octets or records of a File object read by AtomicReadFile (15.1)
+    fn read_file(&self, _access: &FileReadAccess) -> Result<AtomicReadFileAck, BACnetError> {
+        Err(not_a_file())
+    }
+
+    /// Write octets or records of a File object by AtomicWriteFile (15.2),
+    /// modifying it at `time`
+    fn write_file(
+        &mut self,
+        _data: FileData,
+        _time: BACnetDateTime,
+    ) -> Result<AtomicWriteFileAck, BACnetError> {
+        Err(not_a_file())
+    }
+
+    /// Error to return when reading a range of a property that is not a list
+    fn not_a_list(&self, property: PropertyIdentifier) -> BACnetError {
+        match self.has_property(property) {
+            true => BACnetError::new(ErrorClass::Services, ErrorCode::PropertyIsNotAList),
+            false => BACnetError::property(ErrorCode::UnknownProperty),
+        }
+    }
+
+    /// Error to return when writing a property that cannot be written
+    fn unwritable(&self, property: PropertyIdentifier) -> BACnetError {
+        match self.has_property(property) {
+            true => BACnetError::property(ErrorCode::WriteAccessDenied),
+            false => BACnetError::property(ErrorCode::UnknownProperty),
+        }
+    }
+
+    fn has_property(&self, property: PropertyIdentifier) -> bool {
+        use PropertyIdentifier::*;
+        matches!(
+            property,
+            ObjectIdentifier | ObjectName | ObjectType | PropertyList
+        ) || self.property_list().contains(&property)
+    }
+
+    /// Read one of the properties every object has
+    fn read_common_property(
+        &self,
+        property: PropertyIdentifier,
+        array_index: Option<u32>,
+    ) -> Result<BACnetValue, BACnetError> {
+        let value = match property {
+            PropertyIdentifier::ObjectIdentifier => {
+                BACnetValue::ObjectIdentifier(self.object_identifier())
+            }
+            PropertyIdentifier::ObjectName => {
+                BACnetValue::CharacterString(self.object_name().into())
+            }
+            PropertyIdentifier::ObjectType => {
+                BACnetValue::Enumerated(self.object_type().number() as u32)
+            }
+            PropertyIdentifier::PropertyList => BACnetValue::Array(
+                self.property_list()
+                    .into_iter()
+                    .map(|p| BACnetValue::Enumerated(p.number()))
+                    .collect(),
+            ),
+            _ => return Err(BACnetError::property(ErrorCode::UnknownProperty)),
+        };
+        value.array_element(array_index)
+    }
+}
+
+/// Error of AtomicReadFile and AtomicWriteFile for objects other than files
+pub(crate) fn not_a_file() -> BACnetError {
+    BACnetError::new(ErrorClass::Services, ErrorCode::InconsistentObjectType)
+}
+
+/// Check that a written value has the expected datatype
+pub(crate) fn expect_boolean(value: BACnetValue) -> Result<bool, BACnetError> {
+    bool::try_from(value)
+}
+
+pub(crate) fn expect_unsigned(value: BACnetValue) -> Result<u32, BACnetError> {
+    u32::try_from(value)
+}
+
+pub(crate) fn expect_real(value: BACnetValue) -> Result<f32, BACnetError> {
+    f32::try_from(value)
+}
+
+/// Helpers shared by the tests of the objects
+#[cfg(test)]
+pub(crate) mod testing {
+    use crate::application::{BACnetDate, BACnetDateTime, BACnetTime};
+
+    /// A time stamp of the test day, at the given minute
+    pub(crate) fn at(minute: u8) -> BACnetDateTime {
+        BACnetDateTime::new(
+            BACnetDate::new(2021, 1, 25),
+            BACnetTime::new(12, minute, 0, 0),
+        )
+    }
+}
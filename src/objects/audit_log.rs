@@ -0,0 +1,387 @@
+use crate::application::{
+    BACnetDateTime, BACnetError, BACnetValue, ErrorCode, ObjectIdentifier, ObjectType,
+    PropertyIdentifier, Range,
+};
+use crate::objects::{expect_boolean, expect_unsigned, LogBuffer, LogRange, LogStatus, Object};
+
+use num_derive::{FromPrimitive, ToPrimitive};
+
+/// BACnetAuditOperation (Clause 21)
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash, FromPrimitive, ToPrimitive)]
+pub enum AuditOperation {
+    Read = 0,
+    Write = 1,
+    Create = 2,
+    Delete = 3,
+    LifeSafety = 4,
+    AcknowledgeAlarm = 5,
+    DeviceDisableComm = 6,
+    DeviceEnableComm = 7,
+    DeviceReset = 8,
+    DeviceBackup = 9,
+    DeviceRestore = 10,
+    Subscription = 11,
+    Notification = 12,
+    AuditingFailure = 13,
+    NetworkChanges = 14,
+    General = 15,
+}
+
+/// BACnetAuditNotification (Clause 21)
+///
+/// Describes one audited operation: who did it (source), what it was applied
+/// to (target) and whether it failed (`result`).
+#[derive(Clone, Debug, PartialEq)]
+pub struct AuditNotification {
+    pub operation: AuditOperation,
+    pub source_device: ObjectIdentifier,
+    pub source_object: Option<ObjectIdentifier>,
+    pub source_comment: Option<String>,
+    pub invoke_id: Option<u8>,
+    pub target_device: ObjectIdentifier,
+    pub target_object: Option<ObjectIdentifier>,
+    pub target_property: Option<PropertyIdentifier>,
+    pub target_priority: Option<u8>,
+    pub target_value: Option<BACnetValue>,
+    /// `None` if the operation succeeded
+    pub result: Option<BACnetError>,
+}
+
+impl AuditNotification {
+    pub fn new(
+        operation: AuditOperation,
+        source_device: ObjectIdentifier,
+        target_device: ObjectIdentifier,
+    ) -> Self {
+        Self {
+            operation,
+            source_device,
+            source_object: None,
+            source_comment: None,
+            invoke_id: None,
+            target_device,
+            target_object: None,
+            target_property: None,
+            target_priority: None,
+            target_value: None,
+            result: None,
+        }
+    }
+}
+
+/// log-datum of a BACnetAuditLogRecord (Clause 21)
+#[derive(Clone, Debug, PartialEq)]
+pub enum AuditLogDatum {
+    LogStatus(LogStatus),
+    AuditNotification(Box<AuditNotification>),
+    TimeChange(f32),
+}
+
+/// Audit Log object (12.64)
+///
+/// Records are kept in a circular buffer and are read back with ReadRange,
+/// reading Log_Buffer with ReadProperty is denied.
+#[derive(Clone, Debug)]
+pub struct AuditLog {
+    instance: u32,
+    name: String,
+    pub description: Option<String>,
+    enable: bool,
+    delete_on_forward: bool,
+    buffer: LogBuffer<AuditLogDatum>,
+}
+
+impl AuditLog {
+    pub fn new<S: Into<String>>(instance: u32, name: S, buffer_size: u32) -> Self {
+        Self {
+            instance,
+            name: name.into(),
+            description: None,
+            enable: true,
+            delete_on_forward: false,
+            buffer: LogBuffer::new(buffer_size),
+        }
+    }
+
+    /// Record an audited operation, returns the sequence number of the new
+    /// record or `None` while logging is disabled
+    pub fn log(
+        &mut self,
+        timestamp: BACnetDateTime,
+        notification: AuditNotification,
+    ) -> Option<u32> {
+        if !self.enable {
+            return None;
+        }
+        Some(self.buffer.push(
+            timestamp,
+            AuditLogDatum::AuditNotification(Box::new(notification)),
+        ))
+    }
+
+    /// Record a change of the device clock by `offset` seconds
+    pub fn log_time_change(&mut self, timestamp: BACnetDateTime, offset: f32) -> Option<u32> {
+        if !self.enable {
+            return None;
+        }
+        Some(
+            self.buffer
+                .push(timestamp, AuditLogDatum::TimeChange(offset)),
+        )
+    }
+
+    pub fn enable(&self) -> bool {
+        self.enable
+    }
+
+    /// Enable or disable logging, adding a log-status record on changes
+    pub fn set_enable(&mut self, timestamp: BACnetDateTime, enable: bool) {
+        if self.enable != enable {
+            self.enable = enable;
+            self.buffer.push(
+                timestamp,
+                AuditLogDatum::LogStatus(LogStatus {
+                    log_disabled: !enable,
+                    ..Default::default()
+                }),
+            );
+        }
+    }
+
+    /// Delete all records, leaving a log-status record with buffer-purged set
+    pub fn purge(&mut self, timestamp: BACnetDateTime) {
+        self.buffer.clear();
+        self.buffer.push(
+            timestamp,
+            AuditLogDatum::LogStatus(LogStatus {
+                log_disabled: !self.enable,
+                buffer_purged: true,
+                ..Default::default()
+            }),
+        );
+    }
+
+    pub fn records(&self) -> &LogBuffer<AuditLogDatum> {
+        &self.buffer
+    }
+
+    /// Answer a ReadRange request on Log_Buffer
+    pub fn read_range(&self, range: Option<&Range>) -> LogRange<'_, AuditLogDatum> {
+        self.buffer.read_range(range)
+    }
+}
+
+impl Object for AuditLog {
+    fn object_identifier(&self) -> ObjectIdentifier {
+        ObjectIdentifier::new(ObjectType::AuditLog, self.instance)
+    }
+
+    fn object_name(&self) -> &str {
+        &self.name
+    }
+
+    fn property_list(&self) -> Vec<PropertyIdentifier> {
+        use PropertyIdentifier::*;
+        let mut properties = vec![
+            StatusFlags,
+            EventState,
+            Enable,
+            BufferSize,
+            LogBuffer,
+            RecordCount,
+            TotalRecordCount,
+            DeleteOnForward,
+        ];
+        if self.description.is_some() {
+            properties.push(Description);
+        }
+        properties
+    }
+
+    fn read_property(
+        &self,
+        property: PropertyIdentifier,
+        array_index: Option<u32>,
+    ) -> Result<BACnetValue, BACnetError> {
+        let value = match property {
+            PropertyIdentifier::Description => match &self.description {
+                Some(d) => BACnetValue::CharacterString(d.as_str().into()),
+                None => return Err(BACnetError::property(ErrorCode::UnknownProperty)),
+            },
+            PropertyIdentifier::StatusFlags => BACnetValue::BitString(vec![false; 4].into()),
+            PropertyIdentifier::EventState => BACnetValue::Enumerated(0), // normal
+            PropertyIdentifier::Enable => BACnetValue::Boolean(self.enable),
+            PropertyIdentifier::BufferSize => BACnetValue::Unsigned(self.buffer.buffer_size()),
+            PropertyIdentifier::LogBuffer => {
+                return Err(BACnetError::property(ErrorCode::ReadAccessDenied))
+            }
+            PropertyIdentifier::RecordCount => BACnetValue::Unsigned(self.buffer.len() as u32),
+            PropertyIdentifier::TotalRecordCount => {
+                BACnetValue::Unsigned(self.buffer.total_record_count())
+            }
+            PropertyIdentifier::DeleteOnForward => BACnetValue::Boolean(self.delete_on_forward),
+            _ => return self.read_common_property(property, array_index),
+        };
+        value.array_element(array_index)
+    }
+
+    fn write_property(
+        &mut self,
+        property: PropertyIdentifier,
+        array_index: Option<u32>,
+        value: BACnetValue,
+        _priority: Option<u8>,
+        time: BACnetDateTime,
+    ) -> Result<(), BACnetError> {
+        match property {
+            PropertyIdentifier::Enable | PropertyIdentifier::RecordCount
+                if array_index.is_some() =>
+            {
+                Err(BACnetError::property(ErrorCode::PropertyIsNotAnArray))
+            }
+            PropertyIdentifier::Enable => {
+                let enable = expect_boolean(value)?;
+                self.set_enable(time, enable);
+                Ok(())
+            }
+            // Only zero may be written, which clears the buffer (12.64.11)
+            PropertyIdentifier::RecordCount => match expect_unsigned(value)? {
+                0 => {
+                    self.purge(time);
+                    Ok(())
+                }
+                _ => Err(BACnetError::property(ErrorCode::ValueOutOfRange)),
+            },
+            _ => Err(self.unwritable(property)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::objects::testing::at;
+
+    fn write_notification() -> AuditNotification {
+        let mut notification = AuditNotification::new(
+            AuditOperation::Write,
+            ObjectIdentifier::new(ObjectType::Device, 1),
+            ObjectIdentifier::new(ObjectType::Device, 2),
+        );
+        notification.target_object = Some(ObjectIdentifier::new(ObjectType::AnalogValue, 3));
+        notification.target_property = Some(PropertyIdentifier::Description);
+        notification
+    }
+
+    #[test]
+    fn test_log_and_read_range() {
+        let mut log = AuditLog::new(1, "Audit", 10);
+        assert_eq!(log.log(at(0), write_notification()), Some(1));
+        assert_eq!(log.log(at(1), write_notification()), Some(2));
+
+        let range = log.read_range(Some(&Range::BySequenceNumber {
+            reference_sequence_number: 2,
+            count: 1,
+        }));
+        assert_eq!(range.items.len(), 1);
+        assert_eq!(range.items[0].timestamp, at(1));
+        assert!(matches!(
+            &range.items[0].datum,
+            AuditLogDatum::AuditNotification(n) if n.operation == AuditOperation::Write
+        ));
+    }
+
+    #[test]
+    fn test_disable_logs_status() {
+        let mut log = AuditLog::new(1, "Audit", 10);
+        log.set_enable(at(0), false);
+        assert_eq!(log.log(at(1), write_notification()), None);
+        assert_eq!(log.records().len(), 1);
+        assert!(matches!(
+            log.records().iter().next().unwrap().datum,
+            AuditLogDatum::LogStatus(LogStatus {
+                log_disabled: true,
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn test_read_properties() {
+        let mut log = AuditLog::new(7, "Audit", 10);
+        log.log(at(0), write_notification());
+
+        assert_eq!(
+            log.read_property(PropertyIdentifier::ObjectIdentifier, None),
+            Ok(BACnetValue::ObjectIdentifier(ObjectIdentifier::new(
+                ObjectType::AuditLog,
+                7
+            )))
+        );
+        assert_eq!(
+            log.read_property(PropertyIdentifier::RecordCount, None),
+            Ok(BACnetValue::Unsigned(1))
+        );
+        assert_eq!(
+            log.read_property(PropertyIdentifier::LogBuffer, None),
+            Err(BACnetError::property(ErrorCode::ReadAccessDenied))
+        );
+        assert_eq!(
+            log.read_property(PropertyIdentifier::Description, None),
+            Err(BACnetError::property(ErrorCode::UnknownProperty))
+        );
+        assert_eq!(
+            log.read_property(PropertyIdentifier::PropertyList, Some(0)),
+            Ok(BACnetValue::Unsigned(8))
+        );
+    }
+
+    #[test]
+    fn test_write_record_count_purges() {
+        let mut log = AuditLog::new(1, "Audit", 10);
+        log.log(at(0), write_notification());
+        log.log(at(1), write_notification());
+
+        assert_eq!(
+            log.write_property(
+                PropertyIdentifier::RecordCount,
+                None,
+                BACnetValue::Unsigned(1),
+                None,
+                at(0)
+            ),
+            Err(BACnetError::property(ErrorCode::ValueOutOfRange))
+        );
+        assert_eq!(
+            log.write_property(
+                PropertyIdentifier::RecordCount,
+                Some(1),
+                BACnetValue::Unsigned(0),
+                None,
+                at(0)
+            ),
+            Err(BACnetError::property(ErrorCode::PropertyIsNotAnArray))
+        );
+        assert_eq!(log.records().len(), 2);
+        log.write_property(
+            PropertyIdentifier::RecordCount,
+            None,
+            BACnetValue::Unsigned(0),
+            None,
+            at(0),
+        )
+        .unwrap();
+        assert_eq!(log.records().len(), 1);
+        assert_eq!(log.records().total_record_count(), 3);
+        assert_eq!(
+            log.write_property(
+                PropertyIdentifier::TotalRecordCount,
+                None,
+                BACnetValue::Unsigned(0),
+                None,
+                at(0)
+            ),
+            Err(BACnetError::property(ErrorCode::WriteAccessDenied))
+        );
+    }
+}
@@ -0,0 +1,361 @@
+use crate::application::time::{days_in_month, month_matches};
+use crate::application::{
+    BACnetDate, BACnetDateTime, BACnetError, BACnetValue, ErrorCode, ObjectIdentifier, ObjectType,
+    PropertyIdentifier, UNSPECIFIED,
+};
+use crate::objects::Object;
+
+use std::convert::TryFrom;
+
+fn invalid_data_type() -> BACnetError {
+    BACnetError::property(ErrorCode::InvalidDataType)
+}
+
+/// Order of concrete dates, ignoring the day of week
+fn date_key(date: &BACnetDate) -> (u8, u8, u8) {
+    (date.year, date.month, date.day)
+}
+
+/// Whether a date is within a (inclusive) date range, an unspecified start
+/// or end leaves the range open
+pub(crate) fn in_range(start: &BACnetDate, end: &BACnetDate, date: &BACnetDate) -> bool {
+    let after_start = *start == BACnetDate::ANY || date_key(start) <= date_key(date);
+    let before_end = *end == BACnetDate::ANY || date_key(date) <= date_key(end);
+    after_start && before_end
+}
+
+pub(crate) fn date_range_value(start: BACnetDate, end: BACnetDate) -> BACnetValue {
+    BACnetValue::Array(vec![BACnetValue::Date(start), BACnetValue::Date(end)])
+}
+
+pub(crate) fn date_range(value: BACnetValue) -> Result<(BACnetDate, BACnetDate), BACnetError> {
+    match value {
+        BACnetValue::Array(dates) => match dates.as_slice() {
+            [BACnetValue::Date(start), BACnetValue::Date(end)] => Ok((*start, *end)),
+            _ => Err(invalid_data_type()),
+        },
+        _ => Err(invalid_data_type()),
+    }
+}
+
+/// A date of a context tagged primitive, as decoded from a request or
+/// encoded
+fn context_date(value: BACnetValue) -> Result<BACnetDate, BACnetError> {
+    match value {
+        BACnetValue::Date(date) => Ok(date),
+        BACnetValue::OctetString(octets) => match octets.as_slice() {
+            [year, month, day, weekday] => Ok(BACnetDate {
+                year: *year,
+                month: *month,
+                day: *day,
+                weekday: *weekday,
+            }),
+            _ => Err(invalid_data_type()),
+        },
+        _ => Err(invalid_data_type()),
+    }
+}
+
+/// BACnetCalendarEntry (Clause 21)
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum CalendarEntry {
+    /// A date or a date pattern
+    Date(BACnetDate),
+    /// The dates from the first to the second, inclusive
+    DateRange(BACnetDate, BACnetDate),
+    /// BACnetWeekNDay, [`UNSPECIFIED`] fields match any day
+    WeekNDay {
+        /// 1 to 12, 13 for odd and 14 for even months
+        month: u8,
+        /// 1 for days 1 to 7, ... 5 for days 29 to 31, 6 for the last 7
+        /// days of the month, 7 to 9 for the 7 days before those
+        week_of_month: u8,
+        /// 1 (Monday) to 7 (Sunday)
+        day_of_week: u8,
+    },
+}
+
+impl CalendarEntry {
+    /// Whether the entry includes a concrete date
+    pub fn matches(&self, date: &BACnetDate) -> bool {
+        match self {
+            Self::Date(pattern) => pattern.matches(date),
+            Self::DateRange(start, end) => in_range(start, end, date),
+            Self::WeekNDay {
+                month,
+                week_of_month,
+                day_of_week,
+            } => {
+                let days = days_in_month(date.year().unwrap_or(1900), date.month);
+                let week = match *week_of_month {
+                    UNSPECIFIED => true,
+                    week @ 1..=5 => (date.day - 1) / 7 + 1 == week,
+                    // Counted in weeks from the end of the month
+                    week @ 6..=9 => {
+                        let from_end = days - date.day;
+                        from_end / 7 == week - 6
+                    }
+                    _ => false,
+                };
+                month_matches(*month, date.month)
+                    && week
+                    && (*day_of_week == UNSPECIFIED || *day_of_week == date.weekday)
+            }
+        }
+    }
+}
+
+impl From<CalendarEntry> for BACnetValue {
+    fn from(entry: CalendarEntry) -> Self {
+        let element = match entry {
+            CalendarEntry::Date(date) => (0, BACnetValue::Date(date)),
+            CalendarEntry::DateRange(start, end) => (1, date_range_value(start, end)),
+            CalendarEntry::WeekNDay {
+                month,
+                week_of_month,
+                day_of_week,
+            } => (
+                2,
+                BACnetValue::OctetString(vec![month, week_of_month, day_of_week]),
+            ),
+        };
+        BACnetValue::Constructed(vec![element])
+    }
+}
+
+impl TryFrom<BACnetValue> for CalendarEntry {
+    type Error = BACnetError;
+
+    fn try_from(value: BACnetValue) -> Result<Self, Self::Error> {
+        let element = match value {
+            BACnetValue::Constructed(mut elements) if elements.len() == 1 => elements.remove(0),
+            _ => return Err(invalid_data_type()),
+        };
+        match element {
+            (0, date) => Ok(Self::Date(context_date(date)?)),
+            (1, range) => {
+                let (start, end) = date_range(range)?;
+                Ok(Self::DateRange(start, end))
+            }
+            (2, BACnetValue::OctetString(octets)) if octets.len() == 3 => Ok(Self::WeekNDay {
+                month: octets[0],
+                week_of_month: octets[1],
+                day_of_week: octets[2],
+            }),
+            _ => Err(invalid_data_type()),
+        }
+    }
+}
+
+/// The entries of a Date_List as decoded from a request, where entries that
+/// follow each other in tag order are elements of one constructed value
+fn date_list(value: BACnetValue) -> Result<Vec<CalendarEntry>, BACnetError> {
+    let values = match value {
+        BACnetValue::Array(values) | BACnetValue::List(values) => values,
+        value => vec![value],
+    };
+    let mut entries = Vec::new();
+    for value in values {
+        match value {
+            BACnetValue::Constructed(elements) => {
+                for element in elements {
+                    let entry = BACnetValue::Constructed(vec![element]);
+                    entries.push(CalendarEntry::try_from(entry)?);
+                }
+            }
+            _ => return Err(invalid_data_type()),
+        }
+    }
+    Ok(entries)
+}
+
+/// Calendar object (12.9)
+///
+/// Present_Value tells whether the current date is in the Date_List, it is
+/// updated when the owning runtime calls [`Calendar::evaluate`] with the
+/// local date. Schedules refer to calendars in their exception schedule:
+///
+/// ```
+/// # use bacnet::application::{BACnetDate, BACnetDateTime, BACnetTime, BACnetValue};
+/// # use bacnet::objects::{Calendar, CalendarEntry, Object, Schedule};
+/// let mut holidays = Calendar::new(1, "Holidays");
+/// holidays.date_list.push(CalendarEntry::Date(BACnetDate::new(2024, 12, 25)));
+/// let schedule = Schedule::new(1, "Occupancy", BACnetValue::Enumerated(0));
+///
+/// let now = BACnetDateTime::new(BACnetDate::new(2024, 12, 25), BACnetTime::new(9, 0, 0, 0));
+/// let value = schedule.value_at(&now, |calendar| {
+///     calendar == holidays.object_identifier() && holidays.matches(&now.date)
+/// });
+/// # assert_eq!(value, BACnetValue::Enumerated(0));
+/// ```
+#[derive(Clone, Debug)]
+pub struct Calendar {
+    instance: u32,
+    name: String,
+    present_value: bool,
+    pub date_list: Vec<CalendarEntry>,
+}
+
+impl Calendar {
+    pub fn new<S: Into<String>>(instance: u32, name: S) -> Self {
+        Self {
+            instance,
+            name: name.into(),
+            present_value: false,
+            date_list: Vec::new(),
+        }
+    }
+
+    pub fn present_value(&self) -> bool {
+        self.present_value
+    }
+
+    /// Whether an entry of the date list includes a concrete date
+    pub fn matches(&self, date: &BACnetDate) -> bool {
+        self.date_list.iter().any(|entry| entry.matches(date))
+    }
+
+    /// Update Present_Value for the local date
+    pub fn evaluate(&mut self, date: &BACnetDate) {
+        self.present_value = self.matches(date);
+    }
+}
+
+impl Object for Calendar {
+    fn object_identifier(&self) -> ObjectIdentifier {
+        ObjectIdentifier::new(ObjectType::Calendar, self.instance)
+    }
+
+    fn object_name(&self) -> &str {
+        &self.name
+    }
+
+    fn property_list(&self) -> Vec<PropertyIdentifier> {
+        use PropertyIdentifier::*;
+        vec![PresentValue, DateList]
+    }
+
+    fn read_property(
+        &self,
+        property: PropertyIdentifier,
+        array_index: Option<u32>,
+    ) -> Result<BACnetValue, BACnetError> {
+        let value = match property {
+            PropertyIdentifier::PresentValue => BACnetValue::Boolean(self.present_value),
+            PropertyIdentifier::DateList => BACnetValue::List(
+                self.date_list
+                    .iter()
+                    .copied()
+                    .map(BACnetValue::from)
+                    .collect(),
+            ),
+            _ => return self.read_common_property(property, array_index),
+        };
+        value.array_element(array_index)
+    }
+
+    fn write_property(
+        &mut self,
+        property: PropertyIdentifier,
+        array_index: Option<u32>,
+        value: BACnetValue,
+        _priority: Option<u8>,
+        _time: BACnetDateTime,
+    ) -> Result<(), BACnetError> {
+        match property {
+            PropertyIdentifier::DateList if array_index.is_none() => {
+                self.date_list = date_list(value)?;
+                Ok(())
+            }
+            _ => Err(self.unwritable(property)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::encoding::{encode_application, Reader};
+    use crate::objects::testing::at;
+
+    #[test]
+    fn test_date_patterns() {
+        let entry = |pattern| CalendarEntry::Date(pattern);
+        let last_day = BACnetDate {
+            day: 32,
+            ..BACnetDate::ANY
+        };
+        assert!(entry(last_day).matches(&BACnetDate::new(2024, 2, 29)));
+        assert!(!entry(last_day).matches(&BACnetDate::new(2023, 2, 27)));
+        let even_months = BACnetDate {
+            month: 14,
+            ..BACnetDate::ANY
+        };
+        assert!(entry(even_months).matches(&BACnetDate::new(2024, 2, 1)));
+        assert!(!entry(even_months).matches(&BACnetDate::new(2024, 3, 1)));
+        let first_monday = CalendarEntry::WeekNDay {
+            month: UNSPECIFIED,
+            week_of_month: 1,
+            day_of_week: 1,
+        };
+        assert!(first_monday.matches(&BACnetDate::new(2024, 1, 1)));
+        assert!(!first_monday.matches(&BACnetDate::new(2024, 1, 8)));
+    }
+
+    #[test]
+    fn test_date_range_and_week_n_day() {
+        let mut calendar = Calendar::new(1, "Holidays");
+        calendar.date_list = vec![
+            CalendarEntry::DateRange(BACnetDate::new(2024, 12, 24), BACnetDate::new(2024, 12, 26)),
+            // The last Monday of May
+            CalendarEntry::WeekNDay {
+                month: 5,
+                week_of_month: 6,
+                day_of_week: 1,
+            },
+        ];
+        calendar.evaluate(&BACnetDate::new(2024, 12, 25));
+        assert!(calendar.present_value());
+        assert!(calendar.matches(&BACnetDate::new(2024, 5, 27)));
+        assert!(!calendar.matches(&BACnetDate::new(2024, 5, 20)));
+        calendar.evaluate(&BACnetDate::new(2024, 12, 27));
+        assert_eq!(
+            calendar.read_property(PropertyIdentifier::PresentValue, None),
+            Ok(BACnetValue::Boolean(false))
+        );
+    }
+
+    #[test]
+    fn test_write_date_list() {
+        let mut calendar = Calendar::new(1, "Holidays");
+        let date_list = vec![
+            CalendarEntry::Date(BACnetDate::new(2024, 1, 1)),
+            CalendarEntry::Date(BACnetDate::new(2024, 12, 25)),
+            CalendarEntry::DateRange(BACnetDate::new(2024, 8, 1), BACnetDate::new(2024, 8, 14)),
+            CalendarEntry::WeekNDay {
+                month: UNSPECIFIED,
+                week_of_month: 1,
+                day_of_week: 1,
+            },
+        ];
+        // Encoded and decoded as a WriteProperty request
+        let mut data = Vec::new();
+        let value = BACnetValue::Array(date_list.iter().copied().map(BACnetValue::from).collect());
+        encode_application(&mut data, &value).unwrap();
+        let value = BACnetValue::Array(Reader::new(&data).values_to_end().unwrap());
+        calendar
+            .write_property(PropertyIdentifier::DateList, None, value, None, at(0))
+            .unwrap();
+        assert_eq!(calendar.date_list, date_list);
+        assert_eq!(
+            calendar.write_property(
+                PropertyIdentifier::PresentValue,
+                None,
+                BACnetValue::Boolean(true),
+                None,
+                at(0)
+            ),
+            Err(BACnetError::property(ErrorCode::WriteAccessDenied))
+        );
+    }
+}
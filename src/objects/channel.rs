@@ -0,0 +1,365 @@
+use crate::application::{
+    BACnetDateTime, BACnetError, BACnetValue, DeviceObjectPropertyReference, ErrorCode,
+    ObjectIdentifier, ObjectType, PropertyIdentifier, WriteGroup,
+};
+use crate::objects::{expect_boolean, expect_unsigned, Object, PRIORITIES};
+
+use num_derive::{FromPrimitive, ToPrimitive};
+
+/// BACnetWriteStatus (Clause 21)
+#[derive(Copy, Clone, Debug, Eq, PartialEq, FromPrimitive, ToPrimitive)]
+pub enum WriteStatus {
+    Idle = 0,
+    InProgress = 1,
+    Successful = 2,
+    Failed = 3,
+}
+
+/// A write of the value of a channel or schedule to one of the properties
+/// it refers to
+#[derive(Clone, Debug, PartialEq)]
+pub struct ChannelWrite {
+    pub reference: DeviceObjectPropertyReference,
+    pub value: BACnetValue,
+    pub priority: u8,
+}
+
+/// Channel object (12.53)
+///
+/// The channel does not own its members, writing Present_Value (directly or
+/// through WriteGroup) queues one [`ChannelWrite`] per member. The owner of
+/// the objects collects them with [`Channel::take_writes`], performs them and
+/// reports the outcome with [`Channel::complete_writes`], which is reflected
+/// in Write_Status.
+#[derive(Clone, Debug)]
+pub struct Channel {
+    instance: u32,
+    name: String,
+    present_value: BACnetValue,
+    last_priority: u8,
+    write_status: WriteStatus,
+    pending: Vec<ChannelWrite>,
+    pub channel_number: u16,
+    pub members: Vec<DeviceObjectPropertyReference>,
+    /// Groups this channel listens to in WriteGroup requests, 0 is unused
+    pub control_groups: Vec<u32>,
+    pub allow_group_delay_inhibit: bool,
+    pub out_of_service: bool,
+}
+
+impl Channel {
+    pub fn new<S: Into<String>>(instance: u32, name: S, channel_number: u16) -> Self {
+        Self {
+            instance,
+            name: name.into(),
+            present_value: BACnetValue::Null,
+            last_priority: PRIORITIES as u8,
+            write_status: WriteStatus::Idle,
+            pending: Vec::new(),
+            channel_number,
+            members: Vec::new(),
+            control_groups: Vec::new(),
+            allow_group_delay_inhibit: false,
+            out_of_service: false,
+        }
+    }
+
+    pub fn present_value(&self) -> &BACnetValue {
+        &self.present_value
+    }
+
+    pub fn last_priority(&self) -> u8 {
+        self.last_priority
+    }
+
+    pub fn write_status(&self) -> WriteStatus {
+        self.write_status
+    }
+
+    /// Write the channel value, queueing a write to every member
+    pub fn write(&mut self, value: BACnetValue, priority: u8) -> Result<(), BACnetError> {
+        if !(1..=16).contains(&priority) {
+            return Err(BACnetError::property(ErrorCode::ValueOutOfRange));
+        }
+        self.present_value = value.clone();
+        self.last_priority = priority;
+        if self.out_of_service {
+            return Ok(());
+        }
+        self.pending = self
+            .members
+            .iter()
+            .map(|reference| ChannelWrite {
+                reference: *reference,
+                value: value.clone(),
+                priority,
+            })
+            .collect();
+        self.write_status = match self.pending.is_empty() {
+            true => WriteStatus::Successful,
+            false => WriteStatus::InProgress,
+        };
+        Ok(())
+    }
+
+    /// Apply the changes of a WriteGroup request addressed to this channel
+    ///
+    /// Returns whether the channel was written.
+    pub fn write_group(&mut self, request: &WriteGroup) -> Result<bool, BACnetError> {
+        if request.group_number == 0 || !self.control_groups.contains(&request.group_number) {
+            return Ok(false);
+        }
+        let channel_number = self.channel_number;
+        let mut written = false;
+        for change in request
+            .change_list
+            .iter()
+            .filter(|c| c.channel == channel_number)
+        {
+            let priority = change.overriding_priority.unwrap_or(request.write_priority);
+            self.write(change.value.clone(), priority)?;
+            written = true;
+        }
+        Ok(written)
+    }
+
+    /// Writes queued since the last call
+    pub fn take_writes(&mut self) -> Vec<ChannelWrite> {
+        std::mem::take(&mut self.pending)
+    }
+
+    /// Report whether all member writes succeeded
+    pub fn complete_writes(&mut self, success: bool) {
+        self.write_status = match success {
+            true => WriteStatus::Successful,
+            false => WriteStatus::Failed,
+        };
+    }
+}
+
+impl Object for Channel {
+    fn object_identifier(&self) -> ObjectIdentifier {
+        ObjectIdentifier::new(ObjectType::Channel, self.instance)
+    }
+
+    fn object_name(&self) -> &str {
+        &self.name
+    }
+
+    fn property_list(&self) -> Vec<PropertyIdentifier> {
+        use PropertyIdentifier::*;
+        vec![
+            PresentValue,
+            LastPriority,
+            WriteStatus,
+            StatusFlags,
+            OutOfService,
+            ListOfObjectPropertyReferences,
+            ChannelNumber,
+            ControlGroups,
+            AllowGroupDelayInhibit,
+        ]
+    }
+
+    fn read_property(
+        &self,
+        property: PropertyIdentifier,
+        array_index: Option<u32>,
+    ) -> Result<BACnetValue, BACnetError> {
+        let value = match property {
+            PropertyIdentifier::PresentValue => self.present_value.clone(),
+            PropertyIdentifier::LastPriority => BACnetValue::Unsigned(self.last_priority as u32),
+            PropertyIdentifier::WriteStatus => BACnetValue::Enumerated(self.write_status as u32),
+            PropertyIdentifier::StatusFlags => {
+                let fault = self.write_status == WriteStatus::Failed;
+                BACnetValue::BitString(vec![false, fault, false, self.out_of_service].into())
+            }
+            PropertyIdentifier::OutOfService => BACnetValue::Boolean(self.out_of_service),
+            PropertyIdentifier::ListOfObjectPropertyReferences => {
+                BACnetValue::Array(self.members.iter().map(|m| (*m).into()).collect())
+            }
+            PropertyIdentifier::ChannelNumber => BACnetValue::Unsigned(self.channel_number as u32),
+            PropertyIdentifier::ControlGroups => BACnetValue::Array(
+                self.control_groups
+                    .iter()
+                    .map(|g| BACnetValue::Unsigned(*g))
+                    .collect(),
+            ),
+            PropertyIdentifier::AllowGroupDelayInhibit => {
+                BACnetValue::Boolean(self.allow_group_delay_inhibit)
+            }
+            _ => return self.read_common_property(property, array_index),
+        };
+        value.array_element(array_index)
+    }
+
+    fn write_property(
+        &mut self,
+        property: PropertyIdentifier,
+        array_index: Option<u32>,
+        value: BACnetValue,
+        priority: Option<u8>,
+        _time: BACnetDateTime,
+    ) -> Result<(), BACnetError> {
+        match property {
+            PropertyIdentifier::PresentValue => {
+                self.write(value, priority.unwrap_or(PRIORITIES as u8))
+            }
+            PropertyIdentifier::ChannelNumber => match expect_unsigned(value)? {
+                n if n <= u16::MAX as u32 => {
+                    self.channel_number = n as u16;
+                    Ok(())
+                }
+                _ => Err(BACnetError::property(ErrorCode::ValueOutOfRange)),
+            },
+            PropertyIdentifier::ControlGroups => match array_index {
+                Some(i) if i >= 1 && (i as usize) <= self.control_groups.len() => {
+                    self.control_groups[i as usize - 1] = expect_unsigned(value)?;
+                    Ok(())
+                }
+                Some(_) => Err(BACnetError::property(ErrorCode::InvalidArrayIndex)),
+                None => match value {
+                    BACnetValue::Array(groups) => {
+                        self.control_groups = groups
+                            .into_iter()
+                            .map(expect_unsigned)
+                            .collect::<Result<_, _>>()?;
+                        Ok(())
+                    }
+                    _ => Err(BACnetError::property(ErrorCode::InvalidDataType)),
+                },
+            },
+            PropertyIdentifier::OutOfService => {
+                self.out_of_service = expect_boolean(value)?;
+                Ok(())
+            }
+            PropertyIdentifier::AllowGroupDelayInhibit => {
+                self.allow_group_delay_inhibit = expect_boolean(value)?;
+                Ok(())
+            }
+            _ => Err(self.unwritable(property)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::application::GroupChannelValue;
+    use crate::objects::testing::at;
+    use crate::objects::{LightingCommand, LightingOutput};
+
+    fn light(instance: u32) -> DeviceObjectPropertyReference {
+        DeviceObjectPropertyReference::new(
+            ObjectIdentifier::new(ObjectType::LightingOutput, instance),
+            PropertyIdentifier::LightingCommand,
+        )
+    }
+
+    fn channel() -> Channel {
+        let mut channel = Channel::new(1, "Lights", 7);
+        channel.members = vec![light(1), light(2)];
+        channel.control_groups = vec![3];
+        channel
+    }
+
+    #[test]
+    fn test_write_fans_out() {
+        let mut channel = channel();
+        let command: BACnetValue = LightingCommand::fade_to(60.0, None).into();
+        channel
+            .write_property(
+                PropertyIdentifier::PresentValue,
+                None,
+                command.clone(),
+                Some(9),
+                at(0),
+            )
+            .unwrap();
+
+        assert_eq!(channel.write_status(), WriteStatus::InProgress);
+        let writes = channel.take_writes();
+        assert_eq!(writes.len(), 2);
+        assert_eq!(writes[1].reference, light(2));
+        assert_eq!(writes[1].value, command);
+        assert_eq!(writes[1].priority, 9);
+        assert!(channel.take_writes().is_empty());
+
+        // Deliver to the member objects like a device would
+        let mut outputs = [LightingOutput::new(1, "L1"), LightingOutput::new(2, "L2")];
+        let success = writes.into_iter().all(|w| {
+            outputs[w.reference.object_identifier.instance as usize - 1]
+                .write_property(
+                    w.reference.property_identifier,
+                    w.reference.property_array_index,
+                    w.value,
+                    Some(w.priority),
+                    at(0),
+                )
+                .is_ok()
+        });
+        channel.complete_writes(success);
+        assert_eq!(channel.write_status(), WriteStatus::Successful);
+        assert_eq!(outputs[0].priority_array().get(9), None);
+        assert_eq!(outputs[0].priority_array().get(16), Some(&60.0));
+    }
+
+    #[test]
+    fn test_write_group() {
+        let mut channel = channel();
+        let mut request = WriteGroup {
+            group_number: 3,
+            write_priority: 12,
+            change_list: vec![
+                GroupChannelValue {
+                    channel: 8,
+                    overriding_priority: None,
+                    value: BACnetValue::Real(1.0),
+                },
+                GroupChannelValue {
+                    channel: 7,
+                    overriding_priority: Some(5),
+                    value: BACnetValue::Real(2.0),
+                },
+            ],
+            inhibit_delay: None,
+        };
+
+        assert_eq!(channel.write_group(&request), Ok(true));
+        assert_eq!(channel.present_value(), &BACnetValue::Real(2.0));
+        assert_eq!(channel.last_priority(), 5);
+        assert_eq!(channel.take_writes().len(), 2);
+
+        request.group_number = 4;
+        assert_eq!(channel.write_group(&request), Ok(false));
+        assert!(channel.take_writes().is_empty());
+    }
+
+    #[test]
+    fn test_control_groups_property() {
+        let mut channel = channel();
+        channel
+            .write_property(
+                PropertyIdentifier::ControlGroups,
+                Some(1),
+                BACnetValue::Unsigned(9),
+                None,
+                at(0),
+            )
+            .unwrap();
+        assert_eq!(
+            channel.read_property(PropertyIdentifier::ControlGroups, Some(1)),
+            Ok(BACnetValue::Unsigned(9))
+        );
+        assert_eq!(
+            channel.write_property(
+                PropertyIdentifier::ControlGroups,
+                Some(2),
+                BACnetValue::Unsigned(9),
+                None,
+                at(0),
+            ),
+            Err(BACnetError::property(ErrorCode::InvalidArrayIndex))
+        );
+    }
+}
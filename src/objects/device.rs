@@ -0,0 +1,281 @@
+use crate::application::{
+    BACnetError, BACnetValue, ConfirmedServiceChoice, ObjectIdentifier, ObjectType,
+    ObjectTypesSupported, PropertyIdentifier, Segmentation, ServicesSupported,
+    UnconfirmedServiceChoice, DEFAULT_SEGMENT_TIMEOUT,
+};
+use crate::objects::Object;
+
+use num_derive::{FromPrimitive, ToPrimitive};
+use std::time::Duration;
+
+/// Protocol_Version of the Device object (12.11.18)
+const PROTOCOL_VERSION: u32 = 1;
+
+/// Protocol_Revision of the Device object (12.11.19)
+const PROTOCOL_REVISION: u32 = 22;
+
+/// BACnetDeviceStatus (Clause 21)
+#[derive(Copy, Clone, Debug, Eq, PartialEq, FromPrimitive, ToPrimitive)]
+pub enum DeviceStatus {
+    Operational = 0,
+    OperationalReadOnly = 1,
+    DownloadRequired = 2,
+    DownloadInProgress = 3,
+    NonOperational = 4,
+    BackupInProgress = 5,
+}
+
+/// Device object (12.11)
+///
+/// The device object has the required properties of a device, its
+/// Object_List is the device itself followed by
+/// [`object_list`](Self::object_list). Protocol_Object_Types_Supported are
+/// the types of these objects, Protocol_Services_Supported the services
+/// listed as executed.
+#[derive(Clone, Debug)]
+pub struct DeviceObject {
+    instance: u32,
+    name: String,
+    pub system_status: DeviceStatus,
+    pub vendor_name: String,
+    pub vendor_identifier: u16,
+    pub model_name: String,
+    pub firmware_revision: String,
+    pub application_software_version: String,
+    /// Objects of the device besides the device object itself
+    pub object_list: Vec<ObjectIdentifier>,
+    pub confirmed_services: Vec<ConfirmedServiceChoice>,
+    pub unconfirmed_services: Vec<UnconfirmedServiceChoice>,
+    pub max_apdu_length_accepted: u32,
+    pub segmentation_supported: Segmentation,
+    /// Segments of a request or response accepted, only with segmentation
+    pub max_segments_accepted: u32,
+    pub apdu_timeout: Duration,
+    /// Timeout of segments, only with segmentation
+    pub apdu_segment_timeout: Duration,
+    pub number_of_apdu_retries: u32,
+    /// Incremented when objects are created, deleted or renamed
+    pub database_revision: u32,
+}
+
+impl DeviceObject {
+    /// An operational device accepting APDUs up to 1476 octets, without
+    /// segmentation, that executes ReadProperty and Who-Is
+    pub fn new<S: Into<String>>(instance: u32, name: S, vendor_identifier: u16) -> Self {
+        Self {
+            instance,
+            name: name.into(),
+            system_status: DeviceStatus::Operational,
+            vendor_name: String::new(),
+            vendor_identifier,
+            model_name: String::new(),
+            firmware_revision: String::new(),
+            application_software_version: String::new(),
+            object_list: Vec::new(),
+            confirmed_services: vec![ConfirmedServiceChoice::ReadProperty],
+            unconfirmed_services: vec![UnconfirmedServiceChoice::WhoIs],
+            max_apdu_length_accepted: 1476,
+            segmentation_supported: Segmentation::NoSegmentation,
+            max_segments_accepted: 64,
+            apdu_timeout: Duration::from_secs(3),
+            apdu_segment_timeout: DEFAULT_SEGMENT_TIMEOUT,
+            number_of_apdu_retries: 3,
+            database_revision: 0,
+        }
+    }
+
+    fn segmentation(&self) -> bool {
+        self.segmentation_supported != Segmentation::NoSegmentation
+    }
+
+    fn services_supported(&self) -> ServicesSupported {
+        let mut services = ServicesSupported::new();
+        for service in &self.confirmed_services {
+            services.insert_confirmed(*service);
+        }
+        for service in &self.unconfirmed_services {
+            services.insert_unconfirmed(*service);
+        }
+        services
+    }
+
+    fn object_types_supported(&self) -> ObjectTypesSupported {
+        let mut object_types = ObjectTypesSupported::new();
+        object_types.insert(ObjectType::Device);
+        for object in &self.object_list {
+            object_types.insert(object.object_type);
+        }
+        object_types
+    }
+}
+
+impl Object for DeviceObject {
+    fn object_identifier(&self) -> ObjectIdentifier {
+        ObjectIdentifier::new(ObjectType::Device, self.instance)
+    }
+
+    fn object_name(&self) -> &str {
+        &self.name
+    }
+
+    fn property_list(&self) -> Vec<PropertyIdentifier> {
+        use PropertyIdentifier::*;
+        let mut properties = vec![
+            SystemStatus,
+            VendorName,
+            VendorIdentifier,
+            ModelName,
+            FirmwareRevision,
+            ApplicationSoftwareVersion,
+            ProtocolVersion,
+            ProtocolRevision,
+            ProtocolServicesSupported,
+            ProtocolObjectTypesSupported,
+            ObjectList,
+            MaxApduLengthAccepted,
+            SegmentationSupported,
+            ApduTimeout,
+            NumberOfApduRetries,
+            DeviceAddressBinding,
+            DatabaseRevision,
+        ];
+        if self.segmentation() {
+            properties.extend([MaxSegmentsAccepted, ApduSegmentTimeout]);
+        }
+        properties
+    }
+
+    fn read_property(
+        &self,
+        property: PropertyIdentifier,
+        array_index: Option<u32>,
+    ) -> Result<BACnetValue, BACnetError> {
+        let millis = |duration: Duration| BACnetValue::Unsigned(duration.as_millis() as u32);
+        let value = match property {
+            PropertyIdentifier::SystemStatus => BACnetValue::Enumerated(self.system_status as u32),
+            PropertyIdentifier::VendorName => {
+                BACnetValue::CharacterString(self.vendor_name.as_str().into())
+            }
+            PropertyIdentifier::VendorIdentifier => {
+                BACnetValue::Unsigned(self.vendor_identifier as u32)
+            }
+            PropertyIdentifier::ModelName => {
+                BACnetValue::CharacterString(self.model_name.as_str().into())
+            }
+            PropertyIdentifier::FirmwareRevision => {
+                BACnetValue::CharacterString(self.firmware_revision.as_str().into())
+            }
+            PropertyIdentifier::ApplicationSoftwareVersion => {
+                BACnetValue::CharacterString(self.application_software_version.as_str().into())
+            }
+            PropertyIdentifier::ProtocolVersion => BACnetValue::Unsigned(PROTOCOL_VERSION),
+            PropertyIdentifier::ProtocolRevision => BACnetValue::Unsigned(PROTOCOL_REVISION),
+            PropertyIdentifier::ProtocolServicesSupported => self.services_supported().into(),
+            PropertyIdentifier::ProtocolObjectTypesSupported => {
+                self.object_types_supported().into()
+            }
+            // The Device object is part of its own object list
+            PropertyIdentifier::ObjectList => BACnetValue::Array(
+                std::iter::once(self.object_identifier())
+                    .chain(self.object_list.iter().copied())
+                    .map(BACnetValue::ObjectIdentifier)
+                    .collect(),
+            ),
+            PropertyIdentifier::MaxApduLengthAccepted => {
+                BACnetValue::Unsigned(self.max_apdu_length_accepted)
+            }
+            PropertyIdentifier::SegmentationSupported => {
+                BACnetValue::Enumerated(self.segmentation_supported as u32)
+            }
+            PropertyIdentifier::MaxSegmentsAccepted if self.segmentation() => {
+                BACnetValue::Unsigned(self.max_segments_accepted)
+            }
+            PropertyIdentifier::ApduTimeout => millis(self.apdu_timeout),
+            PropertyIdentifier::ApduSegmentTimeout if self.segmentation() => {
+                millis(self.apdu_segment_timeout)
+            }
+            PropertyIdentifier::NumberOfApduRetries => {
+                BACnetValue::Unsigned(self.number_of_apdu_retries)
+            }
+            // Bindings are not shared with other devices
+            PropertyIdentifier::DeviceAddressBinding => BACnetValue::List(Vec::new()),
+            PropertyIdentifier::DatabaseRevision => BACnetValue::Unsigned(self.database_revision),
+            _ => return self.read_common_property(property, array_index),
+        };
+        value.array_element(array_index)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::application::{BitString, ErrorCode};
+    use crate::objects::testing::at;
+    use std::convert::TryFrom;
+
+    #[test]
+    fn test_device_object() {
+        let mut device = DeviceObject::new(12, "Controller", 15);
+        device.object_list = vec![
+            ObjectIdentifier::new(ObjectType::AnalogInput, 1),
+            ObjectIdentifier::new(ObjectType::LightingOutput, 2),
+        ];
+        device
+            .confirmed_services
+            .push(ConfirmedServiceChoice::SubscribeCovProperty);
+        device
+            .unconfirmed_services
+            .push(UnconfirmedServiceChoice::UtcTimeSynchronization);
+
+        let read = |property| device.read_property(property, None).unwrap();
+        assert_eq!(
+            device.read_property(PropertyIdentifier::ObjectList, Some(0)),
+            Ok(BACnetValue::Unsigned(3))
+        );
+        assert_eq!(
+            read(PropertyIdentifier::ApduTimeout),
+            BACnetValue::Unsigned(3000)
+        );
+        assert_eq!(
+            read(PropertyIdentifier::SystemStatus),
+            BACnetValue::Enumerated(DeviceStatus::Operational as u32)
+        );
+        let bits = |value| {
+            BitString::try_from(value)
+                .unwrap()
+                .ones()
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            bits(read(PropertyIdentifier::ProtocolServicesSupported)),
+            [12, 34, 36, 38]
+        );
+        assert_eq!(
+            bits(read(PropertyIdentifier::ProtocolObjectTypesSupported)),
+            [0, 8, 54]
+        );
+
+        // Segmentation properties only with segmentation
+        let segment_timeout = PropertyIdentifier::ApduSegmentTimeout;
+        assert!(!device.has_property(segment_timeout));
+        assert_eq!(
+            device.read_property(segment_timeout, None),
+            Err(BACnetError::property(ErrorCode::UnknownProperty))
+        );
+        device.segmentation_supported = Segmentation::SegmentedBoth;
+        assert_eq!(
+            device.read_property(segment_timeout, None),
+            Ok(BACnetValue::Unsigned(2000))
+        );
+        assert_eq!(
+            device.write_property(
+                PropertyIdentifier::ModelName,
+                None,
+                BACnetValue::Null,
+                None,
+                at(0)
+            ),
+            Err(BACnetError::property(ErrorCode::WriteAccessDenied))
+        );
+    }
+}
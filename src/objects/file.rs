@@ -0,0 +1,571 @@
+use crate::application::{
+    AtomicReadFileAck, AtomicWriteFileAck, BACnetDate, BACnetDateTime, BACnetError, BACnetTime,
+    BACnetValue, ErrorClass, ErrorCode, FileData, FileReadAccess, ObjectIdentifier, ObjectType,
+    PropertyIdentifier, UNSPECIFIED,
+};
+use crate::objects::{expect_boolean, expect_unsigned, Object};
+
+use num_derive::{FromPrimitive, ToPrimitive};
+use std::io::{Read, Seek, SeekFrom, Write};
+
+/// BACnetFileAccessMethod (Clause 21)
+#[derive(Copy, Clone, Debug, Eq, PartialEq, FromPrimitive, ToPrimitive)]
+pub enum FileAccessMethod {
+    RecordAccess = 0,
+    StreamAccess = 1,
+}
+
+/// Where the contents of a File object are kept
+#[derive(Debug)]
+pub enum FileStorage {
+    Memory(Vec<u8>),
+    File(std::fs::File),
+}
+
+impl From<Vec<u8>> for FileStorage {
+    fn from(contents: Vec<u8>) -> Self {
+        FileStorage::Memory(contents)
+    }
+}
+
+impl From<std::fs::File> for FileStorage {
+    fn from(file: std::fs::File) -> Self {
+        FileStorage::File(file)
+    }
+}
+
+fn file_error(error_code: ErrorCode) -> BACnetError {
+    BACnetError::new(ErrorClass::Services, error_code)
+}
+
+fn access_denied(_: std::io::Error) -> BACnetError {
+    file_error(ErrorCode::FileAccessDenied)
+}
+
+impl FileStorage {
+    fn size(&self) -> std::io::Result<u64> {
+        match self {
+            FileStorage::Memory(contents) => Ok(contents.len() as u64),
+            FileStorage::File(file) => Ok(file.metadata()?.len()),
+        }
+    }
+
+    fn read_at(&self, position: u64, count: u64) -> std::io::Result<Vec<u8>> {
+        match self {
+            FileStorage::Memory(contents) => {
+                let start = (position as usize).min(contents.len());
+                let end = (start as u64 + count).min(contents.len() as u64) as usize;
+                Ok(contents[start..end].to_vec())
+            }
+            FileStorage::File(file) => {
+                let mut file: &std::fs::File = file;
+                let mut octets = Vec::new();
+                file.seek(SeekFrom::Start(position))?;
+                file.take(count).read_to_end(&mut octets)?;
+                Ok(octets)
+            }
+        }
+    }
+
+    fn write_at(&mut self, position: u64, octets: &[u8]) -> std::io::Result<()> {
+        match self {
+            FileStorage::Memory(contents) => {
+                let start = position as usize;
+                let end = start + octets.len();
+                if contents.len() < end {
+                    contents.resize(end, 0);
+                }
+                contents[start..end].copy_from_slice(octets);
+                Ok(())
+            }
+            FileStorage::File(file) => {
+                file.seek(SeekFrom::Start(position))?;
+                file.write_all(octets)?;
+                file.flush()
+            }
+        }
+    }
+
+    fn set_size(&mut self, size: u64) -> std::io::Result<()> {
+        match self {
+            FileStorage::Memory(contents) => {
+                contents.resize(size as usize, 0);
+                Ok(())
+            }
+            FileStorage::File(file) => file.set_len(size),
+        }
+    }
+
+    /// The records of the file, its lines without the line feeds ending
+    /// them
+    fn records(&self) -> std::io::Result<Vec<Vec<u8>>> {
+        let contents = self.read_at(0, self.size()?)?;
+        Ok(contents
+            .split_inclusive(|b| *b == b'\n')
+            .map(|line| line.strip_suffix(b"\n").unwrap_or(line).to_vec())
+            .collect())
+    }
+
+    fn set_records(&mut self, records: &[Vec<u8>]) -> std::io::Result<()> {
+        let contents: Vec<u8> = records
+            .iter()
+            .flat_map(|record| record.iter().chain(b"\n"))
+            .copied()
+            .collect();
+        self.write_at(0, &contents)?;
+        self.set_size(contents.len() as u64)
+    }
+}
+
+/// File object (12.13)
+///
+/// Its contents are read and written with AtomicReadFile and
+/// AtomicWriteFile, kept in memory or in a file of the local file system.
+/// With record access the records are the lines of the file, written
+/// records are ended with a line feed.
+///
+/// ```
+/// use bacnet::application::{FileData, FileReadAccess};
+/// use bacnet::objects::{FileAccessMethod, FileObject, Object};
+///
+/// let config = b"setpoint=21\n".to_vec();
+/// let file = FileObject::new(1, "config", FileAccessMethod::StreamAccess, config);
+/// let access = FileReadAccess::Stream {
+///     file_start_position: 9,
+///     requested_octet_count: 10,
+/// };
+/// let ack = file.read_file(&access).unwrap();
+/// assert!(ack.end_of_file);
+/// assert_eq!(
+///     ack.data,
+///     FileData::Stream {
+///         file_start_position: 9,
+///         file_data: b"21\n".to_vec()
+///     }
+/// );
+/// ```
+#[derive(Debug)]
+pub struct FileObject {
+    instance: u32,
+    name: String,
+    pub file_type: String,
+    access_method: FileAccessMethod,
+    storage: FileStorage,
+    /// Whether the file has been archived, cleared when it is modified
+    pub archive: bool,
+    pub read_only: bool,
+    pub modification_date: BACnetDateTime,
+}
+
+impl FileObject {
+    /// A writable file of an unspecified type, modified at an unspecified
+    /// time unless it is in the file system
+    pub fn new<S: Into<String>, T: Into<FileStorage>>(
+        instance: u32,
+        name: S,
+        access_method: FileAccessMethod,
+        storage: T,
+    ) -> Self {
+        let storage = storage.into();
+        let modification_date = match &storage {
+            FileStorage::File(file) => file
+                .metadata()
+                .and_then(|metadata| metadata.modified())
+                .ok()
+                .map(BACnetDateTime::from),
+            FileStorage::Memory(_) => None,
+        };
+        let unspecified = BACnetDateTime::new(
+            BACnetDate {
+                year: UNSPECIFIED,
+                month: UNSPECIFIED,
+                day: UNSPECIFIED,
+                weekday: UNSPECIFIED,
+            },
+            BACnetTime::new(UNSPECIFIED, UNSPECIFIED, UNSPECIFIED, UNSPECIFIED),
+        );
+        Self {
+            instance,
+            name: name.into(),
+            file_type: String::new(),
+            access_method,
+            storage,
+            archive: false,
+            read_only: false,
+            modification_date: modification_date.unwrap_or(unspecified),
+        }
+    }
+
+    pub fn access_method(&self) -> FileAccessMethod {
+        self.access_method
+    }
+
+    pub fn storage(&self) -> &FileStorage {
+        &self.storage
+    }
+
+    fn modified(&mut self, time: BACnetDateTime) {
+        self.modification_date = time;
+        self.archive = false;
+    }
+}
+
+/// The position to start at, which must be within the file or, if
+/// `append` is allowed, -1 for its end
+fn start_position(start: i32, end: u64, append: bool) -> Result<u64, BACnetError> {
+    match start {
+        -1 if append => Ok(end),
+        start if start >= 0 && start as u64 <= end => Ok(start as u64),
+        _ => Err(file_error(ErrorCode::InvalidFileStartPosition)),
+    }
+}
+
+impl Object for FileObject {
+    fn object_identifier(&self) -> ObjectIdentifier {
+        ObjectIdentifier::new(ObjectType::File, self.instance)
+    }
+
+    fn object_name(&self) -> &str {
+        &self.name
+    }
+
+    fn property_list(&self) -> Vec<PropertyIdentifier> {
+        use PropertyIdentifier::*;
+        let mut properties = vec![
+            FileType,
+            FileSize,
+            ModificationDate,
+            Archive,
+            ReadOnly,
+            FileAccessMethod,
+        ];
+        if self.access_method == self::FileAccessMethod::RecordAccess {
+            properties.push(RecordCount);
+        }
+        properties
+    }
+
+    fn read_property(
+        &self,
+        property: PropertyIdentifier,
+        array_index: Option<u32>,
+    ) -> Result<BACnetValue, BACnetError> {
+        let value = match property {
+            PropertyIdentifier::FileType => {
+                BACnetValue::CharacterString(self.file_type.as_str().into())
+            }
+            PropertyIdentifier::FileSize => {
+                let size = self.storage.size().map_err(access_denied)?;
+                BACnetValue::Unsigned(size as u32)
+            }
+            PropertyIdentifier::ModificationDate => BACnetValue::Array(vec![
+                BACnetValue::Date(self.modification_date.date),
+                BACnetValue::Time(self.modification_date.time),
+            ]),
+            PropertyIdentifier::Archive => BACnetValue::Boolean(self.archive),
+            PropertyIdentifier::ReadOnly => BACnetValue::Boolean(self.read_only),
+            PropertyIdentifier::FileAccessMethod => {
+                BACnetValue::Enumerated(self.access_method as u32)
+            }
+            PropertyIdentifier::RecordCount
+                if self.access_method == FileAccessMethod::RecordAccess =>
+            {
+                let records = self.storage.records().map_err(access_denied)?;
+                BACnetValue::Unsigned(records.len() as u32)
+            }
+            _ => return self.read_common_property(property, array_index),
+        };
+        value.array_element(array_index)
+    }
+
+    fn write_property(
+        &mut self,
+        property: PropertyIdentifier,
+        _array_index: Option<u32>,
+        value: BACnetValue,
+        _priority: Option<u8>,
+        _time: BACnetDateTime,
+    ) -> Result<(), BACnetError> {
+        match property {
+            PropertyIdentifier::Archive => {
+                self.archive = expect_boolean(value)?;
+                Ok(())
+            }
+            // Truncating or extending a stream, emptying it with zero
+            // (12.13.6)
+            PropertyIdentifier::FileSize
+                if !self.read_only && self.access_method == FileAccessMethod::StreamAccess =>
+            {
+                let size = expect_unsigned(value)?;
+                self.storage
+                    .set_size(size as u64)
+                    .map_err(|_| BACnetError::property(ErrorCode::WriteAccessDenied))
+            }
+            _ => Err(self.unwritable(property)),
+        }
+    }
+
+    fn read_file(&self, access: &FileReadAccess) -> Result<AtomicReadFileAck, BACnetError> {
+        match (access, self.access_method) {
+            (
+                FileReadAccess::Stream {
+                    file_start_position,
+                    requested_octet_count,
+                },
+                FileAccessMethod::StreamAccess,
+            ) => {
+                let size = self.storage.size().map_err(access_denied)?;
+                let start = start_position(*file_start_position, size, false)?;
+                let file_data = self
+                    .storage
+                    .read_at(start, *requested_octet_count as u64)
+                    .map_err(access_denied)?;
+                Ok(AtomicReadFileAck {
+                    end_of_file: start + file_data.len() as u64 >= size,
+                    data: FileData::Stream {
+                        file_start_position: *file_start_position,
+                        file_data,
+                    },
+                })
+            }
+            (
+                FileReadAccess::Record {
+                    file_start_record,
+                    requested_record_count,
+                },
+                FileAccessMethod::RecordAccess,
+            ) => {
+                let records = self.storage.records().map_err(access_denied)?;
+                let count = records.len();
+                let start = start_position(*file_start_record, count as u64, false)?;
+                let file_record_data: Vec<_> = records
+                    .into_iter()
+                    .skip(start as usize)
+                    .take(*requested_record_count as usize)
+                    .collect();
+                let end = start as usize + file_record_data.len();
+                Ok(AtomicReadFileAck {
+                    end_of_file: end >= count,
+                    data: FileData::Record {
+                        file_start_record: *file_start_record,
+                        file_record_data,
+                    },
+                })
+            }
+            _ => Err(file_error(ErrorCode::InvalidFileAccessMethod)),
+        }
+    }
+
+    fn write_file(
+        &mut self,
+        data: FileData,
+        time: BACnetDateTime,
+    ) -> Result<AtomicWriteFileAck, BACnetError> {
+        if self.read_only {
+            return Err(file_error(ErrorCode::FileAccessDenied));
+        }
+        let ack = match (data, self.access_method) {
+            (
+                FileData::Stream {
+                    file_start_position,
+                    file_data,
+                },
+                FileAccessMethod::StreamAccess,
+            ) => {
+                let size = self.storage.size().map_err(access_denied)?;
+                let start = start_position(file_start_position, size, true)?;
+                self.storage
+                    .write_at(start, &file_data)
+                    .map_err(access_denied)?;
+                AtomicWriteFileAck::FileStartPosition(start as i32)
+            }
+            (
+                FileData::Record {
+                    file_start_record,
+                    file_record_data,
+                },
+                FileAccessMethod::RecordAccess,
+            ) => {
+                let mut records = self.storage.records().map_err(access_denied)?;
+                let start = start_position(file_start_record, records.len() as u64, true)?;
+                let start = start as usize;
+                let end = (start + file_record_data.len()).min(records.len());
+                records.splice(start..end, file_record_data);
+                self.storage.set_records(&records).map_err(access_denied)?;
+                AtomicWriteFileAck::FileStartRecord(start as i32)
+            }
+            _ => return Err(file_error(ErrorCode::InvalidFileAccessMethod)),
+        };
+        self.modified(time);
+        Ok(ack)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn time() -> BACnetDateTime {
+        BACnetDateTime::new(BACnetDate::new(2021, 1, 25), BACnetTime::new(12, 0, 0, 0))
+    }
+
+    #[test]
+    fn test_stream_access() {
+        let mut file = FileObject::new(1, "firmware", FileAccessMethod::StreamAccess, vec![]);
+        file.archive = true;
+        let write = |file: &mut FileObject, start, octets: &[u8]| {
+            let data = FileData::Stream {
+                file_start_position: start,
+                file_data: octets.to_vec(),
+            };
+            file.write_file(data, time())
+        };
+        assert_eq!(
+            write(&mut file, 0, b"abcd"),
+            Ok(AtomicWriteFileAck::FileStartPosition(0))
+        );
+        // Appending
+        assert_eq!(
+            write(&mut file, -1, b"efg"),
+            Ok(AtomicWriteFileAck::FileStartPosition(4))
+        );
+        assert_eq!(
+            write(&mut file, 2, b"CD"),
+            Ok(AtomicWriteFileAck::FileStartPosition(2))
+        );
+        assert_eq!(
+            write(&mut file, 8, b"h"),
+            Err(file_error(ErrorCode::InvalidFileStartPosition))
+        );
+        assert!(!file.archive);
+        assert_eq!(file.modification_date, time());
+        assert_eq!(
+            file.read_property(PropertyIdentifier::FileSize, None),
+            Ok(BACnetValue::Unsigned(7))
+        );
+
+        let read = |file: &FileObject, start, count| {
+            file.read_file(&FileReadAccess::Stream {
+                file_start_position: start,
+                requested_octet_count: count,
+            })
+            .map(|ack| match ack.data {
+                FileData::Stream { file_data, .. } => (ack.end_of_file, file_data),
+                _ => panic!("Record data of a stream"),
+            })
+        };
+        assert_eq!(read(&file, 0, 4), Ok((false, b"abCD".to_vec())));
+        assert_eq!(read(&file, 4, 10), Ok((true, b"efg".to_vec())));
+        assert_eq!(read(&file, 7, 10), Ok((true, vec![])));
+        assert_eq!(
+            read(&file, -1, 10),
+            Err(file_error(ErrorCode::InvalidFileStartPosition))
+        );
+        let records = FileReadAccess::Record {
+            file_start_record: 0,
+            requested_record_count: 1,
+        };
+        assert_eq!(
+            file.read_file(&records),
+            Err(file_error(ErrorCode::InvalidFileAccessMethod))
+        );
+
+        file.write_property(
+            PropertyIdentifier::FileSize,
+            None,
+            BACnetValue::Unsigned(0),
+            None,
+            time(),
+        )
+        .unwrap();
+        assert_eq!(read(&file, 0, 4), Ok((true, vec![])));
+
+        file.read_only = true;
+        assert_eq!(
+            write(&mut file, 0, b"abcd"),
+            Err(file_error(ErrorCode::FileAccessDenied))
+        );
+    }
+
+    #[test]
+    fn test_record_access() {
+        let contents = b"12:00,45.6\n12:15,44.8\n".to_vec();
+        let mut file = FileObject::new(2, "log", FileAccessMethod::RecordAccess, contents);
+        assert_eq!(
+            file.read_property(PropertyIdentifier::RecordCount, None),
+            Ok(BACnetValue::Unsigned(2))
+        );
+        let records = |records: &[&[u8]]| records.iter().map(|r| r.to_vec()).collect();
+        let data = FileData::Record {
+            file_start_record: 1,
+            file_record_data: records(&[b"12:15,44.9", b"12:30,44.1"]),
+        };
+        assert_eq!(
+            file.write_file(data, time()),
+            Ok(AtomicWriteFileAck::FileStartRecord(1))
+        );
+        let data = FileData::Record {
+            file_start_record: -1,
+            file_record_data: records(&[b"12:45,43.7"]),
+        };
+        assert_eq!(
+            file.write_file(data, time()),
+            Ok(AtomicWriteFileAck::FileStartRecord(3))
+        );
+        match file.storage() {
+            FileStorage::Memory(contents) => assert_eq!(
+                contents,
+                b"12:00,45.6\n12:15,44.9\n12:30,44.1\n12:45,43.7\n"
+            ),
+            FileStorage::File(_) => unreachable!(),
+        }
+
+        let access = FileReadAccess::Record {
+            file_start_record: 1,
+            requested_record_count: 2,
+        };
+        assert_eq!(
+            file.read_file(&access),
+            Ok(AtomicReadFileAck {
+                end_of_file: false,
+                data: FileData::Record {
+                    file_start_record: 1,
+                    file_record_data: records(&[b"12:15,44.9", b"12:30,44.1"]),
+                },
+            })
+        );
+    }
+
+    #[test]
+    fn test_file_storage() {
+        let path = std::env::temp_dir().join(format!("bacnet-file-{}", std::process::id()));
+        let storage = std::fs::OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(&path)
+            .unwrap();
+        let mut file = FileObject::new(3, "config", FileAccessMethod::StreamAccess, storage);
+        let data = FileData::Stream {
+            file_start_position: 0,
+            file_data: b"setpoint=21\n".to_vec(),
+        };
+        file.write_file(data, time()).unwrap();
+        let access = FileReadAccess::Stream {
+            file_start_position: 9,
+            requested_octet_count: 2,
+        };
+        let ack = file.read_file(&access).unwrap();
+        assert_eq!(
+            ack.data,
+            FileData::Stream {
+                file_start_position: 9,
+                file_data: b"21".to_vec()
+            }
+        );
+        assert!(!ack.end_of_file);
+        assert_eq!(std::fs::read(&path).unwrap(), b"setpoint=21\n");
+        std::fs::remove_file(&path).unwrap();
+    }
+}
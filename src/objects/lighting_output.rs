@@ -0,0 +1,709 @@
+use crate::application::{
+    BACnetDateTime, BACnetError, BACnetValue, ErrorCode, ObjectIdentifier, ObjectType,
+    PropertyIdentifier,
+};
+use crate::objects::{
+    expect_boolean, expect_real, expect_unsigned, Object, PriorityArray, PRIORITIES,
+};
+
+use num_derive::{FromPrimitive, ToPrimitive};
+use num_traits::FromPrimitive;
+use std::convert::TryFrom;
+use std::time::Duration;
+
+/// BACnetLightingOperation (Clause 21)
+#[derive(Copy, Clone, Debug, Eq, PartialEq, FromPrimitive, ToPrimitive)]
+pub enum LightingOperation {
+    None = 0,
+    FadeTo = 1,
+    RampTo = 2,
+    StepUp = 3,
+    StepDown = 4,
+    StepOn = 5,
+    StepOff = 6,
+    Warn = 7,
+    WarnOff = 8,
+    WarnRelinquish = 9,
+    Stop = 10,
+}
+
+/// BACnetLightingInProgress (Clause 21)
+#[derive(Copy, Clone, Debug, Eq, PartialEq, FromPrimitive, ToPrimitive)]
+pub enum LightingInProgress {
+    Idle = 0,
+    FadeActive = 1,
+    RampActive = 2,
+    NotControlled = 3,
+    Other = 4,
+}
+
+/// BACnetLightingCommand (Clause 21)
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct LightingCommand {
+    pub operation: LightingOperation,
+    /// Level in percent
+    pub target_level: Option<f32>,
+    /// Percent per second
+    pub ramp_rate: Option<f32>,
+    /// Percent
+    pub step_increment: Option<f32>,
+    /// Milliseconds
+    pub fade_time: Option<u32>,
+    pub priority: Option<u8>,
+}
+
+impl LightingCommand {
+    pub fn new(operation: LightingOperation) -> Self {
+        Self {
+            operation,
+            target_level: None,
+            ramp_rate: None,
+            step_increment: None,
+            fade_time: None,
+            priority: None,
+        }
+    }
+
+    pub fn fade_to(target_level: f32, fade_time: Option<u32>) -> Self {
+        Self {
+            target_level: Some(target_level),
+            fade_time,
+            ..Self::new(LightingOperation::FadeTo)
+        }
+    }
+
+    pub fn ramp_to(target_level: f32, ramp_rate: Option<f32>) -> Self {
+        Self {
+            target_level: Some(target_level),
+            ramp_rate,
+            ..Self::new(LightingOperation::RampTo)
+        }
+    }
+
+    pub fn with_priority(self, priority: u8) -> Self {
+        Self {
+            priority: Some(priority),
+            ..self
+        }
+    }
+
+    /// Check the parameter ranges given in Clause 21
+    fn validate(&self) -> Result<(), BACnetError> {
+        let out_of_range = || BACnetError::property(ErrorCode::ValueOutOfRange);
+        let in_range = |v: Option<f32>, min: f32| v.is_none_or(|v| (min..=100.0).contains(&v));
+        if !in_range(self.target_level, 0.0)
+            || !in_range(self.ramp_rate, 0.1)
+            || !in_range(self.step_increment, 0.1)
+            || !self
+                .fade_time
+                .is_none_or(|t| (100..=86_400_000).contains(&t))
+            || !self.priority.is_none_or(|p| (1..=16).contains(&p))
+        {
+            return Err(out_of_range());
+        }
+        match self.operation {
+            LightingOperation::FadeTo | LightingOperation::RampTo
+                if self.target_level.is_none() =>
+            {
+                Err(out_of_range())
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+impl From<LightingCommand> for BACnetValue {
+    fn from(command: LightingCommand) -> Self {
+        let mut elements = vec![(0, BACnetValue::Enumerated(command.operation as u32))];
+        if let Some(level) = command.target_level {
+            elements.push((1, BACnetValue::Real(level)));
+        }
+        if let Some(rate) = command.ramp_rate {
+            elements.push((2, BACnetValue::Real(rate)));
+        }
+        if let Some(increment) = command.step_increment {
+            elements.push((3, BACnetValue::Real(increment)));
+        }
+        if let Some(time) = command.fade_time {
+            elements.push((4, BACnetValue::Unsigned(time)));
+        }
+        if let Some(priority) = command.priority {
+            elements.push((5, BACnetValue::Unsigned(priority as u32)));
+        }
+        BACnetValue::Constructed(elements)
+    }
+}
+
+impl TryFrom<BACnetValue> for LightingCommand {
+    type Error = BACnetError;
+
+    fn try_from(value: BACnetValue) -> Result<Self, Self::Error> {
+        let invalid = || BACnetError::property(ErrorCode::InvalidDataType);
+        let real = |tag| match value.context(tag) {
+            None => Ok(None),
+            Some(BACnetValue::Real(r)) => Ok(Some(*r)),
+            Some(_) => Err(invalid()),
+        };
+        let unsigned = |tag| match value.context(tag) {
+            None => Ok(None),
+            Some(BACnetValue::Unsigned(u)) => Ok(Some(*u)),
+            Some(_) => Err(invalid()),
+        };
+        let operation = match value.context(0) {
+            Some(BACnetValue::Enumerated(e)) => LightingOperation::from_u32(*e)
+                .ok_or_else(|| BACnetError::property(ErrorCode::ValueOutOfRange))?,
+            _ => return Err(invalid()),
+        };
+        Ok(Self {
+            operation,
+            target_level: real(1)?,
+            ramp_rate: real(2)?,
+            step_increment: real(3)?,
+            fade_time: unsigned(4)?,
+            priority: unsigned(5)?.map(|p| p.min(u8::MAX as u32) as u8),
+        })
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+enum Transition {
+    Fade {
+        from: f32,
+        to: f32,
+        duration: Duration,
+        elapsed: Duration,
+    },
+    Ramp {
+        to: f32,
+        rate: f32,
+    },
+}
+
+/// Pending end of a WARN_OFF or WARN_RELINQUISH egress period
+#[derive(Copy, Clone, Debug, PartialEq)]
+struct Egress {
+    remaining: Duration,
+    priority: u8,
+    relinquish: bool,
+}
+
+/// Lighting Output object (12.54)
+///
+/// Present_Value is the commanded level, Tracking_Value the actual level of
+/// the output. Fades, ramps and egress delays progress only when the owning
+/// runtime calls [`LightingOutput::advance`] from its timer.
+#[derive(Clone, Debug)]
+pub struct LightingOutput {
+    instance: u32,
+    name: String,
+    priority_array: PriorityArray<f32>,
+    relinquish_default: f32,
+    tracking_value: f32,
+    lighting_command: LightingCommand,
+    in_progress: LightingInProgress,
+    transition: Option<Transition>,
+    egress: Option<Egress>,
+    pub out_of_service: bool,
+    pub blink_warn_enable: bool,
+    /// Seconds
+    pub egress_time: u32,
+    /// Milliseconds
+    pub default_fade_time: u32,
+    /// Percent per second
+    pub default_ramp_rate: f32,
+    /// Percent
+    pub default_step_increment: f32,
+    pub default_priority: u8,
+}
+
+impl LightingOutput {
+    pub fn new<S: Into<String>>(instance: u32, name: S) -> Self {
+        Self {
+            instance,
+            name: name.into(),
+            priority_array: PriorityArray::new(),
+            relinquish_default: 0.0,
+            tracking_value: 0.0,
+            lighting_command: LightingCommand::new(LightingOperation::None),
+            in_progress: LightingInProgress::Idle,
+            transition: None,
+            egress: None,
+            out_of_service: false,
+            blink_warn_enable: false,
+            egress_time: 0,
+            default_fade_time: 100,
+            default_ramp_rate: 100.0,
+            default_step_increment: 1.0,
+            default_priority: PRIORITIES as u8,
+        }
+    }
+
+    pub fn present_value(&self) -> f32 {
+        self.priority_array
+            .effective()
+            .map(|(_, v)| *v)
+            .unwrap_or(self.relinquish_default)
+    }
+
+    pub fn tracking_value(&self) -> f32 {
+        self.tracking_value
+    }
+
+    pub fn in_progress(&self) -> LightingInProgress {
+        self.in_progress
+    }
+
+    pub fn egress_active(&self) -> bool {
+        self.egress.is_some()
+    }
+
+    pub fn priority_array(&self) -> &PriorityArray<f32> {
+        &self.priority_array
+    }
+
+    /// Command Present_Value at a priority, the output follows immediately
+    pub fn command(&mut self, priority: u8, value: Option<f32>) -> Result<(), BACnetError> {
+        if value.is_some_and(|v| !(0.0..=100.0).contains(&v)) {
+            return Err(BACnetError::property(ErrorCode::ValueOutOfRange));
+        }
+        self.priority_array.set(priority, value)?;
+        self.settle();
+        Ok(())
+    }
+
+    /// Execute a lighting command as if written to Lighting_Command
+    pub fn execute(&mut self, command: LightingCommand) -> Result<(), BACnetError> {
+        command.validate()?;
+        let priority = command.priority.unwrap_or(self.default_priority);
+        let level = self.present_value();
+        let increment = command
+            .step_increment
+            .unwrap_or(self.default_step_increment);
+
+        match command.operation {
+            LightingOperation::None | LightingOperation::Warn => {}
+            LightingOperation::FadeTo | LightingOperation::RampTo => {
+                let target = command.target_level.unwrap_or(level);
+                self.priority_array.set(priority, Some(target))?;
+                if self.effective_priority() == Some(priority) {
+                    self.transition = Some(match command.operation {
+                        LightingOperation::FadeTo => Transition::Fade {
+                            from: self.tracking_value,
+                            to: target,
+                            duration: Duration::from_millis(
+                                command.fade_time.unwrap_or(self.default_fade_time) as u64,
+                            ),
+                            elapsed: Duration::default(),
+                        },
+                        _ => Transition::Ramp {
+                            to: target,
+                            rate: command.ramp_rate.unwrap_or(self.default_ramp_rate),
+                        },
+                    });
+                    self.advance(Duration::default());
+                }
+            }
+            // Stepping up or down does not turn on an output that is off,
+            // and stepping down does not turn it off
+            LightingOperation::StepUp | LightingOperation::StepDown if level == 0.0 => {}
+            LightingOperation::StepUp | LightingOperation::StepOn => {
+                self.command(priority, Some((level + increment).min(100.0)))?
+            }
+            LightingOperation::StepDown => {
+                self.command(priority, Some((level - increment).max(1.0)))?
+            }
+            LightingOperation::StepOff => {
+                self.command(priority, Some((level - increment).max(0.0)))?
+            }
+            LightingOperation::WarnOff | LightingOperation::WarnRelinquish => {
+                self.egress = Some(Egress {
+                    remaining: Duration::from_secs(self.egress_time as u64),
+                    priority,
+                    relinquish: command.operation == LightingOperation::WarnRelinquish,
+                });
+                self.advance(Duration::default());
+            }
+            LightingOperation::Stop => {
+                if self.transition.take().is_some() {
+                    self.priority_array
+                        .set(priority, Some(self.tracking_value))?;
+                    self.in_progress = LightingInProgress::Idle;
+                }
+            }
+        }
+        self.lighting_command = command;
+        Ok(())
+    }
+
+    /// Progress fades, ramps and egress timers by `elapsed`
+    pub fn advance(&mut self, elapsed: Duration) {
+        if let Some(mut egress) = self.egress {
+            egress.remaining = egress.remaining.saturating_sub(elapsed);
+            self.egress = Some(egress);
+            if egress.remaining.is_zero() {
+                self.egress = None;
+                let value = match egress.relinquish {
+                    true => None,
+                    false => Some(0.0),
+                };
+                // The priority was validated when the command was executed
+                let _ = self.priority_array.set(egress.priority, value);
+                self.settle();
+            }
+        }
+
+        self.transition = match self.transition {
+            Some(Transition::Fade {
+                from,
+                to,
+                duration,
+                elapsed: done,
+            }) => {
+                let done = done + elapsed;
+                if done >= duration {
+                    self.tracking_value = to;
+                    None
+                } else {
+                    let fraction = done.as_secs_f32() / duration.as_secs_f32();
+                    self.tracking_value = from + (to - from) * fraction;
+                    Some(Transition::Fade {
+                        from,
+                        to,
+                        duration,
+                        elapsed: done,
+                    })
+                }
+            }
+            Some(Transition::Ramp { to, rate }) => {
+                let step = rate * elapsed.as_secs_f32();
+                let distance = to - self.tracking_value;
+                if distance.abs() <= step {
+                    self.tracking_value = to;
+                    None
+                } else {
+                    self.tracking_value += step.copysign(distance);
+                    Some(Transition::Ramp { to, rate })
+                }
+            }
+            None => None,
+        };
+        self.in_progress = match self.transition {
+            Some(Transition::Fade { .. }) => LightingInProgress::FadeActive,
+            Some(Transition::Ramp { .. }) => LightingInProgress::RampActive,
+            None => LightingInProgress::Idle,
+        };
+    }
+
+    fn effective_priority(&self) -> Option<u8> {
+        self.priority_array.effective().map(|(p, _)| p)
+    }
+
+    /// Jump the output to Present_Value, cancelling any transition
+    fn settle(&mut self) {
+        self.transition = None;
+        self.in_progress = LightingInProgress::Idle;
+        self.tracking_value = self.present_value();
+    }
+}
+
+impl Object for LightingOutput {
+    fn object_identifier(&self) -> ObjectIdentifier {
+        ObjectIdentifier::new(ObjectType::LightingOutput, self.instance)
+    }
+
+    fn object_name(&self) -> &str {
+        &self.name
+    }
+
+    fn property_list(&self) -> Vec<PropertyIdentifier> {
+        use PropertyIdentifier::*;
+        vec![
+            PresentValue,
+            TrackingValue,
+            LightingCommand,
+            InProgress,
+            StatusFlags,
+            OutOfService,
+            BlinkWarnEnable,
+            EgressTime,
+            EgressActive,
+            DefaultFadeTime,
+            DefaultRampRate,
+            DefaultStepIncrement,
+            PriorityArray,
+            RelinquishDefault,
+            LightingCommandDefaultPriority,
+        ]
+    }
+
+    fn read_property(
+        &self,
+        property: PropertyIdentifier,
+        array_index: Option<u32>,
+    ) -> Result<BACnetValue, BACnetError> {
+        let value = match property {
+            PropertyIdentifier::PresentValue => BACnetValue::Real(self.present_value()),
+            PropertyIdentifier::TrackingValue => BACnetValue::Real(self.tracking_value),
+            PropertyIdentifier::LightingCommand => self.lighting_command.into(),
+            PropertyIdentifier::InProgress => BACnetValue::Enumerated(self.in_progress as u32),
+            PropertyIdentifier::StatusFlags => {
+                BACnetValue::BitString(vec![false, false, false, self.out_of_service].into())
+            }
+            PropertyIdentifier::OutOfService => BACnetValue::Boolean(self.out_of_service),
+            PropertyIdentifier::BlinkWarnEnable => BACnetValue::Boolean(self.blink_warn_enable),
+            PropertyIdentifier::EgressTime => BACnetValue::Unsigned(self.egress_time),
+            PropertyIdentifier::EgressActive => BACnetValue::Boolean(self.egress_active()),
+            PropertyIdentifier::DefaultFadeTime => BACnetValue::Unsigned(self.default_fade_time),
+            PropertyIdentifier::DefaultRampRate => BACnetValue::Real(self.default_ramp_rate),
+            PropertyIdentifier::DefaultStepIncrement => {
+                BACnetValue::Real(self.default_step_increment)
+            }
+            PropertyIdentifier::PriorityArray => {
+                self.priority_array.to_value(|v| BACnetValue::Real(*v))
+            }
+            PropertyIdentifier::RelinquishDefault => BACnetValue::Real(self.relinquish_default),
+            PropertyIdentifier::LightingCommandDefaultPriority => {
+                BACnetValue::Unsigned(self.default_priority as u32)
+            }
+            _ => return self.read_common_property(property, array_index),
+        };
+        value.array_element(array_index)
+    }
+
+    fn write_property(
+        &mut self,
+        property: PropertyIdentifier,
+        _array_index: Option<u32>,
+        value: BACnetValue,
+        priority: Option<u8>,
+        _time: BACnetDateTime,
+    ) -> Result<(), BACnetError> {
+        let out_of_range = || BACnetError::property(ErrorCode::ValueOutOfRange);
+        match property {
+            PropertyIdentifier::PresentValue => {
+                let level = match value {
+                    BACnetValue::Null => None,
+                    v => Some(expect_real(v)?),
+                };
+                self.command(priority.unwrap_or(PRIORITIES as u8), level)
+            }
+            PropertyIdentifier::LightingCommand => self.execute(LightingCommand::try_from(value)?),
+            PropertyIdentifier::RelinquishDefault => {
+                self.relinquish_default = expect_real(value)?;
+                self.settle();
+                Ok(())
+            }
+            PropertyIdentifier::OutOfService => {
+                self.out_of_service = expect_boolean(value)?;
+                Ok(())
+            }
+            PropertyIdentifier::BlinkWarnEnable => {
+                self.blink_warn_enable = expect_boolean(value)?;
+                Ok(())
+            }
+            PropertyIdentifier::EgressTime => {
+                self.egress_time = expect_unsigned(value)?;
+                Ok(())
+            }
+            PropertyIdentifier::DefaultFadeTime => match expect_unsigned(value)? {
+                t @ 100..=86_400_000 => {
+                    self.default_fade_time = t;
+                    Ok(())
+                }
+                _ => Err(out_of_range()),
+            },
+            PropertyIdentifier::DefaultRampRate => match expect_real(value)? {
+                r if (0.1..=100.0).contains(&r) => {
+                    self.default_ramp_rate = r;
+                    Ok(())
+                }
+                _ => Err(out_of_range()),
+            },
+            PropertyIdentifier::DefaultStepIncrement => match expect_real(value)? {
+                s if (0.1..=100.0).contains(&s) => {
+                    self.default_step_increment = s;
+                    Ok(())
+                }
+                _ => Err(out_of_range()),
+            },
+            PropertyIdentifier::LightingCommandDefaultPriority => match expect_unsigned(value)? {
+                p @ 1..=16 => {
+                    self.default_priority = p as u8;
+                    Ok(())
+                }
+                _ => Err(out_of_range()),
+            },
+            _ => Err(self.unwritable(property)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::objects::testing::at;
+
+    #[test]
+    fn test_fade_to() {
+        let mut output = LightingOutput::new(1, "Light");
+        output
+            .execute(LightingCommand::fade_to(80.0, Some(2000)))
+            .unwrap();
+
+        assert_eq!(output.present_value(), 80.0);
+        assert_eq!(output.tracking_value(), 0.0);
+        assert_eq!(output.in_progress(), LightingInProgress::FadeActive);
+
+        output.advance(Duration::from_millis(500));
+        assert_eq!(output.tracking_value(), 20.0);
+        output.advance(Duration::from_millis(1500));
+        assert_eq!(output.tracking_value(), 80.0);
+        assert_eq!(output.in_progress(), LightingInProgress::Idle);
+    }
+
+    #[test]
+    fn test_ramp_to_and_stop() {
+        let mut output = LightingOutput::new(1, "Light");
+        output.command(16, Some(50.0)).unwrap();
+        output
+            .execute(LightingCommand::ramp_to(10.0, Some(10.0)))
+            .unwrap();
+        assert_eq!(output.in_progress(), LightingInProgress::RampActive);
+
+        output.advance(Duration::from_secs(1));
+        assert_eq!(output.tracking_value(), 40.0);
+
+        output
+            .execute(LightingCommand::new(LightingOperation::Stop))
+            .unwrap();
+        assert_eq!(output.in_progress(), LightingInProgress::Idle);
+        assert_eq!(output.present_value(), 40.0);
+        output.advance(Duration::from_secs(1));
+        assert_eq!(output.tracking_value(), 40.0);
+    }
+
+    #[test]
+    fn test_step() {
+        let mut output = LightingOutput::new(1, "Light");
+        output.default_step_increment = 10.0;
+
+        output
+            .execute(LightingCommand::new(LightingOperation::StepUp))
+            .unwrap();
+        assert_eq!(output.present_value(), 0.0);
+
+        output
+            .execute(LightingCommand::new(LightingOperation::StepOn))
+            .unwrap();
+        assert_eq!(output.present_value(), 10.0);
+
+        output
+            .execute(LightingCommand::new(LightingOperation::StepDown))
+            .unwrap();
+        assert_eq!(output.present_value(), 1.0);
+
+        output
+            .execute(LightingCommand::new(LightingOperation::StepOff))
+            .unwrap();
+        assert_eq!(output.present_value(), 0.0);
+        assert_eq!(output.tracking_value(), 0.0);
+    }
+
+    #[test]
+    fn test_warn_relinquish_after_egress() {
+        let mut output = LightingOutput::new(1, "Light");
+        output.egress_time = 5;
+        output.command(8, Some(100.0)).unwrap();
+
+        output
+            .execute(LightingCommand::new(LightingOperation::WarnRelinquish).with_priority(8))
+            .unwrap();
+        assert!(output.egress_active());
+        assert_eq!(output.present_value(), 100.0);
+
+        output.advance(Duration::from_secs(5));
+        assert!(!output.egress_active());
+        assert_eq!(output.present_value(), 0.0);
+        assert_eq!(output.priority_array().get(8), None);
+    }
+
+    #[test]
+    fn test_lower_priority_fade_is_not_visible() {
+        let mut output = LightingOutput::new(1, "Light");
+        output.command(1, Some(30.0)).unwrap();
+        output
+            .execute(LightingCommand::fade_to(80.0, None).with_priority(10))
+            .unwrap();
+        assert_eq!(output.in_progress(), LightingInProgress::Idle);
+        assert_eq!(output.present_value(), 30.0);
+    }
+
+    #[test]
+    fn test_write_lighting_command_property() {
+        let mut output = LightingOutput::new(1, "Light");
+        let command = LightingCommand::fade_to(50.0, Some(1000)).with_priority(9);
+        output
+            .write_property(
+                PropertyIdentifier::LightingCommand,
+                None,
+                command.into(),
+                None,
+                at(0),
+            )
+            .unwrap();
+        assert_eq!(
+            output.read_property(PropertyIdentifier::LightingCommand, None),
+            Ok(command.into())
+        );
+        assert_eq!(output.priority_array().get(9), Some(&50.0));
+
+        assert_eq!(
+            output.write_property(
+                PropertyIdentifier::LightingCommand,
+                None,
+                LightingCommand::fade_to(150.0, None).into(),
+                None,
+                at(0)
+            ),
+            Err(BACnetError::property(ErrorCode::ValueOutOfRange))
+        );
+        assert_eq!(
+            output.write_property(
+                PropertyIdentifier::LightingCommand,
+                None,
+                BACnetValue::Real(1.0),
+                None,
+                at(0)
+            ),
+            Err(BACnetError::property(ErrorCode::InvalidDataType))
+        );
+    }
+
+    #[test]
+    fn test_relinquish_present_value() {
+        let mut output = LightingOutput::new(1, "Light");
+        output
+            .write_property(
+                PropertyIdentifier::PresentValue,
+                None,
+                BACnetValue::Real(75.0),
+                Some(5),
+                at(0),
+            )
+            .unwrap();
+        assert_eq!(output.tracking_value(), 75.0);
+        output
+            .write_property(
+                PropertyIdentifier::PresentValue,
+                None,
+                BACnetValue::Null,
+                Some(5),
+                at(0),
+            )
+            .unwrap();
+        assert_eq!(
+            output.read_property(PropertyIdentifier::PresentValue, None),
+            Ok(BACnetValue::Real(0.0))
+        );
+    }
+}
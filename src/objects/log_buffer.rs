@@ -0,0 +1,369 @@
+use crate::application::{BACnetDateTime, Range, ResultFlags};
+
+use std::collections::VecDeque;
+
+/// BACnetLogStatus (Clause 21)
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub struct LogStatus {
+    pub log_disabled: bool,
+    pub buffer_purged: bool,
+    pub log_interrupted: bool,
+}
+
+/// A record in a [`LogBuffer`] together with its sequence number
+#[derive(Clone, Debug, PartialEq)]
+pub struct LogEntry<T> {
+    pub sequence_number: u32,
+    pub timestamp: BACnetDateTime,
+    pub datum: T,
+}
+
+/// Items selected by [`LogBuffer::read_range`]
+#[derive(Clone, Debug, PartialEq)]
+pub struct LogRange<'a, T> {
+    pub result_flags: ResultFlags,
+    /// Sequence number of the first returned item, only reported for
+    /// requests by sequence number or time (15.8.1.3.7)
+    pub first_sequence_number: Option<u32>,
+    pub items: Vec<&'a LogEntry<T>>,
+}
+
+impl<T> LogRange<'_, T> {
+    /// Encode the selected records for a ReadRange-ACK
+    pub fn encode(
+        &self,
+        encode: impl Fn(&mut Vec<u8>, &LogEntry<T>) -> crate::error::Result<()>,
+    ) -> crate::error::Result<RangeItems> {
+        Ok(RangeItems {
+            result_flags: self.result_flags,
+            first_sequence_number: self.first_sequence_number,
+            items: self
+                .items
+                .iter()
+                .map(|entry| {
+                    let mut data = Vec::new();
+                    encode(&mut data, entry)?;
+                    Ok(data)
+                })
+                .collect::<crate::error::Result<_>>()?,
+        })
+    }
+}
+
+/// Items of a list returned by [`Object::read_range`](super::Object::read_range)
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct RangeItems {
+    pub result_flags: ResultFlags,
+    pub first_sequence_number: Option<u32>,
+    /// Each item as encoded in the itemData of a ReadRange-ACK (15.8.1.3.6)
+    pub items: Vec<Vec<u8>>,
+}
+
+/// Circular record buffer shared by the log objects (Trend Log, Event Log,
+/// Audit Log)
+///
+/// Once `buffer_size` records are stored the oldest record is dropped for
+/// each new one. Sequence numbers count every record ever added, starting at
+/// 1 and wrapping from 2^32-1 back to 1 (12.25.16).
+#[derive(Clone, Debug)]
+pub struct LogBuffer<T> {
+    records: VecDeque<LogEntry<T>>,
+    buffer_size: u32,
+    total_record_count: u32,
+}
+
+impl<T> LogBuffer<T> {
+    pub fn new(buffer_size: u32) -> Self {
+        Self {
+            records: VecDeque::with_capacity(buffer_size.min(1024) as usize),
+            buffer_size,
+            total_record_count: 0,
+        }
+    }
+
+    /// Add a record, returning its sequence number
+    pub fn push(&mut self, timestamp: BACnetDateTime, datum: T) -> u32 {
+        if self.buffer_size == 0 {
+            return self.total_record_count;
+        }
+        while self.records.len() >= self.buffer_size as usize {
+            self.records.pop_front();
+        }
+        self.total_record_count = match self.total_record_count {
+            u32::MAX => 1,
+            n => n + 1,
+        };
+        self.records.push_back(LogEntry {
+            sequence_number: self.total_record_count,
+            timestamp,
+            datum,
+        });
+        self.total_record_count
+    }
+
+    /// Number of records currently stored (Record_Count)
+    pub fn len(&self) -> usize {
+        self.records.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.records.is_empty()
+    }
+
+    pub fn buffer_size(&self) -> u32 {
+        self.buffer_size
+    }
+
+    /// Change the capacity, dropping the oldest records if needed
+    pub fn set_buffer_size(&mut self, buffer_size: u32) {
+        self.buffer_size = buffer_size;
+        while self.records.len() > buffer_size as usize {
+            self.records.pop_front();
+        }
+    }
+
+    /// Number of records ever added (Total_Record_Count)
+    pub fn total_record_count(&self) -> u32 {
+        self.total_record_count
+    }
+
+    /// Remove all records, sequence numbers keep counting
+    pub fn clear(&mut self) {
+        self.records.clear();
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &LogEntry<T>> {
+        self.records.iter()
+    }
+
+    /// Select records as specified by a ReadRange request (15.8.1.1.4)
+    ///
+    /// Without a range all records are returned.
+    pub fn read_range(&self, range: Option<&Range>) -> LogRange<'_, T> {
+        let len = self.records.len();
+        // Positions are 0 based here, ReadRange uses 1 based indexes
+        let (positions, report_sequence) = match range {
+            None => (0..len, false),
+            Some(Range::ByPosition {
+                reference_index,
+                count,
+            }) => {
+                let index = *reference_index as usize;
+                let positions = if index == 0 || index > len || *count == 0 {
+                    0..0
+                } else if *count > 0 {
+                    index - 1..(index - 1 + *count as usize).min(len)
+                } else {
+                    index.saturating_sub(count.unsigned_abs() as usize)..index
+                };
+                (positions, false)
+            }
+            Some(Range::BySequenceNumber {
+                reference_sequence_number,
+                count,
+            }) => {
+                // Stored records have consecutive sequence numbers, so the
+                // reference is located by its distance from the oldest one
+                let reference = match self.records.front() {
+                    Some(oldest) => {
+                        sequence_offset(oldest.sequence_number, *reference_sequence_number)
+                    }
+                    None => 0,
+                };
+                let (low, high) = match *count as i64 {
+                    c if c > 0 => (reference, reference + c - 1),
+                    c => (reference + c + 1, reference),
+                };
+                let positions = match *count {
+                    0 => 0..0,
+                    _ if high < 0 || low >= len as i64 => 0..0,
+                    _ => low.max(0) as usize..(high as usize + 1).min(len),
+                };
+                (positions, true)
+            }
+            Some(Range::ByTime {
+                reference_time,
+                count,
+            }) => {
+                let positions = match *count {
+                    0 => 0..0,
+                    c if c > 0 => {
+                        let start = self
+                            .records
+                            .iter()
+                            .position(|r| r.timestamp > *reference_time)
+                            .unwrap_or(len);
+                        start..(start + c as usize).min(len)
+                    }
+                    c => {
+                        let end = self
+                            .records
+                            .iter()
+                            .rposition(|r| r.timestamp < *reference_time)
+                            .map(|e| e + 1)
+                            .unwrap_or(0);
+                        end.saturating_sub(c.unsigned_abs() as usize)..end
+                    }
+                };
+                (positions, true)
+            }
+        };
+
+        let items: Vec<_> = self.records.range(positions.clone()).collect();
+        let result_flags = ResultFlags {
+            first_item: !items.is_empty() && positions.start == 0,
+            last_item: !items.is_empty() && positions.end == len,
+            more_items: false,
+        };
+        let first_sequence_number = match report_sequence {
+            true => items.first().map(|i| i.sequence_number),
+            false => None,
+        };
+
+        LogRange {
+            result_flags,
+            first_sequence_number,
+            items,
+        }
+    }
+}
+
+/// Signed distance from the sequence number `from` to `to`, in the cycle of
+/// sequence numbers from 1 to 2^32-1
+fn sequence_offset(from: u32, to: u32) -> i64 {
+    let cycle = u32::MAX as i64;
+    let distance = (to as i64 - from as i64).rem_euclid(cycle);
+    match distance > cycle / 2 {
+        true => distance - cycle,
+        false => distance,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::objects::testing::at;
+
+    fn buffer() -> LogBuffer<u8> {
+        let mut buffer = LogBuffer::new(5);
+        for i in 1..=7 {
+            buffer.push(at(i), i);
+        }
+        buffer
+    }
+
+    fn data(range: &LogRange<'_, u8>) -> Vec<u8> {
+        range.items.iter().map(|i| i.datum).collect()
+    }
+
+    #[test]
+    fn test_buffer_wraps() {
+        let buffer = buffer();
+        assert_eq!(buffer.len(), 5);
+        assert_eq!(buffer.total_record_count(), 7);
+        assert_eq!(buffer.iter().next().unwrap().sequence_number, 3);
+    }
+
+    #[test]
+    fn test_read_range_all() {
+        let buffer = buffer();
+        let range = buffer.read_range(None);
+        assert_eq!(data(&range), vec![3, 4, 5, 6, 7]);
+        assert!(range.result_flags.first_item);
+        assert!(range.result_flags.last_item);
+        assert_eq!(range.first_sequence_number, None);
+    }
+
+    #[test]
+    fn test_read_range_by_position() {
+        let buffer = buffer();
+        let range = buffer.read_range(Some(&Range::ByPosition {
+            reference_index: 2,
+            count: 2,
+        }));
+        assert_eq!(data(&range), vec![4, 5]);
+        assert!(!range.result_flags.first_item);
+        assert!(!range.result_flags.last_item);
+
+        let range = buffer.read_range(Some(&Range::ByPosition {
+            reference_index: 2,
+            count: -3,
+        }));
+        assert_eq!(data(&range), vec![3, 4]);
+        assert!(range.result_flags.first_item);
+
+        let range = buffer.read_range(Some(&Range::ByPosition {
+            reference_index: 6,
+            count: 1,
+        }));
+        assert!(range.items.is_empty());
+    }
+
+    #[test]
+    fn test_read_range_by_sequence_number() {
+        let buffer = buffer();
+        let range = buffer.read_range(Some(&Range::BySequenceNumber {
+            reference_sequence_number: 6,
+            count: 10,
+        }));
+        assert_eq!(data(&range), vec![6, 7]);
+        assert!(range.result_flags.last_item);
+        assert_eq!(range.first_sequence_number, Some(6));
+
+        let range = buffer.read_range(Some(&Range::BySequenceNumber {
+            reference_sequence_number: 4,
+            count: -3,
+        }));
+        assert_eq!(data(&range), vec![3, 4]);
+        assert_eq!(range.first_sequence_number, Some(3));
+    }
+
+    #[test]
+    fn test_read_range_by_wrapped_sequence_number() {
+        let mut buffer = LogBuffer::new(5);
+        buffer.total_record_count = u32::MAX - 2;
+        for i in 1..=5 {
+            buffer.push(at(i), i);
+        }
+        let sequence_numbers: Vec<_> = buffer.iter().map(|r| r.sequence_number).collect();
+        assert_eq!(sequence_numbers, vec![u32::MAX - 1, u32::MAX, 1, 2, 3]);
+
+        let range = buffer.read_range(Some(&Range::BySequenceNumber {
+            reference_sequence_number: u32::MAX,
+            count: 3,
+        }));
+        assert_eq!(data(&range), vec![2, 3, 4]);
+        assert_eq!(range.first_sequence_number, Some(u32::MAX));
+
+        let range = buffer.read_range(Some(&Range::BySequenceNumber {
+            reference_sequence_number: 2,
+            count: -10,
+        }));
+        assert_eq!(data(&range), vec![1, 2, 3, 4]);
+        assert!(range.result_flags.first_item);
+
+        let range = buffer.read_range(Some(&Range::BySequenceNumber {
+            reference_sequence_number: 4,
+            count: 2,
+        }));
+        assert!(range.items.is_empty());
+    }
+
+    #[test]
+    fn test_read_range_by_time() {
+        let buffer = buffer();
+        let range = buffer.read_range(Some(&Range::ByTime {
+            reference_time: at(4),
+            count: 2,
+        }));
+        assert_eq!(data(&range), vec![5, 6]);
+        assert_eq!(range.first_sequence_number, Some(5));
+
+        let range = buffer.read_range(Some(&Range::ByTime {
+            reference_time: at(4),
+            count: -5,
+        }));
+        assert_eq!(data(&range), vec![3]);
+        assert!(range.result_flags.first_item);
+    }
+}
@@ -0,0 +1,594 @@
+use crate::application::{
+    BACnetDateTime, BACnetError, BACnetValue, ErrorCode, EventState, ObjectIdentifier, ObjectType,
+    PropertyIdentifier,
+};
+use crate::objects::{expect_boolean, expect_unsigned, Object, PriorityArray, PRIORITIES};
+
+/// Commands of a commandable multi-state object, with the state in effect
+/// when all priorities are relinquished
+#[derive(Clone, Debug)]
+struct Commands {
+    priority_array: PriorityArray<u32>,
+    relinquish_default: u32,
+}
+
+/// The states shared by the multi-state objects (12.18, 12.19, 12.20)
+///
+/// States are numbered from 1 to Number_Of_States, State_Text is either
+/// absent or has a text for every state.
+#[derive(Clone, Debug)]
+struct States {
+    number_of_states: u32,
+    state_text: Vec<String>,
+    present_value: u32,
+    commands: Option<Commands>,
+}
+
+impl States {
+    fn new(number_of_states: u32, commandable: bool) -> Self {
+        Self {
+            number_of_states: number_of_states.max(1),
+            state_text: Vec::new(),
+            present_value: 1,
+            commands: commandable.then(|| Commands {
+                priority_array: PriorityArray::new(),
+                relinquish_default: 1,
+            }),
+        }
+    }
+
+    fn present_value(&self) -> u32 {
+        match &self.commands {
+            Some(commands) => commands
+                .priority_array
+                .effective()
+                .map(|(_, state)| *state)
+                .unwrap_or(commands.relinquish_default),
+            None => self.present_value,
+        }
+    }
+
+    fn state(&self, value: BACnetValue) -> Result<u32, BACnetError> {
+        match expect_unsigned(value)? {
+            state if (1..=self.number_of_states).contains(&state) => Ok(state),
+            _ => Err(BACnetError::property(ErrorCode::ValueOutOfRange)),
+        }
+    }
+
+    fn set_present_value(&mut self, state: u32) -> Result<(), BACnetError> {
+        self.present_value = self.state(BACnetValue::Unsigned(state))?;
+        Ok(())
+    }
+
+    fn set_state_text(&mut self, state_text: Vec<String>) -> Result<(), BACnetError> {
+        if !state_text.is_empty() && state_text.len() != self.number_of_states as usize {
+            return Err(BACnetError::property(ErrorCode::InconsistentParameters));
+        }
+        self.state_text = state_text;
+        Ok(())
+    }
+
+    fn command(&mut self, priority: u8, state: Option<u32>) -> Result<(), BACnetError> {
+        let state = match state {
+            Some(state) => Some(self.state(BACnetValue::Unsigned(state))?),
+            None => None,
+        };
+        match &mut self.commands {
+            Some(commands) => commands.priority_array.set(priority, state),
+            None => Err(BACnetError::property(ErrorCode::WriteAccessDenied)),
+        }
+    }
+
+    fn property_list(&self) -> Vec<PropertyIdentifier> {
+        use PropertyIdentifier::*;
+        let mut properties = vec![
+            PresentValue,
+            StatusFlags,
+            EventState,
+            OutOfService,
+            NumberOfStates,
+        ];
+        if !self.state_text.is_empty() {
+            properties.push(StateText);
+        }
+        if self.commands.is_some() {
+            properties.extend([PriorityArray, RelinquishDefault, CurrentCommandPriority]);
+        }
+        properties
+    }
+
+    /// The value of a property of multi-state objects, `None` for the other
+    /// properties
+    fn read_property(
+        &self,
+        property: PropertyIdentifier,
+        out_of_service: bool,
+    ) -> Option<BACnetValue> {
+        let commands = self.commands.as_ref();
+        Some(match property {
+            PropertyIdentifier::PresentValue => BACnetValue::Unsigned(self.present_value()),
+            PropertyIdentifier::StatusFlags => {
+                BACnetValue::BitString(vec![false, false, false, out_of_service].into())
+            }
+            PropertyIdentifier::EventState => BACnetValue::Enumerated(EventState::Normal as u32),
+            PropertyIdentifier::OutOfService => BACnetValue::Boolean(out_of_service),
+            PropertyIdentifier::NumberOfStates => BACnetValue::Unsigned(self.number_of_states),
+            PropertyIdentifier::StateText if !self.state_text.is_empty() => BACnetValue::Array(
+                self.state_text
+                    .iter()
+                    .map(|text| BACnetValue::CharacterString(text.as_str().into()))
+                    .collect(),
+            ),
+            PropertyIdentifier::PriorityArray => commands?
+                .priority_array
+                .to_value(|state| BACnetValue::Unsigned(*state)),
+            PropertyIdentifier::RelinquishDefault => {
+                BACnetValue::Unsigned(commands?.relinquish_default)
+            }
+            PropertyIdentifier::CurrentCommandPriority => {
+                match commands?.priority_array.effective() {
+                    Some((priority, _)) => BACnetValue::Unsigned(priority as u32),
+                    None => BACnetValue::Null,
+                }
+            }
+            _ => return None,
+        })
+    }
+
+    /// Write Present_Value, commanded at a priority if the object is
+    /// commandable, or Relinquish_Default, `None` for the other properties
+    fn write_property(
+        &mut self,
+        property: PropertyIdentifier,
+        value: BACnetValue,
+        priority: Option<u8>,
+    ) -> Option<Result<(), BACnetError>> {
+        Some(match (property, self.commands.is_some()) {
+            (PropertyIdentifier::PresentValue, true) => {
+                let state = match value {
+                    BACnetValue::Null => None,
+                    value => match self.state(value) {
+                        Ok(state) => Some(state),
+                        Err(error) => return Some(Err(error)),
+                    },
+                };
+                self.command(priority.unwrap_or(PRIORITIES as u8), state)
+            }
+            (PropertyIdentifier::PresentValue, false) => {
+                self.state(value).map(|state| self.present_value = state)
+            }
+            (PropertyIdentifier::RelinquishDefault, true) => self.state(value).map(|state| {
+                if let Some(commands) = &mut self.commands {
+                    commands.relinquish_default = state;
+                }
+            }),
+            _ => return None,
+        })
+    }
+}
+
+/// Multi-state Input object (12.18)
+///
+/// Present_Value is the state of the input, updated by the owner with
+/// [`MultiStateInput::set_present_value`]. It can be written only while
+/// the object is out of service.
+#[derive(Clone, Debug)]
+pub struct MultiStateInput {
+    instance: u32,
+    name: String,
+    states: States,
+    pub out_of_service: bool,
+}
+
+impl MultiStateInput {
+    /// An input in state 1 of `number_of_states`, at least one
+    pub fn new<S: Into<String>>(instance: u32, name: S, number_of_states: u32) -> Self {
+        Self {
+            instance,
+            name: name.into(),
+            states: States::new(number_of_states, false),
+            out_of_service: false,
+        }
+    }
+
+    pub fn present_value(&self) -> u32 {
+        self.states.present_value()
+    }
+
+    pub fn set_present_value(&mut self, state: u32) -> Result<(), BACnetError> {
+        self.states.set_present_value(state)
+    }
+
+    pub fn number_of_states(&self) -> u32 {
+        self.states.number_of_states
+    }
+
+    pub fn state_text(&self) -> &[String] {
+        &self.states.state_text
+    }
+
+    /// Name the states, with a text for each state
+    pub fn set_state_text(&mut self, state_text: Vec<String>) -> Result<(), BACnetError> {
+        self.states.set_state_text(state_text)
+    }
+}
+
+impl Object for MultiStateInput {
+    fn object_identifier(&self) -> ObjectIdentifier {
+        ObjectIdentifier::new(ObjectType::MultiStateInput, self.instance)
+    }
+
+    fn object_name(&self) -> &str {
+        &self.name
+    }
+
+    fn property_list(&self) -> Vec<PropertyIdentifier> {
+        self.states.property_list()
+    }
+
+    fn read_property(
+        &self,
+        property: PropertyIdentifier,
+        array_index: Option<u32>,
+    ) -> Result<BACnetValue, BACnetError> {
+        match self.states.read_property(property, self.out_of_service) {
+            Some(value) => value.array_element(array_index),
+            None => self.read_common_property(property, array_index),
+        }
+    }
+
+    fn write_property(
+        &mut self,
+        property: PropertyIdentifier,
+        _array_index: Option<u32>,
+        value: BACnetValue,
+        priority: Option<u8>,
+        _time: BACnetDateTime,
+    ) -> Result<(), BACnetError> {
+        match property {
+            PropertyIdentifier::PresentValue if !self.out_of_service => {
+                Err(self.unwritable(property))
+            }
+            PropertyIdentifier::OutOfService => {
+                self.out_of_service = expect_boolean(value)?;
+                Ok(())
+            }
+            _ => match self.states.write_property(property, value, priority) {
+                Some(result) => result,
+                None => Err(self.unwritable(property)),
+            },
+        }
+    }
+}
+
+/// Multi-state Output object (12.19)
+///
+/// Present_Value is commanded through the priority array, the owner drives
+/// the output to [`MultiStateOutput::present_value`].
+#[derive(Clone, Debug)]
+pub struct MultiStateOutput {
+    instance: u32,
+    name: String,
+    states: States,
+    pub out_of_service: bool,
+}
+
+impl MultiStateOutput {
+    /// An output of `number_of_states`, at least one, relinquished to
+    /// state 1
+    pub fn new<S: Into<String>>(instance: u32, name: S, number_of_states: u32) -> Self {
+        Self {
+            instance,
+            name: name.into(),
+            states: States::new(number_of_states, true),
+            out_of_service: false,
+        }
+    }
+
+    pub fn present_value(&self) -> u32 {
+        self.states.present_value()
+    }
+
+    /// Command Present_Value at a priority, `None` relinquishes it
+    pub fn command(&mut self, priority: u8, state: Option<u32>) -> Result<(), BACnetError> {
+        self.states.command(priority, state)
+    }
+
+    pub fn number_of_states(&self) -> u32 {
+        self.states.number_of_states
+    }
+
+    pub fn state_text(&self) -> &[String] {
+        &self.states.state_text
+    }
+
+    /// Name the states, with a text for each state
+    pub fn set_state_text(&mut self, state_text: Vec<String>) -> Result<(), BACnetError> {
+        self.states.set_state_text(state_text)
+    }
+}
+
+impl Object for MultiStateOutput {
+    fn object_identifier(&self) -> ObjectIdentifier {
+        ObjectIdentifier::new(ObjectType::MultiStateOutput, self.instance)
+    }
+
+    fn object_name(&self) -> &str {
+        &self.name
+    }
+
+    fn property_list(&self) -> Vec<PropertyIdentifier> {
+        self.states.property_list()
+    }
+
+    fn read_property(
+        &self,
+        property: PropertyIdentifier,
+        array_index: Option<u32>,
+    ) -> Result<BACnetValue, BACnetError> {
+        match self.states.read_property(property, self.out_of_service) {
+            Some(value) => value.array_element(array_index),
+            None => self.read_common_property(property, array_index),
+        }
+    }
+
+    fn write_property(
+        &mut self,
+        property: PropertyIdentifier,
+        _array_index: Option<u32>,
+        value: BACnetValue,
+        priority: Option<u8>,
+        _time: BACnetDateTime,
+    ) -> Result<(), BACnetError> {
+        match property {
+            PropertyIdentifier::OutOfService => {
+                self.out_of_service = expect_boolean(value)?;
+                Ok(())
+            }
+            _ => match self.states.write_property(property, value, priority) {
+                Some(result) => result,
+                None => Err(self.unwritable(property)),
+            },
+        }
+    }
+}
+
+/// Multi-state Value object (12.20)
+///
+/// A value is written directly, or, if created with
+/// [`MultiStateValue::commandable`], commanded through the priority array.
+#[derive(Clone, Debug)]
+pub struct MultiStateValue {
+    instance: u32,
+    name: String,
+    states: States,
+    pub out_of_service: bool,
+}
+
+impl MultiStateValue {
+    /// A value in state 1 of `number_of_states`, at least one
+    pub fn new<S: Into<String>>(instance: u32, name: S, number_of_states: u32) -> Self {
+        Self {
+            instance,
+            name: name.into(),
+            states: States::new(number_of_states, false),
+            out_of_service: false,
+        }
+    }
+
+    /// A value with a priority array, relinquished to state 1
+    pub fn commandable<S: Into<String>>(instance: u32, name: S, number_of_states: u32) -> Self {
+        Self {
+            states: States::new(number_of_states, true),
+            ..Self::new(instance, name, number_of_states)
+        }
+    }
+
+    pub fn present_value(&self) -> u32 {
+        self.states.present_value()
+    }
+
+    /// Set Present_Value of a value that is not commandable
+    pub fn set_present_value(&mut self, state: u32) -> Result<(), BACnetError> {
+        match self.states.commands {
+            Some(_) => Err(BACnetError::property(ErrorCode::WriteAccessDenied)),
+            None => self.states.set_present_value(state),
+        }
+    }
+
+    /// Command Present_Value of a commandable value at a priority, `None`
+    /// relinquishes it
+    pub fn command(&mut self, priority: u8, state: Option<u32>) -> Result<(), BACnetError> {
+        self.states.command(priority, state)
+    }
+
+    pub fn number_of_states(&self) -> u32 {
+        self.states.number_of_states
+    }
+
+    pub fn state_text(&self) -> &[String] {
+        &self.states.state_text
+    }
+
+    /// Name the states, with a text for each state
+    pub fn set_state_text(&mut self, state_text: Vec<String>) -> Result<(), BACnetError> {
+        self.states.set_state_text(state_text)
+    }
+}
+
+impl Object for MultiStateValue {
+    fn object_identifier(&self) -> ObjectIdentifier {
+        ObjectIdentifier::new(ObjectType::MultiStateValue, self.instance)
+    }
+
+    fn object_name(&self) -> &str {
+        &self.name
+    }
+
+    fn property_list(&self) -> Vec<PropertyIdentifier> {
+        self.states.property_list()
+    }
+
+    fn read_property(
+        &self,
+        property: PropertyIdentifier,
+        array_index: Option<u32>,
+    ) -> Result<BACnetValue, BACnetError> {
+        match self.states.read_property(property, self.out_of_service) {
+            Some(value) => value.array_element(array_index),
+            None => self.read_common_property(property, array_index),
+        }
+    }
+
+    fn write_property(
+        &mut self,
+        property: PropertyIdentifier,
+        _array_index: Option<u32>,
+        value: BACnetValue,
+        priority: Option<u8>,
+        _time: BACnetDateTime,
+    ) -> Result<(), BACnetError> {
+        match property {
+            PropertyIdentifier::OutOfService => {
+                self.out_of_service = expect_boolean(value)?;
+                Ok(())
+            }
+            _ => match self.states.write_property(property, value, priority) {
+                Some(result) => result,
+                None => Err(self.unwritable(property)),
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::objects::testing::at;
+
+    fn out_of_range() -> BACnetError {
+        BACnetError::property(ErrorCode::ValueOutOfRange)
+    }
+
+    #[test]
+    fn test_multi_state_input() {
+        let mut input = MultiStateInput::new(1, "Fan speed", 3);
+        assert_eq!(input.set_present_value(4), Err(out_of_range()));
+        input.set_present_value(3).unwrap();
+        assert_eq!(input.present_value(), 3);
+
+        // Written only while out of service
+        let present_value = PropertyIdentifier::PresentValue;
+        assert_eq!(
+            input.write_property(present_value, None, BACnetValue::Unsigned(2), None, at(0)),
+            Err(BACnetError::property(ErrorCode::WriteAccessDenied))
+        );
+        input.out_of_service = true;
+        input
+            .write_property(present_value, None, BACnetValue::Unsigned(2), None, at(0))
+            .unwrap();
+        assert_eq!(input.present_value(), 2);
+        assert!(!input.has_property(PropertyIdentifier::PriorityArray));
+    }
+
+    #[test]
+    fn test_state_text() {
+        let mut value = MultiStateValue::new(1, "Mode", 3);
+        let text = |states: &[&str]| states.iter().map(|s| s.to_string()).collect();
+        assert!(!value.has_property(PropertyIdentifier::StateText));
+        assert_eq!(
+            value.set_state_text(text(&["Off", "On"])),
+            Err(BACnetError::property(ErrorCode::InconsistentParameters))
+        );
+        value
+            .set_state_text(text(&["Off", "Heat", "Cool"]))
+            .unwrap();
+        assert_eq!(
+            value.read_property(PropertyIdentifier::StateText, Some(3)),
+            Ok(BACnetValue::CharacterString("Cool".into()))
+        );
+        assert_eq!(
+            value.read_property(PropertyIdentifier::StateText, Some(0)),
+            Ok(BACnetValue::Unsigned(3))
+        );
+    }
+
+    #[test]
+    fn test_multi_state_output() {
+        let mut output = MultiStateOutput::new(1, "Damper", 4);
+        let present_value = PropertyIdentifier::PresentValue;
+        assert_eq!(output.present_value(), 1);
+        output
+            .write_property(
+                present_value,
+                None,
+                BACnetValue::Unsigned(3),
+                Some(8),
+                at(0),
+            )
+            .unwrap();
+        output.command(10, Some(2)).unwrap();
+        assert_eq!(output.present_value(), 3);
+        assert_eq!(
+            output.read_property(PropertyIdentifier::CurrentCommandPriority, None),
+            Ok(BACnetValue::Unsigned(8))
+        );
+        assert_eq!(
+            output.write_property(
+                present_value,
+                None,
+                BACnetValue::Unsigned(0),
+                Some(8),
+                at(0)
+            ),
+            Err(out_of_range())
+        );
+
+        output
+            .write_property(present_value, None, BACnetValue::Null, Some(8), at(0))
+            .unwrap();
+        assert_eq!(output.present_value(), 2);
+        output.command(10, None).unwrap();
+        output
+            .write_property(
+                PropertyIdentifier::RelinquishDefault,
+                None,
+                BACnetValue::Unsigned(4),
+                None,
+                at(0),
+            )
+            .unwrap();
+        assert_eq!(output.present_value(), 4);
+        assert_eq!(
+            output.read_property(PropertyIdentifier::CurrentCommandPriority, None),
+            Ok(BACnetValue::Null)
+        );
+    }
+
+    #[test]
+    fn test_commandable_value() {
+        let mut value = MultiStateValue::commandable(1, "Occupancy", 2);
+        assert!(value.has_property(PropertyIdentifier::PriorityArray));
+        assert_eq!(
+            value.set_present_value(2),
+            Err(BACnetError::property(ErrorCode::WriteAccessDenied))
+        );
+        value
+            .write_property(
+                PropertyIdentifier::PresentValue,
+                None,
+                BACnetValue::Unsigned(2),
+                None,
+                at(0),
+            )
+            .unwrap();
+        assert_eq!(
+            value.read_property(PropertyIdentifier::PriorityArray, Some(16)),
+            Ok(BACnetValue::Unsigned(2))
+        );
+        assert_eq!(
+            MultiStateValue::new(2, "Mode", 2).command(8, Some(1)),
+            Err(BACnetError::property(ErrorCode::WriteAccessDenied))
+        );
+    }
+}
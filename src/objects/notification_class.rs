@@ -0,0 +1,416 @@
+use crate::application::{
+    BACnetDateTime, BACnetError, BACnetTime, BACnetValue, ErrorCode, EventState, ObjectIdentifier,
+    ObjectType, PropertyIdentifier,
+};
+use crate::network::Address;
+use crate::objects::{expect_unsigned, Object};
+
+use crate::Decode;
+use std::convert::TryFrom;
+
+/// Transition of an event state, indexing BACnetEventTransitionBits
+/// (Clause 21)
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub enum Transition {
+    ToOffnormal = 0,
+    ToFault = 1,
+    ToNormal = 2,
+}
+
+impl From<EventState> for Transition {
+    /// The transition into the state, all off-normal states are reached
+    /// with to-offnormal
+    fn from(state: EventState) -> Self {
+        match state {
+            EventState::Normal => Self::ToNormal,
+            EventState::Fault => Self::ToFault,
+            _ => Self::ToOffnormal,
+        }
+    }
+}
+
+/// BACnetRecipient (Clause 21)
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Recipient {
+    /// A device, whose address is found with Who-Is
+    Device(ObjectIdentifier),
+    Address(Address),
+}
+
+/// BACnetDestination (Clause 21), a recipient of the notifications of a
+/// notification class
+#[derive(Clone, Debug, PartialEq)]
+pub struct Destination {
+    /// Days the destination is used on, starting with Monday
+    pub valid_days: [bool; 7],
+    pub from_time: BACnetTime,
+    pub to_time: BACnetTime,
+    pub recipient: Recipient,
+    pub process_identifier: u32,
+    pub issue_confirmed_notifications: bool,
+    /// Transitions notified, indexed by [`Transition`]
+    pub transitions: [bool; 3],
+}
+
+impl Destination {
+    /// A destination for unconfirmed notifications of all transitions at any
+    /// time
+    pub fn new(recipient: Recipient, process_identifier: u32) -> Self {
+        Self {
+            valid_days: [true; 7],
+            from_time: BACnetTime::new(0, 0, 0, 0),
+            to_time: BACnetTime::new(23, 59, 59, 99),
+            recipient,
+            process_identifier,
+            issue_confirmed_notifications: false,
+            transitions: [true; 3],
+        }
+    }
+
+    /// Whether a notification of the transition at the time is sent to this
+    /// destination
+    pub fn applies(&self, transition: Transition, time: &BACnetDateTime) -> bool {
+        let day = time.date.weekday as usize;
+        (1..=7).contains(&day)
+            && self.valid_days[day - 1]
+            && (self.from_time..=self.to_time).contains(&time.time)
+            && self.transitions[transition as usize]
+    }
+}
+
+fn invalid_data_type() -> BACnetError {
+    BACnetError::property(ErrorCode::InvalidDataType)
+}
+
+fn bits<const N: usize>(value: BACnetValue) -> Result<[bool; N], BACnetError> {
+    match value {
+        BACnetValue::BitString(bits) if bits.bit_len() >= N => {
+            let mut array = [false; N];
+            array.copy_from_slice(&bits.bits()[..N]);
+            Ok(array)
+        }
+        _ => Err(invalid_data_type()),
+    }
+}
+
+impl From<Recipient> for BACnetValue {
+    fn from(recipient: Recipient) -> Self {
+        let element = match recipient {
+            Recipient::Device(device) => (0, BACnetValue::ObjectIdentifier(device)),
+            // Network number 0 is the local network
+            Recipient::Address(address) => (
+                1,
+                BACnetValue::Array(vec![
+                    BACnetValue::Unsigned(address.net.unwrap_or(0) as u32),
+                    BACnetValue::OctetString(address.mac),
+                ]),
+            ),
+        };
+        BACnetValue::Constructed(vec![element])
+    }
+}
+
+impl TryFrom<BACnetValue> for Recipient {
+    type Error = BACnetError;
+
+    /// Decoded requests carry the device as the octets of a context tagged
+    /// object identifier
+    fn try_from(value: BACnetValue) -> Result<Self, Self::Error> {
+        match value {
+            BACnetValue::Constructed(mut elements) if elements.len() == 1 => {
+                match elements.remove(0) {
+                    (0, BACnetValue::ObjectIdentifier(device)) => Ok(Self::Device(device)),
+                    (0, BACnetValue::OctetString(octets)) => {
+                        let device = ObjectIdentifier::decode_slice(&octets)
+                            .map_err(|_| invalid_data_type())?;
+                        Ok(Self::Device(device))
+                    }
+                    (1, BACnetValue::Array(address)) => match address.as_slice() {
+                        [BACnetValue::Unsigned(net), BACnetValue::OctetString(mac)] => {
+                            let net = match *net {
+                                0 => None,
+                                net if net <= u16::MAX as u32 => Some(net as u16),
+                                _ => return Err(BACnetError::property(ErrorCode::ValueOutOfRange)),
+                            };
+                            Ok(Self::Address(Address {
+                                net,
+                                mac: mac.clone(),
+                            }))
+                        }
+                        _ => Err(invalid_data_type()),
+                    },
+                    _ => Err(invalid_data_type()),
+                }
+            }
+            _ => Err(invalid_data_type()),
+        }
+    }
+}
+
+impl From<Destination> for BACnetValue {
+    fn from(destination: Destination) -> Self {
+        BACnetValue::Array(vec![
+            BACnetValue::BitString(destination.valid_days.to_vec().into()),
+            BACnetValue::Time(destination.from_time),
+            BACnetValue::Time(destination.to_time),
+            destination.recipient.into(),
+            BACnetValue::Unsigned(destination.process_identifier),
+            BACnetValue::Boolean(destination.issue_confirmed_notifications),
+            BACnetValue::BitString(destination.transitions.to_vec().into()),
+        ])
+    }
+}
+
+impl TryFrom<BACnetValue> for Destination {
+    type Error = BACnetError;
+
+    fn try_from(value: BACnetValue) -> Result<Self, Self::Error> {
+        let elements = match value {
+            BACnetValue::Array(elements) if elements.len() == 7 => elements,
+            _ => return Err(invalid_data_type()),
+        };
+        let mut elements = elements.into_iter();
+        let mut next = || elements.next().unwrap();
+        Ok(Self {
+            valid_days: bits(next())?,
+            from_time: BACnetTime::try_from(next())?,
+            to_time: BACnetTime::try_from(next())?,
+            recipient: Recipient::try_from(next())?,
+            process_identifier: u32::try_from(next())?,
+            issue_confirmed_notifications: bool::try_from(next())?,
+            transitions: bits(next())?,
+        })
+    }
+}
+
+/// Destinations of a Recipient_List, either as an array of destinations or,
+/// as decoded from a request, the elements of all destinations in a row
+fn destinations(value: BACnetValue) -> Result<Vec<Destination>, BACnetError> {
+    let value = match value {
+        BACnetValue::List(elements) => BACnetValue::Array(elements),
+        value => value,
+    };
+    match value {
+        BACnetValue::Array(elements)
+            if elements.iter().all(|e| matches!(e, BACnetValue::Array(_))) =>
+        {
+            elements.into_iter().map(Destination::try_from).collect()
+        }
+        BACnetValue::Array(elements) if elements.len() % 7 == 0 => elements
+            .chunks(7)
+            .map(|d| Destination::try_from(BACnetValue::Array(d.to_vec())))
+            .collect(),
+        _ => Err(invalid_data_type()),
+    }
+}
+
+/// Notification Class object (12.21)
+///
+/// Objects that report events refer to a notification class by its
+/// instance, which gives the priorities of their notifications, whether
+/// transitions must be acknowledged and who receives them.
+#[derive(Clone, Debug)]
+pub struct NotificationClass {
+    instance: u32,
+    name: String,
+    /// Priorities of notifications, indexed by [`Transition`]
+    pub priority: [u8; 3],
+    /// Transitions that must be acknowledged, indexed by [`Transition`]
+    pub ack_required: [bool; 3],
+    pub recipient_list: Vec<Destination>,
+}
+
+/// The Notification Class object, see [`NotificationClass`]
+pub type NotificationClassObject = NotificationClass;
+
+impl NotificationClass {
+    /// A notification class without recipients at the lowest priority
+    pub fn new<S: Into<String>>(instance: u32, name: S) -> Self {
+        Self {
+            instance,
+            name: name.into(),
+            priority: [255; 3],
+            ack_required: [false; 3],
+            recipient_list: Vec::new(),
+        }
+    }
+}
+
+impl Object for NotificationClass {
+    fn object_identifier(&self) -> ObjectIdentifier {
+        ObjectIdentifier::new(ObjectType::NotificationClass, self.instance)
+    }
+
+    fn object_name(&self) -> &str {
+        &self.name
+    }
+
+    fn property_list(&self) -> Vec<PropertyIdentifier> {
+        use PropertyIdentifier::*;
+        vec![NotificationClass, Priority, AckRequired, RecipientList]
+    }
+
+    fn read_property(
+        &self,
+        property: PropertyIdentifier,
+        array_index: Option<u32>,
+    ) -> Result<BACnetValue, BACnetError> {
+        let value = match property {
+            PropertyIdentifier::NotificationClass => BACnetValue::Unsigned(self.instance),
+            PropertyIdentifier::Priority => BACnetValue::Array(
+                self.priority
+                    .iter()
+                    .map(|p| BACnetValue::Unsigned(*p as u32))
+                    .collect(),
+            ),
+            PropertyIdentifier::AckRequired => {
+                BACnetValue::BitString(self.ack_required.to_vec().into())
+            }
+            PropertyIdentifier::RecipientList => BACnetValue::List(
+                self.recipient_list
+                    .iter()
+                    .cloned()
+                    .map(BACnetValue::from)
+                    .collect(),
+            ),
+            _ => return self.read_common_property(property, array_index),
+        };
+        value.array_element(array_index)
+    }
+
+    fn write_property(
+        &mut self,
+        property: PropertyIdentifier,
+        array_index: Option<u32>,
+        value: BACnetValue,
+        _priority: Option<u8>,
+        _time: BACnetDateTime,
+    ) -> Result<(), BACnetError> {
+        let priority = |value| match expect_unsigned(value)? {
+            p if p <= 255 => Ok(p as u8),
+            _ => Err(BACnetError::property(ErrorCode::ValueOutOfRange)),
+        };
+        match property {
+            PropertyIdentifier::Priority => match array_index {
+                Some(i) if (1..=3).contains(&i) => {
+                    self.priority[i as usize - 1] = priority(value)?;
+                    Ok(())
+                }
+                Some(_) => Err(BACnetError::property(ErrorCode::InvalidArrayIndex)),
+                None => match value {
+                    BACnetValue::Array(priorities) if priorities.len() == 3 => {
+                        for (i, p) in priorities.into_iter().enumerate() {
+                            self.priority[i] = priority(p)?;
+                        }
+                        Ok(())
+                    }
+                    _ => Err(invalid_data_type()),
+                },
+            },
+            PropertyIdentifier::AckRequired => {
+                self.ack_required = bits(value)?;
+                Ok(())
+            }
+            PropertyIdentifier::RecipientList => {
+                self.recipient_list = destinations(value)?;
+                Ok(())
+            }
+            _ => Err(self.unwritable(property)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::application::BACnetDate;
+    use crate::encoding::{encode_context, Reader};
+    use crate::objects::testing::at;
+
+    fn destination() -> Destination {
+        Destination {
+            valid_days: [true, true, true, true, true, false, false],
+            from_time: BACnetTime::new(6, 0, 0, 0),
+            to_time: BACnetTime::new(18, 0, 0, 0),
+            issue_confirmed_notifications: true,
+            transitions: [true, false, true],
+            ..Destination::new(
+                Recipient::Device(ObjectIdentifier::new(ObjectType::Device, 7)),
+                3,
+            )
+        }
+    }
+
+    #[test]
+    fn test_destination_applies() {
+        let destination = destination();
+        // 2021-03-01 was a Monday
+        let monday = BACnetDateTime::new(BACnetDate::new(2021, 3, 1), BACnetTime::new(12, 0, 0, 0));
+        assert!(destination.applies(Transition::ToOffnormal, &monday));
+        assert!(!destination.applies(Transition::ToFault, &monday));
+        let evening = BACnetDateTime {
+            time: BACnetTime::new(18, 0, 0, 1),
+            ..monday
+        };
+        assert!(!destination.applies(Transition::ToNormal, &evening));
+        let sunday = BACnetDateTime {
+            date: BACnetDate::new(2021, 2, 28),
+            ..monday
+        };
+        assert!(!destination.applies(Transition::ToNormal, &sunday));
+        assert_eq!(
+            Transition::from(EventState::HighLimit),
+            Transition::ToOffnormal
+        );
+    }
+
+    #[test]
+    fn test_recipient_list() {
+        let mut class = NotificationClass::new(1, "Alarms");
+        let address = Destination::new(Recipient::Address(Address::remote(5, vec![1, 2])), 4);
+        class.recipient_list = vec![destination(), address];
+        let value = class
+            .read_property(PropertyIdentifier::RecipientList, None)
+            .unwrap();
+
+        // Writing the list as decoded from a request restores it
+        let mut data = Vec::new();
+        encode_context(&mut data, 3, &value).unwrap();
+        let mut reader = Reader::new(&data);
+        reader.opening_tag(3).unwrap();
+        let decoded = reader.values_until_closing_tag(3).unwrap();
+        let mut written = NotificationClass::new(2, "Copy");
+        written
+            .write_property(
+                PropertyIdentifier::RecipientList,
+                None,
+                BACnetValue::Array(decoded),
+                None,
+                at(0),
+            )
+            .unwrap();
+        assert_eq!(written.recipient_list, class.recipient_list);
+
+        written
+            .write_property(
+                PropertyIdentifier::Priority,
+                Some(2),
+                BACnetValue::Unsigned(10),
+                None,
+                at(0),
+            )
+            .unwrap();
+        assert_eq!(written.priority, [255, 10, 255]);
+        assert_eq!(
+            written.write_property(
+                PropertyIdentifier::NotificationClass,
+                None,
+                BACnetValue::Unsigned(3),
+                None,
+                at(0)
+            ),
+            Err(BACnetError::property(ErrorCode::WriteAccessDenied))
+        );
+    }
+}
@@ -0,0 +1,112 @@
+use crate::application::{BACnetError, BACnetValue, ErrorCode};
+
+/// Number of command priorities (19.2.1)
+pub const PRIORITIES: usize = 16;
+
+/// Priority reserved for the minimum on/off algorithm (19.2.3)
+pub const MINIMUM_ON_OFF_PRIORITY: u8 = 6;
+
+/// Command prioritization of commandable properties (19.2)
+///
+/// Slot 1 has the highest priority, an empty slot is relinquished.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PriorityArray<T> {
+    slots: [Option<T>; PRIORITIES],
+}
+
+impl<T> Default for PriorityArray<T> {
+    fn default() -> Self {
+        Self {
+            slots: Default::default(),
+        }
+    }
+}
+
+impl<T> PriorityArray<T> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Command or relinquish (`None`) the given priority
+    pub fn set(&mut self, priority: u8, value: Option<T>) -> Result<(), BACnetError> {
+        match priority {
+            MINIMUM_ON_OFF_PRIORITY => Err(BACnetError::property(ErrorCode::WriteAccessDenied)),
+            1..=16 => {
+                self.slots[priority as usize - 1] = value;
+                Ok(())
+            }
+            _ => Err(BACnetError::property(ErrorCode::ValueOutOfRange)),
+        }
+    }
+
+    pub fn get(&self, priority: u8) -> Option<&T> {
+        match priority {
+            1..=16 => self.slots[priority as usize - 1].as_ref(),
+            _ => None,
+        }
+    }
+
+    /// The highest priority command in effect and its priority
+    pub fn effective(&self) -> Option<(u8, &T)> {
+        self.slots
+            .iter()
+            .enumerate()
+            .find_map(|(i, s)| s.as_ref().map(|v| (i as u8 + 1, v)))
+    }
+
+    /// Priority_Array as a BACnetARRAY, relinquished slots are NULL
+    pub fn to_value<F: Fn(&T) -> BACnetValue>(&self, f: F) -> BACnetValue {
+        BACnetValue::Array(
+            self.slots
+                .iter()
+                .map(|s| s.as_ref().map(&f).unwrap_or(BACnetValue::Null))
+                .collect(),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_effective_priority() {
+        let mut array = PriorityArray::new();
+        assert_eq!(array.effective(), None);
+        array.set(10, Some(1.0)).unwrap();
+        array.set(8, Some(2.0)).unwrap();
+        assert_eq!(array.effective(), Some((8, &2.0)));
+        array.set(8, None).unwrap();
+        assert_eq!(array.effective(), Some((10, &1.0)));
+    }
+
+    #[test]
+    fn test_invalid_priority() {
+        let mut array = PriorityArray::new();
+        assert_eq!(
+            array.set(0, Some(1)),
+            Err(BACnetError::property(ErrorCode::ValueOutOfRange))
+        );
+        assert_eq!(
+            array.set(17, Some(1)),
+            Err(BACnetError::property(ErrorCode::ValueOutOfRange))
+        );
+        assert_eq!(
+            array.set(6, Some(1)),
+            Err(BACnetError::property(ErrorCode::WriteAccessDenied))
+        );
+    }
+
+    #[test]
+    fn test_to_value() {
+        let mut array = PriorityArray::new();
+        array.set(16, Some(3)).unwrap();
+        let value = array.to_value(|v| BACnetValue::Unsigned(*v));
+        assert_eq!(
+            value.clone().array_element(Some(0)),
+            Ok(BACnetValue::Unsigned(16))
+        );
+        assert_eq!(value.clone().array_element(Some(1)), Ok(BACnetValue::Null));
+        assert_eq!(value.array_element(Some(16)), Ok(BACnetValue::Unsigned(3)));
+    }
+}
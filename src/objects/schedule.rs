@@ -0,0 +1,616 @@
+use crate::application::{
+    BACnetDate, BACnetDateTime, BACnetError, BACnetTime, BACnetValue,
+    DeviceObjectPropertyReference, ErrorCode, ObjectIdentifier, ObjectType, PropertyIdentifier,
+};
+use crate::objects::calendar::{date_range, date_range_value, in_range};
+use crate::objects::{
+    expect_boolean, expect_unsigned, CalendarEntry, ChannelWrite, Object, PRIORITIES,
+};
+
+use crate::Decode;
+use std::convert::TryFrom;
+
+fn invalid_data_type() -> BACnetError {
+    BACnetError::property(ErrorCode::InvalidDataType)
+}
+
+/// BACnetTimeValue (Clause 21), the value a schedule takes from a time of
+/// the day on
+#[derive(Clone, Debug, PartialEq)]
+pub struct TimeValue {
+    pub time: BACnetTime,
+    /// NULL relinquishes the value
+    pub value: BACnetValue,
+}
+
+impl TimeValue {
+    pub fn new(time: BACnetTime, value: BACnetValue) -> Self {
+        Self { time, value }
+    }
+}
+
+/// A SEQUENCE OF BACnetTimeValue, the times and values in a row
+fn time_values_value(time_values: &[TimeValue]) -> BACnetValue {
+    BACnetValue::Array(
+        time_values
+            .iter()
+            .flat_map(|tv| [BACnetValue::Time(tv.time), tv.value.clone()])
+            .collect(),
+    )
+}
+
+fn time_values(value: BACnetValue) -> Result<Vec<TimeValue>, BACnetError> {
+    let elements = match value {
+        BACnetValue::Array(elements) if elements.len().is_multiple_of(2) => elements,
+        _ => return Err(invalid_data_type()),
+    };
+    let mut elements = elements.into_iter();
+    let mut time_values = Vec::new();
+    while let (Some(time), Some(value)) = (elements.next(), elements.next()) {
+        let time = BACnetTime::try_from(time)?;
+        if matches!(value, BACnetValue::Array(_) | BACnetValue::Constructed(_)) {
+            return Err(invalid_data_type());
+        }
+        time_values.push(TimeValue { time, value });
+    }
+    Ok(time_values)
+}
+
+/// BACnetDailySchedule (Clause 21), the time values of a day in context
+/// tag 0
+fn daily_schedule(value: BACnetValue) -> Result<Vec<TimeValue>, BACnetError> {
+    match value {
+        BACnetValue::Constructed(mut elements) if elements.len() == 1 => match elements.remove(0) {
+            (0, value) => time_values(value),
+            _ => Err(invalid_data_type()),
+        },
+        _ => Err(invalid_data_type()),
+    }
+}
+
+fn context_unsigned(value: BACnetValue) -> Result<u32, BACnetError> {
+    match value {
+        BACnetValue::OctetString(octets) if (1..=4).contains(&octets.len()) => {
+            Ok(octets.iter().fold(0, |v, b| v << 8 | *b as u32))
+        }
+        value => expect_unsigned(value),
+    }
+}
+
+/// When a special event applies
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Period {
+    CalendarEntry(CalendarEntry),
+    /// The days a Calendar object is true
+    CalendarReference(ObjectIdentifier),
+}
+
+/// BACnetSpecialEvent (Clause 21), an exception to the weekly schedule
+#[derive(Clone, Debug, PartialEq)]
+pub struct SpecialEvent {
+    pub period: Period,
+    pub time_values: Vec<TimeValue>,
+    /// 1 (highest) to 16
+    pub priority: u8,
+}
+
+impl From<SpecialEvent> for BACnetValue {
+    fn from(event: SpecialEvent) -> Self {
+        let period = match event.period {
+            Period::CalendarEntry(entry) => (0, entry.into()),
+            Period::CalendarReference(calendar) => (1, BACnetValue::ObjectIdentifier(calendar)),
+        };
+        BACnetValue::Constructed(vec![
+            period,
+            (2, time_values_value(&event.time_values)),
+            (3, BACnetValue::Unsigned(event.priority as u32)),
+        ])
+    }
+}
+
+impl TryFrom<BACnetValue> for SpecialEvent {
+    type Error = BACnetError;
+
+    fn try_from(value: BACnetValue) -> Result<Self, Self::Error> {
+        let elements = match value {
+            BACnetValue::Constructed(elements) if elements.len() == 3 => elements,
+            _ => return Err(invalid_data_type()),
+        };
+        let mut elements = elements.into_iter();
+        let period = match elements.next() {
+            Some((0, entry)) => Period::CalendarEntry(CalendarEntry::try_from(entry)?),
+            Some((1, BACnetValue::ObjectIdentifier(calendar))) => {
+                Period::CalendarReference(calendar)
+            }
+            Some((1, BACnetValue::OctetString(octets))) => {
+                let calendar =
+                    ObjectIdentifier::decode_slice(&octets).map_err(|_| invalid_data_type())?;
+                Period::CalendarReference(calendar)
+            }
+            _ => return Err(invalid_data_type()),
+        };
+        let time_values = match elements.next() {
+            Some((2, value)) => time_values(value)?,
+            _ => return Err(invalid_data_type()),
+        };
+        let priority = match elements.next() {
+            Some((3, value)) => match context_unsigned(value)? {
+                p @ 1..=16 => p as u8,
+                _ => return Err(BACnetError::property(ErrorCode::ValueOutOfRange)),
+            },
+            _ => return Err(invalid_data_type()),
+        };
+        Ok(Self {
+            period,
+            time_values,
+            priority,
+        })
+    }
+}
+
+/// The value of the latest time value at or before `time`, `None` if there
+/// is none or it was relinquished
+fn value_at<'a>(time_values: &'a [TimeValue], time: &BACnetTime) -> Option<&'a BACnetValue> {
+    time_values
+        .iter()
+        .filter(|tv| tv.time <= *time)
+        .max_by_key(|tv| tv.time)
+        .map(|tv| &tv.value)
+        .filter(|value| **value != BACnetValue::Null)
+}
+
+/// Schedule object (12.24)
+///
+/// Present_Value follows the weekly schedule, overridden by the exception
+/// schedule, and is Schedule_Default when neither has a value or outside
+/// the effective period. The schedule is evaluated when the owning runtime
+/// calls [`Schedule::evaluate`] with the local time, changes of the value
+/// queue one [`ChannelWrite`] per referenced property, collected with
+/// [`Schedule::take_writes`].
+#[derive(Clone, Debug)]
+pub struct Schedule {
+    instance: u32,
+    name: String,
+    present_value: BACnetValue,
+    pending: Vec<ChannelWrite>,
+    /// First and last day the schedule is effective, unspecified dates
+    /// leave the period open
+    pub effective_period: (BACnetDate, BACnetDate),
+    /// Time values of each day, from Monday to Sunday
+    pub weekly_schedule: [Vec<TimeValue>; 7],
+    pub exception_schedule: Vec<SpecialEvent>,
+    pub schedule_default: BACnetValue,
+    pub references: Vec<DeviceObjectPropertyReference>,
+    pub priority_for_writing: u8,
+    pub out_of_service: bool,
+}
+
+impl Schedule {
+    /// An always effective schedule without time values, defaulting to
+    /// `schedule_default`
+    pub fn new<S: Into<String>>(instance: u32, name: S, schedule_default: BACnetValue) -> Self {
+        Self {
+            instance,
+            name: name.into(),
+            present_value: schedule_default.clone(),
+            pending: Vec::new(),
+            effective_period: (BACnetDate::ANY, BACnetDate::ANY),
+            weekly_schedule: Default::default(),
+            exception_schedule: Vec::new(),
+            schedule_default,
+            references: Vec::new(),
+            priority_for_writing: PRIORITIES as u8,
+            out_of_service: false,
+        }
+    }
+
+    pub fn present_value(&self) -> &BACnetValue {
+        &self.present_value
+    }
+
+    /// The value scheduled at a local date and time
+    ///
+    /// Exceptions whose period refers to a Calendar object apply on the
+    /// days `calendar` is true for it, see
+    /// [`Calendar::matches`](crate::objects::Calendar::matches)..
+    pub fn value_at(
+        &self,
+        at: &BACnetDateTime,
+        calendar: impl Fn(ObjectIdentifier) -> bool,
+    ) -> BACnetValue {
+        let (start, end) = &self.effective_period;
+        if !in_range(start, end, &at.date) {
+            return self.schedule_default.clone();
+        }
+        // The highest priority exception with a value, the first one of
+        // equal priorities
+        let exception = self
+            .exception_schedule
+            .iter()
+            .filter(|event| match &event.period {
+                Period::CalendarEntry(entry) => entry.matches(&at.date),
+                Period::CalendarReference(object) => calendar(*object),
+            })
+            .filter_map(|event| Some((event.priority, value_at(&event.time_values, &at.time)?)))
+            .min_by_key(|(priority, _)| *priority);
+        if let Some((_, value)) = exception {
+            return value.clone();
+        }
+        let day = match at.date.weekday {
+            weekday @ 1..=7 => &self.weekly_schedule[weekday as usize - 1],
+            _ => return self.schedule_default.clone(),
+        };
+        value_at(day, &at.time)
+            .cloned()
+            .unwrap_or_else(|| self.schedule_default.clone())
+    }
+
+    /// Update Present_Value to the value scheduled at the local date and
+    /// time, writing it to the referenced properties if it changed
+    ///
+    /// The value is not updated while the schedule is out of service.
+    pub fn evaluate(&mut self, at: &BACnetDateTime, calendar: impl Fn(ObjectIdentifier) -> bool) {
+        if self.out_of_service {
+            return;
+        }
+        let value = self.value_at(at, calendar);
+        if value != self.present_value {
+            self.present_value = value;
+            self.queue_writes();
+        }
+    }
+
+    /// Writes queued since the last call
+    pub fn take_writes(&mut self) -> Vec<ChannelWrite> {
+        std::mem::take(&mut self.pending)
+    }
+
+    fn queue_writes(&mut self) {
+        let writes: Vec<_> = self
+            .references
+            .iter()
+            .map(|reference| ChannelWrite {
+                reference: *reference,
+                value: self.present_value.clone(),
+                priority: self.priority_for_writing,
+            })
+            .collect();
+        self.pending.extend(writes);
+    }
+}
+
+impl Object for Schedule {
+    fn object_identifier(&self) -> ObjectIdentifier {
+        ObjectIdentifier::new(ObjectType::Schedule, self.instance)
+    }
+
+    fn object_name(&self) -> &str {
+        &self.name
+    }
+
+    fn property_list(&self) -> Vec<PropertyIdentifier> {
+        use PropertyIdentifier::*;
+        vec![
+            PresentValue,
+            EffectivePeriod,
+            WeeklySchedule,
+            ExceptionSchedule,
+            ScheduleDefault,
+            ListOfObjectPropertyReferences,
+            PriorityForWriting,
+            StatusFlags,
+            Reliability,
+            OutOfService,
+        ]
+    }
+
+    fn read_property(
+        &self,
+        property: PropertyIdentifier,
+        array_index: Option<u32>,
+    ) -> Result<BACnetValue, BACnetError> {
+        let value = match property {
+            PropertyIdentifier::PresentValue => self.present_value.clone(),
+            PropertyIdentifier::EffectivePeriod => {
+                date_range_value(self.effective_period.0, self.effective_period.1)
+            }
+            PropertyIdentifier::WeeklySchedule => BACnetValue::Array(
+                self.weekly_schedule
+                    .iter()
+                    .map(|day| BACnetValue::Constructed(vec![(0, time_values_value(day))]))
+                    .collect(),
+            ),
+            PropertyIdentifier::ExceptionSchedule => BACnetValue::Array(
+                self.exception_schedule
+                    .iter()
+                    .cloned()
+                    .map(BACnetValue::from)
+                    .collect(),
+            ),
+            PropertyIdentifier::ScheduleDefault => self.schedule_default.clone(),
+            PropertyIdentifier::ListOfObjectPropertyReferences => {
+                BACnetValue::List(self.references.iter().map(|r| (*r).into()).collect())
+            }
+            PropertyIdentifier::PriorityForWriting => {
+                BACnetValue::Unsigned(self.priority_for_writing as u32)
+            }
+            PropertyIdentifier::StatusFlags => {
+                BACnetValue::BitString(vec![false, false, false, self.out_of_service].into())
+            }
+            PropertyIdentifier::Reliability => BACnetValue::Enumerated(0), // no-fault-detected
+            PropertyIdentifier::OutOfService => BACnetValue::Boolean(self.out_of_service),
+            _ => return self.read_common_property(property, array_index),
+        };
+        value.array_element(array_index)
+    }
+
+    fn write_property(
+        &mut self,
+        property: PropertyIdentifier,
+        array_index: Option<u32>,
+        value: BACnetValue,
+        _priority: Option<u8>,
+        _time: BACnetDateTime,
+    ) -> Result<(), BACnetError> {
+        match property {
+            PropertyIdentifier::PresentValue if self.out_of_service => {
+                self.present_value = value;
+                self.queue_writes();
+                Ok(())
+            }
+            PropertyIdentifier::EffectivePeriod => {
+                self.effective_period = date_range(value)?;
+                Ok(())
+            }
+            PropertyIdentifier::WeeklySchedule => match array_index {
+                Some(day @ 1..=7) => {
+                    self.weekly_schedule[day as usize - 1] = daily_schedule(value)?;
+                    Ok(())
+                }
+                Some(_) => Err(BACnetError::property(ErrorCode::InvalidArrayIndex)),
+                None => match value {
+                    BACnetValue::Array(days) if days.len() == 7 => {
+                        let mut weekly_schedule: [Vec<TimeValue>; 7] = Default::default();
+                        for (i, day) in days.into_iter().enumerate() {
+                            weekly_schedule[i] = daily_schedule(day)?;
+                        }
+                        self.weekly_schedule = weekly_schedule;
+                        Ok(())
+                    }
+                    _ => Err(invalid_data_type()),
+                },
+            },
+            PropertyIdentifier::ExceptionSchedule if array_index.is_none() => {
+                self.exception_schedule = match value {
+                    BACnetValue::Array(events) => events
+                        .into_iter()
+                        .map(SpecialEvent::try_from)
+                        .collect::<Result<_, _>>()?,
+                    // A single event is not decoded as an array
+                    event => vec![SpecialEvent::try_from(event)?],
+                };
+                Ok(())
+            }
+            PropertyIdentifier::ScheduleDefault => {
+                self.schedule_default = value;
+                Ok(())
+            }
+            PropertyIdentifier::PriorityForWriting => match expect_unsigned(value)? {
+                p @ 1..=16 => {
+                    self.priority_for_writing = p as u8;
+                    Ok(())
+                }
+                _ => Err(BACnetError::property(ErrorCode::ValueOutOfRange)),
+            },
+            PropertyIdentifier::OutOfService => {
+                self.out_of_service = expect_boolean(value)?;
+                Ok(())
+            }
+            _ => Err(self.unwritable(property)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::application::UNSPECIFIED;
+    use crate::encoding::{encode_application, Reader};
+
+    fn at(year: u16, month: u8, day: u8, hour: u8) -> BACnetDateTime {
+        BACnetDateTime::new(
+            BACnetDate::new(year, month, day),
+            BACnetTime::new(hour, 0, 0, 0),
+        )
+    }
+
+    fn time_value(hour: u8, value: BACnetValue) -> TimeValue {
+        TimeValue::new(BACnetTime::new(hour, 0, 0, 0), value)
+    }
+
+    /// Occupied from 8 to 18 on weekdays
+    fn schedule() -> Schedule {
+        let mut schedule = Schedule::new(1, "Occupancy", BACnetValue::Enumerated(0));
+        for day in &mut schedule.weekly_schedule[..5] {
+            *day = vec![
+                time_value(8, BACnetValue::Enumerated(1)),
+                time_value(18, BACnetValue::Null),
+            ];
+        }
+        schedule
+    }
+
+    /// Encode a value as a request would and decode it back
+    fn round_trip(value: &BACnetValue) -> BACnetValue {
+        let mut data = Vec::new();
+        encode_application(&mut data, value).unwrap();
+        let mut values = Reader::new(&data).values_to_end().unwrap();
+        match values.len() {
+            1 => values.remove(0),
+            _ => BACnetValue::Array(values),
+        }
+    }
+
+    #[test]
+    fn test_weekly_schedule() {
+        let schedule = schedule();
+        // Monday 2024-01-15
+        assert_eq!(
+            schedule.value_at(&at(2024, 1, 15, 7), |_| false),
+            BACnetValue::Enumerated(0)
+        );
+        assert_eq!(
+            schedule.value_at(&at(2024, 1, 15, 8), |_| false),
+            BACnetValue::Enumerated(1)
+        );
+        assert_eq!(
+            schedule.value_at(&at(2024, 1, 15, 18), |_| false),
+            BACnetValue::Enumerated(0)
+        );
+        // Saturday
+        assert_eq!(
+            schedule.value_at(&at(2024, 1, 20, 12), |_| false),
+            BACnetValue::Enumerated(0)
+        );
+    }
+
+    #[test]
+    fn test_exception_schedule() {
+        let mut schedule = schedule();
+        let holiday = BACnetDate {
+            year: UNSPECIFIED,
+            ..BACnetDate::new(2024, 1, 1)
+        };
+        let calendar = ObjectIdentifier::new(ObjectType::Calendar, 1);
+        let holiday = SpecialEvent {
+            period: Period::CalendarEntry(CalendarEntry::Date(BACnetDate {
+                weekday: UNSPECIFIED,
+                ..holiday
+            })),
+            time_values: vec![time_value(0, BACnetValue::Enumerated(0))],
+            priority: 10,
+        };
+        // Evenings of the last Friday of the month
+        let last_friday = SpecialEvent {
+            period: Period::CalendarEntry(CalendarEntry::WeekNDay {
+                month: UNSPECIFIED,
+                week_of_month: 6,
+                day_of_week: 5,
+            }),
+            time_values: vec![
+                time_value(18, BACnetValue::Enumerated(1)),
+                time_value(22, BACnetValue::Null),
+            ],
+            priority: 12,
+        };
+        let maintenance = SpecialEvent {
+            period: Period::CalendarReference(calendar),
+            time_values: vec![time_value(10, BACnetValue::Enumerated(2))],
+            priority: 8,
+        };
+        schedule.exception_schedule = vec![holiday, last_friday, maintenance];
+
+        // New Year's Day 2024 is a Monday
+        assert_eq!(
+            schedule.value_at(&at(2024, 1, 1, 9), |_| false),
+            BACnetValue::Enumerated(0)
+        );
+        assert_eq!(
+            schedule.value_at(&at(2024, 1, 1, 11), |c| c == calendar),
+            BACnetValue::Enumerated(2)
+        );
+        // The last Friday of January 2024 is the 26th
+        assert_eq!(
+            schedule.value_at(&at(2024, 1, 26, 19), |_| false),
+            BACnetValue::Enumerated(1)
+        );
+        assert_eq!(
+            schedule.value_at(&at(2024, 1, 26, 22), |_| false),
+            BACnetValue::Enumerated(0)
+        );
+        assert_eq!(
+            schedule.value_at(&at(2024, 1, 19, 19), |_| false),
+            BACnetValue::Enumerated(0)
+        );
+    }
+
+    #[test]
+    fn test_effective_period_and_writes() {
+        let mut schedule = schedule();
+        let output = ObjectIdentifier::new(ObjectType::MultiStateOutput, 3);
+        schedule.references = vec![DeviceObjectPropertyReference::new(
+            output,
+            PropertyIdentifier::PresentValue,
+        )];
+        schedule.priority_for_writing = 12;
+        schedule.effective_period = (BACnetDate::new(2024, 1, 1), BACnetDate::new(2024, 6, 30));
+
+        schedule.evaluate(&at(2024, 1, 15, 9), |_| false);
+        let writes = schedule.take_writes();
+        assert_eq!(writes.len(), 1);
+        assert_eq!(writes[0].value, BACnetValue::Enumerated(1));
+        assert_eq!(writes[0].priority, 12);
+        // Unchanged values are not written again
+        schedule.evaluate(&at(2024, 1, 15, 10), |_| false);
+        assert!(schedule.take_writes().is_empty());
+        schedule.evaluate(&at(2024, 7, 1, 9), |_| false);
+        assert_eq!(schedule.present_value(), &BACnetValue::Enumerated(0));
+        assert_eq!(schedule.take_writes().len(), 1);
+    }
+
+    #[test]
+    fn test_write_schedules() {
+        let mut setpoint = Schedule::new(1, "Setpoint", BACnetValue::Real(18.0));
+        let weekly = schedule().read_property(PropertyIdentifier::WeeklySchedule, None);
+        let weekly = round_trip(&weekly.unwrap());
+        setpoint
+            .write_property(
+                PropertyIdentifier::WeeklySchedule,
+                None,
+                weekly,
+                None,
+                at(2021, 1, 25, 12),
+            )
+            .unwrap();
+        assert_eq!(setpoint.weekly_schedule, schedule().weekly_schedule);
+
+        let events = vec![
+            SpecialEvent {
+                period: Period::CalendarEntry(CalendarEntry::DateRange(
+                    BACnetDate::new(2024, 12, 24),
+                    BACnetDate::new(2024, 12, 26),
+                )),
+                time_values: vec![time_value(0, BACnetValue::Real(15.0))],
+                priority: 3,
+            },
+            SpecialEvent {
+                period: Period::CalendarReference(ObjectIdentifier::new(ObjectType::Calendar, 2)),
+                time_values: vec![],
+                priority: 16,
+            },
+        ];
+        for events in [events.clone(), events[..1].to_vec()] {
+            let value = BACnetValue::Array(events.iter().cloned().map(BACnetValue::from).collect());
+            setpoint
+                .write_property(
+                    PropertyIdentifier::ExceptionSchedule,
+                    None,
+                    round_trip(&value),
+                    None,
+                    at(2021, 1, 25, 12),
+                )
+                .unwrap();
+            assert_eq!(setpoint.exception_schedule, events);
+        }
+        assert_eq!(
+            setpoint.write_property(
+                PropertyIdentifier::PriorityForWriting,
+                None,
+                BACnetValue::Unsigned(17),
+                None,
+                at(2021, 1, 25, 12)
+            ),
+            Err(BACnetError::property(ErrorCode::ValueOutOfRange))
+        );
+    }
+}
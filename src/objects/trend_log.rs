@@ -0,0 +1,584 @@
+use crate::application::{
+    BACnetDateTime, BACnetError, BACnetValue, CovNotification, DeviceObjectPropertyReference,
+    ErrorCode, ObjectIdentifier, ObjectType, PropertyIdentifier, Range,
+};
+use crate::encoding::*;
+use crate::objects::{
+    expect_boolean, expect_unsigned, LogBuffer, LogEntry, LogStatus, Object, RangeItems,
+};
+
+use num_derive::{FromPrimitive, ToPrimitive};
+use num_traits::FromPrimitive;
+use std::time::{Duration, SystemTime};
+
+/// log-datum of a BACnetLogRecord (Clause 21)
+#[derive(Clone, Debug, PartialEq)]
+pub enum LogDatum {
+    LogStatus(LogStatus),
+    /// A logged value, any-value is returned as [`BACnetValue::Array`] if it
+    /// has more than one element
+    Value(BACnetValue),
+    /// Reading the monitored property failed
+    Failure(BACnetError),
+    /// The clock was changed by this many seconds
+    TimeChange(f32),
+}
+
+/// A record of a Trend Log, the datum with the status flags of the
+/// monitored object if they are known
+#[derive(Clone, Debug, PartialEq)]
+pub struct LogRecord {
+    pub datum: LogDatum,
+    pub status_flags: Option<Vec<bool>>,
+}
+
+/// BACnetLoggingType (Clause 21)
+#[derive(Copy, Clone, Debug, Eq, PartialEq, FromPrimitive, ToPrimitive)]
+pub enum LoggingType {
+    Polled = 0,
+    Cov = 1,
+    Triggered = 2,
+}
+
+/// Encode the log-datum CHOICE of a BACnetLogRecord
+fn encode_datum(buf: &mut Vec<u8>, datum: &LogDatum) -> crate::error::Result<()> {
+    match datum {
+        LogDatum::LogStatus(status) => {
+            let bits = vec![
+                status.log_disabled,
+                status.buffer_purged,
+                status.log_interrupted,
+            ];
+            encode_context_bit_string(buf, 0, &bits);
+        }
+        LogDatum::Value(value) => {
+            let tag = match value {
+                BACnetValue::Boolean(_) => 1,
+                BACnetValue::Real(_) => 2,
+                BACnetValue::Enumerated(_) => 3,
+                BACnetValue::Unsigned(_) => 4,
+                BACnetValue::Signed(_) => 5,
+                BACnetValue::BitString(_) => 6,
+                BACnetValue::Null => 7,
+                value => {
+                    encode_opening_tag(buf, 10);
+                    encode_application(buf, value)?;
+                    encode_closing_tag(buf, 10);
+                    return Ok(());
+                }
+            };
+            encode_context(buf, tag, value)?;
+        }
+        LogDatum::Failure(error) => {
+            encode_opening_tag(buf, 8);
+            encode_enumerated(buf, error.error_class as u32);
+            encode_enumerated(buf, error.error_code as u32);
+            encode_closing_tag(buf, 8);
+        }
+        LogDatum::TimeChange(offset) => encode_context_real(buf, 9, *offset),
+    }
+    Ok(())
+}
+
+/// Encode a BACnetLogRecord (Clause 21)
+fn encode_record(buf: &mut Vec<u8>, entry: &LogEntry<LogRecord>) -> crate::error::Result<()> {
+    entry.timestamp.encode_context(buf, 0);
+    encode_opening_tag(buf, 1);
+    encode_datum(buf, &entry.datum.datum)?;
+    encode_closing_tag(buf, 1);
+    if let Some(flags) = &entry.datum.status_flags {
+        encode_context_bit_string(buf, 2, flags);
+    }
+    Ok(())
+}
+
+/// Trend Log object (12.25)
+///
+/// The value of the monitored property is logged every
+/// [`log_interval`](Self::log_interval) when the owning runtime calls
+/// [`TrendLogObject::poll`], or from the COV notifications passed to
+/// [`TrendLogObject::cov_notification`]. Records are read back with
+/// ReadRange, reading Log_Buffer with ReadProperty is denied.
+#[derive(Clone, Debug)]
+pub struct TrendLogObject {
+    instance: u32,
+    name: String,
+    pub description: Option<String>,
+    enable: bool,
+    pub log_device_object_property: Option<DeviceObjectPropertyReference>,
+    pub logging_type: LoggingType,
+    pub log_interval: Duration,
+    /// Disable logging once the buffer is full instead of dropping the
+    /// oldest records
+    pub stop_when_full: bool,
+    buffer: LogBuffer<LogRecord>,
+    last_poll: Option<SystemTime>,
+}
+
+impl TrendLogObject {
+    /// A Trend Log polling once a minute
+    pub fn new<S: Into<String>>(instance: u32, name: S, buffer_size: u32) -> Self {
+        Self {
+            instance,
+            name: name.into(),
+            description: None,
+            enable: true,
+            log_device_object_property: None,
+            logging_type: LoggingType::Polled,
+            log_interval: Duration::from_secs(60),
+            stop_when_full: false,
+            buffer: LogBuffer::new(buffer_size),
+            last_poll: None,
+        }
+    }
+
+    fn push(&mut self, timestamp: BACnetDateTime, record: LogRecord) -> Option<u32> {
+        if !self.enable {
+            return None;
+        }
+        let sequence_number = self.buffer.push(timestamp, record);
+        if self.stop_when_full && self.buffer.len() >= self.buffer.buffer_size() as usize {
+            self.enable = false;
+        }
+        Some(sequence_number)
+    }
+
+    /// Record a value of the monitored property, or the error reading it
+    ///
+    /// Returns the sequence number of the new record or `None` while logging
+    /// is disabled.
+    pub fn log(
+        &mut self,
+        timestamp: BACnetDateTime,
+        value: Result<BACnetValue, BACnetError>,
+        status_flags: Option<Vec<bool>>,
+    ) -> Option<u32> {
+        let datum = match value {
+            Ok(value) => LogDatum::Value(value),
+            Err(error) => LogDatum::Failure(error),
+        };
+        self.push(
+            timestamp,
+            LogRecord {
+                datum,
+                status_flags,
+            },
+        )
+    }
+
+    /// Log the monitored property with `read` if the log interval passed
+    /// since the last poll, only for polled logging
+    pub fn poll(
+        &mut self,
+        timestamp: BACnetDateTime,
+        read: impl FnOnce(&DeviceObjectPropertyReference) -> Result<BACnetValue, BACnetError>,
+    ) -> Option<u32> {
+        let reference = self.log_device_object_property?;
+        if self.logging_type != LoggingType::Polled || !self.enable {
+            return None;
+        }
+        let now = timestamp.system_time()?;
+        let due = match self.last_poll {
+            Some(last) => now
+                .duration_since(last)
+                .map_or(true, |elapsed| elapsed >= self.log_interval),
+            None => true,
+        };
+        if !due {
+            return None;
+        }
+        self.last_poll = Some(now);
+        self.log(timestamp, read(&reference), None)
+    }
+
+    /// Log the monitored property from a COV notification about its
+    /// object, only for COV logging
+    pub fn cov_notification(
+        &mut self,
+        timestamp: BACnetDateTime,
+        notification: &CovNotification,
+    ) -> Option<u32> {
+        let reference = self.log_device_object_property?;
+        if self.logging_type != LoggingType::Cov
+            || reference.object_identifier != notification.monitored_object_identifier
+            || reference
+                .device_identifier
+                .is_some_and(|d| d != notification.initiating_device_identifier)
+        {
+            return None;
+        }
+        let value = |property| {
+            notification
+                .values
+                .iter()
+                .find(|v| v.property_identifier == property)
+                .map(|v| v.value.clone())
+        };
+        let status_flags = match value(PropertyIdentifier::StatusFlags) {
+            Some(BACnetValue::BitString(flags)) => Some(flags.into()),
+            _ => None,
+        };
+        let value = value(reference.property_identifier)?;
+        self.log(timestamp, Ok(value), status_flags)
+    }
+
+    /// Record a change of the device clock by `offset` seconds
+    pub fn log_time_change(&mut self, timestamp: BACnetDateTime, offset: f32) -> Option<u32> {
+        let datum = LogDatum::TimeChange(offset);
+        self.push(
+            timestamp,
+            LogRecord {
+                datum,
+                status_flags: None,
+            },
+        )
+    }
+
+    pub fn enable(&self) -> bool {
+        self.enable
+    }
+
+    /// Enable or disable logging, adding a log-status record on changes
+    pub fn set_enable(&mut self, timestamp: BACnetDateTime, enable: bool) {
+        if self.enable != enable {
+            self.enable = enable;
+            let status = LogStatus {
+                log_disabled: !enable,
+                ..Default::default()
+            };
+            self.buffer.push(
+                timestamp,
+                LogRecord {
+                    datum: LogDatum::LogStatus(status),
+                    status_flags: None,
+                },
+            );
+        }
+    }
+
+    /// Delete all records, leaving a log-status record with buffer-purged set
+    pub fn purge(&mut self, timestamp: BACnetDateTime) {
+        self.buffer.clear();
+        let status = LogStatus {
+            log_disabled: !self.enable,
+            buffer_purged: true,
+            ..Default::default()
+        };
+        self.buffer.push(
+            timestamp,
+            LogRecord {
+                datum: LogDatum::LogStatus(status),
+                status_flags: None,
+            },
+        );
+    }
+
+    pub fn records(&self) -> &LogBuffer<LogRecord> {
+        &self.buffer
+    }
+}
+
+impl Object for TrendLogObject {
+    fn object_identifier(&self) -> ObjectIdentifier {
+        ObjectIdentifier::new(ObjectType::TrendLog, self.instance)
+    }
+
+    fn object_name(&self) -> &str {
+        &self.name
+    }
+
+    fn property_list(&self) -> Vec<PropertyIdentifier> {
+        use PropertyIdentifier::*;
+        let mut properties = vec![
+            StatusFlags,
+            EventState,
+            Enable,
+            StopWhenFull,
+            BufferSize,
+            LogBuffer,
+            RecordCount,
+            TotalRecordCount,
+            LoggingType,
+        ];
+        if self.logging_type == self::LoggingType::Polled {
+            properties.push(LogInterval);
+        }
+        if self.log_device_object_property.is_some() {
+            properties.push(LogDeviceObjectProperty);
+        }
+        if self.description.is_some() {
+            properties.push(Description);
+        }
+        properties
+    }
+
+    fn read_property(
+        &self,
+        property: PropertyIdentifier,
+        array_index: Option<u32>,
+    ) -> Result<BACnetValue, BACnetError> {
+        let value = match property {
+            PropertyIdentifier::Description => match &self.description {
+                Some(d) => BACnetValue::CharacterString(d.as_str().into()),
+                None => return Err(BACnetError::property(ErrorCode::UnknownProperty)),
+            },
+            PropertyIdentifier::StatusFlags => BACnetValue::BitString(vec![false; 4].into()),
+            PropertyIdentifier::EventState => BACnetValue::Enumerated(0), // normal
+            PropertyIdentifier::Enable => BACnetValue::Boolean(self.enable),
+            PropertyIdentifier::StopWhenFull => BACnetValue::Boolean(self.stop_when_full),
+            PropertyIdentifier::BufferSize => BACnetValue::Unsigned(self.buffer.buffer_size()),
+            PropertyIdentifier::LogBuffer => {
+                return Err(BACnetError::property(ErrorCode::ReadAccessDenied))
+            }
+            PropertyIdentifier::RecordCount => BACnetValue::Unsigned(self.buffer.len() as u32),
+            PropertyIdentifier::TotalRecordCount => {
+                BACnetValue::Unsigned(self.buffer.total_record_count())
+            }
+            PropertyIdentifier::LoggingType => BACnetValue::Enumerated(self.logging_type as u32),
+            // In hundredths of a second
+            PropertyIdentifier::LogInterval if self.logging_type == LoggingType::Polled => {
+                BACnetValue::Unsigned((self.log_interval.as_millis() / 10) as u32)
+            }
+            PropertyIdentifier::LogDeviceObjectProperty => match self.log_device_object_property {
+                Some(reference) => BACnetValue::from(reference),
+                None => return Err(BACnetError::property(ErrorCode::UnknownProperty)),
+            },
+            _ => return self.read_common_property(property, array_index),
+        };
+        value.array_element(array_index)
+    }
+
+    fn write_property(
+        &mut self,
+        property: PropertyIdentifier,
+        _array_index: Option<u32>,
+        value: BACnetValue,
+        _priority: Option<u8>,
+        time: BACnetDateTime,
+    ) -> Result<(), BACnetError> {
+        match property {
+            PropertyIdentifier::Enable => {
+                let enable = expect_boolean(value)?;
+                self.set_enable(time, enable);
+                Ok(())
+            }
+            PropertyIdentifier::StopWhenFull => {
+                self.stop_when_full = expect_boolean(value)?;
+                Ok(())
+            }
+            PropertyIdentifier::LogInterval if self.logging_type == LoggingType::Polled => {
+                let hundredths = expect_unsigned(value)?;
+                self.log_interval = Duration::from_millis(hundredths as u64 * 10);
+                Ok(())
+            }
+            PropertyIdentifier::LoggingType => match value {
+                BACnetValue::Enumerated(e) => {
+                    self.logging_type = LoggingType::from_u32(e)
+                        .ok_or_else(|| BACnetError::property(ErrorCode::ValueOutOfRange))?;
+                    Ok(())
+                }
+                _ => Err(BACnetError::property(ErrorCode::InvalidDataType)),
+            },
+            // Only zero may be written, which clears the buffer (12.25.15)
+            PropertyIdentifier::RecordCount => match expect_unsigned(value)? {
+                0 => {
+                    self.purge(time);
+                    Ok(())
+                }
+                _ => Err(BACnetError::property(ErrorCode::ValueOutOfRange)),
+            },
+            _ => Err(self.unwritable(property)),
+        }
+    }
+
+    fn read_range(
+        &self,
+        property: PropertyIdentifier,
+        array_index: Option<u32>,
+        range: Option<&Range>,
+    ) -> Result<RangeItems, BACnetError> {
+        match (property, array_index) {
+            // Logged values are encoded again, which fails for an object
+            // identifier out of range
+            (PropertyIdentifier::LogBuffer, None) => self
+                .buffer
+                .read_range(range)
+                .encode(encode_record)
+                .map_err(|_| BACnetError::property(ErrorCode::Other)),
+            (PropertyIdentifier::LogBuffer, Some(_)) => {
+                Err(BACnetError::property(ErrorCode::PropertyIsNotAnArray))
+            }
+            _ => Err(self.not_a_list(property)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::application::{ErrorClass, PropertyValue};
+    use crate::objects::testing::at;
+
+    fn trend_log() -> TrendLogObject {
+        let mut log = TrendLogObject::new(1, "Temperature", 3);
+        let input = ObjectIdentifier::new(ObjectType::AnalogInput, 2);
+        log.log_device_object_property = Some(DeviceObjectPropertyReference::new(
+            input,
+            PropertyIdentifier::PresentValue,
+        ));
+        log
+    }
+
+    #[test]
+    fn test_poll() {
+        let mut log = trend_log();
+        let read = |_: &DeviceObjectPropertyReference| Ok(BACnetValue::Real(21.5));
+        assert_eq!(log.poll(at(0), read), Some(1));
+        // Not before the log interval passed
+        assert_eq!(log.poll(at(0), read), None);
+        assert_eq!(
+            log.poll(at(1), |_| Err(BACnetError::object(
+                ErrorCode::UnknownObject
+            ))),
+            Some(2)
+        );
+        let records: Vec<_> = log.records().iter().map(|r| &r.datum.datum).collect();
+        assert_eq!(
+            records,
+            [
+                &LogDatum::Value(BACnetValue::Real(21.5)),
+                &LogDatum::Failure(BACnetError::object(ErrorCode::UnknownObject))
+            ]
+        );
+
+        log.logging_type = LoggingType::Cov;
+        assert_eq!(log.poll(at(2), read), None);
+        assert!(!log.has_property(PropertyIdentifier::LogInterval));
+    }
+
+    #[test]
+    fn test_cov_notification() {
+        let mut log = trend_log();
+        log.logging_type = LoggingType::Cov;
+        let mut notification = CovNotification {
+            subscriber_process_identifier: 1,
+            initiating_device_identifier: ObjectIdentifier::new(ObjectType::Device, 12),
+            monitored_object_identifier: ObjectIdentifier::new(ObjectType::AnalogInput, 2),
+            time_remaining: 0,
+            values: vec![
+                PropertyValue::new(PropertyIdentifier::PresentValue, BACnetValue::Real(20.0)),
+                PropertyValue::new(
+                    PropertyIdentifier::StatusFlags,
+                    BACnetValue::BitString(vec![false, true, false, false].into()),
+                ),
+            ],
+        };
+        assert_eq!(log.cov_notification(at(0), &notification), Some(1));
+        let record = &log.records().iter().next().unwrap().datum;
+        assert_eq!(record.datum, LogDatum::Value(BACnetValue::Real(20.0)));
+        assert_eq!(record.status_flags, Some(vec![false, true, false, false]));
+
+        notification.monitored_object_identifier =
+            ObjectIdentifier::new(ObjectType::AnalogInput, 3);
+        assert_eq!(log.cov_notification(at(1), &notification), None);
+    }
+
+    #[test]
+    fn test_stop_when_full() {
+        let mut log = trend_log();
+        log.stop_when_full = true;
+        for minute in 0..3 {
+            assert!(log
+                .log(at(minute), Ok(BACnetValue::Boolean(true)), None)
+                .is_some());
+        }
+        assert!(!log.enable());
+        assert_eq!(log.log(at(3), Ok(BACnetValue::Boolean(true)), None), None);
+        assert_eq!(
+            log.read_property(PropertyIdentifier::RecordCount, None),
+            Ok(BACnetValue::Unsigned(3))
+        );
+    }
+
+    #[test]
+    fn test_write_enable_and_purge() {
+        let mut log = trend_log();
+        log.write_property(
+            PropertyIdentifier::Enable,
+            None,
+            BACnetValue::Boolean(false),
+            None,
+            at(5),
+        )
+        .unwrap();
+        let record = log.records().iter().last().unwrap();
+        assert_eq!(record.timestamp, at(5));
+        assert!(matches!(
+            record.datum.datum,
+            LogDatum::LogStatus(LogStatus {
+                log_disabled: true,
+                ..
+            })
+        ));
+
+        log.write_property(
+            PropertyIdentifier::RecordCount,
+            None,
+            BACnetValue::Unsigned(0),
+            None,
+            at(6),
+        )
+        .unwrap();
+        assert_eq!(log.records().len(), 1);
+        assert_eq!(log.records().iter().next().unwrap().timestamp, at(6));
+    }
+
+    #[test]
+    fn test_read_range() {
+        let mut log = trend_log();
+        log.log(at(0), Ok(BACnetValue::Real(1.0)), Some(vec![false; 4]));
+        log.set_enable(at(1), false);
+        log.set_enable(at(2), true);
+        log.log_time_change(at(3), -60.0);
+
+        let range = Range::BySequenceNumber {
+            reference_sequence_number: 3,
+            count: 2,
+        };
+        let items = log
+            .read_range(PropertyIdentifier::LogBuffer, None, Some(&range))
+            .unwrap();
+        assert!(items.result_flags.last_item);
+        assert!(!items.result_flags.first_item);
+        assert_eq!(items.first_sequence_number, Some(3));
+        assert_eq!(
+            items.items.iter().map(hex::encode).collect::<Vec<_>>(),
+            [
+                // Log enabled again, then the clock set back a minute
+                "0ea479011901b40c0200000f1e0a05001f",
+                "0ea479011901b40c0300000f1e9cc27000001f",
+            ]
+        );
+
+        // The first value was dropped from the full buffer
+        let items = log
+            .read_range(PropertyIdentifier::LogBuffer, None, None)
+            .unwrap();
+        assert_eq!(items.items.len(), 3);
+        assert_eq!(
+            hex::encode(&items.items[0]),
+            "0ea479011901b40c0100000f1e0a05801f"
+        );
+        assert_eq!(
+            log.read_range(PropertyIdentifier::Enable, None, None),
+            Err(BACnetError::new(
+                ErrorClass::Services,
+                ErrorCode::PropertyIsNotAList
+            ))
+        );
+        assert_eq!(
+            log.read_property(PropertyIdentifier::LogBuffer, None),
+            Err(BACnetError::property(ErrorCode::ReadAccessDenied))
+        );
+    }
+}
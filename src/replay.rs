@@ -0,0 +1,479 @@
+//! Replay of recorded traffic through the stack
+//!
+//! A [`Replay`] hands the frames a station received in a recording to a
+//! [`BacnetClient`](crate::client::BacnetClient) or
+//! [`BacnetDevice`](crate::server::BacnetDevice) over a [`ReplayLink`], at
+//! the times they were recorded, and keeps what the stack sends in their
+//! place. Field issues are reproduced from a capture of the site, and the
+//! behavior of the stack is regression tested against real-world traffic.
+//!
+//! Recordings are read from a pcap or pcapng capture (with the `capture`
+//! feature) or written as hex, one frame per line with the time in
+//! seconds, `>` for frames received by the station and `<` for frames it
+//! sent, the MAC address of the peer (empty `-` for broadcasts) and the
+//! NPDU:
+//!
+//! ```
+//! use bacnet::clock::MockClock;
+//! use bacnet::replay::Replay;
+//! use bacnet::server::{BacnetDevice, DeviceInfo};
+//! use std::time::{Duration, UNIX_EPOCH};
+//!
+//! # async_std::task::block_on(async {
+//! let mut replay = Replay::parse(
+//!     "# Who-Is of a workstation, answered with an I-Am broadcast
+//!      0.0 > 0a000002bac0 0120ffff00ff 1008
+//!      0.0 < - 0100 1000 c4020004d2 2205c4 9103 2108",
+//! )?;
+//! let clock = MockClock::new(UNIX_EPOCH);
+//! replay.set_clock(clock.clone());
+//! let (link, log) = replay.link();
+//! let device = BacnetDevice::new(link, DeviceInfo::new(1234, "Controller", 8));
+//! log.finished().await;
+//! assert_eq!(log.sent().len(), log.expected().len());
+//! # Ok::<(), std::io::Error>(())
+//! # });
+//! ```
+
+use crate::clock::{Clock, SystemClock};
+use crate::network::NPDU;
+use crate::transport::{BoxFuture, DataLink};
+use crate::Decode;
+
+use async_std::channel::{self, Receiver, Sender};
+use std::io::{Error, ErrorKind};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// A frame of a recording
+#[derive(Clone, Debug, PartialEq)]
+pub struct ReplayFrame {
+    /// Time since the start of the recording
+    pub time: Duration,
+    /// The frame was sent by the station, not received
+    pub sent: bool,
+    /// MAC address of the sender of a received frame, of the destination of
+    /// a sent one, empty for broadcasts
+    pub peer: Vec<u8>,
+    pub npdu: NPDU,
+}
+
+fn invalid(line: usize, msg: &str) -> Error {
+    Error::new(ErrorKind::InvalidData, format!("Line {}: {}", line, msg))
+}
+
+/// A recording to replay, see the [module](self)
+pub struct Replay {
+    frames: Vec<ReplayFrame>,
+    clock: Arc<dyn Clock>,
+    speed: f64,
+}
+
+impl Replay {
+    /// Replay the frames, in the order of their time
+    pub fn new(mut frames: Vec<ReplayFrame>) -> Self {
+        frames.sort_by_key(|frame| frame.time);
+        Self {
+            frames,
+            clock: Arc::new(SystemClock),
+            speed: 1.0,
+        }
+    }
+
+    /// A recording written as hex, comments start with `#`
+    pub fn parse(text: &str) -> std::io::Result<Self> {
+        let mut frames = Vec::new();
+        for (number, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let mut fields = line.split_whitespace();
+            let mut field = |name| {
+                fields
+                    .next()
+                    .ok_or_else(|| invalid(number + 1, &format!("Expected {}", name)))
+            };
+            let time = field("a time")?
+                .parse::<f64>()
+                .ok()
+                .filter(|time| time.is_finite() && *time >= 0.0)
+                .map(Duration::from_secs_f64)
+                .ok_or_else(|| invalid(number + 1, "Invalid time"))?;
+            let sent = match field("a direction")? {
+                "<" => true,
+                ">" => false,
+                _ => return Err(invalid(number + 1, "Expected `<` or `>`")),
+            };
+            let peer = match field("a MAC address")? {
+                "-" => Vec::new(),
+                peer => {
+                    hex::decode(peer).map_err(|_| invalid(number + 1, "Invalid MAC address"))?
+                }
+            };
+            let npdu: String = fields.collect();
+            let npdu = hex::decode(npdu).map_err(|_| invalid(number + 1, "Invalid hex"))?;
+            let npdu = NPDU::decode_slice(&npdu)
+                .map_err(|e| invalid(number + 1, &format!("Invalid NPDU: {}", e)))?;
+            frames.push(ReplayFrame {
+                time,
+                sent,
+                peer,
+                npdu,
+            });
+        }
+        Ok(Self::new(frames))
+    }
+
+    /// The NPDUs a station at `station` sent and received in a capture,
+    /// e.g. read with a [`CaptureReader`](crate::capture::CaptureReader)
+    ///
+    /// Unicasts between other stations and frames without NPDU are
+    /// skipped, as are frames that are not valid BACnet/IP.
+    #[cfg(feature = "capture")]
+    pub fn from_capture<I>(records: I, station: std::net::SocketAddrV4) -> std::io::Result<Self>
+    where
+        I: IntoIterator<Item = std::io::Result<crate::capture::Record>>,
+    {
+        use crate::transport::bacnetip::{mac_from_addr, BVLCFunction};
+
+        let mut frames = Vec::new();
+        let mut start = None;
+        for record in records {
+            let record = record?;
+            let (broadcast, origin, npdu) = match record.bvlc {
+                Ok(bvlc) => match bvlc.function {
+                    BVLCFunction::OriginalUnicastNPDU(npdu) => (false, record.source, npdu),
+                    BVLCFunction::OriginalBroadcastNPDU(npdu)
+                    | BVLCFunction::DistributeBroadcastToNetwork(npdu) => {
+                        (true, record.source, npdu)
+                    }
+                    BVLCFunction::ForwardedNPDU(origin, npdu) => (true, origin, npdu),
+                    _ => continue,
+                },
+                Err(_) => continue,
+            };
+            let (sent, peer) = if record.source == station {
+                match broadcast {
+                    true => (true, Vec::new()),
+                    false => (true, mac_from_addr(record.destination)),
+                }
+            } else if record.destination == station || broadcast {
+                (false, mac_from_addr(origin))
+            } else {
+                continue;
+            };
+            let start = *start.get_or_insert(record.timestamp);
+            frames.push(ReplayFrame {
+                time: record.timestamp.saturating_sub(start),
+                sent,
+                peer,
+                npdu,
+            });
+        }
+        Ok(Self::new(frames))
+    }
+
+    pub fn frames(&self) -> &[ReplayFrame] {
+        &self.frames
+    }
+
+    /// Wait for the time of the frames on `clock`, e.g. a
+    /// [`MockClock`](crate::clock::MockClock) that is advanced by the test
+    pub fn set_clock<C: Clock + 'static>(&mut self, clock: C) {
+        self.clock = Arc::new(clock);
+    }
+
+    /// Replay `speed` times faster than recorded, infinitely fast without
+    /// waiting
+    pub fn set_speed(&mut self, speed: f64) {
+        self.speed = speed;
+    }
+
+    /// The data link to run the stack on, and the log of what it sent
+    ///
+    /// The link delivers the received frames from the first time the stack
+    /// waits for one on.
+    pub fn link(&self) -> (ReplayLink, ReplayLog) {
+        let (done, finished) = channel::bounded(1);
+        let state = Arc::new(Mutex::new(ReplayState {
+            start: None,
+            next: 0,
+            sent: Vec::new(),
+            done: Some(done),
+        }));
+        let received = self.frames.iter().filter(|f| !f.sent).cloned().collect();
+        let link = ReplayLink {
+            received,
+            clock: self.clock.clone(),
+            speed: self.speed,
+            state: state.clone(),
+        };
+        let log = ReplayLog {
+            expected: self.frames.iter().filter(|f| f.sent).cloned().collect(),
+            state,
+            finished,
+        };
+        (link, log)
+    }
+}
+
+struct ReplayState {
+    /// When the replay started, on the clock of the replay
+    start: Option<Instant>,
+    /// Index of the next received frame to deliver
+    next: usize,
+    sent: Vec<ReplayFrame>,
+    /// Dropped once all frames are delivered and processed
+    done: Option<Sender<()>>,
+}
+
+/// A data link that delivers the frames received in a recording
+pub struct ReplayLink {
+    received: Vec<ReplayFrame>,
+    clock: Arc<dyn Clock>,
+    speed: f64,
+    state: Arc<Mutex<ReplayState>>,
+}
+
+impl DataLink for ReplayLink {
+    fn send<'a>(&'a self, mac: &'a [u8], npdu: &'a NPDU) -> BoxFuture<'a, std::io::Result<()>> {
+        Box::pin(async move {
+            let now = self.clock.now();
+            let mut state = self.state.lock().unwrap();
+            let start = *state.start.get_or_insert(now);
+            state.sent.push(ReplayFrame {
+                time: now.duration_since(start),
+                sent: true,
+                peer: mac.to_vec(),
+                npdu: npdu.clone(),
+            });
+            Ok(())
+        })
+    }
+
+    /// The next frame at its time, frames of the past right away
+    ///
+    /// Once all frames are delivered no further ones are received.
+    fn recv(&self) -> BoxFuture<'_, std::io::Result<(Vec<u8>, NPDU)>> {
+        Box::pin(async move {
+            let next = {
+                let now = self.clock.now();
+                let mut state = self.state.lock().unwrap();
+                let start = *state.start.get_or_insert(now);
+                let next = self.received.get(state.next).cloned();
+                match &next {
+                    Some(_) => state.next += 1,
+                    None => drop(state.done.take()),
+                }
+                next.map(|frame| {
+                    let at = match self.speed {
+                        speed if speed.is_finite() => frame.time.div_f64(speed),
+                        _ => Duration::ZERO,
+                    };
+                    (frame, (start + at).saturating_duration_since(now))
+                })
+            };
+            let (frame, wait) = match next {
+                Some(next) => next,
+                None => return std::future::pending().await,
+            };
+            if wait > Duration::ZERO {
+                self.clock.sleep(wait).await;
+            }
+            Ok((frame.peer, frame.npdu))
+        })
+    }
+}
+
+/// What the stack sent during a replay, see [`Replay::link`]
+#[derive(Clone)]
+pub struct ReplayLog {
+    expected: Vec<ReplayFrame>,
+    state: Arc<Mutex<ReplayState>>,
+    finished: Receiver<()>,
+}
+
+impl ReplayLog {
+    /// The frames the station sent in the recording
+    pub fn expected(&self) -> &[ReplayFrame] {
+        &self.expected
+    }
+
+    /// The frames the stack sent so far, with the time since the start of
+    /// the replay
+    pub fn sent(&self) -> Vec<ReplayFrame> {
+        self.state.lock().unwrap().sent.clone()
+    }
+
+    /// Whether all frames were delivered, and the stack waits for the next
+    pub fn is_finished(&self) -> bool {
+        self.state.lock().unwrap().done.is_none()
+    }
+
+    /// Wait until all frames were delivered and the stack waits for the
+    /// next
+    pub async fn finished(&self) {
+        let _ = self.finished.recv().await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::application::*;
+    use crate::client::BacnetClient;
+    use crate::clock::MockClock;
+    use crate::network::{NPDUContent, NPDUPriority};
+    use crate::server::{BacnetDevice, DeviceInfo};
+    use crate::Encode;
+
+    use async_std::task;
+    use std::time::UNIX_EPOCH;
+
+    const WHO_IS: &str = "
+        # Who-Is answered with an I-Am broadcast
+        0.5 > 0a000002bac0 0120ffff00ff 1008
+        0.5 < - 0100 1000 c4020004d2 2205c4 9103 2108
+        2.0 > 0a000003bac0 0120ffff00ff 1008 0a03e8 1a03e8
+    ";
+
+    fn apdu(npdu: &NPDU) -> &APDU {
+        match &npdu.content {
+            NPDUContent::APDU(apdu) => apdu,
+            NPDUContent::Message(_) => panic!("Not an APDU"),
+        }
+    }
+
+    #[test]
+    fn test_parse() {
+        let replay = Replay::parse(WHO_IS).unwrap();
+        let frames = replay.frames();
+        assert_eq!(frames.len(), 3);
+        assert_eq!(frames[0].time, Duration::from_millis(500));
+        assert!(!frames[0].sent);
+        assert_eq!(frames[0].peer, [10, 0, 0, 2, 0xba, 0xc0]);
+        assert!(frames[1].sent && frames[1].peer.is_empty());
+        assert_eq!(
+            apdu(&frames[2].npdu).service_choice().unwrap(),
+            UnconfirmedServiceChoice::WhoIs as u8
+        );
+
+        for text in ["x > - 01", "1 = - 01", "1 > zz 01", "1 > - 01ff"] {
+            let err = Replay::parse(text).err().unwrap();
+            assert_eq!(err.kind(), ErrorKind::InvalidData);
+            assert!(err.to_string().starts_with("Line 1: "), "{}", err);
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "capture")]
+    fn test_from_capture() {
+        use crate::capture::Record;
+        use crate::transport::bacnetip::{BVLCFunction, BVLC};
+        use std::net::SocketAddrV4;
+
+        let addr = |host| SocketAddrV4::new([10, 0, 0, host].into(), 0xbac0);
+        let record = |second: u64, source, destination, function, npdu: &str| {
+            let npdu = NPDU::decode_slice(&hex::decode(npdu).unwrap()).unwrap();
+            let bvlc = BVLC::new(match function {
+                0x0a => BVLCFunction::OriginalUnicastNPDU(npdu),
+                _ => BVLCFunction::OriginalBroadcastNPDU(npdu),
+            });
+            Ok(Record {
+                timestamp: Duration::from_secs(1_600_000_000 + second),
+                source: addr(source),
+                destination,
+                data: bvlc.encode_vec().unwrap(),
+                bvlc: Ok(bvlc),
+            })
+        };
+        let broadcast = SocketAddrV4::new([10, 0, 0, 255].into(), 0xbac0);
+        let records = vec![
+            record(0, 2, broadcast, 0x0b, "01001008"),
+            record(1, 1, addr(2), 0x0a, "0100100800"),
+            // Between other stations
+            record(2, 2, addr(3), 0x0a, "01001008"),
+            Ok(Record {
+                bvlc: Err(crate::error::EncodingError::Truncated.into()),
+                ..record(3, 2, addr(1), 0x0a, "01001008").unwrap()
+            }),
+            record(4, 3, addr(1), 0x0a, "01001008"),
+        ];
+        let replay = Replay::from_capture(records, addr(1)).unwrap();
+        let frames = replay.frames();
+        assert_eq!(frames.len(), 3);
+        assert_eq!((frames[0].time, frames[0].sent), (Duration::ZERO, false));
+        assert_eq!(frames[0].peer, [10, 0, 0, 2, 0xba, 0xc0]);
+        assert_eq!(
+            (frames[1].time, frames[1].sent),
+            (Duration::from_secs(1), true)
+        );
+        assert_eq!(frames[1].peer, [10, 0, 0, 2, 0xba, 0xc0]);
+        assert_eq!(frames[2].time, Duration::from_secs(4));
+        assert_eq!(frames[2].peer, [10, 0, 0, 3, 0xba, 0xc0]);
+    }
+
+    #[test]
+    fn test_replay_device() {
+        task::block_on(async {
+            let mut replay = Replay::parse(WHO_IS).unwrap();
+            let clock = MockClock::new(UNIX_EPOCH);
+            replay.set_clock(clock.clone());
+            let (link, log) = replay.link();
+            let mut device = BacnetDevice::new(link, DeviceInfo::new(1234, "Controller", 8));
+            device.set_clock(clock.clone());
+
+            // Nothing is received before its time
+            task::sleep(Duration::from_millis(20)).await;
+            assert!(log.sent().is_empty());
+            clock.advance(Duration::from_millis(500));
+            while log.sent().is_empty() {
+                task::sleep(Duration::from_millis(5)).await;
+            }
+            assert!(!log.is_finished());
+            clock.advance(Duration::from_millis(1500));
+            log.finished().await;
+            assert!(log.is_finished());
+
+            // The second Who-Is is for another device
+            let sent = log.sent();
+            assert_eq!(sent.len(), 1);
+            assert_eq!(sent[0].time, Duration::from_millis(500));
+            assert_eq!(sent[0].peer, log.expected()[0].peer);
+            assert_eq!(
+                apdu(&sent[0].npdu).encode_vec().unwrap(),
+                apdu(&log.expected()[0].npdu).encode_vec().unwrap()
+            );
+        });
+    }
+
+    #[test]
+    fn test_replay_client() {
+        task::block_on(async {
+            let i_am = IAm {
+                device_identifier: ObjectIdentifier::new(ObjectType::Device, 7),
+                max_apdu_length_accepted: 480,
+                segmentation_supported: Segmentation::NoSegmentation,
+                vendor_id: 8,
+            };
+            let data = i_am.encode_vec().unwrap();
+            let apdu = APDU::unconfirmed_request(UnconfirmedServiceChoice::IAm as u8, data);
+            let frame = |time| ReplayFrame {
+                time: Duration::from_secs(time),
+                sent: false,
+                peer: vec![7],
+                npdu: NPDU::new(apdu.clone(), None, None, NPDUPriority::Normal),
+            };
+            let mut replay = Replay::new(vec![frame(3600), frame(0)]);
+            replay.set_speed(f64::INFINITY);
+            let (link, log) = replay.link();
+            let client = BacnetClient::new(link);
+            log.finished().await;
+            assert_eq!(
+                client.address(7),
+                Some(crate::network::Address::local(vec![7]))
+            );
+        });
+    }
+}
@@ -0,0 +1,3135 @@
+//! Device (server) runtime
+//!
+//! A [`BacnetDevice`] serves the objects of an [`ObjectStore`] on a
+//! [`DataLink`]: it answers Who-Is with an I-Am from its [`DeviceInfo`],
+//! Who-Has, ReadProperty, WriteProperty, ReadPropertyMultiple and
+//! SubscribeCOV(Property), notifying subscribers of changes. Event state
+//! transitions reported with [`BacnetDevice::report_event`], or found by the
+//! intrinsic reporting of analog, binary and multi-state objects, are
+//! notified to the recipients of their notification class, and can be
+//! acknowledged with AcknowledgeAlarm and listed with GetEventInformation.
+//! DeviceCommunicationControl suspends communication and ReinitializeDevice
+//! is passed to the application, both protected by an optional password.
+//! UTCTimeSynchronization sets the [`Clock`] of the device. ReadRange reads
+//! the buffers of logs, AtomicReadFile and AtomicWriteFile the contents of
+//! File objects.
+//! A [`Gateway`] hosts several devices on a virtual network behind it.
+//! Objects not kept in memory, in a database or hardware I/O, are served
+//! from an [`ObjectDatabase`].
+//! The [`DeviceObject`] itself is provided by the runtime. Applications can
+//! handle further services, or replace the built-in handling, with
+//! [`BacnetDevice::on_confirmed`] and [`BacnetDevice::on_unconfirmed`].
+//!
+//! ```no_run
+//! # use bacnet::objects::LightingOutput;
+//! # use bacnet::server::{BacnetDevice, DeviceInfo};
+//! # use bacnet::transport::bacnetip::{BacnetIp, DEFAULT_PORT};
+//! # use std::net::{Ipv4Addr, SocketAddrV4};
+//! # async_std::task::block_on(async {
+//! let link = BacnetIp::bind(
+//!     SocketAddrV4::new(Ipv4Addr::UNSPECIFIED, DEFAULT_PORT),
+//!     SocketAddrV4::new(Ipv4Addr::BROADCAST, DEFAULT_PORT),
+//! )
+//! .await?;
+//! let device = BacnetDevice::new(link, DeviceInfo::new(1234, "Lighting Controller", 999));
+//! device.objects().insert(LightingOutput::new(1, "Lamp"));
+//! device.announce().await?;
+//! async_std::future::pending::<()>().await;
+//! # Ok::<(), std::io::Error>(())
+//! # });
+//! ```
+
+use crate::application::*;
+use crate::client::{ClientError, Statistics, DEFAULT_APDU_RETRIES, DEFAULT_APDU_TIMEOUT};
+use crate::clock::Clock;
+use crate::encoding::*;
+use crate::error::ServiceError;
+use crate::network::*;
+use crate::objects::{not_a_file, DeviceObject, Object, Recipient};
+use crate::station::{frame_span, Port, Station};
+use crate::transport::DataLink;
+use crate::{Decode, Encode};
+
+use async_std::channel::{self, Sender};
+use async_std::task::{self, JoinHandle};
+use futures_lite::future;
+use num_traits::FromPrimitive;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+
+use tracing::{trace, warn};
+
+mod alarms;
+use alarms::Alarms;
+pub mod cov;
+pub use cov::*;
+pub mod events;
+pub use events::*;
+pub mod gateway;
+pub use gateway::*;
+pub mod handler;
+pub use handler::*;
+pub mod limits;
+pub use limits::*;
+pub mod store;
+pub use store::*;
+mod transactions;
+use transactions::{Responder, Segmented};
+
+/// Interval of checking objects for changes to notify to COV subscribers
+/// and of delivering event notifications due
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Smallest max APDU length a device can accept (20.1.2.5)
+const MIN_APDU: u32 = 50;
+
+/// Times a window of a segmented response is sent again before the
+/// response is given up
+const SEGMENT_RETRIES: usize = 3;
+
+/// A response to a request
+enum Reply {
+    Unsegmented(APDU),
+    /// A complex ACK larger than the client accepts, sent in segments
+    Segmented(SegmentedSender),
+    /// The SegmentACK of the last segment of a request, then its response
+    Reassembled(APDU, Box<Reply>),
+}
+
+/// Identity of a device, as announced with I-Am
+#[derive(Clone, Debug)]
+pub struct DeviceInfo {
+    pub instance: u32,
+    pub name: String,
+    pub vendor_id: u16,
+    pub max_apdu_length_accepted: u32,
+    pub segmentation_supported: Segmentation,
+    /// Password of DeviceCommunicationControl and ReinitializeDevice
+    /// requests, without one the password of requests is not checked
+    pub password: Option<String>,
+}
+
+impl DeviceInfo {
+    /// A device accepting APDUs up to 1476 octets, without segmentation
+    pub fn new<S: Into<String>>(instance: u32, name: S, vendor_id: u16) -> Self {
+        Self {
+            instance,
+            name: name.into(),
+            vendor_id,
+            max_apdu_length_accepted: 1476,
+            segmentation_supported: Segmentation::NoSegmentation,
+            password: None,
+        }
+    }
+
+    pub fn object_identifier(&self) -> ObjectIdentifier {
+        ObjectIdentifier::new(ObjectType::Device, self.instance)
+    }
+
+    pub fn i_am(&self) -> IAm {
+        IAm {
+            device_identifier: self.object_identifier(),
+            max_apdu_length_accepted: self.max_apdu_length_accepted,
+            segmentation_supported: self.segmentation_supported,
+            vendor_id: self.vendor_id,
+        }
+    }
+}
+
+fn invalid(msg: &'static str) -> std::io::Error {
+    ServiceError::Invalid(msg).into()
+}
+
+/// Decode a single value or the elements of a list or array
+fn value_until_closing_tag(reader: &mut Reader, tag_number: u8) -> std::io::Result<BACnetValue> {
+    reader.opening_tag(tag_number)?;
+    let mut values = reader.values_until_closing_tag(tag_number)?;
+    Ok(match values.len() {
+        1 => values.remove(0),
+        _ => BACnetValue::Array(values),
+    })
+}
+
+/// Why services are not initiated while initiation is disabled
+fn initiation_disabled() -> std::io::Error {
+    std::io::Error::new(
+        std::io::ErrorKind::PermissionDenied,
+        "Initiation disabled by DeviceCommunicationControl",
+    )
+}
+
+/// Communication state set with DeviceCommunicationControl (16.1)
+struct Communication {
+    state: EnableDisable,
+    /// End of the time duration of the request
+    until: Option<Instant>,
+}
+
+impl Default for Communication {
+    fn default() -> Self {
+        Self {
+            state: EnableDisable::Enable,
+            until: None,
+        }
+    }
+}
+
+struct Inner<D> {
+    station: Station<D>,
+    info: DeviceInfo,
+    /// The Device object, brought up to date with the info, the objects and
+    /// the services handled when it is read
+    device: Mutex<DeviceObject>,
+    objects: Mutex<ObjectStore>,
+    /// Database set with [`BacnetDevice::set_database`], locked after the
+    /// objects
+    database: Mutex<Option<Box<dyn ObjectDatabase + Send>>>,
+    /// Handlers registered with [`BacnetDevice::on_confirmed`]
+    confirmed_handlers: Mutex<HashMap<ConfirmedServiceChoice, Arc<dyn ConfirmedHandler>>>,
+    /// Handlers registered with [`BacnetDevice::on_unconfirmed`]
+    unconfirmed_handlers: Mutex<HashMap<UnconfirmedServiceChoice, Arc<dyn UnconfirmedHandler>>>,
+    reinitialize_handler: Mutex<Option<Arc<dyn ReinitializeHandler>>>,
+    communication: Mutex<Communication>,
+    limits: Mutex<Limits>,
+    rate_limiter: Mutex<RateLimiter>,
+    /// Broadcast answers, as destination and encoded APDU, until they are
+    /// sent and the broadcast response interval after that passed
+    broadcast_answers: Mutex<HashMap<(Address, Vec<u8>), Instant>>,
+    cov_subscriptions: Mutex<CovSubscriptions>,
+    events: Mutex<Events>,
+    /// Event states of the objects with intrinsic reporting, reported to
+    /// `events`
+    alarms: Mutex<Alarms>,
+    /// Addresses of devices that announced themselves with I-Am, to deliver
+    /// event notifications to
+    devices: Mutex<HashMap<u32, Address>>,
+    apdu_timeout: Mutex<Duration>,
+    apdu_retries: Mutex<usize>,
+    responder: Mutex<Responder>,
+    /// Segmented responses being sent, receiving the SegmentACKs and aborts
+    /// of their clients
+    segmented_responses: Mutex<HashMap<(Address, u8), Sender<APDU>>>,
+}
+
+impl<D: DataLink> Inner<D> {
+    /// Process a received NPDU, returning the response to send
+    fn receive(&self, port: usize, mac: Vec<u8>, npdu: NPDU) -> Option<(Address, Reply)> {
+        let address = self.station.source(port, mac, &npdu);
+        let _frame = frame_span("received", &address, &npdu.content).entered();
+        let apdu = match npdu.content {
+            NPDUContent::APDU(apdu) => apdu,
+            NPDUContent::Message(_) => return None,
+        };
+
+        let is_request = matches!(
+            apdu,
+            APDU::ConfirmedRequest { .. } | APDU::UnconfirmedRequest { .. }
+        );
+        let requests_per_second = self.limits.lock().unwrap().requests_per_second;
+        if let (true, Some(limit)) = (is_request, requests_per_second) {
+            let mut rate_limiter = self.rate_limiter.lock().unwrap();
+            if !rate_limiter.allow(&address, limit, self.station.clock().now()) {
+                trace!("Rate limit exceeded, ignoring request from {:?}", address);
+                return None;
+            }
+        }
+
+        // Only communication can be enabled again while it is disabled
+        let communication = self.communication();
+        if communication == EnableDisable::Disable
+            && !matches!(
+                &apdu,
+                APDU::ConfirmedRequest { service_choice, .. }
+                    if *service_choice == ConfirmedServiceChoice::DeviceCommunicationControl as u8
+                        || *service_choice == ConfirmedServiceChoice::ReinitializeDevice as u8
+            )
+        {
+            trace!("Communication disabled, ignoring {:?}", apdu);
+            return None;
+        }
+
+        match apdu {
+            APDU::UnconfirmedRequest {
+                service_choice,
+                ref user_data,
+            } => {
+                let service = UnconfirmedServiceChoice::from_u8(service_choice);
+                if service == Some(UnconfirmedServiceChoice::IAm) {
+                    if let Ok(i_am) = IAm::decode_slice(user_data) {
+                        let instance = i_am.device_identifier.instance;
+                        self.devices
+                            .lock()
+                            .unwrap()
+                            .insert(instance, address.clone());
+                    }
+                }
+                let handler = service.and_then(|s| self.unconfirmed_handler(s));
+                let response = match (handler, service) {
+                    (Some(handler), _) => {
+                        let mut objects = self.objects.lock().unwrap();
+                        handler
+                            .handle(&address, user_data, &mut objects)
+                            .map(|_| None)
+                    }
+                    (None, Some(UnconfirmedServiceChoice::WhoIs)) => self.who_is(user_data),
+                    (None, Some(UnconfirmedServiceChoice::UtcTimeSynchronization)) => {
+                        self.utc_time_synchronization(user_data)
+                    }
+                    // Only I-Am may be initiated while initiation is disabled
+                    (None, Some(UnconfirmedServiceChoice::WhoHas))
+                        if communication == EnableDisable::Enable =>
+                    {
+                        self.who_has(user_data)
+                    }
+                    _ => Ok(None),
+                };
+                match response {
+                    // Answers are broadcast on the network of the request,
+                    // globally to requests from remote networks
+                    Ok(Some(response)) => {
+                        let destination = match address.net {
+                            Some(net) if self.station.is_attached(net) => {
+                                Address::remote(net, vec![])
+                            }
+                            Some(_) => Address::global_broadcast(),
+                            None => Address::broadcast(),
+                        };
+                        return Some((destination, Reply::Unsegmented(response)));
+                    }
+                    Ok(None) => {}
+                    Err(e) => {
+                        trace!("Invalid request from {:?}: {}", address, e);
+                        self.station.decode_error();
+                    }
+                }
+            }
+            APDU::ConfirmedRequest { invoke_id, .. } => {
+                return self
+                    .confirmed_transaction(&address, apdu, invoke_id)
+                    .map(|response| (address, response));
+            }
+            // Clients acknowledge or abort segmented responses
+            APDU::SegmentAck {
+                server: false,
+                invoke_id,
+                ..
+            }
+            | APDU::Abort {
+                server: false,
+                invoke_id,
+                ..
+            } => {
+                let key = (address, invoke_id);
+                match self.segmented_responses.lock().unwrap().get(&key) {
+                    Some(acks) => {
+                        let _ = acks.try_send(apdu);
+                    }
+                    None if apdu.pdu_type() == BACnetPDU::Abort => {
+                        self.station.complete(key.0, apdu)
+                    }
+                    None => trace!("No segmented response for {:?}", key),
+                }
+            }
+            APDU::SimpleAck { .. }
+            | APDU::ComplexAck { .. }
+            | APDU::Error { .. }
+            | APDU::Reject { .. }
+            | APDU::Abort { .. } => self.station.complete(address, apdu),
+            APDU::SegmentAck { .. } => trace!("Ignoring APDU from {:?}: {:?}", address, apdu),
+        }
+        None
+    }
+
+    /// Process a confirmed request or a segment of one, answering
+    /// duplicates of requests answered before with the same response
+    ///
+    /// Segmented requests are reassembled if the device supports it.
+    fn confirmed_transaction(
+        &self,
+        source: &Address,
+        request: APDU,
+        invoke_id: u8,
+    ) -> Option<Reply> {
+        let key = (source.clone(), invoke_id);
+        // The segmented response to the request is still being sent
+        if self.segmented_responses.lock().unwrap().contains_key(&key) {
+            trace!("Ignoring duplicate request {:?}", key);
+            return None;
+        }
+        let now = self.station.clock().now();
+        let segmentation = matches!(
+            self.info.segmentation_supported,
+            Segmentation::SegmentedBoth | Segmentation::SegmentedReceive
+        );
+        let (request, ack) = match request.segment() {
+            Some(_) if segmentation => {
+                let mut responder = self.responder.lock().unwrap();
+                let segmented = responder.segment(
+                    source,
+                    &request,
+                    DEFAULT_WINDOW_SIZE,
+                    DEFAULT_SEGMENT_TIMEOUT,
+                    now,
+                );
+                match segmented {
+                    Segmented::Pending(ack) => return ack.map(Reply::Unsegmented),
+                    Segmented::Complete(request, ack) => (request, Some(ack)),
+                    Segmented::Invalid => {
+                        let reason = AbortReason::InvalidApduInThisState;
+                        let abort = AbortPDU::new(true, invoke_id, reason).to_apdu();
+                        return Some(Reply::Unsegmented(abort));
+                    }
+                }
+            }
+            _ => (request, None),
+        };
+
+        let duplicate = self
+            .responder
+            .lock()
+            .unwrap()
+            .duplicate(source, &request, now);
+        let response = match duplicate {
+            Some(response) => {
+                trace!("Answering duplicate request {:?} again", key);
+                Reply::Unsegmented(response)
+            }
+            None => {
+                let response = self.confirmed(source, &request).unwrap_or_else(|e| {
+                    trace!("Invalid request from {:?}: {}", source, e);
+                    self.station.decode_error();
+                    Reply::Unsegmented(APDU::reject(invoke_id, RejectReason::InvalidTag as u8))
+                });
+                // Responses are kept while the client may retry
+                if let Reply::Unsegmented(response) = &response {
+                    let attempts = *self.apdu_retries.lock().unwrap() as u32 + 1;
+                    let until = now + *self.apdu_timeout.lock().unwrap() * attempts;
+                    self.responder.lock().unwrap().answered(
+                        source.clone(),
+                        request,
+                        response.clone(),
+                        until,
+                    );
+                }
+                response
+            }
+        };
+        match ack {
+            Some(ack) => Some(Reply::Reassembled(ack, Box::new(response))),
+            None => Some(response),
+        }
+    }
+
+    fn confirmed_handler(
+        &self,
+        service: ConfirmedServiceChoice,
+    ) -> Option<Arc<dyn ConfirmedHandler>> {
+        self.confirmed_handlers
+            .lock()
+            .unwrap()
+            .get(&service)
+            .cloned()
+    }
+
+    fn unconfirmed_handler(
+        &self,
+        service: UnconfirmedServiceChoice,
+    ) -> Option<Arc<dyn UnconfirmedHandler>> {
+        self.unconfirmed_handlers
+            .lock()
+            .unwrap()
+            .get(&service)
+            .cloned()
+    }
+
+    /// Execute a confirmed request, returning the response
+    ///
+    /// Registered handlers take precedence over the built-in ones. Complex
+    /// ACKs larger than the client accepts are segmented if both support
+    /// it.
+    fn confirmed(&self, source: &Address, request: &APDU) -> std::io::Result<Reply> {
+        let (invoke_id, service, segmented_response_accepted) = match request {
+            // Segmented requests are not reassembled
+            APDU::ConfirmedRequest {
+                invoke_id,
+                segment: Some(_),
+                ..
+            } => {
+                let reason = AbortReason::SegmentationNotSupported;
+                return Ok(Reply::Unsegmented(
+                    AbortPDU::new(true, *invoke_id, reason).to_apdu(),
+                ));
+            }
+            APDU::ConfirmedRequest {
+                invoke_id,
+                service_choice,
+                segmented_response_accepted,
+                ..
+            } => (*invoke_id, *service_choice, *segmented_response_accepted),
+            _ => return Err(ServiceError::Invalid("Not a confirmed request").into()),
+        };
+        let data = request.user_data();
+        let max_apdu = request
+            .max_apdu_length_accepted()
+            .unwrap_or(MIN_APDU)
+            .min(self.info.max_apdu_length_accepted) as usize;
+        let choice = ConfirmedServiceChoice::from_u8(service);
+        let response = match (choice.and_then(|c| self.confirmed_handler(c)), choice) {
+            (Some(handler), _) => {
+                let mut objects = self.objects.lock().unwrap();
+                handler.handle(source, data, &mut objects)?
+            }
+            (None, Some(ConfirmedServiceChoice::ReadProperty)) => self.read_property(data)?,
+            (None, Some(ConfirmedServiceChoice::ReadPropertyMultiple)) => {
+                self.read_property_multiple(data)?
+            }
+            (None, Some(ConfirmedServiceChoice::WriteProperty)) => self.write_property(data)?,
+            (None, Some(ConfirmedServiceChoice::ReadRange)) => {
+                let request = ReadRangeRequest::decode_slice(data)?;
+                self.read_range(&request, max_apdu)?
+            }
+            (None, Some(ConfirmedServiceChoice::AtomicReadFile)) => {
+                let request = AtomicReadFileRequest::decode_slice(data)?;
+                self.atomic_read_file(&request)?
+            }
+            (None, Some(ConfirmedServiceChoice::AtomicWriteFile)) => {
+                let request = AtomicWriteFileRequest::decode_slice(data)?;
+                self.atomic_write_file(request)?
+            }
+            (None, Some(ConfirmedServiceChoice::SubscribeCov)) => {
+                let request = SubscribeCov::decode_slice(data)?;
+                self.subscribe_cov(source, &request, None, None)
+            }
+            (None, Some(ConfirmedServiceChoice::SubscribeCovProperty)) => {
+                let request = SubscribeCovProperty::decode_slice(data)?;
+                match PropertyIdentifier::from_u32(request.monitored_property_identifier) {
+                    Some(property) => self.subscribe_cov(
+                        source,
+                        &request.subscription,
+                        Some((property, request.monitored_property_array_index)),
+                        request.cov_increment,
+                    ),
+                    None => Response::Error(BACnetError::property(ErrorCode::UnknownProperty)),
+                }
+            }
+            (None, Some(ConfirmedServiceChoice::AcknowledgeAlarm)) => {
+                let request = AcknowledgeAlarm::decode_slice(data)?;
+                self.acknowledge_alarm(&request)
+            }
+            (None, Some(ConfirmedServiceChoice::GetEventInformation)) => {
+                let request = GetEventInformationRequest::decode_slice(data)?;
+                self.get_event_information(&request, max_apdu)?
+            }
+            (None, Some(ConfirmedServiceChoice::DeviceCommunicationControl)) => {
+                let request = DeviceCommunicationControl::decode_slice(data)?;
+                self.device_communication_control(&request)
+            }
+            (None, Some(ConfirmedServiceChoice::ReinitializeDevice)) => {
+                let request = ReinitializeDeviceRequest::decode_slice(data)?;
+                self.reinitialize_device(&request)
+            }
+            _ => Response::Reject(RejectReason::UnrecognizedService as u8),
+        };
+        let response = match response {
+            Response::SimpleAck => APDU::simple_ack(invoke_id, service),
+            Response::ComplexAck(data) => APDU::complex_ack(invoke_id, service, data),
+            Response::Error(error) => APDU::error(invoke_id, service, error.encode_vec()?),
+            Response::ServiceError(data) => APDU::error(invoke_id, service, data),
+            Response::Reject(reason) => APDU::reject(invoke_id, reason),
+        };
+
+        if response.len() <= max_apdu {
+            return Ok(Reply::Unsegmented(response));
+        }
+        let segmentation = matches!(
+            self.info.segmentation_supported,
+            Segmentation::SegmentedBoth | Segmentation::SegmentedTransmit
+        );
+        let reason = match (&response, segmentation && segmented_response_accepted) {
+            (APDU::ComplexAck { .. }, true) => {
+                let max_segments = request.max_segments_accepted();
+                match SegmentedSender::new(&response, max_apdu, DEFAULT_WINDOW_SIZE, max_segments) {
+                    Ok(sender) => return Ok(Reply::Segmented(sender)),
+                    Err(_) => AbortReason::BufferOverflow,
+                }
+            }
+            _ => AbortReason::SegmentationNotSupported,
+        };
+        Ok(Reply::Unsegmented(
+            AbortPDU::new(true, invoke_id, reason).to_apdu(),
+        ))
+    }
+
+    /// The object addressed, the Device object with the wildcard instance
+    /// (15.5.1.1.1)
+    fn local(&self, object: ObjectIdentifier) -> ObjectIdentifier {
+        match object {
+            ObjectIdentifier {
+                object_type: ObjectType::Device,
+                instance: ObjectIdentifier::MAX_INSTANCE,
+            } => self.info.object_identifier(),
+            object => object,
+        }
+    }
+
+    fn with_object<T>(
+        &self,
+        objects: &ObjectStore,
+        object: ObjectIdentifier,
+        f: impl FnOnce(&dyn Object) -> Result<T, BACnetError>,
+    ) -> Result<T, BACnetError> {
+        if object == self.info.object_identifier() {
+            return f(&*self.device_object(objects));
+        }
+        match objects.get(object) {
+            Some(object) => f(object),
+            None => Err(BACnetError::object(ErrorCode::UnknownObject)),
+        }
+    }
+
+    fn read(
+        &self,
+        objects: &ObjectStore,
+        object: ObjectIdentifier,
+        property: u32,
+        array_index: Option<u32>,
+    ) -> Result<BACnetValue, BACnetError> {
+        // Reserved and proprietary identifiers are left to the objects,
+        // numbers out of range are no property at all
+        let property = PropertyIdentifier::from_u32(property)
+            .ok_or_else(|| BACnetError::property(ErrorCode::UnknownProperty))?;
+        if self.in_database(objects, object) {
+            return self.with_database(|d| d.read_property(object, property, array_index));
+        }
+        self.with_object(objects, object, |o| o.read_property(property, array_index))
+    }
+
+    /// The Device object, with the objects of the store and the database
+    fn device_object(&self, objects: &ObjectStore) -> MutexGuard<'_, DeviceObject> {
+        let database = self.database.lock().unwrap();
+        let mut device = self.device.lock().unwrap();
+        device.vendor_identifier = self.info.vendor_id;
+        device.max_apdu_length_accepted = self.info.max_apdu_length_accepted;
+        device.segmentation_supported = self.info.segmentation_supported;
+        device.apdu_timeout = *self.apdu_timeout.lock().unwrap();
+        device.number_of_apdu_retries = *self.apdu_retries.lock().unwrap() as u32;
+        // Objects of the store take precedence over the database
+        let identifier = self.info.object_identifier();
+        let database = database
+            .as_ref()
+            .map(|d| d.object_list())
+            .unwrap_or_default();
+        device.object_list = objects
+            .object_list()
+            .into_iter()
+            .chain(
+                database
+                    .into_iter()
+                    .filter(|o| objects.get(*o).is_none() && *o != identifier),
+            )
+            .collect();
+        device.confirmed_services = self.confirmed_services();
+        device.unconfirmed_services = self.unconfirmed_services();
+        device
+    }
+
+    /// The confirmed services executed, built in or handled by the
+    /// application
+    fn confirmed_services(&self) -> Vec<ConfirmedServiceChoice> {
+        use ConfirmedServiceChoice::*;
+        let mut services = vec![
+            AcknowledgeAlarm,
+            SubscribeCov,
+            ReadProperty,
+            ReadPropertyMultiple,
+            WriteProperty,
+            DeviceCommunicationControl,
+            SubscribeCovProperty,
+            GetEventInformation,
+            ReadRange,
+            AtomicReadFile,
+            AtomicWriteFile,
+        ];
+        if self.reinitialize_handler.lock().unwrap().is_some() {
+            services.push(ReinitializeDevice);
+        }
+        services.extend(self.confirmed_handlers.lock().unwrap().keys().copied());
+        services.sort_by_key(|s| *s as u8);
+        services.dedup();
+        services
+    }
+
+    fn unconfirmed_services(&self) -> Vec<UnconfirmedServiceChoice> {
+        use UnconfirmedServiceChoice::*;
+        let mut services = vec![WhoHas, WhoIs, UtcTimeSynchronization];
+        services.extend(self.unconfirmed_handlers.lock().unwrap().keys().copied());
+        services.sort_by_key(|s| *s as u8);
+        services.dedup();
+        services
+    }
+
+    /// Whether an object is served from the database rather than the store
+    fn in_database(&self, objects: &ObjectStore, object: ObjectIdentifier) -> bool {
+        object != self.info.object_identifier() && objects.get(object).is_none()
+    }
+
+    fn with_database<T>(
+        &self,
+        f: impl FnOnce(&mut (dyn ObjectDatabase + Send)) -> Result<T, BACnetError>,
+    ) -> Result<T, BACnetError> {
+        match self.database.lock().unwrap().as_deref_mut() {
+            Some(database) => f(database),
+            None => Err(BACnetError::object(ErrorCode::UnknownObject)),
+        }
+    }
+
+    fn property_list(
+        &self,
+        objects: &ObjectStore,
+        object: ObjectIdentifier,
+    ) -> Result<Vec<PropertyIdentifier>, BACnetError> {
+        match self.in_database(objects, object) {
+            true => self.with_database(|d| d.property_list(object)),
+            false => self.with_object(objects, object, |o| Ok(o.property_list())),
+        }
+    }
+
+    /// The properties read for a property identifier of a
+    /// ReadPropertyMultiple request, which can be All, Required or Optional
+    /// (15.7.3.1.1)
+    ///
+    /// Objects don't tell their required properties apart, Required yields
+    /// the properties every object has and Optional the others.
+    fn expand(&self, objects: &ObjectStore, object: ObjectIdentifier, property: u32) -> Vec<u32> {
+        use PropertyIdentifier::*;
+        let common = [ObjectIdentifier, ObjectName, ObjectType, PropertyList];
+        let expanded = match PropertyIdentifier::from_u32(property) {
+            Some(All) => self
+                .property_list(objects, object)
+                .map(|list| common.iter().copied().chain(list).collect()),
+            Some(Required) => self.property_list(objects, object).map(|_| common.to_vec()),
+            Some(Optional) => self.property_list(objects, object),
+            _ => return vec![property],
+        };
+        match expanded {
+            Ok(properties) => properties.into_iter().map(|p| p.number()).collect(),
+            // Reading the special property reports the unknown object
+            Err(_) => vec![property],
+        }
+    }
+
+    /// Who-Is (16.10), answered with an I-Am
+    fn who_is(&self, data: &[u8]) -> std::io::Result<Option<APDU>> {
+        if !WhoIs::decode_slice(data)?.matches(self.info.instance) {
+            return Ok(None);
+        }
+        let i_am = self.info.i_am().encode_vec()?;
+        let service = UnconfirmedServiceChoice::IAm as u8;
+        Ok(Some(APDU::unconfirmed_request(service, i_am)))
+    }
+
+    /// UTCTimeSynchronization (16.8), setting the date and time of the
+    /// clock
+    fn utc_time_synchronization(&self, data: &[u8]) -> std::io::Result<Option<APDU>> {
+        let time = TimeSynchronization::decode_slice(data)?.date_time();
+        trace!("Time synchronization to {:?}", time);
+        self.station.clock().set_date_time(time);
+        Ok(None)
+    }
+
+    /// Who-Has (16.9), answered with an I-Have if the object is served
+    fn who_has(&self, data: &[u8]) -> std::io::Result<Option<APDU>> {
+        let who_has = WhoHas::decode_slice(data)?;
+        if !who_has.matches(self.info.instance) {
+            return Ok(None);
+        }
+        let objects = self.objects.lock().unwrap();
+        let found = match who_has.object {
+            WhoHasObject::Identifier(object) => self
+                .with_object(&objects, object, |o| {
+                    Ok((o.object_identifier(), o.object_name().to_string()))
+                })
+                .ok(),
+            WhoHasObject::Name(name) => match self.info.name == name {
+                true => Some((self.info.object_identifier(), name)),
+                false => objects
+                    .iter()
+                    .find(|o| o.object_name() == name)
+                    .map(|o| (o.object_identifier(), name)),
+            },
+        };
+        let (object_identifier, object_name) = match found {
+            Some(found) => found,
+            None => return Ok(None),
+        };
+        let i_have = IHave {
+            device_identifier: self.info.object_identifier(),
+            object_identifier,
+            object_name,
+        };
+        let service = UnconfirmedServiceChoice::IHave as u8;
+        Ok(Some(APDU::unconfirmed_request(
+            service,
+            i_have.encode_vec()?,
+        )))
+    }
+
+    /// ReadProperty (15.5)
+    fn read_property(&self, data: &[u8]) -> std::io::Result<Response> {
+        let mut reader = Reader::new(data);
+        let object = self.local(reader.context_object_identifier(0)?);
+        let property = reader.context_enumerated(1)?;
+        let array_index = reader.optional_context_unsigned(2)?;
+
+        let objects = self.objects.lock().unwrap();
+        let value = match self.read(&objects, object, property, array_index) {
+            Ok(value) => value,
+            Err(error) => return Ok(Response::Error(error)),
+        };
+        let mut ack = Vec::new();
+        encode_context_object_identifier(&mut ack, 0, object)?;
+        encode_context_enumerated(&mut ack, 1, property);
+        if let Some(index) = array_index {
+            encode_context_unsigned(&mut ack, 2, index);
+        }
+        encode_opening_tag(&mut ack, 3);
+        encode_application(&mut ack, &value)?;
+        encode_closing_tag(&mut ack, 3);
+        Ok(Response::ComplexAck(ack))
+    }
+
+    /// ReadPropertyMultiple (15.7), errors reading a property are returned
+    /// in its place
+    fn read_property_multiple(&self, data: &[u8]) -> std::io::Result<Response> {
+        let mut reader = Reader::new(data);
+        if reader.is_empty() {
+            return Err(invalid("Missing read access specification"));
+        }
+        let objects = self.objects.lock().unwrap();
+        let mut ack = Vec::new();
+        while !reader.is_empty() {
+            let object = self.local(reader.context_object_identifier(0)?);
+            encode_context_object_identifier(&mut ack, 0, object)?;
+            encode_opening_tag(&mut ack, 1);
+            reader.opening_tag(1)?;
+            while !reader.is_closing_tag(1) {
+                let requested = reader.context_enumerated(0)?;
+                let array_index = reader.optional_context_unsigned(1)?;
+                for property in self.expand(&objects, object, requested) {
+                    encode_context_enumerated(&mut ack, 2, property);
+                    if let Some(index) = array_index {
+                        encode_context_unsigned(&mut ack, 3, index);
+                    }
+                    match self.read(&objects, object, property, array_index) {
+                        Ok(value) => {
+                            encode_opening_tag(&mut ack, 4);
+                            encode_application(&mut ack, &value)?;
+                            encode_closing_tag(&mut ack, 4);
+                        }
+                        Err(error) => {
+                            encode_opening_tag(&mut ack, 5);
+                            error.encode(&mut ack)?;
+                            encode_closing_tag(&mut ack, 5);
+                        }
+                    }
+                }
+            }
+            reader.closing_tag(1)?;
+            encode_closing_tag(&mut ack, 1);
+        }
+        Ok(Response::ComplexAck(ack))
+    }
+
+    /// WriteProperty (15.9)
+    fn write_property(&self, data: &[u8]) -> std::io::Result<Response> {
+        let mut reader = Reader::new(data);
+        let object = self.local(reader.context_object_identifier(0)?);
+        let property = reader.context_enumerated(1)?;
+        let array_index = reader.optional_context_unsigned(2)?;
+        let value = value_until_closing_tag(&mut reader, 3)?;
+        let priority = reader.optional_context_unsigned(4)?.map(|p| p as u8);
+
+        let property = match PropertyIdentifier::from_u32(property) {
+            Some(property) => property,
+            None => {
+                let error = BACnetError::property(ErrorCode::UnknownProperty);
+                return Ok(Response::Error(error));
+            }
+        };
+        let time = self.station.clock().date_time();
+        let mut objects = self.objects.lock().unwrap();
+        let result = if object == self.info.object_identifier() {
+            let mut device = self.device_object(&objects);
+            device.write_property(property, array_index, value, priority, time)
+        } else {
+            match objects.get_mut(object) {
+                Some(object) => object.write_property(property, array_index, value, priority, time),
+                None => self.with_database(|d| {
+                    d.write_property(object, property, array_index, value, priority, time)
+                }),
+            }
+        };
+        Ok(match result {
+            Ok(()) => Response::SimpleAck,
+            Err(error) => Response::Error(error),
+        })
+    }
+
+    /// ReadRange (15.8), the items that don't fit in a response of
+    /// `max_apdu` octets are left out and More_Items is set
+    fn read_range(&self, request: &ReadRangeRequest, max_apdu: usize) -> std::io::Result<Response> {
+        let object = self.local(request.object_identifier);
+        let property = match PropertyIdentifier::from_u32(request.property_identifier) {
+            Some(property) => property,
+            None => {
+                let error = BACnetError::property(ErrorCode::UnknownProperty);
+                return Ok(Response::Error(error));
+            }
+        };
+        let index = request.property_array_index;
+        let objects = self.objects.lock().unwrap();
+        let result = match self.in_database(&objects, object) {
+            // Database objects have no lists
+            true => self
+                .with_database(|d| d.read_property(object, property, index))
+                .and(Err(BACnetError::new(
+                    ErrorClass::Services,
+                    ErrorCode::PropertyIsNotAList,
+                ))),
+            false => self.with_object(&objects, object, |o| {
+                o.read_range(property, index, request.range.as_ref())
+            }),
+        };
+        let mut range = match result {
+            Ok(range) => range,
+            Err(error) => return Ok(Response::Error(error)),
+        };
+
+        let mut ack = Vec::new();
+        encode_context_object_identifier(&mut ack, 0, object)?;
+        encode_context_enumerated(&mut ack, 1, request.property_identifier);
+        if let Some(index) = index {
+            encode_context_unsigned(&mut ack, 2, index);
+        }
+        // Complex ACK header, result flags, item count, item data tags and
+        // first sequence number
+        let mut available = max_apdu.saturating_sub(ack.len() + 3 + 3 + 6 + 2 + 6);
+        let fitting = range
+            .items
+            .iter()
+            .take_while(|item| match available.checked_sub(item.len()) {
+                Some(left) => {
+                    available = left;
+                    true
+                }
+                None => false,
+            })
+            .count();
+        if fitting < range.items.len() {
+            range.items.truncate(fitting);
+            range.result_flags.last_item = false;
+            range.result_flags.more_items = true;
+        }
+        let flags = &range.result_flags;
+        let flags = vec![flags.first_item, flags.last_item, flags.more_items];
+        encode_context_bit_string(&mut ack, 3, &flags);
+        encode_context_unsigned(&mut ack, 4, range.items.len() as u32);
+        encode_opening_tag(&mut ack, 5);
+        range
+            .items
+            .iter()
+            .for_each(|item| ack.extend_from_slice(item));
+        encode_closing_tag(&mut ack, 5);
+        if let (Some(first), false) = (range.first_sequence_number, range.items.is_empty()) {
+            encode_context_unsigned(&mut ack, 6, first);
+        }
+        Ok(Response::ComplexAck(ack))
+    }
+
+    /// AtomicReadFile (15.1)
+    fn atomic_read_file(&self, request: &AtomicReadFileRequest) -> std::io::Result<Response> {
+        let object = request.file_identifier;
+        let objects = self.objects.lock().unwrap();
+        let result = match self.in_database(&objects, object) {
+            // Database objects are no files
+            true => self
+                .with_database(|d| d.property_list(object))
+                .and(Err(not_a_file())),
+            false => self.with_object(&objects, object, |o| o.read_file(&request.access)),
+        };
+        Ok(match result {
+            Ok(ack) => Response::ComplexAck(ack.encode_vec()?),
+            Err(error) => Response::Error(error),
+        })
+    }
+
+    /// AtomicWriteFile (15.2), the file is modified at the time of the
+    /// clock
+    fn atomic_write_file(&self, request: AtomicWriteFileRequest) -> std::io::Result<Response> {
+        let object = request.file_identifier;
+        let time = self.station.clock().date_time();
+        let mut objects = self.objects.lock().unwrap();
+        let result = match objects.get_mut(object) {
+            Some(file) => file.write_file(request.data, time),
+            None if object == self.info.object_identifier() => Err(not_a_file()),
+            None => self
+                .with_database(|d| d.property_list(object))
+                .and(Err(not_a_file())),
+        };
+        Ok(match result {
+            Ok(ack) => Response::ComplexAck(ack.encode_vec()?),
+            Err(error) => Response::Error(error),
+        })
+    }
+
+    /// SubscribeCOV (13.14) and SubscribeCOVProperty (13.15), the initial
+    /// notification is sent with the next check for changes
+    fn subscribe_cov(
+        &self,
+        source: &Address,
+        request: &SubscribeCov,
+        monitored_property: Option<(PropertyIdentifier, Option<u32>)>,
+        cov_increment: Option<f32>,
+    ) -> Response {
+        let subscription = CovSubscription::new(
+            source.clone(),
+            request,
+            monitored_property,
+            cov_increment,
+            self.station.clock().now(),
+        );
+        let objects = self.objects.lock().unwrap();
+        let mut subscriptions = self.cov_subscriptions.lock().unwrap();
+        // Cancelling a subscription that does not exist is not an error
+        if request.is_cancellation() {
+            subscriptions.cancel(&subscription);
+            return Response::SimpleAck;
+        }
+        let object = request.monitored_object_identifier;
+        match self.with_object(&objects, object, |o| subscription.values(o)) {
+            Ok(_) => {
+                subscriptions.subscribe(subscription);
+                Response::SimpleAck
+            }
+            Err(error) => Response::Error(error),
+        }
+    }
+
+    /// AcknowledgeAlarm (13.5), the acknowledgement is notified to the
+    /// recipients of the notification class
+    fn acknowledge_alarm(&self, request: &AcknowledgeAlarm) -> Response {
+        let clock = self.station.clock();
+        let objects = self.objects.lock().unwrap();
+        let result = self.events.lock().unwrap().acknowledge(
+            &objects,
+            self.info.object_identifier(),
+            request,
+            clock.date_time(),
+            clock.now(),
+        );
+        match result {
+            Ok(()) => Response::SimpleAck,
+            Err(error) => Response::Error(error),
+        }
+    }
+
+    /// GetEventInformation (13.12), listing the objects following the last
+    /// one received, as many as fit into an APDU of `max_apdu` octets
+    fn get_event_information(
+        &self,
+        request: &GetEventInformationRequest,
+        max_apdu: usize,
+    ) -> std::io::Result<Response> {
+        let summaries = self
+            .events
+            .lock()
+            .unwrap()
+            .summaries(request.last_received_object_identifier);
+        let mut ack = GetEventInformationAck::default();
+        // Header of a Complex-ACK is 3 octets
+        let mut len = 3 + ack.len();
+        for summary in summaries {
+            let mut data = Vec::new();
+            summary.encode(&mut data)?;
+            if len + data.len() > max_apdu && !ack.list_of_event_summaries.is_empty() {
+                ack.more_events = true;
+                break;
+            }
+            len += data.len();
+            ack.list_of_event_summaries.push(summary);
+        }
+        Ok(Response::ComplexAck(ack.encode_vec()?))
+    }
+
+    /// The communication state, enabled again once the time duration of the
+    /// DeviceCommunicationControl request passed
+    fn communication(&self) -> EnableDisable {
+        let mut communication = self.communication.lock().unwrap();
+        if communication
+            .until
+            .is_some_and(|until| until <= self.station.clock().now())
+        {
+            *communication = Communication::default();
+        }
+        communication.state
+    }
+
+    fn may_initiate(&self) -> bool {
+        self.communication() == EnableDisable::Enable
+    }
+
+    /// Check the password of a request against the one of the device
+    fn check_password(&self, password: &Option<String>) -> Result<(), BACnetError> {
+        match &self.info.password {
+            Some(expected) if password.as_ref() != Some(expected) => Err(BACnetError::new(
+                ErrorClass::Security,
+                ErrorCode::PasswordFailure,
+            )),
+            _ => Ok(()),
+        }
+    }
+
+    /// DeviceCommunicationControl (16.1)
+    fn device_communication_control(&self, request: &DeviceCommunicationControl) -> Response {
+        if let Err(error) = self.check_password(&request.password) {
+            return Response::Error(error);
+        }
+        let until = match request.enable_disable {
+            EnableDisable::Enable => None,
+            _ => request.time_duration.map(|minutes| {
+                self.station.clock().now() + Duration::from_secs(minutes as u64 * 60)
+            }),
+        };
+        *self.communication.lock().unwrap() = Communication {
+            state: request.enable_disable,
+            until,
+        };
+        Response::SimpleAck
+    }
+
+    /// ReinitializeDevice (16.4), executed by the handler registered with
+    /// [`BacnetDevice::on_reinitialize`]
+    ///
+    /// Restarting the device enables communication.
+    fn reinitialize_device(&self, request: &ReinitializeDeviceRequest) -> Response {
+        if let Err(error) = self.check_password(&request.password) {
+            return Response::Error(error);
+        }
+        let handler = match self.reinitialize_handler.lock().unwrap().clone() {
+            Some(handler) => handler,
+            None => {
+                let error = ErrorCode::OptionalFunctionalityNotSupported;
+                return Response::Error(BACnetError::new(ErrorClass::Services, error));
+            }
+        };
+        let state = request.reinitialized_state;
+        match handler.reinitialize(state) {
+            Ok(()) => {
+                if matches!(
+                    state,
+                    ReinitializedState::Coldstart | ReinitializedState::Warmstart
+                ) {
+                    *self.communication.lock().unwrap() = Communication::default();
+                }
+                Response::SimpleAck
+            }
+            Err(error) => Response::Error(error),
+        }
+    }
+
+    async fn notify(
+        &self,
+        address: &Address,
+        service: Result<ConfirmedServiceChoice, UnconfirmedServiceChoice>,
+        data: Vec<u8>,
+    ) -> Result<(), ClientError> {
+        if !self.may_initiate() {
+            return Err(ClientError::Io(initiation_disabled()));
+        }
+        match service {
+            Ok(service) => {
+                let max_pending = self.limits.lock().unwrap().max_pending_transactions;
+                let pending = self.station.transactions.len();
+                if max_pending.is_some_and(|max| pending >= max) {
+                    let error = std::io::Error::new(
+                        std::io::ErrorKind::WouldBlock,
+                        "Too many pending transactions",
+                    );
+                    return Err(ClientError::Io(error));
+                }
+                let timeout = *self.apdu_timeout.lock().unwrap();
+                let retries = *self.apdu_retries.lock().unwrap();
+                self.station
+                    .confirmed_request(address, service, data, timeout, retries)
+                    .await?;
+            }
+            Err(service) => {
+                let request = APDU::unconfirmed_request(service as u8, data);
+                self.station.send(address, request).await?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Send COV notifications of changed values to their subscribers,
+/// confirmed ones are acknowledged in the background
+///
+/// Changes are held back while initiation is disabled.
+async fn notify_changes<D: DataLink + 'static>(inner: &Arc<Inner<D>>) {
+    if !inner.may_initiate() {
+        return;
+    }
+    let pending = {
+        let objects = inner.objects.lock().unwrap();
+        let mut subscriptions = inner.cov_subscriptions.lock().unwrap();
+        subscriptions.changes(
+            &objects,
+            inner.info.object_identifier(),
+            inner.station.clock().now(),
+        )
+    };
+    for pending in pending {
+        let data = match pending.notification.encode_vec() {
+            Ok(data) => data,
+            Err(e) => {
+                warn!("Failed to encode COV notification: {}", e);
+                continue;
+            }
+        };
+        let subscriber = pending.subscriber;
+        match pending.confirmed {
+            true => {
+                let inner = inner.clone();
+                let service = Ok(ConfirmedServiceChoice::ConfirmedCovNotification);
+                task::spawn(async move {
+                    if let Err(e) = inner.notify(&subscriber, service, data).await {
+                        warn!("Failed to notify {:?}: {}", subscriber, e);
+                    }
+                });
+            }
+            false => {
+                let service = Err(UnconfirmedServiceChoice::UnconfirmedCovNotification);
+                if let Err(e) = inner.notify(&subscriber, service, data).await {
+                    warn!("Failed to notify {:?}: {}", subscriber, e);
+                }
+            }
+        }
+    }
+}
+
+/// Address of the recipient of an event notification
+///
+/// Devices that have not announced themselves are looked for with a Who-Is,
+/// their notifications wait for the next attempt.
+async fn resolve<D: DataLink + 'static>(
+    inner: &Arc<Inner<D>>,
+    recipient: &Recipient,
+) -> Option<Address> {
+    let device = match recipient {
+        Recipient::Address(address) => return Some(address.clone()),
+        Recipient::Device(device) => device.instance,
+    };
+    if let Some(address) = inner.devices.lock().unwrap().get(&device) {
+        return Some(address.clone());
+    }
+    let who_is = WhoIsBuilder::new().device(device).build();
+    if let Err(e) = inner
+        .station
+        .send(&Address::global_broadcast(), who_is)
+        .await
+    {
+        warn!("Failed to look for device {}: {}", device, e);
+    }
+    None
+}
+
+/// Deliver the event notifications that are due, confirmed ones are
+/// acknowledged in the background
+///
+/// Notifications that can't be delivered, as the recipient is unknown or
+/// does not acknowledge them, are retried later. Notifications wait while
+/// initiation is disabled.
+async fn deliver_events<D: DataLink + 'static>(inner: &Arc<Inner<D>>) {
+    if !inner.may_initiate() {
+        return;
+    }
+    let due = inner
+        .events
+        .lock()
+        .unwrap()
+        .due(inner.station.clock().now());
+    for event in due {
+        let data = match event.notification.encode_vec() {
+            Ok(data) => data,
+            Err(e) => {
+                warn!("Failed to encode event notification: {}", e);
+                continue;
+            }
+        };
+        let address = match resolve(inner, &event.recipient).await {
+            Some(address) => address,
+            None => {
+                retry_event(inner, event);
+                continue;
+            }
+        };
+        match event.confirmed {
+            true => {
+                let inner = inner.clone();
+                let service = Ok(ConfirmedServiceChoice::ConfirmedEventNotification);
+                task::spawn(async move {
+                    match inner.notify(&address, service, data).await {
+                        Ok(()) => {}
+                        Err(e @ ClientError::Timeout) | Err(e @ ClientError::Io(_)) => {
+                            warn!("Failed to notify {:?}: {}", address, e);
+                            retry_event(&inner, event);
+                        }
+                        Err(e) => warn!("Failed to notify {:?}: {}", address, e),
+                    }
+                });
+            }
+            false => {
+                let service = Err(UnconfirmedServiceChoice::UnconfirmedEventNotification);
+                if let Err(e) = inner.notify(&address, service, data).await {
+                    warn!("Failed to notify {:?}: {}", address, e);
+                    retry_event(inner, event);
+                }
+            }
+        }
+    }
+}
+
+/// Report the event state transitions of the objects with intrinsic
+/// reporting
+fn report_alarms<D>(inner: &Inner<D>) {
+    let clock = inner.station.clock();
+    let objects = inner.objects.lock().unwrap();
+    let reports = inner.alarms.lock().unwrap().evaluate(&objects, clock.now());
+    let mut events = inner.events.lock().unwrap();
+    for report in reports {
+        let object = report.event_object_identifier;
+        let device = inner.info.object_identifier();
+        if let Err(e) = events.report(&objects, device, report, clock.date_time(), clock.now()) {
+            warn!("Failed to report event of {:?}: {}", object, e);
+        }
+    }
+}
+
+fn retry_event<D>(inner: &Inner<D>, event: PendingEvent) {
+    let recipient = event.recipient.clone();
+    if !inner
+        .events
+        .lock()
+        .unwrap()
+        .retry(event, inner.station.clock().now())
+    {
+        warn!("Dropping event notification to {:?}", recipient);
+    }
+}
+
+/// Send the answer to a request, broadcast answers after the jitter delay
+/// in the background
+async fn respond<D: DataLink + 'static>(inner: &Arc<Inner<D>>, address: Address, response: APDU) {
+    let (delay, interval) = {
+        let limits = inner.limits.lock().unwrap();
+        (limits.jitter(), limits.broadcast_response_interval)
+    };
+    if !address.is_broadcast() || delay + interval == Duration::ZERO {
+        if let Err(e) = inner.station.send(&address, response).await {
+            warn!("Failed to respond to {:?}: {}", address, e);
+        }
+        return;
+    }
+    let key = match response.encode_vec() {
+        Ok(data) => (address.clone(), data),
+        Err(e) => {
+            warn!("Failed to encode response: {}", e);
+            return;
+        }
+    };
+    // Answers to a storm of requests are sent once
+    let now = inner.station.clock().now();
+    {
+        let mut answers = inner.broadcast_answers.lock().unwrap();
+        answers.retain(|_, until| *until > now);
+        if answers.contains_key(&key) {
+            trace!("Not repeating the answer to {:?}", address);
+            return;
+        }
+        answers.insert(key, now + delay + interval);
+    }
+    if delay == Duration::ZERO {
+        if let Err(e) = inner.station.send(&address, response).await {
+            warn!("Failed to respond to {:?}: {}", address, e);
+        }
+        return;
+    }
+    let inner = inner.clone();
+    task::spawn(async move {
+        inner.station.clock().sleep(delay).await;
+        if let Err(e) = inner.station.send(&address, response).await {
+            warn!("Failed to respond to {:?}: {}", address, e);
+        }
+    });
+}
+
+/// Send a segmented response a window at a time, until the client
+/// acknowledged all segments or aborted the transaction (5.4.5)
+///
+/// Windows that are not acknowledged within the APDU timeout are sent
+/// again.
+async fn send_segmented<D: DataLink + 'static>(
+    inner: Arc<Inner<D>>,
+    address: Address,
+    mut sender: SegmentedSender,
+) {
+    let key = (address.clone(), sender.invoke_id());
+    let (acks, receiver) = channel::bounded(4);
+    inner
+        .segmented_responses
+        .lock()
+        .unwrap()
+        .insert(key.clone(), acks);
+    let timeout = *inner.apdu_timeout.lock().unwrap();
+    let mut retries = 0;
+    let mut send = true;
+    while !sender.is_complete() {
+        if send {
+            for segment in sender.window() {
+                if let Err(e) = inner.station.send(&address, segment).await {
+                    warn!("Failed to respond to {:?}: {}", address, e);
+                }
+            }
+        }
+        let sleep = inner.station.clock().sleep(timeout);
+        let ack = future::or(async { receiver.recv().await.ok() }, async {
+            sleep.await;
+            None
+        });
+        match ack.await {
+            Some(APDU::Abort { reason, .. }) => {
+                trace!("Segmented response aborted by {:?}: {}", address, reason);
+                break;
+            }
+            Some(ack) => {
+                let before = sender.progress();
+                let progress = match sender.segment_ack(&ack) {
+                    Ok(progress) => progress,
+                    Err(_) => continue,
+                };
+                trace!("Segmented response to {:?}: {:?}", address, progress);
+                // Duplicate ACKs don't repeat the window
+                send = progress != before || matches!(ack, APDU::SegmentAck { negative: true, .. });
+                retries = 0;
+            }
+            None if retries < SEGMENT_RETRIES => {
+                retries += 1;
+                send = true;
+            }
+            None => {
+                warn!("Segmented response to {:?} not acknowledged", address);
+                break;
+            }
+        }
+    }
+    inner.segmented_responses.lock().unwrap().remove(&key);
+}
+
+async fn run<D: DataLink + 'static>(inner: Arc<Inner<D>>, port: usize) {
+    loop {
+        match inner.station.recv(port).await {
+            Ok((mac, npdu)) => {
+                if let Some((address, response)) = inner.receive(port, mac, npdu) {
+                    // Confirmed requests may have changed values
+                    let confirmed = !matches!(
+                        response,
+                        Reply::Unsegmented(APDU::UnconfirmedRequest { .. })
+                    );
+                    let mut response = response;
+                    loop {
+                        match response {
+                            Reply::Unsegmented(response) => {
+                                respond(&inner, address, response).await
+                            }
+                            Reply::Segmented(sender) => {
+                                task::spawn(send_segmented(inner.clone(), address, sender));
+                            }
+                            Reply::Reassembled(ack, reply) => {
+                                respond(&inner, address.clone(), ack).await;
+                                response = *reply;
+                                continue;
+                            }
+                        }
+                        break;
+                    }
+                    if confirmed {
+                        notify_changes(&inner).await;
+                        deliver_events(&inner).await;
+                    }
+                }
+            }
+            Err(e) => {
+                warn!("Data link failed: {}", e);
+                break;
+            }
+        }
+    }
+}
+
+/// Check for changes made to the objects by the application, evaluate
+/// intrinsic reporting and retry event notifications
+async fn poll<D: DataLink + 'static>(inner: Arc<Inner<D>>) {
+    loop {
+        inner.station.clock().sleep(POLL_INTERVAL).await;
+        notify_changes(&inner).await;
+        report_alarms(&inner);
+        deliver_events(&inner).await;
+    }
+}
+
+/// BACnet device on one or more data links
+///
+/// Received requests are served by a background task per data link, which
+/// are stopped when the device is dropped. Requests are answered on the data
+/// link they were received on. Who-Is requests whose range includes the
+/// instance of the device are answered with an I-Am.
+///
+/// Subscribers are notified of changes of values made by requests right
+/// away, of changes made through [`BacnetDevice::objects`] within a second.
+pub struct BacnetDevice<D: DataLink + 'static> {
+    inner: Arc<Inner<D>>,
+    tasks: Vec<JoinHandle<()>>,
+}
+
+impl<D: DataLink + 'static> BacnetDevice<D> {
+    /// A device on a single data link
+    pub fn new(link: D, info: DeviceInfo) -> Self {
+        Self::start(Station::new(link), info)
+    }
+
+    /// A device on several data links, each with its distinct network
+    /// number
+    ///
+    /// Stations on the data links are addressed on their network, local
+    /// addresses without a network number are on the first data link. Data
+    /// links of different types are combined as `Box<dyn DataLink>`. NPDUs
+    /// are not routed between the networks, see [`Gateway`] for devices
+    /// behind a router.
+    pub fn with_ports(ports: Vec<(u16, D)>, info: DeviceInfo) -> Self {
+        let ports = ports
+            .into_iter()
+            .map(|(network, link)| Port {
+                link,
+                network: Some(network),
+            })
+            .collect();
+        Self::start(Station::with_ports(ports), info)
+    }
+
+    fn start(station: Station<D>, info: DeviceInfo) -> Self {
+        let device = DeviceObject::new(info.instance, info.name.clone(), info.vendor_id);
+        let inner = Arc::new(Inner {
+            station,
+            info,
+            device: Mutex::new(device),
+            objects: Mutex::new(ObjectStore::new()),
+            database: Mutex::new(None),
+            confirmed_handlers: Mutex::new(HashMap::new()),
+            unconfirmed_handlers: Mutex::new(HashMap::new()),
+            reinitialize_handler: Mutex::new(None),
+            communication: Mutex::new(Communication::default()),
+            limits: Mutex::new(Limits::default()),
+            rate_limiter: Mutex::new(RateLimiter::default()),
+            broadcast_answers: Mutex::new(HashMap::new()),
+            cov_subscriptions: Mutex::new(CovSubscriptions::default()),
+            events: Mutex::new(Events::default()),
+            alarms: Mutex::new(Alarms::default()),
+            devices: Mutex::new(HashMap::new()),
+            apdu_timeout: Mutex::new(DEFAULT_APDU_TIMEOUT),
+            apdu_retries: Mutex::new(DEFAULT_APDU_RETRIES),
+            responder: Mutex::new(Responder::default()),
+            segmented_responses: Mutex::new(HashMap::new()),
+        });
+        let mut tasks: Vec<_> = (0..inner.station.port_count())
+            .map(|port| task::spawn(run(inner.clone(), port)))
+            .collect();
+        tasks.push(task::spawn(poll(inner.clone())));
+        Self { inner, tasks }
+    }
+
+    pub fn info(&self) -> &DeviceInfo {
+        &self.inner.info
+    }
+
+    /// The objects served, requests wait while they are borrowed
+    pub fn objects(&self) -> MutexGuard<'_, ObjectStore> {
+        self.inner.objects.lock().unwrap()
+    }
+
+    /// Handle a confirmed service with `handler`, replacing the built-in
+    /// or previously registered handling of the service
+    ///
+    /// ```
+    /// # use bacnet::application::ConfirmedServiceChoice;
+    /// # use bacnet::network::Address;
+    /// # use bacnet::server::{BacnetDevice, ObjectStore, Response};
+    /// # fn f<D: bacnet::transport::DataLink>(device: BacnetDevice<D>) {
+    /// device.on_confirmed(
+    ///     ConfirmedServiceChoice::ConfirmedPrivateTransfer,
+    ///     |_: &Address, data: &[u8], _: &mut ObjectStore| Ok(Response::ComplexAck(data.to_vec())),
+    /// );
+    /// # }
+    /// ```
+    pub fn on_confirmed<H: ConfirmedHandler + 'static>(
+        &self,
+        service: ConfirmedServiceChoice,
+        handler: H,
+    ) {
+        let mut handlers = self.inner.confirmed_handlers.lock().unwrap();
+        handlers.insert(service, Arc::new(handler));
+    }
+
+    /// Handle an unconfirmed service with `handler`, replacing the built-in
+    /// or previously registered handling of the service
+    pub fn on_unconfirmed<H: UnconfirmedHandler + 'static>(
+        &self,
+        service: UnconfirmedServiceChoice,
+        handler: H,
+    ) {
+        let mut handlers = self.inner.unconfirmed_handlers.lock().unwrap();
+        handlers.insert(service, Arc::new(handler));
+    }
+
+    /// The Device object, whose vendor name, model name, software versions,
+    /// system status and database revision are set by the application
+    ///
+    /// The identity, object list, services supported and APDU settings are
+    /// those of the device.
+    pub fn device_object(&self) -> MutexGuard<'_, DeviceObject> {
+        self.inner.device.lock().unwrap()
+    }
+
+    /// Serve the objects that are not in the object store from `database`
+    pub fn set_database<B: ObjectDatabase + Send + 'static>(&self, database: B) {
+        *self.inner.database.lock().unwrap() = Some(Box::new(database));
+    }
+
+    /// Execute ReinitializeDevice requests with `handler`, which is called
+    /// before the request is acknowledged
+    ///
+    /// Without a handler the requests fail.
+    pub fn on_reinitialize<H: ReinitializeHandler + 'static>(&self, handler: H) {
+        *self.inner.reinitialize_handler.lock().unwrap() = Some(Arc::new(handler));
+    }
+
+    /// The communication state set with DeviceCommunicationControl
+    pub fn communication(&self) -> EnableDisable {
+        self.inner.communication()
+    }
+
+    /// Counters of the frames sent and received so far
+    pub fn statistics(&self) -> Statistics {
+        self.inner.station.statistics()
+    }
+
+    /// Active COV subscriptions
+    pub fn cov_subscriptions(&self) -> Vec<CovSubscription> {
+        self.inner.cov_subscriptions.lock().unwrap().list()
+    }
+
+    /// Objects that are not normal or have unacknowledged transitions
+    pub fn event_summaries(&self) -> Vec<EventSummary> {
+        self.inner.events.lock().unwrap().summaries(None)
+    }
+
+    pub fn limits(&self) -> Limits {
+        self.inner.limits.lock().unwrap().clone()
+    }
+
+    /// Protect the device from misbehaving clients
+    pub fn set_limits(&self, limits: Limits) {
+        *self.inner.limits.lock().unwrap() = limits;
+    }
+
+    pub fn apdu_timeout(&self) -> Duration {
+        *self.inner.apdu_timeout.lock().unwrap()
+    }
+
+    /// Time to wait for confirmed notifications and the segments of
+    /// responses to be acknowledged
+    pub fn set_apdu_timeout(&mut self, timeout: Duration) {
+        *self.inner.apdu_timeout.lock().unwrap() = timeout;
+    }
+
+    pub fn apdu_retries(&self) -> usize {
+        *self.inner.apdu_retries.lock().unwrap()
+    }
+
+    /// Send confirmed notifications that are not acknowledged within the
+    /// APDU timeout again, up to `retries` times
+    pub fn set_apdu_retries(&mut self, retries: usize) {
+        *self.inner.apdu_retries.lock().unwrap() = retries;
+    }
+
+    /// Read the time from `clock` instead of the system clock
+    ///
+    /// UTCTimeSynchronization requests set the date and time of the clock,
+    /// if it can be set.
+    pub fn set_clock<C: Clock + 'static>(&mut self, clock: C) {
+        self.inner.station.set_clock(Arc::new(clock));
+    }
+
+    /// Broadcast an I-Am, e.g. on startup
+    ///
+    /// Fails while initiation is disabled by DeviceCommunicationControl.
+    pub async fn announce(&self) -> std::io::Result<()> {
+        if !self.inner.may_initiate() {
+            return Err(initiation_disabled());
+        }
+        let data = self.inner.info.i_am().encode_vec()?;
+        let request = APDU::unconfirmed_request(UnconfirmedServiceChoice::IAm as u8, data);
+        self.inner
+            .station
+            .send(&Address::global_broadcast(), request)
+            .await
+    }
+
+    /// Record a transition of the event state of an object and notify it to
+    /// the recipients of its notification class
+    ///
+    /// The notifications are sent in the background and retried for
+    /// recipients that can't be reached. Fails if the Notification Class
+    /// object does not exist.
+    pub async fn report_event(&self, report: EventReport) -> Result<(), BACnetError> {
+        {
+            let clock = self.inner.station.clock();
+            let objects = self.inner.objects.lock().unwrap();
+            self.inner.events.lock().unwrap().report(
+                &objects,
+                self.inner.info.object_identifier(),
+                report,
+                clock.date_time(),
+                clock.now(),
+            )?;
+        }
+        deliver_events(&self.inner).await;
+        Ok(())
+    }
+
+    /// Send a COV notification (13.14, 13.15), a confirmed one waits for
+    /// the acknowledgement
+    pub async fn send_cov_notification(
+        &self,
+        address: &Address,
+        notification: &CovNotification,
+        confirmed: bool,
+    ) -> Result<(), ClientError> {
+        let service = match confirmed {
+            true => Ok(ConfirmedServiceChoice::ConfirmedCovNotification),
+            false => Err(UnconfirmedServiceChoice::UnconfirmedCovNotification),
+        };
+        self.inner
+            .notify(address, service, notification.encode_vec()?)
+            .await
+    }
+
+    /// Send an event notification (13.8, 13.9), a confirmed one waits for
+    /// the acknowledgement
+    pub async fn send_event_notification(
+        &self,
+        address: &Address,
+        notification: &EventNotification,
+        confirmed: bool,
+    ) -> Result<(), ClientError> {
+        let service = match confirmed {
+            true => Ok(ConfirmedServiceChoice::ConfirmedEventNotification),
+            false => Err(UnconfirmedServiceChoice::UnconfirmedEventNotification),
+        };
+        self.inner
+            .notify(address, service, notification.encode_vec()?)
+            .await
+    }
+}
+
+impl<D: DataLink + 'static> Drop for BacnetDevice<D> {
+    fn drop(&mut self) {
+        for task in self.tasks.drain(..) {
+            task::spawn(task.cancel());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::tests::{apdu, link_pair, reply, MockLink};
+    use crate::client::BacnetClient;
+    use crate::clock::MockClock;
+    use crate::objects::testing::at;
+    use crate::objects::LightingOutput;
+    use crate::Decode;
+    use std::convert::TryFrom;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    fn device(link: MockLink) -> BacnetDevice<MockLink> {
+        let device = BacnetDevice::new(link, DeviceInfo::new(12, "Controller", 15));
+        device.objects().insert(LightingOutput::new(1, "Lamp"));
+        device
+    }
+
+    fn lamp() -> ObjectIdentifier {
+        ObjectIdentifier::new(ObjectType::LightingOutput, 1)
+    }
+
+    /// A client on the other end of the link, which knows the device
+    fn client(link: MockLink) -> BacnetClient<MockLink> {
+        let client = BacnetClient::new(link);
+        client.add_device(12, Address::local(vec![1]));
+        client
+    }
+
+    /// Send raw APDU octets to the device and return its response
+    async fn request(peer: &MockLink, data: &str) -> APDU {
+        let request = APDU::decode_slice(&hex::decode(data).unwrap()).unwrap();
+        reply(peer, request).await;
+        apdu(peer.recv().await.unwrap().1)
+    }
+
+    #[test]
+    fn test_who_is() {
+        task::block_on(async {
+            let (link, peer) = link_pair();
+            let _device = device(link);
+            let client = BacnetClient::new(peer);
+
+            let devices = client
+                .who_is(Some((10, 20)), Duration::from_millis(100))
+                .await
+                .unwrap();
+            assert_eq!(devices.len(), 1);
+            assert_eq!(devices[0].1, DeviceInfo::new(12, "Controller", 15).i_am());
+            let devices = client
+                .who_is(Some((13, 20)), Duration::from_millis(100))
+                .await
+                .unwrap();
+            assert!(devices.is_empty());
+            let found = client
+                .find_object("Lamp", None, Duration::from_millis(100))
+                .await
+                .unwrap();
+            assert_eq!(found.len(), 1);
+            assert_eq!(found[0].1.object_identifier, lamp());
+        });
+    }
+
+    #[test]
+    fn test_device_object() {
+        task::block_on(async {
+            let (link, peer) = link_pair();
+            let mut device = device(link);
+            device.set_apdu_retries(5);
+            device.device_object().model_name = "Lamp controller".into();
+            device.on_confirmed(
+                ConfirmedServiceChoice::ConfirmedPrivateTransfer,
+                |_: &Address, _: &[u8], _: &mut ObjectStore| Ok(Response::SimpleAck),
+            );
+            let client = client(peer);
+
+            let object = device.info().object_identifier();
+            let read = |property| client.read(12, object, property);
+            assert_eq!(
+                read(PropertyIdentifier::ModelName).await.unwrap(),
+                BACnetValue::CharacterString("Lamp controller".into())
+            );
+            assert_eq!(
+                read(PropertyIdentifier::NumberOfApduRetries).await.unwrap(),
+                BACnetValue::Unsigned(5)
+            );
+            match read(PropertyIdentifier::ProtocolServicesSupported)
+                .await
+                .unwrap()
+            {
+                BACnetValue::BitString(bits) => {
+                    let services = ServicesSupported::from(bits);
+                    assert!(services.confirmed(ConfirmedServiceChoice::ReadProperty));
+                    assert!(services.confirmed(ConfirmedServiceChoice::ConfirmedPrivateTransfer));
+                    assert!(services.unconfirmed(UnconfirmedServiceChoice::WhoIs));
+                    // ReinitializeDevice without a handler
+                    assert!(!services.confirmed(ConfirmedServiceChoice::ReinitializeDevice));
+                }
+                value => panic!("Unexpected {:?}", value),
+            }
+            assert!(client
+                .write(
+                    12,
+                    object,
+                    PropertyIdentifier::ModelName,
+                    BACnetValue::Null,
+                    None
+                )
+                .await
+                .is_err());
+        });
+    }
+
+    /// Analog values kept by instance, as an application database would
+    #[derive(Default)]
+    struct Sensors(HashMap<u32, f32>);
+
+    impl ObjectDatabase for Sensors {
+        fn object_list(&self) -> Vec<ObjectIdentifier> {
+            let mut list: Vec<_> = self
+                .0
+                .keys()
+                .map(|i| ObjectIdentifier::new(ObjectType::AnalogValue, *i))
+                .collect();
+            list.sort();
+            list
+        }
+
+        fn property_list(
+            &self,
+            _: ObjectIdentifier,
+        ) -> Result<Vec<PropertyIdentifier>, BACnetError> {
+            Ok(vec![PropertyIdentifier::PresentValue])
+        }
+
+        fn read_property(
+            &self,
+            object: ObjectIdentifier,
+            property: PropertyIdentifier,
+            _: Option<u32>,
+        ) -> Result<BACnetValue, BACnetError> {
+            let value = self
+                .0
+                .get(&object.instance)
+                .ok_or_else(|| BACnetError::object(ErrorCode::UnknownObject))?;
+            match property {
+                PropertyIdentifier::ObjectIdentifier => Ok(BACnetValue::ObjectIdentifier(object)),
+                PropertyIdentifier::PresentValue => Ok(BACnetValue::Real(*value)),
+                _ => Err(BACnetError::property(ErrorCode::UnknownProperty)),
+            }
+        }
+
+        fn write_property(
+            &mut self,
+            object: ObjectIdentifier,
+            _: PropertyIdentifier,
+            _: Option<u32>,
+            value: BACnetValue,
+            _: Option<u8>,
+            _time: BACnetDateTime,
+        ) -> Result<(), BACnetError> {
+            match (self.0.get_mut(&object.instance), value) {
+                (Some(present_value), BACnetValue::Real(value)) => {
+                    *present_value = value;
+                    Ok(())
+                }
+                (Some(_), _) => Err(BACnetError::property(ErrorCode::InvalidDataType)),
+                (None, _) => Err(BACnetError::object(ErrorCode::UnknownObject)),
+            }
+        }
+    }
+
+    #[test]
+    fn test_object_database() {
+        task::block_on(async {
+            let (link, peer) = link_pair();
+            let device = device(link);
+            device.set_database(Sensors(HashMap::from([(3, 21.5)])));
+            let client = client(peer);
+
+            let sensor = ObjectIdentifier::new(ObjectType::AnalogValue, 3);
+            let list = client
+                .read(
+                    12,
+                    device.info().object_identifier(),
+                    PropertyIdentifier::ObjectList,
+                )
+                .await
+                .unwrap();
+            assert_eq!(
+                list,
+                BACnetValue::Array(vec![
+                    BACnetValue::ObjectIdentifier(device.info().object_identifier()),
+                    BACnetValue::ObjectIdentifier(lamp()),
+                    BACnetValue::ObjectIdentifier(sensor),
+                ])
+            );
+            client
+                .write(
+                    12,
+                    sensor,
+                    PropertyIdentifier::PresentValue,
+                    BACnetValue::Real(19.0),
+                    None,
+                )
+                .await
+                .unwrap();
+            let results = client
+                .read_multiple(
+                    12,
+                    &[
+                        (sensor, PropertyIdentifier::PresentValue),
+                        (sensor, PropertyIdentifier::Description),
+                        (lamp(), PropertyIdentifier::ObjectName),
+                    ],
+                )
+                .await
+                .unwrap();
+            assert_eq!(
+                results,
+                vec![
+                    Ok(BACnetValue::Real(19.0)),
+                    Err(BACnetError::property(ErrorCode::UnknownProperty)),
+                    Ok(BACnetValue::CharacterString("Lamp".into())),
+                ]
+            );
+            let unknown = ObjectIdentifier::new(ObjectType::AnalogValue, 4);
+            assert!(client
+                .read(12, unknown, PropertyIdentifier::PresentValue)
+                .await
+                .is_err());
+        });
+    }
+
+    #[test]
+    fn test_read_property() {
+        task::block_on(async {
+            let (link, peer) = link_pair();
+            let _device = device(link);
+            let client = client(peer);
+
+            let device = ObjectIdentifier::new(ObjectType::Device, 12);
+            let list = client
+                .read(12, device, PropertyIdentifier::ObjectList)
+                .await
+                .unwrap();
+            assert_eq!(
+                list,
+                BACnetValue::Array(vec![
+                    BACnetValue::ObjectIdentifier(device),
+                    BACnetValue::ObjectIdentifier(lamp()),
+                ])
+            );
+            let name = client
+                .read_as::<String>(12, lamp(), PropertyIdentifier::ObjectName)
+                .await
+                .unwrap();
+            assert_eq!(name, "Lamp");
+
+            let missing = ObjectIdentifier::new(ObjectType::LightingOutput, 2);
+            let result = client
+                .read(12, missing, PropertyIdentifier::PresentValue)
+                .await;
+            assert!(matches!(
+                result,
+                Err(ClientError::Error(e)) if e == BACnetError::object(ErrorCode::UnknownObject)
+            ));
+            let result = client
+                .read(12, device, PropertyIdentifier::PresentValue)
+                .await;
+            assert!(matches!(
+                result,
+                Err(ClientError::Error(e)) if e == BACnetError::property(ErrorCode::UnknownProperty)
+            ));
+            let result = client
+                .read(12, lamp(), PropertyIdentifier::Reserved(18))
+                .await;
+            assert!(matches!(
+                result,
+                Err(ClientError::Error(e)) if e == BACnetError::property(ErrorCode::UnknownProperty)
+            ));
+        });
+    }
+
+    #[test]
+    fn test_write_property() {
+        task::block_on(async {
+            let (link, peer) = link_pair();
+            let device = device(link);
+            let client = client(peer);
+
+            client
+                .write(
+                    12,
+                    lamp(),
+                    PropertyIdentifier::PresentValue,
+                    BACnetValue::Real(50.0),
+                    Some(8),
+                )
+                .await
+                .unwrap();
+            let value = device
+                .objects()
+                .get(lamp())
+                .unwrap()
+                .read_property(PropertyIdentifier::PresentValue, None);
+            assert_eq!(value.unwrap(), BACnetValue::Real(50.0));
+
+            let result = client
+                .write(
+                    12,
+                    device.info().object_identifier(),
+                    PropertyIdentifier::ObjectName,
+                    BACnetValue::CharacterString("Renamed".into()),
+                    None,
+                )
+                .await;
+            assert!(matches!(
+                result,
+                Err(ClientError::Error(e)) if e == BACnetError::property(ErrorCode::WriteAccessDenied)
+            ));
+        });
+    }
+
+    #[test]
+    fn test_read_range() {
+        use crate::client::LogStart;
+        use crate::objects::{LogDatum, TrendLogObject};
+        use futures_lite::StreamExt;
+
+        task::block_on(async {
+            let (link, peer) = link_pair();
+            let device = device(link);
+            let client = client(peer);
+            let object = ObjectIdentifier::new(ObjectType::TrendLog, 1);
+            let mut log = TrendLogObject::new(1, "Power", 100);
+            for minute in 0..100 {
+                let timestamp = BACnetDateTime::new(
+                    BACnetDate::new(2021, 1, 25),
+                    BACnetTime::new(12 + minute / 60, minute % 60, 0, 0),
+                );
+                log.log(timestamp, Ok(BACnetValue::Real(minute as f32)), None);
+            }
+            device.objects().insert(log);
+
+            // Read in pages that fit in 480 octets
+            let records: Vec<_> = client
+                .trend_log_records(12, object, LogStart::SequenceNumber(1))
+                .collect()
+                .await;
+            assert_eq!(records.len(), 100);
+            let last = records.last().unwrap().as_ref().unwrap();
+            assert_eq!(last.sequence_number, 100);
+            assert_eq!(last.datum, LogDatum::Value(BACnetValue::Real(99.0)));
+
+            // Records that don't fit in a response are left out
+            let range = Range::BySequenceNumber {
+                reference_sequence_number: 1,
+                count: 100,
+            };
+            let page = client.read_log_range(12, object, range).await.unwrap();
+            assert!(page.result_flags.more_items);
+            assert!(!page.result_flags.last_item);
+            assert!(page.records.len() < 100);
+
+            let result = client.read_log_range(12, lamp(), range).await;
+            assert!(matches!(
+                result,
+                Err(ClientError::Error(e)) if e.error_code == ErrorCode::UnknownProperty
+            ));
+        });
+    }
+
+    #[test]
+    fn test_atomic_file() {
+        use crate::objects::{FileAccessMethod, FileObject};
+
+        task::block_on(async {
+            let (link, peer) = link_pair();
+            let device = device(link);
+            let client = client(peer);
+            let file = ObjectIdentifier::new(ObjectType::File, 1);
+            let firmware = FileObject::new(1, "firmware", FileAccessMethod::StreamAccess, vec![]);
+            device.objects().insert(firmware);
+
+            for chunk in [&b"abcd"[..], b"efg"] {
+                let request = AtomicWriteFileRequest {
+                    file_identifier: file,
+                    data: FileData::Stream {
+                        file_start_position: -1,
+                        file_data: chunk.to_vec(),
+                    },
+                };
+                client.atomic_write_file(12, &request).await.unwrap();
+            }
+            let request = AtomicReadFileRequest {
+                file_identifier: file,
+                access: FileReadAccess::Stream {
+                    file_start_position: 2,
+                    requested_octet_count: 100,
+                },
+            };
+            let ack = client.atomic_read_file(12, &request).await.unwrap();
+            assert!(ack.end_of_file);
+            assert_eq!(
+                ack.data,
+                FileData::Stream {
+                    file_start_position: 2,
+                    file_data: b"cdefg".to_vec(),
+                }
+            );
+
+            let request = AtomicReadFileRequest {
+                file_identifier: lamp(),
+                ..request
+            };
+            let result = client.atomic_read_file(12, &request).await;
+            assert!(matches!(
+                result,
+                Err(ClientError::Error(e)) if e.error_code == ErrorCode::InconsistentObjectType
+            ));
+        });
+    }
+
+    #[test]
+    fn test_read_property_multiple() {
+        task::block_on(async {
+            let (link, peer) = link_pair();
+            let _device = device(link);
+            let client = client(peer);
+
+            let missing = ObjectIdentifier::new(ObjectType::LightingOutput, 2);
+            let results = client
+                .read_multiple(
+                    12,
+                    &[
+                        (lamp(), PropertyIdentifier::ObjectName),
+                        (lamp(), PropertyIdentifier::PriorityArray),
+                        (missing, PropertyIdentifier::ObjectName),
+                    ],
+                )
+                .await
+                .unwrap();
+            assert_eq!(results[0], Ok(BACnetValue::CharacterString("Lamp".into())));
+            assert!(matches!(&results[1], Ok(BACnetValue::Array(a)) if a.len() == 16));
+            assert_eq!(
+                results[2],
+                Err(BACnetError::object(ErrorCode::UnknownObject))
+            );
+        });
+    }
+
+    #[test]
+    fn test_read_all() {
+        task::block_on(async {
+            let (link, peer) = link_pair();
+            let device = device(link);
+
+            // Required properties of the device, up to 1476 octets
+            let ack = request(&peer, "0005010e0c0200000c1e09691f").await;
+            assert_eq!(ack.pdu_type(), BACnetPDU::ComplexACK);
+            let mut expected = hex::decode("0c0200000c1e").unwrap();
+            let info = device.info();
+            for (property, value) in [
+                (75, BACnetValue::ObjectIdentifier(info.object_identifier())),
+                (77, BACnetValue::CharacterString(info.name.as_str().into())),
+                (
+                    79,
+                    BACnetValue::Enumerated(ObjectType::Device.number() as u32),
+                ),
+            ] {
+                encode_context_enumerated(&mut expected, 2, property);
+                encode_opening_tag(&mut expected, 4);
+                encode_application(&mut expected, &value).unwrap();
+                encode_closing_tag(&mut expected, 4);
+            }
+            assert!(ack.user_data().starts_with(&expected));
+
+            // All properties of the lamp do not fit 50 octets
+            let abort = request(&peer, "0000020e0c0d8000011e09081f").await;
+            assert_eq!(
+                abort,
+                AbortPDU::new(true, 2, AbortReason::SegmentationNotSupported).to_apdu()
+            );
+        });
+    }
+
+    #[test]
+    fn test_wildcard_instance() {
+        task::block_on(async {
+            let (link, peer) = link_pair();
+            let _device = device(link);
+
+            // The response identifies the device
+            let ack = request(&peer, "0005010c0c023fffff194d").await;
+            assert_eq!(ack.pdu_type(), BACnetPDU::ComplexACK);
+            assert!(ack
+                .user_data()
+                .starts_with(&hex::decode("0c0200000c194d3e").unwrap()));
+        });
+    }
+
+    #[test]
+    fn test_reject() {
+        task::block_on(async {
+            let (link, peer) = link_pair();
+            let _device = device(link);
+
+            // CreateObject is not executed
+            let reject = request(&peer, "0005030a0e0c0d8000010f").await;
+            assert_eq!(reject.pdu_type(), BACnetPDU::Reject);
+            let reject = RejectPDU::try_from(&reject).unwrap();
+            assert_eq!(reject.reason(), Some(RejectReason::UnrecognizedService));
+            // ReadProperty without a property identifier
+            let reject = request(&peer, "0005040c0c0200000c").await;
+            // The first segment of a segmented ReadPropertyMultiple
+            let abort = request(&peer, "0c0505000f0e0c00000001").await;
+            let abort = AbortPDU::try_from(&abort).unwrap();
+            assert_eq!(
+                abort,
+                AbortPDU::new(true, 5, AbortReason::SegmentationNotSupported)
+            );
+            assert_eq!(
+                RejectPDU::try_from(&reject).unwrap().reason(),
+                Some(RejectReason::InvalidTag)
+            );
+        });
+    }
+
+    #[test]
+    fn test_segmented_response() {
+        task::block_on(async {
+            let (link, peer) = link_pair();
+            let mut info = DeviceInfo::new(12, "Controller", 15);
+            info.segmentation_supported = Segmentation::SegmentedBoth;
+            let device = BacnetDevice::new(link, info);
+            device.objects().insert(LightingOutput::new(1, "Lamp"));
+
+            // All properties of the lamp, in up to 50 octets
+            let rpm = "0e0c0d8000011e09081f";
+            let first = request(&peer, &format!("020007{}", rpm)).await;
+            assert_eq!(first.segment().unwrap().sequence_number, 0);
+            let mut segments = vec![first];
+            reply(&peer, APDU::segment_ack(false, false, 7, 0, 2)).await;
+
+            let mut nak = true;
+            let mut resent = None;
+            loop {
+                let mut window = vec![apdu(peer.recv().await.unwrap().1)];
+                if window[0].segment().unwrap().more_follows {
+                    window.push(apdu(peer.recv().await.unwrap().1));
+                }
+                if let Some(resent) = resent.take() {
+                    assert_eq!(window[0], resent);
+                }
+                assert!(window.iter().all(|s| s.encode_vec().unwrap().len() <= 50));
+                let last = window.last().unwrap().segment().unwrap();
+                // The second segment of the first window is asked for again
+                if nak {
+                    nak = false;
+                    let sequence_number = window[0].segment().unwrap().sequence_number;
+                    segments.push(window.remove(0));
+                    resent = window.pop();
+                    reply(&peer, APDU::segment_ack(true, false, 7, sequence_number, 2)).await;
+                    continue;
+                }
+                segments.extend(window);
+                reply(
+                    &peer,
+                    APDU::segment_ack(false, false, 7, last.sequence_number, 2),
+                )
+                .await;
+                if !last.more_follows {
+                    break;
+                }
+            }
+            let data: Vec<u8> = segments
+                .iter()
+                .flat_map(|s| s.user_data().to_vec())
+                .collect();
+            let ack = ReadPropertyMultipleAck::decode_slice(&data).unwrap();
+            assert!(ack.results().count() > 10);
+
+            // Without segmented responses accepted, or with too few segments
+            let abort = request(&peer, &format!("000008{}", rpm)).await;
+            assert_eq!(
+                AbortPDU::try_from(&abort).unwrap().reason(),
+                Some(AbortReason::SegmentationNotSupported)
+            );
+            let abort = request(&peer, &format!("021009{}", rpm)).await;
+            assert_eq!(
+                AbortPDU::try_from(&abort).unwrap().reason(),
+                Some(AbortReason::BufferOverflow)
+            );
+        });
+    }
+
+    #[test]
+    fn test_duplicate_request() {
+        task::block_on(async {
+            let (link, peer) = link_pair();
+            let mut device = device(link);
+            let clock = MockClock::new(std::time::UNIX_EPOCH);
+            device.set_clock(clock.clone());
+            let executed = Arc::new(AtomicUsize::new(0));
+            let count = executed.clone();
+            device.on_confirmed(
+                ConfirmedServiceChoice::ConfirmedPrivateTransfer,
+                move |_: &Address, _: &[u8], _: &mut ObjectStore| {
+                    let count = count.fetch_add(1, Ordering::Relaxed) as u8 + 1;
+                    Ok(Response::ComplexAck(vec![count]))
+                },
+            );
+
+            // The retransmission of a request is answered without executing
+            // it again, until the client stopped retrying
+            let transfer = "001201120919190a";
+            let first = request(&peer, transfer).await;
+            assert_eq!(first.user_data(), [1]);
+            assert_eq!(request(&peer, transfer).await, first);
+            assert_eq!(executed.load(Ordering::Relaxed), 1);
+            assert_eq!(request(&peer, "001201120919190b").await.user_data(), [2]);
+            clock.advance(DEFAULT_APDU_TIMEOUT * (DEFAULT_APDU_RETRIES as u32 + 1));
+            assert_eq!(request(&peer, transfer).await.user_data(), [3]);
+        });
+    }
+
+    #[test]
+    fn test_segmented_request() {
+        task::block_on(async {
+            let (link, peer) = link_pair();
+            let mut info = DeviceInfo::new(12, "Controller", 15);
+            info.segmentation_supported = Segmentation::SegmentedBoth;
+            let device = BacnetDevice::new(link, info);
+            device.on_confirmed(
+                ConfirmedServiceChoice::ConfirmedPrivateTransfer,
+                |_: &Address, data: &[u8], _: &mut ObjectStore| {
+                    Ok(Response::ComplexAck(data.to_vec()))
+                },
+            );
+
+            let transfer = hex::decode("0919190a2e6500064c6f6e6720706172616d65746572732f").unwrap();
+            let request = APDU::confirmed_request(4, 18, transfer.clone());
+            let mut sender = SegmentedSender::new(&request, 16, 2, None).unwrap();
+            while !sender.is_complete() {
+                for segment in sender.window() {
+                    reply(&peer, segment).await;
+                }
+                let ack = apdu(peer.recv().await.unwrap().1);
+                assert!(matches!(ack, APDU::SegmentAck { server: true, .. }));
+                sender.segment_ack(&ack).unwrap();
+            }
+            let response = apdu(peer.recv().await.unwrap().1);
+            assert_eq!(response, APDU::complex_ack(4, 18, transfer));
+
+            // A segment without the first one
+            let request = APDU::confirmed_request(5, 18, vec![0; 20]);
+            let mut sender = SegmentedSender::new(&request, 16, 2, None).unwrap();
+            sender
+                .segment_ack(&APDU::segment_ack(false, true, 5, 0, 2))
+                .unwrap();
+            reply(&peer, sender.window().remove(0)).await;
+            let abort = AbortPDU::try_from(&apdu(peer.recv().await.unwrap().1)).unwrap();
+            assert_eq!(abort.reason(), Some(AbortReason::InvalidApduInThisState));
+        });
+    }
+
+    #[test]
+    fn test_ports() {
+        task::block_on(async {
+            let (link_a, peer_a) = link_pair();
+            let (link_b, peer_b) = link_pair();
+            let info = DeviceInfo::new(12, "Router", 15);
+            let device = BacnetDevice::with_ports(vec![(1, link_a), (2, link_b)], info);
+
+            // Answered on the network of the request
+            reply(&peer_b, APDU::unconfirmed_request(8, vec![])).await;
+            let (_, npdu) = peer_b.recv().await.unwrap();
+            assert!(npdu.destination.is_none());
+            assert_eq!(
+                apdu(npdu).service_choice().unwrap(),
+                UnconfirmedServiceChoice::IAm as u8
+            );
+            let ack = request(&peer_a, "0005010c0c0200000c194d").await;
+            assert_eq!(ack.pdu_type(), BACnetPDU::ComplexACK);
+
+            device.announce().await.unwrap();
+            for peer in [&peer_a, &peer_b] {
+                let (_, npdu) = peer.recv().await.unwrap();
+                assert_eq!(npdu.destination.unwrap().net, GLOBAL_BROADCAST);
+            }
+            let quiet = async_std::future::timeout(Duration::from_millis(20), peer_a.recv());
+            assert!(quiet.await.is_err());
+        });
+    }
+
+    #[test]
+    fn test_private_transfer() {
+        task::block_on(async {
+            let (link, peer) = link_pair();
+            let device = device(link);
+            device.on_confirmed(
+                ConfirmedServiceChoice::ConfirmedPrivateTransfer,
+                |_: &Address, data: &[u8], _: &mut ObjectStore| {
+                    let transfer = PrivateTransfer::decode_slice(data)?;
+                    match transfer.service_number {
+                        1 => {
+                            let result = transfer.service_parameters.clone();
+                            let ack = PrivateTransferAck::new(&transfer, result);
+                            Ok(Response::ComplexAck(ack.encode_vec()?))
+                        }
+                        _ => {
+                            let error = BACnetError::new(ErrorClass::Services, ErrorCode::Other);
+                            let error = PrivateTransferError::new(&transfer, error);
+                            Ok(Response::ServiceError(error.encode_vec()?))
+                        }
+                    }
+                },
+            );
+            let client = client(peer);
+
+            let echo = PrivateTransfer::new(25, 1).service_parameters(vec![0x21, 0x05]);
+            let ack = client.private_transfer(12, &echo).await.unwrap();
+            assert_eq!(ack.result().unwrap(), Some(BACnetValue::Unsigned(5)));
+            let result = client
+                .private_transfer(12, &PrivateTransfer::new(25, 2))
+                .await;
+            assert!(matches!(
+                result,
+                Err(ClientError::PrivateTransfer(e)) if e.service_number == 2 && e.error.error_code == ErrorCode::Other
+            ));
+        });
+    }
+
+    #[test]
+    fn test_virtual_terminal() {
+        task::block_on(async {
+            let (link, peer) = link_pair();
+            let device = device(link);
+            device.on_confirmed(
+                ConfirmedServiceChoice::VtOpen,
+                |_: &Address, data: &[u8], _: &mut ObjectStore| {
+                    let request = VtOpenRequest::decode_slice(data)?;
+                    let ack = VtOpenAck {
+                        remote_vt_session_identifier: request.local_vt_session_identifier + 1,
+                    };
+                    Ok(Response::ComplexAck(ack.encode_vec()?))
+                },
+            );
+            // Accepts up to 4 octets at a time
+            device.on_confirmed(
+                ConfirmedServiceChoice::VtData,
+                |_: &Address, data: &[u8], _: &mut ObjectStore| {
+                    let request = VtDataRequest::decode_slice(data)?;
+                    let ack = match request.vt_new_data.len() {
+                        0..=4 => VtDataAck {
+                            all_new_data_accepted: true,
+                            accepted_octet_count: None,
+                        },
+                        _ => VtDataAck {
+                            all_new_data_accepted: false,
+                            accepted_octet_count: Some(4),
+                        },
+                    };
+                    Ok(Response::ComplexAck(ack.encode_vec()?))
+                },
+            );
+            device.on_confirmed(
+                ConfirmedServiceChoice::VtClose,
+                |_: &Address, data: &[u8], _: &mut ObjectStore| {
+                    let request = VtCloseRequest::decode_slice(data)?;
+                    let unknown: Vec<u8> = request
+                        .list_of_remote_vt_session_identifiers
+                        .into_iter()
+                        .filter(|&id| id != 6)
+                        .collect();
+                    match unknown.is_empty() {
+                        true => Ok(Response::SimpleAck),
+                        false => {
+                            let error = VtCloseError {
+                                error: BACnetError::new(
+                                    ErrorClass::VT,
+                                    ErrorCode::UnknownVTSession,
+                                ),
+                                list_of_vt_session_identifiers: Some(unknown),
+                            };
+                            Ok(Response::ServiceError(error.encode_vec()?))
+                        }
+                    }
+                },
+            );
+            let client = client(peer);
+
+            let request = VtOpenRequest::new(VtClass::DefaultTerminal, 5);
+            let session = client.vt_open(12, &request).await.unwrap();
+            assert_eq!(session, 6);
+            let ack = client
+                .vt_data(12, &VtDataRequest::new(session, b"ls\r".to_vec()))
+                .await
+                .unwrap();
+            assert!(ack.all_new_data_accepted);
+            let ack = client
+                .vt_data(12, &VtDataRequest::new(session, b"help\r".to_vec()))
+                .await
+                .unwrap();
+            assert_eq!(ack.accepted_octet_count, Some(4));
+            client
+                .vt_close(12, &VtCloseRequest::new(vec![session]))
+                .await
+                .unwrap();
+            let result = client.vt_close(12, &VtCloseRequest::new(vec![6, 9])).await;
+            assert!(matches!(
+                result,
+                Err(ClientError::VtClose(e)) if e.list_of_vt_session_identifiers == Some(vec![9])
+            ));
+        });
+    }
+
+    #[test]
+    fn test_handlers() {
+        task::block_on(async {
+            let (link, peer) = link_pair();
+            let device = device(link);
+            device.on_confirmed(
+                ConfirmedServiceChoice::ConfirmedPrivateTransfer,
+                |_: &Address, data: &[u8], _: &mut ObjectStore| {
+                    Ok(Response::ComplexAck(data.to_vec()))
+                },
+            );
+            // Replaces the built-in ReadProperty
+            device.on_confirmed(
+                ConfirmedServiceChoice::ReadProperty,
+                |_: &Address, _: &[u8], objects: &mut ObjectStore| match objects.is_empty() {
+                    true => Ok(Response::SimpleAck),
+                    false => Ok(Response::Error(BACnetError::object(ErrorCode::Other))),
+                },
+            );
+            let (sender, receiver) = async_std::channel::unbounded();
+            device.on_unconfirmed(
+                UnconfirmedServiceChoice::WhoIs,
+                move |source: &Address, _: &[u8], _: &mut ObjectStore| {
+                    let _ = sender.try_send(source.clone());
+                    Ok(())
+                },
+            );
+
+            let ack = request(&peer, "000501120901").await;
+            assert_eq!(ack.pdu_type(), BACnetPDU::ComplexACK);
+            assert_eq!(ack.user_data(), [0x09, 0x01]);
+            let error = request(&peer, "0005020c0c0d8000011955").await;
+            assert_eq!(error.pdu_type(), BACnetPDU::Error);
+            assert_eq!(error.user_data(), [0x91, 0x01, 0x91, 0x00]);
+
+            // No I-Am is sent for the Who-Is, the response to the following
+            // request is received first
+            reply(&peer, APDU::unconfirmed_request(8, vec![])).await;
+            assert_eq!(receiver.recv().await.unwrap(), Address::local(vec![2]));
+            let ack = request(&peer, "000503120902").await;
+            assert_eq!(ack.invoke_id().unwrap(), 3);
+        });
+    }
+
+    /// The monitored value of the next COV notification
+    async fn cov_value<S>(notifications: &mut S) -> BACnetValue
+    where
+        S: futures_lite::Stream<Item = (Address, crate::client::Notification)> + Unpin,
+    {
+        match futures_lite::StreamExt::next(notifications)
+            .await
+            .unwrap()
+            .1
+        {
+            crate::client::Notification::Cov { notification, .. } => {
+                assert_eq!(notification.monitored_object_identifier, lamp());
+                assert_eq!(notification.values.len(), 2);
+                notification.values[0].value.clone()
+            }
+            n => panic!("Unexpected notification {:?}", n),
+        }
+    }
+
+    #[test]
+    fn test_subscribe_cov() {
+        task::block_on(async {
+            let (link, peer) = link_pair();
+            let device = device(link);
+            let client = client(peer);
+            let mut notifications = Box::pin(client.notifications());
+            client
+                .subscribe_cov(12, 1, lamp(), true, Some(60))
+                .await
+                .unwrap();
+            assert_eq!(cov_value(&mut notifications).await, BACnetValue::Real(0.0));
+            let subscriptions = device.cov_subscriptions();
+            assert_eq!(subscriptions.len(), 1);
+            assert_eq!(subscriptions[0].subscriber, Address::local(vec![2]));
+            assert!(subscriptions[0].issue_confirmed_notifications);
+
+            // Changes by requests and by the application are notified
+            client
+                .write(
+                    12,
+                    lamp(),
+                    PropertyIdentifier::PresentValue,
+                    BACnetValue::Real(50.0),
+                    Some(8),
+                )
+                .await
+                .unwrap();
+            assert_eq!(cov_value(&mut notifications).await, BACnetValue::Real(50.0));
+            device
+                .objects()
+                .get_mut(lamp())
+                .unwrap()
+                .write_property(
+                    PropertyIdentifier::PresentValue,
+                    None,
+                    BACnetValue::Null,
+                    Some(8),
+                    at(0),
+                )
+                .unwrap();
+            assert_eq!(cov_value(&mut notifications).await, BACnetValue::Real(0.0));
+
+            let cancellation = SubscribeCov {
+                subscriber_process_identifier: 1,
+                monitored_object_identifier: lamp(),
+                issue_confirmed_notifications: None,
+                lifetime: None,
+            };
+            let address = Address::local(vec![1]);
+            let service = ConfirmedServiceChoice::SubscribeCov;
+            let data = cancellation.encode_vec().unwrap();
+            client
+                .confirmed_request(&address, service, data)
+                .await
+                .unwrap();
+            assert!(device.cov_subscriptions().is_empty());
+
+            // The Device object has no Present_Value
+            let result = client
+                .subscribe_cov(12, 1, device.info().object_identifier(), false, None)
+                .await;
+            assert!(matches!(
+                result,
+                Err(ClientError::Error(e))
+                    if e == BACnetError::object(ErrorCode::OptionalFunctionalityNotSupported)
+            ));
+        });
+    }
+
+    /// The next event notification
+    async fn event<S>(notifications: &mut S) -> EventNotification
+    where
+        S: futures_lite::Stream<Item = (Address, crate::client::Notification)> + Unpin,
+    {
+        match futures_lite::StreamExt::next(notifications)
+            .await
+            .unwrap()
+            .1
+        {
+            crate::client::Notification::Event {
+                notification,
+                confirmed: true,
+            } => notification,
+            n => panic!("Unexpected notification {:?}", n),
+        }
+    }
+
+    #[test]
+    fn test_report_event() {
+        task::block_on(async {
+            let (link, peer) = link_pair();
+            let device = device(link);
+            let mut class = crate::objects::NotificationClass::new(3, "Alarms");
+            class.ack_required = [true, false, false];
+            let recipient = Recipient::Device(ObjectIdentifier::new(ObjectType::Device, 99));
+            let mut destination = crate::objects::Destination::new(recipient, 5);
+            destination.issue_confirmed_notifications = true;
+            class.recipient_list = vec![destination];
+            device.objects().insert(class);
+            let report = |from_state, to_state| EventReport {
+                event_object_identifier: lamp(),
+                notification_class: 3,
+                event_type: 0,
+                message_text: None,
+                notify_type: NotifyType::Alarm,
+                from_state,
+                to_state,
+                event_values: None,
+            };
+
+            // The recipient is looked for before it can be notified
+            device
+                .report_event(report(EventState::Normal, EventState::HighLimit))
+                .await
+                .unwrap();
+            let who_is = apdu(peer.recv().await.unwrap().1);
+            assert_eq!(
+                who_is.service_choice(),
+                Some(UnconfirmedServiceChoice::WhoIs as u8)
+            );
+            assert_eq!(who_is.user_data(), hex::decode("09631963").unwrap());
+            reply(&peer, crate::client::tests::i_am(99)).await;
+            request(&peer, "0005010c0c0200000c194d").await;
+
+            let client = client(peer);
+            let mut notifications = Box::pin(client.notifications());
+            device
+                .report_event(report(EventState::HighLimit, EventState::Normal))
+                .await
+                .unwrap();
+            let notification = event(&mut notifications).await;
+            assert_eq!(notification.process_identifier, 5);
+            assert_eq!(notification.to_state, EventState::Normal);
+            assert_eq!(notification.ack_required, Some(false));
+
+            // Listed until the high limit is acknowledged
+            let address = Address::local(vec![1]);
+            let service = ConfirmedServiceChoice::GetEventInformation;
+            let ack = client
+                .confirmed_request(&address, service, vec![])
+                .await
+                .unwrap();
+            assert!(ack.starts_with(&hex::decode("0e0c0d8000011900").unwrap()));
+            assert!(ack.ends_with(&hex::decode("0f1900").unwrap()));
+            let summaries = device.event_summaries();
+            assert_eq!(summaries[0].acked_transitions, [false, true, true]);
+
+            let acknowledgement = AcknowledgeAlarm {
+                acknowledging_process_identifier: 1,
+                event_object_identifier: lamp(),
+                event_state_acknowledged: EventState::HighLimit,
+                time_stamp: summaries[0].event_time_stamps[0],
+                acknowledgment_source: "Operator".into(),
+                time_of_acknowledgment: TimeStamp::SequenceNumber(1),
+            };
+            let service = ConfirmedServiceChoice::AcknowledgeAlarm;
+            let data = acknowledgement.encode_vec().unwrap();
+            client
+                .confirmed_request(&address, service, data.clone())
+                .await
+                .unwrap();
+            let notification = event(&mut notifications).await;
+            assert_eq!(notification.notify_type, NotifyType::AckNotification);
+            assert!(device.event_summaries().is_empty());
+            let service = ConfirmedServiceChoice::GetEventInformation;
+            let ack = client
+                .confirmed_request(&address, service, vec![])
+                .await
+                .unwrap();
+            assert_eq!(ack, hex::decode("0e0f1900").unwrap());
+        });
+    }
+
+    #[test]
+    fn test_get_event_information() {
+        task::block_on(async {
+            let (link, peer) = link_pair();
+            let device = device(link);
+            device
+                .objects()
+                .insert(crate::objects::NotificationClass::new(3, "Alarms"));
+            for instance in 1..=5 {
+                let report = EventReport {
+                    event_object_identifier: ObjectIdentifier::new(
+                        ObjectType::AnalogInput,
+                        instance,
+                    ),
+                    notification_class: 3,
+                    event_type: 5,
+                    message_text: None,
+                    notify_type: NotifyType::Alarm,
+                    from_state: EventState::Normal,
+                    to_state: EventState::HighLimit,
+                    event_values: None,
+                };
+                device.report_event(report).await.unwrap();
+            }
+
+            // Only three summaries fit into an APDU of 206 octets
+            let response = request(&peer, "0002011d").await;
+            let ack = GetEventInformationAck::decode_slice(response.user_data()).unwrap();
+            assert_eq!(ack.list_of_event_summaries.len(), 3);
+            assert!(ack.more_events);
+            assert!(response.len() <= 206);
+            let response = request(&peer, "0002021d0c00000003").await;
+            let ack = GetEventInformationAck::decode_slice(response.user_data()).unwrap();
+            assert_eq!(
+                ack.list_of_event_summaries[0].object_identifier,
+                ObjectIdentifier::new(ObjectType::AnalogInput, 4)
+            );
+            assert!(!ack.more_events);
+
+            let client = client(peer);
+            let summaries = client.get_event_information(12).await.unwrap();
+            assert_eq!(summaries, device.event_summaries());
+            assert_eq!(summaries.len(), 5);
+        });
+    }
+
+    /// Send a DeviceCommunicationControl request from the client
+    async fn dcc(
+        client: &BacnetClient<MockLink>,
+        enable_disable: EnableDisable,
+        password: &str,
+    ) -> Result<bytes::Bytes, ClientError> {
+        let request = DeviceCommunicationControl {
+            time_duration: Some(1),
+            enable_disable,
+            password: Some(password.into()),
+        };
+        let service = ConfirmedServiceChoice::DeviceCommunicationControl;
+        let data = request.encode_vec().unwrap();
+        client
+            .confirmed_request(&Address::local(vec![1]), service, data)
+            .await
+    }
+
+    #[test]
+    fn test_device_communication_control() {
+        task::block_on(async {
+            let (link, peer) = link_pair();
+            let info = DeviceInfo {
+                password: Some("secret".into()),
+                ..DeviceInfo::new(12, "Controller", 15)
+            };
+            let device = BacnetDevice::new(link, info);
+            device.objects().insert(LightingOutput::new(1, "Lamp"));
+            let mut client = client(peer);
+            client.set_apdu_timeout(Duration::from_millis(100));
+
+            let result = dcc(&client, EnableDisable::Disable, "wrong").await;
+            assert!(matches!(
+                result,
+                Err(ClientError::Error(e))
+                    if e == BACnetError::new(ErrorClass::Security, ErrorCode::PasswordFailure)
+            ));
+            dcc(&client, EnableDisable::Disable, "secret")
+                .await
+                .unwrap();
+            assert_eq!(device.communication(), EnableDisable::Disable);
+            let result = client
+                .read(12, lamp(), PropertyIdentifier::ObjectName)
+                .await;
+            assert!(matches!(result, Err(ClientError::Timeout)));
+            dcc(&client, EnableDisable::Enable, "secret").await.unwrap();
+            client
+                .read(12, lamp(), PropertyIdentifier::ObjectName)
+                .await
+                .unwrap();
+
+            // Who-Is is still answered while initiation is disabled
+            dcc(&client, EnableDisable::DisableInitiation, "secret")
+                .await
+                .unwrap();
+            assert!(device.announce().await.is_err());
+            let timeout = Duration::from_millis(100);
+            let devices = client.who_is(None, timeout).await.unwrap();
+            assert_eq!(devices.len(), 1);
+            let found = client.find_object("Lamp", None, timeout).await.unwrap();
+            assert!(found.is_empty());
+
+            // Communication is enabled after the time duration
+            device.inner.communication.lock().unwrap().until = Some(Instant::now());
+            assert_eq!(device.communication(), EnableDisable::Enable);
+        });
+    }
+
+    #[test]
+    fn test_clock() {
+        task::block_on(async {
+            let (link, peer) = link_pair();
+            let info = DeviceInfo {
+                password: Some("secret".into()),
+                ..DeviceInfo::new(12, "Controller", 15)
+            };
+            let mut device = BacnetDevice::new(link, info);
+            let clock = MockClock::new(std::time::UNIX_EPOCH);
+            device.set_clock(clock.clone());
+            let client = client(peer);
+
+            dcc(&client, EnableDisable::Disable, "secret")
+                .await
+                .unwrap();
+            clock.advance(Duration::from_secs(59));
+            assert_eq!(device.communication(), EnableDisable::Disable);
+            clock.advance(Duration::from_secs(1));
+            assert_eq!(device.communication(), EnableDisable::Enable);
+
+            let time =
+                BACnetDateTime::new(BACnetDate::new(2021, 1, 25), BACnetTime::new(8, 0, 0, 0));
+            let data = TimeSynchronization::from(time).encode_vec().unwrap();
+            let service = UnconfirmedServiceChoice::UtcTimeSynchronization;
+            let address = Address::local(vec![1]);
+            client
+                .unconfirmed_request(&address, service, data)
+                .await
+                .unwrap();
+            for _ in 0..100 {
+                if clock.date_time() == time {
+                    break;
+                }
+                task::sleep(Duration::from_millis(10)).await;
+            }
+            assert_eq!(clock.date_time(), time);
+        });
+    }
+
+    #[test]
+    fn test_reinitialize_device() {
+        task::block_on(async {
+            let (link, peer) = link_pair();
+            let device = device(link);
+            let client = client(peer);
+            let client = &client;
+            let reinitialize = |state| async move {
+                let request = ReinitializeDeviceRequest::new(state);
+                client.reinitialize_device(12, &request).await
+            };
+
+            let result = reinitialize(ReinitializedState::Warmstart).await;
+            assert!(matches!(
+                result,
+                Err(ClientError::Error(e)) if e.error_code == ErrorCode::OptionalFunctionalityNotSupported
+            ));
+
+            let (sender, receiver) = async_std::channel::unbounded();
+            device.on_reinitialize(move |state| match state {
+                ReinitializedState::StartBackup => Err(BACnetError::new(
+                    ErrorClass::Device,
+                    ErrorCode::ConfigurationInProgress,
+                )),
+                state => {
+                    let _ = sender.try_send(state);
+                    Ok(())
+                }
+            });
+            dcc(client, EnableDisable::DisableInitiation, "")
+                .await
+                .unwrap();
+            reinitialize(ReinitializedState::Warmstart).await.unwrap();
+            assert_eq!(
+                receiver.recv().await.unwrap(),
+                ReinitializedState::Warmstart
+            );
+            assert_eq!(device.communication(), EnableDisable::Enable);
+            let result = reinitialize(ReinitializedState::StartBackup).await;
+            assert!(matches!(
+                result,
+                Err(ClientError::Error(e)) if e.error_code == ErrorCode::ConfigurationInProgress
+            ));
+        });
+    }
+
+    #[test]
+    fn test_limits() {
+        task::block_on(async {
+            let (link, peer) = link_pair();
+            let device = device(link);
+            device.set_limits(Limits {
+                requests_per_second: Some(2),
+                max_pending_transactions: Some(0),
+                broadcast_response_jitter: Duration::from_millis(50),
+                ..Limits::default()
+            });
+            let start = Instant::now();
+            let quiet = |peer| async_std::future::timeout(Duration::from_millis(100), peer);
+
+            // A single I-Am answers both Who-Is
+            reply(&peer, APDU::unconfirmed_request(8, vec![])).await;
+            reply(&peer, APDU::unconfirmed_request(8, vec![])).await;
+            let (_, npdu) = peer.recv().await.unwrap();
+            assert_eq!(
+                apdu(npdu).service_choice().unwrap(),
+                UnconfirmedServiceChoice::IAm as u8
+            );
+            assert!(quiet(peer.recv()).await.is_err());
+
+            // The third request within a second is ignored
+            let read = hex::decode("0005010c0c0200000c194d").unwrap();
+            reply(&peer, APDU::decode_slice(&read).unwrap()).await;
+            assert!(quiet(peer.recv()).await.is_err());
+            task::sleep(Duration::from_secs(1).saturating_sub(start.elapsed())).await;
+            let ack = request(&peer, "0005010c0c0200000c194d").await;
+            assert_eq!(ack.pdu_type(), BACnetPDU::ComplexACK);
+
+            let notification = CovNotification {
+                subscriber_process_identifier: 1,
+                initiating_device_identifier: device.info().object_identifier(),
+                monitored_object_identifier: lamp(),
+                time_remaining: 0,
+                values: vec![],
+            };
+            let address = Address::local(vec![2]);
+            let result = device
+                .send_cov_notification(&address, &notification, true)
+                .await;
+            assert!(
+                matches!(result, Err(ClientError::Io(e)) if e.kind() == std::io::ErrorKind::WouldBlock)
+            );
+        });
+    }
+
+    #[test]
+    fn test_broadcast_response_interval() {
+        task::block_on(async {
+            let (link, peer) = link_pair();
+            let device = device(link);
+            device.set_limits(Limits {
+                broadcast_response_interval: Duration::from_millis(200),
+                ..Limits::default()
+            });
+            let quiet = |peer| async_std::future::timeout(Duration::from_millis(100), peer);
+            let who_is = || APDU::unconfirmed_request(8, vec![]);
+
+            reply(&peer, who_is()).await;
+            let (_, npdu) = peer.recv().await.unwrap();
+            assert_eq!(
+                apdu(npdu).service_choice().unwrap(),
+                UnconfirmedServiceChoice::IAm as u8
+            );
+            // Repeated Who-Is within the interval are not answered
+            reply(&peer, who_is()).await;
+            assert!(quiet(peer.recv()).await.is_err());
+            task::sleep(Duration::from_millis(100)).await;
+            reply(&peer, who_is()).await;
+            let (_, npdu) = peer.recv().await.unwrap();
+            assert_eq!(
+                apdu(npdu).service_choice().unwrap(),
+                UnconfirmedServiceChoice::IAm as u8
+            );
+        });
+    }
+
+    #[test]
+    fn test_send_cov_notification() {
+        task::block_on(async {
+            let (link, peer) = link_pair();
+            let device = device(link);
+            let client = client(peer);
+            let mut notifications = Box::pin(client.notifications());
+
+            let notification = CovNotification {
+                subscriber_process_identifier: 1,
+                initiating_device_identifier: device.info().object_identifier(),
+                monitored_object_identifier: lamp(),
+                time_remaining: 0,
+                values: vec![PropertyValue::new(
+                    PropertyIdentifier::PresentValue,
+                    BACnetValue::Real(50.0),
+                )],
+            };
+            let address = Address::local(vec![2]);
+            device
+                .send_cov_notification(&address, &notification, true)
+                .await
+                .unwrap();
+            let (_, received) = futures_lite::StreamExt::next(&mut notifications)
+                .await
+                .unwrap();
+            assert_eq!(
+                received,
+                crate::client::Notification::Cov {
+                    notification,
+                    confirmed: true
+                }
+            );
+            assert_eq!(device.inner.station.transactions.len(), 0);
+        });
+    }
+}
@@ -0,0 +1,431 @@
+use crate::application::*;
+use crate::objects::Object;
+use crate::server::{EventReport, ObjectStore};
+
+use num_traits::FromPrimitive;
+use std::collections::BTreeMap;
+use std::convert::TryFrom;
+use std::time::{Duration, Instant};
+
+/// BACnetPropertyStates choice of binary-value (Clause 21)
+const BINARY_VALUE: u8 = 1;
+
+/// BACnetPropertyStates choice of unsigned-value (Clause 21)
+const UNSIGNED_VALUE: u8 = 11;
+
+/// Event algorithm of an object and the value it monitors
+#[derive(Clone, Debug, PartialEq)]
+enum Algorithm {
+    /// OUT_OF_RANGE (13.3.6) of analog objects
+    OutOfRange {
+        value: f32,
+        high_limit: f32,
+        low_limit: f32,
+        deadband: f32,
+        low_limit_enable: bool,
+        high_limit_enable: bool,
+    },
+    /// CHANGE_OF_STATE (13.3.2) of binary and multi-state objects, `choice`
+    /// is the BACnetPropertyStates choice of the value
+    ChangeOfState {
+        value: u32,
+        alarm_values: Vec<u32>,
+        choice: u8,
+    },
+}
+
+impl Algorithm {
+    /// The event state the algorithm moves to from `state`
+    fn target(&self, state: EventState) -> EventState {
+        match self {
+            Self::OutOfRange {
+                value,
+                high_limit,
+                low_limit,
+                deadband,
+                low_limit_enable,
+                high_limit_enable,
+            } => {
+                if *high_limit_enable && value > high_limit {
+                    EventState::HighLimit
+                } else if *low_limit_enable && value < low_limit {
+                    EventState::LowLimit
+                } else {
+                    // Back to normal only past the deadband
+                    match state {
+                        EventState::HighLimit
+                            if *high_limit_enable && *value >= high_limit - deadband =>
+                        {
+                            EventState::HighLimit
+                        }
+                        EventState::LowLimit
+                            if *low_limit_enable && *value <= low_limit + deadband =>
+                        {
+                            EventState::LowLimit
+                        }
+                        _ => EventState::Normal,
+                    }
+                }
+            }
+            Self::ChangeOfState {
+                value,
+                alarm_values,
+                ..
+            } => match alarm_values.contains(value) {
+                true => EventState::Offnormal,
+                false => EventState::Normal,
+            },
+        }
+    }
+
+    fn event_type(&self) -> EventType {
+        match self {
+            Self::OutOfRange { .. } => EventType::OutOfRange,
+            Self::ChangeOfState { .. } => EventType::ChangeOfState,
+        }
+    }
+
+    /// Event values of a transition from `from_state`
+    fn event_values(
+        &self,
+        from_state: EventState,
+        status_flags: [bool; 4],
+    ) -> NotificationParameters {
+        match self {
+            Self::OutOfRange {
+                value,
+                high_limit,
+                low_limit,
+                deadband,
+                ..
+            } => {
+                // The limit exceeded, or the one no longer exceeded
+                let exceeded_limit = match (self.target(from_state), from_state) {
+                    (EventState::LowLimit, _) | (EventState::Normal, EventState::LowLimit) => {
+                        *low_limit
+                    }
+                    _ => *high_limit,
+                };
+                NotificationParameters::OutOfRange {
+                    exceeding_value: *value,
+                    status_flags,
+                    deadband: *deadband,
+                    exceeded_limit,
+                }
+            }
+            Self::ChangeOfState { value, choice, .. } => NotificationParameters::ChangeOfState {
+                new_state: PropertyState {
+                    choice: *choice,
+                    value: *value,
+                },
+                status_flags,
+            },
+        }
+    }
+}
+
+/// Intrinsic reporting properties of an object (13.2.2)
+struct Reporting {
+    algorithm: Algorithm,
+    notification_class: u32,
+    notify_type: NotifyType,
+    time_delay: Duration,
+    time_delay_normal: Duration,
+    status_flags: [bool; 4],
+}
+
+fn enumerated(value: BACnetValue) -> Option<u32> {
+    match value {
+        BACnetValue::Enumerated(e) => Some(e),
+        _ => None,
+    }
+}
+
+/// The intrinsic reporting of an object, `None` if it doesn't have the
+/// properties of an algorithm or no Notification_Class
+fn reporting(object: &dyn Object) -> Option<Reporting> {
+    let read = |property| object.read_property(property, None).ok();
+    let real = |property| read(property).and_then(|v| f32::try_from(v).ok());
+    let unsigned = |property| read(property).and_then(|v| u32::try_from(v).ok());
+
+    let algorithm = match object.object_type() {
+        ObjectType::AnalogInput | ObjectType::AnalogOutput | ObjectType::AnalogValue => {
+            let limit_enable = match read(PropertyIdentifier::LimitEnable)? {
+                BACnetValue::BitString(bits) => bits,
+                _ => return None,
+            };
+            let enabled = |bit: usize| limit_enable.get(bit);
+            Algorithm::OutOfRange {
+                value: real(PropertyIdentifier::PresentValue)?,
+                high_limit: real(PropertyIdentifier::HighLimit)?,
+                low_limit: real(PropertyIdentifier::LowLimit)?,
+                deadband: real(PropertyIdentifier::Deadband)?,
+                low_limit_enable: enabled(0),
+                high_limit_enable: enabled(1),
+            }
+        }
+        ObjectType::BinaryInput | ObjectType::BinaryOutput | ObjectType::BinaryValue => {
+            Algorithm::ChangeOfState {
+                value: enumerated(read(PropertyIdentifier::PresentValue)?)?,
+                alarm_values: vec![enumerated(read(PropertyIdentifier::AlarmValue)?)?],
+                choice: BINARY_VALUE,
+            }
+        }
+        ObjectType::MultiStateInput | ObjectType::MultiStateValue => {
+            let alarm_values = match read(PropertyIdentifier::AlarmValues)? {
+                BACnetValue::Array(values) => values
+                    .into_iter()
+                    .map(u32::try_from)
+                    .collect::<Result<_, _>>()
+                    .ok()?,
+                value => vec![u32::try_from(value).ok()?],
+            };
+            Algorithm::ChangeOfState {
+                value: unsigned(PropertyIdentifier::PresentValue)?,
+                alarm_values,
+                choice: UNSIGNED_VALUE,
+            }
+        }
+        _ => return None,
+    };
+    let time_delay =
+        Duration::from_secs(unsigned(PropertyIdentifier::TimeDelay).unwrap_or(0) as u64);
+    let status_flags = read(PropertyIdentifier::StatusFlags)
+        .and_then(|value| StatusFlags::try_from(value).ok())
+        .unwrap_or_default()
+        .into();
+    Some(Reporting {
+        algorithm,
+        notification_class: unsigned(PropertyIdentifier::NotificationClass)?,
+        notify_type: read(PropertyIdentifier::NotifyType)
+            .and_then(enumerated)
+            .and_then(NotifyType::from_u32)
+            .unwrap_or(NotifyType::Alarm),
+        time_delay,
+        time_delay_normal: unsigned(PropertyIdentifier::TimeDelayNormal)
+            .map(|s| Duration::from_secs(s as u64))
+            .unwrap_or(time_delay),
+        status_flags,
+    })
+}
+
+/// Event state of an object and the state it is about to change to
+#[derive(Clone, Debug)]
+struct AlarmState {
+    event_state: EventState,
+    /// State the algorithm moved to and since when, reported once the time
+    /// delay passed
+    pending: Option<(EventState, Instant)>,
+}
+
+/// Intrinsic reporting (13.2) of the objects of a device
+///
+/// The analog objects with Limit_Enable, High_Limit, Low_Limit and
+/// Deadband use OUT_OF_RANGE, binary objects with an Alarm_Value and
+/// multi-state objects with Alarm_Values CHANGE_OF_STATE. A new event state
+/// is reported when the algorithm stayed in it for Time_Delay, or
+/// Time_Delay_Normal when returning to normal.
+#[derive(Default)]
+pub(crate) struct Alarms {
+    states: BTreeMap<ObjectIdentifier, AlarmState>,
+}
+
+impl Alarms {
+    /// Evaluate the event algorithms of the objects, returning the
+    /// transitions to report
+    pub(crate) fn evaluate(&mut self, objects: &ObjectStore, now: Instant) -> Vec<EventReport> {
+        let mut reports = Vec::new();
+        for object in objects.iter() {
+            let identifier = object.object_identifier();
+            let reporting = match reporting(object) {
+                Some(reporting) => reporting,
+                None => {
+                    self.states.remove(&identifier);
+                    continue;
+                }
+            };
+            let state = self.states.entry(identifier).or_insert(AlarmState {
+                event_state: EventState::Normal,
+                pending: None,
+            });
+            let target = reporting.algorithm.target(state.event_state);
+            if target == state.event_state {
+                state.pending = None;
+                continue;
+            }
+            let since = match state.pending {
+                Some((pending, since)) if pending == target => since,
+                _ => now,
+            };
+            let time_delay = match target {
+                EventState::Normal => reporting.time_delay_normal,
+                _ => reporting.time_delay,
+            };
+            if now.duration_since(since) < time_delay {
+                state.pending = Some((target, since));
+                continue;
+            }
+
+            let from_state = state.event_state;
+            state.event_state = target;
+            state.pending = None;
+            let algorithm = &reporting.algorithm;
+            reports.push(EventReport {
+                event_object_identifier: identifier,
+                notification_class: reporting.notification_class,
+                event_type: algorithm.event_type() as u32,
+                message_text: None,
+                notify_type: reporting.notify_type,
+                from_state,
+                to_state: target,
+                event_values: Some(algorithm.event_values(from_state, reporting.status_flags)),
+            });
+        }
+        // Forget objects that were removed
+        self.states
+            .retain(|identifier, _| objects.get(*identifier).is_some());
+        reports
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// An Analog Value with the properties of intrinsic reporting
+    struct Temperature {
+        value: f32,
+        limit_enable: Vec<bool>,
+    }
+
+    impl Object for Temperature {
+        fn object_identifier(&self) -> ObjectIdentifier {
+            ObjectIdentifier::new(ObjectType::AnalogValue, 1)
+        }
+
+        fn object_name(&self) -> &str {
+            "Temperature"
+        }
+
+        fn property_list(&self) -> Vec<PropertyIdentifier> {
+            use PropertyIdentifier::*;
+            vec![
+                PresentValue,
+                HighLimit,
+                LowLimit,
+                Deadband,
+                LimitEnable,
+                NotificationClass,
+                TimeDelay,
+            ]
+        }
+
+        fn read_property(
+            &self,
+            property: PropertyIdentifier,
+            array_index: Option<u32>,
+        ) -> Result<BACnetValue, BACnetError> {
+            Ok(match property {
+                PropertyIdentifier::PresentValue => BACnetValue::Real(self.value),
+                PropertyIdentifier::HighLimit => BACnetValue::Real(30.0),
+                PropertyIdentifier::LowLimit => BACnetValue::Real(10.0),
+                PropertyIdentifier::Deadband => BACnetValue::Real(2.0),
+                PropertyIdentifier::LimitEnable => {
+                    BACnetValue::BitString(self.limit_enable.clone().into())
+                }
+                PropertyIdentifier::NotificationClass => BACnetValue::Unsigned(3),
+                PropertyIdentifier::TimeDelay => BACnetValue::Unsigned(10),
+                _ => return self.read_common_property(property, array_index),
+            })
+        }
+    }
+
+    fn temperature() -> ObjectIdentifier {
+        ObjectIdentifier::new(ObjectType::AnalogValue, 1)
+    }
+
+    fn set(objects: &mut ObjectStore, value: f32) {
+        objects.insert(Temperature {
+            value,
+            limit_enable: vec![true, true],
+        });
+    }
+
+    #[test]
+    fn test_out_of_range() {
+        let mut objects = ObjectStore::new();
+        let mut alarms = Alarms::default();
+        let now = Instant::now();
+        let later = |s| now + Duration::from_secs(s);
+        set(&mut objects, 20.0);
+        assert!(alarms.evaluate(&objects, now).is_empty());
+
+        // Reported once above the limit for the time delay
+        set(&mut objects, 31.0);
+        assert!(alarms.evaluate(&objects, now).is_empty());
+        assert!(alarms.evaluate(&objects, later(9)).is_empty());
+        let reports = alarms.evaluate(&objects, later(10));
+        assert_eq!(reports.len(), 1);
+        assert_eq!(reports[0].to_state, EventState::HighLimit);
+        assert_eq!(reports[0].event_type, EventType::OutOfRange as u32);
+        assert_eq!(
+            reports[0].event_values,
+            Some(NotificationParameters::OutOfRange {
+                exceeding_value: 31.0,
+                status_flags: [false; 4],
+                deadband: 2.0,
+                exceeded_limit: 30.0,
+            })
+        );
+
+        // Within the deadband the object stays in high limit
+        set(&mut objects, 29.0);
+        assert!(alarms.evaluate(&objects, later(10)).is_empty());
+        assert!(alarms.evaluate(&objects, later(30)).is_empty());
+        set(&mut objects, 27.0);
+        assert!(alarms.evaluate(&objects, later(30)).is_empty());
+        // Going back above the limit restarts the time delay
+        set(&mut objects, 31.0);
+        assert!(alarms.evaluate(&objects, later(35)).is_empty());
+        set(&mut objects, 27.0);
+        assert!(alarms.evaluate(&objects, later(40)).is_empty());
+        let reports = alarms.evaluate(&objects, later(50));
+        assert_eq!(reports[0].from_state, EventState::HighLimit);
+        assert_eq!(reports[0].to_state, EventState::Normal);
+
+        // Disabled limits are not reported
+        objects.insert(Temperature {
+            value: 5.0,
+            limit_enable: vec![false, true],
+        });
+        assert!(alarms.evaluate(&objects, later(50)).is_empty());
+        assert!(alarms.evaluate(&objects, later(100)).is_empty());
+        set(&mut objects, 5.0);
+        alarms.evaluate(&objects, later(100));
+        let reports = alarms.evaluate(&objects, later(110));
+        assert_eq!(reports[0].to_state, EventState::LowLimit);
+        objects.remove(temperature());
+        assert!(alarms.evaluate(&objects, later(120)).is_empty());
+        assert!(alarms.states.is_empty());
+    }
+
+    #[test]
+    fn test_change_of_state() {
+        let algorithm = Algorithm::ChangeOfState {
+            value: 1,
+            alarm_values: vec![1],
+            choice: BINARY_VALUE,
+        };
+        assert_eq!(algorithm.target(EventState::Normal), EventState::Offnormal);
+        assert_eq!(
+            algorithm.event_values(EventState::Normal, [true, false, false, false]),
+            NotificationParameters::ChangeOfState {
+                new_state: PropertyState {
+                    choice: 1,
+                    value: 1
+                },
+                status_flags: [true, false, false, false],
+            }
+        );
+    }
+}
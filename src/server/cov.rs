@@ -0,0 +1,273 @@
+use crate::application::*;
+use crate::network::Address;
+use crate::objects::Object;
+use crate::server::ObjectStore;
+
+use std::time::{Duration, Instant};
+
+/// A COV subscription of a client (13.14, 13.15)
+#[derive(Clone, Debug, PartialEq)]
+pub struct CovSubscription {
+    pub subscriber: Address,
+    pub subscriber_process_identifier: u32,
+    pub monitored_object_identifier: ObjectIdentifier,
+    /// Property and array index of a SubscribeCOVProperty subscription,
+    /// other subscriptions monitor Present_Value
+    pub monitored_property: Option<(PropertyIdentifier, Option<u32>)>,
+    pub issue_confirmed_notifications: bool,
+    /// End of the subscription, `None` for indefinite subscriptions
+    pub expires: Option<Instant>,
+    /// Change of a real value that is notified, without one the
+    /// COV_Increment of the object or any change
+    pub cov_increment: Option<f32>,
+    /// Values sent with the last notification, `None` before the initial
+    /// notification
+    last_values: Option<Vec<PropertyValue>>,
+}
+
+impl CovSubscription {
+    pub fn new(
+        subscriber: Address,
+        request: &SubscribeCov,
+        monitored_property: Option<(PropertyIdentifier, Option<u32>)>,
+        cov_increment: Option<f32>,
+        now: Instant,
+    ) -> Self {
+        let expires = match request.lifetime {
+            None | Some(0) => None,
+            Some(lifetime) => Some(now + Duration::from_secs(lifetime as u64)),
+        };
+        Self {
+            subscriber,
+            subscriber_process_identifier: request.subscriber_process_identifier,
+            monitored_object_identifier: request.monitored_object_identifier,
+            monitored_property,
+            issue_confirmed_notifications: request.issue_confirmed_notifications.unwrap_or(false),
+            expires,
+            cov_increment,
+            last_values: None,
+        }
+    }
+
+    /// Whether both subscriptions are by the same subscriber for the same
+    /// object and property
+    fn matches(&self, other: &Self) -> bool {
+        self.subscriber == other.subscriber
+            && self.subscriber_process_identifier == other.subscriber_process_identifier
+            && self.monitored_object_identifier == other.monitored_object_identifier
+            && self.monitored_property == other.monitored_property
+    }
+
+    /// The values reported: the monitored property and the Status_Flags of
+    /// the object, if it has them
+    pub(crate) fn values(&self, object: &dyn Object) -> Result<Vec<PropertyValue>, BACnetError> {
+        let (property, array_index) = self
+            .monitored_property
+            .unwrap_or((PropertyIdentifier::PresentValue, None));
+        let value = object.read_property(property, array_index).map_err(|e| {
+            match (e.error_code, self.monitored_property) {
+                // Objects without a Present_Value do not support COV reporting
+                (ErrorCode::UnknownProperty, None) => {
+                    BACnetError::object(ErrorCode::OptionalFunctionalityNotSupported)
+                }
+                _ => e,
+            }
+        })?;
+        let mut values = vec![PropertyValue {
+            property_array_index: array_index,
+            ..PropertyValue::new(property, value)
+        }];
+        let status_flags = PropertyIdentifier::StatusFlags;
+        if property != status_flags && object.has_property(status_flags) {
+            if let Ok(flags) = object.read_property(status_flags, None) {
+                values.push(PropertyValue::new(status_flags, flags));
+            }
+        }
+        Ok(values)
+    }
+
+    /// The increment of real values that is notified
+    fn increment(&self, object: &dyn Object) -> Option<f32> {
+        match self.monitored_property {
+            Some((PropertyIdentifier::PresentValue, _)) | None => {
+                self.cov_increment.or_else(|| {
+                    match object.read_property(PropertyIdentifier::CovIncrement, None) {
+                        Ok(BACnetValue::Real(increment)) => Some(increment),
+                        _ => None,
+                    }
+                })
+            }
+            Some(_) => self.cov_increment,
+        }
+    }
+
+    fn time_remaining(&self, now: Instant) -> u32 {
+        match self.expires {
+            Some(expires) => expires.saturating_duration_since(now).as_secs() as u32,
+            None => 0,
+        }
+    }
+}
+
+/// Whether values changed enough to notify them (13.1.4), real values by at
+/// least the increment
+fn changed(old: &[PropertyValue], new: &[PropertyValue], increment: Option<f32>) -> bool {
+    old.len() != new.len()
+        || old
+            .iter()
+            .zip(new)
+            .any(|(old, new)| match (&old.value, &new.value, increment) {
+                (BACnetValue::Real(a), BACnetValue::Real(b), Some(increment)) => {
+                    (a - b).abs() >= increment
+                }
+                (a, b, _) => a != b,
+            })
+}
+
+/// A COV notification to send to a subscriber
+pub(crate) struct PendingNotification {
+    pub(crate) subscriber: Address,
+    pub(crate) confirmed: bool,
+    pub(crate) notification: CovNotification,
+}
+
+/// COV subscriptions of a device
+#[derive(Default)]
+pub(crate) struct CovSubscriptions {
+    subscriptions: Vec<CovSubscription>,
+}
+
+impl CovSubscriptions {
+    /// Add a subscription, replacing the one it renews
+    pub(crate) fn subscribe(&mut self, subscription: CovSubscription) {
+        self.cancel(&subscription);
+        self.subscriptions.push(subscription);
+    }
+
+    pub(crate) fn cancel(&mut self, subscription: &CovSubscription) {
+        self.subscriptions.retain(|s| !s.matches(subscription));
+    }
+
+    pub(crate) fn list(&self) -> Vec<CovSubscription> {
+        self.subscriptions.clone()
+    }
+
+    /// Remove expired subscriptions and subscriptions to objects that no
+    /// longer exist, and return the notifications of changed values
+    ///
+    /// New subscriptions get an initial notification.
+    pub(crate) fn changes(
+        &mut self,
+        objects: &ObjectStore,
+        device: ObjectIdentifier,
+        now: Instant,
+    ) -> Vec<PendingNotification> {
+        self.subscriptions.retain(|s| {
+            s.expires.is_none_or(|expires| expires > now)
+                && objects.get(s.monitored_object_identifier).is_some()
+        });
+
+        let mut notifications = Vec::new();
+        for subscription in &mut self.subscriptions {
+            let object = match objects.get(subscription.monitored_object_identifier) {
+                Some(object) => object,
+                None => continue,
+            };
+            let values = match subscription.values(object) {
+                Ok(values) => values,
+                Err(_) => continue,
+            };
+            let increment = subscription.increment(object);
+            if let Some(last) = &subscription.last_values {
+                if !changed(last, &values, increment) {
+                    continue;
+                }
+            }
+            subscription.last_values = Some(values.clone());
+            notifications.push(PendingNotification {
+                subscriber: subscription.subscriber.clone(),
+                confirmed: subscription.issue_confirmed_notifications,
+                notification: CovNotification {
+                    subscriber_process_identifier: subscription.subscriber_process_identifier,
+                    initiating_device_identifier: device,
+                    monitored_object_identifier: subscription.monitored_object_identifier,
+                    time_remaining: subscription.time_remaining(now),
+                    values,
+                },
+            });
+        }
+        notifications
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::objects::testing::at;
+    use crate::objects::LightingOutput;
+
+    fn lamp() -> ObjectIdentifier {
+        ObjectIdentifier::new(ObjectType::LightingOutput, 1)
+    }
+
+    fn subscription(lifetime: u32, cov_increment: Option<f32>, now: Instant) -> CovSubscription {
+        let request = SubscribeCov {
+            subscriber_process_identifier: 1,
+            monitored_object_identifier: lamp(),
+            issue_confirmed_notifications: Some(false),
+            lifetime: Some(lifetime),
+        };
+        CovSubscription::new(Address::local(vec![2]), &request, None, cov_increment, now)
+    }
+
+    fn write(objects: &mut ObjectStore, level: f32) {
+        objects
+            .get_mut(lamp())
+            .unwrap()
+            .write_property(
+                PropertyIdentifier::PresentValue,
+                None,
+                BACnetValue::Real(level),
+                Some(8),
+                at(0),
+            )
+            .unwrap();
+    }
+
+    #[test]
+    fn test_changes() {
+        let device = ObjectIdentifier::new(ObjectType::Device, 12);
+        let mut objects = ObjectStore::new();
+        objects.insert(LightingOutput::new(1, "Lamp"));
+        let now = Instant::now();
+        let mut subscriptions = CovSubscriptions::default();
+        subscriptions.subscribe(subscription(60, Some(5.0), now));
+        // Renewing replaces the subscription
+        subscriptions.subscribe(subscription(120, Some(5.0), now));
+        assert_eq!(subscriptions.list().len(), 1);
+
+        let initial = subscriptions.changes(&objects, device, now);
+        assert_eq!(initial.len(), 1);
+        let notification = &initial[0].notification;
+        assert_eq!(notification.time_remaining, 120);
+        assert_eq!(notification.values.len(), 2);
+        assert_eq!(notification.values[0].value, BACnetValue::Real(0.0));
+        assert!(subscriptions.changes(&objects, device, now).is_empty());
+
+        // Changes below the increment are not notified
+        write(&mut objects, 4.0);
+        assert!(subscriptions.changes(&objects, device, now).is_empty());
+        write(&mut objects, 5.0);
+        let changes = subscriptions.changes(&objects, device, now + Duration::from_secs(20));
+        assert_eq!(changes[0].notification.time_remaining, 100);
+        assert_eq!(
+            changes[0].notification.values[0].value,
+            BACnetValue::Real(5.0)
+        );
+
+        write(&mut objects, 50.0);
+        let expired = now + Duration::from_secs(120);
+        assert!(subscriptions.changes(&objects, device, expired).is_empty());
+        assert!(subscriptions.list().is_empty());
+    }
+}
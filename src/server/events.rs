@@ -0,0 +1,371 @@
+use crate::application::*;
+use crate::objects::{Destination, Recipient, Transition};
+use crate::server::ObjectStore;
+
+use std::collections::BTreeMap;
+use std::convert::TryFrom;
+use std::time::{Duration, Instant};
+
+/// Time between attempts to deliver an event notification
+const RETRY_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Attempts to deliver an event notification before it is dropped
+const MAX_ATTEMPTS: u32 = 6;
+
+/// A transition of the event state of an object, distributed to the
+/// recipients of its notification class by
+/// [`BacnetDevice::report_event`](super::BacnetDevice::report_event)
+#[derive(Clone, Debug, PartialEq)]
+pub struct EventReport {
+    pub event_object_identifier: ObjectIdentifier,
+    /// Instance of the Notification Class object
+    pub notification_class: u32,
+    /// BACnetEventType (Clause 21), see [`EventType`]
+    pub event_type: u32,
+    pub message_text: Option<String>,
+    /// Alarm or event
+    pub notify_type: NotifyType,
+    pub from_state: EventState,
+    pub to_state: EventState,
+    pub event_values: Option<NotificationParameters>,
+}
+
+/// An event notification to deliver to a recipient
+pub(crate) struct PendingEvent {
+    pub(crate) recipient: Recipient,
+    pub(crate) confirmed: bool,
+    pub(crate) notification: EventNotification,
+    attempts: u32,
+    due: Instant,
+}
+
+/// Event state of an object and what is needed to notify its
+/// acknowledgement
+struct EventStatus {
+    summary: EventSummary,
+    notification_class: u32,
+    event_type: u32,
+}
+
+/// Priority, Ack_Required and Recipient_List of a notification class
+struct Class {
+    priority: [u8; 3],
+    ack_required: [bool; 3],
+    recipients: Vec<Destination>,
+}
+
+fn notification_class(objects: &ObjectStore, instance: u32) -> Result<Class, BACnetError> {
+    let class = ObjectIdentifier::new(ObjectType::NotificationClass, instance);
+    let class = objects
+        .get(class)
+        .ok_or_else(|| BACnetError::object(ErrorCode::UnknownObject))?;
+    let priority = match class.read_property(PropertyIdentifier::Priority, None)? {
+        BACnetValue::Array(p) if p.len() == 3 => {
+            let p = p
+                .into_iter()
+                .map(u32::try_from)
+                .collect::<Result<Vec<_>, _>>()?;
+            [p[0] as u8, p[1] as u8, p[2] as u8]
+        }
+        _ => return Err(BACnetError::property(ErrorCode::InvalidDataType)),
+    };
+    let ack_required = transition_bits(class.read_property(PropertyIdentifier::AckRequired, None)?)
+        .ok_or_else(|| BACnetError::property(ErrorCode::InvalidDataType))?;
+    let recipients = Vec::<Destination>::try_from(
+        class.read_property(PropertyIdentifier::RecipientList, None)?,
+    )?;
+    Ok(Class {
+        priority,
+        ack_required,
+        recipients,
+    })
+}
+
+/// A BACnetEventTransitionBits value
+fn transition_bits(value: BACnetValue) -> Option<[bool; 3]> {
+    match value {
+        BACnetValue::BitString(bits) if bits.bit_len() >= 3 => Some([bits[0], bits[1], bits[2]]),
+        _ => None,
+    }
+}
+
+/// Event states of the objects of a device and the notifications waiting
+/// to be delivered
+#[derive(Default)]
+pub(crate) struct Events {
+    statuses: BTreeMap<ObjectIdentifier, EventStatus>,
+    pending: Vec<PendingEvent>,
+}
+
+impl Events {
+    /// Record a transition and queue its notification for the recipients of
+    /// the notification class
+    ///
+    /// Transitions disabled by the Event_Enable of the object are recorded
+    /// but not notified.
+    pub(crate) fn report(
+        &mut self,
+        objects: &ObjectStore,
+        device: ObjectIdentifier,
+        report: EventReport,
+        time: BACnetDateTime,
+        now: Instant,
+    ) -> Result<(), BACnetError> {
+        let class = notification_class(objects, report.notification_class)?;
+        let object = report.event_object_identifier;
+        let event_enable = objects
+            .get(object)
+            .and_then(|o| o.read_property(PropertyIdentifier::EventEnable, None).ok())
+            .and_then(transition_bits)
+            .unwrap_or([true; 3]);
+        let transition = Transition::from(report.to_state);
+        let index = transition as usize;
+        let ack_required = class.ack_required[index] && event_enable[index];
+        let time_stamp = TimeStamp::DateTime(time);
+
+        let status = self.statuses.entry(object).or_insert_with(|| EventStatus {
+            summary: EventSummary::new(object),
+            notification_class: report.notification_class,
+            event_type: report.event_type,
+        });
+        status.notification_class = report.notification_class;
+        status.event_type = report.event_type;
+        let summary = &mut status.summary;
+        summary.event_state = report.to_state;
+        summary.acked_transitions[index] = !ack_required;
+        summary.event_time_stamps[index] = time_stamp;
+        summary.notify_type = report.notify_type;
+        summary.event_enable = event_enable;
+        summary.event_priorities = class.priority;
+        if !event_enable[index] {
+            return Ok(());
+        }
+
+        let notification = EventNotification {
+            process_identifier: 0,
+            initiating_device_identifier: device,
+            event_object_identifier: object,
+            time_stamp,
+            notification_class: report.notification_class,
+            priority: class.priority[index],
+            event_type: report.event_type,
+            message_text: report.message_text,
+            notify_type: report.notify_type,
+            ack_required: Some(ack_required),
+            from_state: Some(report.from_state),
+            to_state: report.to_state,
+            event_values: report.event_values,
+        };
+        self.queue(&class.recipients, transition, &time, notification, now);
+        Ok(())
+    }
+
+    /// Acknowledge a transition (13.5) and queue the acknowledgement
+    /// notification
+    pub(crate) fn acknowledge(
+        &mut self,
+        objects: &ObjectStore,
+        device: ObjectIdentifier,
+        request: &AcknowledgeAlarm,
+        time: BACnetDateTime,
+        now: Instant,
+    ) -> Result<(), BACnetError> {
+        let object = request.event_object_identifier;
+        let status = self
+            .statuses
+            .get_mut(&object)
+            .ok_or_else(|| BACnetError::object(ErrorCode::UnknownObject))?;
+        let transition = Transition::from(request.event_state_acknowledged);
+        let index = transition as usize;
+        if status.summary.event_time_stamps[index] != request.time_stamp {
+            return Err(BACnetError::new(
+                ErrorClass::Services,
+                ErrorCode::InvalidTimeStamp,
+            ));
+        }
+        status.summary.acked_transitions[index] = true;
+
+        let notification = EventNotification {
+            process_identifier: 0,
+            initiating_device_identifier: device,
+            event_object_identifier: object,
+            time_stamp: request.time_of_acknowledgment,
+            notification_class: status.notification_class,
+            priority: status.summary.event_priorities[index],
+            event_type: status.event_type,
+            message_text: None,
+            notify_type: NotifyType::AckNotification,
+            ack_required: None,
+            from_state: None,
+            to_state: request.event_state_acknowledged,
+            event_values: None,
+        };
+        // The acknowledgement stands even if there is no one to notify
+        if let Ok(class) = notification_class(objects, status.notification_class) {
+            self.queue(&class.recipients, transition, &time, notification, now);
+        }
+        Ok(())
+    }
+
+    fn queue(
+        &mut self,
+        recipients: &[Destination],
+        transition: Transition,
+        time: &BACnetDateTime,
+        notification: EventNotification,
+        now: Instant,
+    ) {
+        let pending = recipients
+            .iter()
+            .filter(|d| d.applies(transition, time))
+            .map(|d| PendingEvent {
+                recipient: d.recipient.clone(),
+                confirmed: d.issue_confirmed_notifications,
+                notification: EventNotification {
+                    process_identifier: d.process_identifier,
+                    ..notification.clone()
+                },
+                attempts: 0,
+                due: now,
+            });
+        self.pending.extend(pending);
+    }
+
+    /// Take the notifications due for delivery
+    pub(crate) fn due(&mut self, now: Instant) -> Vec<PendingEvent> {
+        let (due, later) = std::mem::take(&mut self.pending)
+            .into_iter()
+            .partition(|p| p.due <= now);
+        self.pending = later;
+        due
+    }
+
+    /// Queue a notification that could not be delivered for another
+    /// attempt, returns false if it is dropped
+    pub(crate) fn retry(&mut self, mut event: PendingEvent, now: Instant) -> bool {
+        event.attempts += 1;
+        if event.attempts >= MAX_ATTEMPTS {
+            return false;
+        }
+        event.due = now + RETRY_INTERVAL;
+        self.pending.push(event);
+        true
+    }
+
+    /// Summaries of the objects that are not normal or have unacknowledged
+    /// transitions, following `after` in the order of their identifiers
+    pub(crate) fn summaries(&self, after: Option<ObjectIdentifier>) -> Vec<EventSummary> {
+        self.statuses
+            .values()
+            .map(|s| &s.summary)
+            .filter(|s| after.is_none_or(|after| s.object_identifier > after))
+            .filter(|s| s.is_active())
+            .cloned()
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::network::Address;
+    use crate::objects::NotificationClass;
+
+    fn sensor() -> ObjectIdentifier {
+        ObjectIdentifier::new(ObjectType::AnalogInput, 1)
+    }
+
+    fn report(from_state: EventState, to_state: EventState) -> EventReport {
+        EventReport {
+            event_object_identifier: sensor(),
+            notification_class: 3,
+            event_type: 5,
+            message_text: None,
+            notify_type: NotifyType::Alarm,
+            from_state,
+            to_state,
+            event_values: None,
+        }
+    }
+
+    #[test]
+    fn test_events() {
+        let device = ObjectIdentifier::new(ObjectType::Device, 12);
+        let mut class = NotificationClass::new(3, "Alarms");
+        class.priority = [10, 20, 30];
+        class.ack_required = [true, false, false];
+        let mut confirmed = Destination::new(Recipient::Address(Address::local(vec![2])), 7);
+        confirmed.issue_confirmed_notifications = true;
+        let mut offnormal = Destination::new(Recipient::Address(Address::local(vec![3])), 8);
+        offnormal.transitions = [true, false, false];
+        class.recipient_list = vec![confirmed, offnormal];
+        let mut objects = ObjectStore::new();
+        objects.insert(class);
+
+        let mut events = Events::default();
+        let (time, now) = (BACnetDateTime::now(), Instant::now());
+        let high = report(EventState::Normal, EventState::HighLimit);
+        events.report(&objects, device, high, time, now).unwrap();
+        let due = events.due(now);
+        assert_eq!(due.len(), 2);
+        assert!(due[0].confirmed);
+        assert_eq!(due[0].notification.process_identifier, 7);
+        assert_eq!(due[0].notification.priority, 10);
+        assert_eq!(due[0].notification.ack_required, Some(true));
+        assert_eq!(due[1].notification.process_identifier, 8);
+
+        let summaries = events.summaries(None);
+        assert_eq!(summaries.len(), 1);
+        assert_eq!(summaries[0].acked_transitions, [false, true, true]);
+        assert!(events.summaries(Some(sensor())).is_empty());
+
+        // Undelivered notifications are retried later, a limited number of
+        // times
+        let mut event = due.into_iter().next().unwrap();
+        for attempt in 1..MAX_ATTEMPTS {
+            let retry = now + RETRY_INTERVAL * attempt;
+            assert!(events.retry(event, retry - RETRY_INTERVAL));
+            assert!(events.due(retry - Duration::from_secs(1)).is_empty());
+            event = events.due(retry).pop().unwrap();
+        }
+        assert!(!events.retry(event, now));
+
+        // Back to normal the object is listed until acknowledged
+        let normal = report(EventState::HighLimit, EventState::Normal);
+        events.report(&objects, device, normal, time, now).unwrap();
+        assert_eq!(events.due(now).len(), 1);
+        let mut ack = AcknowledgeAlarm {
+            acknowledging_process_identifier: 1,
+            event_object_identifier: sensor(),
+            event_state_acknowledged: EventState::HighLimit,
+            time_stamp: TimeStamp::SequenceNumber(1),
+            acknowledgment_source: "Operator".into(),
+            time_of_acknowledgment: TimeStamp::DateTime(time),
+        };
+        assert_eq!(
+            events.acknowledge(&objects, device, &ack, time, now),
+            Err(BACnetError::new(
+                ErrorClass::Services,
+                ErrorCode::InvalidTimeStamp
+            ))
+        );
+        ack.time_stamp = TimeStamp::DateTime(time);
+        events
+            .acknowledge(&objects, device, &ack, time, now)
+            .unwrap();
+        assert!(events.summaries(None).is_empty());
+        let due = events.due(now);
+        assert_eq!(due.len(), 2);
+        assert_eq!(due[0].notification.notify_type, NotifyType::AckNotification);
+
+        // Without the notification class there is no one to notify
+        let missing = EventReport {
+            notification_class: 4,
+            ..report(EventState::Normal, EventState::Fault)
+        };
+        assert_eq!(
+            events.report(&objects, device, missing, time, now),
+            Err(BACnetError::object(ErrorCode::UnknownObject))
+        );
+    }
+}
@@ -0,0 +1,410 @@
+//! Virtual devices behind a routed network
+//!
+//! A [`Gateway`] routes between a data link and a virtual network it hosts
+//! devices on, the usual pattern for protocol gateways: each virtual device
+//! is a [`BacnetDevice`] with its own instance and object store, reached
+//! from the data link through the gateway like any device behind a router.
+
+use crate::network::*;
+use crate::server::{BacnetDevice, DeviceInfo};
+use crate::transport::{BoxFuture, DataLink};
+
+use async_std::channel::{self, Receiver, Sender};
+use async_std::task::{self, JoinHandle};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use tracing::{trace, warn};
+
+/// MAC address of the gateway on the virtual network, instance numbers of
+/// virtual devices don't exceed 22 bits
+const ROUTER_MAC: [u8; 3] = [0xFF; 3];
+
+/// NPDU sent on the virtual network, with the MAC addresses of sender and
+/// receiver
+type Frame = (Vec<u8>, Vec<u8>, NPDU);
+
+/// NPDU received by a virtual device, with the MAC address of the sender
+type Incoming = (Vec<u8>, NPDU);
+
+/// The data link of a virtual device, connected to the [`Gateway`]
+pub struct VirtualLink {
+    mac: Vec<u8>,
+    outgoing: Sender<Frame>,
+    incoming: Receiver<Incoming>,
+}
+
+impl DataLink for VirtualLink {
+    fn send<'a>(&'a self, mac: &'a [u8], npdu: &'a NPDU) -> BoxFuture<'a, std::io::Result<()>> {
+        Box::pin(async move {
+            self.outgoing
+                .send((self.mac.clone(), mac.to_vec(), npdu.clone()))
+                .await
+                .map_err(|_| std::io::Error::new(std::io::ErrorKind::BrokenPipe, "Gateway closed"))
+        })
+    }
+
+    fn recv(&self) -> BoxFuture<'_, std::io::Result<(Vec<u8>, NPDU)>> {
+        Box::pin(async move {
+            self.incoming
+                .recv()
+                .await
+                .map_err(|_| std::io::Error::new(std::io::ErrorKind::BrokenPipe, "Gateway closed"))
+        })
+    }
+}
+
+struct Inner<D> {
+    link: D,
+    network: u16,
+    virtual_network: u16,
+    /// Virtual devices by MAC address
+    devices: Mutex<HashMap<Vec<u8>, Sender<Incoming>>>,
+    /// MAC address of the router to each network beyond the data link
+    routers: Mutex<HashMap<u16, Vec<u8>>>,
+    outgoing: Sender<Frame>,
+}
+
+impl<D: DataLink> Inner<D> {
+    /// Hand an NPDU to the virtual device with MAC address `to`, or to all
+    /// but the sender if it is empty
+    ///
+    /// Devices that were dropped are forgotten.
+    fn deliver(&self, to: &[u8], from: &[u8], npdu: &NPDU) {
+        self.devices.lock().unwrap().retain(|mac, device| {
+            match (to.is_empty() && mac.as_slice() != from) || mac.as_slice() == to {
+                true => device.try_send((from.to_vec(), npdu.clone())).is_ok(),
+                false => true,
+            }
+        });
+    }
+
+    /// The I-Am-Router-To-Network answering a Who-Is-Router-To-Network for
+    /// the virtual network
+    fn router_answer(&self, npdu: &NPDU) -> Option<NPDU> {
+        let message = match &npdu.content {
+            NPDUContent::Message(m) if m.message_type == NPDUMessage::WhoIsRouterToNetwork => m,
+            _ => return None,
+        };
+        match message.networks().ok()?.as_slice() {
+            [] => {}
+            [network] if *network == self.virtual_network => {}
+            _ => return None,
+        }
+        let answer = NetworkMessage::i_am_router_to_network(&[self.virtual_network]);
+        Some(NPDU::new(
+            NPDUContent::Message(answer),
+            None,
+            None,
+            NPDUPriority::Normal,
+        ))
+    }
+
+    /// Route an NPDU received on the data link to the virtual network
+    fn route_to_devices(&self, mac: Vec<u8>, mut npdu: NPDU) {
+        let destination = match npdu.destination.take() {
+            Some(d) if d.net == self.virtual_network || d.net == GLOBAL_BROADCAST => d,
+            _ => return,
+        };
+        let source = match npdu.source.take() {
+            Some(source) => {
+                self.routers.lock().unwrap().insert(source.net, mac.clone());
+                source
+            }
+            None => NPDUSource {
+                net: self.network,
+                adr: mac,
+            },
+        };
+        npdu.source = Some(source);
+        self.deliver(&destination.adr, &ROUTER_MAC, &npdu);
+    }
+
+    /// Route an NPDU sent by a virtual device, returning the MAC address
+    /// and NPDU to send on the data link
+    fn route_to_link(&self, from: Vec<u8>, to: Vec<u8>, mut npdu: NPDU) -> Option<(Vec<u8>, NPDU)> {
+        let mut destination = match npdu.destination.take() {
+            Some(d) if d.net != self.virtual_network => d,
+            Some(d) => {
+                self.deliver(&d.adr, &from, &npdu);
+                return None;
+            }
+            None => {
+                self.deliver(&to, &from, &npdu);
+                return None;
+            }
+        };
+        if destination.net == GLOBAL_BROADCAST {
+            self.deliver(&[], &from, &npdu);
+        }
+        npdu.source = Some(NPDUSource {
+            net: self.virtual_network,
+            adr: from,
+        });
+        if destination.net == self.network {
+            return Some((destination.adr, npdu));
+        }
+        // Routers on the data link forward to networks beyond it
+        let router = match destination.net {
+            GLOBAL_BROADCAST => None,
+            net => self.routers.lock().unwrap().get(&net).cloned(),
+        };
+        destination.hops = destination.hops.saturating_sub(1);
+        if destination.hops == 0 {
+            trace!("Hop count exhausted, dropping {:?}", npdu);
+            return None;
+        }
+        npdu.destination = Some(destination);
+        Some((router.unwrap_or_default(), npdu))
+    }
+}
+
+async fn run_link<D: DataLink + 'static>(inner: Arc<Inner<D>>) {
+    loop {
+        match inner.link.recv().await {
+            Ok((mac, npdu)) => match inner.router_answer(&npdu) {
+                Some(answer) => {
+                    if let Err(e) = inner.link.send(&[], &answer).await {
+                        warn!("Sending failed: {}", e);
+                    }
+                }
+                None => inner.route_to_devices(mac, npdu),
+            },
+            Err(e) => {
+                warn!("Receiving failed: {}", e);
+                return;
+            }
+        }
+    }
+}
+
+async fn run_devices<D: DataLink + 'static>(inner: Arc<Inner<D>>, receiver: Receiver<Frame>) {
+    while let Ok((from, to, npdu)) = receiver.recv().await {
+        if let Some((mac, npdu)) = inner.route_to_link(from, to, npdu) {
+            if let Err(e) = inner.link.send(&mac, &npdu).await {
+                warn!("Sending failed: {}", e);
+            }
+        }
+    }
+}
+
+/// A router between a data link and a virtual network of devices
+///
+/// ```no_run
+/// # use bacnet::objects::LightingOutput;
+/// # use bacnet::server::{DeviceInfo, Gateway};
+/// # use bacnet::transport::bacnetip::{BacnetIp, DEFAULT_PORT};
+/// # use std::net::{Ipv4Addr, SocketAddrV4};
+/// # async_std::task::block_on(async {
+/// let link = BacnetIp::bind(
+///     SocketAddrV4::new(Ipv4Addr::UNSPECIFIED, DEFAULT_PORT),
+///     SocketAddrV4::new(Ipv4Addr::BROADCAST, DEFAULT_PORT),
+/// )
+/// .await?;
+/// let gateway = Gateway::new(link, 1, 100);
+/// gateway.announce().await?;
+/// let device = gateway.add_device(DeviceInfo::new(1001, "Room 1", 999));
+/// device.objects().insert(LightingOutput::new(1, "Lamp"));
+/// device.announce().await?;
+/// async_std::future::pending::<()>().await;
+/// # Ok::<(), std::io::Error>(())
+/// # });
+/// ```
+pub struct Gateway<D: DataLink + 'static> {
+    inner: Arc<Inner<D>>,
+    tasks: Vec<JoinHandle<()>>,
+}
+
+impl<D: DataLink + 'static> Gateway<D> {
+    /// A gateway on a data link with network number `network`, hosting
+    /// devices on network `virtual_network`
+    pub fn new(link: D, network: u16, virtual_network: u16) -> Self {
+        let (outgoing, receiver) = channel::unbounded();
+        let inner = Arc::new(Inner {
+            link,
+            network,
+            virtual_network,
+            devices: Mutex::new(HashMap::new()),
+            routers: Mutex::new(HashMap::new()),
+            outgoing,
+        });
+        let tasks = vec![
+            task::spawn(run_link(inner.clone())),
+            task::spawn(run_devices(inner.clone(), receiver)),
+        ];
+        Self { inner, tasks }
+    }
+
+    pub fn network(&self) -> u16 {
+        self.inner.network
+    }
+
+    pub fn virtual_network(&self) -> u16 {
+        self.inner.virtual_network
+    }
+
+    /// Broadcast an I-Am-Router-To-Network for the virtual network, e.g. on
+    /// startup
+    pub async fn announce(&self) -> std::io::Result<()> {
+        let message = NetworkMessage::i_am_router_to_network(&[self.inner.virtual_network]);
+        let npdu = NPDU::new(
+            NPDUContent::Message(message),
+            None,
+            None,
+            NPDUPriority::Normal,
+        );
+        self.inner.link.send(&[], &npdu).await
+    }
+
+    /// Add a device to the virtual network, its MAC address is its instance
+    /// number
+    ///
+    /// The device leaves the network when it is dropped, or replaces an
+    /// earlier one with the same instance number.
+    pub fn add_device(&self, info: DeviceInfo) -> BacnetDevice<VirtualLink> {
+        let mac = info.instance.to_be_bytes()[1..].to_vec();
+        let (sender, incoming) = channel::unbounded();
+        self.inner
+            .devices
+            .lock()
+            .unwrap()
+            .insert(mac.clone(), sender);
+        let link = VirtualLink {
+            mac,
+            outgoing: self.inner.outgoing.clone(),
+            incoming,
+        };
+        BacnetDevice::with_ports(vec![(self.inner.virtual_network, link)], info)
+    }
+
+    /// The address of a virtual device on the routed network
+    pub fn address(&self, instance: u32) -> Address {
+        Address::remote(self.inner.virtual_network, &instance.to_be_bytes()[1..])
+    }
+
+    /// Instance numbers of the virtual devices
+    pub fn devices(&self) -> Vec<u32> {
+        let mut devices: Vec<_> = self
+            .inner
+            .devices
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|(_, device)| !device.is_closed())
+            .map(|(mac, _)| u32::from_be_bytes([0, mac[0], mac[1], mac[2]]))
+            .collect();
+        devices.sort_unstable();
+        devices
+    }
+}
+
+impl<D: DataLink + 'static> Drop for Gateway<D> {
+    fn drop(&mut self) {
+        for task in self.tasks.drain(..) {
+            task::spawn(task.cancel());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::application::*;
+    use crate::client::tests::link_pair;
+    use crate::client::BacnetClient;
+    use crate::objects::LightingOutput;
+
+    use std::time::Duration;
+
+    #[test]
+    fn test_gateway() {
+        task::block_on(async {
+            let (a, b) = link_pair();
+            let gateway = Gateway::new(a, 1, 100);
+            let first = gateway.add_device(DeviceInfo::new(1001, "Room 1", 15));
+            let second = gateway.add_device(DeviceInfo::new(1002, "Room 2", 15));
+            first.objects().insert(LightingOutput::new(1, "Lamp 1"));
+            second.objects().insert(LightingOutput::new(1, "Lamp 2"));
+            assert_eq!(gateway.devices(), vec![1001, 1002]);
+
+            let client = BacnetClient::new(b);
+            let routers = client
+                .who_is_router_to_network(Some(100), Duration::from_millis(100))
+                .await
+                .unwrap();
+            assert_eq!(routers.len(), 1);
+            assert_eq!(routers[0].networks, vec![100]);
+            let mut found: Vec<_> = client
+                .who_is(None, Duration::from_millis(200))
+                .await
+                .unwrap()
+                .into_iter()
+                .map(|(address, i_am)| (i_am.device_identifier.instance, address))
+                .collect();
+            found.sort_unstable();
+            assert_eq!(
+                found,
+                vec![(1001, gateway.address(1001)), (1002, gateway.address(1002))]
+            );
+            assert_eq!(client.address(1002), Some(gateway.address(1002)));
+
+            let name = client
+                .read(
+                    1002,
+                    ObjectIdentifier::new(ObjectType::LightingOutput, 1),
+                    PropertyIdentifier::ObjectName,
+                )
+                .await
+                .unwrap();
+            assert_eq!(name, BACnetValue::CharacterString("Lamp 2".into()));
+
+            drop(second);
+            task::sleep(Duration::from_millis(50)).await;
+            assert_eq!(gateway.devices(), vec![1001]);
+        });
+    }
+
+    #[test]
+    fn test_route_to_link() {
+        let (a, _b) = link_pair();
+        let (outgoing, _receiver) = channel::unbounded();
+        let inner = Inner {
+            link: a,
+            network: 1,
+            virtual_network: 100,
+            devices: Mutex::new(HashMap::new()),
+            routers: Mutex::new(HashMap::new()),
+            outgoing,
+        };
+        inner.routers.lock().unwrap().insert(5, vec![9]);
+        let apdu = APDU::unconfirmed_request(UnconfirmedServiceChoice::WhoIs as u8, vec![]);
+        let from = vec![0, 3, 0xE9];
+
+        // Beyond the data link through the router
+        let npdu = NPDU::new(
+            apdu.clone(),
+            NPDUDest::to_address(&Address::remote(5, vec![7])),
+            None,
+            NPDUPriority::Normal,
+        );
+        let (mac, npdu) = inner.route_to_link(from.clone(), vec![], npdu).unwrap();
+        assert_eq!(mac, vec![9]);
+        assert_eq!(npdu.destination.unwrap().hops, 254);
+        assert_eq!(npdu.source.unwrap().net, 100);
+
+        // On the data link itself
+        let npdu = NPDU::new(
+            apdu.clone(),
+            NPDUDest::to_address(&Address::remote(1, vec![2])),
+            None,
+            NPDUPriority::Normal,
+        );
+        let (mac, npdu) = inner.route_to_link(from.clone(), vec![], npdu).unwrap();
+        assert_eq!(mac, vec![2]);
+        assert_eq!(npdu.destination, None);
+
+        // Local to the virtual network
+        let npdu = NPDU::new(apdu, None, None, NPDUPriority::Normal);
+        assert!(inner.route_to_link(from, vec![], npdu).is_none());
+    }
+}
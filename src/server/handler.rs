@@ -0,0 +1,89 @@
+use crate::application::{BACnetError, ReinitializedState};
+use crate::network::Address;
+use crate::server::ObjectStore;
+
+/// Response to a confirmed request
+#[derive(Clone, Debug, PartialEq)]
+pub enum Response {
+    SimpleAck,
+    /// A Complex-ACK with the service ACK parameters
+    ComplexAck(Vec<u8>),
+    Error(BACnetError),
+    /// An Error PDU with the encoded error parameters of the service, e.g. a
+    /// [`PrivateTransferError`](crate::application::PrivateTransferError)
+    ServiceError(Vec<u8>),
+    /// A Reject PDU with the [`RejectReason`](crate::application::RejectReason)
+    Reject(u8),
+}
+
+/// Handler of a confirmed service, see
+/// [`BacnetDevice::on_confirmed`](super::BacnetDevice::on_confirmed)
+///
+/// Requests that can't be decoded are answered with an invalid-tag reject
+/// when the handler fails.
+pub trait ConfirmedHandler: Send + Sync {
+    /// Execute a request from `source` with the service parameters `data`
+    fn handle(
+        &self,
+        source: &Address,
+        data: &[u8],
+        objects: &mut ObjectStore,
+    ) -> std::io::Result<Response>;
+}
+
+impl<F> ConfirmedHandler for F
+where
+    F: Fn(&Address, &[u8], &mut ObjectStore) -> std::io::Result<Response> + Send + Sync,
+{
+    fn handle(
+        &self,
+        source: &Address,
+        data: &[u8],
+        objects: &mut ObjectStore,
+    ) -> std::io::Result<Response> {
+        self(source, data, objects)
+    }
+}
+
+/// Handler of an unconfirmed service, see
+/// [`BacnetDevice::on_unconfirmed`](super::BacnetDevice::on_unconfirmed)
+pub trait UnconfirmedHandler: Send + Sync {
+    /// Execute a request from `source` with the service parameters `data`
+    fn handle(
+        &self,
+        source: &Address,
+        data: &[u8],
+        objects: &mut ObjectStore,
+    ) -> std::io::Result<()>;
+}
+
+impl<F> UnconfirmedHandler for F
+where
+    F: Fn(&Address, &[u8], &mut ObjectStore) -> std::io::Result<()> + Send + Sync,
+{
+    fn handle(
+        &self,
+        source: &Address,
+        data: &[u8],
+        objects: &mut ObjectStore,
+    ) -> std::io::Result<()> {
+        self(source, data, objects)
+    }
+}
+
+/// Handler of ReinitializeDevice requests, see
+/// [`BacnetDevice::on_reinitialize`](super::BacnetDevice::on_reinitialize)
+pub trait ReinitializeHandler: Send + Sync {
+    /// Reinitialize the device into `state`, an error is returned to the
+    /// requester
+    fn reinitialize(&self, state: ReinitializedState) -> Result<(), BACnetError>;
+}
+
+impl<F> ReinitializeHandler for F
+where
+    F: Fn(ReinitializedState) -> Result<(), BACnetError> + Send + Sync,
+{
+    fn reinitialize(&self, state: ReinitializedState) -> Result<(), BACnetError> {
+        self(state)
+    }
+}
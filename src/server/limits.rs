@@ -0,0 +1,98 @@
+use crate::network::Address;
+
+use std::collections::hash_map::RandomState;
+use std::collections::HashMap;
+use std::hash::{BuildHasher, Hasher};
+use std::time::{Duration, Instant};
+
+/// Sources tracked before those without requests in the current second are
+/// forgotten
+const MAX_SOURCES: usize = 256;
+
+/// Limits protecting a [`BacnetDevice`](super::BacnetDevice) from
+/// misbehaving clients, none are enforced by default
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct Limits {
+    /// Requests accepted from a source per second, further ones are
+    /// ignored
+    pub requests_per_second: Option<u32>,
+    /// Confirmed notifications waiting for an acknowledgement, further ones
+    /// fail
+    pub max_pending_transactions: Option<usize>,
+    /// Upper bound of the random delay of broadcast answers, e.g. I-Am to a
+    /// Who-Is, identical answers pending are sent once
+    pub broadcast_response_jitter: Duration,
+    /// Time after a broadcast answer in which identical ones are not sent
+    /// again, e.g. to repeated Who-Is broadcasts
+    pub broadcast_response_interval: Duration,
+}
+
+impl Limits {
+    /// A random delay up to the broadcast response jitter
+    pub(crate) fn jitter(&self) -> Duration {
+        let nanos = self.broadcast_response_jitter.as_nanos() as u64;
+        match nanos {
+            0 => Duration::ZERO,
+            nanos => Duration::from_nanos(RandomState::new().build_hasher().finish() % nanos),
+        }
+    }
+}
+
+/// Requests per source in one second windows
+#[derive(Default)]
+pub(crate) struct RateLimiter {
+    windows: HashMap<Address, (Instant, u32)>,
+}
+
+impl RateLimiter {
+    /// Count a request from the source, returns whether it is within the
+    /// limit
+    pub(crate) fn allow(&mut self, source: &Address, limit: u32, now: Instant) -> bool {
+        let second = Duration::from_secs(1);
+        if self.windows.len() >= MAX_SOURCES && !self.windows.contains_key(source) {
+            self.windows
+                .retain(|_, (start, _)| now.duration_since(*start) < second);
+        }
+        let (start, count) = self.windows.entry(source.clone()).or_insert((now, 0));
+        if now.duration_since(*start) >= second {
+            *start = now;
+            *count = 0;
+        }
+        *count += 1;
+        *count <= limit
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rate_limiter() {
+        let mut limiter = RateLimiter::default();
+        let (a, b) = (Address::local(vec![1]), Address::local(vec![2]));
+        let now = Instant::now();
+        assert!(limiter.allow(&a, 2, now));
+        assert!(limiter.allow(&a, 2, now));
+        assert!(!limiter.allow(&a, 2, now + Duration::from_millis(500)));
+        assert!(limiter.allow(&b, 2, now));
+        assert!(limiter.allow(&a, 2, now + Duration::from_secs(1)));
+
+        // Idle sources are forgotten
+        for mac in 0..MAX_SOURCES as u16 {
+            limiter.allow(&Address::local(mac.to_be_bytes().to_vec()), 2, now);
+        }
+        limiter.allow(&Address::local(vec![3]), 2, now + Duration::from_secs(2));
+        assert_eq!(limiter.windows.len(), 1);
+    }
+
+    #[test]
+    fn test_jitter() {
+        let limits = Limits {
+            broadcast_response_jitter: Duration::from_millis(100),
+            ..Limits::default()
+        };
+        assert!(limits.jitter() < Duration::from_millis(100));
+        assert_eq!(Limits::default().jitter(), Duration::ZERO);
+    }
+}
@@ -0,0 +1,202 @@
+use crate::application::{
+    BACnetDateTime, BACnetError, BACnetValue, ErrorCode, ObjectIdentifier, PropertyIdentifier,
+};
+use crate::objects::Object;
+
+use std::collections::BTreeMap;
+
+/// Objects served by a [`BacnetDevice`](super::BacnetDevice), ordered by
+/// their identifier
+#[derive(Default)]
+pub struct ObjectStore {
+    objects: BTreeMap<ObjectIdentifier, Box<dyn Object + Send>>,
+}
+
+impl ObjectStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add an object, replacing and returning one with the same identifier
+    pub fn insert<O: Object + Send + 'static>(
+        &mut self,
+        object: O,
+    ) -> Option<Box<dyn Object + Send>> {
+        self.objects
+            .insert(object.object_identifier(), Box::new(object))
+    }
+
+    pub fn remove(&mut self, identifier: ObjectIdentifier) -> Option<Box<dyn Object + Send>> {
+        self.objects.remove(&identifier)
+    }
+
+    pub fn get(&self, identifier: ObjectIdentifier) -> Option<&(dyn Object + Send)> {
+        self.objects.get(&identifier).map(|o| o.as_ref())
+    }
+
+    pub fn get_mut(&mut self, identifier: ObjectIdentifier) -> Option<&mut (dyn Object + Send)> {
+        match self.objects.get_mut(&identifier) {
+            Some(object) => Some(object.as_mut()),
+            None => None,
+        }
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &(dyn Object + Send)> {
+        self.objects.values().map(|o| o.as_ref())
+    }
+
+    /// Identifiers of all objects
+    pub fn object_list(&self) -> Vec<ObjectIdentifier> {
+        self.objects.keys().copied().collect()
+    }
+
+    pub fn len(&self) -> usize {
+        self.objects.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.objects.is_empty()
+    }
+}
+
+/// Storage of objects that are not kept in memory as [`Object`]s, such as a
+/// database or hardware I/O
+///
+/// A database set with
+/// [`BacnetDevice::set_database`](super::BacnetDevice::set_database) serves
+/// ReadProperty, ReadPropertyMultiple and WriteProperty for the objects
+/// that are not in the [`ObjectStore`] of the device, and they are listed in
+/// the object list of the Device object.
+pub trait ObjectDatabase {
+    /// Identifiers of all objects
+    fn object_list(&self) -> Vec<ObjectIdentifier>;
+
+    /// Properties of an object, excluding Object_Identifier, Object_Name,
+    /// Object_Type and Property_List (12.1.1.4.1)
+    fn property_list(
+        &self,
+        object: ObjectIdentifier,
+    ) -> Result<Vec<PropertyIdentifier>, BACnetError>;
+
+    fn read_property(
+        &self,
+        object: ObjectIdentifier,
+        property: PropertyIdentifier,
+        array_index: Option<u32>,
+    ) -> Result<BACnetValue, BACnetError>;
+
+    /// Write a property of an object, at `time` for the changes it logs
+    fn write_property(
+        &mut self,
+        _object: ObjectIdentifier,
+        _property: PropertyIdentifier,
+        _array_index: Option<u32>,
+        _value: BACnetValue,
+        _priority: Option<u8>,
+        _time: BACnetDateTime,
+    ) -> Result<(), BACnetError> {
+        Err(BACnetError::property(ErrorCode::WriteAccessDenied))
+    }
+}
+
+fn unknown_object() -> BACnetError {
+    BACnetError::object(ErrorCode::UnknownObject)
+}
+
+impl ObjectDatabase for ObjectStore {
+    fn object_list(&self) -> Vec<ObjectIdentifier> {
+        ObjectStore::object_list(self)
+    }
+
+    fn property_list(
+        &self,
+        object: ObjectIdentifier,
+    ) -> Result<Vec<PropertyIdentifier>, BACnetError> {
+        let object = self.get(object).ok_or_else(unknown_object)?;
+        Ok(object.property_list())
+    }
+
+    fn read_property(
+        &self,
+        object: ObjectIdentifier,
+        property: PropertyIdentifier,
+        array_index: Option<u32>,
+    ) -> Result<BACnetValue, BACnetError> {
+        let object = self.get(object).ok_or_else(unknown_object)?;
+        object.read_property(property, array_index)
+    }
+
+    fn write_property(
+        &mut self,
+        object: ObjectIdentifier,
+        property: PropertyIdentifier,
+        array_index: Option<u32>,
+        value: BACnetValue,
+        priority: Option<u8>,
+        time: BACnetDateTime,
+    ) -> Result<(), BACnetError> {
+        let object = self.get_mut(object).ok_or_else(unknown_object)?;
+        object.write_property(property, array_index, value, priority, time)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::application::ObjectType;
+    use crate::objects::testing::at;
+    use crate::objects::{Channel, LightingOutput};
+
+    #[test]
+    fn test_object_store() {
+        let mut store = ObjectStore::new();
+        assert!(store.insert(LightingOutput::new(2, "Lamp")).is_none());
+        assert!(store.insert(Channel::new(1, "Scene", 1)).is_none());
+        assert!(store.insert(LightingOutput::new(2, "Lamp 2")).is_some());
+
+        let lamp = ObjectIdentifier::new(ObjectType::LightingOutput, 2);
+        assert_eq!(store.len(), 2);
+        assert_eq!(store.get(lamp).unwrap().object_name(), "Lamp 2");
+        // Channel (53) sorts before Lighting Output (54)
+        assert_eq!(
+            store.object_list(),
+            [ObjectIdentifier::new(ObjectType::Channel, 1), lamp]
+        );
+        assert!(store.remove(lamp).is_some());
+        assert!(store.get_mut(lamp).is_none());
+    }
+
+    #[test]
+    fn test_object_store_database() {
+        let mut store = ObjectStore::new();
+        store.insert(LightingOutput::new(2, "Lamp"));
+        let lamp = ObjectIdentifier::new(ObjectType::LightingOutput, 2);
+        let database: &mut dyn ObjectDatabase = &mut store;
+
+        assert_eq!(database.object_list(), [lamp]);
+        assert_eq!(
+            database.read_property(lamp, PropertyIdentifier::ObjectName, None),
+            Ok(BACnetValue::CharacterString("Lamp".into()))
+        );
+        assert!(database
+            .property_list(lamp)
+            .unwrap()
+            .contains(&PropertyIdentifier::PresentValue));
+        let unknown = ObjectIdentifier::new(ObjectType::LightingOutput, 3);
+        assert_eq!(
+            database.read_property(unknown, PropertyIdentifier::ObjectName, None),
+            Err(unknown_object())
+        );
+        let value = BACnetValue::Real(50.0);
+        assert!(database
+            .write_property(
+                lamp,
+                PropertyIdentifier::PresentValue,
+                None,
+                value,
+                Some(8),
+                at(0)
+            )
+            .is_ok());
+    }
+}
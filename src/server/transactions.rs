@@ -0,0 +1,217 @@
+//! Responder transaction state machines (5.4.5)
+//!
+//! Clients send a request again when its response is lost. The responses
+//! sent are kept for as long as the client retries, so that a duplicate
+//! request, from the same source with the same invoke ID and parameters, is
+//! answered with the same response instead of being executed twice.
+//! Segmented requests are reassembled until their last segment arrives, or
+//! they are given up after the segment timeout.
+
+use crate::application::*;
+use crate::network::Address;
+
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+/// Transactions kept at most, older ones are forgotten first
+const MAX_TRANSACTIONS: usize = 1024;
+
+struct Answered {
+    request: APDU,
+    response: APDU,
+    until: Instant,
+}
+
+/// A segment of a request, as processed by [`Responder::segment`]
+pub(crate) enum Segmented {
+    /// More segments follow, with the SegmentACK to send if a window is
+    /// complete
+    Pending(Option<APDU>),
+    /// The request is reassembled, its last segment is acknowledged with
+    /// the SegmentACK
+    Complete(APDU, APDU),
+    /// The segment can't be accepted, the transaction is aborted
+    Invalid,
+}
+
+/// The transactions of the requests received by a device
+#[derive(Default)]
+pub(crate) struct Responder {
+    answered: HashMap<(Address, u8), Answered>,
+    reassembling: HashMap<(Address, u8), SegmentedReceiver>,
+}
+
+impl Responder {
+    /// The response to send again if the request was answered before
+    pub(crate) fn duplicate(
+        &mut self,
+        source: &Address,
+        request: &APDU,
+        now: Instant,
+    ) -> Option<APDU> {
+        self.expire(now);
+        let key = (source.clone(), request.invoke_id()?);
+        match self.answered.get(&key) {
+            Some(answered) if same_request(&answered.request, request) => {
+                Some(answered.response.clone())
+            }
+            _ => None,
+        }
+    }
+
+    /// Keep the response to a request until `until`
+    pub(crate) fn answered(
+        &mut self,
+        source: Address,
+        request: APDU,
+        response: APDU,
+        until: Instant,
+    ) {
+        let invoke_id = match request.invoke_id() {
+            Some(invoke_id) => invoke_id,
+            None => return,
+        };
+        if self.answered.len() >= MAX_TRANSACTIONS {
+            let oldest = self.answered.iter().min_by_key(|(_, a)| a.until);
+            if let Some(key) = oldest.map(|(key, _)| key.clone()) {
+                self.answered.remove(&key);
+            }
+        }
+        let answered = Answered {
+            request,
+            response,
+            until,
+        };
+        self.answered.insert((source, invoke_id), answered);
+    }
+
+    /// Process a segment of a request received at `now`
+    ///
+    /// Requests are reassembled in windows of up to `window_size` segments
+    /// and given up when no segment arrives within four times the segment
+    /// timeout.
+    pub(crate) fn segment(
+        &mut self,
+        source: &Address,
+        segment: &APDU,
+        window_size: u8,
+        segment_timeout: Duration,
+        now: Instant,
+    ) -> Segmented {
+        self.expire(now);
+        let key = match segment.invoke_id() {
+            Some(invoke_id) => (source.clone(), invoke_id),
+            None => return Segmented::Invalid,
+        };
+        // The first segment starts the transaction over
+        if segment.segment().is_some_and(|s| s.sequence_number == 0)
+            || !self.reassembling.contains_key(&key)
+        {
+            if self.reassembling.len() >= MAX_TRANSACTIONS {
+                return Segmented::Invalid;
+            }
+            let receiver = SegmentedReceiver::new(window_size, segment_timeout);
+            self.reassembling.insert(key.clone(), receiver);
+        }
+        let receiver = self.reassembling.get_mut(&key).unwrap();
+        let ack = match receiver.receive(segment, now) {
+            Ok(ack) => ack,
+            Err(_) => {
+                self.reassembling.remove(&key);
+                return Segmented::Invalid;
+            }
+        };
+        match (receiver.is_complete(), ack) {
+            (true, Some(ack)) => {
+                let receiver = self.reassembling.remove(&key).unwrap();
+                match receiver.into_apdu() {
+                    Some(request) => Segmented::Complete(request, ack),
+                    None => Segmented::Invalid,
+                }
+            }
+            (_, ack) => Segmented::Pending(ack),
+        }
+    }
+
+    /// Forget the responses no longer retried for and the requests whose
+    /// segments timed out
+    fn expire(&mut self, now: Instant) {
+        self.answered.retain(|_, answered| answered.until > now);
+        self.reassembling
+            .retain(|_, receiver| !receiver.is_timed_out(now));
+    }
+}
+
+fn same_request(a: &APDU, b: &APDU) -> bool {
+    a.service_choice() == b.service_choice() && a.user_data() == b.user_data()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_duplicate() {
+        let mut responder = Responder::default();
+        let (a, b) = (Address::local(vec![1]), Address::local(vec![2]));
+        let now = Instant::now();
+        let request = APDU::confirmed_request(3, 12, vec![0x0c, 0x00, 0x00, 0x00, 0x01]);
+        let response = APDU::complex_ack(3, 12, vec![0x3e]);
+        assert_eq!(responder.duplicate(&a, &request, now), None);
+        let until = now + Duration::from_secs(1);
+        responder.answered(a.clone(), request.clone(), response.clone(), until);
+
+        assert_eq!(responder.duplicate(&a, &request, now), Some(response));
+        assert_eq!(responder.duplicate(&b, &request, now), None);
+        // The invoke ID used for another request
+        let other = APDU::confirmed_request(3, 15, vec![]);
+        assert_eq!(responder.duplicate(&a, &other, now), None);
+        assert_eq!(responder.duplicate(&a, &request, until), None);
+        assert!(responder.answered.is_empty());
+    }
+
+    #[test]
+    fn test_segmented_request() {
+        let mut responder = Responder::default();
+        let source = Address::local(vec![1]);
+        let now = Instant::now();
+        let timeout = Duration::from_secs(1);
+        let request = APDU::confirmed_request(5, 20, (0..12).collect::<Vec<u8>>());
+        let mut sender = SegmentedSender::new(&request, 10, 4, None).unwrap();
+
+        let segment = sender.window().remove(0);
+        let ack = match responder.segment(&source, &segment, 4, timeout, now) {
+            Segmented::Pending(Some(ack)) => ack,
+            _ => panic!("The first segment is acknowledged"),
+        };
+        sender.segment_ack(&ack).unwrap();
+        let segments = sender.window();
+        assert_eq!(segments.len(), 2);
+        assert!(matches!(
+            responder.segment(&source, &segments[0], 4, timeout, now),
+            Segmented::Pending(None)
+        ));
+        match responder.segment(&source, &segments[1], 4, timeout, now) {
+            Segmented::Complete(reassembled, ack) => {
+                assert_eq!(reassembled, request);
+                sender.segment_ack(&ack).unwrap();
+                assert!(sender.is_complete());
+            }
+            _ => panic!("The request is complete"),
+        }
+        assert!(responder.reassembling.is_empty());
+
+        // Given up after four segment timeouts
+        let segment = SegmentedSender::new(&request, 10, 1, None)
+            .unwrap()
+            .window()[0]
+            .clone();
+        responder.segment(&source, &segment, 4, timeout, now);
+        let later = now + timeout * 4;
+        assert!(matches!(
+            responder.segment(&source, &segments[0], 4, timeout, later),
+            Segmented::Invalid
+        ));
+        assert!(responder.reassembling.is_empty());
+    }
+}
@@ -0,0 +1,637 @@
+//! Network and transaction layer shared by the client and the server
+//!
+//! A [`Station`] sends APDUs to [`Address`]es over one or more
+//! [`DataLink`]s, learning the routers to remote networks from received
+//! NPDUs, and matches the responses to confirmed requests it sent.
+
+use crate::application::*;
+use crate::client::ClientError;
+use crate::clock::{Clock, SystemClock};
+use crate::network::*;
+use crate::transport::DataLink;
+use crate::Decode;
+
+use async_std::channel::{self, Receiver, Sender};
+use bytes::Bytes;
+use futures_lite::future;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::hash_map::Entry;
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::hash::{Hash, Hasher};
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use tracing::field::{self, Empty};
+use tracing::{debug_span, trace, Instrument, Span};
+
+/// Shards of the transaction table, requests to peers in different shards
+/// do not contend for a lock
+const SHARDS: usize = 16;
+
+/// Outstanding confirmed requests to the peers of a shard
+#[derive(Default)]
+struct Shard {
+    next_invoke_id: u8,
+    pending: HashMap<(Address, u8), Sender<APDU>>,
+    /// Number of pending requests by peer
+    outstanding: HashMap<Address, usize>,
+    /// Requests waiting for a transaction of a peer of the shard to end
+    waiting: Vec<Sender<()>>,
+}
+
+/// Client transaction state machine (5.4.4) of the confirmed requests of a
+/// station
+///
+/// Requests are registered by peer and invoke ID, sharded by peer, and the
+/// response PDUs received are handed to the request they belong to.
+pub(crate) struct TransactionManager {
+    shards: Vec<Mutex<Shard>>,
+    /// Pending requests in all shards
+    len: AtomicUsize,
+    /// Requests outstanding to a peer at a time, beyond the invoke IDs,
+    /// `usize::MAX` for no limit
+    window: AtomicUsize,
+}
+
+impl Default for TransactionManager {
+    fn default() -> Self {
+        Self {
+            shards: (0..SHARDS).map(|_| Mutex::default()).collect(),
+            len: AtomicUsize::new(0),
+            window: AtomicUsize::new(usize::MAX),
+        }
+    }
+}
+
+impl TransactionManager {
+    fn shard(&self, address: &Address) -> &Mutex<Shard> {
+        let mut hasher = DefaultHasher::new();
+        address.hash(&mut hasher);
+        &self.shards[hasher.finish() as usize % SHARDS]
+    }
+
+    /// Number of pending requests
+    pub(crate) fn len(&self) -> usize {
+        self.len.load(Ordering::Relaxed)
+    }
+
+    fn window(&self) -> Option<usize> {
+        match self.window.load(Ordering::Relaxed) {
+            usize::MAX => None,
+            window => Some(window),
+        }
+    }
+
+    fn set_window(&self, window: Option<usize>) {
+        let window = window.unwrap_or(usize::MAX);
+        self.window.store(window, Ordering::Relaxed);
+    }
+
+    /// Register a request to `address` and return its invoke ID, or a
+    /// receiver notified when a transaction of the shard ends if the window
+    /// of the address is full
+    fn allocate(
+        &self,
+        address: &Address,
+        response: Sender<APDU>,
+    ) -> Result<Result<u8, Receiver<()>>, ClientError> {
+        let mut shard = self.shard(address).lock().unwrap();
+        let outstanding = shard.outstanding.get(address).copied().unwrap_or(0);
+        if outstanding >= self.window.load(Ordering::Relaxed) {
+            let (sender, receiver) = channel::bounded(1);
+            shard.waiting.push(sender);
+            return Ok(Err(receiver));
+        }
+        for _ in 0..=u8::MAX {
+            let invoke_id = shard.next_invoke_id;
+            shard.next_invoke_id = invoke_id.wrapping_add(1);
+            if let Entry::Vacant(entry) = shard.pending.entry((address.clone(), invoke_id)) {
+                entry.insert(response);
+                *shard.outstanding.entry(address.clone()).or_default() += 1;
+                self.len.fetch_add(1, Ordering::Relaxed);
+                return Ok(Ok(invoke_id));
+            }
+        }
+        Err(ClientError::InvokeIdExhausted)
+    }
+
+    /// The response channel of a request, which stays pending
+    fn get(&self, key: &(Address, u8)) -> Option<Sender<APDU>> {
+        let shard = self.shard(&key.0).lock().unwrap();
+        shard.pending.get(key).cloned()
+    }
+
+    fn remove(&self, key: &(Address, u8)) -> Option<Sender<APDU>> {
+        let mut shard = self.shard(&key.0).lock().unwrap();
+        let response = shard.pending.remove(key)?;
+        self.len.fetch_sub(1, Ordering::Relaxed);
+        if let Entry::Occupied(mut outstanding) = shard.outstanding.entry(key.0.clone()) {
+            *outstanding.get_mut() -= 1;
+            if *outstanding.get() == 0 {
+                outstanding.remove();
+            }
+        }
+        for waiting in shard.waiting.drain(..) {
+            let _ = waiting.try_send(());
+        }
+        Some(response)
+    }
+
+    /// Hand a response PDU to the transaction it belongs to
+    fn complete(&self, address: Address, apdu: APDU) {
+        let key = match apdu {
+            // The client of a transaction with this station aborted it, it
+            // is not a response to a request of the station
+            APDU::Abort { server: false, .. } => {
+                trace!("Ignoring abort by client {:?}: {:?}", address, apdu);
+                return;
+            }
+            _ => match apdu.invoke_id() {
+                Some(invoke_id) => (address, invoke_id),
+                None => return,
+            },
+        };
+        // Segments are handed to the transaction until it reassembled them
+        let response = match apdu.segment() {
+            Some(_) => self.get(&key),
+            None => self.remove(&key),
+        };
+        match response {
+            Some(response) => {
+                let _ = response.try_send(apdu);
+            }
+            None => trace!("No transaction for {:?}", key),
+        }
+    }
+}
+
+/// Counters of the traffic of a station, for monitoring
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct Statistics {
+    /// NPDUs received on all ports
+    pub frames_received: u64,
+    /// NPDUs sent on all ports, broadcasts once per port
+    pub frames_sent: u64,
+    /// Frames dropped by the data links and requests rejected because
+    /// they could not be decoded
+    pub decode_errors: u64,
+    /// Confirmed requests waiting for their response
+    pub outstanding_transactions: usize,
+    /// Confirmed requests that were not answered in time
+    pub transaction_timeouts: u64,
+}
+
+/// A data link the station is attached to
+pub(crate) struct Port<D> {
+    pub(crate) link: D,
+    /// Network number of the link, `None` if it is not known
+    pub(crate) network: Option<u16>,
+}
+
+/// The span of the handling of a frame sent to or received from `peer`
+///
+/// APDUs record their type, invoke ID, service and the reason of rejects
+/// and aborts, network messages their type, so that the events of a
+/// transaction can be correlated.
+pub(crate) fn frame_span(direction: &'static str, peer: &Address, content: &NPDUContent) -> Span {
+    let span = debug_span!(
+        "frame",
+        direction,
+        peer = ?peer,
+        pdu_type = Empty,
+        invoke_id = Empty,
+        service = Empty,
+        reason = Empty,
+        message_type = Empty,
+    );
+    match content {
+        NPDUContent::APDU(apdu) => {
+            span.record("pdu_type", field::debug(apdu.pdu_type()));
+            if let Some(invoke_id) = apdu.invoke_id() {
+                span.record("invoke_id", invoke_id);
+            }
+            if let Some(reason) = apdu.reason() {
+                span.record("reason", reason);
+            }
+            match (apdu.service_name(), apdu.service_choice()) {
+                (Some(service), _) => span.record("service", service.as_str()),
+                (None, Some(service_choice)) => span.record("service", service_choice),
+                (None, None) => &span,
+            };
+        }
+        NPDUContent::Message(message) => {
+            span.record("message_type", field::debug(message.message_type));
+        }
+    }
+    span
+}
+
+pub(crate) struct Station<D> {
+    ports: Vec<Port<D>>,
+    pub(crate) transactions: TransactionManager,
+    /// Port and MAC address of the router to each remote network
+    routers: Mutex<HashMap<u16, (usize, Vec<u8>)>>,
+    frames_received: AtomicU64,
+    frames_sent: AtomicU64,
+    decode_errors: AtomicU64,
+    transaction_timeouts: AtomicU64,
+    clock: Mutex<Arc<dyn Clock>>,
+}
+
+impl<D> Station<D> {
+    pub(crate) fn clock(&self) -> Arc<dyn Clock> {
+        self.clock.lock().unwrap().clone()
+    }
+
+    pub(crate) fn set_clock(&self, clock: Arc<dyn Clock>) {
+        *self.clock.lock().unwrap() = clock;
+    }
+}
+
+impl<D: DataLink> Station<D> {
+    /// A station on a single data link with an unknown network number
+    pub(crate) fn new(link: D) -> Self {
+        Self::with_ports(vec![Port {
+            link,
+            network: None,
+        }])
+    }
+
+    pub(crate) fn with_ports(ports: Vec<Port<D>>) -> Self {
+        Self {
+            ports,
+            transactions: TransactionManager::default(),
+            routers: Mutex::new(HashMap::new()),
+            frames_received: AtomicU64::new(0),
+            frames_sent: AtomicU64::new(0),
+            decode_errors: AtomicU64::new(0),
+            transaction_timeouts: AtomicU64::new(0),
+            clock: Mutex::new(Arc::new(SystemClock)),
+        }
+    }
+
+    pub(crate) fn port_count(&self) -> usize {
+        self.ports.len()
+    }
+
+    /// Wait for the next NPDU on a port
+    pub(crate) async fn recv(&self, port: usize) -> std::io::Result<(Vec<u8>, NPDU)> {
+        let received = self.ports[port].link.recv().await?;
+        self.frames_received.fetch_add(1, Ordering::Relaxed);
+        Ok(received)
+    }
+
+    /// Count a received request that could not be decoded
+    pub(crate) fn decode_error(&self) {
+        self.decode_errors.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn statistics(&self) -> Statistics {
+        let link_errors: u64 = self.ports.iter().map(|p| p.link.decode_errors()).sum();
+        Statistics {
+            frames_received: self.frames_received.load(Ordering::Relaxed),
+            frames_sent: self.frames_sent.load(Ordering::Relaxed),
+            decode_errors: self.decode_errors.load(Ordering::Relaxed) + link_errors,
+            outstanding_transactions: self.transactions.len(),
+            transaction_timeouts: self.transaction_timeouts.load(Ordering::Relaxed),
+        }
+    }
+
+    /// The port attached to a network
+    fn port_of(&self, net: u16) -> Option<usize> {
+        self.ports.iter().position(|p| p.network == Some(net))
+    }
+
+    /// Whether the network is attached to one of the ports
+    pub(crate) fn is_attached(&self, net: u16) -> bool {
+        self.port_of(net).is_some()
+    }
+
+    /// Send an APDU, local addresses are on the first port
+    ///
+    /// Broadcasts go out on every port they apply to, as do requests to
+    /// remote networks without a known router, which the router to the
+    /// network forwards.
+    pub(crate) async fn send(&self, address: &Address, apdu: APDU) -> std::io::Result<()> {
+        let expecting_reply = matches!(apdu, APDU::ConfirmedRequest { .. });
+        self.send_npdu(address, apdu.into(), expecting_reply).await
+    }
+
+    /// Send a network layer message, like [`send`](Self::send)
+    pub(crate) async fn send_message(
+        &self,
+        address: &Address,
+        message: NetworkMessage,
+    ) -> std::io::Result<()> {
+        self.send_npdu(address, NPDUContent::Message(message), false)
+            .await
+    }
+
+    async fn send_npdu(
+        &self,
+        address: &Address,
+        content: NPDUContent,
+        expecting_reply: bool,
+    ) -> std::io::Result<()> {
+        let (ports, mac, destination) = match address.net {
+            None if address.is_broadcast() => ((0..self.ports.len()).collect(), vec![], None),
+            None => (vec![0], address.mac.clone(), None),
+            Some(GLOBAL_BROADCAST) => (
+                (0..self.ports.len()).collect(),
+                vec![],
+                NPDUDest::to_address(address),
+            ),
+            Some(net) => match self.port_of(net) {
+                Some(port) => (vec![port], address.mac.clone(), None),
+                None => match self.routers.lock().unwrap().get(&net).cloned() {
+                    Some((port, mac)) => (vec![port], mac, NPDUDest::to_address(address)),
+                    None => (
+                        (0..self.ports.len()).collect(),
+                        vec![],
+                        NPDUDest::to_address(address),
+                    ),
+                },
+            },
+        };
+        let span = frame_span("sent", address, &content);
+        let mut npdu = NPDU::new(content, destination, None, NPDUPriority::Normal);
+        npdu.data_expecting_reply = expecting_reply;
+        async {
+            for port in ports {
+                self.ports[port].link.send(&mac, &npdu).await?;
+                self.frames_sent.fetch_add(1, Ordering::Relaxed);
+            }
+            Ok(())
+        }
+        .instrument(span)
+        .await
+    }
+
+    /// The address of the sender of an NPDU received from `mac` on a port
+    ///
+    /// NPDUs from remote networks are forwarded by a router, which is
+    /// remembered to send to the network. Stations on a port with a known
+    /// network number are addressed on that network.
+    pub(crate) fn source(&self, port: usize, mac: Vec<u8>, npdu: &NPDU) -> Address {
+        match &npdu.source {
+            Some(source) => {
+                self.routers.lock().unwrap().insert(source.net, (port, mac));
+                Address::remote(source.net, source.adr.clone())
+            }
+            None => match self.ports[port].network {
+                Some(net) => Address::remote(net, mac),
+                None => Address::local(mac),
+            },
+        }
+    }
+
+    /// Remember the router with `mac` on a port to reach the networks it
+    /// announced with I-Am-Router-To-Network
+    pub(crate) fn add_routes(&self, port: usize, mac: &[u8], networks: &[u16]) {
+        let mut routers = self.routers.lock().unwrap();
+        for net in networks {
+            routers.insert(*net, (port, mac.to_vec()));
+        }
+    }
+
+    pub(crate) fn window(&self) -> Option<usize> {
+        self.transactions.window()
+    }
+
+    /// Limit the confirmed requests outstanding to an address at a time,
+    /// further ones wait for one to complete
+    pub(crate) fn set_window(&self, window: Option<usize>) {
+        self.transactions.set_window(window);
+    }
+
+    /// Register a confirmed request to `address`, returning its invoke ID
+    ///
+    /// Waits while the window of the address is full.
+    async fn start_transaction(
+        &self,
+        address: &Address,
+        response: Sender<APDU>,
+    ) -> Result<u8, ClientError> {
+        loop {
+            match self.transactions.allocate(address, response.clone())? {
+                Ok(invoke_id) => return Ok(invoke_id),
+                Err(wait) => {
+                    let _ = wait.recv().await;
+                }
+            }
+        }
+    }
+
+    fn end_transaction(&self, address: &Address, invoke_id: u8) {
+        let key = (address.clone(), invoke_id);
+        self.transactions.remove(&key);
+    }
+
+    /// Hand a response PDU to the transaction it belongs to
+    pub(crate) fn complete(&self, address: Address, apdu: APDU) {
+        self.transactions.complete(address, apdu);
+    }
+
+    /// Send a confirmed request and wait up to `timeout` for the response,
+    /// returning the service ACK parameters
+    ///
+    /// Requests that are not answered in time are sent again with the same
+    /// invoke ID, up to `retries` times (5.4.4.1).
+    pub(crate) async fn confirmed_request(
+        &self,
+        address: &Address,
+        service: ConfirmedServiceChoice,
+        data: Vec<u8>,
+        timeout: Duration,
+        retries: usize,
+    ) -> Result<Bytes, ClientError> {
+        // Room for a window of segments
+        let (sender, receiver) = channel::bounded(DEFAULT_WINDOW_SIZE as usize);
+        let invoke_id = self.start_transaction(address, sender).await?;
+        let _transaction = Transaction {
+            station: self,
+            address,
+            invoke_id,
+        };
+
+        let service_choice = service as u8;
+        let request = ConfirmedRequestBuilder::new(service)
+            .invoke_id(invoke_id)
+            .segmented_response_accepted()
+            .user_data(data)
+            .build();
+        let mut attempt = 0;
+        let response = loop {
+            let timeout = self.clock().sleep(timeout);
+            self.send(address, request.clone()).await?;
+            let response = future::or(async { receiver.recv().await.ok() }, async {
+                timeout.await;
+                None
+            });
+            match response.await {
+                Some(response) => break response,
+                None if attempt < retries => {
+                    attempt += 1;
+                    trace!("Retrying request {} to {:?}", invoke_id, address);
+                }
+                None => {
+                    self.transaction_timeouts.fetch_add(1, Ordering::Relaxed);
+                    return Err(ClientError::Timeout);
+                }
+            }
+        };
+
+        let response = match response.segment() {
+            Some(_) => self.reassemble(address, response, &receiver).await?,
+            None => response,
+        };
+
+        match response {
+            APDU::SimpleAck {
+                service_choice: s, ..
+            }
+            | APDU::ComplexAck {
+                service_choice: s, ..
+            } if s == service_choice => Ok(response.into_user_data()),
+            // The error of a private transfer carries the vendor's parameters
+            APDU::Error {
+                service_choice: s,
+                user_data,
+                ..
+            } if s == ConfirmedServiceChoice::ConfirmedPrivateTransfer as u8 => Err(
+                ClientError::PrivateTransfer(PrivateTransferError::decode_slice(&user_data)?),
+            ),
+            APDU::Error {
+                service_choice: s,
+                user_data,
+                ..
+            } if s == ConfirmedServiceChoice::VtClose as u8 => Err(ClientError::VtClose(
+                VtCloseError::decode_slice(&user_data)?,
+            )),
+            error @ APDU::Error { .. } => Err(ErrorPDU::try_from(&error)?.error.into()),
+            APDU::Reject { reason, .. } => Err(ClientError::Reject(reason)),
+            APDU::Abort { reason, .. } => Err(ClientError::Abort(reason)),
+            _ => Err(ClientError::UnexpectedResponse),
+        }
+    }
+
+    /// Receive the segments of a segmented response, starting with the
+    /// first, and return the reassembled ACK (5.4.4.3)
+    ///
+    /// Segments that are not ACKs of the request abort the transaction.
+    async fn reassemble(
+        &self,
+        address: &Address,
+        first: APDU,
+        segments: &Receiver<APDU>,
+    ) -> Result<APDU, ClientError> {
+        let clock = self.clock();
+        let mut receiver = SegmentedReceiver::new(DEFAULT_WINDOW_SIZE, DEFAULT_SEGMENT_TIMEOUT);
+        let mut segment = first;
+        loop {
+            match segment {
+                APDU::ComplexAck { .. } => {}
+                APDU::Abort { reason, .. } => return Err(ClientError::Abort(reason)),
+                _ => return Err(ClientError::UnexpectedResponse),
+            }
+            match receiver.receive(&segment, clock.now()) {
+                Ok(Some(ack)) => self.send(address, ack).await?,
+                Ok(None) => {}
+                Err(_) => {
+                    let invoke_id = segment.invoke_id().unwrap_or_default();
+                    let reason = AbortReason::InvalidApduInThisState;
+                    self.send(address, AbortPDU::new(false, invoke_id, reason).to_apdu())
+                        .await?;
+                    return Err(ClientError::Abort(reason as u8));
+                }
+            }
+            if receiver.is_complete() {
+                break;
+            }
+            let deadline = receiver.deadline().unwrap_or_else(|| clock.now());
+            let timeout = clock.sleep(deadline.saturating_duration_since(clock.now()));
+            let next = future::or(async { segments.recv().await.ok() }, async {
+                timeout.await;
+                None
+            });
+            segment = match next.await {
+                Some(segment) => segment,
+                None => {
+                    self.transaction_timeouts.fetch_add(1, Ordering::Relaxed);
+                    return Err(ClientError::Timeout);
+                }
+            };
+        }
+        receiver.into_apdu().ok_or(ClientError::UnexpectedResponse)
+    }
+}
+
+/// Removes a transaction when the request completes or is dropped
+struct Transaction<'a, D: DataLink> {
+    station: &'a Station<D>,
+    address: &'a Address,
+    invoke_id: u8,
+}
+
+impl<D: DataLink> Drop for Transaction<'_, D> {
+    fn drop(&mut self) {
+        self.station.end_transaction(self.address, self.invoke_id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_transactions() {
+        let transactions = TransactionManager::default();
+        let (sender, _receiver) = channel::bounded(1);
+        let peers: Vec<_> = (0..1000u32)
+            .map(|i| Address::remote(5, i.to_be_bytes().to_vec()))
+            .collect();
+        let mut keys = Vec::new();
+        for peer in &peers {
+            for _ in 0..3 {
+                let invoke_id = transactions.allocate(peer, sender.clone()).unwrap();
+                keys.push((peer.clone(), invoke_id.unwrap()));
+            }
+        }
+        assert_eq!(transactions.len(), 3000);
+        // Invoke IDs are unique per peer
+        let unique: std::collections::HashSet<_> = keys.iter().collect();
+        assert_eq!(unique.len(), keys.len());
+
+        for key in &keys {
+            assert!(transactions.remove(key).is_some());
+            assert!(transactions.remove(key).is_none());
+        }
+        assert_eq!(transactions.len(), 0);
+    }
+
+    #[test]
+    fn test_transaction_window() {
+        let transactions = TransactionManager::default();
+        transactions.set_window(Some(1));
+        assert_eq!(transactions.window(), Some(1));
+        let (sender, _receiver) = channel::bounded(1);
+        let (a, b) = (Address::local(vec![1]), Address::local(vec![2]));
+
+        let first = transactions.allocate(&a, sender.clone()).unwrap().unwrap();
+        assert!(transactions.allocate(&b, sender.clone()).unwrap().is_ok());
+        // The window of a is full until its transaction ends
+        let wait = transactions
+            .allocate(&a, sender.clone())
+            .unwrap()
+            .unwrap_err();
+        assert!(wait.try_recv().is_err());
+        transactions.remove(&(a.clone(), first));
+        assert!(wait.try_recv().is_ok());
+        assert!(transactions.allocate(&a, sender).unwrap().is_ok());
+
+        transactions.set_window(None);
+        assert_eq!(transactions.window(), None);
+    }
+}
@@ -0,0 +1,219 @@
+//! Golden tests of frames written as hex
+//!
+//! Services and objects implemented outside of the crate can be tested
+//! the way the crate tests its own:
+//!
+//! ```
+//! use bacnet::application::{IAm, ObjectIdentifier, ObjectType, Segmentation};
+//! use bacnet::testing::{assert_decodes_to, assert_encodes_to};
+//!
+//! let i_am = IAm {
+//!     device_identifier: ObjectIdentifier::new(ObjectType::Device, 599),
+//!     max_apdu_length_accepted: 1024,
+//!     segmentation_supported: Segmentation::SegmentedBoth,
+//!     vendor_id: 15,
+//! };
+//! assert_decodes_to("c402000257 220400 9100 210f", &i_am);
+//! assert_encodes_to(&i_am, "c402000257 220400 9100 210f");
+//! ```
+//!
+//! The reference frames of the crate, from `tests/vectors`, are available
+//! as [`fixtures`], files in the same format are read with
+//! [`parse_fixtures`]:
+//!
+//! ```text
+//! # Comment
+//! [Name of the fixture]
+//! decode = apdu
+//! frame = 1008
+//! pdu_type = "UnconfirmedRequest"
+//! ```
+//!
+//! `decode` and `frame` are required. `encoded` is the expected encoding
+//! if it differs from the frame, `invalid = true` a frame that must fail
+//! to decode. Further keys are dot separated paths into the JSON of the
+//! decoded frame, see the `json` module of the `serde` feature, with the
+//! expected value as compact JSON.
+
+use crate::{Decode, Encode};
+
+use std::collections::BTreeMap;
+use std::fmt::Debug;
+
+/// The fixture files of the crate, by path below `tests/vectors`
+pub const FIXTURE_FILES: &[(&str, &str)] = &[
+    (
+        "application/apdu.vectors",
+        include_str!("../tests/vectors/application/apdu.vectors"),
+    ),
+    (
+        "network/npdu.vectors",
+        include_str!("../tests/vectors/network/npdu.vectors"),
+    ),
+    (
+        "services/alarm-event.vectors",
+        include_str!("../tests/vectors/services/alarm-event.vectors"),
+    ),
+    (
+        "services/cov.vectors",
+        include_str!("../tests/vectors/services/cov.vectors"),
+    ),
+    (
+        "services/device-management.vectors",
+        include_str!("../tests/vectors/services/device-management.vectors"),
+    ),
+    (
+        "services/error.vectors",
+        include_str!("../tests/vectors/services/error.vectors"),
+    ),
+    (
+        "services/object-access.vectors",
+        include_str!("../tests/vectors/services/object-access.vectors"),
+    ),
+    (
+        "services/private-transfer.vectors",
+        include_str!("../tests/vectors/services/private-transfer.vectors"),
+    ),
+    (
+        "services/virtual-terminal.vectors",
+        include_str!("../tests/vectors/services/virtual-terminal.vectors"),
+    ),
+    (
+        "services/who-is-i-am.vectors",
+        include_str!("../tests/vectors/services/who-is-i-am.vectors"),
+    ),
+    (
+        "transport/bacnetip.vectors",
+        include_str!("../tests/vectors/transport/bacnetip.vectors"),
+    ),
+];
+
+/// A reference frame with its expectations
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Fixture {
+    /// File and section the fixture is defined in
+    pub origin: String,
+    /// Name of the decoder, e.g. `apdu` or `i-am`
+    pub decode: String,
+    pub frame: Vec<u8>,
+    pub encoded: Option<Vec<u8>>,
+    pub invalid: bool,
+    /// Expected values of the JSON of the decoded frame, by path
+    pub fields: BTreeMap<String, String>,
+}
+
+impl Fixture {
+    /// The expected encoding of the decoded frame
+    pub fn expected(&self) -> &[u8] {
+        self.encoded.as_ref().unwrap_or(&self.frame)
+    }
+}
+
+/// The octets of a frame written as hex, spaces are ignored
+///
+/// Panics if the hex is invalid.
+pub fn frame(hex: &str) -> Vec<u8> {
+    let digits: String = hex.split_whitespace().collect();
+    hex::decode(&digits).unwrap_or_else(|e| panic!("Invalid hex {:?}: {}", hex, e))
+}
+
+/// Assert that the frame decodes to `expected`
+pub fn assert_decodes_to<T: Decode + Debug + PartialEq>(hex: &str, expected: &T) {
+    match T::decode_slice(&frame(hex)) {
+        Ok(decoded) => assert_eq!(&decoded, expected, "Decoding of {}", hex),
+        Err(e) => panic!("Decoding of {} failed: {}", hex, e),
+    }
+}
+
+/// Assert that the value encodes to the frame, with a length that matches
+/// the encoding
+pub fn assert_encodes_to<T: Encode + Debug>(value: &T, hex: &str) {
+    let encoded = value
+        .encode_vec()
+        .unwrap_or_else(|e| panic!("Encoding of {:?} failed: {}", value, e));
+    assert_eq!(
+        hex::encode(&encoded),
+        hex::encode(frame(hex)),
+        "Encoding of {:?}",
+        value
+    );
+    assert_eq!(value.len(), encoded.len(), "Length of {:?}", value);
+}
+
+/// The fixtures of the crate
+pub fn fixtures() -> Vec<Fixture> {
+    FIXTURE_FILES
+        .iter()
+        .flat_map(|(file, text)| parse_fixtures(file, text))
+        .collect()
+}
+
+/// The fixtures of a file, `file` names it in the origin of the fixtures
+///
+/// Panics if the file is malformed.
+pub fn parse_fixtures(file: &str, text: &str) -> Vec<Fixture> {
+    let mut sections: Vec<(String, BTreeMap<String, String>)> = Vec::new();
+    for (number, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        if let Some(name) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+            sections.push((format!("{} [{}]", file, name), BTreeMap::new()));
+            continue;
+        }
+        let location = format!("{}:{}", file, number + 1);
+        let (key, value) = line
+            .split_once('=')
+            .unwrap_or_else(|| panic!("{}: expected `key = value`", location));
+        let (_, fields) = sections
+            .last_mut()
+            .unwrap_or_else(|| panic!("{}: key outside of a fixture", location));
+        fields.insert(key.trim().into(), value.trim().into());
+    }
+
+    sections
+        .into_iter()
+        .map(|(origin, mut fields)| {
+            let mut take = |key: &str| fields.remove(key);
+            let decode = take("decode").unwrap_or_else(|| panic!("{}: no decoder", origin));
+            let frame = frame(&take("frame").unwrap_or_else(|| panic!("{}: no frame", origin)));
+            let encoded = take("encoded").map(|e| self::frame(&e));
+            let invalid = take("invalid").as_deref() == Some("true");
+            Fixture {
+                origin,
+                decode,
+                frame,
+                encoded,
+                invalid,
+                fields,
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_fixtures() {
+        let text = "# Comment\n[A]\ndecode = apdu\nframe = 10 08\nservice = \"WhoIs\"\n\n[B]\ndecode = npdu\nframe = 01\ninvalid = true\n";
+        let fixtures = parse_fixtures("a.vectors", text);
+        assert_eq!(fixtures.len(), 2);
+        assert_eq!(fixtures[0].origin, "a.vectors [A]");
+        assert_eq!(fixtures[0].frame, [0x10, 0x08]);
+        assert_eq!(fixtures[0].expected(), [0x10, 0x08]);
+        assert_eq!(fixtures[0].fields["service"], "\"WhoIs\"");
+        assert!(fixtures[1].invalid);
+        assert!(fixtures[1].fields.is_empty());
+    }
+
+    #[test]
+    #[should_panic(expected = "Encoding of")]
+    fn test_assert_encodes_to() {
+        let apdu = crate::application::APDU::unconfirmed_request(8, vec![]);
+        assert_encodes_to(&apdu, "1008");
+        assert_encodes_to(&apdu, "1007");
+    }
+}
@@ -15,3 +15,130 @@
 ///
 pub mod bacnetip;
 pub mod bacnetsc;
+
+use crate::network::NPDU;
+
+use bytes::BytesMut;
+use std::future::Future;
+use std::ops::{Deref, DerefMut};
+use std::pin::Pin;
+use std::sync::Mutex;
+
+pub type BoxFuture<'a, T> = Pin<Box<dyn Future<Output = T> + Send + 'a>>;
+
+/// Buffers not in use that a pool keeps at most
+const POOLED_BUFFERS: usize = 16;
+
+/// Buffers reused for the frames of a data link, so that sending and
+/// receiving does not allocate for each frame
+///
+/// ```
+/// # use bacnet::transport::bacnetip::{BVLCFunction, BVLC};
+/// # use bacnet::transport::BufferPool;
+/// # use bacnet::Encode;
+/// let pool = BufferPool::new(1507);
+/// let mut buf = pool.get();
+/// BVLC::new(BVLCFunction::Result(0)).encode_buf(&mut *buf).unwrap();
+/// assert_eq!(&buf[..], [0x81, 0x00, 0x00, 0x06, 0x00, 0x00]);
+/// drop(buf);
+/// assert_eq!(pool.available(), 1);
+/// ```
+pub struct BufferPool {
+    buffers: Mutex<Vec<BytesMut>>,
+    capacity: usize,
+}
+
+impl BufferPool {
+    /// A pool of buffers with room for `capacity` octets each
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            buffers: Mutex::new(Vec::new()),
+            capacity,
+        }
+    }
+
+    /// An empty buffer, which returns to the pool when dropped
+    pub fn get(&self) -> PooledBuffer<'_> {
+        let buf = self.buffers.lock().unwrap().pop();
+        let mut buf = buf.unwrap_or_default();
+        buf.reserve(self.capacity);
+        PooledBuffer {
+            pool: self,
+            buf: Some(buf),
+        }
+    }
+
+    /// Buffers not in use
+    pub fn available(&self) -> usize {
+        self.buffers.lock().unwrap().len()
+    }
+}
+
+/// A buffer taken from a [`BufferPool`]
+pub struct PooledBuffer<'a> {
+    pool: &'a BufferPool,
+    buf: Option<BytesMut>,
+}
+
+impl Deref for PooledBuffer<'_> {
+    type Target = BytesMut;
+
+    fn deref(&self) -> &BytesMut {
+        self.buf.as_ref().unwrap()
+    }
+}
+
+impl DerefMut for PooledBuffer<'_> {
+    fn deref_mut(&mut self) -> &mut BytesMut {
+        self.buf.as_mut().unwrap()
+    }
+}
+
+impl Drop for PooledBuffer<'_> {
+    fn drop(&mut self) {
+        let mut buf = self.buf.take().unwrap();
+        buf.clear();
+        let mut buffers = self.pool.buffers.lock().unwrap();
+        if buffers.len() < POOLED_BUFFERS {
+            buffers.push(buf);
+        }
+    }
+}
+
+/// A data link the network layer exchanges NPDUs over
+///
+/// Stations are identified by their MAC address on the link, whose format
+/// depends on the data link. An empty MAC address is the local broadcast
+/// address.
+pub trait DataLink: Send + Sync {
+    /// Send an NPDU to the station with the given MAC address
+    fn send<'a>(&'a self, mac: &'a [u8], npdu: &'a NPDU) -> BoxFuture<'a, std::io::Result<()>>;
+
+    /// Wait for the next NPDU, returning it with the MAC address of the
+    /// sender
+    ///
+    /// Frames that cannot be decoded are dropped, an error means the link
+    /// is no longer usable.
+    fn recv(&self) -> BoxFuture<'_, std::io::Result<(Vec<u8>, NPDU)>>;
+
+    /// Number of received frames dropped because they could not be decoded
+    fn decode_errors(&self) -> u64 {
+        0
+    }
+}
+
+/// Data links of different types used together, e.g. by a device on
+/// several networks
+impl DataLink for Box<dyn DataLink> {
+    fn send<'a>(&'a self, mac: &'a [u8], npdu: &'a NPDU) -> BoxFuture<'a, std::io::Result<()>> {
+        self.as_ref().send(mac, npdu)
+    }
+
+    fn recv(&self) -> BoxFuture<'_, std::io::Result<(Vec<u8>, NPDU)>> {
+        self.as_ref().recv()
+    }
+
+    fn decode_errors(&self) -> u64 {
+        self.as_ref().decode_errors()
+    }
+}
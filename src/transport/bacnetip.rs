@@ -1,11 +1,44 @@
 /// Implements BACnet/IP (Annex J)
+use crate::error::TransportError;
+#[cfg(feature = "serde")]
+use crate::json;
 use crate::network::*;
+#[cfg(feature = "runtime")]
+use crate::transport::{BoxFuture, BufferPool, DataLink};
 use crate::{Decode, Encode};
 
+#[cfg(feature = "runtime")]
+use async_std::net::UdpSocket;
 use byteorder::{BigEndian, ReadBytesExt, WriteBytesExt};
-use bytes::BufMut;
+use bytes::Bytes;
+#[cfg(feature = "runtime")]
+use std::net::SocketAddr;
+use std::net::SocketAddrV4;
+#[cfg(feature = "runtime")]
+use std::sync::atomic::{AtomicU64, Ordering};
+#[cfg(feature = "runtime")]
+use std::sync::Mutex;
+#[cfg(feature = "runtime")]
+use std::time::{Duration, Instant};
 
-const BACNETIP: u8 = 0x81;
+#[cfg(feature = "runtime")]
+use tracing::{trace, warn};
+
+pub(crate) const BACNETIP: u8 = 0x81;
+
+/// UDP port 47808 used by BACnet/IP unless configured otherwise (J.1.1)
+pub const DEFAULT_PORT: u16 = 0xBAC0;
+
+#[cfg(feature = "runtime")]
+/// Largest BVLL frame, an NPDU of up to 1497 octets plus the BVLC header
+const MAX_FRAME: usize = 1497 + 10;
+
+#[cfg(feature = "runtime")]
+/// Time to wait for the BBMD to answer a foreign device registration
+const REGISTRATION_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// BVLC-Result code of a successful request (J.2.1.1)
+pub const RESULT_SUCCESSFUL_COMPLETION: u16 = 0x0000;
 
 pub trait AsU8 {
     fn as_u8(&self) -> u8;
@@ -13,7 +46,16 @@ pub trait AsU8 {
 
 /// BACnet Virtual Link Control Function
 #[derive(Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum BVLCFunction {
+    /// Result code of a request to a BBMD
+    Result(u16),
+    /// NPDU broadcast by a BBMD on behalf of the node with the address
+    ForwardedNPDU(SocketAddrV4, NPDU),
+    /// Registration as foreign device, with the time to live in seconds
+    RegisterForeignDevice(u16),
+    /// Broadcast of a foreign device, sent to its BBMD
+    DistributeBroadcastToNetwork(NPDU),
     OriginalBroadcastNPDU(NPDU),
     OriginalUnicastNPDU(NPDU),
 }
@@ -21,6 +63,10 @@ pub enum BVLCFunction {
 impl AsU8 for BVLCFunction {
     fn as_u8(&self) -> u8 {
         match self {
+            Self::Result(_) => 0x00,
+            Self::ForwardedNPDU(..) => 0x04,
+            Self::RegisterForeignDevice(_) => 0x05,
+            Self::DistributeBroadcastToNetwork(_) => 0x09,
             Self::OriginalBroadcastNPDU(_) => 0x0b,
             Self::OriginalUnicastNPDU(_) => 0x0a,
         }
@@ -28,22 +74,63 @@ impl AsU8 for BVLCFunction {
 }
 
 impl Encode for BVLCFunction {
-    fn encode<T: std::io::Write + Sized>(&self, writer: &mut T) -> std::io::Result<()> {
+    fn encode<T: std::io::Write + Sized>(&self, writer: &mut T) -> crate::error::Result<()> {
         match self {
-            Self::OriginalBroadcastNPDU(n) | Self::OriginalUnicastNPDU(n) => n.encode(writer)?,
+            Self::Result(v) | Self::RegisterForeignDevice(v) => {
+                writer.write_u16::<BigEndian>(*v)?
+            }
+            Self::ForwardedNPDU(addr, n) => {
+                writer.write_all(&addr.ip().octets())?;
+                writer.write_u16::<BigEndian>(addr.port())?;
+                n.encode(writer)?
+            }
+            Self::DistributeBroadcastToNetwork(n)
+            | Self::OriginalBroadcastNPDU(n)
+            | Self::OriginalUnicastNPDU(n) => n.encode(writer)?,
         }
         Ok(())
     }
 
     fn len(&self) -> usize {
         match self {
-            Self::OriginalBroadcastNPDU(n) | Self::OriginalUnicastNPDU(n) => n.len(),
+            Self::Result(_) | Self::RegisterForeignDevice(_) => 2,
+            Self::ForwardedNPDU(_, n) => 6 + n.len(),
+            Self::DistributeBroadcastToNetwork(n)
+            | Self::OriginalBroadcastNPDU(n)
+            | Self::OriginalUnicastNPDU(n) => n.len(),
         }
     }
 }
 
+#[cfg(feature = "runtime")]
+/// A function carrying an NPDU that is borrowed, to send it without
+/// cloning
+struct BorrowedNPDU<'a> {
+    function: u8,
+    npdu: &'a NPDU,
+}
+
+#[cfg(feature = "runtime")]
+impl AsU8 for BorrowedNPDU<'_> {
+    fn as_u8(&self) -> u8 {
+        self.function
+    }
+}
+
+#[cfg(feature = "runtime")]
+impl Encode for BorrowedNPDU<'_> {
+    fn encode<T: std::io::Write + Sized>(&self, writer: &mut T) -> crate::error::Result<()> {
+        self.npdu.encode(writer)
+    }
+
+    fn len(&self) -> usize {
+        self.npdu.len()
+    }
+}
+
 /// A Struct containing a BACnet Virtual Link Control (Annex J).
 #[derive(Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct BVLC<F = BVLCFunction> {
     bvlc_type: u8,
     pub function: F,
@@ -53,7 +140,7 @@ impl<F> BVLC<F> {
     pub fn new(function: F) -> Self {
         Self {
             bvlc_type: BACNETIP, // BACnet/IP (Annex J)
-            function: function,
+            function,
         }
     }
 
@@ -63,7 +150,7 @@ impl<F> BVLC<F> {
 }
 
 impl<F: Encode + AsU8> Encode for BVLC<F> {
-    fn encode<T: std::io::Write + Sized>(&self, writer: &mut T) -> std::io::Result<()> {
+    fn encode<T: std::io::Write + Sized>(&self, writer: &mut T) -> crate::error::Result<()> {
         writer.write_u8(self.bvlc_type)?;
         writer.write_u8(self.function.as_u8())?;
         writer.write_u16::<BigEndian>(self.len() as u16)?;
@@ -81,41 +168,312 @@ impl<F: Encode + AsU8> Encode for BVLC<F> {
     }
 }
 
-impl Decode for BVLC {
-    fn decode<T: std::io::Read + Sized>(reader: &mut T) -> std::io::Result<Self> {
+impl BVLC {
+    /// Original-Broadcast-NPDU to the devices of the local network (J.2.12)
+    pub fn original_broadcast(npdu: NPDU) -> Self {
+        Self::new(BVLCFunction::OriginalBroadcastNPDU(npdu))
+    }
+
+    /// Original-Unicast-NPDU to a single device (J.2.11)
+    pub fn original_unicast(npdu: NPDU) -> Self {
+        Self::new(BVLCFunction::OriginalUnicastNPDU(npdu))
+    }
+
+    /// Distribute-Broadcast-To-Network of a foreign device through its BBMD
+    /// (J.2.10)
+    pub fn distribute_broadcast(npdu: NPDU) -> Self {
+        Self::new(BVLCFunction::DistributeBroadcastToNetwork(npdu))
+    }
+
+    /// The frame with its decoded service parameters as JSON, see
+    /// [`crate::json`]
+    #[cfg(feature = "serde")]
+    pub fn to_json(&self) -> String {
+        json::to_string(self).expect("Frames serialize without errors")
+    }
+}
+
+impl BVLC {
+    /// Decode a BVLC whose NPDU is decoded by `npdu` from the rest of the
+    /// reader
+    fn decode_with<T: std::io::Read + Sized>(
+        reader: &mut T,
+        npdu: impl FnOnce(&mut T) -> crate::error::Result<NPDU>,
+    ) -> crate::error::Result<Self> {
         let bvlc_type = reader.read_u8()?;
         if bvlc_type != BACNETIP {
-            return Err(std::io::Error::new(
-                std::io::ErrorKind::InvalidData,
-                format!("BVLC type not supported: {}", bvlc_type),
-            ));
+            return Err(TransportError::UnsupportedBvlcType(bvlc_type).into());
         }
         let function = reader.read_u8()?;
-        let lenght = reader.read_u16::<BigEndian>()?; // TODO: Check lenght
+        let _length = reader.read_u16::<BigEndian>()?; // TODO: Check length
         let function = match function {
+            0x00 => Ok(BVLCFunction::Result(reader.read_u16::<BigEndian>()?)),
+            0x04 => {
+                let mut ip = [0; 4];
+                reader.read_exact(&mut ip)?;
+                let origin = SocketAddrV4::new(ip.into(), reader.read_u16::<BigEndian>()?);
+                let npdu = npdu(reader)?;
+                Ok(BVLCFunction::ForwardedNPDU(origin, npdu))
+            }
+            0x05 => Ok(BVLCFunction::RegisterForeignDevice(
+                reader.read_u16::<BigEndian>()?,
+            )),
+            0x09 => {
+                let npdu = npdu(reader)?;
+                Ok(BVLCFunction::DistributeBroadcastToNetwork(npdu))
+            }
             0x0b => {
-                let npdu = NPDU::decode(reader)?;
+                let npdu = npdu(reader)?;
                 Ok(BVLCFunction::OriginalBroadcastNPDU(npdu))
             }
             0x0a => {
-                let npdu = NPDU::decode(reader)?;
+                let npdu = npdu(reader)?;
                 Ok(BVLCFunction::OriginalUnicastNPDU(npdu))
             }
-            t => Err(std::io::Error::new(
-                std::io::ErrorKind::InvalidData,
-                format!("BVLC Function not supported: {}", t),
+            t => Err(std::io::Error::from(
+                TransportError::UnsupportedBvlcFunction(t),
             )),
         };
         Ok(Self::new(function?))
     }
 }
 
+impl Decode for BVLC {
+    fn decode<T: std::io::Read + Sized>(reader: &mut T) -> crate::error::Result<Self> {
+        Self::decode_with(reader, NPDU::decode)
+    }
+
+    fn decode_bytes(data: Bytes) -> crate::error::Result<Self> {
+        let mut reader = std::io::Cursor::new(&data[..]);
+        Self::decode_with(&mut reader, |reader| {
+            NPDU::decode_bytes(data.slice(reader.position() as usize..))
+        })
+    }
+}
+
+/// B/IP MAC address of a node, its IP address followed by the UDP port (J.1.2)
+pub fn mac_from_addr(addr: SocketAddrV4) -> Vec<u8> {
+    let mut mac = addr.ip().octets().to_vec();
+    mac.extend_from_slice(&addr.port().to_be_bytes());
+    mac
+}
+
+/// Socket address of a B/IP MAC address, `None` if the length is not 6
+pub fn addr_from_mac(mac: &[u8]) -> Option<SocketAddrV4> {
+    match *mac {
+        [a, b, c, d, p0, p1] => Some(SocketAddrV4::new(
+            [a, b, c, d].into(),
+            u16::from_be_bytes([p0, p1]),
+        )),
+        _ => None,
+    }
+}
+
+#[cfg(feature = "runtime")]
+/// Registration of a foreign device with a BBMD (J.5)
+struct Registration {
+    bbmd: SocketAddrV4,
+    /// Time to live in seconds
+    ttl: u16,
+    /// When the registration is renewed
+    renew: Mutex<Instant>,
+}
+
+#[cfg(feature = "runtime")]
+/// BACnet/IP data link on a UDP socket
+pub struct BacnetIp {
+    socket: UdpSocket,
+    broadcast: SocketAddrV4,
+    registration: Option<Registration>,
+    decode_errors: AtomicU64,
+    /// Buffers of the frames sent and received
+    buffers: BufferPool,
+}
+
+#[cfg(feature = "runtime")]
+impl BacnetIp {
+    /// Bind to a local address, broadcasts are sent to `broadcast`
+    pub async fn bind(local: SocketAddrV4, broadcast: SocketAddrV4) -> std::io::Result<Self> {
+        let socket = UdpSocket::bind(local).await?;
+        socket.set_broadcast(true)?;
+        Ok(Self {
+            socket,
+            broadcast,
+            registration: None,
+            decode_errors: AtomicU64::new(0),
+            buffers: BufferPool::new(MAX_FRAME),
+        })
+    }
+
+    /// Bind to a local address and register as foreign device with the
+    /// BBMD at `bbmd`, to take part in the broadcasts of its network from a
+    /// different IP subnet
+    ///
+    /// Broadcasts are distributed by the BBMD. The registration is renewed
+    /// whenever `ttl` passes while receiving, the BBMD keeps it for 30
+    /// seconds longer. Fails if the BBMD does not accept the registration.
+    pub async fn bind_foreign(
+        local: SocketAddrV4,
+        bbmd: SocketAddrV4,
+        ttl: Duration,
+    ) -> std::io::Result<Self> {
+        let socket = UdpSocket::bind(local).await?;
+        let ttl = ttl.as_secs().clamp(1, u16::MAX as u64) as u16;
+        let link = Self {
+            socket,
+            broadcast: bbmd,
+            registration: Some(Registration {
+                bbmd,
+                ttl,
+                renew: Mutex::new(Instant::now()),
+            }),
+            decode_errors: AtomicU64::new(0),
+            buffers: BufferPool::new(MAX_FRAME),
+        };
+        link.register().await?;
+
+        let mut buf = vec![0u8; MAX_FRAME];
+        let result = async_std::future::timeout(REGISTRATION_TIMEOUT, async {
+            loop {
+                let (n, peer) = link.socket.recv_from(&mut buf).await?;
+                if peer != SocketAddr::V4(bbmd) {
+                    continue;
+                }
+                if let Ok(BVLC {
+                    function: BVLCFunction::Result(result),
+                    ..
+                }) = BVLC::decode_slice(&buf[..n])
+                {
+                    return Ok::<_, std::io::Error>(result);
+                }
+            }
+        })
+        .await
+        .map_err(|_| TransportError::RegistrationTimeout)??;
+        match result {
+            RESULT_SUCCESSFUL_COMPLETION => Ok(link),
+            result => Err(TransportError::RegistrationRejected(result).into()),
+        }
+    }
+
+    pub fn local_addr(&self) -> std::io::Result<SocketAddr> {
+        self.socket.local_addr()
+    }
+
+    /// The BBMD the link is registered with as foreign device
+    pub fn bbmd(&self) -> Option<SocketAddrV4> {
+        self.registration.as_ref().map(|r| r.bbmd)
+    }
+
+    /// Send the foreign device registration, if any
+    async fn register(&self) -> std::io::Result<()> {
+        if let Some(registration) = &self.registration {
+            let data =
+                BVLC::new(BVLCFunction::RegisterForeignDevice(registration.ttl)).encode_vec()?;
+            trace!("Register with {}", registration.bbmd);
+            self.socket.send_to(&data, registration.bbmd).await?;
+            *registration.renew.lock().unwrap() =
+                Instant::now() + Duration::from_secs(registration.ttl as u64);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(feature = "runtime")]
+impl DataLink for BacnetIp {
+    fn send<'a>(&'a self, mac: &'a [u8], npdu: &'a NPDU) -> BoxFuture<'a, std::io::Result<()>> {
+        Box::pin(async move {
+            let (function, addr) = match mac {
+                // Distribute-Broadcast-To-Network
+                [] if self.registration.is_some() => (0x09, self.broadcast),
+                // Original-Broadcast-NPDU
+                [] => (0x0b, self.broadcast),
+                mac => {
+                    let addr = addr_from_mac(mac)
+                        .ok_or_else(|| TransportError::InvalidAddress(mac.to_vec()))?;
+                    // Original-Unicast-NPDU
+                    (0x0a, addr)
+                }
+            };
+            let mut data = self.buffers.get();
+            BVLC::new(BorrowedNPDU { function, npdu }).encode_buf(&mut *data)?;
+            trace!("Send to {}: {:02x?}", addr, &data[..]);
+            self.socket.send_to(&data, addr).await?;
+            Ok(())
+        })
+    }
+
+    fn recv(&self) -> BoxFuture<'_, std::io::Result<(Vec<u8>, NPDU)>> {
+        Box::pin(async move {
+            let mut buf = self.buffers.get();
+            buf.resize(MAX_FRAME, 0);
+            loop {
+                let (n, peer) = match &self.registration {
+                    Some(registration) => {
+                        let renew = *registration.renew.lock().unwrap();
+                        let wait = renew.saturating_duration_since(Instant::now());
+                        match async_std::future::timeout(wait, self.socket.recv_from(&mut buf))
+                            .await
+                        {
+                            Ok(received) => received?,
+                            Err(_) => {
+                                if let Err(e) = self.register().await {
+                                    warn!("Foreign device registration failed: {}", e);
+                                }
+                                continue;
+                            }
+                        }
+                    }
+                    None => self.socket.recv_from(&mut buf).await?,
+                };
+                let data = &buf[..n];
+                trace!("Data from {}: {:02x?}", peer, data);
+                let peer = match peer {
+                    SocketAddr::V4(peer) => peer,
+                    SocketAddr::V6(_) => continue,
+                };
+                // The frame is copied once, the APDU shares its buffer
+                match BVLC::decode_bytes(Bytes::copy_from_slice(data)) {
+                    Ok(bvlc) => match bvlc.function {
+                        BVLCFunction::OriginalBroadcastNPDU(npdu)
+                        | BVLCFunction::OriginalUnicastNPDU(npdu) => {
+                            return Ok((mac_from_addr(peer), npdu))
+                        }
+                        BVLCFunction::ForwardedNPDU(origin, npdu) => {
+                            return Ok((mac_from_addr(origin), npdu))
+                        }
+                        BVLCFunction::Result(RESULT_SUCCESSFUL_COMPLETION) => {}
+                        BVLCFunction::Result(result) => {
+                            warn!("BVLC-Result from {}: {:#06x}", peer, result)
+                        }
+                        function => trace!("Ignoring {:?} from {}", function, peer),
+                    },
+                    Err(e) => {
+                        trace!("Dropping frame from {}: {}", peer, e);
+                        self.decode_errors.fetch_add(1, Ordering::Relaxed);
+                    }
+                }
+            }
+        })
+    }
+
+    fn decode_errors(&self) -> u64 {
+        self.decode_errors.load(Ordering::Relaxed)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::application::{WhoIsBuilder, APDU};
+    use crate::error::EncodingError;
     use crate::{Decode, Encode};
     use bytes::{BufMut, BytesMut};
+    #[cfg(feature = "runtime")]
+    use futures_lite::future;
     use hex;
+    #[cfg(feature = "runtime")]
+    use std::net::Ipv4Addr;
 
     use crate::tests::*;
 
@@ -134,15 +492,269 @@ mod tests {
         assert_eq!(w.into_inner().to_vec(), vec![129, 0, 0, 4]);
     }
 
+    #[test]
+    fn test_mac_address() {
+        let addr = SocketAddrV4::new([192, 168, 1, 10].into(), DEFAULT_PORT);
+        let mac = mac_from_addr(addr);
+        assert_eq!(mac, vec![192, 168, 1, 10, 0xBA, 0xC0]);
+        assert_eq!(addr_from_mac(&mac), Some(addr));
+        assert_eq!(addr_from_mac(&[1]), None);
+    }
+
     #[test]
     fn test_decode_invalid_bvlc_type() {
         let data = hex::decode("00000000").unwrap();
         let err = BVLC::decode(&mut std::io::Cursor::new(&data)).unwrap_err();
 
-        assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+        assert!(matches!(
+            err,
+            crate::Error::Transport(TransportError::UnsupportedBvlcType(0))
+        ));
         assert_eq!(
-            err.into_inner().unwrap().to_string(),
+            std::io::Error::from(err).into_inner().unwrap().to_string(),
             "BVLC type not supported: 0".to_string()
         );
     }
+
+    #[test]
+    fn test_bvlc_functions() {
+        let npdu = NPDU::new(
+            APDU::unconfirmed_request(8, vec![]),
+            None,
+            None,
+            NPDUPriority::Normal,
+        );
+        let origin = SocketAddrV4::new([192, 168, 1, 10].into(), DEFAULT_PORT);
+        let frames = [
+            ("810000060000", BVLCFunction::Result(0)),
+            ("8105000600b4", BVLCFunction::RegisterForeignDevice(180)),
+            (
+                "8104000ec0a8010abac001001008",
+                BVLCFunction::ForwardedNPDU(origin, npdu.clone()),
+            ),
+            (
+                "8109000801001008",
+                BVLCFunction::DistributeBroadcastToNetwork(npdu),
+            ),
+        ];
+        for (data, function) in frames.iter().cloned() {
+            let data = hex::decode(data).unwrap();
+            let bvlc = BVLC::decode_slice(&data).unwrap();
+            assert_eq!(bvlc.function, function);
+            assert_eq!(bvlc.encode_vec().unwrap(), data);
+        }
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_deserialize() {
+        let frames = [
+            "810a0011010400050f0c0c000000011955",
+            "810a0017010030010c0c0000000119553e4441a000003f",
+            "810a00090100600309",
+            "810a000a010042050307",
+            "810a000c0120ffff00ff1008",
+            "810a00090180000001",
+            "8104000ec0a8010abac001001008",
+            "8105000600b4",
+        ];
+        for data in frames {
+            let data = hex::decode(data).unwrap();
+            let bvlc = BVLC::decode_slice(&data).unwrap();
+            let json = crate::json::to_string(&bvlc).unwrap();
+            let decoded: BVLC = crate::json::from_str(&json).unwrap();
+            assert_eq!(decoded, bvlc, "{}", json);
+            assert_eq!(decoded.encode_vec().unwrap(), data);
+        }
+    }
+
+    #[test]
+    fn test_builders() {
+        let npdu = NPDU::builder()
+            .global_broadcast()
+            .build(WhoIsBuilder::new().build());
+        let bvlc = BVLC::original_broadcast(npdu);
+        assert_eq!(
+            hex::encode(bvlc.encode_vec().unwrap()),
+            "810b000c0120ffff00ff1008"
+        );
+
+        let npdu = NPDU::builder().build(WhoIsBuilder::new().build());
+        assert_eq!(BVLC::original_unicast(npdu.clone()).function.as_u8(), 0x0a);
+        assert_eq!(BVLC::distribute_broadcast(npdu).function.as_u8(), 0x09);
+    }
+
+    #[test]
+    fn test_encode_fixed_buffer() {
+        let origin = SocketAddrV4::new([192, 168, 1, 10].into(), DEFAULT_PORT);
+        let npdu = NPDU::builder().build(APDU::unconfirmed_request(8, vec![]));
+        let bvlc = BVLC::new(BVLCFunction::ForwardedNPDU(origin, npdu.clone()));
+        let data = hex::decode("8104000ec0a8010abac001001008").unwrap();
+
+        // The frame and its layers are encoded in place of a reused buffer
+        let mut buf = [0xff; 32];
+        assert_eq!(bvlc.encode_into(&mut buf).unwrap(), data.len());
+        assert_eq!(&buf[..data.len()], &data[..]);
+        assert_eq!(npdu.encode_into(&mut buf).unwrap(), 4);
+        assert_eq!(&buf[..4], &data[10..]);
+        let apdu = APDU::abort(true, 3, 4);
+        assert_eq!(apdu.encode_into(&mut buf[29..]).unwrap(), 3);
+
+        let mut slice = &mut buf[..];
+        bvlc.encode_buf(&mut slice).unwrap();
+        assert_eq!(slice.len(), 32 - data.len());
+        assert_eq!(&buf[..data.len()], &data[..]);
+
+        // Buffers without room for the frame are left as they are
+        let mut buf = [0xff; 13];
+        let err = bvlc.encode_buf(&mut &mut buf[..]).unwrap_err();
+        assert!(matches!(
+            err,
+            crate::Error::Encoding(EncodingError::BufferTooShort {
+                len: 13,
+                required: 14
+            })
+        ));
+        assert_eq!(buf, [0xff; 13]);
+    }
+
+    #[test]
+    #[cfg(feature = "runtime")]
+    fn test_encode_into() {
+        let npdu = NPDU::new(
+            APDU::unconfirmed_request(8, vec![]),
+            None,
+            None,
+            NPDUPriority::Normal,
+        );
+        let bvlc = BVLC::new(BVLCFunction::OriginalUnicastNPDU(npdu.clone()));
+        let data = bvlc.encode_vec().unwrap();
+        let mut buf = [0xff; MAX_FRAME];
+        assert_eq!(bvlc.encode_into(&mut buf).unwrap(), data.len());
+        assert_eq!(&buf[..data.len()], &data[..]);
+        assert_eq!(buf[data.len()], 0xff);
+
+        // Frames of borrowed NPDUs are the same
+        let borrowed = BVLC::new(BorrowedNPDU {
+            function: 0x0a,
+            npdu: &npdu,
+        });
+        assert_eq!(borrowed.len(), data.len());
+        assert_eq!(borrowed.encode_vec().unwrap(), data);
+
+        let err = bvlc.encode_into(&mut buf[..data.len() - 1]).unwrap_err();
+        assert!(matches!(
+            err,
+            crate::Error::Encoding(EncodingError::BufferTooShort { len, required })
+                if len == data.len() - 1 && required == data.len()
+        ));
+    }
+
+    #[test]
+    #[cfg(feature = "runtime")]
+    fn test_foreign_device() {
+        async_std::task::block_on(async {
+            let localhost = |port| SocketAddrV4::new(Ipv4Addr::LOCALHOST, port);
+            let bbmd = UdpSocket::bind(localhost(0)).await.unwrap();
+            let bbmd_addr = match bbmd.local_addr().unwrap() {
+                SocketAddr::V4(addr) => addr,
+                SocketAddr::V6(_) => unreachable!(),
+            };
+
+            let registering =
+                BacnetIp::bind_foreign(localhost(0), bbmd_addr, Duration::from_secs(60));
+            let answering = async {
+                let mut buf = [0; MAX_FRAME];
+                let (n, peer) = bbmd.recv_from(&mut buf).await.unwrap();
+                assert_eq!(&buf[..n], &hex::decode("81050006003c").unwrap()[..]);
+                let result = BVLC::new(BVLCFunction::Result(0)).encode_vec().unwrap();
+                bbmd.send_to(&result, peer).await.unwrap();
+                peer
+            };
+            let (link, peer) = future::zip(registering, answering).await;
+            let link = link.unwrap();
+            assert_eq!(link.bbmd(), Some(bbmd_addr));
+
+            // Broadcasts are distributed by the BBMD
+            let npdu = NPDU::new(
+                APDU::unconfirmed_request(8, vec![]),
+                None,
+                None,
+                NPDUPriority::Normal,
+            );
+            link.send(&[], &npdu).await.unwrap();
+            let mut buf = [0; MAX_FRAME];
+            let (n, _) = bbmd.recv_from(&mut buf).await.unwrap();
+            let bvlc = BVLC::decode_slice(&buf[..n]).unwrap();
+            assert_eq!(
+                bvlc.function,
+                BVLCFunction::DistributeBroadcastToNetwork(npdu.clone())
+            );
+
+            // Forwarded NPDUs are from their origin
+            let origin = SocketAddrV4::new([192, 168, 1, 10].into(), DEFAULT_PORT);
+            let forwarded = BVLC::new(BVLCFunction::ForwardedNPDU(origin, npdu.clone()));
+            bbmd.send_to(&forwarded.encode_vec().unwrap(), peer)
+                .await
+                .unwrap();
+            assert_eq!(link.recv().await.unwrap(), (mac_from_addr(origin), npdu));
+        });
+    }
+
+    #[test]
+    #[cfg(feature = "runtime")]
+    fn test_unicast() {
+        async_std::task::block_on(async {
+            let localhost = SocketAddrV4::new(Ipv4Addr::LOCALHOST, 0);
+            let a = BacnetIp::bind(localhost, localhost).await.unwrap();
+            let b = BacnetIp::bind(localhost, localhost).await.unwrap();
+            let mac = |link: &BacnetIp| match link.local_addr().unwrap() {
+                SocketAddr::V4(addr) => mac_from_addr(addr),
+                SocketAddr::V6(_) => unreachable!(),
+            };
+
+            let npdu = NPDU::new(
+                APDU::unconfirmed_request(8, vec![]),
+                None,
+                None,
+                NPDUPriority::Normal,
+            );
+            for _ in 0..3 {
+                a.send(&mac(&b), &npdu).await.unwrap();
+                assert_eq!(b.recv().await.unwrap(), (mac(&a), npdu.clone()));
+            }
+            // The buffers are reused for every frame
+            assert_eq!(a.buffers.available(), 1);
+            assert_eq!(b.buffers.available(), 1);
+        });
+    }
+
+    #[test]
+    #[cfg(feature = "runtime")]
+    fn test_foreign_device_rejected() {
+        async_std::task::block_on(async {
+            let localhost = |port| SocketAddrV4::new(Ipv4Addr::LOCALHOST, port);
+            let bbmd = UdpSocket::bind(localhost(0)).await.unwrap();
+            let bbmd_addr = match bbmd.local_addr().unwrap() {
+                SocketAddr::V4(addr) => addr,
+                SocketAddr::V6(_) => unreachable!(),
+            };
+
+            let registering =
+                BacnetIp::bind_foreign(localhost(0), bbmd_addr, Duration::from_secs(60));
+            let answering = async {
+                let mut buf = [0; MAX_FRAME];
+                let (_, peer) = bbmd.recv_from(&mut buf).await.unwrap();
+                let result = BVLC::new(BVLCFunction::Result(0x0030))
+                    .encode_vec()
+                    .unwrap();
+                bbmd.send_to(&result, peer).await.unwrap();
+            };
+            let (link, _) = future::zip(registering, answering).await;
+            assert_eq!(
+                link.err().unwrap().kind(),
+                std::io::ErrorKind::ConnectionRefused
+            );
+        });
+    }
 }
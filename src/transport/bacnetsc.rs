@@ -1,7 +1,3 @@
 /// Implements BACnet/SC (Annex YY)
-use crate::network::*;
-use crate::{Decode, Encode};
-
-use byteorder::{BigEndian, ReadBytesExt, WriteBytesExt};
-
+#[allow(dead_code)]
 const BACNETSC: u8 = 0x81;